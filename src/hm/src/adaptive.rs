@@ -0,0 +1,87 @@
+//! Adaptive sampling interval for MCP-ZERO Hardware Manager
+//!
+//! With `adaptive_sampling` on, the monitoring loop samples less often while usage is
+//! well within the limits and more often as it nears them. While every resource is
+//! below half its limit, the interval doubles after each sample up to
+//! `max_refresh_interval_ms`. From half the limit up to a resource's warning threshold
+//! it's cut in proportion to how close usage is, reaching `min_refresh_interval_ms` at
+//! the threshold. In between it only ever shortens, so usage settling just above half
+//! the limit keeps the shorter interval rather than swinging back and forth.
+
+use std::time::Duration;
+
+/// Fraction of a limit below which sampling slows down
+const IDLE_FRACTION: f32 = 0.5;
+
+/// Sampling interval adjusted to how close usage is to the limits
+#[derive(Debug, Clone)]
+pub(crate) struct AdaptiveInterval {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl AdaptiveInterval {
+    /// Start at `initial`, kept between `min` and `max`
+    pub fn new(initial: Duration, min: Duration, max: Duration) -> Self {
+        let max = max.max(min);
+        Self { min, max, current: initial.clamp(min, max) }
+    }
+    
+    /// Interval to wait before the next sample
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+    
+    /// Adjust the interval to a sample's usage, given as (fraction of limit, warning
+    /// threshold) for each limited resource, returning the new interval
+    pub fn observe(&mut self, usage: &[(f32, f32)]) -> Duration {
+        let idle = usage.iter().all(|&(percent_of_limit, _)| percent_of_limit < IDLE_FRACTION);
+        if idle {
+            self.current = (self.current * 2).min(self.max);
+            return self.current;
+        }
+        
+        // The resource closest to its warning threshold sets the pace
+        let closeness = usage.iter()
+            .map(|&(percent_of_limit, warning_threshold)| {
+                let band = (warning_threshold - IDLE_FRACTION).max(f32::EPSILON);
+                ((percent_of_limit - IDLE_FRACTION) / band).clamp(0.0, 1.0)
+            })
+            .fold(0.0, f32::max);
+        let target = self.max.mul_f32(1.0 - closeness) + self.min.mul_f32(closeness);
+        self.current = self.current.min(target).max(self.min);
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_adaptive_interval() {
+        let mut interval = AdaptiveInterval::new(Duration::from_secs(2), Duration::from_secs(1), Duration::from_secs(8));
+        let millis = |interval: Duration| interval.as_millis() as u64;
+        
+        // Idle usage backs off to the maximum
+        let idle = [(0.1, 0.75), (0.3, 0.75)];
+        let backing_off: Vec<u64> = (0..3).map(|_| millis(interval.observe(&idle))).collect();
+        assert_eq!(backing_off, [4000, 8000, 8000]);
+        
+        // Climbing towards the threshold shortens it, reaching the minimum there
+        let climbing: Vec<u64> = [0.5, 0.5625, 0.625, 0.6875, 0.75, 0.9]
+            .into_iter()
+            .map(|memory| millis(interval.observe(&[(0.1, 0.75), (memory, 0.75)])))
+            .collect();
+        assert_eq!(climbing, [8000, 6250, 4500, 2750, 1000, 1000]);
+        
+        // Easing back within the band holds the short interval until usage is idle again
+        assert_eq!(millis(interval.observe(&[(0.6, 0.75)])), 1000);
+        assert_eq!(millis(interval.observe(&[(0.4, 0.75)])), 2000);
+        
+        // Each resource is measured against its own threshold
+        let mut interval = AdaptiveInterval::new(Duration::from_secs(8), Duration::from_secs(1), Duration::from_secs(8));
+        assert_eq!(millis(interval.observe(&[(0.7, 0.9), (0.6, 0.6)])), 1000);
+    }
+}
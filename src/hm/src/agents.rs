@@ -0,0 +1,219 @@
+//! Per-agent process monitoring for MCP-ZERO Hardware Manager
+//!
+//! Agents running as separate processes register their PIDs. Each monitoring sample
+//! adds up the CPU and memory of an agent's processes and compares the totals with
+//! the agent's allocation, keeping an alert open while an agent exceeds it. PIDs whose
+//! processes have exited are unregistered.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use serde::{Serialize, Deserialize};
+use sysinfo::{Pid, ProcessExt, ProcessStatus, System, SystemExt};
+
+use crate::alert::{Alert, AlertLevel, AlertManager};
+use crate::resource::ResourceType;
+use crate::sampling::refresh_process;
+use crate::AgentAllocation;
+
+/// Measured usage of an agent's processes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentUsage {
+    /// Agent ID
+    pub agent_id: String,
+    
+    /// Registered process IDs
+    pub pids: Vec<u32>,
+    
+    /// CPU usage summed over the processes (percentage)
+    pub cpu_percent: f32,
+    
+    /// Memory usage summed over the processes (MB)
+    pub memory_mb: u32,
+    
+    /// Timestamp when usage was sampled
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Tracks agent processes and their usage
+#[derive(Debug, Default)]
+pub(crate) struct AgentMonitor {
+    /// Registered PIDs per agent
+    processes: RwLock<HashMap<String, Vec<u32>>>,
+    
+    /// Usage from the latest sample per agent
+    usage: RwLock<HashMap<String, AgentUsage>>,
+}
+
+impl AgentMonitor {
+    /// Register a process as belonging to an agent; returns false if it already was
+    pub(crate) fn register(&self, agent_id: &str, pid: u32) -> bool {
+        let mut processes = self.processes.write().unwrap_or_else(|e| e.into_inner());
+        let pids = processes.entry(agent_id.to_string()).or_default();
+        if pids.contains(&pid) {
+            return false;
+        }
+        pids.push(pid);
+        true
+    }
+    
+    /// Unregister a process of an agent; returns false if it wasn't registered
+    pub(crate) fn unregister(&self, agent_id: &str, pid: u32) -> bool {
+        let mut processes = self.processes.write().unwrap_or_else(|e| e.into_inner());
+        let Some(pids) = processes.get_mut(agent_id) else {
+            return false;
+        };
+        let before = pids.len();
+        pids.retain(|&registered| registered != pid);
+        let removed = pids.len() < before;
+        
+        if pids.is_empty() {
+            processes.remove(agent_id);
+            self.usage.write().unwrap_or_else(|e| e.into_inner()).remove(agent_id);
+        }
+        removed
+    }
+    
+    /// Usage of every agent with registered processes, from the latest sample
+    pub(crate) fn usage(&self) -> HashMap<String, AgentUsage> {
+        self.usage.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    
+    /// Refresh and measure each agent's processes in `system`, unregistering exited
+    /// ones and raising or clearing alerts on agents over their allocation; returns the
+    /// (agent, PID) of the processes unregistered
+    pub(crate) fn sample(
+        &self,
+        system: &mut System,
+        allocations: &RwLock<HashMap<String, AgentAllocation>>,
+        alerts: &AlertManager,
+    ) -> Vec<(String, u32)> {
+        let processes = self.processes.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let timestamp = chrono::Utc::now();
+        let mut exited = Vec::new();
+        let mut usage = HashMap::new();
+        
+        for (agent_id, pids) in processes {
+            let mut live = Vec::new();
+            let (mut cpu_percent, mut memory_bytes) = (0.0, 0);
+            for pid in pids {
+                // A process that has exited can't be refreshed but stays in `system`
+                let process = if refresh_process(system, pid) {
+                    system.process(Pid::from(pid as usize))
+                } else {
+                    None
+                };
+                match process {
+                    Some(process) if process.status() != ProcessStatus::Zombie => {
+                        cpu_percent += process.cpu_usage();
+                        memory_bytes += process.memory();
+                        live.push(pid);
+                    },
+                    _ => exited.push((agent_id.clone(), pid)),
+                }
+            }
+            
+            if !live.is_empty() {
+                let memory_mb = (memory_bytes / (1024 * 1024)) as u32;
+                usage.insert(agent_id.clone(), AgentUsage { agent_id, pids: live, cpu_percent, memory_mb, timestamp });
+            }
+        }
+        
+        // Unregister processes that have exited
+        exited.retain(|(agent_id, pid)| self.unregister(agent_id, *pid));
+        for (agent_id, pid) in &exited {
+            let message = format!("Agent {} process {} exited and was unregistered", agent_id, pid);
+            alerts.emit(Alert::new(AlertLevel::Info, ResourceType::Process, &message, *pid as f64, 0.0).with_agent(agent_id));
+        }
+        
+        // Compare usage with allocations
+        {
+            let allocations = allocations.read().unwrap_or_else(|e| e.into_inner());
+            for agent in usage.values() {
+                metrics::gauge!("mcp.hm.agent_cpu_usage", agent.cpu_percent as f64, "agent_id" => agent.agent_id.clone());
+                metrics::gauge!("mcp.hm.agent_memory_usage", agent.memory_mb as f64, "agent_id" => agent.agent_id.clone());
+                
+                // Without an allocation there's nothing to exceed
+                let usage = [
+                    (ResourceType::CPU, agent.cpu_percent as f64, allocations.get(&agent.agent_id).map(|a| a.cpu_percent as f64), "%"),
+                    (ResourceType::Memory, agent.memory_mb as f64, allocations.get(&agent.agent_id).map(|a| a.memory_mb as f64), " MB"),
+                ];
+                for (resource_type, current, allocated, unit) in usage {
+                    let Some(allocated) = allocated.filter(|&allocated| current > allocated) else {
+                        alerts.clear(resource_type, Some(&agent.agent_id));
+                        continue;
+                    };
+                    let message = format!(
+                        "Agent {} {:?} usage {:.2}{} exceeds its allocation of {:.2}{}",
+                        agent.agent_id, resource_type, current, unit, allocated, unit
+                    );
+                    alerts.raise(Alert::new(AlertLevel::Warning, resource_type, &message, current, allocated).with_agent(&agent.agent_id));
+                }
+            }
+        }
+        alerts.resolve_agents(|agent_id| usage.contains_key(agent_id));
+        
+        *self.usage.write().unwrap_or_else(|e| e.into_inner()) = usage;
+        exited
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use crate::alert::AlertHandler;
+    
+    #[derive(Clone, Default)]
+    struct RecordingHandler {
+        alerts: Arc<Mutex<Vec<Alert>>>,
+    }
+    
+    impl AlertHandler for RecordingHandler {
+        fn handle(&self, alert: &Alert) {
+            self.alerts.lock().unwrap().push(alert.clone());
+        }
+    }
+    
+    #[test]
+    fn test_agent_processes() {
+        let monitor = AgentMonitor::default();
+        let alerts = AlertManager::new(AlertLevel::Info);
+        let recorder = RecordingHandler::default();
+        alerts.add_handler(Box::new(recorder.clone()));
+        
+        // This process runs, the child has exited and been reaped
+        let own = std::process::id();
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let exited = child.id();
+        child.wait().unwrap();
+        assert!(monitor.register("worker", own));
+        assert!(!monitor.register("worker", own));
+        assert!(monitor.register("worker", exited));
+        
+        // The worker is allocated less memory than this process uses
+        let allocations = RwLock::new(HashMap::from([
+            ("worker".to_string(), AgentAllocation::new("worker", 100.0, 0, 5)),
+        ]));
+        let mut system = System::new();
+        assert_eq!(monitor.sample(&mut system, &allocations, &alerts), [("worker".to_string(), exited)]);
+        
+        let usage = monitor.usage();
+        assert_eq!(usage["worker"].pids, [own]);
+        assert!(usage["worker"].memory_mb > 0);
+        let raised: Vec<(AlertLevel, ResourceType, Option<String>)> = recorder.alerts.lock().unwrap().drain(..)
+            .map(|alert| (alert.level, alert.resource_type, alert.agent_id))
+            .collect();
+        assert_eq!(raised, [
+            (AlertLevel::Info, ResourceType::Process, Some("worker".to_string())),
+            (AlertLevel::Warning, ResourceType::Memory, Some("worker".to_string())),
+        ]);
+        
+        // The allocation alert stays open, and exited processes stay unregistered
+        monitor.sample(&mut system, &allocations, &alerts);
+        assert!(recorder.alerts.lock().unwrap().is_empty());
+        assert!(!monitor.unregister("worker", exited));
+        
+        assert!(monitor.unregister("worker", own));
+        assert!(monitor.usage().is_empty());
+    }
+}
@@ -2,13 +2,24 @@
 //!
 //! Provides alerting mechanisms when resource constraints are violated
 //! or approaching thresholds.
+//!
+//! A breached condition opens an alert with an ID; while it persists, further breaches
+//! only update the open alert. Once the condition has stayed clear for a cool-down,
+//! the alert resolves and handlers are told.
+//!
+//! Escalation rules raise the level of alerts left open too long at one level, sending
+//! the alert again under its ID. Routes restrict the handlers each level is sent to.
 
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
 use serde::{Serialize, Deserialize};
 use crate::resource::ResourceType;
 
 /// Alert level
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum AlertLevel {
     /// Informational alert
     Info,
@@ -49,10 +60,117 @@ pub struct Alert {
     /// Threshold value
     pub threshold_value: f64,
     
+    /// Agent the alert is about, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+    
+    /// ID of the open alert this belongs to; one-off alerts have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    
+    /// Level the alert was escalated from, if an escalation rule raised it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalated_from: Option<AlertLevel>,
+    
     /// Timestamp
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+/// An alerted condition that hasn't resolved yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertInstance {
+    /// Alert ID
+    pub id: u64,
+    
+    /// Resource in breach
+    pub resource_type: ResourceType,
+    
+    /// Agent in breach, if the alert is about one
+    pub agent_id: Option<String>,
+    
+    /// Highest level reached
+    pub level: AlertLevel,
+    
+    /// Message of the latest breach
+    pub message: String,
+    
+    /// Value of the latest breach
+    pub current_value: f64,
+    
+    /// Threshold of the latest breach
+    pub threshold_value: f64,
+    
+    /// When the alert opened
+    pub opened_at: chrono::DateTime<chrono::Utc>,
+    
+    /// When the condition was last breached
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+    
+    /// When the alert reached its current level
+    pub level_since: chrono::DateTime<chrono::Utc>,
+    
+    /// Number of breaches seen
+    pub count: u64,
+    
+    /// When the condition cleared, if it has stayed clear since its last breach
+    pub clear_since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Notice that an open alert has resolved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertResolution {
+    /// ID of the resolved alert
+    pub id: u64,
+    
+    /// Resource that was in breach
+    pub resource_type: ResourceType,
+    
+    /// Agent that was in breach, if the alert was about one
+    pub agent_id: Option<String>,
+    
+    /// Highest level the alert reached
+    pub level: AlertLevel,
+    
+    /// When the alert opened
+    pub opened_at: chrono::DateTime<chrono::Utc>,
+    
+    /// When the alert resolved
+    pub resolved_at: chrono::DateTime<chrono::Utc>,
+    
+    /// Number of breaches seen while open
+    pub count: u64,
+}
+
+impl AlertResolution {
+    fn new(instance: AlertInstance, resolved_at: chrono::DateTime<chrono::Utc>) -> Self {
+        Self {
+            id: instance.id,
+            resource_type: instance.resource_type,
+            agent_id: instance.agent_id,
+            level: instance.level,
+            opened_at: instance.opened_at,
+            resolved_at,
+            count: instance.count,
+        }
+    }
+    
+    /// Format resolution as string
+    pub fn format(&self) -> String {
+        let subject = match &self.agent_id {
+            Some(agent_id) => format!("{:?} of agent {}", self.resource_type, agent_id),
+            None => format!("{:?}", self.resource_type),
+        };
+        format!(
+            "[RESOLVED] {} - alert {} for {} resolved after {} breaches since {}",
+            self.resolved_at.to_rfc3339(),
+            self.id,
+            subject,
+            self.count,
+            self.opened_at.to_rfc3339()
+        )
+    }
+}
+
 impl Alert {
     /// Create a new alert
     pub fn new(
@@ -68,10 +186,19 @@ impl Alert {
             message: message.to_string(),
             current_value,
             threshold_value,
+            agent_id: None,
+            id: None,
+            escalated_from: None,
             timestamp: chrono::Utc::now(),
         }
     }
     
+    /// Attribute the alert to an agent
+    pub fn with_agent(mut self, agent_id: &str) -> Self {
+        self.agent_id = Some(agent_id.to_string());
+        self
+    }
+    
     /// Format alert as string
     pub fn format(&self) -> String {
         format!(
@@ -85,10 +212,72 @@ impl Alert {
     }
 }
 
+/// Raises alerts left open at one level for too long
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscalationRule {
+    /// Level the alert must be at
+    pub from: AlertLevel,
+    
+    /// Level to escalate to
+    pub to: AlertLevel,
+    
+    /// How long the alert must have been at `from` (ms)
+    pub after_ms: u64,
+}
+
+impl EscalationRule {
+    /// Create a new escalation rule
+    pub fn new(from: AlertLevel, to: AlertLevel, after: Duration) -> Self {
+        Self { from, to, after_ms: after.as_millis() as u64 }
+    }
+}
+
+/// An alert opening or resolving, as logged and sent to webhooks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum AlertEvent {
+    /// An alert was raised
+    Open(Alert),
+    /// An open alert resolved
+    Resolved(AlertResolution),
+}
+
+impl AlertEvent {
+    /// When the event happened
+    pub fn timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+        match self {
+            AlertEvent::Open(alert) => alert.timestamp,
+            AlertEvent::Resolved(resolution) => resolution.resolved_at,
+        }
+    }
+    
+    /// Format event as string
+    pub fn format(&self) -> String {
+        match self {
+            AlertEvent::Open(alert) => alert.format(),
+            AlertEvent::Resolved(resolution) => resolution.format(),
+        }
+    }
+}
+
 /// Alert handler trait
 pub trait AlertHandler: Send + Sync {
     /// Handle an alert
     fn handle(&self, alert: &Alert);
+    
+    /// Handle an open alert resolving; ignored unless implemented
+    fn resolve(&self, _resolution: &AlertResolution) {}
+    
+    /// Name alert routes refer to the handler by
+    fn name(&self) -> &str {
+        "custom"
+    }
+    
+    /// Wait up to `timeout` for alerts handled in the background to be delivered;
+    /// returns whether they all were
+    fn flush(&self, _timeout: Duration) -> bool {
+        true
+    }
 }
 
 /// Console alert handler
@@ -103,77 +292,293 @@ impl AlertHandler for ConsoleAlertHandler {
             AlertLevel::Fatal => tracing::error!("{}", alert.format()),
         }
     }
-}
-
-/// File alert handler
-pub struct FileAlertHandler {
-    /// Output file path
-    path: std::path::PathBuf,
-}
-
-impl FileAlertHandler {
-    /// Create a new file alert handler
-    pub fn new<P: AsRef<std::path::Path>>(path: P) -> Self {
-        Self {
-            path: path.as_ref().to_path_buf(),
-        }
+    
+    fn resolve(&self, resolution: &AlertResolution) {
+        tracing::info!("{}", resolution.format());
     }
-}
-
-impl AlertHandler for FileAlertHandler {
-    fn handle(&self, alert: &Alert) {
-        // Format alert
-        let formatted = format!(
-            "{} [{}] {}\n",
-            alert.timestamp.to_rfc3339(),
-            alert.level,
-            alert.message
-        );
-        
-        // Append to file
-        if let Err(e) = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.path)
-            .and_then(|mut file| std::io::Write::write_all(&mut file, formatted.as_bytes()))
-        {
-            tracing::error!("Failed to write alert to file: {}", e);
-        }
+    
+    fn name(&self) -> &str {
+        "console"
     }
 }
 
+/// Key of an open alert: the resource and, for agent alerts, the agent
+type AlertKey = (ResourceType, Option<String>);
+
 /// Alert manager
+///
+/// Handlers can be added while alerts are being emitted from another thread.
 pub struct AlertManager {
     /// Alert handlers
-    handlers: Vec<Box<dyn AlertHandler>>,
+    handlers: RwLock<Vec<Box<dyn AlertHandler>>>,
     
     /// Minimum level to trigger alerts
     min_level: AlertLevel,
+    
+    /// Open alerts by resource and agent
+    open: Mutex<HashMap<AlertKey, AlertInstance>>,
+    
+    /// ID for the next alert opened
+    next_id: AtomicU64,
+    
+    /// Number of alerts resolved
+    resolved: AtomicU64,
+    
+    /// How long a condition must stay clear before its alert resolves
+    cooldown: chrono::Duration,
+    
+    /// Rules escalating alerts left open
+    escalations: Vec<EscalationRule>,
+    
+    /// Names of the handlers each level is sent to; unrouted levels go to every handler
+    routes: BTreeMap<AlertLevel, Vec<String>>,
+    
+    /// Source of the current time
+    clock: Box<dyn Fn() -> chrono::DateTime<chrono::Utc> + Send + Sync>,
 }
 
 impl AlertManager {
     /// Create a new alert manager
     pub fn new(min_level: AlertLevel) -> Self {
         Self {
-            handlers: Vec::new(),
+            handlers: RwLock::new(Vec::new()),
             min_level,
+            open: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            resolved: AtomicU64::new(0),
+            cooldown: chrono::Duration::minutes(1),
+            escalations: Vec::new(),
+            routes: BTreeMap::new(),
+            clock: Box::new(chrono::Utc::now),
         }
     }
     
+    /// Set how long a condition must stay clear before its alert resolves
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = chrono::Duration::from_std(cooldown).unwrap_or(chrono::Duration::MAX);
+        self
+    }
+    
+    /// Set the rules escalating alerts left open
+    pub fn with_escalations(mut self, escalations: Vec<EscalationRule>) -> Self {
+        self.escalations = escalations;
+        self
+    }
+    
+    /// Send each routed level only to the handlers named for it
+    pub fn with_routes(mut self, routes: BTreeMap<AlertLevel, Vec<String>>) -> Self {
+        self.routes = routes;
+        self
+    }
+    
+    /// Take the current time from `clock` instead of the system clock
+    #[cfg(test)]
+    pub(crate) fn with_clock(mut self, clock: impl Fn() -> chrono::DateTime<chrono::Utc> + Send + Sync + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+    
     /// Add an alert handler
-    pub fn add_handler(&mut self, handler: Box<dyn AlertHandler>) {
-        self.handlers.push(handler);
+    pub fn add_handler(&self, handler: Box<dyn AlertHandler>) {
+        self.handlers.write().unwrap_or_else(|e| e.into_inner()).push(handler);
     }
     
-    /// Emit an alert
+    /// Emit a one-off alert
     pub fn emit(&self, alert: Alert) {
         // Check if alert level meets minimum threshold
         if alert.level as u8 >= self.min_level as u8 {
-            // Send to all handlers
-            for handler in &self.handlers {
-                handler.handle(&alert);
+            self.dispatch(&alert);
+        }
+    }
+    
+    /// Report a breached condition, identified by the alert's resource and agent
+    ///
+    /// The first breach opens an alert and is sent to handlers. Further breaches only
+    /// update the open alert, unless they escalate its level, which is sent again
+    /// under the same ID.
+    pub fn raise(&self, mut alert: Alert) {
+        if (alert.level as u8) < self.min_level as u8 {
+            return;
+        }
+        
+        {
+            let now = (self.clock)();
+            let mut open = self.lock_open();
+            let key = (alert.resource_type, alert.agent_id.clone());
+            match open.get_mut(&key) {
+                Some(instance) => {
+                    instance.last_seen = alert.timestamp;
+                    instance.count += 1;
+                    instance.clear_since = None;
+                    instance.message = alert.message.clone();
+                    instance.current_value = alert.current_value;
+                    instance.threshold_value = alert.threshold_value;
+                    if alert.level as u8 <= instance.level as u8 {
+                        return;
+                    }
+                    instance.level = alert.level;
+                    instance.level_since = now;
+                    alert.id = Some(instance.id);
+                },
+                None => {
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    alert.id = Some(id);
+                    open.insert(key, AlertInstance {
+                        id,
+                        resource_type: alert.resource_type,
+                        agent_id: alert.agent_id.clone(),
+                        level: alert.level,
+                        message: alert.message.clone(),
+                        current_value: alert.current_value,
+                        threshold_value: alert.threshold_value,
+                        opened_at: alert.timestamp,
+                        last_seen: alert.timestamp,
+                        level_since: now,
+                        count: 1,
+                        clear_since: None,
+                    });
+                },
             }
         }
+        self.dispatch(&alert);
+    }
+    
+    /// Report a condition as clear, resolving its open alert once it has stayed clear
+    /// for the cool-down
+    pub fn clear(&self, resource_type: ResourceType, agent_id: Option<&str>) {
+        let now = (self.clock)();
+        let resolved = {
+            let mut open = self.lock_open();
+            let key = (resource_type, agent_id.map(str::to_string));
+            let Some(instance) = open.get_mut(&key) else {
+                return;
+            };
+            let clear_since = *instance.clear_since.get_or_insert(now);
+            if now.signed_duration_since(clear_since) < self.cooldown {
+                return;
+            }
+            open.remove(&key)
+        };
+        
+        if let Some(instance) = resolved {
+            self.dispatch_resolution(&AlertResolution::new(instance, now));
+        }
+    }
+    
+    /// Resolve the open alerts of agents no longer monitored, without waiting out the
+    /// cool-down
+    pub(crate) fn resolve_agents(&self, monitored: impl Fn(&str) -> bool) {
+        let now = (self.clock)();
+        let resolved: Vec<AlertInstance> = {
+            let mut open = self.lock_open();
+            let stale: Vec<AlertKey> = open.keys()
+                .filter(|(_, agent_id)| agent_id.as_deref().is_some_and(|agent_id| !monitored(agent_id)))
+                .cloned()
+                .collect();
+            stale.iter().filter_map(|key| open.remove(key)).collect()
+        };
+        
+        for instance in resolved {
+            self.dispatch_resolution(&AlertResolution::new(instance, now));
+        }
+    }
+    
+    /// Escalate the open alerts that have been at a rule's level for its duration,
+    /// sending each again under its ID
+    ///
+    /// Alerts whose condition has cleared aren't escalated while they wait out the
+    /// cool-down.
+    pub fn escalate(&self) {
+        if self.escalations.is_empty() {
+            return;
+        }
+        
+        let now = (self.clock)();
+        let escalated: Vec<Alert> = {
+            let mut open = self.lock_open();
+            open.values_mut()
+                .filter(|instance| instance.clear_since.is_none())
+                .filter_map(|instance| {
+                    let held = now.signed_duration_since(instance.level_since);
+                    let rule = self.escalations.iter().find(|rule| {
+                        rule.from == instance.level
+                            && rule.to > rule.from
+                            && held >= chrono::Duration::milliseconds(rule.after_ms as i64)
+                    })?;
+                    
+                    let message = format!("{} (escalated from {} after {}s)", instance.message, instance.level, held.num_seconds());
+                    let alert = Alert {
+                        agent_id: instance.agent_id.clone(),
+                        id: Some(instance.id),
+                        escalated_from: Some(instance.level),
+                        timestamp: now,
+                        ..Alert::new(rule.to, instance.resource_type, &message, instance.current_value, instance.threshold_value)
+                    };
+                    instance.level = rule.to;
+                    instance.level_since = now;
+                    Some(alert)
+                })
+                .collect()
+        };
+        
+        for alert in &escalated {
+            metrics::counter!("mcp.hm.alerts_escalated", 1, "level" => alert.level.to_string());
+            self.dispatch(alert);
+        }
+    }
+    
+    /// Alerts currently open, oldest first
+    pub fn open_alerts(&self) -> Vec<AlertInstance> {
+        let mut open: Vec<AlertInstance> = self.lock_open().values().cloned().collect();
+        open.sort_by_key(|instance| instance.id);
+        open
+    }
+    
+    /// Number of alerts resolved so far
+    pub fn resolved_count(&self) -> u64 {
+        self.resolved.load(Ordering::Relaxed)
+    }
+    
+    fn lock_open(&self) -> std::sync::MutexGuard<'_, HashMap<AlertKey, AlertInstance>> {
+        self.open.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Send an alert to all handlers
+    fn dispatch(&self, alert: &Alert) {
+        metrics::counter!("mcp.hm.alerts", 1, "level" => alert.level.to_string(), "resource" => format!("{:?}", alert.resource_type));
+        
+        let handlers = self.handlers.read().unwrap_or_else(|e| e.into_inner());
+        for handler in handlers.iter().filter(|handler| self.routed(alert.level, handler.as_ref())) {
+            handler.handle(alert);
+        }
+    }
+    
+    /// Send a resolution to all handlers
+    fn dispatch_resolution(&self, resolution: &AlertResolution) {
+        self.resolved.fetch_add(1, Ordering::Relaxed);
+        metrics::counter!("mcp.hm.alerts_resolved", 1, "resource" => format!("{:?}", resolution.resource_type));
+        
+        let handlers = self.handlers.read().unwrap_or_else(|e| e.into_inner());
+        for handler in handlers.iter().filter(|handler| self.routed(resolution.level, handler.as_ref())) {
+            handler.resolve(resolution);
+        }
+    }
+    
+    /// Wait up to `timeout` for every handler to deliver the alerts it has queued;
+    /// returns whether they all did
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let deadline = std::time::Instant::now() + timeout;
+        let handlers = self.handlers.read().unwrap_or_else(|e| e.into_inner());
+        let mut flushed = true;
+        for handler in handlers.iter() {
+            flushed &= handler.flush(deadline.saturating_duration_since(std::time::Instant::now()));
+        }
+        flushed
+    }
+    
+    /// Whether alerts at `level` are sent to `handler`
+    fn routed(&self, level: AlertLevel, handler: &dyn AlertHandler) -> bool {
+        self.routes.get(&level).is_none_or(|names| names.iter().any(|name| name == handler.name()))
     }
     
     /// Create and emit an alert with the given parameters
@@ -189,3 +594,133 @@ impl AlertManager {
         self.emit(alert);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    
+    /// Alert handler that keeps the IDs of the alerts and resolutions it receives
+    #[derive(Clone, Default)]
+    struct RecordingHandler {
+        opened: Arc<Mutex<Vec<(u64, AlertLevel)>>>,
+        resolved: Arc<Mutex<Vec<u64>>>,
+    }
+    
+    impl AlertHandler for RecordingHandler {
+        fn handle(&self, alert: &Alert) {
+            self.opened.lock().unwrap().push((alert.id.unwrap(), alert.level));
+        }
+        
+        fn resolve(&self, resolution: &AlertResolution) {
+            self.resolved.lock().unwrap().push(resolution.id);
+        }
+    }
+    
+    fn memory(level: AlertLevel) -> Alert {
+        Alert::new(level, ResourceType::Memory, "memory high", 700.0, 640.0)
+    }
+    
+    #[test]
+    fn test_alert_lifecycle() {
+        let alerts = AlertManager::new(AlertLevel::Info).with_cooldown(Duration::from_millis(50));
+        let recorder = RecordingHandler::default();
+        alerts.add_handler(Box::new(recorder.clone()));
+        
+        // Repeated breaches update the open alert; escalating is sent again
+        alerts.raise(memory(AlertLevel::Warning));
+        alerts.raise(memory(AlertLevel::Warning));
+        alerts.raise(memory(AlertLevel::Critical));
+        alerts.raise(memory(AlertLevel::Warning));
+        assert_eq!(*recorder.opened.lock().unwrap(), [(1, AlertLevel::Warning), (1, AlertLevel::Critical)]);
+        let open = alerts.open_alerts();
+        assert_eq!(open.len(), 1);
+        assert_eq!((open[0].id, open[0].level, open[0].count), (1, AlertLevel::Critical, 4));
+        
+        // A breach during the cool-down keeps the alert open
+        alerts.clear(ResourceType::Memory, None);
+        alerts.raise(memory(AlertLevel::Warning));
+        std::thread::sleep(Duration::from_millis(60));
+        alerts.clear(ResourceType::Memory, None);
+        assert_eq!(alerts.open_alerts().len(), 1);
+        
+        // Staying clear for the cool-down resolves it
+        std::thread::sleep(Duration::from_millis(60));
+        alerts.clear(ResourceType::Memory, None);
+        assert!(alerts.open_alerts().is_empty());
+        assert_eq!(*recorder.resolved.lock().unwrap(), [1]);
+        assert_eq!(alerts.resolved_count(), 1);
+        
+        // Agents' alerts are kept apart and resolve once they're no longer monitored
+        alerts.raise(memory(AlertLevel::Warning));
+        alerts.raise(memory(AlertLevel::Warning).with_agent("worker"));
+        assert_eq!(alerts.open_alerts().iter().map(|alert| alert.id).collect::<Vec<_>>(), [2, 3]);
+        alerts.resolve_agents(|agent_id| agent_id != "worker");
+        assert_eq!(*recorder.resolved.lock().unwrap(), [1, 3]);
+        assert_eq!(alerts.open_alerts()[0].agent_id, None);
+    }
+    
+    /// Alert handler recording the levels it receives under its name
+    #[derive(Clone)]
+    struct NamedHandler {
+        name: &'static str,
+        levels: Arc<Mutex<Vec<AlertLevel>>>,
+    }
+    
+    impl AlertHandler for NamedHandler {
+        fn handle(&self, alert: &Alert) {
+            self.levels.lock().unwrap().push(alert.level);
+        }
+        
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+    
+    #[test]
+    fn test_alert_escalation() {
+        // Time only moves when the test advances it
+        let now = Arc::new(Mutex::new(chrono::Utc::now()));
+        let clock = now.clone();
+        let advance = |minutes| *now.lock().unwrap() += chrono::Duration::minutes(minutes);
+        
+        let alerts = AlertManager::new(AlertLevel::Info)
+            .with_cooldown(Duration::from_secs(60))
+            .with_escalations(vec![
+                EscalationRule::new(AlertLevel::Warning, AlertLevel::Critical, Duration::from_secs(300)),
+                EscalationRule::new(AlertLevel::Critical, AlertLevel::Fatal, Duration::from_secs(300)),
+            ])
+            .with_routes(BTreeMap::from([(AlertLevel::Fatal, vec!["pager".to_string()])]))
+            .with_clock(move || *clock.lock().unwrap());
+        let recorder = RecordingHandler::default();
+        alerts.add_handler(Box::new(recorder.clone()));
+        let pager = NamedHandler { name: "pager", levels: Default::default() };
+        alerts.add_handler(Box::new(pager.clone()));
+        
+        // A warning escalates once it has been open for the rule's duration
+        alerts.raise(memory(AlertLevel::Warning));
+        advance(4);
+        alerts.escalate();
+        assert_eq!(*recorder.opened.lock().unwrap(), [(1, AlertLevel::Warning)]);
+        advance(1);
+        alerts.raise(memory(AlertLevel::Warning));
+        alerts.escalate();
+        assert_eq!(*recorder.opened.lock().unwrap(), [(1, AlertLevel::Warning), (1, AlertLevel::Critical)]);
+        
+        // Critical escalates to fatal 5 minutes later, sent only to the fatal route
+        advance(5);
+        alerts.escalate();
+        assert_eq!(recorder.opened.lock().unwrap().len(), 2);
+        assert_eq!(*pager.levels.lock().unwrap(), [AlertLevel::Warning, AlertLevel::Critical, AlertLevel::Fatal]);
+        assert_eq!(alerts.open_alerts()[0].level, AlertLevel::Fatal);
+        
+        // Once the condition clears, its alert no longer escalates and then resolves
+        alerts.raise(memory(AlertLevel::Warning).with_agent("worker"));
+        alerts.clear(ResourceType::Memory, Some("worker"));
+        advance(10);
+        alerts.escalate();
+        assert_eq!(pager.levels.lock().unwrap().len(), 4);
+        alerts.clear(ResourceType::Memory, Some("worker"));
+        assert_eq!(*recorder.resolved.lock().unwrap(), [2]);
+    }
+}
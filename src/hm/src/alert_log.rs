@@ -0,0 +1,274 @@
+//! Alert log for MCP-ZERO Hardware Manager
+//!
+//! `FileAlertHandler` appends alerts and their resolutions to a log file, either as
+//! text or as JSON lines of `AlertEvent`. With rotation on, a log about to grow past
+//! its size limit is renamed `<log>.1`, `<log>.1` becomes `<log>.2` and so on, keeping
+//! at most the configured number of files. Critical and fatal alerts are synced to
+//! disk before the handler returns.
+//!
+//! Handlers in one process that log to the same path share a lock, so their writes and
+//! rotations don't interleave. Separate processes must not log to the same path.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use serde::{Serialize, Deserialize};
+
+use crate::alert::{Alert, AlertEvent, AlertHandler, AlertLevel, AlertResolution};
+use crate::config::HMConfig;
+
+/// Locks shared by the handlers logging to each path
+static PATH_LOCKS: OnceLock<Mutex<HashMap<PathBuf, Weak<Mutex<()>>>>> = OnceLock::new();
+
+/// Alert log line format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertLogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON `AlertEvent` per line
+    Json,
+}
+
+/// File alert handler
+pub struct FileAlertHandler {
+    /// Output file path
+    path: PathBuf,
+    
+    /// Line format
+    format: AlertLogFormat,
+    
+    /// Size the log may reach before it's rotated (bytes; 0 never rotates)
+    max_bytes: u64,
+    
+    /// Files kept, counting the live log
+    max_files: u32,
+    
+    /// Lock shared with other handlers logging to the same path
+    lock: Arc<Mutex<()>>,
+}
+
+impl FileAlertHandler {
+    /// Create a new file alert handler writing text lines, without rotation
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            format: AlertLogFormat::Text,
+            max_bytes: 0,
+            max_files: 1,
+            lock: path_lock(path.as_ref()),
+        }
+    }
+    
+    /// Create a file alert handler as configured
+    pub fn from_config(config: &HMConfig) -> Self {
+        Self::new(&config.alert_log_path)
+            .with_format(config.alert_log_format)
+            .with_rotation(config.alert_log_max_bytes, config.alert_log_max_files)
+    }
+    
+    /// Set the line format
+    pub fn with_format(mut self, format: AlertLogFormat) -> Self {
+        self.format = format;
+        self
+    }
+    
+    /// Rotate the log before it grows past `max_bytes`, keeping `max_files` files
+    /// including the live one
+    pub fn with_rotation(mut self, max_bytes: u64, max_files: u32) -> Self {
+        self.max_bytes = max_bytes;
+        self.max_files = max_files.max(1);
+        self
+    }
+    
+    /// Append a line, rotating first if it would take the log past its size limit
+    fn write(&self, line: &str, sync: bool) {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        
+        let result = self.rotate_for(line.len() as u64).and_then(|()| {
+            let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+            file.write_all(line.as_bytes())?;
+            if sync {
+                file.sync_data()?;
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            tracing::error!("Failed to write alert to file: {}", e);
+        }
+    }
+    
+    /// Rotate the log if appending `len` bytes would take it past its size limit
+    fn rotate_for(&self, len: u64) -> std::io::Result<()> {
+        if self.max_bytes == 0 {
+            return Ok(());
+        }
+        let size = match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if size == 0 || size + len <= self.max_bytes {
+            return Ok(());
+        }
+        
+        // Shift every file up one, the oldest falling off the end
+        let oldest = rotated_path(&self.path, self.max_files - 1);
+        match std::fs::remove_file(&oldest) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {},
+        }
+        for index in (1..self.max_files).rev() {
+            let from = rotated_path(&self.path, index - 1);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.path, index))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AlertHandler for FileAlertHandler {
+    fn handle(&self, alert: &Alert) {
+        let line = match self.format {
+            AlertLogFormat::Text => format!("{} [{}] {}\n", alert.timestamp.to_rfc3339(), alert.level, alert.message),
+            AlertLogFormat::Json => json_line(&AlertEvent::Open(alert.clone())),
+        };
+        self.write(&line, alert.level as u8 >= AlertLevel::Critical as u8);
+    }
+    
+    fn resolve(&self, resolution: &AlertResolution) {
+        let line = match self.format {
+            AlertLogFormat::Text => format!("{}\n", resolution.format()),
+            AlertLogFormat::Json => json_line(&AlertEvent::Resolved(resolution.clone())),
+        };
+        self.write(&line, false);
+    }
+    
+    fn name(&self) -> &str {
+        "file"
+    }
+}
+
+fn json_line(event: &AlertEvent) -> String {
+    let mut line = serde_json::to_string(event).unwrap_or_default();
+    line.push('\n');
+    line
+}
+
+/// Lock for the handlers logging to `path`
+fn path_lock(path: &Path) -> Arc<Mutex<()>> {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut locks = PATH_LOCKS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    locks.retain(|_, lock| lock.strong_count() > 0);
+    if let Some(lock) = locks.get(&path).and_then(Weak::upgrade) {
+        return lock;
+    }
+    let lock = Arc::new(Mutex::new(()));
+    locks.insert(path, Arc::downgrade(&lock));
+    lock
+}
+
+/// Path of the log rotated `index` times; 0 is the live log
+pub fn rotated_path(path: &Path, index: u32) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", index));
+    PathBuf::from(rotated)
+}
+
+/// Parse a JSON alert log line; text lines and anything else give None
+pub fn parse_alert_line(line: &str) -> Option<AlertEvent> {
+    serde_json::from_str(line.trim()).ok()
+}
+
+/// Read the events logged at `path` and its rotated files, oldest first, leaving out
+/// any before `since`
+pub fn read_alert_log(path: &Path, since: Option<chrono::DateTime<chrono::Utc>>) -> std::io::Result<Vec<AlertEvent>> {
+    let mut rotations = 0;
+    while rotated_path(path, rotations + 1).exists() {
+        rotations += 1;
+    }
+    
+    let mut events = Vec::new();
+    for index in (0..=rotations).rev() {
+        let file = match File::open(rotated_path(path, index)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        for line in BufReader::new(file).lines() {
+            let Some(event) = parse_alert_line(&line?) else {
+                continue;
+            };
+            if since.is_none_or(|since| event.timestamp() >= since) {
+                events.push(event);
+            }
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::ResourceType;
+    
+    fn alert(level: AlertLevel, message: &str, seconds: i64) -> Alert {
+        Alert {
+            timestamp: chrono::DateTime::<chrono::Utc>::from_timestamp(1_700_000_000 + seconds, 0).unwrap(),
+            ..Alert::new(level, ResourceType::Memory, message, 700.0, 640.0)
+        }
+    }
+    
+    #[test]
+    fn test_alert_log_rotation() {
+        let dir = std::env::temp_dir().join(format!("mcp-hm-alert-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("mcp-hm.log");
+        
+        // Two handlers share the log, with room for two lines per file
+        let max_bytes = json_line(&AlertEvent::Open(alert(AlertLevel::Critical, "alert 0", 0))).len() as u64 * 2;
+        let first = FileAlertHandler::new(&path).with_format(AlertLogFormat::Json).with_rotation(max_bytes, 3);
+        let second = FileAlertHandler::new(&path).with_format(AlertLogFormat::Json).with_rotation(max_bytes, 3);
+        for seconds in 0..8 {
+            let handler = if seconds % 2 == 0 { &first } else { &second };
+            handler.handle(&alert(AlertLevel::Critical, &format!("alert {}", seconds), seconds));
+        }
+        
+        // Only the newest three files are kept, each within the size limit
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+        for index in 0..3 {
+            assert_eq!(std::fs::metadata(rotated_path(&path, index)).unwrap().len(), max_bytes);
+        }
+        
+        // Text lines are skipped when reading; this handler doesn't rotate
+        FileAlertHandler::new(&path).handle(&alert(AlertLevel::Warning, "text", 9));
+        let messages = |since| -> Vec<String> {
+            read_alert_log(&path, since).unwrap().into_iter()
+                .map(|event| match event {
+                    AlertEvent::Open(alert) => alert.message,
+                    AlertEvent::Resolved(resolution) => resolution.id.to_string(),
+                })
+                .collect()
+        };
+        assert_eq!(messages(None), ["alert 2", "alert 3", "alert 4", "alert 5", "alert 6", "alert 7"]);
+        let since = chrono::DateTime::<chrono::Utc>::from_timestamp(1_700_000_006, 0);
+        assert_eq!(messages(since), ["alert 6", "alert 7"]);
+        
+        // Alerts keep their full serialization
+        let line = std::fs::read_to_string(&path).unwrap();
+        let Some(AlertEvent::Open(logged)) = parse_alert_line(line.lines().next().unwrap()) else {
+            panic!("unexpected log line: {}", line);
+        };
+        assert_eq!((logged.resource_type, logged.current_value, logged.threshold_value), (ResourceType::Memory, 700.0, 640.0));
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,246 @@
+//! HTTP control API for MCP-ZERO Hardware Manager
+//!
+//! When `api_listen` is configured, the daemon serves a small REST API:
+//!
+//! - `GET /health`: liveness and the number of samples taken
+//! - `GET /stats`: current `ResourceStats`
+//! - `GET /report`: the resource report
+//! - `GET /allocations`: every agent allocation
+//! - `POST /allocations`: submit an `AgentAllocation`; 409 if it would exceed the limits,
+//!   even after preempting lower priority agents when `allocation_preemption` is on
+//! - `DELETE /allocations/{agent_id}`: release an agent's allocation
+//!
+//! With `api_token` set, every endpoint but `/health` requires an
+//! `Authorization: Bearer <token>` header. A token is mandatory unless the API only
+//! listens on loopback.
+
+use std::convert::Infallible;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use anyhow::{Result, Context, anyhow};
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use serde::Serialize;
+
+use crate::{AgentAllocation, HardwareManager, HMError};
+
+/// Largest request body accepted (bytes)
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// State shared by the API's request handlers
+struct Api {
+    hm: Arc<HardwareManager>,
+    token: Option<String>,
+}
+
+/// Error response with its status
+#[derive(Debug)]
+struct ApiError(StatusCode, String);
+
+/// Serve the control API on `listen`
+///
+/// The API runs on its own thread for the life of the process, and keeps `hm` alive
+/// for as long.
+pub fn start_api_server(hm: Arc<HardwareManager>, listen: &str, token: Option<&str>) -> Result<SocketAddr> {
+    let addr: SocketAddr = listen.parse()
+        .with_context(|| format!("Invalid API listen address: {}", listen))?;
+    let token = token.filter(|token| !token.is_empty()).map(str::to_string);
+    if token.is_none() && !addr.ip().is_loopback() {
+        return Err(anyhow!("An API token is required to serve the control API on non-loopback address {}", addr));
+    }
+
+    let listener = TcpListener::bind(addr)
+        .with_context(|| format!("Failed to bind control API on {}", addr))?;
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .context("Failed to create control API runtime")?;
+    let server = {
+        let _runtime = runtime.enter();
+        hyper::Server::from_tcp(listener)
+            .with_context(|| format!("Failed to start control API on {}", addr))?
+    };
+
+    let api = Arc::new(Api { hm, token });
+    std::thread::Builder::new()
+        .name("mcp-hm-api".to_string())
+        .spawn(move || {
+            let service = make_service_fn(move |_| {
+                let api = api.clone();
+                async move { Ok::<_, Infallible>(service_fn(move |request| handle(api.clone(), request))) }
+            });
+            if let Err(e) = runtime.block_on(server.serve(service)) {
+                tracing::error!("Control API failed: {}", e);
+            }
+        })
+        .context("Failed to start control API thread")?;
+
+    tracing::info!("Serving control API at http://{}", addr);
+    Ok(addr)
+}
+
+/// Route a request, turning errors into JSON error responses
+async fn handle(api: Arc<Api>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = route(&api, request).await.unwrap_or_else(|ApiError(status, message)| {
+        let mut response = json_response(status, &serde_json::json!({ "error": message }));
+        if status == StatusCode::UNAUTHORIZED {
+            response.headers_mut().insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Bearer"));
+        }
+        response
+    });
+    Ok(response)
+}
+
+async fn route(api: &Api, request: Request<Body>) -> Result<Response<Body>, ApiError> {
+    let path = request.uri().path().to_string();
+    let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+
+    if segments != ["health"] && !api.authorized(&request) {
+        return Err(ApiError(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token".to_string()));
+    }
+
+    match (request.method().clone(), segments.as_slice()) {
+        (Method::GET, ["health"]) => {
+            Ok(json_response(StatusCode::OK, &serde_json::json!({ "status": "ok", "samples": api.hm.sample_count() })))
+        },
+        (Method::GET, ["stats"]) => Ok(json_response(StatusCode::OK, &api.hm.get_stats())),
+        (Method::GET, ["report"]) => Ok(json_response(StatusCode::OK, &api.hm.generate_report())),
+        (Method::GET, ["allocations"]) => Ok(json_response(StatusCode::OK, &api.hm.get_allocations())),
+        (Method::POST, ["allocations"]) => {
+            let body = read_body(request.into_body()).await?;
+            let allocation: AgentAllocation = serde_json::from_slice(&body)
+                .map_err(|e| ApiError(StatusCode::BAD_REQUEST, format!("Invalid allocation: {}", e)))?;
+            if allocation.agent_id.is_empty() {
+                return Err(ApiError(StatusCode::BAD_REQUEST, "Invalid allocation: agent_id is empty".to_string()));
+            }
+
+            match api.hm.allocate_resources(allocation.clone()) {
+                Ok(_) => Ok(json_response(StatusCode::CREATED, &allocation)),
+                Err(e @ HMError::ResourceLimitExceeded(_)) => Err(ApiError(StatusCode::CONFLICT, e.to_string())),
+                Err(e) => Err(ApiError(StatusCode::BAD_REQUEST, e.to_string())),
+            }
+        },
+        (Method::DELETE, ["allocations", agent_id]) => {
+            api.hm.release_resources(agent_id)
+                .map_err(|e| ApiError(StatusCode::NOT_FOUND, e.to_string()))?;
+            Ok(Response::builder().status(StatusCode::NO_CONTENT).body(Body::empty()).unwrap())
+        },
+        (_, ["health" | "stats" | "report" | "allocations"] | ["allocations", _]) => {
+            Err(ApiError(StatusCode::METHOD_NOT_ALLOWED, format!("{} is not supported on {}", request.method(), path)))
+        },
+        _ => Err(ApiError(StatusCode::NOT_FOUND, format!("No such endpoint: {}", path))),
+    }
+}
+
+impl Api {
+    /// Whether the request carries the configured bearer token, if any
+    fn authorized(&self, request: &Request<Body>) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        request.headers().get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+    }
+}
+
+/// Compare secrets without exiting early on the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Read a request body of at most MAX_BODY_BYTES
+async fn read_body(mut body: Body) -> Result<Vec<u8>, ApiError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| ApiError(StatusCode::BAD_REQUEST, format!("Failed to read request body: {}", e)))?;
+        if bytes.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(ApiError(StatusCode::PAYLOAD_TOO_LARGE, format!("Request body exceeds {} bytes", MAX_BODY_BYTES)));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+fn json_response<T: Serialize>(status: StatusCode, value: &T) -> Response<Body> {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HMConfig;
+
+    fn request(method: Method, path: &str, token: Option<&str>, body: &str) -> Request<Body> {
+        let mut request = Request::builder().method(method).uri(path);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request.body(Body::from(body.to_string())).unwrap()
+    }
+
+    /// Send a request to the API, returning the status and JSON body
+    fn send(api: &Arc<Api>, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            let response = handle(api.clone(), request).await.unwrap();
+            let status = response.status();
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+        })
+    }
+
+    #[test]
+    fn test_api_routes() {
+        let hm = Arc::new(HardwareManager::new(HMConfig::default()));
+        let api = Arc::new(Api { hm, token: Some("secret".to_string()) });
+
+        // Only health is served without the token
+        assert_eq!(send(&api, request(Method::GET, "/health", None, "")).0, StatusCode::OK);
+        assert_eq!(send(&api, request(Method::GET, "/stats", None, "")).0, StatusCode::UNAUTHORIZED);
+        assert_eq!(send(&api, request(Method::GET, "/stats", Some("wrong"), "")).0, StatusCode::UNAUTHORIZED);
+        let (status, stats) = send(&api, request(Method::GET, "/stats", Some("secret"), ""));
+        assert_eq!(status, StatusCode::OK);
+        assert!(stats.get("cpu_percent").is_some());
+        assert!(send(&api, request(Method::GET, "/report", Some("secret"), "")).1.get("limits").is_some());
+
+        // Allocations within the limits are accepted, ones past them conflict
+        let allocation = r#"{"agent_id": "worker", "cpu_percent": 10.0, "memory_mb": 100, "priority": 5}"#;
+        assert_eq!(send(&api, request(Method::POST, "/allocations", Some("secret"), allocation)).0, StatusCode::CREATED);
+        let greedy = r#"{"agent_id": "greedy", "cpu_percent": 25.0, "memory_mb": 100, "priority": 5}"#;
+        let (status, error) = send(&api, request(Method::POST, "/allocations", Some("secret"), greedy));
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(error["error"].as_str().unwrap().contains("CPU"));
+        assert_eq!(send(&api, request(Method::POST, "/allocations", Some("secret"), "{")).0, StatusCode::BAD_REQUEST);
+
+        let (status, allocations) = send(&api, request(Method::GET, "/allocations", Some("secret"), ""));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(allocations.as_array().unwrap().len(), 1);
+        assert_eq!(allocations[0]["agent_id"], "worker");
+
+        // Releasing twice finds nothing the second time
+        assert_eq!(send(&api, request(Method::DELETE, "/allocations/worker", Some("secret"), "")).0, StatusCode::NO_CONTENT);
+        assert_eq!(send(&api, request(Method::DELETE, "/allocations/worker", Some("secret"), "")).0, StatusCode::NOT_FOUND);
+
+        assert_eq!(send(&api, request(Method::PUT, "/stats", Some("secret"), "")).0, StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(send(&api, request(Method::GET, "/nothing", Some("secret"), "")).0, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_api_requires_token_off_loopback() {
+        let hm = Arc::new(HardwareManager::new(HMConfig::default()));
+        assert!(start_api_server(hm.clone(), "0.0.0.0:0", None).is_err());
+        assert!(start_api_server(hm.clone(), "0.0.0.0:0", Some("")).is_err());
+        assert!(start_api_server(hm, "127.0.0.1:0", None).is_ok());
+    }
+}
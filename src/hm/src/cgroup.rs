@@ -0,0 +1,326 @@
+//! cgroup v2 enforcement for MCP-ZERO Hardware Manager
+//!
+//! Monitoring only notices an agent over budget at the next sample. With
+//! `cgroup_enforcement` on, each agent with registered processes gets a cgroup under
+//! `cgroup_parent` whose `cpu.max` and `memory.max` follow its AgentAllocation, so the
+//! kernel holds it to the budget in between. Degradation steps are applied to the
+//! cgroup too, suspending an agent by freezing it.
+//!
+//! Where cgroup v2 isn't available — other platforms, a v1 hierarchy, or missing
+//! permissions — the manager falls back to monitoring only and raises a Warning alert.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::alert::{AlertLevel, AlertManager};
+use crate::config::HMConfig;
+use crate::degrade::{DegradationAction, DegradationKind};
+use crate::resource::ResourceType;
+use crate::AgentAllocation;
+
+/// Scheduling period written to `cpu.max`, in microseconds
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// Smallest quota the kernel accepts in `cpu.max`, in microseconds
+const MIN_CPU_QUOTA_US: u64 = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    /// Not set up yet
+    Unchecked,
+    /// Enforcing through cgroups
+    Active,
+    /// Enforcement off, or unavailable
+    MonitoringOnly,
+}
+
+#[derive(Debug)]
+struct EnforcerState {
+    backend: Backend,
+    
+    /// Agents with a cgroup, and their registered processes
+    agents: HashMap<String, Vec<u32>>,
+}
+
+/// Keeps a cgroup per agent in line with its allocation
+#[derive(Debug)]
+pub(crate) struct CgroupEnforcer {
+    parent: PathBuf,
+    state: Mutex<EnforcerState>,
+}
+
+impl CgroupEnforcer {
+    pub(crate) fn new(config: &HMConfig) -> Self {
+        let backend = if config.cgroup_enforcement { Backend::Unchecked } else { Backend::MonitoringOnly };
+        Self {
+            parent: config.cgroup_parent.clone(),
+            state: Mutex::new(EnforcerState { backend, agents: HashMap::new() }),
+        }
+    }
+    
+    /// Whether allocations are being enforced
+    pub(crate) fn is_active(&self) -> bool {
+        self.lock_state().backend == Backend::Active
+    }
+    
+    /// Set up the parent cgroup on first use, falling back to monitoring only if it
+    /// can't be; returns whether enforcement is active
+    pub(crate) fn ensure_ready(&self, alerts: &AlertManager) -> bool {
+        let mut state = self.lock_state();
+        Self::ready(&self.parent, &mut state, alerts)
+    }
+    
+    /// Move a newly registered process into its agent's cgroup
+    pub(crate) fn add_process(&self, agent_id: &str, pid: u32, allocation: Option<&AgentAllocation>, alerts: &AlertManager) {
+        let mut state = self.lock_state();
+        if !Self::ready(&self.parent, &mut state, alerts) {
+            return;
+        }
+        
+        let cgroup = self.cgroup_path(agent_id);
+        let result = std::fs::create_dir_all(&cgroup)
+            .and_then(|_| match allocation {
+                Some(allocation) => write_limits(&cgroup, allocation.cpu_percent, allocation.memory_mb),
+                None => Ok(()),
+            })
+            .and_then(|_| write(&cgroup, "cgroup.procs", &pid.to_string()));
+        match result {
+            Ok(()) => state.agents.entry(agent_id.to_string()).or_default().push(pid),
+            Err(e) => Self::fall_back(&mut state, alerts, &format!("Failed to add process {} to cgroup {}: {}", pid, cgroup.display(), e)),
+        }
+    }
+    
+    /// Forget a process that was unregistered or exited, removing the agent's cgroup
+    /// once it has none left
+    pub(crate) fn remove_process(&self, agent_id: &str, pid: u32) {
+        let mut state = self.lock_state();
+        let Some(pids) = state.agents.get_mut(agent_id) else {
+            return;
+        };
+        pids.retain(|&registered| registered != pid);
+        if !pids.is_empty() {
+            return;
+        }
+        state.agents.remove(agent_id);
+        
+        // Processes still running in the cgroup keep it in place
+        let cgroup = self.cgroup_path(agent_id);
+        if let Err(e) = std::fs::remove_dir(&cgroup) {
+            tracing::debug!("Leaving cgroup {} in place: {}", cgroup.display(), e);
+        }
+    }
+    
+    /// Apply a changed allocation to the agent's cgroup, if it has one
+    pub(crate) fn update_allocation(&self, allocation: &AgentAllocation, alerts: &AlertManager) {
+        self.write_agent(&allocation.agent_id, alerts, |cgroup| {
+            write_limits(cgroup, allocation.cpu_percent, allocation.memory_mb)
+        });
+    }
+    
+    /// Apply a degradation step to the agent's cgroup, if it has one
+    pub(crate) fn apply_action(&self, action: &DegradationAction, alerts: &AlertManager) {
+        self.write_agent(&action.agent_id, alerts, |cgroup| match action.kind {
+            DegradationKind::Suspend => write(cgroup, "cgroup.freeze", "1"),
+            DegradationKind::Throttle | DegradationKind::Restore => {
+                write_limits(cgroup, action.cpu_percent, action.memory_mb)?;
+                write(cgroup, "cgroup.freeze", "0")
+            },
+        });
+    }
+    
+    /// Path of an agent's cgroup
+    pub(crate) fn cgroup_path(&self, agent_id: &str) -> PathBuf {
+        // Agent IDs become a single directory name
+        let name: String = agent_id.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+            .collect();
+        self.parent.join(format!("agent-{}", name.trim_start_matches('.')))
+    }
+    
+    fn write_agent(&self, agent_id: &str, alerts: &AlertManager, apply: impl FnOnce(&Path) -> std::io::Result<()>) {
+        let mut state = self.lock_state();
+        if state.backend != Backend::Active || !state.agents.contains_key(agent_id) {
+            return;
+        }
+        
+        let cgroup = self.cgroup_path(agent_id);
+        if let Err(e) = apply(&cgroup) {
+            Self::fall_back(&mut state, alerts, &format!("Failed to update cgroup {}: {}", cgroup.display(), e));
+        }
+    }
+    
+    fn ready(parent: &Path, state: &mut EnforcerState, alerts: &AlertManager) -> bool {
+        if state.backend == Backend::Unchecked {
+            match setup_parent(parent) {
+                Ok(()) => {
+                    tracing::info!("Enforcing agent allocations with cgroups under {}", parent.display());
+                    state.backend = Backend::Active;
+                },
+                Err(e) => Self::fall_back(state, alerts, &format!("cgroup enforcement unavailable at {}: {}", parent.display(), e)),
+            }
+        }
+        state.backend == Backend::Active
+    }
+    
+    /// Stop enforcing, warning that only monitoring remains
+    fn fall_back(state: &mut EnforcerState, alerts: &AlertManager, reason: &str) {
+        state.backend = Backend::MonitoringOnly;
+        state.agents.clear();
+        let message = format!("{}; falling back to monitoring only", reason);
+        alerts.send(AlertLevel::Warning, ResourceType::Process, &message, 0.0, 0.0);
+    }
+    
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, EnforcerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Create the parent cgroup and let its children use the cpu and memory controllers
+#[cfg(target_os = "linux")]
+fn setup_parent(parent: &Path) -> std::io::Result<()> {
+    use std::io::{Error, ErrorKind};
+    
+    std::fs::create_dir_all(parent)?;
+    let controllers = std::fs::read_to_string(parent.join("cgroup.controllers"))
+        .map_err(|_| Error::new(ErrorKind::Unsupported, "not a cgroup v2 hierarchy"))?;
+    for controller in ["cpu", "memory"] {
+        if !controllers.split_whitespace().any(|available| available == controller) {
+            return Err(Error::new(ErrorKind::Unsupported, format!("{} controller not available", controller)));
+        }
+    }
+    write(parent, "cgroup.subtree_control", "+cpu +memory")
+}
+
+#[cfg(not(target_os = "linux"))]
+fn setup_parent(_parent: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "cgroups are only available on Linux"))
+}
+
+/// Write `cpu.max` and `memory.max` for an allocation; a zero memory allocation leaves
+/// memory unlimited
+fn write_limits(cgroup: &Path, cpu_percent: f32, memory_mb: u32) -> std::io::Result<()> {
+    let quota = ((cpu_percent.max(0.0) as f64 / 100.0) * CPU_PERIOD_US as f64) as u64;
+    write(cgroup, "cpu.max", &format!("{} {}", quota.max(MIN_CPU_QUOTA_US), CPU_PERIOD_US))?;
+    
+    let memory = match memory_mb {
+        0 => "max".to_string(),
+        mb => (mb as u64 * 1024 * 1024).to_string(),
+    };
+    write(cgroup, "memory.max", &memory)
+}
+
+fn write(cgroup: &Path, file: &str, value: &str) -> std::io::Result<()> {
+    std::fs::write(cgroup.join(file), value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::alert::{Alert, AlertHandler};
+    
+    #[derive(Clone, Default)]
+    struct RecordingHandler {
+        alerts: Arc<Mutex<Vec<Alert>>>,
+    }
+    
+    impl AlertHandler for RecordingHandler {
+        fn handle(&self, alert: &Alert) {
+            self.alerts.lock().unwrap().push(alert.clone());
+        }
+    }
+    
+    fn recording_alerts() -> (AlertManager, RecordingHandler) {
+        let alerts = AlertManager::new(AlertLevel::Info);
+        let recorder = RecordingHandler::default();
+        alerts.add_handler(Box::new(recorder.clone()));
+        (alerts, recorder)
+    }
+    
+    fn read(cgroup: &Path, file: &str) -> String {
+        std::fs::read_to_string(cgroup.join(file)).unwrap().trim().to_string()
+    }
+    
+    #[test]
+    fn test_monitoring_only_fallback() {
+        let dir = std::env::temp_dir().join(format!("mcp-hm-cgroup-{}", std::process::id()));
+        let (alerts, recorder) = recording_alerts();
+        
+        // Off unless configured
+        let disabled = CgroupEnforcer::new(&HMConfig { cgroup_parent: dir.clone(), ..Default::default() });
+        assert!(!disabled.ensure_ready(&alerts));
+        assert!(recorder.alerts.lock().unwrap().is_empty());
+        
+        // A plain directory isn't a cgroup hierarchy
+        let config = HMConfig { cgroup_enforcement: true, cgroup_parent: dir.clone(), ..Default::default() };
+        let enforcer = CgroupEnforcer::new(&config);
+        enforcer.add_process("agent/1", std::process::id(), Some(&AgentAllocation::new("agent/1", 5.0, 100, 5)), &alerts);
+        assert!(!enforcer.is_active());
+        assert!(!enforcer.cgroup_path("agent/1").exists());
+        let raised = recorder.alerts.lock().unwrap().drain(..).collect::<Vec<_>>();
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].level, AlertLevel::Warning);
+        assert!(raised[0].message.ends_with("falling back to monitoring only"));
+        
+        // The warning isn't repeated
+        enforcer.update_allocation(&AgentAllocation::new("agent/1", 10.0, 200, 5), &alerts);
+        assert!(!enforcer.ensure_ready(&alerts));
+        assert!(recorder.alerts.lock().unwrap().is_empty());
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    /// Needs write access to a cgroup v2 hierarchy with the cpu and memory controllers:
+    /// set MCP_HM_CGROUP_TESTS=1, and MCP_HM_CGROUP_PARENT to a delegated cgroup if the
+    /// default isn't writable
+    #[test]
+    fn test_cgroup_enforcement() {
+        if std::env::var("MCP_HM_CGROUP_TESTS").is_err() {
+            return;
+        }
+        let parent = std::env::var("MCP_HM_CGROUP_PARENT")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| HMConfig::default().cgroup_parent);
+        let config = HMConfig { cgroup_enforcement: true, cgroup_parent: parent, ..Default::default() };
+        let enforcer = CgroupEnforcer::new(&config);
+        let (alerts, recorder) = recording_alerts();
+        
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        let allocation = AgentAllocation::new("enforced", 5.0, 64, 5);
+        enforcer.add_process("enforced", pid, Some(&allocation), &alerts);
+        assert!(enforcer.is_active(), "{:?}", recorder.alerts.lock().unwrap());
+        let cgroup = enforcer.cgroup_path("enforced");
+        assert_eq!(read(&cgroup, "cpu.max"), "5000 100000");
+        assert_eq!(read(&cgroup, "memory.max"), (64 * 1024 * 1024).to_string());
+        assert!(read(&cgroup, "cgroup.procs").lines().any(|line| line == pid.to_string()));
+        
+        // Allocation changes and degradation steps follow
+        enforcer.update_allocation(&AgentAllocation::new("enforced", 20.0, 128, 5), &alerts);
+        assert_eq!(read(&cgroup, "cpu.max"), "20000 100000");
+        let action = |kind, cpu_percent, memory_mb| DegradationAction {
+            agent_id: "enforced".to_string(),
+            kind,
+            priority: 5,
+            cpu_percent,
+            memory_mb,
+            reason: "test".to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+        enforcer.apply_action(&action(DegradationKind::Throttle, 10.0, 64), &alerts);
+        assert_eq!(read(&cgroup, "cpu.max"), "10000 100000");
+        enforcer.apply_action(&action(DegradationKind::Suspend, 0.0, 0), &alerts);
+        assert_eq!(read(&cgroup, "cgroup.freeze"), "1");
+        enforcer.apply_action(&action(DegradationKind::Restore, 20.0, 128), &alerts);
+        assert_eq!(read(&cgroup, "cgroup.freeze"), "0");
+        assert_eq!(read(&cgroup, "memory.max"), (128 * 1024 * 1024).to_string());
+        assert!(recorder.alerts.lock().unwrap().is_empty());
+        
+        // The cgroup goes once its last process has
+        child.kill().unwrap();
+        child.wait().unwrap();
+        enforcer.remove_process("enforced", pid);
+        assert!(!cgroup.exists());
+    }
+}
@@ -2,10 +2,16 @@
 //!
 //! Defines configuration structures and loading mechanisms for the hardware manager.
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
 use anyhow::{Result, Context};
 
+use crate::alert::{AlertLevel, EscalationRule};
+use crate::alert_log::AlertLogFormat;
+use crate::resource::{AllocationStrategy, ResourceType};
+use crate::webhook::WebhookConfig;
+
 /// Hardware Manager configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HMConfig {
@@ -21,21 +27,169 @@ pub struct HMConfig {
     #[serde(default = "default_refresh_interval")]
     pub refresh_interval_ms: u64,
     
+    /// Whether to sample less often while usage is below half the limits and more often
+    /// as it nears the alert thresholds, between the interval bounds below
+    #[serde(default)]
+    pub adaptive_sampling: bool,
+    
+    /// Shortest refresh interval under adaptive sampling, used near the alert thresholds (ms)
+    #[serde(default = "default_min_refresh_interval")]
+    pub min_refresh_interval_ms: u64,
+    
+    /// Longest refresh interval under adaptive sampling, used while usage is low (ms)
+    #[serde(default = "default_max_refresh_interval")]
+    pub max_refresh_interval_ms: u64,
+    
     /// Alert threshold as percentage of limit (0.0-1.0)
     #[serde(default = "default_alert_threshold")]
     pub alert_threshold: f32,
     
+    /// Thresholds of resources that don't use `alert_threshold`, keyed by CPU, Memory,
+    /// Storage or Network
+    #[serde(default)]
+    pub alert_thresholds: BTreeMap<ResourceType, AlertThreshold>,
+    
+    /// Swap in use on the host (MB) past which to warn (never when unset)
+    #[serde(default)]
+    pub swap_alert_threshold_mb: Option<u32>,
+    
+    /// CPU package temperature (°C) past which to warn (never when unset)
+    #[serde(default)]
+    pub temperature_alert_threshold_celsius: Option<f32>,
+    
+    /// How long a resource must stay below its alert threshold before its alert resolves (ms)
+    #[serde(default = "default_alert_cooldown")]
+    pub alert_cooldown_ms: u64,
+    
+    /// Rules escalating alerts left open at one level
+    #[serde(default = "default_alert_escalations")]
+    pub alert_escalations: Vec<EscalationRule>,
+    
+    /// Handlers each alert level is sent to, by name (console, file, webhook); levels
+    /// left out go to every handler
+    #[serde(default)]
+    pub alert_routes: BTreeMap<AlertLevel, Vec<String>>,
+    
+    /// File alerts are logged to
+    #[serde(default = "default_alert_log_path")]
+    pub alert_log_path: PathBuf,
+    
+    /// Alert log line format
+    #[serde(default)]
+    pub alert_log_format: AlertLogFormat,
+    
+    /// Size the alert log may reach before it's rotated (bytes; 0 never rotates)
+    #[serde(default = "default_alert_log_max_bytes")]
+    pub alert_log_max_bytes: u64,
+    
+    /// Alert log files kept, counting the live one
+    #[serde(default = "default_alert_log_max_files")]
+    pub alert_log_max_files: u32,
+    
     /// Whether to enable graceful degradation
     #[serde(default)]
     pub enable_graceful_degradation: bool,
     
+    /// Usage as a fraction of limit (0.0-1.0) below which degraded agents are restored
+    #[serde(default = "default_recovery_threshold")]
+    pub recovery_threshold: f32,
+    
+    /// How long usage must stay past the alert threshold before degrading (ms)
+    #[serde(default = "default_degradation_sustain")]
+    pub degradation_sustain_ms: u64,
+    
     /// Whether to enable detailed metrics
     #[serde(default)]
     pub enable_detailed_metrics: bool,
     
+    /// Maximum disk throughput in MB/s, read plus written (unlimited when unset)
+    #[serde(default)]
+    pub max_disk_mbps: Option<f32>,
+    
+    /// Maximum network throughput in MB/s, received plus transmitted (unlimited when unset)
+    #[serde(default)]
+    pub max_net_mbps: Option<f32>,
+    
+    /// How agent allocations are sized: even, priority_based or fcfs
+    #[serde(default)]
+    pub allocation_strategy: AllocationStrategy,
+    
+    /// CPU percentage every agent is guaranteed under the priority_based strategy, and
+    /// that preemption leaves it
+    #[serde(default = "default_min_cpu_per_agent")]
+    pub min_cpu_per_agent: f32,
+    
+    /// Memory in MB every agent is guaranteed under the priority_based strategy, and
+    /// that preemption leaves it
+    #[serde(default = "default_min_memory_per_agent")]
+    pub min_memory_per_agent_mb: u32,
+    
+    /// Whether an allocation that doesn't fit may shrink or evict allocations of lower
+    /// priority to make room
+    #[serde(default)]
+    pub allocation_preemption: bool,
+    
+    /// File agent allocations are saved to as they change and restored from on startup,
+    /// as JSON if it ends in .json and YAML otherwise (not saved when unset)
+    #[serde(default)]
+    pub allocation_state_path: Option<PathBuf>,
+    
+    /// How long to wait after an allocation changes for more before saving (ms)
+    #[serde(default = "default_allocation_state_debounce")]
+    pub allocation_state_debounce_ms: u64,
+    
     /// History retention time in minutes
     #[serde(default = "default_history_minutes")]
     pub history_minutes: u32,
+    
+    /// Whether to enforce agent allocations with cgroup v2 (Linux only)
+    #[serde(default)]
+    pub cgroup_enforcement: bool,
+    
+    /// cgroup under which agent cgroups are created
+    #[serde(default = "default_cgroup_parent")]
+    pub cgroup_parent: PathBuf,
+    
+    /// Address to serve Prometheus metrics on, e.g. "127.0.0.1:9184" (disabled when unset)
+    #[serde(default)]
+    pub metrics_listen: Option<String>,
+    
+    /// Address to serve the HTTP control API on, e.g. "127.0.0.1:9185" (disabled when unset)
+    #[serde(default)]
+    pub api_listen: Option<String>,
+    
+    /// Bearer token required by the control API; mandatory unless it listens on loopback
+    #[serde(default)]
+    pub api_token: Option<String>,
+    
+    /// Webhooks alerts are delivered to
+    #[serde(default)]
+    pub alert_webhooks: Vec<WebhookConfig>,
+    
+    /// File the daemon writes its final report to on shutdown (printed when unset)
+    #[serde(default)]
+    pub final_report_path: Option<PathBuf>,
+    
+    /// PID file held by the background daemon
+    #[serde(default = "default_pid_file")]
+    pub pid_file: PathBuf,
+    
+    /// File the background daemon's output is appended to (discarded when unset)
+    #[serde(default)]
+    pub daemon_log_path: Option<PathBuf>,
+}
+
+/// Alert thresholds of one resource, as fractions of its limit (0.0-1.0)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlertThreshold {
+    /// Usage at which a warning opens (`alert_threshold` when unset)
+    #[serde(default)]
+    pub warn: Option<f32>,
+    
+    /// Usage below which an open warning starts to resolve, below `warn` so usage
+    /// hovering around it doesn't flap (`warn` when unset)
+    #[serde(default)]
+    pub clear: Option<f32>,
 }
 
 fn default_max_cpu() -> f32 {
@@ -50,10 +204,70 @@ fn default_refresh_interval() -> u64 {
     1000 // 1 second refresh interval
 }
 
+fn default_min_refresh_interval() -> u64 {
+    250 // 250 milliseconds
+}
+
+fn default_max_refresh_interval() -> u64 {
+    5000 // 5 seconds
+}
+
 fn default_alert_threshold() -> f32 {
     0.8 // 80% threshold
 }
 
+fn default_alert_cooldown() -> u64 {
+    60_000 // 1 minute
+}
+
+fn default_alert_escalations() -> Vec<EscalationRule> {
+    // Warnings open for 5 minutes become critical, and critical alerts fatal 5 minutes later
+    vec![
+        EscalationRule { from: AlertLevel::Warning, to: AlertLevel::Critical, after_ms: 300_000 },
+        EscalationRule { from: AlertLevel::Critical, to: AlertLevel::Fatal, after_ms: 300_000 },
+    ]
+}
+
+fn default_alert_log_path() -> PathBuf {
+    PathBuf::from("mcp-hm.log")
+}
+
+fn default_alert_log_max_bytes() -> u64 {
+    10 * 1024 * 1024 // 10 MB
+}
+
+fn default_alert_log_max_files() -> u32 {
+    5
+}
+
+fn default_pid_file() -> PathBuf {
+    PathBuf::from("mcp-hm.pid")
+}
+
+fn default_recovery_threshold() -> f32 {
+    0.6 // 60% threshold
+}
+
+fn default_degradation_sustain() -> u64 {
+    10_000 // 10 seconds
+}
+
+fn default_cgroup_parent() -> PathBuf {
+    PathBuf::from("/sys/fs/cgroup/mcp-zero")
+}
+
+fn default_min_cpu_per_agent() -> f32 {
+    1.0 // 1% CPU
+}
+
+fn default_min_memory_per_agent() -> u32 {
+    10 // 10 MB
+}
+
+fn default_allocation_state_debounce() -> u64 {
+    1000 // 1 second
+}
+
 fn default_history_minutes() -> u32 {
     60 // 1 hour
 }
@@ -64,10 +278,42 @@ impl Default for HMConfig {
             max_cpu_percent: default_max_cpu(),
             max_memory_mb: default_max_memory(),
             refresh_interval_ms: default_refresh_interval(),
+            adaptive_sampling: false,
+            min_refresh_interval_ms: default_min_refresh_interval(),
+            max_refresh_interval_ms: default_max_refresh_interval(),
             alert_threshold: default_alert_threshold(),
+            alert_thresholds: BTreeMap::new(),
+            swap_alert_threshold_mb: None,
+            temperature_alert_threshold_celsius: None,
+            alert_cooldown_ms: default_alert_cooldown(),
+            alert_escalations: default_alert_escalations(),
+            alert_routes: BTreeMap::new(),
+            alert_log_path: default_alert_log_path(),
+            alert_log_format: AlertLogFormat::default(),
+            alert_log_max_bytes: default_alert_log_max_bytes(),
+            alert_log_max_files: default_alert_log_max_files(),
             enable_graceful_degradation: true,
+            recovery_threshold: default_recovery_threshold(),
+            degradation_sustain_ms: default_degradation_sustain(),
             enable_detailed_metrics: true,
+            max_disk_mbps: None,
+            max_net_mbps: None,
+            allocation_strategy: AllocationStrategy::default(),
+            min_cpu_per_agent: default_min_cpu_per_agent(),
+            min_memory_per_agent_mb: default_min_memory_per_agent(),
+            allocation_preemption: false,
+            allocation_state_path: None,
+            allocation_state_debounce_ms: default_allocation_state_debounce(),
             history_minutes: default_history_minutes(),
+            cgroup_enforcement: false,
+            cgroup_parent: default_cgroup_parent(),
+            metrics_listen: None,
+            api_listen: None,
+            api_token: None,
+            alert_webhooks: Vec::new(),
+            final_report_path: None,
+            pid_file: default_pid_file(),
+            daemon_log_path: None,
         }
     }
 }
@@ -115,24 +361,180 @@ impl HMConfig {
             config.refresh_interval_ms = refresh;
         }
         
+        if let Ok(value) = std::env::var("MCP_HM_ADAPTIVE_SAMPLING") {
+            config.adaptive_sampling = value.to_lowercase() == "true";
+        }
+        
+        if let Ok(min_refresh) = std::env::var("MCP_HM_MIN_REFRESH_INTERVAL")
+            .and_then(|v| v.parse::<u64>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.min_refresh_interval_ms = min_refresh;
+        }
+        
+        if let Ok(max_refresh) = std::env::var("MCP_HM_MAX_REFRESH_INTERVAL")
+            .and_then(|v| v.parse::<u64>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.max_refresh_interval_ms = max_refresh;
+        }
+        
         if let Ok(threshold) = std::env::var("MCP_HM_ALERT_THRESHOLD")
             .and_then(|v| v.parse::<f32>().map_err(|_| std::env::VarError::NotPresent)) {
             config.alert_threshold = threshold;
         }
         
+        // Per-resource thresholds, e.g. MCP_HM_CPU_ALERT_THRESHOLD and MCP_HM_CPU_CLEAR_THRESHOLD
+        for (resource_type, name) in [
+            (ResourceType::CPU, "CPU"),
+            (ResourceType::Memory, "MEMORY"),
+            (ResourceType::Storage, "DISK"),
+            (ResourceType::Network, "NET"),
+        ] {
+            let threshold = |kind: &str| {
+                std::env::var(format!("MCP_HM_{}_{}_THRESHOLD", name, kind)).ok().and_then(|v| v.parse::<f32>().ok())
+            };
+            let (warn, clear) = (threshold("ALERT"), threshold("CLEAR"));
+            if warn.is_some() || clear.is_some() {
+                let entry = config.alert_thresholds.entry(resource_type).or_default();
+                entry.warn = warn.or(entry.warn);
+                entry.clear = clear.or(entry.clear);
+            }
+        }
+        
+        if let Ok(threshold) = std::env::var("MCP_HM_SWAP_ALERT_THRESHOLD")
+            .and_then(|v| v.parse::<u32>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.swap_alert_threshold_mb = Some(threshold);
+        }
+        
+        if let Ok(threshold) = std::env::var("MCP_HM_TEMPERATURE_ALERT_THRESHOLD")
+            .and_then(|v| v.parse::<f32>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.temperature_alert_threshold_celsius = Some(threshold);
+        }
+        
+        if let Ok(cooldown) = std::env::var("MCP_HM_ALERT_COOLDOWN")
+            .and_then(|v| v.parse::<u64>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.alert_cooldown_ms = cooldown;
+        }
+        
+        if let Ok(path) = std::env::var("MCP_HM_ALERT_LOG") {
+            config.alert_log_path = PathBuf::from(path);
+        }
+        
+        match std::env::var("MCP_HM_ALERT_LOG_FORMAT").map(|v| v.to_lowercase()).as_deref() {
+            Ok("text") => config.alert_log_format = AlertLogFormat::Text,
+            Ok("json") => config.alert_log_format = AlertLogFormat::Json,
+            _ => {},
+        }
+        
+        if let Ok(max_bytes) = std::env::var("MCP_HM_ALERT_LOG_MAX_BYTES")
+            .and_then(|v| v.parse::<u64>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.alert_log_max_bytes = max_bytes;
+        }
+        
+        if let Ok(max_files) = std::env::var("MCP_HM_ALERT_LOG_MAX_FILES")
+            .and_then(|v| v.parse::<u32>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.alert_log_max_files = max_files;
+        }
+        
         if let Ok(value) = std::env::var("MCP_HM_ENABLE_GRACEFUL_DEGRADATION") {
             config.enable_graceful_degradation = value.to_lowercase() == "true";
         }
         
+        if let Ok(threshold) = std::env::var("MCP_HM_RECOVERY_THRESHOLD")
+            .and_then(|v| v.parse::<f32>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.recovery_threshold = threshold;
+        }
+        
+        if let Ok(sustain) = std::env::var("MCP_HM_DEGRADATION_SUSTAIN")
+            .and_then(|v| v.parse::<u64>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.degradation_sustain_ms = sustain;
+        }
+        
         if let Ok(value) = std::env::var("MCP_HM_ENABLE_DETAILED_METRICS") {
             config.enable_detailed_metrics = value.to_lowercase() == "true";
         }
         
+        if let Ok(limit) = std::env::var("MCP_HM_MAX_DISK_MBPS")
+            .and_then(|v| v.parse::<f32>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.max_disk_mbps = Some(limit);
+        }
+        
+        if let Ok(limit) = std::env::var("MCP_HM_MAX_NET_MBPS")
+            .and_then(|v| v.parse::<f32>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.max_net_mbps = Some(limit);
+        }
+        
+        match std::env::var("MCP_HM_ALLOCATION_STRATEGY").map(|v| v.to_lowercase()).as_deref() {
+            Ok("even") => config.allocation_strategy = AllocationStrategy::Even,
+            Ok("priority_based") => config.allocation_strategy = AllocationStrategy::PriorityBased,
+            Ok("fcfs") => config.allocation_strategy = AllocationStrategy::FCFS,
+            _ => {},
+        }
+        
+        if let Ok(min_cpu) = std::env::var("MCP_HM_MIN_CPU_PER_AGENT")
+            .and_then(|v| v.parse::<f32>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.min_cpu_per_agent = min_cpu;
+        }
+        
+        if let Ok(min_memory) = std::env::var("MCP_HM_MIN_MEMORY_PER_AGENT")
+            .and_then(|v| v.parse::<u32>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.min_memory_per_agent_mb = min_memory;
+        }
+        
+        if let Ok(value) = std::env::var("MCP_HM_ALLOCATION_PREEMPTION") {
+            config.allocation_preemption = value.to_lowercase() == "true";
+        }
+        
+        if let Ok(path) = std::env::var("MCP_HM_ALLOCATION_STATE") {
+            config.allocation_state_path = Some(PathBuf::from(path)).filter(|path| !path.as_os_str().is_empty());
+        }
+        
+        if let Ok(debounce) = std::env::var("MCP_HM_ALLOCATION_STATE_DEBOUNCE")
+            .and_then(|v| v.parse::<u64>().map_err(|_| std::env::VarError::NotPresent)) {
+            config.allocation_state_debounce_ms = debounce;
+        }
+        
         if let Ok(minutes) = std::env::var("MCP_HM_HISTORY_MINUTES")
             .and_then(|v| v.parse::<u32>().map_err(|_| std::env::VarError::NotPresent)) {
             config.history_minutes = minutes;
         }
         
+        if let Ok(value) = std::env::var("MCP_HM_CGROUP_ENFORCEMENT") {
+            config.cgroup_enforcement = value.to_lowercase() == "true";
+        }
+        
+        if let Ok(parent) = std::env::var("MCP_HM_CGROUP_PARENT") {
+            config.cgroup_parent = PathBuf::from(parent);
+        }
+        
+        if let Ok(listen) = std::env::var("MCP_HM_METRICS_LISTEN") {
+            config.metrics_listen = Some(listen).filter(|listen| !listen.is_empty());
+        }
+        
+        if let Ok(listen) = std::env::var("MCP_HM_API_LISTEN") {
+            config.api_listen = Some(listen).filter(|listen| !listen.is_empty());
+        }
+        
+        if let Ok(token) = std::env::var("MCP_HM_API_TOKEN") {
+            config.api_token = Some(token).filter(|token| !token.is_empty());
+        }
+        
+        // A single webhook for warnings and up; configure more in the config file
+        if let Ok(url) = std::env::var("MCP_HM_ALERT_WEBHOOK") {
+            if !url.is_empty() {
+                config.alert_webhooks.push(WebhookConfig { url, headers: Default::default(), min_level: AlertLevel::Warning });
+            }
+        }
+        
+        if let Ok(path) = std::env::var("MCP_HM_FINAL_REPORT") {
+            config.final_report_path = Some(PathBuf::from(path)).filter(|path| !path.as_os_str().is_empty());
+        }
+        
+        if let Ok(path) = std::env::var("MCP_HM_PID_FILE") {
+            config.pid_file = PathBuf::from(path);
+        }
+        
+        if let Ok(path) = std::env::var("MCP_HM_DAEMON_LOG") {
+            config.daemon_log_path = Some(PathBuf::from(path)).filter(|path| !path.as_os_str().is_empty());
+        }
+        
         config
     }
     
@@ -148,16 +550,97 @@ impl HMConfig {
             return Err(anyhow::anyhow!("Invalid memory limit: must be greater than 0"));
         }
         
+        // Check IO limits
+        if self.max_disk_mbps.is_some_and(|limit| limit <= 0.0) {
+            return Err(anyhow::anyhow!("Invalid disk limit: must be greater than 0"));
+        }
+        if self.max_net_mbps.is_some_and(|limit| limit <= 0.0) {
+            return Err(anyhow::anyhow!("Invalid network limit: must be greater than 0"));
+        }
+        
+        // Check per-agent minimums fit within the limits
+        if !(0.0..=self.max_cpu_percent).contains(&self.min_cpu_per_agent) {
+            return Err(anyhow::anyhow!("Invalid minimum CPU per agent: must be between 0 and the CPU limit"));
+        }
+        if self.min_memory_per_agent_mb > self.max_memory_mb {
+            return Err(anyhow::anyhow!("Invalid minimum memory per agent: must not exceed the memory limit"));
+        }
+        
         // Check refresh interval
         if self.refresh_interval_ms == 0 {
             return Err(anyhow::anyhow!("Invalid refresh interval: must be greater than 0"));
         }
         
+        // Check adaptive sampling bounds
+        if self.adaptive_sampling && (self.min_refresh_interval_ms == 0 || self.min_refresh_interval_ms > self.max_refresh_interval_ms) {
+            return Err(anyhow::anyhow!("Invalid adaptive refresh intervals: the minimum must be greater than 0 and at most the maximum"));
+        }
+        
         // Check alert threshold
         if self.alert_threshold <= 0.0 || self.alert_threshold > 1.0 {
             return Err(anyhow::anyhow!("Invalid alert threshold: must be between 0 and 1"));
         }
         
+        // Check per-resource thresholds, which must clear below where they warn
+        for (resource_type, threshold) in &self.alert_thresholds {
+            if matches!(resource_type, ResourceType::Process | ResourceType::Swap | ResourceType::Temperature) {
+                return Err(anyhow::anyhow!("Invalid alert threshold for {:?}: only CPU, Memory, Storage and Network have limits", resource_type));
+            }
+            let warn = threshold.warn.unwrap_or(self.alert_threshold);
+            if warn <= 0.0 || warn > 1.0 {
+                return Err(anyhow::anyhow!("Invalid {:?} alert threshold: must be between 0 and 1", resource_type));
+            }
+            if threshold.clear.is_some_and(|clear| !(0.0..warn).contains(&clear)) {
+                return Err(anyhow::anyhow!("Invalid {:?} clear threshold: must be between 0 and its alert threshold", resource_type));
+            }
+        }
+        
+        // Check the temperature threshold
+        if self.temperature_alert_threshold_celsius.is_some_and(|threshold| !threshold.is_finite() || threshold <= 0.0) {
+            return Err(anyhow::anyhow!("Invalid temperature alert threshold: must be greater than 0"));
+        }
+        
+        // Check escalations only ever raise the level
+        for rule in &self.alert_escalations {
+            if rule.to <= rule.from || rule.after_ms == 0 {
+                return Err(anyhow::anyhow!("Invalid alert escalation from {} to {}: must raise the level after more than 0 ms", rule.from, rule.to));
+            }
+        }
+        
+        // Check alert log rotation
+        if self.alert_log_max_files == 0 {
+            return Err(anyhow::anyhow!("Invalid alert log file count: must be at least 1"));
+        }
+        
+        // Check recovery threshold, which must sit below the alert threshold
+        if self.recovery_threshold < 0.0 || self.recovery_threshold >= self.alert_threshold {
+            return Err(anyhow::anyhow!("Invalid recovery threshold: must be between 0 and the alert threshold"));
+        }
+        
+        // Check metrics listen address
+        if let Some(listen) = &self.metrics_listen {
+            if listen.parse::<std::net::SocketAddr>().is_err() {
+                return Err(anyhow::anyhow!("Invalid metrics listen address: {}", listen));
+            }
+        }
+        
+        // Check webhook URLs
+        for webhook in &self.alert_webhooks {
+            if !webhook.url.starts_with("http://") && !webhook.url.starts_with("https://") {
+                return Err(anyhow::anyhow!("Invalid alert webhook URL: must be http:// or https://"));
+            }
+        }
+        
+        // Check the control API address, which needs a token unless only local
+        if let Some(listen) = &self.api_listen {
+            let addr = listen.parse::<std::net::SocketAddr>()
+                .map_err(|_| anyhow::anyhow!("Invalid API listen address: {}", listen))?;
+            let has_token = self.api_token.as_ref().is_some_and(|token| !token.is_empty());
+            if !addr.ip().is_loopback() && !has_token {
+                return Err(anyhow::anyhow!("An API token is required to serve the control API on non-loopback address {}", addr));
+            }
+        }
+        
         Ok(())
     }
 }
@@ -0,0 +1,239 @@
+//! Daemonization for MCP-ZERO Hardware Manager
+//!
+//! `daemonize` detaches the process from its terminal with the usual double fork and
+//! records the daemon's PID in a PID file. The process that started it only exits
+//! once the daemon holds the PID file, succeeding if it does. A PID file naming a
+//! process that no longer runs is left over from a crash and is removed.
+//!
+//! The daemon keeps the working directory it was started in, so relative paths in
+//! the configuration still resolve.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context, anyhow};
+
+/// PID file held by the running daemon, removed when dropped
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl PidFile {
+    /// Record this process in a PID file at `path`, failing if a live process already
+    /// holds it
+    pub fn acquire<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        check_pid_file(path)?;
+        
+        // Created exclusively, so of two daemons starting at once only one wins
+        let pid = std::process::id();
+        let mut file = OpenOptions::new().write(true).create_new(true).open(path)
+            .with_context(|| format!("Failed to create PID file {}", path.display()))?;
+        writeln!(file, "{}", pid).with_context(|| format!("Failed to write PID file {}", path.display()))?;
+        Ok(Self { path: path.to_path_buf(), pid })
+    }
+    
+    /// Path of the PID file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // Leave the file alone if another daemon has since taken it over
+        if read_pid_file(&self.path) == Some(self.pid) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// PID recorded in the PID file at `path`, if it exists and holds one
+pub fn read_pid_file(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether a process with `pid` is running
+#[cfg(unix)]
+pub fn process_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if pid <= 0 {
+        return false;
+    }
+    
+    // A zombie has exited and is only waiting to be reaped
+    #[cfg(target_os = "linux")]
+    if let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        if stat.rsplit_once(") ").is_some_and(|(_, state)| state.starts_with('Z')) {
+            return false;
+        }
+    }
+    
+    // Signal 0 only checks that the process exists; EPERM means it does but isn't ours
+    let signalled = unsafe { libc::kill(pid, 0) } == 0;
+    signalled || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether a process with `pid` is running
+#[cfg(not(unix))]
+pub fn process_running(_pid: u32) -> bool {
+    false
+}
+
+/// PID of the daemon holding the PID file at `path`, removing the file if the process
+/// it names has exited
+pub fn running_daemon(path: &Path) -> Option<u32> {
+    if !path.exists() {
+        return None;
+    }
+    match read_pid_file(path) {
+        Some(pid) if process_running(pid) => Some(pid),
+        pid => {
+            tracing::warn!("Removing stale PID file {} (pid {:?} is not running)", path.display(), pid);
+            let _ = std::fs::remove_file(path);
+            None
+        },
+    }
+}
+
+/// Fail if a live daemon holds the PID file at `path`; a stale one is removed
+fn check_pid_file(path: &Path) -> Result<()> {
+    match running_daemon(path) {
+        Some(pid) => Err(anyhow!("Hardware manager is already running (pid {}, PID file {})", pid, path.display())),
+        None => Ok(()),
+    }
+}
+
+/// Detach from the terminal and run in the background, holding the PID file at
+/// `pid_path`
+///
+/// Only the daemon returns. Its standard output and error go to `log_path`, or are
+/// discarded when unset. The starting process exits once the daemon holds the PID
+/// file, printing its PID, or fails if it didn't get that far. Must be called before
+/// any other thread is started.
+#[cfg(unix)]
+pub fn daemonize(pid_path: &Path, log_path: Option<&Path>) -> Result<PidFile> {
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::fd::{AsRawFd, FromRawFd};
+    
+    // Fail early, while errors still reach the terminal
+    check_pid_file(pid_path)?;
+    let log = match log_path {
+        Some(path) => OpenOptions::new().create(true).append(true).open(path)
+            .with_context(|| format!("Failed to open daemon log {}", path.display()))?,
+        None => OpenOptions::new().write(true).open("/dev/null")?,
+    };
+    let null = File::open("/dev/null")?;
+    
+    // The daemon reports its PID back through a pipe once it holds the PID file
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to create daemon pipe");
+    }
+    let (mut ready_reader, mut ready_writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+    
+    match unsafe { libc::fork() } {
+        -1 => return Err(std::io::Error::last_os_error()).context("Failed to fork daemon"),
+        0 => {},
+        child => {
+            // Wait for the daemon to report in, then leave it running
+            drop(ready_writer);
+            unsafe { libc::waitpid(child, std::ptr::null_mut(), 0) };
+            let mut pid = String::new();
+            let _ = ready_reader.read_to_string(&mut pid);
+            if pid.is_empty() {
+                eprintln!("Hardware manager daemon failed to start; see {}", log_path.map_or("its log".to_string(), |path| path.display().to_string()));
+                std::process::exit(1);
+            }
+            println!("Hardware manager daemon started (pid {})", pid.trim());
+            std::process::exit(0);
+        },
+    }
+    drop(ready_reader);
+    
+    // Lead a new session without a controlling terminal, then fork again so the
+    // daemon isn't a session leader and can never acquire one
+    if unsafe { libc::setsid() } == -1 {
+        return Err(std::io::Error::last_os_error()).context("Failed to start daemon session");
+    }
+    match unsafe { libc::fork() } {
+        -1 => return Err(std::io::Error::last_os_error()).context("Failed to fork daemon"),
+        0 => {},
+        _ => unsafe { libc::_exit(0) },
+    }
+    
+    unsafe {
+        libc::umask(0o022);
+        libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO);
+        libc::dup2(log.as_raw_fd(), libc::STDOUT_FILENO);
+        libc::dup2(log.as_raw_fd(), libc::STDERR_FILENO);
+    }
+    
+    let pid_file = PidFile::acquire(pid_path)?;
+    writeln!(ready_writer, "{}", pid_file.pid)?;
+    Ok(pid_file)
+}
+
+/// Detach from the terminal and run in the background; only supported on unix
+#[cfg(not(unix))]
+pub fn daemonize(_pid_path: &Path, _log_path: Option<&Path>) -> Result<PidFile> {
+    Err(anyhow!("Daemon mode is only supported on unix; run with --foreground under a service manager instead"))
+}
+
+/// Send SIGTERM to the daemon with `pid`
+#[cfg(unix)]
+pub fn terminate(pid: u32) -> Result<()> {
+    let pid = libc::pid_t::try_from(pid).map_err(|_| anyhow!("Invalid pid {}", pid))?;
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        return Err(std::io::Error::last_os_error()).with_context(|| format!("Failed to signal pid {}", pid));
+    }
+    Ok(())
+}
+
+/// Send SIGTERM to the daemon with `pid`; only supported on unix
+#[cfg(not(unix))]
+pub fn terminate(pid: u32) -> Result<()> {
+    Err(anyhow!("Stopping pid {} is only supported on unix", pid))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_pid_file() {
+        let dir = std::env::temp_dir().join(format!("mcp-hm-pid-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("mcp-hm.pid");
+        
+        // A live holder keeps others out until it's dropped
+        let pid_file = PidFile::acquire(&path).unwrap();
+        assert_eq!(read_pid_file(&path), Some(std::process::id()));
+        assert_eq!(running_daemon(&path), Some(std::process::id()));
+        assert!(PidFile::acquire(&path).is_err());
+        drop(pid_file);
+        assert!(!path.exists());
+        
+        // A file left by a process that has exited is stale and replaced
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let exited = child.id();
+        child.wait().unwrap();
+        std::fs::write(&path, format!("{}\n", exited)).unwrap();
+        assert!(!process_running(exited));
+        let pid_file = PidFile::acquire(&path).unwrap();
+        assert_eq!(read_pid_file(pid_file.path()), Some(std::process::id()));
+        drop(pid_file);
+        
+        // Garbage is stale too
+        std::fs::write(&path, "not a pid").unwrap();
+        assert_eq!(running_daemon(&path), None);
+        assert!(!path.exists());
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
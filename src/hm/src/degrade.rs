@@ -0,0 +1,332 @@
+//! Graceful degradation for MCP-ZERO Hardware Manager
+//!
+//! When usage reaches the limit, or stays past the alert threshold for a sustained
+//! period, agents are throttled and then suspended one at a time, lowest priority
+//! first. Registered degradation handlers carry out each step, e.g. by pausing
+//! agents or cutting plugin fuel. Once usage falls below the recovery threshold the
+//! agents are restored one at a time, most recently degraded first.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+
+use crate::alert::{AlertLevel, AlertManager};
+use crate::config::HMConfig;
+use crate::resource::{ResourceLimit, ResourceStats, ResourceType};
+use crate::AgentAllocation;
+
+/// Degradation step applied to an agent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DegradationKind {
+    /// Run the agent on half its allocation
+    Throttle,
+    /// Stop running the agent
+    Suspend,
+    /// Return the agent to its full allocation
+    Restore,
+}
+
+/// Degradation step for a handler to carry out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegradationAction {
+    /// Agent ID
+    pub agent_id: String,
+    
+    /// Step to apply
+    pub kind: DegradationKind,
+    
+    /// Priority of the agent
+    pub priority: u8,
+    
+    /// CPU the agent should run with (percentage)
+    pub cpu_percent: f32,
+    
+    /// Memory the agent should run with (MB)
+    pub memory_mb: u32,
+    
+    /// Why the step was taken
+    pub reason: String,
+    
+    /// Timestamp
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Carries out degradation steps, e.g. the kernel pausing agents
+pub trait DegradationHandler: Send + Sync {
+    /// Apply a degradation step
+    fn handle(&self, action: &DegradationAction);
+}
+
+/// Degradation state of one agent
+#[derive(Debug, Clone, Serialize)]
+struct Degraded {
+    agent_id: String,
+    kind: DegradationKind,
+    priority: u8,
+    since: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Default)]
+struct DegraderState {
+    /// Degraded agents, in the order they were first degraded
+    degraded: Vec<Degraded>,
+    
+    /// When usage went past the alert threshold, if it still is
+    pressure_since: Option<Instant>,
+}
+
+/// Decides and applies degradation steps from sampled usage
+pub(crate) struct Degrader {
+    enabled: bool,
+    alert_threshold: f32,
+    recovery_threshold: f32,
+    sustain: Duration,
+    state: Mutex<DegraderState>,
+    handlers: RwLock<Vec<Box<dyn DegradationHandler>>>,
+}
+
+impl Degrader {
+    pub(crate) fn new(config: &HMConfig) -> Self {
+        Self {
+            enabled: config.enable_graceful_degradation,
+            alert_threshold: config.alert_threshold,
+            recovery_threshold: config.recovery_threshold,
+            sustain: Duration::from_millis(config.degradation_sustain_ms),
+            state: Mutex::new(DegraderState::default()),
+            handlers: RwLock::new(Vec::new()),
+        }
+    }
+    
+    pub(crate) fn add_handler(&self, handler: Box<dyn DegradationHandler>) {
+        self.handlers.write().unwrap_or_else(|e| e.into_inner()).push(handler);
+    }
+    
+    /// Take at most one degradation or recovery step for a usage sample
+    pub(crate) fn observe(
+        &self,
+        stats: &ResourceStats,
+        limits: &ResourceLimit,
+        allocations: &RwLock<HashMap<String, AgentAllocation>>,
+        alerts: &AlertManager,
+    ) -> Option<DegradationAction> {
+        if !self.enabled {
+            return None;
+        }
+        
+        // The resource closest to its limit drives degradation
+        let cpu = stats.cpu_percent / limits.cpu_percent;
+        let memory = stats.memory_mb as f32 / limits.memory_mb as f32;
+        let (resource_type, percent_of_limit, current, limit) = if cpu >= memory {
+            (ResourceType::CPU, cpu, stats.cpu_percent as f64, limits.cpu_percent as f64)
+        } else {
+            (ResourceType::Memory, memory, stats.memory_mb as f64, limits.memory_mb as f64)
+        };
+        
+        let action = {
+            let allocations = allocations.read().unwrap_or_else(|e| e.into_inner());
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            
+            // Forget agents whose resources were released
+            state.degraded.retain(|degraded| allocations.contains_key(&degraded.agent_id));
+            
+            let now = Instant::now();
+            if percent_of_limit < self.alert_threshold {
+                state.pressure_since = None;
+            } else if state.pressure_since.is_none() {
+                state.pressure_since = Some(now);
+            }
+            let sustained = state.pressure_since.is_some_and(|since| now.duration_since(since) >= self.sustain);
+            
+            let reason = format!("{:?} usage at {:.0}% of limit", resource_type, percent_of_limit * 100.0);
+            if percent_of_limit >= 1.0 || sustained {
+                // Wait out another sustained period before the next step
+                state.pressure_since = Some(now);
+                Self::degrade_next(&mut state, &allocations, reason)
+            } else if percent_of_limit < self.recovery_threshold {
+                Self::restore_next(&mut state, &allocations, reason)
+            } else {
+                None
+            }
+        }?;
+        
+        let verb = match action.kind {
+            DegradationKind::Throttle => "Throttled",
+            DegradationKind::Suspend => "Suspended",
+            DegradationKind::Restore => "Restored",
+        };
+        let message = format!(
+            "{} agent {} (priority {}) to {:.2}% CPU and {} MB: {}",
+            verb, action.agent_id, action.priority, action.cpu_percent, action.memory_mb, action.reason
+        );
+        alerts.send(AlertLevel::Critical, resource_type, &message, current, limit);
+        
+        for handler in self.handlers.read().unwrap_or_else(|e| e.into_inner()).iter() {
+            handler.handle(&action);
+        }
+        Some(action)
+    }
+    
+    /// Throttle the lowest-priority agent running normally, or once every agent is
+    /// throttled, suspend the lowest-priority one
+    fn degrade_next(
+        state: &mut DegraderState,
+        allocations: &HashMap<String, AgentAllocation>,
+        reason: String,
+    ) -> Option<DegradationAction> {
+        let lowest = |kind: Option<DegradationKind>| {
+            allocations.values()
+                .filter(|allocation| {
+                    let current = state.degraded.iter().find(|degraded| degraded.agent_id == allocation.agent_id);
+                    current.map(|degraded| degraded.kind) == kind
+                })
+                .min_by(|a, b| a.priority.cmp(&b.priority).then_with(|| a.agent_id.cmp(&b.agent_id)))
+                .cloned()
+        };
+        
+        let (allocation, kind) = match lowest(None) {
+            Some(allocation) => (allocation, DegradationKind::Throttle),
+            None => (lowest(Some(DegradationKind::Throttle))?, DegradationKind::Suspend),
+        };
+        
+        let timestamp = chrono::Utc::now();
+        match state.degraded.iter_mut().find(|degraded| degraded.agent_id == allocation.agent_id) {
+            Some(degraded) => degraded.kind = kind,
+            None => state.degraded.push(Degraded {
+                agent_id: allocation.agent_id.clone(),
+                kind,
+                priority: allocation.priority,
+                since: timestamp,
+            }),
+        }
+        
+        let (cpu_percent, memory_mb) = match kind {
+            DegradationKind::Throttle => (allocation.cpu_percent / 2.0, allocation.memory_mb / 2),
+            _ => (0.0, 0),
+        };
+        Some(DegradationAction {
+            agent_id: allocation.agent_id,
+            kind,
+            priority: allocation.priority,
+            cpu_percent,
+            memory_mb,
+            reason,
+            timestamp,
+        })
+    }
+    
+    /// Restore the most recently degraded agent to its full allocation
+    fn restore_next(
+        state: &mut DegraderState,
+        allocations: &HashMap<String, AgentAllocation>,
+        reason: String,
+    ) -> Option<DegradationAction> {
+        let degraded = state.degraded.pop()?;
+        let allocation = allocations.get(&degraded.agent_id)?;
+        Some(DegradationAction {
+            agent_id: degraded.agent_id,
+            kind: DegradationKind::Restore,
+            priority: allocation.priority,
+            cpu_percent: allocation.cpu_percent,
+            memory_mb: allocation.memory_mb,
+            reason,
+            timestamp: chrono::Utc::now(),
+        })
+    }
+    
+    /// Degradation state for the resource report
+    pub(crate) fn report(&self) -> serde_json::Value {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        serde_json::json!({
+            "enabled": self.enabled,
+            "recovery_threshold": self.recovery_threshold,
+            "degraded": state.degraded,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    
+    #[derive(Clone, Default)]
+    struct RecordingHandler {
+        actions: Arc<Mutex<Vec<DegradationAction>>>,
+    }
+    
+    impl DegradationHandler for RecordingHandler {
+        fn handle(&self, action: &DegradationAction) {
+            self.actions.lock().unwrap().push(action.clone());
+        }
+    }
+    
+    #[test]
+    fn test_degradation_order() {
+        let config = HMConfig { degradation_sustain_ms: 3_600_000, ..Default::default() };
+        let degrader = Degrader::new(&config);
+        let recorder = RecordingHandler::default();
+        degrader.add_handler(Box::new(recorder.clone()));
+        let limits = ResourceLimit { cpu_percent: 30.0, memory_mb: 800, ..Default::default() };
+        let allocations = RwLock::new(HashMap::from([
+            ("low".to_string(), AgentAllocation::new("low", 4.0, 100, 1)),
+            ("high".to_string(), AgentAllocation::new("high", 8.0, 200, 9)),
+        ]));
+        let alerts = AlertManager::new(AlertLevel::Info);
+        let step = |cpu_percent: f32| {
+            let stats = ResourceStats { cpu_percent, memory_mb: 100, timestamp: chrono::Utc::now(), ..Default::default() };
+            degrader.observe(&stats, &limits, &allocations, &alerts).map(|action| (action.agent_id, action.kind))
+        };
+        
+        // Under the limit nothing happens until usage has been high for the sustained period
+        assert_eq!(step(20.0), None);
+        assert_eq!(step(27.0), None);
+        
+        // At the limit agents are throttled, then suspended, lowest priority first
+        let degrade = |agent_id: &str, kind| Some((agent_id.to_string(), kind));
+        assert_eq!(step(31.0), degrade("low", DegradationKind::Throttle));
+        assert_eq!(step(31.0), degrade("high", DegradationKind::Throttle));
+        assert_eq!(step(31.0), degrade("low", DegradationKind::Suspend));
+        assert_eq!(step(31.0), degrade("high", DegradationKind::Suspend));
+        assert_eq!(step(31.0), None);
+        let report = degrader.report();
+        assert_eq!(report["degraded"][0]["agent_id"], "low");
+        assert_eq!(report["degraded"][0]["kind"], "suspend");
+        
+        // Recovery waits for usage to fall below the recovery threshold
+        assert_eq!(step(20.0), None);
+        assert_eq!(step(10.0), degrade("high", DegradationKind::Restore));
+        assert_eq!(step(10.0), degrade("low", DegradationKind::Restore));
+        assert_eq!(step(10.0), None);
+        
+        let actions = recorder.actions.lock().unwrap();
+        assert_eq!(actions.len(), 6);
+        assert_eq!((actions[0].cpu_percent, actions[0].memory_mb), (2.0, 50));
+        assert_eq!((actions[5].cpu_percent, actions[5].memory_mb), (4.0, 100));
+    }
+    
+    #[test]
+    fn test_sustained_pressure() {
+        let config = HMConfig { degradation_sustain_ms: 0, ..Default::default() };
+        let degrader = Degrader::new(&config);
+        let limits = ResourceLimit { cpu_percent: 30.0, memory_mb: 800, ..Default::default() };
+        let allocations = RwLock::new(HashMap::from([
+            ("agent".to_string(), AgentAllocation::new("agent", 4.0, 100, 5)),
+        ]));
+        let alerts = AlertManager::new(AlertLevel::Info);
+        let stats = ResourceStats { cpu_percent: 1.0, memory_mb: 700, timestamp: chrono::Utc::now(), ..Default::default() };
+        
+        let action = degrader.observe(&stats, &limits, &allocations, &alerts).unwrap();
+        assert_eq!(action.kind, DegradationKind::Throttle);
+        assert!(action.reason.starts_with("Memory usage at 88%"));
+        
+        // Released agents are forgotten
+        allocations.write().unwrap().clear();
+        assert!(degrader.observe(&stats, &limits, &allocations, &alerts).is_none());
+        assert_eq!(degrader.report()["degraded"], serde_json::json!([]));
+        
+        let disabled = Degrader::new(&HMConfig { enable_graceful_degradation: false, ..config });
+        assert!(disabled.observe(&stats, &limits, &allocations, &alerts).is_none());
+    }
+}
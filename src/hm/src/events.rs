@@ -0,0 +1,171 @@
+//! Allocation change notifications for MCP-ZERO Hardware Manager
+//!
+//! Every change to the allocations map is published as an `AllocationEvent` to each
+//! subscriber's channel, so the kernel and external schedulers can apply new limits as
+//! soon as the HM rebalances, preempts or restores them. Events are published while
+//! the allocations are still locked, so they arrive in the order the changes were
+//! made. Publishing never blocks: a subscriber whose channel is full misses the event,
+//! which is counted. The most recent events are kept for the resource report.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Mutex;
+use serde::{Serialize, Deserialize};
+
+use crate::AgentAllocation;
+
+/// Events buffered per subscriber before new ones are dropped
+const SUBSCRIBER_CAPACITY: usize = 256;
+
+/// Events kept for the resource report
+const RECENT_EVENTS: usize = 32;
+
+/// A change to an agent's allocation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AllocationEvent {
+    /// An agent was allocated resources, or its allocation was replaced
+    Allocated {
+        timestamp: chrono::DateTime<chrono::Utc>,
+        allocation: AgentAllocation,
+    },
+    
+    /// An agent's resources were released
+    Released {
+        timestamp: chrono::DateTime<chrono::Utc>,
+        allocation: AgentAllocation,
+    },
+    
+    /// An allocation was resized to its share under the allocation strategy
+    Rebalanced {
+        timestamp: chrono::DateTime<chrono::Utc>,
+        agent_id: String,
+        old: AgentAllocation,
+        new: AgentAllocation,
+    },
+    
+    /// An allocation was shrunk, or evicted if `new` is none, to make room for
+    /// `preempted_by`
+    Preempted {
+        timestamp: chrono::DateTime<chrono::Utc>,
+        agent_id: String,
+        old: AgentAllocation,
+        new: Option<AgentAllocation>,
+        preempted_by: String,
+    },
+    
+    /// An allocation was restored from a state file
+    Restored {
+        timestamp: chrono::DateTime<chrono::Utc>,
+        allocation: AgentAllocation,
+    },
+}
+
+impl AllocationEvent {
+    /// When the change was made
+    pub fn timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+        match self {
+            AllocationEvent::Allocated { timestamp, .. }
+            | AllocationEvent::Released { timestamp, .. }
+            | AllocationEvent::Rebalanced { timestamp, .. }
+            | AllocationEvent::Preempted { timestamp, .. }
+            | AllocationEvent::Restored { timestamp, .. } => *timestamp,
+        }
+    }
+    
+    /// Agent whose allocation changed
+    pub fn agent_id(&self) -> &str {
+        match self {
+            AllocationEvent::Allocated { allocation, .. }
+            | AllocationEvent::Released { allocation, .. }
+            | AllocationEvent::Restored { allocation, .. } => &allocation.agent_id,
+            AllocationEvent::Rebalanced { agent_id, .. } | AllocationEvent::Preempted { agent_id, .. } => agent_id,
+        }
+    }
+}
+
+/// Subscribers to allocation events, and the most recent events
+#[derive(Default)]
+pub(crate) struct AllocationEvents {
+    subscribers: Mutex<Vec<SyncSender<AllocationEvent>>>,
+    recent: Mutex<VecDeque<AllocationEvent>>,
+    dropped: AtomicU64,
+}
+
+impl AllocationEvents {
+    /// Open a channel receiving every event published from now on
+    pub fn subscribe(&self) -> Receiver<AllocationEvent> {
+        let (sender, receiver) = mpsc::sync_channel(SUBSCRIBER_CAPACITY);
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner()).push(sender);
+        receiver
+    }
+    
+    /// Send `event` to every subscriber with room for it, forgetting those that hung up
+    pub fn publish(&self, event: AllocationEvent) {
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner()).retain(|subscriber| {
+            match subscriber.try_send(event.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    metrics::counter!("mcp.hm.allocation_events_dropped", 1);
+                    true
+                },
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+        
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == RECENT_EVENTS {
+            recent.pop_front();
+        }
+        recent.push_back(event);
+    }
+    
+    /// The most recent events, oldest first
+    pub fn recent(&self) -> Vec<AllocationEvent> {
+        self.recent.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+    
+    /// Number of events subscribers missed because their channel was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+    
+    /// Number of subscribers still listening
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn allocated(agent_id: &str) -> AllocationEvent {
+        AllocationEvent::Allocated { timestamp: chrono::Utc::now(), allocation: AgentAllocation::new(agent_id, 5.0, 50, 5) }
+    }
+    
+    #[test]
+    fn test_full_subscriber_drops() {
+        let events = AllocationEvents::default();
+        let slow = events.subscribe();
+        for index in 0..SUBSCRIBER_CAPACITY + 3 {
+            events.publish(allocated(&format!("agent-{}", index)));
+        }
+        
+        // The overflow is counted rather than blocking, and the earliest events kept
+        assert_eq!(events.dropped(), 3);
+        assert_eq!(slow.try_iter().count(), SUBSCRIBER_CAPACITY);
+        assert_eq!(events.recent().len(), RECENT_EVENTS);
+        assert_eq!(events.recent().last().unwrap().agent_id(), format!("agent-{}", SUBSCRIBER_CAPACITY + 2));
+        
+        // Subscribers that hang up are forgotten
+        drop(slow);
+        events.publish(allocated("late"));
+        assert_eq!(events.subscriber_count(), 0);
+        
+        let json = serde_json::to_value(allocated("tagged")).unwrap();
+        assert_eq!(json["event"], "allocated");
+    }
+}
@@ -0,0 +1,50 @@
+//! Prometheus metrics endpoint for MCP-ZERO Hardware Manager
+//!
+//! When `metrics_listen` is configured, the daemon serves the `mcp.hm.*` metrics at
+//! `/metrics` on that address for Prometheus to scrape. Metric names are rendered in
+//! Prometheus form, e.g. `mcp.hm.cpu_usage` becomes `mcp_hm_cpu_usage`:
+//!
+//! - `mcp_hm_cpu_usage`, `mcp_hm_memory_usage`: sampled usage (% and MB)
+//! - `mcp_hm_disk_usage`, `mcp_hm_net_usage`: disk and network throughput (MB/s)
+//! - `mcp_hm_swap_usage`: swap in use on the host (MB)
+//! - `mcp_hm_cpu_core_usage`: CPU usage of each of the host's cores (%), by `core`
+//! - `mcp_hm_temperature`: CPU package temperature (°C), absent without a sensor for it
+//! - `mcp_hm_cpu_limit`, `mcp_hm_memory_limit`, `mcp_hm_disk_limit`, `mcp_hm_net_limit`:
+//!   configured limits, the IO ones only when set
+//! - `mcp_hm_agent_cpu_allocation`, `mcp_hm_agent_memory_allocation`: per agent, by `agent_id`
+//! - `mcp_hm_agent_cpu_usage`, `mcp_hm_agent_memory_usage`: measured usage of agents'
+//!   registered processes, by `agent_id`
+//! - `mcp_hm_agents_preempted`: allocations shrunk or evicted for higher priority ones,
+//!   by `action`
+//! - `mcp_hm_allocation_events_dropped`: allocation events a subscriber missed because
+//!   its channel was full
+//! - `mcp_hm_alerts`: alerts emitted, by `level` and `resource`
+//! - `mcp_hm_alerts_resolved`: open alerts resolved, by `resource`
+//! - `mcp_hm_alerts_escalated`: open alerts escalated, by the `level` reached
+//! - `mcp_hm_webhook_dropped`, `mcp_hm_webhook_failures`: alerts dropped from a full
+//!   webhook queue and alerts that couldn't be delivered
+//! - `mcp_hm_monitor_lag_ms`: how far the monitoring loop fell behind its refresh interval
+//! - `mcp_hm_sample_duration_ms`: time the monitoring loop spent taking its latest sample
+//! - `mcp_hm_sample_interval_ms`: how long the monitoring loop waits before its next
+//!   sample, which varies with usage under adaptive sampling
+
+use std::net::SocketAddr;
+use anyhow::{Result, Context};
+use metrics_exporter_prometheus::PrometheusBuilder;
+
+/// Serve metrics at `/metrics` on `listen`
+///
+/// The endpoint runs on its own thread for the life of the process. Only one metrics
+/// recorder can be installed per process, so a second call fails.
+pub fn install_metrics_exporter(listen: &str) -> Result<SocketAddr> {
+    let addr: SocketAddr = listen.parse()
+        .with_context(|| format!("Invalid metrics listen address: {}", listen))?;
+    
+    PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .install()
+        .with_context(|| format!("Failed to start metrics endpoint on {}", addr))?;
+    
+    tracing::info!("Serving metrics at http://{}/metrics", addr);
+    Ok(addr)
+}
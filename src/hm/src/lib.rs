@@ -6,6 +6,8 @@
 //! - Efficient resource allocation to agents
 
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 use anyhow::{Result, Context, anyhow};
@@ -15,13 +17,48 @@ use metrics::{gauge, histogram};
 use dashmap::DashMap;
 use thiserror::Error;
 
+mod adaptive;
 mod config;
 mod resource;
 mod alert;
+mod alert_log;
+mod agents;
+mod api;
+mod cgroup;
+mod daemon;
+mod degrade;
+mod events;
+mod exporter;
+mod monitor;
+mod webhook;
+mod sampling;
+mod shutdown;
+mod state;
 
-pub use config::HMConfig;
-pub use resource::{ResourceStats, ResourceLimit, ResourceType};
-pub use alert::{Alert, AlertLevel, AlertHandler, ConsoleAlertHandler, FileAlertHandler};
+pub use config::{AlertThreshold, HMConfig};
+pub use resource::{AllocationStrategy, ResourceStats, ResourceLimit, ResourceType};
+pub use alert::{Alert, AlertEvent, AlertInstance, AlertLevel, AlertHandler, AlertResolution, ConsoleAlertHandler, EscalationRule};
+pub use alert_log::{AlertLogFormat, FileAlertHandler, parse_alert_line, read_alert_log, rotated_path};
+pub use agents::AgentUsage;
+pub use api::start_api_server;
+pub use daemon::{PidFile, daemonize, process_running, read_pid_file, running_daemon, terminate};
+pub use degrade::{DegradationAction, DegradationHandler, DegradationKind};
+pub use events::AllocationEvent;
+pub use exporter::install_metrics_exporter;
+pub use monitor::MonitorHandle;
+pub use webhook::{WebhookAlertHandler, WebhookConfig};
+pub use sampling::{SamplingCost, measure_sampling_cost, refresh_host, refresh_network, refresh_process, sampling_system};
+pub use shutdown::ShutdownToken;
+
+use adaptive::AdaptiveInterval;
+use alert::AlertManager;
+use agents::AgentMonitor;
+use cgroup::CgroupEnforcer;
+use degrade::Degrader;
+use events::AllocationEvents;
+use monitor::{FinishGuard, MonitorSignal};
+use resource::{ResourceAllocator, ResourceTracker};
+use state::{StateWriter, read_allocations, set_aside, write_allocations};
 
 /// Error types for the Hardware Manager
 #[derive(Error, Debug)]
@@ -53,6 +90,36 @@ pub struct AgentAllocation {
     
     /// Priority level (higher = more important)
     pub priority: u8,
+    
+    /// Last reported size of the agent's state (bytes of serialized JSON)
+    #[serde(default)]
+    pub state_bytes: u64,
+}
+
+/// An allocation resized by rebalancing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationChange {
+    /// Agent ID
+    pub agent_id: String,
+    
+    /// Allocation before rebalancing
+    pub old: AgentAllocation,
+    
+    /// Allocation after rebalancing
+    pub new: AgentAllocation,
+}
+
+/// An allocation shrunk or evicted to make room for one of higher priority
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preemption {
+    /// Agent ID
+    pub agent_id: String,
+    
+    /// Allocation before preemption
+    pub old: AgentAllocation,
+    
+    /// Allocation after preemption, or none if it was evicted
+    pub new: Option<AgentAllocation>,
 }
 
 /// Hardware Manager implementation
@@ -66,11 +133,32 @@ pub struct HardwareManager {
     /// Agent allocations
     allocations: Arc<RwLock<HashMap<String, AgentAllocation>>>,
     
+    /// Sizes allocations under the configured strategy
+    allocator: ResourceAllocator,
+    
+    /// Saves allocations as they change, when `allocation_state_path` is set
+    state: Option<StateWriter>,
+    
+    /// Subscribers to allocation changes, and the most recent changes
+    events: AllocationEvents,
+    
     /// System information collector
     system: Arc<Mutex<System>>,
     
-    /// Alert handlers
-    alert_handlers: Vec<Box<dyn AlertHandler>>,
+    /// Alert handlers, shared with the monitoring thread
+    alerts: Arc<AlertManager>,
+    
+    /// Usage history, bounded to `history_minutes`, checked against the alert thresholds
+    tracker: Arc<Mutex<ResourceTracker>>,
+    
+    /// Graceful degradation state and handlers, shared with the monitoring thread
+    degrader: Arc<Degrader>,
+    
+    /// Registered agent processes and their usage
+    agent_monitor: Arc<AgentMonitor>,
+    
+    /// Enforces allocations through cgroups when configured
+    cgroups: Arc<CgroupEnforcer>,
     
     /// Process ID
     process_id: u32,
@@ -78,6 +166,15 @@ pub struct HardwareManager {
     /// Last update time
     last_update: Arc<Mutex<Instant>>,
     
+    /// Number of samples the monitoring thread has taken
+    samples: Arc<AtomicU64>,
+    
+    /// Interval the monitoring thread waits before its next sample (ms)
+    sample_interval_ms: Arc<AtomicU64>,
+    
+    /// Handle to the monitoring thread, once started
+    monitor: Mutex<Option<MonitorHandle>>,
+    
     /// Configuration
     config: HMConfig,
 }
@@ -85,90 +182,261 @@ pub struct HardwareManager {
 impl HardwareManager {
     /// Create a new HardwareManager with the given configuration
     pub fn new(config: HMConfig) -> Self {
-        let mut system = System::new_all();
-        system.refresh_all();
-        
         // Get own process ID
         let process_id = std::process::id();
         
+        // Load only what sampling reads
+        let mut system = sampling_system();
+        refresh_process(&mut system, process_id);
+        
+        // Keep history_minutes of samples, taken as often as the interval allows
+        let shortest_interval = match config.adaptive_sampling {
+            true => config.min_refresh_interval_ms.min(config.refresh_interval_ms),
+            false => config.refresh_interval_ms,
+        };
+        let history = (config.history_minutes as u64 * 60_000 / shortest_interval.max(1)).max(1) as usize;
+        let mut tracker = ResourceTracker::new(history)
+            .with_retention(Duration::from_secs(config.history_minutes as u64 * 60))
+            .with_warning_threshold(config.alert_threshold)
+            .with_host_thresholds(config.swap_alert_threshold_mb, config.temperature_alert_threshold_celsius);
+        for (&resource_type, threshold) in &config.alert_thresholds {
+            let warning = threshold.warn.unwrap_or(config.alert_threshold);
+            tracker = tracker.with_thresholds(resource_type, warning, threshold.clear.unwrap_or(warning));
+        }
+        
+        let limits = ResourceLimit {
+            cpu_percent: config.max_cpu_percent,
+            memory_mb: config.max_memory_mb,
+            disk_mbps: config.max_disk_mbps,
+            net_mbps: config.max_net_mbps,
+        };
+        let mut allocator = ResourceAllocator::new(limits.clone(), config.allocation_strategy);
+        allocator.set_minimums(config.min_cpu_per_agent, config.min_memory_per_agent_mb);
+        let state = config.allocation_state_path.clone()
+            .map(|path| StateWriter::new(path, Duration::from_millis(config.allocation_state_debounce_ms)));
+        
         Self {
-            limits: ResourceLimit {
-                cpu_percent: config.max_cpu_percent,
-                memory_mb: config.max_memory_mb,
-            },
+            limits,
             stats: Arc::new(Mutex::new(ResourceStats {
                 cpu_percent: 0.0,
                 memory_mb: 0,
                 timestamp: chrono::Utc::now(),
+                ..Default::default()
             })),
             allocations: Arc::new(RwLock::new(HashMap::new())),
+            allocator,
+            state,
+            events: AllocationEvents::default(),
             system: Arc::new(Mutex::new(system)),
-            alert_handlers: Vec::new(),
+            alerts: Arc::new(
+                AlertManager::new(AlertLevel::Info)
+                    .with_cooldown(Duration::from_millis(config.alert_cooldown_ms))
+                    .with_escalations(config.alert_escalations.clone())
+                    .with_routes(config.alert_routes.clone()),
+            ),
+            tracker: Arc::new(Mutex::new(tracker)),
+            degrader: Arc::new(Degrader::new(&config)),
+            agent_monitor: Arc::new(AgentMonitor::default()),
+            cgroups: Arc::new(CgroupEnforcer::new(&config)),
             process_id,
             last_update: Arc::new(Mutex::new(Instant::now())),
+            samples: Arc::new(AtomicU64::new(0)),
+            sample_interval_ms: Arc::new(AtomicU64::new(config.refresh_interval_ms)),
+            monitor: Mutex::new(None),
             config,
         }
     }
     
     /// Start the hardware monitoring loop
-    pub fn start_monitoring(&self) -> Result<()> {
+    ///
+    /// Only one monitoring thread runs at a time: starting again while it runs is an
+    /// error, and starting after `stop_monitoring` or `MonitorHandle::stop` waits for
+    /// the old thread to exit first.
+    pub fn start_monitoring(&self) -> Result<MonitorHandle> {
+        let mut monitor = self.monitor.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(previous) = monitor.as_ref() {
+            if previous.is_running() && !previous.is_stopped() {
+                return Err(HMError::MonitoringError("Monitoring is already running".to_string()).into());
+            }
+            previous.stop_and_wait();
+        }
+        
+        let signal = Arc::new(MonitorSignal::default());
+        let finish = FinishGuard(signal.clone());
+        let samples = self.samples.clone();
         let stats = self.stats.clone();
         let system = self.system.clone();
         let process_id = self.process_id;
         let limits = self.limits.clone();
+        let alerts = self.alerts.clone();
+        let tracker = self.tracker.clone();
+        let degrader = self.degrader.clone();
+        let agent_monitor = self.agent_monitor.clone();
+        let cgroups = self.cgroups.clone();
+        cgroups.ensure_ready(&alerts);
+        let allocations = self.allocations.clone();
         let last_update = self.last_update.clone();
-        let interval = self.config.refresh_interval_ms;
+        
+        // Start the IO counters' first interval now
+        {
+            let mut sys = system.lock().unwrap();
+            refresh_process(&mut sys, process_id);
+            refresh_network(&mut sys);
+        }
+        *last_update.lock().unwrap() = Instant::now();
+        let fixed_interval = Duration::from_millis(self.config.refresh_interval_ms);
+        let mut adaptive = self.config.adaptive_sampling.then(|| AdaptiveInterval::new(
+            fixed_interval,
+            Duration::from_millis(self.config.min_refresh_interval_ms),
+            Duration::from_millis(self.config.max_refresh_interval_ms),
+        ));
+        let sample_interval = self.sample_interval_ms.clone();
+        sample_interval.store(adaptive.as_ref().map_or(fixed_interval, AdaptiveInterval::current).as_millis() as u64, Ordering::Relaxed);
         
         // Spawn monitoring thread
-        std::thread::spawn(move || {
-            loop {
-                // Sleep for the refresh interval
-                std::thread::sleep(Duration::from_millis(interval));
-                
-                // Update last check time
-                {
-                    let mut last = last_update.lock().unwrap();
-                    *last = Instant::now();
-                }
-                
-                // Update system stats
-                {
-                    let mut sys = system.lock().unwrap();
-                    sys.refresh_all();
+        let thread = std::thread::Builder::new()
+            .name("mcp-hm-monitor".to_string())
+            .spawn(move || {
+                // Marks the thread finished on exit
+                let finish = finish;
+                loop {
+                    // Sleep for the refresh interval, exiting once stopped
+                    let interval = adaptive.as_ref().map_or(fixed_interval, AdaptiveInterval::current);
+                    if finish.0.wait(interval) {
+                        break;
+                    }
                     
-                    // Get process info
-                    if let Some(process) = sys.process(sysinfo::Pid::from(process_id as usize)) {
-                        let cpu_usage = process.cpu_usage();
-                        let memory_usage = process.memory() / (1024 * 1024); // Convert to MB
-                        
-                        // Update stats
-                        let mut current_stats = stats.lock().unwrap();
-                        current_stats.cpu_percent = cpu_usage;
-                        current_stats.memory_mb = memory_usage as u32;
-                        current_stats.timestamp = chrono::Utc::now();
-                        
-                        // Export metrics
-                        gauge!("mcp.hm.cpu_usage", cpu_usage as f64); // Convert f32 to f64
-                        gauge!("mcp.hm.memory_usage", memory_usage as f64);
+                    // Update last check time, reporting how late this check is
+                    let elapsed = {
+                        let mut last = last_update.lock().unwrap();
+                        let elapsed = last.elapsed();
+                        let lag = elapsed.saturating_sub(interval);
+                        gauge!("mcp.hm.monitor_lag_ms", lag.as_secs_f64() * 1000.0);
+                        *last = Instant::now();
+                        elapsed
+                    };
+                    
+                    // Update system stats, timing the sample
+                    let sample_start = Instant::now();
+                    {
+                        let mut sys = system.lock().unwrap();
+                        refresh_process(&mut sys, process_id);
+                        let (net_rx, net_tx) = refresh_network(&mut sys);
                         
-                        // Check limits
-                        if cpu_usage > limits.cpu_percent {
-                            tracing::warn!("CPU usage exceeded limit: {:.2}% > {:.2}%", 
-                                         cpu_usage, limits.cpu_percent);
-                            // In a real implementation, would trigger alerts and take action
+                        // Get process info
+                        if let Some(process) = sys.process(sysinfo::Pid::from(process_id as usize)) {
+                            let cpu_usage = process.cpu_usage();
+                            let memory_usage = process.memory() / (1024 * 1024); // Convert to MB
+                            let disk = process.disk_usage();
+                            
+                            // Update stats
+                            let current = {
+                                let mut current_stats = stats.lock().unwrap();
+                                refresh_host(&mut sys, &mut current_stats);
+                                current_stats.cpu_percent = cpu_usage;
+                                current_stats.memory_mb = memory_usage as u32;
+                                current_stats.disk_read_bytes = disk.read_bytes;
+                                current_stats.disk_written_bytes = disk.written_bytes;
+                                current_stats.net_rx_bytes = net_rx;
+                                current_stats.net_tx_bytes = net_tx;
+                                current_stats.interval_ms = elapsed.as_millis() as u64;
+                                current_stats.timestamp = chrono::Utc::now();
+                                current_stats.clone()
+                            };
+                            
+                            // Export metrics
+                            gauge!("mcp.hm.cpu_usage", cpu_usage as f64); // Convert f32 to f64
+                            gauge!("mcp.hm.memory_usage", memory_usage as f64);
+                            gauge!("mcp.hm.disk_usage", current.disk_mbps() as f64);
+                            gauge!("mcp.hm.net_usage", current.net_mbps() as f64);
+                            gauge!("mcp.hm.swap_usage", current.swap_mb as f64);
+                            for (core, usage) in current.cpu_per_core.iter().enumerate() {
+                                gauge!("mcp.hm.cpu_core_usage", *usage as f64, "core" => core.to_string());
+                            }
+                            if let Some(temperature) = current.temperature_celsius {
+                                gauge!("mcp.hm.temperature", temperature as f64);
+                            }
+                            gauge!("mcp.hm.cpu_limit", limits.cpu_percent as f64);
+                            gauge!("mcp.hm.memory_limit", limits.memory_mb as f64);
+                            if let Some(limit) = limits.disk_mbps {
+                                gauge!("mcp.hm.disk_limit", limit as f64);
+                            }
+                            if let Some(limit) = limits.net_mbps {
+                                gauge!("mcp.hm.net_limit", limit as f64);
+                            }
+                            
+                            // Check limits
+                            raise_alerts(&current, &limits, &tracker, &alerts);
+                            
+                            // Sample more often the closer usage is to the alert thresholds
+                            if let Some(adaptive) = adaptive.as_mut() {
+                                let usage: Vec<(f32, f32)> = {
+                                    let tracker = tracker.lock().unwrap_or_else(|e| e.into_inner());
+                                    limits.usage(&current).into_iter()
+                                        .map(|(resource_type, percent_of_limit)| (percent_of_limit, tracker.warning_threshold(resource_type)))
+                                        .collect()
+                                };
+                                adaptive.observe(&usage);
+                            }
+                            
+                            // Shed or restore agent allocations
+                            if let Some(action) = degrader.observe(&current, &limits, &allocations, &alerts) {
+                                cgroups.apply_action(&action, &alerts);
+                            }
                         }
                         
-                        if memory_usage as u32 > limits.memory_mb {
-                            tracing::warn!("Memory usage exceeded limit: {} MB > {} MB", 
-                                         memory_usage, limits.memory_mb);
-                            // In a real implementation, would trigger alerts and take action
+                        // Attribute usage to agent processes
+                        for (agent_id, pid) in agent_monitor.sample(&mut sys, &allocations, &alerts) {
+                            cgroups.remove_process(&agent_id, pid);
                         }
+                        
+                        // Escalate alerts left open
+                        alerts.escalate();
                     }
+                    gauge!("mcp.hm.sample_duration_ms", sample_start.elapsed().as_secs_f64() * 1000.0);
+                    let next_interval = adaptive.as_ref().map_or(fixed_interval, AdaptiveInterval::current);
+                    gauge!("mcp.hm.sample_interval_ms", next_interval.as_secs_f64() * 1000.0);
+                    sample_interval.store(next_interval.as_millis() as u64, Ordering::Relaxed);
+                    samples.fetch_add(1, Ordering::Relaxed);
                 }
-            }
-        });
+            })
+            .map_err(|e| HMError::SystemError(format!("Failed to start monitoring thread: {}", e)))?;
         
-        Ok(())
+        let handle = MonitorHandle::new(signal, thread);
+        *monitor = Some(handle.clone());
+        Ok(handle)
+    }
+    
+    /// Stop the monitoring thread, if running, and wait for it to exit
+    pub fn stop_monitoring(&self) {
+        let monitor = self.monitor.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(handle) = monitor {
+            handle.stop_and_wait();
+        }
+    }
+    
+    /// Monitor until `shutdown` is triggered, then stop monitoring and wait up to
+    /// `flush_timeout` for queued alerts to be delivered, returning the final report
+    pub fn run_until(&self, shutdown: &ShutdownToken, flush_timeout: Duration) -> Result<serde_json::Value> {
+        self.start_monitoring()?;
+        shutdown.wait();
+        
+        self.stop_monitoring();
+        if !self.alerts.flush(flush_timeout) {
+            tracing::warn!("Some alerts were still undelivered after {:?}", flush_timeout);
+        }
+        Ok(self.generate_report())
+    }
+    
+    /// Number of samples taken by the monitoring thread
+    pub fn sample_count(&self) -> u64 {
+        self.samples.load(Ordering::Relaxed)
+    }
+    
+    /// Interval the monitoring thread waits before its next sample, which varies with
+    /// usage under adaptive sampling
+    pub fn sample_interval(&self) -> Duration {
+        Duration::from_millis(self.sample_interval_ms.load(Ordering::Relaxed))
     }
     
     /// Get current resource stats
@@ -198,59 +466,469 @@ impl HardwareManager {
         Ok(())
     }
     
-    /// Allocate resources to an agent
-    pub fn allocate_resources(&self, allocation: AgentAllocation) -> Result<(), HMError> {
+    /// Allocate resources to an agent, returning the allocations preempted to make room
+    ///
+    /// Under the even and priority_based strategies, a new agent joining first shrinks
+    /// the other allocations to their rebalanced shares, which are reported in an Info
+    /// alert. With `allocation_preemption` on, an allocation that still doesn't fit
+    /// shrinks allocations of strictly lower priority, lowest first, down to the
+    /// per-agent minimums, then evicts them until it does. Each preempted agent is
+    /// reported in a Warning alert, and callers throttle them to their new allocations.
+    /// Nothing changes if the allocation doesn't fit even then.
+    pub fn allocate_resources(&self, allocation: AgentAllocation) -> Result<Vec<Preemption>, HMError> {
+        let mut allocations = self.allocations.write().unwrap();
+        let changes = match allocations.contains_key(&allocation.agent_id) {
+            true => Vec::new(),
+            false => self.rebalanced(&allocations, 1),
+        };
+        
+        // The other allocations, as rebalanced
+        let others: Vec<&AgentAllocation> = allocations.values()
+            .filter(|alloc| alloc.agent_id != allocation.agent_id)
+            .map(|alloc| changes.iter().find(|change| change.agent_id == alloc.agent_id).map_or(alloc, |change| &change.new))
+            .collect();
+        
         // Check if allocation is within limits
-        {
-            let allocations = self.allocations.read().unwrap();
+        let total_cpu = allocation.cpu_percent + others.iter().map(|alloc| alloc.cpu_percent).sum::<f32>();
+        let total_memory = allocation.memory_mb + others.iter().map(|alloc| alloc.memory_mb).sum::<u32>();
+        
+        let exceeded = if total_cpu > self.limits.cpu_percent {
+            Some(HMError::ResourceLimitExceeded(
+                format!("Total CPU allocation would exceed limit: {:.2}% > {:.2}%", 
+                      total_cpu, self.limits.cpu_percent)
+            ))
+        } else if total_memory > self.limits.memory_mb {
+            Some(HMError::ResourceLimitExceeded(
+                format!("Total memory allocation would exceed limit: {} MB > {} MB", 
+                      total_memory, self.limits.memory_mb)
+            ))
+        } else {
+            None
+        };
+        
+        // Make room by preempting lower priorities, if allowed
+        let preemptions = match exceeded {
+            None => Vec::new(),
+            Some(error) if self.config.allocation_preemption => {
+                let cpu_needed = (total_cpu - self.limits.cpu_percent).max(0.0);
+                let memory_needed = total_memory.saturating_sub(self.limits.memory_mb);
+                self.plan_preemption(allocation.priority, &others, cpu_needed, memory_needed).ok_or(error)?
+            },
+            Some(error) => return Err(error),
+        };
+        
+        // Store allocation
+        self.apply_changes(&mut allocations, &changes);
+        self.apply_preemptions(&mut allocations, &allocation, &preemptions);
+        self.store_allocation(&mut allocations, allocation.clone());
+        self.events.publish(AllocationEvent::Allocated { timestamp: chrono::Utc::now(), allocation });
+        self.persist(&allocations);
+        
+        Ok(preemptions)
+    }
+    
+    /// Shrink or evict allocations in `others` of lower priority than `priority` to free
+    /// `cpu_needed` and `memory_needed`, or none if they can't
+    fn plan_preemption(&self, priority: u8, others: &[&AgentAllocation], cpu_needed: f32, memory_needed: u32) -> Option<Vec<Preemption>> {
+        let mut candidates: Vec<&AgentAllocation> = others.iter().copied().filter(|other| other.priority < priority).collect();
+        candidates.sort_by(|a, b| a.priority.cmp(&b.priority).then_with(|| a.agent_id.cmp(&b.agent_id)));
+        let (mut cpu_needed, mut memory_needed) = (cpu_needed, memory_needed);
+        
+        // Shrink the lowest priorities first, down to the minimums
+        let mut planned: Vec<AgentAllocation> = candidates.iter().map(|&candidate| candidate.clone()).collect();
+        for plan in &mut planned {
+            let cpu_cut = cpu_needed.min((plan.cpu_percent - self.config.min_cpu_per_agent).max(0.0));
+            let memory_cut = memory_needed.min(plan.memory_mb.saturating_sub(self.config.min_memory_per_agent_mb));
+            plan.cpu_percent -= cpu_cut;
+            plan.memory_mb -= memory_cut;
+            cpu_needed -= cpu_cut;
+            memory_needed -= memory_cut;
+        }
+        
+        // Then evict them in the same order until there's room
+        let mut evicted = 0;
+        while (cpu_needed > 0.0 || memory_needed > 0) && evicted < planned.len() {
+            cpu_needed -= planned[evicted].cpu_percent;
+            memory_needed = memory_needed.saturating_sub(planned[evicted].memory_mb);
+            evicted += 1;
+        }
+        if cpu_needed > 0.0 || memory_needed > 0 {
+            return None;
+        }
+        
+        let preemptions = candidates.into_iter().zip(planned).enumerate()
+            .filter_map(|(index, (old, new))| {
+                let new = (index >= evicted).then_some(new);
+                let changed = new.as_ref().is_none_or(|new| new.cpu_percent < old.cpu_percent || new.memory_mb < old.memory_mb);
+                changed.then(|| Preemption { agent_id: old.agent_id.clone(), old: old.clone(), new })
+            })
+            .collect();
+        Some(preemptions)
+    }
+    
+    /// Store preempted allocations, reporting each in a Warning alert
+    fn apply_preemptions(&self, allocations: &mut HashMap<String, AgentAllocation>, preempting: &AgentAllocation, preemptions: &[Preemption]) {
+        for preemption in preemptions {
+            let old = &preemption.old;
+            let (action, cpu_percent, memory_mb) = match &preemption.new {
+                Some(new) => {
+                    self.store_allocation(allocations, new.clone());
+                    ("shrunk", new.cpu_percent, new.memory_mb)
+                },
+                None => {
+                    allocations.remove(&preemption.agent_id);
+                    gauge!("mcp.hm.agent_cpu_allocation", 0.0, "agent_id" => preemption.agent_id.clone());
+                    gauge!("mcp.hm.agent_memory_allocation", 0.0, "agent_id" => preemption.agent_id.clone());
+                    ("evicted", 0.0, 0)
+                },
+            };
+            metrics::counter!("mcp.hm.agents_preempted", 1, "action" => action);
+            self.events.publish(AllocationEvent::Preempted {
+                timestamp: chrono::Utc::now(),
+                agent_id: preemption.agent_id.clone(),
+                old: old.clone(),
+                new: preemption.new.clone(),
+                preempted_by: preempting.agent_id.clone(),
+            });
             
-            let mut total_cpu = allocation.cpu_percent;
-            let mut total_memory = allocation.memory_mb;
+            let message = format!(
+                "Agent {} (priority {}) {} from {:.2}%/{} MB to {:.2}%/{} MB to make room for {} (priority {})",
+                preemption.agent_id, old.priority, action, old.cpu_percent, old.memory_mb, cpu_percent, memory_mb,
+                preempting.agent_id, preempting.priority
+            );
+            tracing::warn!("{}", message);
+            let alert = match cpu_percent < old.cpu_percent {
+                true => Alert::new(AlertLevel::Warning, ResourceType::CPU, &message, cpu_percent as f64, old.cpu_percent as f64),
+                false => Alert::new(AlertLevel::Warning, ResourceType::Memory, &message, memory_mb as f64, old.memory_mb as f64),
+            };
+            self.alerts.emit(alert.with_agent(&preemption.agent_id));
+        }
+    }
+    
+    /// Release resources allocated to an agent
+    ///
+    /// Under the even and priority_based strategies, the remaining allocations grow into
+    /// the freed share.
+    pub fn release_resources(&self, agent_id: &str) -> Result<(), HMError> {
+        let mut allocations = self.allocations.write().unwrap();
+        
+        if let Some(allocation) = allocations.remove(agent_id) {
+            gauge!("mcp.hm.agent_cpu_allocation", 0.0, "agent_id" => agent_id.to_string());
+            gauge!("mcp.hm.agent_memory_allocation", 0.0, "agent_id" => agent_id.to_string());
+            tracing::info!("Resources released for agent {}", agent_id);
+            self.events.publish(AllocationEvent::Released { timestamp: chrono::Utc::now(), allocation });
             
-            for (id, alloc) in allocations.iter() {
-                if id != &allocation.agent_id {
-                    total_cpu += alloc.cpu_percent;
-                    total_memory += alloc.memory_mb;
-                }
+            let changes = self.rebalanced(&allocations, 0);
+            self.apply_changes(&mut allocations, &changes);
+            self.persist(&allocations);
+            Ok(())
+        } else {
+            Err(HMError::ConfigError(format!("No allocation found for agent {}", agent_id)))
+        }
+    }
+    
+    /// Suggest an allocation for an agent with `priority` under the configured strategy,
+    /// sized as if it had joined the agents allocated now
+    pub fn suggest_allocation(&self, agent_id: &str, priority: u8) -> AgentAllocation {
+        let allocations = self.allocations.read().unwrap();
+        let agent_count = allocations.len() + usize::from(!allocations.contains_key(agent_id));
+        let (cpu_percent, memory_mb) = self.allocator.calculate_allocation(priority, agent_count);
+        AgentAllocation::new(agent_id, cpu_percent, memory_mb, priority)
+    }
+    
+    /// Resize every allocation to its share under the even or priority_based strategy,
+    /// returning the allocations that changed, ordered by agent ID
+    ///
+    /// Callers apply the new limits to the agents. Changes are also reported in an Info
+    /// alert. Allocations are left alone under fcfs.
+    pub fn rebalance(&self) -> Vec<AllocationChange> {
+        let mut allocations = self.allocations.write().unwrap();
+        let changes = self.rebalanced(&allocations, 0);
+        self.apply_changes(&mut allocations, &changes);
+        if !changes.is_empty() {
+            self.persist(&allocations);
+        }
+        changes
+    }
+    
+    /// Allocations the strategy gives the agents in `allocations` once `joining` more
+    /// have joined, for those that would change
+    fn rebalanced(&self, allocations: &HashMap<String, AgentAllocation>, joining: usize) -> Vec<AllocationChange> {
+        if !self.allocator.strategy().rebalances() {
+            return Vec::new();
+        }
+        
+        let agent_count = allocations.len() + joining;
+        let mut changes: Vec<AllocationChange> = allocations.values()
+            .filter_map(|old| {
+                let (cpu_percent, memory_mb) = self.allocator.calculate_allocation(old.priority, agent_count);
+                let changed = (cpu_percent - old.cpu_percent).abs() > f32::EPSILON || memory_mb != old.memory_mb;
+                changed.then(|| AllocationChange {
+                    agent_id: old.agent_id.clone(),
+                    old: old.clone(),
+                    new: AgentAllocation { cpu_percent, memory_mb, ..old.clone() },
+                })
+            })
+            .collect();
+        changes.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+        changes
+    }
+    
+    /// Store rebalanced allocations, reporting them in an Info alert
+    fn apply_changes(&self, allocations: &mut HashMap<String, AgentAllocation>, changes: &[AllocationChange]) {
+        if changes.is_empty() {
+            return;
+        }
+        for change in changes {
+            self.store_allocation(allocations, change.new.clone());
+            self.events.publish(AllocationEvent::Rebalanced {
+                timestamp: chrono::Utc::now(),
+                agent_id: change.agent_id.clone(),
+                old: change.old.clone(),
+                new: change.new.clone(),
+            });
+        }
+        
+        let summary: Vec<String> = changes.iter()
+            .map(|change| format!(
+                "{} {:.2}%/{} MB -> {:.2}%/{} MB",
+                change.agent_id, change.old.cpu_percent, change.old.memory_mb, change.new.cpu_percent, change.new.memory_mb
+            ))
+            .collect();
+        let message = format!("Rebalanced {} allocation(s) under the {:?} strategy: {}", changes.len(), self.allocator.strategy(), summary.join(", "));
+        tracing::info!("{}", message);
+        let total_cpu: f32 = allocations.values().map(|allocation| allocation.cpu_percent).sum();
+        self.alerts.send(AlertLevel::Info, ResourceType::CPU, &message, total_cpu as f64, self.limits.cpu_percent as f64);
+    }
+    
+    /// Store an allocation, publishing it and applying it to the agent's cgroup
+    fn store_allocation(&self, allocations: &mut HashMap<String, AgentAllocation>, allocation: AgentAllocation) {
+        gauge!("mcp.hm.agent_cpu_allocation", allocation.cpu_percent as f64, "agent_id" => allocation.agent_id.clone());
+        gauge!("mcp.hm.agent_memory_allocation", allocation.memory_mb as f64, "agent_id" => allocation.agent_id.clone());
+        self.cgroups.update_allocation(&allocation, &self.alerts);
+        allocations.insert(allocation.agent_id.clone(), allocation);
+    }
+    
+    /// Get every agent allocation, ordered by agent ID
+    pub fn get_allocations(&self) -> Vec<AgentAllocation> {
+        let allocations = self.allocations.read().unwrap();
+        let mut allocations: Vec<AgentAllocation> = allocations.values().cloned().collect();
+        allocations.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+        allocations
+    }
+    
+    /// Subscribe to changes to the allocations
+    ///
+    /// The channel receives every change made from now on, in order, as it's made:
+    /// allocations, releases, rebalancing, preemption and restores. It holds a bounded
+    /// number of events; if the subscriber falls that far behind, later events are
+    /// dropped rather than holding up the change, and counted in the report. Dropping
+    /// the receiver unsubscribes.
+    pub fn subscribe(&self) -> std::sync::mpsc::Receiver<AllocationEvent> {
+        self.events.subscribe()
+    }
+    
+    /// Save every allocation to `path`, as JSON if it ends in .json and YAML otherwise
+    pub fn export_allocations<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_allocations(path.as_ref(), &self.get_allocations())
+    }
+    
+    /// Replace every allocation with those saved at `path`, returning the ones dropped
+    /// because they no longer fit within the limits
+    ///
+    /// Under the even and priority_based strategies, the allocations are resized to
+    /// their shares first. They're admitted by priority, highest first, and allocations
+    /// over the limits are reported in a Warning alert.
+    pub fn import_allocations<P: AsRef<Path>>(&self, path: P) -> Result<Vec<AgentAllocation>> {
+        let restored = read_allocations(path.as_ref())?;
+        Ok(self.replace_allocations(restored))
+    }
+    
+    /// Restore the allocations saved at `allocation_state_path`, if any, returning how
+    /// many were restored
+    ///
+    /// Meant for startup, once alert handlers are added. Allocations are admitted as by
+    /// `import_allocations`. A state file that can't be read is moved aside and
+    /// reported in a Warning alert rather than failing startup.
+    pub fn restore_allocations(&self) -> usize {
+        let Some(path) = self.config.allocation_state_path.as_deref().filter(|path| path.exists()) else {
+            return 0;
+        };
+        match read_allocations(path) {
+            Ok(restored) => {
+                let count = restored.len() - self.replace_allocations(restored).len();
+                tracing::info!("Restored {} allocation(s) from {}", count, path.display());
+                count
+            },
+            Err(e) => {
+                let message = match set_aside(path) {
+                    Ok(aside) => format!("Allocation state was unreadable and moved to {}: {:#}", aside.display(), e),
+                    Err(aside_error) => format!("Allocation state was unreadable and couldn't be moved aside ({:#}): {:#}", aside_error, e),
+                };
+                tracing::warn!("{}", message);
+                self.alerts.send(AlertLevel::Warning, ResourceType::Process, &message, 0.0, 0.0);
+                0
+            },
+        }
+    }
+    
+    /// Replace every allocation with `restored`, returning those dropped to fit the limits
+    fn replace_allocations(&self, mut restored: Vec<AgentAllocation>) -> Vec<AgentAllocation> {
+        let mut allocations = self.allocations.write().unwrap();
+        let mut replaced: Vec<AgentAllocation> = allocations.drain().map(|(_, allocation)| allocation).collect();
+        replaced.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+        for allocation in replaced {
+            gauge!("mcp.hm.agent_cpu_allocation", 0.0, "agent_id" => allocation.agent_id.clone());
+            gauge!("mcp.hm.agent_memory_allocation", 0.0, "agent_id" => allocation.agent_id.clone());
+            self.events.publish(AllocationEvent::Released { timestamp: chrono::Utc::now(), allocation });
+        }
+        
+        let total_cpu: f32 = restored.iter().map(|allocation| allocation.cpu_percent).sum();
+        let total_memory: u32 = restored.iter().map(|allocation| allocation.memory_mb).sum();
+        let over_limits = total_cpu > self.limits.cpu_percent || total_memory > self.limits.memory_mb;
+        
+        // Resize to the strategy's shares, then admit the highest priorities first
+        if self.allocator.strategy().rebalances() {
+            let agent_count = restored.len();
+            for allocation in &mut restored {
+                (allocation.cpu_percent, allocation.memory_mb) = self.allocator.calculate_allocation(allocation.priority, agent_count);
             }
-            
-            if total_cpu > self.limits.cpu_percent {
-                return Err(HMError::ResourceLimitExceeded(
-                    format!("Total CPU allocation would exceed limit: {:.2}% > {:.2}%", 
-                          total_cpu, self.limits.cpu_percent)
-                ));
+        }
+        restored.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.agent_id.cmp(&b.agent_id)));
+        let (mut cpu, mut memory) = (0.0, 0);
+        let mut dropped = Vec::new();
+        for allocation in restored {
+            if allocations.contains_key(&allocation.agent_id) {
+                continue;
             }
-            
-            if total_memory > self.limits.memory_mb {
-                return Err(HMError::ResourceLimitExceeded(
-                    format!("Total memory allocation would exceed limit: {} MB > {} MB", 
-                          total_memory, self.limits.memory_mb)
-                ));
+            if cpu + allocation.cpu_percent > self.limits.cpu_percent || memory + allocation.memory_mb > self.limits.memory_mb {
+                dropped.push(allocation);
+                continue;
             }
+            cpu += allocation.cpu_percent;
+            memory += allocation.memory_mb;
+            self.store_allocation(&mut allocations, allocation.clone());
+            self.events.publish(AllocationEvent::Restored { timestamp: chrono::Utc::now(), allocation });
         }
         
-        // Store allocation
-        let mut allocations = self.allocations.write().unwrap();
-        allocations.insert(allocation.agent_id.clone(), allocation);
+        // The rest grow into what the dropped ones would have had
+        let changes = self.rebalanced(&allocations, 0);
+        self.apply_changes(&mut allocations, &changes);
+        self.persist(&allocations);
         
-        Ok(())
+        if over_limits {
+            let mut message = format!(
+                "Restored allocations total {:.2}%/{} MB, over the limits of {:.2}%/{} MB",
+                total_cpu, total_memory, self.limits.cpu_percent, self.limits.memory_mb
+            );
+            if self.allocator.strategy().rebalances() {
+                message.push_str(", and were rebalanced");
+            }
+            if !dropped.is_empty() {
+                let agent_ids: Vec<&str> = dropped.iter().map(|allocation| allocation.agent_id.as_str()).collect();
+                message.push_str(&format!("; dropped {}", agent_ids.join(", ")));
+            }
+            tracing::warn!("{}", message);
+            let alert = match total_cpu > self.limits.cpu_percent {
+                true => Alert::new(AlertLevel::Warning, ResourceType::CPU, &message, total_cpu as f64, self.limits.cpu_percent as f64),
+                false => Alert::new(AlertLevel::Warning, ResourceType::Memory, &message, total_memory as f64, self.limits.memory_mb as f64),
+            };
+            self.alerts.emit(alert);
+        }
+        dropped
     }
     
-    /// Release resources allocated to an agent
-    pub fn release_resources(&self, agent_id: &str) -> Result<(), HMError> {
+    /// Save `allocations` to the state file, if configured
+    fn persist(&self, allocations: &HashMap<String, AgentAllocation>) {
+        if let Some(state) = &self.state {
+            let mut snapshot: Vec<AgentAllocation> = allocations.values().cloned().collect();
+            snapshot.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+            state.save(snapshot);
+        }
+    }
+    
+    /// Record the current state size of an agent
+    pub fn record_state_size(&self, agent_id: &str, state_bytes: u64) -> Result<(), HMError> {
         let mut allocations = self.allocations.write().unwrap();
         
-        if allocations.remove(agent_id).is_some() {
-            tracing::info!("Resources released for agent {}", agent_id);
+        match allocations.get_mut(agent_id) {
+            Some(allocation) => {
+                allocation.state_bytes = state_bytes;
+                self.persist(&allocations);
+                Ok(())
+            },
+            None => Err(HMError::ConfigError(format!("No allocation found for agent {}", agent_id))),
+        }
+    }
+    
+    /// Register a process as running part of an agent
+    ///
+    /// The monitoring loop adds the process's usage to the agent's and alerts when the
+    /// total exceeds the agent's allocation. The process is unregistered once it exits.
+    /// With cgroup enforcement on, the process is also moved into the agent's cgroup.
+    pub fn register_agent_process(&self, agent_id: &str, pid: u32) -> Result<(), HMError> {
+        if self.agent_monitor.register(agent_id, pid) {
+            let allocation = self.allocations.read().unwrap().get(agent_id).cloned();
+            self.cgroups.add_process(agent_id, pid, allocation.as_ref(), &self.alerts);
+            tracing::info!("Process {} registered for agent {}", pid, agent_id);
             Ok(())
         } else {
-            Err(HMError::ConfigError(format!("No allocation found for agent {}", agent_id)))
+            Err(HMError::ConfigError(format!("Process {} is already registered for agent {}", pid, agent_id)))
         }
     }
     
+    /// Unregister a process of an agent
+    pub fn unregister_agent_process(&self, agent_id: &str, pid: u32) -> Result<(), HMError> {
+        if self.agent_monitor.unregister(agent_id, pid) {
+            self.cgroups.remove_process(agent_id, pid);
+            tracing::info!("Process {} unregistered for agent {}", pid, agent_id);
+            Ok(())
+        } else {
+            Err(HMError::ConfigError(format!("Process {} is not registered for agent {}", pid, agent_id)))
+        }
+    }
+    
+    /// Whether allocations are enforced through cgroups, rather than only monitored
+    pub fn is_enforcing(&self) -> bool {
+        self.cgroups.is_active()
+    }
+    
+    /// Measured usage of each agent with registered processes, from the latest sample
+    pub fn get_agent_usage(&self) -> HashMap<String, AgentUsage> {
+        self.agent_monitor.usage()
+    }
+    
     /// Add an alert handler
-    pub fn add_alert_handler(&mut self, handler: Box<dyn AlertHandler>) {
-        self.alert_handlers.push(handler);
+    ///
+    /// Handlers added after monitoring has started receive the alerts raised from then on.
+    pub fn add_alert_handler(&self, handler: Box<dyn AlertHandler>) {
+        self.alerts.add_handler(handler);
+    }
+    
+    /// Samples taken by the monitoring thread within `range` of the latest, oldest first
+    pub fn get_history(&self, range: Duration) -> Vec<ResourceStats> {
+        self.lock_tracker().get_history(range)
+    }
+    
+    /// Average (CPU percentage, memory MB) over the history
+    pub fn get_average_usage(&self) -> (f32, u32) {
+        self.lock_tracker().get_average_usage()
+    }
+    
+    /// Maximum (CPU percentage, memory MB) over the history
+    pub fn get_max_usage(&self) -> (f32, u32) {
+        self.lock_tracker().get_max_usage()
+    }
+    
+    fn lock_tracker(&self) -> std::sync::MutexGuard<'_, ResourceTracker> {
+        self.tracker.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Add a handler for graceful degradation steps
+    ///
+    /// Handlers only hear of steps with `enable_graceful_degradation` on.
+    pub fn add_degradation_handler(&self, handler: Box<dyn DegradationHandler>) {
+        self.degrader.add_handler(handler);
     }
     
     /// Generate a resource report
@@ -264,27 +942,154 @@ impl HardwareManager {
         
         let total_allocated_cpu: f32 = allocations.values().map(|a| a.cpu_percent).sum();
         let total_allocated_memory: u32 = allocations.values().map(|a| a.memory_mb).sum();
+        let total_state_bytes: u64 = allocations.values().map(|a| a.state_bytes).sum();
+        
+        // Actual against allocated usage per agent
+        let usage = self.agent_monitor.usage();
+        let mut agent_ids: Vec<&String> = allocations.keys().chain(usage.keys()).collect();
+        agent_ids.sort();
+        agent_ids.dedup();
+        let agents: serde_json::Map<String, serde_json::Value> = agent_ids.into_iter()
+            .map(|agent_id| {
+                let actual = usage.get(agent_id);
+                let allocated = allocations.get(agent_id);
+                (agent_id.clone(), serde_json::json!({
+                    "pids": actual.map(|usage| usage.pids.clone()).unwrap_or_default(),
+                    "actual": actual.map(|usage| serde_json::json!({"cpu_percent": usage.cpu_percent, "memory_mb": usage.memory_mb})),
+                    "allocated": allocated.map(|allocation| serde_json::json!({"cpu_percent": allocation.cpu_percent, "memory_mb": allocation.memory_mb})),
+                }))
+            })
+            .collect();
+        
+        // Summarize the usage history
+        let history = {
+            let tracker = self.lock_tracker();
+            let usage = |(cpu_percent, memory_mb): (f32, u32)| serde_json::json!({"cpu_percent": cpu_percent, "memory_mb": memory_mb});
+            let io = |(disk_mbps, net_mbps): (f32, f32)| serde_json::json!({"disk_mbps": disk_mbps, "net_mbps": net_mbps});
+            serde_json::json!({
+                "samples": tracker.len(),
+                "average": usage(tracker.get_average_usage()),
+                "max": usage(tracker.get_max_usage()),
+                "p50": usage(tracker.get_percentile_usage(50.0)),
+                "p95": usage(tracker.get_percentile_usage(95.0)),
+                "p99": usage(tracker.get_percentile_usage(99.0)),
+                "io_average": io(tracker.get_average_io()),
+                "io_max": io(tracker.get_max_io()),
+            })
+        };
         
         serde_json::json!({
             "timestamp": stats.timestamp.to_rfc3339(),
             "system": {
                 "cpu_percent": stats.cpu_percent,
                 "memory_mb": stats.memory_mb,
+                "disk_read_bytes": stats.disk_read_bytes,
+                "disk_written_bytes": stats.disk_written_bytes,
+                "disk_mbps": stats.disk_mbps(),
+                "net_rx_bytes": stats.net_rx_bytes,
+                "net_tx_bytes": stats.net_tx_bytes,
+                "net_mbps": stats.net_mbps(),
+                "interval_ms": stats.interval_ms,
+                "swap_mb": stats.swap_mb,
+                "cpu_per_core": stats.cpu_per_core,
+                "temperature_celsius": stats.temperature_celsius,
             },
             "limits": {
                 "cpu_percent": self.limits.cpu_percent,
                 "memory_mb": self.limits.memory_mb,
+                "disk_mbps": self.limits.disk_mbps,
+                "net_mbps": self.limits.net_mbps,
             },
             "allocations": {
+                "strategy": self.allocator.strategy(),
                 "count": allocations.len(),
                 "total_cpu_percent": total_allocated_cpu,
                 "total_memory_mb": total_allocated_memory,
+                "total_state_bytes": total_state_bytes,
                 "details": allocation_map,
-            }
+            },
+            "allocation_events": {
+                "subscribers": self.events.subscriber_count(),
+                "dropped": self.events.dropped(),
+                "recent": self.events.recent(),
+            },
+            "agents": agents,
+            "sampling": {
+                "adaptive": self.config.adaptive_sampling,
+                "interval_ms": self.sample_interval().as_millis() as u64,
+            },
+            "cgroup_enforcement": self.cgroups.is_active(),
+            "history": history,
+            "alerts": {
+                "open": self.alerts.open_alerts(),
+                "resolved": self.alerts.resolved_count(),
+            },
+            "degradation": self.degrader.report(),
         })
     }
 }
 
+/// Record a sample of resource usage, raising alerts for resources in breach and
+/// clearing the rest
+fn raise_alerts(stats: &ResourceStats, limits: &ResourceLimit, tracker: &Mutex<ResourceTracker>, alerts: &AlertManager) {
+    let (raised, warning_thresholds, host_raised) = {
+        let mut tracker = tracker.lock().unwrap_or_else(|e| e.into_inner());
+        tracker.add_stats(stats);
+        let raised = tracker.check_alerts(limits);
+        let warning_thresholds: Vec<f32> = raised.iter().map(|&(resource_type, ..)| tracker.warning_threshold(resource_type)).collect();
+        (raised, warning_thresholds, tracker.check_host_alerts())
+    };
+    
+    for (&(resource_type, level, percent_of_limit), warning_threshold) in raised.iter().zip(warning_thresholds) {
+        let (current, limit, unit) = match resource_type {
+            ResourceType::Memory => (stats.memory_mb as f64, limits.memory_mb as f64, " MB"),
+            ResourceType::Storage => (stats.disk_mbps() as f64, limits.disk_mbps.unwrap_or_default() as f64, " MB/s"),
+            ResourceType::Network => (stats.net_mbps() as f64, limits.net_mbps.unwrap_or_default() as f64, " MB/s"),
+            _ => (stats.cpu_percent as f64, limits.cpu_percent as f64, "%"),
+        };
+        let threshold = match level {
+            AlertLevel::Critical => limit,
+            _ => limit * warning_threshold as f64,
+        };
+        let message = format!(
+            "{:?} usage at {:.0}% of limit: {:.2}{} of {:.2}{}",
+            resource_type, percent_of_limit * 100.0, current, unit, limit, unit
+        );
+        alerts.raise(Alert::new(level, resource_type, &message, current, threshold));
+    }
+    
+    // Swap and temperature warn past their own thresholds rather than a limit
+    for &(resource_type, current, threshold) in &host_raised {
+        let unit = if resource_type == ResourceType::Temperature { "°C" } else { " MB" };
+        let message = format!("{:?} at {:.1}{}, past the alert threshold of {:.1}{}", resource_type, current, unit, threshold, unit);
+        alerts.raise(Alert::new(AlertLevel::Warning, resource_type, &message, current as f64, threshold as f64));
+    }
+    
+    // Resources back within their thresholds count towards resolving their alerts
+    let resource_types = [
+        ResourceType::CPU,
+        ResourceType::Memory,
+        ResourceType::Storage,
+        ResourceType::Network,
+        ResourceType::Swap,
+        ResourceType::Temperature,
+    ];
+    for resource_type in resource_types {
+        let breached = raised.iter().map(|&(raised_type, ..)| raised_type)
+            .chain(host_raised.iter().map(|&(raised_type, ..)| raised_type))
+            .any(|raised_type| raised_type == resource_type);
+        if !breached {
+            alerts.clear(resource_type, None);
+        }
+    }
+}
+
+impl Drop for HardwareManager {
+    fn drop(&mut self) {
+        self.stop_monitoring();
+    }
+}
+
 // Implementation for AgentAllocation
 impl AgentAllocation {
     /// Create a new agent allocation
@@ -294,6 +1099,420 @@ impl AgentAllocation {
             cpu_percent,
             memory_mb,
             priority,
+            state_bytes: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Alert handler that keeps every alert it receives
+    #[derive(Clone, Default)]
+    struct RecordingHandler {
+        alerts: Arc<Mutex<Vec<Alert>>>,
+    }
+    
+    impl AlertHandler for RecordingHandler {
+        fn handle(&self, alert: &Alert) {
+            self.alerts.lock().unwrap().push(alert.clone());
         }
     }
+    
+    impl RecordingHandler {
+        fn take(&self) -> Vec<(ResourceType, AlertLevel, f64)> {
+            self.alerts.lock().unwrap().drain(..)
+                .map(|alert| (alert.resource_type, alert.level, alert.threshold_value))
+                .collect()
+        }
+    }
+    
+    fn sample(cpu_percent: f32, memory_mb: u32) -> ResourceStats {
+        ResourceStats { cpu_percent, memory_mb, timestamp: chrono::Utc::now(), ..Default::default() }
+    }
+    
+    #[test]
+    fn test_alert_levels() {
+        let hm = HardwareManager::new(HMConfig::default());
+        let recorder = RecordingHandler::default();
+        hm.add_alert_handler(Box::new(recorder.clone()));
+        let check = |stats: ResourceStats| {
+            raise_alerts(&stats, &hm.limits, &hm.tracker, &hm.alerts);
+            recorder.take()
+        };
+        
+        assert!(check(sample(10.0, 100)).is_empty());
+        
+        // Warnings at the alert threshold open an alert that repeated breaches only update
+        let warning = 30.0 * HMConfig::default().alert_threshold as f64;
+        assert_eq!(check(sample(25.0, 100)), [(ResourceType::CPU, AlertLevel::Warning, warning)]);
+        assert!(check(sample(26.0, 100)).is_empty());
+        
+        // Reaching the limit escalates the open alert; other resources alert on their own
+        assert_eq!(check(sample(31.0, 700)), [
+            (ResourceType::CPU, AlertLevel::Critical, 30.0),
+            (ResourceType::Memory, AlertLevel::Warning, 800.0 * HMConfig::default().alert_threshold as f64),
+        ]);
+        assert!(check(sample(35.0, 750)).is_empty());
+    }
+    
+    #[test]
+    fn test_alert_hysteresis() {
+        let thresholds = [(ResourceType::CPU, AlertThreshold { warn: Some(0.9), clear: Some(0.7) })];
+        let hm = HardwareManager::new(HMConfig {
+            alert_thresholds: thresholds.into_iter().collect(),
+            alert_cooldown_ms: 0,
+            ..Default::default()
+        });
+        let recorder = RecordingHandler::default();
+        hm.add_alert_handler(Box::new(recorder.clone()));
+        
+        // Against a 30% limit, warnings open at 27% and clear below 21%; usage wandering
+        // around inside that band neither reopens nor resolves the alert
+        for cpu_percent in [10.0, 25.0, 28.0, 25.0, 22.0, 27.5, 21.5, 20.0, 23.0, 26.0, 22.0, 10.0] {
+            raise_alerts(&sample(cpu_percent, 100), &hm.limits, &hm.tracker, &hm.alerts);
+        }
+        assert_eq!(recorder.take(), [(ResourceType::CPU, AlertLevel::Warning, 30.0 * 0.9f32 as f64)]);
+        assert_eq!(hm.alerts.resolved_count(), 1);
+        assert!(hm.alerts.open_alerts().is_empty());
+        
+        // Memory keeps the single threshold
+        raise_alerts(&sample(10.0, 640), &hm.limits, &hm.tracker, &hm.alerts);
+        assert_eq!(recorder.take(), [(ResourceType::Memory, AlertLevel::Warning, 800.0 * HMConfig::default().alert_threshold as f64)]);
+        
+        let invalid = HMConfig { alert_thresholds: [(ResourceType::Memory, AlertThreshold { warn: Some(0.75), clear: Some(0.75) })].into_iter().collect(), ..Default::default() };
+        assert!(invalid.validate().is_err());
+    }
+    
+    #[test]
+    fn test_host_alerts() {
+        let hm = HardwareManager::new(HMConfig {
+            swap_alert_threshold_mb: Some(100),
+            temperature_alert_threshold_celsius: Some(80.0),
+            alert_cooldown_ms: 0,
+            ..Default::default()
+        });
+        let recorder = RecordingHandler::default();
+        hm.add_alert_handler(Box::new(recorder.clone()));
+        let check = |swap_mb: u32, temperature_celsius: Option<f32>| {
+            let stats = ResourceStats { swap_mb, temperature_celsius, ..sample(1.0, 100) };
+            raise_alerts(&stats, &hm.limits, &hm.tracker, &hm.alerts);
+            recorder.take()
+        };
+        
+        // No sensor reads as unknown rather than cold, in the report and for alerts
+        assert!(hm.generate_report()["system"]["temperature_celsius"].is_null());
+        assert!(check(50, None).is_empty());
+        
+        assert_eq!(check(150, Some(85.0)), [
+            (ResourceType::Swap, AlertLevel::Warning, 100.0),
+            (ResourceType::Temperature, AlertLevel::Warning, 80.0),
+        ]);
+        assert!(check(50, Some(60.0)).is_empty());
+        assert_eq!(hm.alerts.resolved_count(), 2);
+    }
+    
+    #[test]
+    fn test_monitoring_alerts() {
+        let hm = HardwareManager::new(HMConfig {
+            max_memory_mb: 1,
+            refresh_interval_ms: 20,
+            ..Default::default()
+        });
+        hm.start_monitoring().unwrap();
+        
+        // Handlers added after monitoring started receive its alerts
+        let recorder = RecordingHandler::default();
+        hm.add_alert_handler(Box::new(recorder.clone()));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while recorder.alerts.lock().unwrap().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(recorder.take().contains(&(ResourceType::Memory, AlertLevel::Critical, 1.0)));
+        hm.stop_monitoring();
+        
+        // Adaptive sampling speeds up past the alert threshold
+        let hm = HardwareManager::new(HMConfig {
+            max_memory_mb: 1,
+            refresh_interval_ms: 40,
+            adaptive_sampling: true,
+            min_refresh_interval_ms: 10,
+            ..Default::default()
+        });
+        assert_eq!(hm.sample_interval(), Duration::from_millis(40));
+        hm.start_monitoring().unwrap();
+        while hm.sample_count() == 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        hm.stop_monitoring();
+        assert_eq!(hm.sample_interval(), Duration::from_millis(10));
+        assert_eq!(hm.generate_report()["sampling"]["interval_ms"], 10);
+    }
+    
+    #[test]
+    fn test_usage_history() {
+        let hm = HardwareManager::new(HMConfig { refresh_interval_ms: 10, ..Default::default() });
+        assert!(hm.get_history(Duration::from_secs(60)).is_empty());
+        
+        let monitor = hm.start_monitoring().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while hm.get_history(Duration::from_secs(60)).len() < 3 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        hm.stop_monitoring();
+        assert!(!monitor.is_running());
+        
+        let history = hm.get_history(Duration::from_secs(60));
+        assert!(history.len() >= 3);
+        assert!(history.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+        let (max_cpu, max_memory) = hm.get_max_usage();
+        assert_eq!(max_memory, history.iter().map(|stats| stats.memory_mb).max().unwrap());
+        assert!(hm.get_average_usage().0 <= max_cpu);
+        
+        let report = hm.generate_report();
+        assert_eq!(report["history"]["samples"], history.len());
+        assert_eq!(report["history"]["max"]["memory_mb"], max_memory);
+    }
+    
+    #[test]
+    fn test_agent_usage_report() {
+        let hm = HardwareManager::new(HMConfig { refresh_interval_ms: 10, ..Default::default() });
+        hm.allocate_resources(AgentAllocation::new("worker", 5.0, 100, 5)).unwrap();
+        hm.register_agent_process("worker", std::process::id()).unwrap();
+        assert!(hm.register_agent_process("worker", std::process::id()).is_err());
+        
+        hm.start_monitoring().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !hm.get_agent_usage().contains_key("worker") && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        hm.stop_monitoring();
+        
+        let usage = &hm.get_agent_usage()["worker"];
+        let report = hm.generate_report();
+        assert_eq!(report["agents"]["worker"]["pids"], serde_json::json!([std::process::id()]));
+        assert_eq!(report["agents"]["worker"]["actual"]["memory_mb"], usage.memory_mb);
+        assert_eq!(report["agents"]["worker"]["allocated"]["memory_mb"], 100);
+        
+        hm.unregister_agent_process("worker", std::process::id()).unwrap();
+        assert!(hm.unregister_agent_process("worker", std::process::id()).is_err());
+        assert!(hm.generate_report()["agents"]["worker"]["actual"].is_null());
+    }
+    
+    #[test]
+    fn test_monitor_lifecycle() {
+        let hm = HardwareManager::new(HMConfig { refresh_interval_ms: 10, ..Default::default() });
+        let wait_for_samples = |count: u64| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while hm.sample_count() < count && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            assert!(hm.sample_count() >= count);
+        };
+        
+        let first = hm.start_monitoring().unwrap();
+        wait_for_samples(2);
+        assert!(hm.start_monitoring().is_err());
+        
+        // A stopped thread exits without sampling again
+        first.stop();
+        assert!(first.join(Duration::from_secs(5)));
+        assert!(!first.is_running());
+        let stopped_at = hm.sample_count();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(hm.sample_count(), stopped_at);
+        
+        // Restarting runs a single new thread, which stop_monitoring ends
+        let second = hm.start_monitoring().unwrap();
+        wait_for_samples(stopped_at + 2);
+        assert!(hm.start_monitoring().is_err());
+        hm.stop_monitoring();
+        assert!(!second.is_running());
+        let stopped_at = hm.sample_count();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(hm.sample_count(), stopped_at);
+        
+        // Dropping the manager stops its thread
+        let third = hm.start_monitoring().unwrap();
+        drop(hm);
+        assert!(!third.is_running());
+    }
+    
+    #[test]
+    fn test_rebalance() {
+        let shares = |hm: &HardwareManager| -> Vec<(String, f32, u32)> {
+            hm.get_allocations().into_iter().map(|a| (a.agent_id, a.cpu_percent, a.memory_mb)).collect()
+        };
+        
+        // FCFS only ever suggests the minimums and leaves allocations alone
+        let hm = HardwareManager::new(HMConfig::default());
+        let suggested = hm.suggest_allocation("worker", 5);
+        assert_eq!((suggested.cpu_percent, suggested.memory_mb), (1.0, 10));
+        
+        // Even: the first agent gets 90% of the limits, which a second joining halves
+        let hm = HardwareManager::new(HMConfig { allocation_strategy: AllocationStrategy::Even, ..Default::default() });
+        let recorder = RecordingHandler::default();
+        hm.add_alert_handler(Box::new(recorder.clone()));
+        hm.allocate_resources(hm.suggest_allocation("first", 5)).unwrap();
+        assert_eq!(shares(&hm), [("first".to_string(), 27.0, 720)]);
+        assert!(recorder.take().is_empty());
+        
+        hm.allocate_resources(hm.suggest_allocation("second", 5)).unwrap();
+        assert_eq!(shares(&hm), [("first".to_string(), 13.5, 360), ("second".to_string(), 13.5, 360)]);
+        assert_eq!(recorder.take(), [(ResourceType::CPU, AlertLevel::Info, 30.0)]);
+        assert!(hm.rebalance().is_empty());
+        
+        // Leaving hands the share back
+        hm.release_resources("second").unwrap();
+        assert_eq!(shares(&hm), [("first".to_string(), 27.0, 720)]);
+        
+        // A request that doesn't fit even after rebalancing changes nothing
+        assert!(hm.allocate_resources(AgentAllocation::new("greedy", 20.0, 100, 5)).is_err());
+        assert_eq!(shares(&hm), [("first".to_string(), 27.0, 720)]);
+        
+        // PriorityBased: shares above the minimums follow priority
+        let hm = HardwareManager::new(HMConfig { allocation_strategy: AllocationStrategy::PriorityBased, ..Default::default() });
+        hm.allocate_resources(AgentAllocation::new("low", 5.0, 100, 5)).unwrap();
+        hm.allocate_resources(AgentAllocation::new("high", 10.0, 100, 10)).unwrap();
+        let changes: Vec<(String, f32, u32)> = hm.rebalance().into_iter()
+            .map(|change| (change.agent_id, change.new.cpu_percent, change.new.memory_mb))
+            .collect();
+        assert_eq!(changes, [("high".to_string(), 15.0, 400)]);
+        assert_eq!(shares(&hm), [("high".to_string(), 15.0, 400), ("low".to_string(), 8.0, 205)]);
+    }
+    
+    #[test]
+    fn test_preemption() {
+        let hm = HardwareManager::new(HMConfig { allocation_preemption: true, ..Default::default() });
+        let recorder = RecordingHandler::default();
+        hm.add_alert_handler(Box::new(recorder.clone()));
+        let shares = || -> Vec<(String, f32, u32)> {
+            hm.get_allocations().into_iter().map(|a| (a.agent_id, a.cpu_percent, a.memory_mb)).collect()
+        };
+        let preempted = |preemptions: Vec<Preemption>| -> Vec<(String, Option<f32>)> {
+            preemptions.into_iter().map(|p| (p.agent_id, p.new.map(|new| new.cpu_percent))).collect()
+        };
+        hm.allocate_resources(AgentAllocation::new("batch", 10.0, 300, 1)).unwrap();
+        hm.allocate_resources(AgentAllocation::new("worker", 15.0, 300, 3)).unwrap();
+        
+        // Nothing of strictly lower priority, nothing preempted
+        assert!(hm.allocate_resources(AgentAllocation::new("peer", 10.0, 100, 1)).is_err());
+        
+        // The lowest priority shrinks first
+        let preemptions = hm.allocate_resources(AgentAllocation::new("urgent", 12.0, 100, 10)).unwrap();
+        assert_eq!(preempted(preemptions), [("batch".to_string(), Some(3.0))]);
+        assert_eq!(recorder.take(), [(ResourceType::CPU, AlertLevel::Warning, 10.0)]);
+        
+        // Equal priorities are spared, and a request that can't fit changes nothing
+        let before = shares();
+        assert!(hm.allocate_resources(AgentAllocation::new("critical", 20.0, 100, 10)).is_err());
+        assert_eq!(shares(), before);
+        assert!(recorder.take().is_empty());
+        
+        // Shrinking everything to the minimums isn't enough, so the lowest is evicted
+        let preemptions = hm.allocate_resources(AgentAllocation::new("critical", 17.0, 100, 10)).unwrap();
+        assert_eq!(preempted(preemptions), [("batch".to_string(), None), ("worker".to_string(), Some(1.0))]);
+        assert_eq!(shares(), [
+            ("critical".to_string(), 17.0, 100),
+            ("urgent".to_string(), 12.0, 100),
+            ("worker".to_string(), 1.0, 300),
+        ]);
+        assert_eq!(recorder.take().len(), 2);
+        
+        // Off by default
+        let hm = HardwareManager::new(HMConfig::default());
+        hm.allocate_resources(AgentAllocation::new("batch", 25.0, 300, 1)).unwrap();
+        assert!(hm.allocate_resources(AgentAllocation::new("urgent", 10.0, 100, 10)).is_err());
+    }
+    
+    #[test]
+    fn test_allocation_persistence() {
+        let dir = std::env::temp_dir().join(format!("mcp-hm-allocations-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("allocations.yaml");
+        let config = HMConfig { allocation_state_path: Some(path.clone()), allocation_state_debounce_ms: 10, ..Default::default() };
+        let restarted = |config: &HMConfig| {
+            let hm = HardwareManager::new(config.clone());
+            let recorder = RecordingHandler::default();
+            hm.add_alert_handler(Box::new(recorder.clone()));
+            (hm.restore_allocations(), hm, recorder)
+        };
+        let agent_ids = |hm: &HardwareManager| -> Vec<String> {
+            hm.get_allocations().into_iter().map(|allocation| allocation.agent_id).collect()
+        };
+        
+        // Allocations survive a restart
+        let (restored, hm, _) = restarted(&config);
+        assert_eq!(restored, 0);
+        hm.allocate_resources(AgentAllocation::new("low", 10.0, 100, 1)).unwrap();
+        hm.allocate_resources(AgentAllocation::new("high", 15.0, 100, 9)).unwrap();
+        hm.record_state_size("high", 4096).unwrap();
+        drop(hm);
+        let (restored, hm, recorder) = restarted(&config);
+        assert_eq!(restored, 2);
+        assert_eq!(agent_ids(&hm), ["high", "low"]);
+        assert_eq!(hm.get_allocations()[0].state_bytes, 4096);
+        assert!(recorder.take().is_empty());
+        
+        // Exported allocations import elsewhere
+        let exported = dir.join("exported.json");
+        hm.export_allocations(&exported).unwrap();
+        let other = HardwareManager::new(HMConfig::default());
+        assert!(other.import_allocations(&exported).unwrap().is_empty());
+        assert_eq!(agent_ids(&other), ["high", "low"]);
+        drop(hm);
+        
+        // Under tighter limits the highest priorities are kept
+        let (restored, hm, recorder) = restarted(&HMConfig { max_cpu_percent: 20.0, ..config.clone() });
+        assert_eq!(restored, 1);
+        assert_eq!(agent_ids(&hm), ["high"]);
+        assert_eq!(recorder.take(), [(ResourceType::CPU, AlertLevel::Warning, 20.0)]);
+        drop(hm);
+        
+        // A corrupt file is moved aside and reported
+        std::fs::write(&path, "allocations: [{agent_id: 7").unwrap();
+        let (restored, hm, recorder) = restarted(&config);
+        assert_eq!(restored, 0);
+        assert!(agent_ids(&hm).is_empty());
+        assert!(!path.exists());
+        assert_eq!(recorder.take(), [(ResourceType::Process, AlertLevel::Warning, 0.0)]);
+        assert!(std::fs::read_dir(&dir).unwrap().any(|entry| entry.unwrap().file_name().to_string_lossy().starts_with("allocations.yaml.corrupt-")));
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn test_allocation_events() {
+        let hm = HardwareManager::new(HMConfig { allocation_strategy: AllocationStrategy::Even, ..Default::default() });
+        let events = hm.subscribe();
+        let kinds = || -> Vec<(String, String)> {
+            events.try_iter()
+                .map(|event| (serde_json::to_value(&event).unwrap()["event"].as_str().unwrap().to_string(), event.agent_id().to_string()))
+                .collect()
+        };
+        let event = |kind: &str, agent_id: &str| (kind.to_string(), agent_id.to_string());
+        
+        // A second agent joining halves the first, which is published before it's added
+        hm.allocate_resources(hm.suggest_allocation("first", 5)).unwrap();
+        hm.allocate_resources(hm.suggest_allocation("second", 5)).unwrap();
+        assert_eq!(kinds(), [event("allocated", "first"), event("rebalanced", "first"), event("allocated", "second")]);
+        
+        // The remaining agent grows into a released share
+        hm.release_resources("first").unwrap();
+        assert_eq!(kinds(), [event("released", "first"), event("rebalanced", "second")]);
+        let AllocationEvent::Rebalanced { old, new, .. } = hm.events.recent().pop().unwrap() else {
+            panic!("expected the rebalance last");
+        };
+        assert_eq!((old.cpu_percent, new.cpu_percent), (13.5, 27.0));
+        
+        // The report keeps the recent events, and dropping the receiver unsubscribes
+        drop(events);
+        let report = hm.generate_report();
+        assert_eq!(report["allocation_events"]["recent"].as_array().unwrap().len(), 5);
+        assert_eq!(report["allocation_events"]["subscribers"], 1);
+        hm.rebalance();
+        hm.release_resources("second").unwrap();
+        assert_eq!(hm.generate_report()["allocation_events"]["subscribers"], 0);
+    }
 }
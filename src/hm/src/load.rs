@@ -0,0 +1,109 @@
+//! Synthetic load for the MCP-ZERO Hardware Manager benchmark
+//!
+//! The benchmark samples its own process, which does next to nothing when idle. A
+//! `SyntheticLoad` makes it use a chosen amount of CPU and memory instead, so a run
+//! shows whether usage beyond the limits is detected and alerted on. Worker threads
+//! stop and the buffer is freed when the load is dropped.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context, anyhow};
+
+/// Period each worker splits between spinning and sleeping
+const DUTY_CYCLE: Duration = Duration::from_millis(10);
+
+/// CPU and memory held by the benchmark process on purpose
+pub struct SyntheticLoad {
+    stop: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+    memory: Vec<u8>,
+}
+
+impl SyntheticLoad {
+    /// Start using about `cpu_percent` of CPU, where 100% is one core as in the
+    /// process's usage, and holding `memory_mb` MB of memory
+    pub fn start(cpu_percent: Option<f32>, memory_mb: Option<u32>) -> Result<Self> {
+        let cpu_percent = cpu_percent.unwrap_or(0.0);
+        if !cpu_percent.is_finite() || cpu_percent < 0.0 {
+            return Err(anyhow!("Invalid CPU load {}%", cpu_percent));
+        }
+        
+        // Allocate and write every page, so the memory is resident rather than reserved
+        let bytes = memory_mb.unwrap_or(0) as usize * 1024 * 1024;
+        let mut memory = Vec::new();
+        memory.try_reserve_exact(bytes).with_context(|| format!("Failed to allocate {} MB of memory load", bytes / (1024 * 1024)))?;
+        memory.resize(bytes, 1u8);
+        
+        // Spread the load over as many workers as it takes cores
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut load = Self { stop: stop.clone(), workers: Vec::new(), memory };
+        if cpu_percent > 0.0 {
+            let count = (cpu_percent / 100.0).ceil() as usize;
+            let busy = DUTY_CYCLE.mul_f32((cpu_percent / count as f32 / 100.0).min(1.0));
+            for index in 0..count {
+                let stop = stop.clone();
+                let worker = std::thread::Builder::new()
+                    .name(format!("mcp-hm-load-{}", index))
+                    .spawn(move || spin(&stop, busy))
+                    .context("Failed to start load worker")?;
+                load.workers.push(worker);
+            }
+        }
+        Ok(load)
+    }
+    
+    /// Number of worker threads using CPU
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+    
+    /// Memory held, in MB
+    pub fn memory_mb(&self) -> usize {
+        self.memory.len() / (1024 * 1024)
+    }
+}
+
+impl Drop for SyntheticLoad {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Spin for `busy` of every duty cycle and sleep for the rest, until stopped
+fn spin(stop: &AtomicBool, busy: Duration) {
+    while !stop.load(Ordering::Relaxed) {
+        let start = Instant::now();
+        while start.elapsed() < busy {
+            std::hint::spin_loop();
+        }
+        if let Some(idle) = DUTY_CYCLE.checked_sub(start.elapsed()) {
+            std::thread::sleep(idle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_synthetic_load() {
+        let load = SyntheticLoad::start(Some(150.0), Some(4)).unwrap();
+        assert_eq!(load.workers(), 2);
+        assert_eq!(load.memory_mb(), 4);
+        
+        // Dropping stops the workers promptly
+        let start = Instant::now();
+        drop(load);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        
+        let idle = SyntheticLoad::start(None, None).unwrap();
+        assert_eq!((idle.workers(), idle.memory_mb()), (0, 0));
+        assert!(SyntheticLoad::start(Some(-1.0), None).is_err());
+    }
+}
@@ -4,12 +4,20 @@
 //! for MCP-ZERO infrastructure.
 
 use std::path::PathBuf;
-use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use anyhow::{Result, Context};
 use clap::{Parser, Subcommand};
+use serde::Serialize;
 use sysinfo::{SystemExt, ProcessExt};
 
 // Import from the local library crate using the package name from Cargo.toml (mcp-hm -> mcp_hm)
-use mcp_hm::{HardwareManager, HMConfig, ResourceStats, Alert, AlertLevel, AlertHandler, ConsoleAlertHandler, FileAlertHandler, ResourceType};
+mod load;
+mod render;
+mod watch;
+
+use load::SyntheticLoad;
+use render::{OutputFormat, Table};
+use mcp_hm::{HardwareManager, HMConfig, ResourceStats, Alert, AlertLevel, AlertHandler, ConsoleAlertHandler, FileAlertHandler, AlertLogFormat, ResourceType, WebhookAlertHandler, read_alert_log, install_metrics_exporter, ShutdownToken, daemonize, process_running, read_pid_file, running_daemon, terminate, start_api_server, measure_sampling_cost, refresh_host, refresh_network, refresh_process, sampling_system};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -17,7 +25,11 @@ struct Cli {
     /// Path to configuration file
     #[arg(short, long, value_name = "FILE")]
     config: Option<PathBuf>,
-
+    
+    /// Output format
+    #[arg(short, long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+    
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -31,14 +43,67 @@ enum Commands {
         foreground: bool,
     },
     
+    /// Stop the background daemon
+    Stop {
+        /// Seconds to wait for it to exit
+        #[arg(short, long, default_value = "30")]
+        timeout: u64,
+    },
+    
+    /// Report whether the background daemon is running
+    Status,
+    
     /// Check current resource usage
-    Stats,
+    Stats {
+        /// Fetch the stats from a running daemon's control API instead, e.g. http://127.0.0.1:9185
+        #[arg(long, value_name = "URL")]
+        remote: Option<String>,
+    },
+    
+    /// Show live resource usage, redrawn in place until Ctrl+C
+    Watch {
+        /// Time between refreshes, such as 1s or 500ms
+        #[arg(short, long, default_value = "1s", value_parser = watch::parse_interval)]
+        interval: std::time::Duration,
+        
+        /// Don't colour usage by how close it is to its limit
+        #[arg(long)]
+        no_color: bool,
+        
+        /// Watch a running daemon through its control API instead, e.g. http://127.0.0.1:9185
+        #[arg(long, value_name = "URL")]
+        remote: Option<String>,
+    },
     
-    /// Run a resource benchmark
+    /// Run a resource benchmark; exits with status 2 if usage exceeded the limits
     Benchmark {
         /// Duration in seconds
         #[arg(short, long, default_value = "10")]
         duration: u64,
+        
+        /// Spin worker threads to use this much CPU, where 100 is one core
+        #[arg(long, value_name = "PERCENT")]
+        cpu_load: Option<f32>,
+        
+        /// Hold this much memory for the run
+        #[arg(long, value_name = "MB")]
+        memory_load: Option<u32>,
+    },
+    
+    /// Inspect the alert log
+    Alerts {
+        #[command(subcommand)]
+        command: AlertsCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum AlertsCommand {
+    /// Print logged alerts, oldest first (needs alert_log_format: json)
+    Tail {
+        /// Only alerts since this time: RFC 3339, or an age such as 30s, 15m, 2h or 1d
+        #[arg(long)]
+        since: Option<String>,
     },
 }
 
@@ -58,195 +123,778 @@ fn main() -> Result<()> {
     // Validate config
     config.validate()?;
     
-    // Print banner
-    print_banner(&config);
+    // Print banner, unless the output is for scripts
+    let output = cli.output;
+    if output == OutputFormat::Table {
+        print_banner(&config);
+    }
     
     // Process command
     match cli.command.unwrap_or(Commands::Start { foreground: true }) {
         Commands::Start { foreground } => {
+            // Detach first, as forking is only safe before any thread has started
+            let _pid_file = match foreground {
+                true => None,
+                false => Some(daemonize(&config.pid_file, config.daemon_log_path.as_deref())?),
+            };
+            
+            // Shut down cleanly on SIGINT or SIGTERM
+            let shutdown = ShutdownToken::new();
+            shutdown.install_signal_handlers()?;
+            
             if foreground {
-                run_foreground(config)
+                run_foreground(config, shutdown)
             } else {
-                run_daemon(config)
+                run_daemon(config, shutdown)
             }
         },
-        Commands::Stats => print_stats(),
-        Commands::Benchmark { duration } => run_benchmark(config, duration),
+        Commands::Stop { timeout } => stop_daemon(&config, timeout),
+        Commands::Status => print_status(&config),
+        Commands::Stats { remote: Some(url) } => print_remote_stats(&url, config.api_token.as_deref(), output),
+        Commands::Stats { remote: None } => print_stats(output),
+        Commands::Watch { interval, no_color, remote } => {
+            // Ctrl+C ends the watch cleanly, restoring the terminal
+            let shutdown = ShutdownToken::new();
+            shutdown.install_signal_handlers()?;
+            watch::watch(config, remote, interval, !no_color, &shutdown)
+        },
+        Commands::Benchmark { duration, cpu_load, memory_load } => {
+            // Ctrl+C cuts the run short, still releasing the load and reporting
+            let shutdown = ShutdownToken::new();
+            shutdown.install_signal_handlers()?;
+            
+            // Fail when a limit was exceeded, so a benchmark can gate a pipeline
+            let load = BenchmarkLoad { cpu_percent: cpu_load, memory_mb: memory_load };
+            if !run_benchmark(config, duration, load, &shutdown, output)? {
+                eprintln!("Benchmark exceeded the configured limits");
+                std::process::exit(2);
+            }
+            Ok(())
+        },
+        Commands::Alerts { command: AlertsCommand::Tail { since } } => tail_alerts(&config, since.as_deref()),
     }
 }
 
 /// Print application banner
 fn print_banner(config: &HMConfig) {
-    println!("╔═════════════════════════════════════════════╗");
-    println!("║ MCP-ZERO v9 Hardware Manager                ║");
-    println!("╠═════════════════════════════════════════════╣");
-    println!("║ Constraints:                                ║");
-    println!("║ - CPU: Max {:.1}%                           ║", config.max_cpu_percent);
-    println!("║ - Memory: Max {} MB                        ║", config.max_memory_mb);
-    println!("║ - Refresh: {} ms                          ║", config.refresh_interval_ms);
-    println!("╚═════════════════════════════════════════════╝");
-}
-
-/// Run the hardware manager in foreground
-fn run_foreground(config: HMConfig) -> Result<()> {
+    Table::new("MCP-ZERO v9 Hardware Manager")
+        .row("Constraints:", "")
+        .row("- CPU:", format!("Max {:.1}%", config.max_cpu_percent))
+        .row("- Memory:", format!("Max {} MB", config.max_memory_mb))
+        .row("- Refresh:", match config.adaptive_sampling {
+            true => format!("{}-{} ms, adaptive", config.min_refresh_interval_ms, config.max_refresh_interval_ms),
+            false => format!("{} ms", config.refresh_interval_ms),
+        })
+        .print();
+}
+
+/// How long shutdown waits for queued alerts to be delivered
+const SHUTDOWN_FLUSH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Run the hardware manager in foreground until `shutdown` is triggered
+fn run_foreground(config: HMConfig, shutdown: ShutdownToken) -> Result<()> {
     tracing::info!("Starting MCP-ZERO Hardware Manager in foreground mode");
     
+    // Serve metrics if configured
+    if let Some(listen) = &config.metrics_listen {
+        install_metrics_exporter(listen)?;
+    }
+    
     // Create hardware manager
-    let mut hm = HardwareManager::new(config);
+    let api = config.api_listen.clone().map(|listen| (listen, config.api_token.clone()));
+    let webhooks = config.alert_webhooks.clone();
+    let alert_log = FileAlertHandler::from_config(&config);
+    let final_report_path = config.final_report_path.clone();
+    let hm = Arc::new(HardwareManager::new(config));
     
     // Add console alert handler
     hm.add_alert_handler(Box::new(ConsoleAlertHandler));
     
     // Add file alert handler
-    let log_path = PathBuf::from("mcp-hm.log");
-    hm.add_alert_handler(Box::new(FileAlertHandler::new(log_path)));
+    hm.add_alert_handler(Box::new(alert_log));
     
-    // Start monitoring
-    hm.start_monitoring()?;
+    // Add configured webhook alert handlers
+    for webhook in &webhooks {
+        hm.add_alert_handler(Box::new(WebhookAlertHandler::from_config(webhook)));
+    }
+    
+    // Pick up the allocations saved before a restart, then take new ones
+    hm.restore_allocations();
+    if let Some((listen, token)) = api {
+        start_api_server(hm.clone(), &listen, token.as_deref())?;
+    }
     
     tracing::info!("Hardware manager started, press Ctrl+C to stop");
     
-    // Setup signal handler for graceful shutdown
-    setup_signal_handler();
+    // Monitor until told to shut down; the control API holds the manager, so
+    // monitoring is stopped explicitly rather than on drop
+    let report = hm.run_until(&shutdown, SHUTDOWN_FLUSH_TIMEOUT)?;
+    write_final_report(&report, final_report_path.as_deref())?;
+    
+    tracing::info!("Hardware manager stopped");
+    Ok(())
+}
+
+/// Run the hardware manager as a detached daemon until `shutdown` is triggered
+fn run_daemon(config: HMConfig, shutdown: ShutdownToken) -> Result<()> {
+    tracing::info!("Starting MCP-ZERO Hardware Manager in daemon mode");
+    
+    // Serve metrics if configured
+    if let Some(listen) = &config.metrics_listen {
+        install_metrics_exporter(listen)?;
+    }
+    
+    // Create hardware manager
+    let api = config.api_listen.clone().map(|listen| (listen, config.api_token.clone()));
+    let webhooks = config.alert_webhooks.clone();
+    let alert_log = FileAlertHandler::from_config(&config);
+    let final_report_path = config.final_report_path.clone();
+    let hm = Arc::new(HardwareManager::new(config));
+    
+    // Add file alert handler
+    hm.add_alert_handler(Box::new(alert_log));
+    
+    // Add configured webhook alert handlers
+    for webhook in &webhooks {
+        hm.add_alert_handler(Box::new(WebhookAlertHandler::from_config(webhook)));
+    }
     
-    // Block until signal received
-    wait_for_signal();
+    // Pick up the allocations saved before a restart, then take new ones
+    hm.restore_allocations();
+    if let Some((listen, token)) = api {
+        start_api_server(hm.clone(), &listen, token.as_deref())?;
+    }
     
-    tracing::info!("Shutting down hardware manager");
+    // Monitor until told to shut down
+    let report = hm.run_until(&shutdown, SHUTDOWN_FLUSH_TIMEOUT)?;
+    write_final_report(&report, final_report_path.as_deref())?;
     
+    tracing::info!("Hardware manager daemon stopped");
     Ok(())
 }
 
-/// Run the hardware manager as a daemon
-fn run_daemon(config: HMConfig) -> Result<()> {
-    tracing::info!("Starting MCP-ZERO Hardware Manager in daemon mode");
+/// Stop the background daemon, waiting up to `timeout` seconds for it to exit
+fn stop_daemon(config: &HMConfig, timeout: u64) -> Result<()> {
+    let Some(pid) = running_daemon(&config.pid_file) else {
+        println!("Hardware manager is not running");
+        return Ok(());
+    };
     
-    // Simple daemon implementation - in production would use a proper daemon framework
-    std::thread::spawn(move || {
-        // Create hardware manager
-        let mut hm = HardwareManager::new(config);
-        
-        // Add file alert handler
-        let log_path = PathBuf::from("mcp-hm.log");
-        hm.add_alert_handler(Box::new(FileAlertHandler::new(log_path)));
-        
-        // Start monitoring
-        if let Err(e) = hm.start_monitoring() {
-            tracing::error!("Failed to start monitoring: {}", e);
-            return;
+    terminate(pid)?;
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(timeout);
+    while process_running(pid) {
+        if std::time::Instant::now() >= deadline {
+            return Err(anyhow::anyhow!("Hardware manager (pid {}) didn't stop within {}s", pid, timeout));
         }
-        
-        // Setup signal handler
-        setup_signal_handler();
-        
-        // Block until signal
-        wait_for_signal();
-    });
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
     
-    println!("Hardware manager daemon started");
+    // The daemon removes its PID file on the way out, unless it died instead
+    if read_pid_file(&config.pid_file) == Some(pid) {
+        let _ = std::fs::remove_file(&config.pid_file);
+    }
+    println!("Hardware manager stopped (pid {})", pid);
+    Ok(())
+}
+
+/// Report whether the background daemon is running, with its stats if the control
+/// API is enabled; exits with status 3 when it isn't running, as init scripts expect
+fn print_status(config: &HMConfig) -> Result<()> {
+    let Some(pid) = running_daemon(&config.pid_file) else {
+        println!("Hardware manager is not running");
+        std::process::exit(3);
+    };
+    println!("Hardware manager is running (pid {})", pid);
+    
+    if let Some(listen) = &config.api_listen {
+        // Reach an API listening on every interface through loopback
+        let mut addr: std::net::SocketAddr = listen.parse()?;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(match addr {
+                std::net::SocketAddr::V4(_) => std::net::Ipv4Addr::LOCALHOST.into(),
+                std::net::SocketAddr::V6(_) => std::net::Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+        if let Err(e) = print_remote_stats(&format!("http://{}", addr), config.api_token.as_deref(), OutputFormat::Table) {
+            tracing::warn!("Daemon is running but its control API didn't answer: {:#}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Write the report taken at shutdown to `path`, or print it
+fn write_final_report(report: &serde_json::Value, path: Option<&std::path::Path>) -> Result<()> {
+    let report = serde_json::to_string_pretty(report)?;
+    match path {
+        Some(path) => {
+            std::fs::write(path, report)
+                .with_context(|| format!("Failed to write final report to {}", path.display()))?;
+            tracing::info!("Final report written to {}", path.display());
+        },
+        None => println!("{}", report),
+    }
+    Ok(())
+}
+
+/// Fetch the resource stats of a running daemon from its control API
+fn fetch_remote_stats(url: &str, token: Option<&str>) -> Result<ResourceStats> {
+    tracing::info!("Fetching resource stats from {}", url);
+    
+    let mut request = ureq::get(&format!("{}/stats", url.trim_end_matches('/')))
+        .timeout(std::time::Duration::from_secs(5));
+    if let Some(token) = token {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            return Err(anyhow::anyhow!("{} returned {}: {}", url, status, body));
+        },
+        Err(e) => return Err(anyhow::anyhow!("Failed to reach {}: {}", url, e)),
+    };
+    Ok(serde_json::from_str(&response.into_string()?)?)
+}
+
+/// Print the resource stats of a running daemon, fetched from its control API
+fn print_remote_stats(url: &str, token: Option<&str>, output: OutputFormat) -> Result<()> {
+    let stats = fetch_remote_stats(url, token)?;
+    if output != OutputFormat::Table {
+        return output.print(&stats);
+    }
     
+    Table::new("MCP-ZERO Hardware Stats (remote)")
+        .row("CPU Usage:", format!("{:.2}%", stats.cpu_percent))
+        .row("Memory Usage:", format!("{} MB", stats.memory_mb))
+        .section()
+        .row("Disk IO:", format!("{:.2} MB/s ({} B read, {} B written)", stats.disk_mbps(), stats.disk_read_bytes, stats.disk_written_bytes))
+        .row("Network IO:", format!("{:.2} MB/s ({} B rx, {} B tx)", stats.net_mbps(), stats.net_rx_bytes, stats.net_tx_bytes))
+        .section()
+        .row("Swap:", format!("{} MB", stats.swap_mb))
+        .row("CPU Cores:", format_cores(&stats.cpu_per_core))
+        .row("Temperature:", format_temperature(stats.temperature_celsius))
+        .row("Sampled:", stats.timestamp.to_rfc3339())
+        .print();
     Ok(())
 }
 
+/// Interval the stats command samples over (ms)
+const STATS_INTERVAL_MS: u64 = 500;
+
+/// Process the stats command sampled
+#[derive(Serialize)]
+struct ProcessInfo {
+    pid: u32,
+    name: String,
+    virtual_memory_mb: u64,
+    runtime_secs: u64,
+}
+
+/// Output of the stats command
+#[derive(Serialize)]
+struct StatsOutput {
+    #[serde(flatten)]
+    stats: ResourceStats,
+    process: ProcessInfo,
+}
+
 /// Print current resource stats
-fn print_stats() -> Result<()> {
+fn print_stats(output: OutputFormat) -> Result<()> {
     tracing::info!("Fetching current resource stats");
     
-    // Create a system info collector
-    let mut system = sysinfo::System::new_all();
-    system.refresh_all();
-    
     // Get own process ID
-    let pid = std::process::id() as usize;
+    let pid = std::process::id();
+    
+    // Create a system info collector loaded with this process only
+    let mut system = sampling_system();
+    let mut host = ResourceStats::default();
+    refresh_process(&mut system, pid);
+    refresh_host(&mut system, &mut host);
+    
+    // Sample again after an interval so usage and IO rates cover it
+    let interval = std::time::Instant::now();
+    std::thread::sleep(std::time::Duration::from_millis(STATS_INTERVAL_MS));
+    refresh_process(&mut system, pid);
+    let (net_rx, net_tx) = refresh_network(&mut system);
+    refresh_host(&mut system, &mut host);
     
     // Get process info
-    let process = system.process(sysinfo::Pid::from(pid))
+    let process = system.process(sysinfo::Pid::from(pid as usize))
         .ok_or_else(|| anyhow::anyhow!("Failed to get process info"))?;
+    let disk = process.disk_usage();
+    let sample = StatsOutput {
+        stats: ResourceStats {
+            cpu_percent: process.cpu_usage(),
+            memory_mb: (process.memory() / (1024 * 1024)) as u32,
+            disk_read_bytes: disk.read_bytes,
+            disk_written_bytes: disk.written_bytes,
+            net_rx_bytes: net_rx,
+            net_tx_bytes: net_tx,
+            interval_ms: interval.elapsed().as_millis() as u64,
+            timestamp: chrono::Utc::now(),
+            ..host
+        },
+        process: ProcessInfo {
+            pid,
+            name: process.name().to_string(),
+            virtual_memory_mb: process.virtual_memory() / (1024 * 1024),
+            runtime_secs: process.run_time(),
+        },
+    };
+    if output != OutputFormat::Table {
+        return output.print(&sample);
+    }
     
-    // Print stats
-    println!("╔═════════════════════════════════════════════╗");
-    println!("║ MCP-ZERO Hardware Stats                     ║");
-    println!("╠═════════════════════════════════════════════╣");
-    println!("║ CPU Usage:    {:.2}%                        ║", process.cpu_usage());
-    println!("║ Memory Usage: {} MB                        ║", process.memory() / (1024 * 1024));
-    println!("║ Virtual Mem:  {} MB                        ║", process.virtual_memory() / (1024 * 1024));
-    println!("║ Runtime:      {}                   ║", format_duration(process.run_time()));
-    println!("╚═════════════════════════════════════════════╝");
-    
+    let (stats, process) = (&sample.stats, &sample.process);
+    Table::new("MCP-ZERO Hardware Stats")
+        .row("CPU Usage:", format!("{:.2}%", stats.cpu_percent))
+        .row("Memory Usage:", format!("{} MB", stats.memory_mb))
+        .row("Virtual Mem:", format!("{} MB", process.virtual_memory_mb))
+        .section()
+        .row("Disk IO:", format!("{:.2} MB/s ({} B read, {} B written)", stats.disk_mbps(), stats.disk_read_bytes, stats.disk_written_bytes))
+        .row("Network IO:", format!("{:.2} MB/s ({} B rx, {} B tx)", stats.net_mbps(), stats.net_rx_bytes, stats.net_tx_bytes))
+        .section()
+        .row("Swap:", format!("{} MB", stats.swap_mb))
+        .row("CPU Cores:", format_cores(&stats.cpu_per_core))
+        .row("Temperature:", format_temperature(stats.temperature_celsius))
+        .row("Runtime:", format_duration(process.runtime_secs))
+        .print();
     Ok(())
 }
 
-/// Run a resource benchmark
-fn run_benchmark(config: HMConfig, duration: u64) -> Result<()> {
+/// Samples taken each way when measuring the sampling cost
+const SAMPLING_COST_ITERATIONS: u32 = 20;
+
+/// Limits a benchmark is judged against
+#[derive(Serialize)]
+struct BenchmarkLimits {
+    cpu_percent: f32,
+    memory_mb: u32,
+}
+
+/// Synthetic load applied during a benchmark
+#[derive(Serialize)]
+struct BenchmarkLoad {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_percent: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory_mb: Option<u32>,
+}
+
+/// Average cost of one sample, refreshing everything or only this process
+#[derive(Serialize)]
+struct BenchmarkSamplingCost {
+    full_ms: f64,
+    targeted_ms: f64,
+    reduction: f64,
+}
+
+/// Output of the benchmark command
+#[derive(Serialize)]
+struct BenchmarkReport {
+    duration_secs: u64,
+    interval_ms: u64,
+    limits: BenchmarkLimits,
+    load: BenchmarkLoad,
+    sampling_cost: BenchmarkSamplingCost,
+    
+    /// Run without the metrics endpoint
+    baseline: BenchmarkRun,
+    
+    /// Run with the metrics endpoint scraped every sample, if it's configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics_endpoint: Option<BenchmarkRun>,
+    
+    /// Alerts the hardware manager raised while watching the runs
+    alerts: Vec<Alert>,
+    
+    /// Whether the benchmark was stopped before its duration was up
+    interrupted: bool,
+    
+    /// Whether every run stayed within the limits
+    within_limits: bool,
+}
+
+/// Alert handler keeping the alerts raised during a benchmark
+struct RecordingAlertHandler(Arc<Mutex<Vec<Alert>>>);
+
+impl AlertHandler for RecordingAlertHandler {
+    fn handle(&self, alert: &Alert) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).push(alert.clone());
+    }
+    
+    fn name(&self) -> &str {
+        "benchmark"
+    }
+}
+
+/// Run a resource benchmark, returning whether usage stayed within the limits
+///
+/// The process samples itself every refresh interval while carrying `load`, and a
+/// hardware manager monitors it alongside, so the report shows the alerts usage over
+/// the limits raises. With `metrics_listen` configured, the benchmark runs a second
+/// time with the metrics endpoint serving and scraped on every sample, and reports the
+/// endpoint's overhead. Triggering `shutdown` ends the benchmark early, reporting the
+/// samples taken so far.
+fn run_benchmark(config: HMConfig, duration: u64, load: BenchmarkLoad, shutdown: &ShutdownToken, output: OutputFormat) -> Result<bool> {
     tracing::info!("Running resource benchmark for {} seconds", duration);
+    let table = output == OutputFormat::Table;
+    
+    if table {
+        println!("Starting benchmark with constraints:");
+        println!("  - CPU: Max {:.1}%", config.max_cpu_percent);
+        println!("  - Memory: Max {} MB", config.max_memory_mb);
+        println!("  - Duration: {} seconds", duration);
+        println!("  - Interval: {} ms", config.refresh_interval_ms);
+        if let Some(cpu_percent) = load.cpu_percent {
+            println!("  - CPU load: {:.1}%", cpu_percent);
+        }
+        if let Some(memory_mb) = load.memory_mb {
+            println!("  - Memory load: {} MB", memory_mb);
+        }
+        println!();
+    }
     
-    println!("Starting benchmark with constraints:");
-    println!("  - CPU: Max {:.1}%", config.max_cpu_percent);
-    println!("  - Memory: Max {} MB", config.max_memory_mb);
-    println!("  - Duration: {} seconds", duration);
-    println!();
+    // Compare a full refresh with the targeted one the monitoring loop does
+    let cost = measure_sampling_cost(&[std::process::id()], SAMPLING_COST_ITERATIONS);
+    if table {
+        println!("Sampling cost per tick over {} samples:", SAMPLING_COST_ITERATIONS);
+        println!("    - Full refresh:     {:.3} ms", cost.full.as_secs_f64() * 1000.0);
+        println!("    - Targeted refresh: {:.3} ms", cost.targeted.as_secs_f64() * 1000.0);
+        println!("    - Reduction:        {:.1}%", cost.reduction() * 100.0);
+        println!();
+    }
     
+    // Held until the runs are over, and released on any early return
+    let synthetic = SyntheticLoad::start(load.cpu_percent, load.memory_mb)?;
+    if table && (synthetic.workers() > 0 || synthetic.memory_mb() > 0) {
+        println!("Applying synthetic load: {} CPU worker(s), {} MB held", synthetic.workers(), synthetic.memory_mb());
+        println!();
+    }
+    
+    // Watch the runs as the daemon would, keeping the alerts raised
+    let alerts = Arc::new(Mutex::new(Vec::new()));
+    let hm = HardwareManager::new(config.clone());
+    hm.add_alert_handler(Box::new(RecordingAlertHandler(alerts.clone())));
+    hm.start_monitoring()?;
+    
+    let baseline = run_benchmark_phase(&config, duration, None, shutdown, table).summarize(&config);
+    if table {
+        println!("\n\nBenchmark complete!");
+        print_benchmark_results(&config, &baseline);
+    }
+    
+    // Measure the metrics endpoint against the baseline
+    let exporting = match &config.metrics_listen {
+        Some(_) if shutdown.is_triggered() => None,
+        None => {
+            if table {
+                println!("\nMetrics endpoint disabled; set metrics_listen to measure its overhead");
+            }
+            None
+        },
+        Some(listen) => {
+            let addr = install_metrics_exporter(listen)?;
+            if table {
+                println!("\nRepeating with the metrics endpoint on {} scraped every sample", addr);
+            }
+            let exporting = run_benchmark_phase(&config, duration, Some(addr), shutdown, table).summarize(&config);
+            if table {
+                println!("\n");
+                print_benchmark_results(&config, &exporting);
+                print_metrics_overhead(&baseline, &exporting);
+            }
+            Some(exporting)
+        },
+    };
+    
+    // Release the load before the hardware manager stops watching
+    drop(synthetic);
+    hm.stop_monitoring();
+    let alerts = std::mem::take(&mut *alerts.lock().unwrap_or_else(|e| e.into_inner()));
+    let interrupted = shutdown.is_triggered();
+    if table {
+        print_benchmark_alerts(&alerts);
+        if interrupted {
+            println!("\nBenchmark interrupted; results cover the samples taken before it stopped");
+        }
+    }
+    
+    let within_limits = baseline.within_limits() && exporting.as_ref().is_none_or(BenchmarkRun::within_limits);
+    output.print(&BenchmarkReport {
+        duration_secs: duration,
+        interval_ms: config.refresh_interval_ms,
+        limits: BenchmarkLimits { cpu_percent: config.max_cpu_percent, memory_mb: config.max_memory_mb },
+        load,
+        sampling_cost: BenchmarkSamplingCost {
+            full_ms: cost.full.as_secs_f64() * 1000.0,
+            targeted_ms: cost.targeted.as_secs_f64() * 1000.0,
+            reduction: cost.reduction(),
+        },
+        baseline,
+        metrics_endpoint: exporting,
+        alerts,
+        interrupted,
+        within_limits,
+    })?;
+    Ok(within_limits)
+}
+
+/// Print the alerts raised during a benchmark
+fn print_benchmark_alerts(alerts: &[Alert]) {
+    if alerts.is_empty() {
+        println!("\nNo alerts raised");
+        return;
+    }
+    println!("\nAlerts raised ({}):", alerts.len());
+    for alert in alerts {
+        println!("    - {}", alert.format());
+    }
+}
+
+/// Print how much the metrics endpoint added to the baseline
+fn print_metrics_overhead(baseline: &BenchmarkRun, exporting: &BenchmarkRun) {
+    if baseline.cpu_samples.is_empty() || exporting.cpu_samples.is_empty() {
+        return;
+    }
+    println!("\nMetrics endpoint overhead:");
+    println!("    - CPU:    {:+.2}%", exporting.cpu_average - baseline.cpu_average);
+    println!("    - Memory: {:+} MB", exporting.memory_average_mb as i64 - baseline.memory_average_mb as i64);
+    if !exporting.scrape_ms.is_empty() {
+        let scrape_ms = exporting.scrape_ms.iter().sum::<f64>() / exporting.scrape_ms.len() as f64;
+        println!("    - Scrape: {:.2} ms average over {} scrapes", scrape_ms, exporting.scrape_ms.len());
+    }
+}
+
+/// Samples taken during one benchmark run
+#[derive(Default)]
+struct BenchmarkSamples {
+    cpu: Vec<f32>,
+    memory: Vec<u64>,
+    intervals: Vec<std::time::Duration>,
+    scrapes: Vec<std::time::Duration>,
+}
+
+/// Samples and statistics of one benchmark run
+#[derive(Serialize)]
+struct BenchmarkRun {
+    cpu_samples: Vec<f32>,
+    memory_samples_mb: Vec<u64>,
+    cpu_average: f32,
+    cpu_max: f32,
+    cpu_p50: f32,
+    cpu_p95: f32,
+    cpu_p99: f32,
+    memory_average_mb: u64,
+    memory_max_mb: u64,
+    memory_p50_mb: u64,
+    memory_p95_mb: u64,
+    memory_p99_mb: u64,
+    
+    /// Average and largest gap between a sample's interval and the refresh interval
+    jitter_ms: f64,
+    max_jitter_ms: f64,
+    
+    cpu_within_limit: bool,
+    memory_within_limit: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    scrape_ms: Vec<f64>,
+}
+
+impl BenchmarkSamples {
+    /// Compute the run's statistics and judge them against the configured limits
+    fn summarize(self, config: &HMConfig) -> BenchmarkRun {
+        let cpu_max = self.cpu.iter().copied().fold(0.0, f32::max);
+        let memory_max_mb = self.memory.iter().copied().max().unwrap_or(0);
+        let mut cpu = self.cpu.clone();
+        let mut memory = self.memory.clone();
+        cpu.sort_by(f32::total_cmp);
+        memory.sort_unstable();
+        
+        let interval = std::time::Duration::from_millis(config.refresh_interval_ms);
+        let jitter: Vec<f64> = self.intervals.iter()
+            .map(|&actual| actual.abs_diff(interval).as_secs_f64() * 1000.0)
+            .collect();
+        BenchmarkRun {
+            cpu_average: self.cpu.iter().sum::<f32>() / self.cpu.len().max(1) as f32,
+            memory_average_mb: self.memory.iter().sum::<u64>() / self.memory.len().max(1) as u64,
+            cpu_max,
+            cpu_p50: percentile(&cpu, 50.0).unwrap_or_default(),
+            cpu_p95: percentile(&cpu, 95.0).unwrap_or_default(),
+            cpu_p99: percentile(&cpu, 99.0).unwrap_or_default(),
+            memory_max_mb,
+            memory_p50_mb: percentile(&memory, 50.0).unwrap_or_default(),
+            memory_p95_mb: percentile(&memory, 95.0).unwrap_or_default(),
+            memory_p99_mb: percentile(&memory, 99.0).unwrap_or_default(),
+            jitter_ms: jitter.iter().sum::<f64>() / jitter.len().max(1) as f64,
+            max_jitter_ms: jitter.iter().copied().fold(0.0, f64::max),
+            cpu_within_limit: cpu_max <= config.max_cpu_percent,
+            memory_within_limit: memory_max_mb <= config.max_memory_mb as u64,
+            scrape_ms: self.scrapes.iter().map(|scrape| scrape.as_secs_f64() * 1000.0).collect(),
+            cpu_samples: self.cpu,
+            memory_samples_mb: self.memory,
+        }
+    }
+}
+
+/// The `percentile` (0-100) of sorted `values`, by nearest rank as the tracker
+/// computes it
+fn percentile<T: Copy>(values: &[T], percentile: f32) -> Option<T> {
+    let rank = ((percentile.clamp(0.0, 100.0) / 100.0) * values.len() as f32).ceil() as usize;
+    values.get(rank.clamp(1, values.len().max(1)) - 1).copied()
+}
+
+impl BenchmarkRun {
+    fn within_limits(&self) -> bool {
+        self.cpu_within_limit && self.memory_within_limit
+    }
+}
+
+/// Sample this process every refresh interval for `duration` seconds or until
+/// `shutdown`, publishing each sample as metrics and scraping them from `scrape` if
+/// given; `progress` prints each sample as it's taken
+fn run_benchmark_phase(config: &HMConfig, duration: u64, scrape: Option<std::net::SocketAddr>, shutdown: &ShutdownToken, progress: bool) -> BenchmarkSamples {
     // Start time
     let start = std::time::Instant::now();
     
     // Create system info collector
-    let mut system = sysinfo::System::new_all();
+    let mut system = sampling_system();
     
     // Storage for samples
-    let mut cpu_samples = Vec::new();
-    let mut memory_samples = Vec::new();
+    let mut samples = BenchmarkSamples::default();
     
     // Get own process ID
-    let pid = std::process::id() as usize;
+    let pid = std::process::id();
     
-    // Sampling loop
+    // Sampling loop, timing the intervals between samples
+    let interval = std::time::Duration::from_millis(config.refresh_interval_ms);
+    let mut last_sample: Option<std::time::Instant> = None;
     while start.elapsed() < std::time::Duration::from_secs(duration) {
-        // Refresh system info
-        system.refresh_all();
+        // Refresh this process only
+        refresh_process(&mut system, pid);
+        if let Some(last) = last_sample.replace(std::time::Instant::now()) {
+            samples.intervals.push(last.elapsed());
+        }
         
         // Get process info
-        if let Some(process) = system.process(sysinfo::Pid::from(pid)) {
+        if let Some(process) = system.process(sysinfo::Pid::from(pid as usize)) {
             let cpu = process.cpu_usage();
             let memory = process.memory() / (1024 * 1024); // Convert to MB
             
-            cpu_samples.push(cpu);
-            memory_samples.push(memory);
+            samples.cpu.push(cpu);
+            samples.memory.push(memory);
+            metrics::gauge!("mcp.hm.cpu_usage", cpu as f64);
+            metrics::gauge!("mcp.hm.memory_usage", memory as f64);
             
-            print!("\rCPU: {:.2}%, Memory: {} MB", cpu, memory);
-            std::io::Write::flush(&mut std::io::stdout()).ok();
+            if progress {
+                print!("\rCPU: {:.2}%, Memory: {} MB", cpu, memory);
+                std::io::Write::flush(&mut std::io::stdout()).ok();
+            }
+        }
+        
+        // Scrape the metrics endpoint as Prometheus would
+        if let Some(addr) = scrape {
+            let scrape_start = std::time::Instant::now();
+            match scrape_metrics(addr) {
+                Ok(_) => samples.scrapes.push(scrape_start.elapsed()),
+                Err(e) => tracing::warn!("Failed to scrape metrics: {}", e),
+            }
         }
         
-        // Sleep for a bit
-        std::thread::sleep(std::time::Duration::from_millis(500));
+        // Sleep until the next sample, stopping early on shutdown
+        if shutdown.wait_timeout(interval) {
+            break;
+        }
     }
     
-    println!("\n\nBenchmark complete!");
+    samples
+}
+
+/// Fetch /metrics over plain HTTP
+fn scrape_metrics(addr: std::net::SocketAddr) -> std::io::Result<String> {
+    use std::io::{Read, Write};
+    
+    let mut stream = std::net::TcpStream::connect_timeout(&addr, std::time::Duration::from_secs(1))?;
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(1)))?;
+    write!(stream, "GET /metrics HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", addr)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+/// Print the statistics of a benchmark run
+fn print_benchmark_results(config: &HMConfig, run: &BenchmarkRun) {
+    if run.cpu_samples.is_empty() || run.memory_samples_mb.is_empty() {
+        return;
+    }
     
-    // Calculate statistics
-    if !cpu_samples.is_empty() && !memory_samples.is_empty() {
-        let avg_cpu = cpu_samples.iter().sum::<f32>() / cpu_samples.len() as f32;
-        let max_cpu = *cpu_samples.iter().max_by(|a, b| a.partial_cmp(b).unwrap()).unwrap();
-        
-        let avg_memory = memory_samples.iter().sum::<u64>() / memory_samples.len() as u64;
-        let max_memory = *memory_samples.iter().max().unwrap();
-        
-        println!("\nResults:");
-        println!("  CPU Usage:");
-        println!("    - Average: {:.2}%", avg_cpu);
-        println!("    - Maximum: {:.2}%", max_cpu);
-        println!("    - Limit:   {:.2}%", config.max_cpu_percent);
-        println!("    - Status:  {}", if max_cpu <= config.max_cpu_percent { "WITHIN LIMIT" } else { "EXCEEDED LIMIT" });
-        
-        println!("  Memory Usage:");
-        println!("    - Average: {} MB", avg_memory);
-        println!("    - Maximum: {} MB", max_memory);
-        println!("    - Limit:   {} MB", config.max_memory_mb);
-        println!("    - Status:  {}", if max_memory <= config.max_memory_mb as u64 { "WITHIN LIMIT" } else { "EXCEEDED LIMIT" });
+    println!("\nResults:");
+    println!("  CPU Usage:");
+    println!("    - Average: {:.2}%", run.cpu_average);
+    println!("    - Maximum: {:.2}%", run.cpu_max);
+    println!("    - p50/p95/p99: {:.2}% / {:.2}% / {:.2}%", run.cpu_p50, run.cpu_p95, run.cpu_p99);
+    println!("    - Limit:   {:.2}%", config.max_cpu_percent);
+    println!("    - Status:  {}", if run.cpu_within_limit { "WITHIN LIMIT" } else { "EXCEEDED LIMIT" });
+    
+    println!("  Memory Usage:");
+    println!("    - Average: {} MB", run.memory_average_mb);
+    println!("    - Maximum: {} MB", run.memory_max_mb);
+    println!("    - p50/p95/p99: {} MB / {} MB / {} MB", run.memory_p50_mb, run.memory_p95_mb, run.memory_p99_mb);
+    println!("    - Limit:   {} MB", config.max_memory_mb);
+    println!("    - Status:  {}", if run.memory_within_limit { "WITHIN LIMIT" } else { "EXCEEDED LIMIT" });
+    
+    println!("  Sampling every {} ms:", config.refresh_interval_ms);
+    println!("    - Jitter:  {:.2} ms average, {:.2} ms maximum", run.jitter_ms, run.max_jitter_ms);
+}
+
+/// Print the alerts logged since `since`
+fn tail_alerts(config: &HMConfig, since: Option<&str>) -> Result<()> {
+    let since = since.map(parse_since).transpose()?;
+    if config.alert_log_format != AlertLogFormat::Json {
+        tracing::warn!("The alert log is written as text; only JSON lines can be read back (set alert_log_format: json)");
     }
     
+    let events = read_alert_log(&config.alert_log_path, since)
+        .with_context(|| format!("Failed to read alert log {}", config.alert_log_path.display()))?;
+    for event in &events {
+        println!("{}", event.format());
+    }
     Ok(())
 }
 
+/// Parse an RFC 3339 time, or an age such as 15m counted back from now
+fn parse_since(since: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(since) {
+        return Ok(time.with_timezone(&chrono::Utc));
+    }
+    
+    let invalid = || anyhow::anyhow!("Invalid --since {}: expected RFC 3339 or an age such as 15m", since);
+    let split = since.len().checked_sub(1).filter(|&split| since.is_char_boundary(split)).ok_or_else(invalid)?;
+    let (amount, unit) = since.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    let age = match unit {
+        "s" => chrono::Duration::seconds(amount),
+        "m" => chrono::Duration::minutes(amount),
+        "h" => chrono::Duration::hours(amount),
+        "d" => chrono::Duration::days(amount),
+        _ => return Err(invalid()),
+    };
+    Ok(chrono::Utc::now() - age)
+}
+
+/// Per-core CPU usage, e.g. "12% 3% 40% 7%"
+fn format_cores(cpu_per_core: &[f32]) -> String {
+    if cpu_per_core.is_empty() {
+        return "unknown".to_string();
+    }
+    cpu_per_core.iter().map(|usage| format!("{:.0}%", usage)).collect::<Vec<_>>().join(" ")
+}
+
+/// Package temperature, or "unknown" without a sensor
+fn format_temperature(temperature_celsius: Option<f32>) -> String {
+    temperature_celsius.map_or("unknown".to_string(), |temperature| format!("{:.1}°C", temperature))
+}
+
 /// Format duration in seconds to a human-readable string
 fn format_duration(seconds: u64) -> String {
     let hours = seconds / 3600;
@@ -261,44 +909,3 @@ fn format_duration(seconds: u64) -> String {
         format!("{}s", secs)
     }
 }
-
-/// Setup signal handler for graceful shutdown
-fn setup_signal_handler() {
-    // In a real implementation, would use proper signal handling libraries
-    // This is simplified for demonstration
-    #[cfg(unix)]
-    {
-        use std::sync::atomic::{AtomicBool, Ordering};
-        
-        static SHUTDOWN: AtomicBool = AtomicBool::new(false);
-        
-        unsafe {
-            libc::signal(libc::SIGINT, handle_signal as usize);
-            libc::signal(libc::SIGTERM, handle_signal as usize);
-        }
-        
-        extern "C" fn handle_signal(_: i32) {
-            SHUTDOWN.store(true, Ordering::SeqCst);
-        }
-    }
-}
-
-/// Wait for shutdown signal
-fn wait_for_signal() {
-    #[cfg(unix)]
-    {
-        use std::sync::atomic::{AtomicBool, Ordering};
-        
-        static SHUTDOWN: AtomicBool = AtomicBool::new(false);
-        
-        while !SHUTDOWN.load(Ordering::SeqCst) {
-            std::thread::sleep(std::time::Duration::from_millis(100));
-        }
-    }
-    
-    #[cfg(not(unix))]
-    {
-        // Just sleep for demo purposes
-        std::thread::sleep(std::time::Duration::from_secs(3600));
-    }
-}
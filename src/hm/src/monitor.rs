@@ -0,0 +1,101 @@
+//! Monitoring thread control for MCP-ZERO Hardware Manager
+//!
+//! Lets the owner of the monitoring thread stop it and wait for it to exit.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// State shared between the monitoring thread and its handles
+#[derive(Debug, Default)]
+pub(crate) struct MonitorSignal {
+    /// Whether the thread should exit
+    stopped: Mutex<bool>,
+    wake: Condvar,
+    
+    /// Whether the thread has exited
+    finished: Mutex<bool>,
+    done: Condvar,
+}
+
+impl MonitorSignal {
+    /// Sleep for `timeout` unless stopped first; returns whether the thread should exit
+    pub(crate) fn wait(&self, timeout: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap_or_else(|e| e.into_inner());
+        let (stopped, _) = self.wake.wait_timeout_while(stopped, timeout, |stopped| !*stopped)
+            .unwrap_or_else(|e| e.into_inner());
+        *stopped
+    }
+}
+
+/// Marks the monitoring thread finished when it exits, even by panicking
+pub(crate) struct FinishGuard(pub(crate) Arc<MonitorSignal>);
+
+impl Drop for FinishGuard {
+    fn drop(&mut self) {
+        *self.0.finished.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.0.done.notify_all();
+    }
+}
+
+/// Handle to a running monitoring thread
+///
+/// Dropping the handle leaves the thread running; it stops when told to or when its
+/// HardwareManager is dropped.
+#[derive(Debug, Clone)]
+pub struct MonitorHandle {
+    signal: Arc<MonitorSignal>,
+    thread: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl MonitorHandle {
+    pub(crate) fn new(signal: Arc<MonitorSignal>, thread: JoinHandle<()>) -> Self {
+        Self { signal, thread: Arc::new(Mutex::new(Some(thread))) }
+    }
+    
+    /// Tell the thread to exit; it does so without waiting out its refresh interval
+    pub fn stop(&self) {
+        *self.signal.stopped.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.signal.wake.notify_all();
+    }
+    
+    /// Whether the thread has been told to stop
+    pub fn is_stopped(&self) -> bool {
+        *self.signal.stopped.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Whether the thread is still running
+    pub fn is_running(&self) -> bool {
+        !*self.signal.finished.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Wait up to `timeout` for the thread to exit; returns whether it did
+    pub fn join(&self, timeout: Duration) -> bool {
+        let finished = self.signal.finished.lock().unwrap_or_else(|e| e.into_inner());
+        let (finished, _) = self.signal.done.wait_timeout_while(finished, timeout, |finished| !*finished)
+            .unwrap_or_else(|e| e.into_inner());
+        if !*finished {
+            return false;
+        }
+        drop(finished);
+        
+        self.reap();
+        true
+    }
+    
+    /// Stop the thread and wait for it to exit, however long a sample takes
+    pub(crate) fn stop_and_wait(&self) {
+        self.stop();
+        self.reap();
+    }
+    
+    /// Join the thread, reporting a panic
+    fn reap(&self) {
+        let thread = self.thread.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(thread) = thread {
+            if thread.join().is_err() {
+                tracing::error!("Hardware monitoring thread panicked");
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Output rendering for the MCP-ZERO Hardware Manager CLI
+//!
+//! Commands print either a boxed table for people or the same data serialized as JSON
+//! or YAML for scripts.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Output format of the CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Boxed tables
+    #[default]
+    Table,
+    /// Pretty-printed JSON
+    Json,
+    /// YAML
+    Yaml,
+}
+
+impl OutputFormat {
+    /// Print `value` serialized in this format; tables are rendered by the caller
+    pub fn print<T: Serialize>(self, value: &T) -> Result<()> {
+        match self {
+            OutputFormat::Table => {},
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        }
+        Ok(())
+    }
+}
+
+/// Boxed table of labelled values, sized to its contents
+pub struct Table {
+    title: String,
+    sections: Vec<Vec<(String, String)>>,
+}
+
+impl Table {
+    /// Create a table with a title row
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { title: title.into(), sections: vec![Vec::new()] }
+    }
+    
+    /// Add a row to the current section
+    pub fn row(mut self, label: impl Into<String>, value: impl ToString) -> Self {
+        self.sections.last_mut().unwrap().push((label.into(), value.to_string()));
+        self
+    }
+    
+    /// Start a new section, drawn below a divider
+    pub fn section(mut self) -> Self {
+        self.sections.push(Vec::new());
+        self
+    }
+    
+    /// Render the table as lines
+    pub fn render(&self) -> Vec<String> {
+        let rows = || self.sections.iter().flatten();
+        let label_width = rows().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+        let width = rows()
+            .map(|(_, value)| label_width + 1 + value.chars().count())
+            .chain([self.title.chars().count()])
+            .max()
+            .unwrap_or(0);
+        
+        let border = |left: &str, right: &str| format!("{}{}{}", left, "═".repeat(width + 2), right);
+        let line = |text: String| {
+            let padding = width - text.chars().count();
+            format!("║ {}{} ║", text, " ".repeat(padding))
+        };
+        
+        let mut lines = vec![border("╔", "╗"), line(self.title.clone())];
+        for section in self.sections.iter().filter(|section| !section.is_empty()) {
+            lines.push(border("╠", "╣"));
+            for (label, value) in section {
+                lines.push(line(format!("{:<label_width$} {}", label, value)));
+            }
+        }
+        lines.push(border("╚", "╝"));
+        lines
+    }
+    
+    /// Print the table
+    pub fn print(&self) {
+        for line in self.render() {
+            println!("{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_table_alignment() {
+        let table = Table::new("MCP-ZERO Hardware Stats")
+            .row("CPU Usage:", "1.50%")
+            .row("Memory Usage:", "12 MB")
+            .section()
+            .row("Disk IO:", "0.00 MB/s (0 B read, 123456789 B written)");
+        let lines = table.render();
+        
+        // Every line is as wide as the widest row needs
+        let widths: Vec<usize> = lines.iter().map(|line| line.chars().count()).collect();
+        assert!(widths.iter().all(|&width| width == widths[0]), "ragged table: {:#?}", lines);
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[3], format!("║ {:<13} {:<41} ║", "CPU Usage:", "1.50%"));
+        assert!(lines[6].ends_with("123456789 B written) ║"));
+    }
+}
@@ -3,11 +3,16 @@
 //! Handles resource tracking, limits, and allocation strategies for
 //! maintaining strict hardware constraints.
 
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::Duration;
 use serde::{Serialize, Deserialize};
+use crate::alert::AlertLevel;
+
+/// Bytes in a megabyte
+const BYTES_PER_MB: f32 = 1024.0 * 1024.0;
 
 /// Resource type enum
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum ResourceType {
     /// CPU resource
     CPU,
@@ -17,10 +22,16 @@ pub enum ResourceType {
     Storage,
     /// Network bandwidth
     Network,
+    /// Agent process lifetime
+    Process,
+    /// Swap in use on the host
+    Swap,
+    /// CPU package temperature
+    Temperature,
 }
 
 /// Resource statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ResourceStats {
     /// CPU usage percentage (0-100)
     pub cpu_percent: f32,
@@ -28,31 +39,114 @@ pub struct ResourceStats {
     /// Memory usage in MB
     pub memory_mb: u32,
     
+    /// Bytes read from disk by this process during the interval
+    #[serde(default)]
+    pub disk_read_bytes: u64,
+    
+    /// Bytes written to disk by this process during the interval
+    #[serde(default)]
+    pub disk_written_bytes: u64,
+    
+    /// Bytes received by the host's network interfaces during the interval
+    #[serde(default)]
+    pub net_rx_bytes: u64,
+    
+    /// Bytes transmitted by the host's network interfaces during the interval
+    #[serde(default)]
+    pub net_tx_bytes: u64,
+    
+    /// Length of the interval the IO counters cover (ms)
+    #[serde(default)]
+    pub interval_ms: u64,
+    
+    /// Swap in use on the host (MB)
+    #[serde(default)]
+    pub swap_mb: u32,
+    
+    /// CPU usage of each of the host's cores (0-100)
+    #[serde(default)]
+    pub cpu_per_core: Vec<f32>,
+    
+    /// CPU package temperature (°C), or none without a sensor for it
+    #[serde(default)]
+    pub temperature_celsius: Option<f32>,
+    
     /// Timestamp when stats were collected
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+impl ResourceStats {
+    /// Disk throughput over the interval, read plus written (MB/s)
+    pub fn disk_mbps(&self) -> f32 {
+        self.rate_mbps(self.disk_read_bytes + self.disk_written_bytes)
+    }
+    
+    /// Network throughput over the interval, received plus transmitted (MB/s)
+    pub fn net_mbps(&self) -> f32 {
+        self.rate_mbps(self.net_rx_bytes + self.net_tx_bytes)
+    }
+    
+    fn rate_mbps(&self, bytes: u64) -> f32 {
+        if self.interval_ms == 0 {
+            return 0.0;
+        }
+        bytes as f32 / BYTES_PER_MB / (self.interval_ms as f32 / 1000.0)
+    }
+}
+
 /// Resource limits
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ResourceLimit {
     /// Maximum CPU percentage (0-100)
     pub cpu_percent: f32,
     
     /// Maximum memory in MB
     pub memory_mb: u32,
+    
+    /// Maximum disk throughput in MB/s (unlimited when unset)
+    #[serde(default)]
+    pub disk_mbps: Option<f32>,
+    
+    /// Maximum network throughput in MB/s (unlimited when unset)
+    #[serde(default)]
+    pub net_mbps: Option<f32>,
+}
+
+impl ResourceLimit {
+    /// Usage in `stats` of each limited resource, as a fraction of its limit
+    pub fn usage(&self, stats: &ResourceStats) -> Vec<(ResourceType, f32)> {
+        let usage = [
+            (ResourceType::CPU, Some(stats.cpu_percent / self.cpu_percent)),
+            (ResourceType::Memory, Some(stats.memory_mb as f32 / self.memory_mb as f32)),
+            (ResourceType::Storage, self.disk_mbps.map(|limit| stats.disk_mbps() / limit)),
+            (ResourceType::Network, self.net_mbps.map(|limit| stats.net_mbps() / limit)),
+        ];
+        usage.into_iter().filter_map(|(resource_type, percent)| Some((resource_type, percent?))).collect()
+    }
 }
 
 /// Resource allocation strategy
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AllocationStrategy {
     /// Even distribution
     Even,
     /// Priority-based (higher priority gets more)
     PriorityBased,
     /// First-come, first-served
+    #[default]
+    #[serde(rename = "fcfs")]
     FCFS,
 }
 
+impl AllocationStrategy {
+    /// Whether the strategy sizes every allocation, so they're recomputed as agents
+    /// join and leave; FCFS keeps allocations as they were granted
+    pub fn rebalances(self) -> bool {
+        matches!(self, AllocationStrategy::Even | AllocationStrategy::PriorityBased)
+    }
+}
+
 /// Resource allocator
 pub struct ResourceAllocator {
     /// Maximum resources available
@@ -99,13 +193,14 @@ impl ResourceAllocator {
                 let priority_factor = priority as f32 / 10.0;
                 
                 // Allocate based on priority but ensure minimum
+                let agent_count = agent_count.max(1);
                 cpu = self.min_cpu_per_agent + 
-                      (self.limits.cpu_percent - (self.min_cpu_per_agent * agent_count as f32)) * 
+                      (self.limits.cpu_percent - (self.min_cpu_per_agent * agent_count as f32)).max(0.0) * 
                       priority_factor / (agent_count as f32);
                 
                 memory = self.min_memory_per_agent +
-                         (self.limits.memory_mb - (self.min_memory_per_agent * agent_count as u32)) *
-                         (priority as u32) / (10 * agent_count as u32);
+                         self.limits.memory_mb.saturating_sub(self.min_memory_per_agent * agent_count as u32) *
+                         (priority.min(10) as u32) / (10 * agent_count as u32);
             },
             
             AllocationStrategy::FCFS => {
@@ -128,114 +223,316 @@ impl ResourceAllocator {
         self.min_cpu_per_agent = min_cpu;
         self.min_memory_per_agent = min_memory;
     }
+    
+    /// Allocation strategy in use
+    pub fn strategy(&self) -> AllocationStrategy {
+        self.strategy
+    }
 }
 
 /// Resource usage tracker
 pub struct ResourceTracker {
-    /// Historical CPU usage (timestamp, percentage)
-    cpu_history: Vec<(chrono::DateTime<chrono::Utc>, f32)>,
-    
-    /// Historical memory usage (timestamp, MB)
-    memory_history: Vec<(chrono::DateTime<chrono::Utc>, u32)>,
+    /// Historical usage, oldest first; a ring buffer of at most `max_history` samples
+    history: VecDeque<ResourceStats>,
     
     /// Maximum history length
     max_history: usize,
     
-    /// Last warning time for throttling alerts
-    last_warning: Option<chrono::DateTime<chrono::Utc>>,
+    /// How long samples are kept, measured back from the latest (unbounded when unset)
+    retention: Option<Duration>,
     
     /// Warning threshold (percentage of limit)
     warning_threshold: f32,
+    
+    /// (warning, clear) thresholds of resources that don't use `warning_threshold`
+    thresholds: HashMap<ResourceType, (f32, f32)>,
+    
+    /// Resources in breach at the latest check
+    breached: HashSet<ResourceType>,
+    
+    /// Swap in use (MB) past which to warn (never when unset)
+    swap_threshold_mb: Option<u32>,
+    
+    /// CPU package temperature (°C) past which to warn (never when unset)
+    temperature_threshold_celsius: Option<f32>,
 }
 
 impl ResourceTracker {
     /// Create a new resource tracker
     pub fn new(max_history: usize) -> Self {
         Self {
-            cpu_history: Vec::with_capacity(max_history),
-            memory_history: Vec::with_capacity(max_history),
-            max_history,
-            last_warning: None,
+            history: VecDeque::new(),
+            max_history: max_history.max(1),
+            retention: None,
             warning_threshold: 0.8, // 80% of limit
+            thresholds: HashMap::new(),
+            breached: HashSet::new(),
+            swap_threshold_mb: None,
+            temperature_threshold_celsius: None,
+        }
+    }
+    
+    /// Drop samples taken more than `retention` before the latest, so the history spans
+    /// the same time however often samples are taken
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+    
+    /// Set the warning threshold (fraction of limit)
+    pub fn with_warning_threshold(mut self, threshold: f32) -> Self {
+        self.warning_threshold = threshold;
+        self
+    }
+    
+    /// Set the thresholds (fractions of limit) at which a resource's warning opens and,
+    /// once open, below which it clears
+    pub fn with_thresholds(mut self, resource_type: ResourceType, warning: f32, clear: f32) -> Self {
+        self.thresholds.insert(resource_type, (warning, clear.min(warning)));
+        self
+    }
+    
+    /// Set the swap (MB) and temperature (°C) past which to warn
+    pub fn with_host_thresholds(mut self, swap_mb: Option<u32>, temperature_celsius: Option<f32>) -> Self {
+        self.swap_threshold_mb = swap_mb;
+        self.temperature_threshold_celsius = temperature_celsius;
+        self
+    }
+    
+    /// Warning threshold of a resource (fraction of limit)
+    pub fn warning_threshold(&self, resource_type: ResourceType) -> f32 {
+        self.thresholds.get(&resource_type).map_or(self.warning_threshold, |&(warning, _)| warning)
+    }
+    
+    /// Whether usage at `percent_of_limit` warrants a warning for a resource
+    ///
+    /// A resource in breach at the latest check stays in breach until usage falls below
+    /// its clear threshold, so usage hovering around the warning threshold doesn't open
+    /// and resolve alerts over and over.
+    pub fn should_warn(&self, resource_type: ResourceType, percent_of_limit: f32) -> bool {
+        let (warning, clear) = self.thresholds.get(&resource_type)
+            .copied()
+            .unwrap_or((self.warning_threshold, self.warning_threshold));
+        match self.breached.contains(&resource_type) {
+            true => percent_of_limit >= clear,
+            false => percent_of_limit >= warning,
         }
     }
     
-    /// Add resource stats to history
+    /// Add resource stats to history, dropping the oldest sample once full and any
+    /// older than the retention
     pub fn add_stats(&mut self, stats: &ResourceStats) {
-        // Add CPU usage
-        self.cpu_history.push((stats.timestamp, stats.cpu_percent));
-        if self.cpu_history.len() > self.max_history {
-            self.cpu_history.remove(0);
+        if self.history.len() == self.max_history {
+            self.history.pop_front();
         }
+        self.history.push_back(stats.clone());
         
-        // Add memory usage
-        self.memory_history.push((stats.timestamp, stats.memory_mb));
-        if self.memory_history.len() > self.max_history {
-            self.memory_history.remove(0);
+        let oldest = self.retention
+            .and_then(|retention| chrono::Duration::from_std(retention).ok())
+            .and_then(|retention| stats.timestamp.checked_sub_signed(retention));
+        if let Some(oldest) = oldest {
+            while self.history.front().is_some_and(|front| front.timestamp < oldest) {
+                self.history.pop_front();
+            }
         }
     }
     
-    /// Check if resources are exceeding threshold and should trigger warning
-    pub fn should_warn(&mut self, limits: &ResourceLimit) -> Option<(ResourceType, f32)> {
-        // Get latest stats
-        if self.cpu_history.is_empty() || self.memory_history.is_empty() {
-            return None;
-        }
-        
-        let &(_, cpu_usage) = self.cpu_history.last().unwrap();
-        let &(_, memory_usage) = self.memory_history.last().unwrap();
-        
-        // Calculate percentages of limits
-        let cpu_percent_of_limit = cpu_usage / limits.cpu_percent;
-        let memory_percent_of_limit = memory_usage as f32 / limits.memory_mb as f32;
+    /// Samples taken within `range` of the latest one, oldest first
+    pub fn get_history(&self, range: Duration) -> Vec<ResourceStats> {
+        let Some(latest) = self.history.back() else {
+            return Vec::new();
+        };
+        let since = chrono::Duration::from_std(range).ok()
+            .and_then(|range| latest.timestamp.checked_sub_signed(range));
         
-        // Check if we should warn and throttle warnings
-        let now = chrono::Utc::now();
-        let should_throttle = match self.last_warning {
-            Some(last) => now.signed_duration_since(last).num_seconds() < 60, // 1 minute throttling
-            None => false,
+        // Samples are in time order, so the ones in range are at the back
+        let start = match since {
+            Some(since) => self.history.partition_point(|stats| stats.timestamp < since),
+            None => 0,
+        };
+        self.history.range(start..).cloned().collect()
+    }
+    
+    /// Number of samples in the history
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+    
+    /// Check the latest stats against the limits, returning the resources in breach as
+    /// (resource, level, fraction of limit)
+    ///
+    /// Usage at the limit is critical, and usage `should_warn` for a warning.
+    pub fn check_alerts(&mut self, limits: &ResourceLimit) -> Vec<(ResourceType, AlertLevel, f32)> {
+        // Get latest stats
+        let Some(stats) = self.history.back() else {
+            return Vec::new();
         };
         
-        if !should_throttle {
-            // Check CPU
-            if cpu_percent_of_limit >= self.warning_threshold {
-                self.last_warning = Some(now);
-                return Some((ResourceType::CPU, cpu_percent_of_limit));
-            }
-            
-            // Check Memory
-            if memory_percent_of_limit >= self.warning_threshold {
-                self.last_warning = Some(now);
-                return Some((ResourceType::Memory, memory_percent_of_limit));
-            }
+        let mut alerts = Vec::new();
+        for (resource_type, percent_of_limit) in limits.usage(stats) {
+            let level = if percent_of_limit >= 1.0 {
+                AlertLevel::Critical
+            } else if self.should_warn(resource_type, percent_of_limit) {
+                AlertLevel::Warning
+            } else {
+                continue;
+            };
+            alerts.push((resource_type, level, percent_of_limit));
         }
         
-        None
+        self.breached = alerts.iter().map(|&(resource_type, ..)| resource_type).collect();
+        alerts
+    }
+    
+    /// Check the latest swap and temperature against their thresholds, returning those
+    /// past them as (resource, current, threshold)
+    ///
+    /// An unknown temperature never warns.
+    pub fn check_host_alerts(&self) -> Vec<(ResourceType, f32, f32)> {
+        let Some(stats) = self.history.back() else {
+            return Vec::new();
+        };
+        let readings = [
+            (ResourceType::Swap, Some(stats.swap_mb as f32), self.swap_threshold_mb.map(|threshold| threshold as f32)),
+            (ResourceType::Temperature, stats.temperature_celsius, self.temperature_threshold_celsius),
+        ];
+        readings.into_iter()
+            .filter_map(|(resource_type, current, threshold)| {
+                let (current, threshold) = (current?, threshold?);
+                (current >= threshold).then_some((resource_type, current, threshold))
+            })
+            .collect()
     }
     
     /// Get average usage over the tracked history
     pub fn get_average_usage(&self) -> (f32, u32) {
-        if self.cpu_history.is_empty() || self.memory_history.is_empty() {
+        if self.history.is_empty() {
             return (0.0, 0);
         }
         
         // Calculate averages
-        let avg_cpu = self.cpu_history.iter().map(|&(_, cpu)| cpu).sum::<f32>() / self.cpu_history.len() as f32;
-        let avg_memory = self.memory_history.iter().map(|&(_, mem)| mem).sum::<u32>() / self.memory_history.len() as u32;
+        let count = self.history.len();
+        let avg_cpu = self.history.iter().map(|stats| stats.cpu_percent).sum::<f32>() / count as f32;
+        let avg_memory = self.history.iter().map(|stats| stats.memory_mb as u64).sum::<u64>() / count as u64;
         
-        (avg_cpu, avg_memory)
+        (avg_cpu, avg_memory as u32)
     }
     
     /// Get maximum usage over the tracked history
     pub fn get_max_usage(&self) -> (f32, u32) {
-        if self.cpu_history.is_empty() || self.memory_history.is_empty() {
+        // Find maximums
+        let max_cpu = self.history.iter().map(|stats| stats.cpu_percent).fold(0.0, f32::max);
+        let max_memory = self.history.iter().map(|stats| stats.memory_mb).fold(0, u32::max);
+        
+        (max_cpu, max_memory)
+    }
+    
+    /// Get the `percentile` (0-100) of usage over the tracked history, by nearest rank
+    pub fn get_percentile_usage(&self, percentile: f32) -> (f32, u32) {
+        if self.history.is_empty() {
             return (0.0, 0);
         }
         
-        // Find maximums
-        let max_cpu = self.cpu_history.iter().map(|&(_, cpu)| cpu).fold(0.0, f32::max);
-        let max_memory = self.memory_history.iter().map(|&(_, mem)| mem).fold(0, u32::max);
+        let mut cpu: Vec<f32> = self.history.iter().map(|stats| stats.cpu_percent).collect();
+        let mut memory: Vec<u32> = self.history.iter().map(|stats| stats.memory_mb).collect();
+        cpu.sort_by(f32::total_cmp);
+        memory.sort_unstable();
         
-        (max_cpu, max_memory)
+        let rank = ((percentile.clamp(0.0, 100.0) / 100.0) * cpu.len() as f32).ceil() as usize;
+        let index = rank.clamp(1, cpu.len()) - 1;
+        (cpu[index], memory[index])
+    }
+    
+    /// Get average disk and network throughput over the tracked history (MB/s)
+    pub fn get_average_io(&self) -> (f32, f32) {
+        if self.history.is_empty() {
+            return (0.0, 0.0);
+        }
+        
+        let count = self.history.len() as f32;
+        let avg_disk = self.history.iter().map(ResourceStats::disk_mbps).sum::<f32>() / count;
+        let avg_net = self.history.iter().map(ResourceStats::net_mbps).sum::<f32>() / count;
+        
+        (avg_disk, avg_net)
+    }
+    
+    /// Get maximum disk and network throughput over the tracked history (MB/s)
+    pub fn get_max_io(&self) -> (f32, f32) {
+        let max_disk = self.history.iter().map(ResourceStats::disk_mbps).fold(0.0, f32::max);
+        let max_net = self.history.iter().map(ResourceStats::net_mbps).fold(0.0, f32::max);
+        
+        (max_disk, max_net)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn sample(seconds: i64, cpu_percent: f32, memory_mb: u32) -> ResourceStats {
+        let timestamp = chrono::DateTime::<chrono::Utc>::from_timestamp(1_700_000_000 + seconds, 0).unwrap();
+        ResourceStats { cpu_percent, memory_mb, timestamp, ..Default::default() }
+    }
+    
+    #[test]
+    fn test_tracker_history() {
+        let mut tracker = ResourceTracker::new(4);
+        assert_eq!(tracker.get_average_usage(), (0.0, 0));
+        assert!(tracker.get_history(Duration::from_secs(60)).is_empty());
+        
+        // The ring buffer keeps the latest samples
+        for (second, cpu, memory) in [(0, 50.0, 500), (1, 10.0, 100), (2, 20.0, 200), (3, 30.0, 300), (4, 40.0, 400)] {
+            tracker.add_stats(&sample(second, cpu, memory));
+        }
+        assert_eq!(tracker.len(), 4);
+        let cpu = |history: Vec<ResourceStats>| history.iter().map(|stats| stats.cpu_percent).collect::<Vec<_>>();
+        assert_eq!(cpu(tracker.get_history(Duration::from_secs(60))), [10.0, 20.0, 30.0, 40.0]);
+        assert_eq!(cpu(tracker.get_history(Duration::from_secs(1))), [30.0, 40.0]);
+        assert_eq!(cpu(tracker.get_history(Duration::ZERO)), [40.0]);
+        
+        assert_eq!(tracker.get_average_usage(), (25.0, 250));
+        assert_eq!(tracker.get_max_usage(), (40.0, 400));
+        assert_eq!(tracker.get_percentile_usage(50.0), (20.0, 200));
+        assert_eq!(tracker.get_percentile_usage(95.0), (40.0, 400));
+        assert_eq!(tracker.get_percentile_usage(0.0), (10.0, 100));
+        
+        // However far apart samples are, only those within the retention are kept
+        let mut tracker = ResourceTracker::new(10).with_retention(Duration::from_secs(10));
+        for second in [0, 5, 12, 14] {
+            tracker.add_stats(&sample(second, 10.0, 100));
+        }
+        assert_eq!(tracker.len(), 3);
+    }
+    
+    #[test]
+    fn test_io_limits() {
+        let mut tracker = ResourceTracker::new(4);
+        let mut limits = ResourceLimit { cpu_percent: 30.0, memory_mb: 800, ..Default::default() };
+        
+        // 3 MB read and 1 MB written over 2 s, 1 MB received over 2 s
+        let stats = ResourceStats {
+            disk_read_bytes: 3 * 1024 * 1024,
+            disk_written_bytes: 1024 * 1024,
+            net_rx_bytes: 1024 * 1024,
+            interval_ms: 2000,
+            ..sample(0, 1.0, 100)
+        };
+        assert_eq!(stats.disk_mbps(), 2.0);
+        assert_eq!(stats.net_mbps(), 0.5);
+        tracker.add_stats(&stats);
+        
+        // IO isn't checked without limits
+        assert!(tracker.check_alerts(&limits).is_empty());
+        
+        limits.disk_mbps = Some(2.0);
+        limits.net_mbps = Some(0.6);
+        assert_eq!(tracker.check_alerts(&limits), [
+            (ResourceType::Storage, AlertLevel::Critical, 1.0),
+            (ResourceType::Network, AlertLevel::Warning, 0.5 / 0.6),
+        ]);
+        
+        tracker.add_stats(&sample(1, 1.0, 100));
+        assert_eq!(tracker.get_average_io(), (1.0, 0.25));
+        assert_eq!(tracker.get_max_io(), (2.0, 0.5));
     }
 }
@@ -0,0 +1,165 @@
+//! Process sampling for MCP-ZERO Hardware Manager
+//!
+//! The monitoring loop only reads the CPU, memory and disk IO of a handful of
+//! processes: the hardware manager itself and the processes registered to agents, plus
+//! the host's network counters, swap, per-core CPU and temperature sensors. Refreshing
+//! those alone avoids walking every process and disk on the host.
+
+use std::time::{Duration, Instant};
+use sysinfo::{ComponentExt, CpuExt, CpuRefreshKind, NetworkExt, NetworksExt, Pid, ProcessRefreshKind, RefreshKind, System, SystemExt};
+
+use crate::resource::ResourceStats;
+
+/// Sensor labels that read the CPU package temperature, best first: Intel's coretemp
+/// package sensor, AMD's control and die sensors, then anything naming the CPU
+const PACKAGE_SENSORS: [&[&str]; 3] = [&["package"], &["tctl", "tdie"], &["cpu"]];
+
+/// Create a system information collector holding only what sampling reads
+///
+/// Global CPU times and network counters are loaded up front so the first sample has
+/// a baseline to compute usage against, and temperature sensors are discovered once.
+pub fn sampling_system() -> System {
+    System::new_with_specifics(
+        RefreshKind::new()
+            .with_cpu(CpuRefreshKind::new().with_cpu_usage())
+            .with_networks()
+            .with_networks_list()
+            .with_components_list(),
+    )
+}
+
+/// Refresh the CPU, memory and disk usage of one process; returns false if it no
+/// longer exists
+pub fn refresh_process(system: &mut System, pid: u32) -> bool {
+    let kind = ProcessRefreshKind::new().with_cpu().with_disk_usage();
+    system.refresh_process_specifics(Pid::from(pid as usize), kind)
+}
+
+/// Refresh the host's network counters, returning the bytes received and transmitted
+/// since the last refresh, leaving out loopback traffic
+pub fn refresh_network(system: &mut System) -> (u64, u64) {
+    system.refresh_networks();
+    system.networks().iter()
+        .filter(|(name, _)| name.as_str() != "lo")
+        .fold((0, 0), |(rx, tx), (_, data)| (rx + data.received(), tx + data.transmitted()))
+}
+
+/// Refresh the host's swap, per-core CPU usage and temperature sensors, recording them
+/// in `stats`
+///
+/// The temperature is left unset when no sensor reads the CPU package.
+pub fn refresh_host(system: &mut System, stats: &mut ResourceStats) {
+    system.refresh_memory();
+    system.refresh_cpu_specifics(CpuRefreshKind::new().with_cpu_usage());
+    system.refresh_components();
+    
+    stats.swap_mb = (system.used_swap() / (1024 * 1024)) as u32;
+    stats.cpu_per_core = system.cpus().iter().map(|cpu| cpu.cpu_usage()).collect();
+    stats.temperature_celsius = package_temperature(
+        system.components().iter().map(|component| (component.label(), component.temperature())),
+    );
+}
+
+/// Hottest reading of the best kind of package sensor present, from (label, °C) pairs
+fn package_temperature<'a>(sensors: impl Iterator<Item = (&'a str, f32)> + Clone) -> Option<f32> {
+    PACKAGE_SENSORS.iter().find_map(|names| {
+        sensors.clone()
+            .filter(|(label, temperature)| {
+                let label = label.to_lowercase();
+                temperature.is_finite() && names.iter().any(|name| label.contains(name))
+            })
+            .map(|(_, temperature)| temperature)
+            .reduce(f32::max)
+    })
+}
+
+/// Average time taken per sample by a full refresh and by a targeted one
+#[derive(Debug, Clone, Copy)]
+pub struct SamplingCost {
+    /// Refreshing everything, as `System::refresh_all` does
+    pub full: Duration,
+    
+    /// Refreshing only the sampled PIDs
+    pub targeted: Duration,
+}
+
+impl SamplingCost {
+    /// Fraction of the full refresh's cost saved by the targeted one
+    pub fn reduction(&self) -> f64 {
+        if self.full.is_zero() {
+            return 0.0;
+        }
+        1.0 - self.targeted.as_secs_f64() / self.full.as_secs_f64()
+    }
+}
+
+/// Time `iterations` samples of `pids` taken each way
+pub fn measure_sampling_cost(pids: &[u32], iterations: u32) -> SamplingCost {
+    let iterations = iterations.max(1);
+    
+    let mut system = System::new_all();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        system.refresh_all();
+    }
+    let full = start.elapsed() / iterations;
+    
+    let mut system = sampling_system();
+    let mut stats = ResourceStats::default();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        for &pid in pids {
+            refresh_process(&mut system, pid);
+        }
+        refresh_network(&mut system);
+        refresh_host(&mut system, &mut stats);
+    }
+    let targeted = start.elapsed() / iterations;
+    
+    SamplingCost { full, targeted }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sysinfo::ProcessExt;
+    
+    #[test]
+    fn test_refresh_process() {
+        let mut system = sampling_system();
+        assert!(system.processes().is_empty());
+        
+        // Only the refreshed process is loaded
+        let own = std::process::id();
+        assert!(refresh_process(&mut system, own));
+        assert_eq!(system.processes().len(), 1);
+        assert!(system.process(Pid::from(own as usize)).unwrap().memory() > 0);
+        
+        // An exited process can't be refreshed
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let exited = child.id();
+        child.wait().unwrap();
+        assert!(!refresh_process(&mut system, exited));
+        
+        let cost = measure_sampling_cost(&[own], 2);
+        assert!(cost.full > Duration::ZERO);
+        assert!(cost.reduction() <= 1.0);
+    }
+    
+    #[test]
+    fn test_package_temperature() {
+        let intel = [("acpitz temp1", 40.0), ("coretemp Core 0", 55.0), ("coretemp Package id 0", 58.0), ("nvme Composite", 70.0)];
+        assert_eq!(package_temperature(intel.into_iter()), Some(58.0));
+        let amd = [("k10temp Tccd1", 61.0), ("k10temp Tctl", 63.5)];
+        assert_eq!(package_temperature(amd.into_iter()), Some(63.5));
+        
+        // Unknown rather than cold without a readable package sensor
+        assert_eq!(package_temperature([("nvme Composite", 70.0)].into_iter()), None);
+        assert_eq!(package_temperature([("coretemp Package id 0", f32::NAN)].into_iter()), None);
+        
+        let mut system = sampling_system();
+        let mut stats = ResourceStats::default();
+        refresh_host(&mut system, &mut stats);
+        assert_eq!(stats.cpu_per_core.len(), system.cpus().len());
+    }
+}
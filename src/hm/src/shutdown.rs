@@ -0,0 +1,124 @@
+//! Shutdown signalling for MCP-ZERO Hardware Manager
+//!
+//! `ShutdownToken` is created once by the daemon and shared by everything that must
+//! stop with it. SIGINT and SIGTERM on unix, or a console control event on windows,
+//! trigger it; a second signal while shutting down exits at once.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use anyhow::{Result, Context};
+
+/// Cloneable flag that is set once, waking everything waiting on it
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownToken {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl ShutdownToken {
+    /// Create a token that hasn't been triggered
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Request shutdown, waking every waiter
+    pub fn trigger(&self) {
+        let (triggered, wake) = &*self.inner;
+        *triggered.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        wake.notify_all();
+    }
+    
+    /// Whether shutdown has been requested
+    pub fn is_triggered(&self) -> bool {
+        *self.inner.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Block until shutdown is requested
+    pub fn wait(&self) {
+        let (triggered, wake) = &*self.inner;
+        let triggered = triggered.lock().unwrap_or_else(|e| e.into_inner());
+        let _triggered = wake.wait_while(triggered, |triggered| !*triggered).unwrap_or_else(|e| e.into_inner());
+    }
+    
+    /// Block for up to `timeout` until shutdown is requested; returns whether it was
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (triggered, wake) = &*self.inner;
+        let triggered = triggered.lock().unwrap_or_else(|e| e.into_inner());
+        let (triggered, _) = wake.wait_timeout_while(triggered, timeout, |triggered| !*triggered)
+            .unwrap_or_else(|e| e.into_inner());
+        *triggered
+    }
+    
+    /// Trigger the token on SIGINT or SIGTERM
+    #[cfg(unix)]
+    pub fn install_signal_handlers(&self) -> Result<()> {
+        use signal_hook::consts::{SIGINT, SIGTERM};
+        use signal_hook::iterator::Signals;
+        
+        let mut signals = Signals::new([SIGINT, SIGTERM]).context("Failed to register signal handlers")?;
+        let token = self.clone();
+        std::thread::Builder::new()
+            .name("mcp-hm-signals".to_string())
+            .spawn(move || {
+                for signal in signals.forever() {
+                    if token.is_triggered() {
+                        tracing::warn!("Received signal {} again, exiting immediately", signal);
+                        std::process::exit(128 + signal);
+                    }
+                    tracing::info!("Received signal {}, shutting down", signal);
+                    token.trigger();
+                }
+            })
+            .context("Failed to start signal handling thread")?;
+        Ok(())
+    }
+    
+    /// Trigger the token on a console control event such as Ctrl+C
+    #[cfg(windows)]
+    pub fn install_signal_handlers(&self) -> Result<()> {
+        use std::sync::OnceLock;
+        use windows_sys::Win32::Foundation::{BOOL, TRUE};
+        use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
+        
+        static TOKEN: OnceLock<ShutdownToken> = OnceLock::new();
+        
+        // Runs on a thread of its own, so it may block on the token's lock
+        unsafe extern "system" fn handle_ctrl(_event: u32) -> BOOL {
+            if let Some(token) = TOKEN.get() {
+                if token.is_triggered() {
+                    std::process::exit(130);
+                }
+                token.trigger();
+            }
+            TRUE
+        }
+        
+        if TOKEN.set(self.clone()).is_err() {
+            return Err(anyhow::anyhow!("Signal handlers are already installed"));
+        }
+        if unsafe { SetConsoleCtrlHandler(Some(handle_ctrl), TRUE) } == 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to register console control handler");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_shutdown_token() {
+        let token = ShutdownToken::new();
+        assert!(!token.wait_timeout(Duration::from_millis(10)));
+        
+        // Clones share the flag, and triggering wakes waiters on other threads
+        let waiter = {
+            let token = token.clone();
+            std::thread::spawn(move || token.wait())
+        };
+        token.clone().trigger();
+        waiter.join().unwrap();
+        assert!(token.is_triggered());
+        assert!(token.wait_timeout(Duration::ZERO));
+    }
+}
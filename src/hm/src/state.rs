@@ -0,0 +1,189 @@
+//! Allocation state persistence for MCP-ZERO Hardware Manager
+//!
+//! With `allocation_state_path` configured, agent allocations are saved to that file
+//! as they change, so a restarted daemon picks up where it left off instead of every
+//! agent registering again. Files ending in `.json` are written as JSON, anything else
+//! as YAML. Saves run on a background thread and are debounced, so a burst of changes
+//! is written once; the latest allocations are always written before the writer is
+//! dropped. Each save replaces the file atomically.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context, anyhow};
+use serde::{Serialize, Deserialize};
+
+use crate::AgentAllocation;
+
+/// Contents of an allocation state file
+#[derive(Debug, Serialize, Deserialize)]
+struct AllocationState {
+    /// When the allocations were saved
+    saved_at: chrono::DateTime<chrono::Utc>,
+    
+    /// Allocations, ordered by agent ID
+    allocations: Vec<AgentAllocation>,
+}
+
+/// Whether `path` is written as JSON rather than YAML
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+/// Write `allocations` to the state file at `path`, replacing it atomically
+pub fn write_allocations(path: &Path, allocations: &[AgentAllocation]) -> Result<()> {
+    let state = AllocationState { saved_at: chrono::Utc::now(), allocations: allocations.to_vec() };
+    let content = match is_json(path) {
+        true => serde_json::to_string_pretty(&state)?,
+        false => serde_yaml::to_string(&state)?,
+    };
+    
+    // Write alongside and rename over, so a crash never leaves half a file
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    std::fs::write(&temp, content).with_context(|| format!("Failed to write allocation state {}", temp.display()))?;
+    std::fs::rename(&temp, path).with_context(|| format!("Failed to replace allocation state {}", path.display()))?;
+    Ok(())
+}
+
+/// Read the allocations saved in the state file at `path`
+pub fn read_allocations(path: &Path) -> Result<Vec<AgentAllocation>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read allocation state {}", path.display()))?;
+    let state: AllocationState = match is_json(path) {
+        true => serde_json::from_str(&content).map_err(anyhow::Error::from),
+        false => serde_yaml::from_str(&content).map_err(anyhow::Error::from),
+    }
+    .with_context(|| format!("Invalid allocation state {}", path.display()))?;
+    
+    for allocation in &state.allocations {
+        if allocation.agent_id.is_empty() || !allocation.cpu_percent.is_finite() || allocation.cpu_percent < 0.0 {
+            return Err(anyhow!("Invalid allocation for agent {:?} in {}", allocation.agent_id, path.display()));
+        }
+    }
+    Ok(state.allocations)
+}
+
+/// Move a corrupt state file at `path` aside, returning where it went
+pub fn set_aside(path: &Path) -> Result<PathBuf> {
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(format!(".corrupt-{}", chrono::Utc::now().format("%Y%m%dT%H%M%S")));
+    let aside = PathBuf::from(aside);
+    std::fs::rename(path, &aside).with_context(|| format!("Failed to move {} aside", path.display()))?;
+    Ok(aside)
+}
+
+/// Allocations waiting to be written, and whether the writer is shutting down
+#[derive(Default)]
+struct Pending {
+    allocations: Option<Vec<AgentAllocation>>,
+    closed: bool,
+}
+
+/// Saves allocations to a state file in the background
+pub(crate) struct StateWriter {
+    pending: Arc<(Mutex<Pending>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StateWriter {
+    /// Start a writer saving to `path` at most once per `debounce`
+    pub fn new(path: PathBuf, debounce: Duration) -> Self {
+        let pending = Arc::new((Mutex::new(Pending::default()), Condvar::new()));
+        let shared = pending.clone();
+        let thread = std::thread::Builder::new()
+            .name("mcp-hm-state".to_string())
+            .spawn(move || write_loop(&path, debounce, &shared))
+            .map_err(|e| tracing::warn!("Failed to start allocation state writer, allocations won't be saved: {}", e))
+            .ok();
+        Self { pending, thread }
+    }
+    
+    /// Save `allocations`, replacing any save still waiting
+    pub fn save(&self, allocations: Vec<AgentAllocation>) {
+        let (lock, condvar) = &*self.pending;
+        lock.lock().unwrap_or_else(|e| e.into_inner()).allocations = Some(allocations);
+        condvar.notify_all();
+    }
+}
+
+impl Drop for StateWriter {
+    fn drop(&mut self) {
+        // Skip the debounce and write whatever is waiting
+        let (lock, condvar) = &*self.pending;
+        lock.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        condvar.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Write pending allocations to `path`, waiting `debounce` after each change for more
+fn write_loop(path: &Path, debounce: Duration, pending: &(Mutex<Pending>, Condvar)) {
+    let (lock, condvar) = pending;
+    let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        while state.allocations.is_none() && !state.closed {
+            state = condvar.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        if state.allocations.is_none() {
+            break;
+        }
+        
+        // Let changes made within the debounce coalesce into one write
+        let deadline = Instant::now() + debounce;
+        while !state.closed {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            state = condvar.wait_timeout(state, remaining).unwrap_or_else(|e| e.into_inner()).0;
+        }
+        
+        let allocations = state.allocations.take().unwrap_or_default();
+        drop(state);
+        if let Err(e) = write_allocations(path, &allocations) {
+            tracing::warn!("Failed to save allocations: {:#}", e);
+        }
+        state = lock.lock().unwrap_or_else(|e| e.into_inner());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_state_writer() {
+        let dir = std::env::temp_dir().join(format!("mcp-hm-state-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        
+        // Changes within the debounce are written once, with the latest allocations
+        for name in ["allocations.yaml", "allocations.json"] {
+            let path = dir.join(name);
+            let writer = StateWriter::new(path.clone(), Duration::from_secs(60));
+            writer.save(vec![AgentAllocation::new("first", 5.0, 100, 5)]);
+            writer.save(vec![AgentAllocation::new("first", 5.0, 100, 5), AgentAllocation::new("second", 10.0, 200, 1)]);
+            assert!(!path.exists());
+            
+            // Dropping skips the rest of the debounce
+            let start = Instant::now();
+            drop(writer);
+            assert!(start.elapsed() < Duration::from_secs(5));
+            let restored = read_allocations(&path).unwrap();
+            assert_eq!(restored.iter().map(|a| a.agent_id.as_str()).collect::<Vec<_>>(), ["first", "second"]);
+        }
+        assert!(std::fs::read_to_string(dir.join("allocations.json")).unwrap().trim_start().starts_with('{'));
+        
+        // Garbage is reported, then moved aside
+        let path = dir.join("corrupt.yaml");
+        std::fs::write(&path, "allocations: [{agent_id: 7").unwrap();
+        assert!(read_allocations(&path).is_err());
+        let aside = set_aside(&path).unwrap();
+        assert!(!path.exists() && aside.exists());
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,310 @@
+//! Live resource display for the MCP-ZERO Hardware Manager CLI
+//!
+//! `mcp-hm watch` redraws a summary of usage, agents and open alerts in place every
+//! interval. Each frame is built from the resource report, taken either from a
+//! hardware manager monitoring this process with the daemon's own loop or from a
+//! running daemon's control API. When stdout isn't a terminal, frames are printed one
+//! after another without colour or cursor movement.
+
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use serde::Deserialize;
+
+use mcp_hm::{AlertInstance, AlertLevel, HardwareManager, HMConfig, ShutdownToken};
+
+/// Open alerts listed per frame
+const MAX_ALERTS: usize = 5;
+
+/// Width of the usage bars
+const BAR_WIDTH: usize = 20;
+
+/// The parts of the resource report a frame shows
+#[derive(Debug, Deserialize)]
+pub struct Snapshot {
+    pub timestamp: String,
+    pub system: SystemUsage,
+    pub limits: Limits,
+    #[serde(default)]
+    pub agents: BTreeMap<String, AgentUsage>,
+    pub alerts: Alerts,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SystemUsage {
+    pub cpu_percent: f32,
+    pub memory_mb: u32,
+    pub disk_mbps: f32,
+    pub net_mbps: f32,
+    #[serde(default)]
+    pub swap_mb: u32,
+    #[serde(default)]
+    pub cpu_per_core: Vec<f32>,
+    #[serde(default)]
+    pub temperature_celsius: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Limits {
+    pub cpu_percent: f32,
+    pub memory_mb: u32,
+    pub disk_mbps: Option<f32>,
+    pub net_mbps: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AgentUsage {
+    #[serde(default)]
+    pub pids: Vec<u32>,
+    pub actual: Option<Usage>,
+    pub allocated: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Usage {
+    pub cpu_percent: f32,
+    pub memory_mb: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Alerts {
+    pub open: Vec<AlertInstance>,
+}
+
+/// Where frames get their report from
+pub enum Source {
+    /// A hardware manager monitoring this process
+    Local(Arc<HardwareManager>),
+    /// A daemon's control API, with its token
+    Remote(String, Option<String>),
+}
+
+impl Source {
+    fn snapshot(&self) -> Result<Snapshot> {
+        let report = match self {
+            Source::Local(hm) => hm.generate_report(),
+            Source::Remote(url, token) => {
+                let mut request = ureq::get(&format!("{}/report", url.trim_end_matches('/')))
+                    .timeout(Duration::from_secs(5));
+                if let Some(token) = token {
+                    request = request.set("Authorization", &format!("Bearer {}", token));
+                }
+                let response = request.call().map_err(|e| anyhow::anyhow!("Failed to fetch report from {}: {}", url, e))?;
+                serde_json::from_str(&response.into_string()?)?
+            },
+        };
+        Ok(serde_json::from_value(report)?)
+    }
+}
+
+/// How frames are drawn
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    /// Colour usage by how close it is to its limit
+    pub color: bool,
+    
+    /// Fraction of a limit from which usage shows as a warning
+    pub warning_threshold: f32,
+}
+
+/// Redraw the display every `interval` until `shutdown` is triggered
+pub fn watch(config: HMConfig, remote: Option<String>, interval: Duration, color: bool, shutdown: &ShutdownToken) -> Result<()> {
+    let warning_threshold = config.alert_threshold;
+    let source = match remote {
+        Some(url) => Source::Remote(url, config.api_token.clone()),
+        None => {
+            // Sample at the display's interval, as the daemon would at its own
+            let config = HMConfig { refresh_interval_ms: interval.as_millis().max(1) as u64, adaptive_sampling: false, ..config };
+            let hm = Arc::new(HardwareManager::new(config));
+            hm.start_monitoring()?;
+            Source::Local(hm)
+        },
+    };
+    
+    let tty = std::io::stdout().is_terminal();
+    let style = Style { color: color && tty && std::env::var_os("NO_COLOR").is_none(), warning_threshold };
+    let mut stdout = std::io::stdout().lock();
+    if tty {
+        // Hide the cursor while redrawing
+        write!(stdout, "\x1b[?25l\x1b[2J")?;
+    }
+    
+    let mut result = Ok(());
+    loop {
+        let frame = match source.snapshot() {
+            Ok(snapshot) => render_frame(&snapshot, interval, style),
+            Err(e) => vec![format!("Failed to take a sample: {:#}", e)],
+        };
+        let drawn = if tty {
+            // Redraw from the top left, clearing what the last frame left behind
+            let mut out = String::from("\x1b[H");
+            for line in &frame {
+                out.push_str(line);
+                out.push_str("\x1b[K\n");
+            }
+            out.push_str("\x1b[J");
+            write!(stdout, "{}", out)
+        } else {
+            writeln!(stdout, "{}\n", frame.join("\n"))
+        };
+        match drawn.and_then(|()| stdout.flush()) {
+            // The reader went away, e.g. `mcp-hm watch | head`
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => break,
+            Err(e) => {
+                result = Err(e.into());
+                break;
+            },
+            Ok(()) => {},
+        }
+        if shutdown.wait_timeout(interval) {
+            break;
+        }
+    }
+    
+    if tty {
+        let _ = write!(stdout, "\x1b[?25h");
+        let _ = stdout.flush();
+    }
+    if let Source::Local(hm) = &source {
+        hm.stop_monitoring();
+    }
+    result
+}
+
+/// Parse a refresh interval such as 1s, 500ms or 2m
+pub fn parse_interval(interval: &str) -> std::result::Result<Duration, String> {
+    let invalid = || format!("invalid interval {}: expected a number with ms, s or m, such as 1s", interval);
+    let split = interval.find(|c: char| !c.is_ascii_digit() && c != '.').ok_or_else(invalid)?;
+    let (amount, unit) = interval.split_at(split);
+    let amount: f64 = amount.parse().map_err(|_| invalid())?;
+    let seconds = match unit {
+        "ms" => amount / 1000.0,
+        "s" => amount,
+        "m" => amount * 60.0,
+        _ => return Err(invalid()),
+    };
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(duration) if duration >= Duration::from_millis(100) => Ok(duration),
+        _ => Err(format!("invalid interval {}: must be at least 100ms", interval)),
+    }
+}
+
+/// Render one frame of the display
+pub fn render_frame(snapshot: &Snapshot, interval: Duration, style: Style) -> Vec<String> {
+    let system = &snapshot.system;
+    let limits = &snapshot.limits;
+    let mut lines = vec![
+        format!("MCP-ZERO Hardware Manager - every {:?}, Ctrl+C to quit - {}", interval, snapshot.timestamp),
+        String::new(),
+        usage_line("CPU", system.cpu_percent, limits.cpu_percent, |v| format!("{:.1}%", v), style),
+        usage_line("Memory", system.memory_mb as f32, limits.memory_mb as f32, |v| format!("{:.0} MB", v), style),
+    ];
+    for (label, value, limit) in [("Disk IO", system.disk_mbps, limits.disk_mbps), ("Net IO", system.net_mbps, limits.net_mbps)] {
+        lines.push(match limit {
+            Some(limit) => usage_line(label, value, limit, |v| format!("{:.2} MB/s", v), style),
+            None => format!("{:<8}{:>22}", label, format!("{:.2} MB/s", value)),
+        });
+    }
+    
+    // Host-wide swap, temperature and load per core
+    let temperature = system.temperature_celsius.map_or("unknown".to_string(), |temperature| format!("{:.1}°C", temperature));
+    lines.push(format!("{:<8}{:>10}   Temp {}", "Swap", format!("{} MB", system.swap_mb), temperature));
+    if !system.cpu_per_core.is_empty() {
+        let cores: Vec<String> = system.cpu_per_core.iter().map(|usage| format!("{:.0}%", usage)).collect();
+        lines.push(format!("{:<8}{}", "Cores", cores.join(" ")));
+    }
+    
+    // Actual against allocated usage per agent
+    lines.push(String::new());
+    if snapshot.agents.is_empty() {
+        lines.push("No agents".to_string());
+    } else {
+        let width = snapshot.agents.keys().map(|agent_id| agent_id.chars().count()).max().unwrap_or(0).max(5);
+        lines.push(format!("{:<width$}  {:>17}  {:>21}  PIDS", "AGENT", "CPU used/alloc", "MEMORY used/alloc"));
+        for (agent_id, agent) in &snapshot.agents {
+            let actual = agent.actual.unwrap_or(Usage { cpu_percent: 0.0, memory_mb: 0 });
+            let cpu = format!("{:.1}%/{}", actual.cpu_percent, agent.allocated.map_or("-".to_string(), |a| format!("{:.1}%", a.cpu_percent)));
+            let memory = format!("{} MB/{}", actual.memory_mb, agent.allocated.map_or("-".to_string(), |a| format!("{} MB", a.memory_mb)));
+            let over = agent.allocated.is_some_and(|a| actual.cpu_percent > a.cpu_percent || actual.memory_mb > a.memory_mb);
+            let pids: Vec<String> = agent.pids.iter().map(u32::to_string).collect();
+            let row = format!("{:<width$}  {:>17}  {:>21}  {}", agent_id, cpu, memory, pids.join(","));
+            lines.push(if over { paint(&row, RED, style) } else { row });
+        }
+    }
+    
+    // Most recently breached open alerts first
+    lines.push(String::new());
+    let mut open: Vec<&AlertInstance> = snapshot.alerts.open.iter().collect();
+    open.sort_by_key(|alert| std::cmp::Reverse(alert.last_seen));
+    if open.is_empty() {
+        lines.push("No open alerts".to_string());
+    } else {
+        lines.push(format!("Open alerts ({}):", open.len()));
+        for alert in open.into_iter().take(MAX_ALERTS) {
+            let line = format!("  #{} [{}] {} (x{})", alert.id, alert.level, alert.message, alert.count);
+            let color = if alert.level == AlertLevel::Warning { YELLOW } else if alert.level >= AlertLevel::Critical { RED } else { "" };
+            lines.push(paint(&line, color, style));
+        }
+    }
+    lines
+}
+
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const GREEN: &str = "\x1b[32m";
+
+/// Wrap `text` in `color` if colour is on
+fn paint(text: &str, color: &str, style: Style) -> String {
+    if style.color && !color.is_empty() {
+        format!("{}{}\x1b[0m", color, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// A labelled value against its limit, with a bar coloured by how close it is
+fn usage_line(label: &str, value: f32, limit: f32, format: impl Fn(f32) -> String, style: Style) -> String {
+    let ratio = if limit > 0.0 { value / limit } else { 0.0 };
+    let filled = ((ratio.clamp(0.0, 1.0) * BAR_WIDTH as f32).round() as usize).min(BAR_WIDTH);
+    let bar = format!("[{}{}]", "#".repeat(filled), ".".repeat(BAR_WIDTH - filled));
+    let color = if ratio >= 1.0 { RED } else if ratio >= style.warning_threshold { YELLOW } else { GREEN };
+    format!("{:<8}{:>10} / {:<10} {}", label, format(value), format(limit), paint(&bar, color, style))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn snapshot() -> Snapshot {
+        let hm = HardwareManager::new(HMConfig::default());
+        hm.allocate_resources(mcp_hm::AgentAllocation::new("worker", 10.0, 100, 5)).unwrap();
+        serde_json::from_value(hm.generate_report()).unwrap()
+    }
+    
+    #[test]
+    fn test_render_frame() {
+        let snapshot = snapshot();
+        let plain = render_frame(&snapshot, Duration::from_secs(1), Style { color: false, warning_threshold: 0.8 });
+        assert!(plain.iter().all(|line| !line.contains('\x1b')), "escape codes in plain frame: {:#?}", plain);
+        assert!(plain.iter().any(|line| line.starts_with("CPU") && line.contains("30.0%")));
+        assert!(plain.iter().any(|line| line.starts_with("worker") && line.contains("0 MB/100 MB")));
+        assert!(plain.contains(&"No open alerts".to_string()));
+        assert!(plain.iter().any(|line| line.starts_with("Swap") && line.contains("Temp ")));
+        
+        let colored = render_frame(&snapshot, Duration::from_secs(1), Style { color: true, warning_threshold: 0.8 });
+        assert!(colored.iter().any(|line| line.contains(GREEN)));
+    }
+    
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("1s"), Ok(Duration::from_secs(1)));
+        assert_eq!(parse_interval("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_interval("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_interval("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_interval("10ms").is_err());
+        assert!(parse_interval("1h").is_err());
+        assert!(parse_interval("s").is_err());
+    }
+}
@@ -0,0 +1,65 @@
+//! Starts the hardware manager daemon with the control API on and queries it remotely
+
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Kills the daemon when the test ends, pass or fail
+struct Daemon(Child);
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn test_remote_stats() {
+    // Pick a free port for the API
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let url = format!("http://{}", addr);
+    
+    let dir = std::env::temp_dir().join(format!("mcp-hm-api-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("hm.yaml");
+    std::fs::write(&config, format!("refresh_interval_ms: 50\napi_listen: \"{}\"\napi_token: \"secret\"\n", addr)).unwrap();
+    
+    let _daemon = Daemon(Command::new(env!("CARGO_BIN_EXE_mcp-hm"))
+        .arg("--config").arg(&config)
+        .args(["start", "--foreground"])
+        .current_dir(&dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap());
+    
+    // Wait for the API to come up
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while ureq::get(&format!("{}/health", url)).call().is_err() {
+        assert!(Instant::now() < deadline, "control API didn't start");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    
+    // The token from the config is sent along
+    let stats = |config: &std::path::Path| Command::new(env!("CARGO_BIN_EXE_mcp-hm"))
+        .arg("--config").arg(config)
+        .args(["stats", "--remote", &url])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let output = stats(&config);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "stats failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("Hardware Stats (remote)"), "unexpected output: {}", stdout);
+    assert!(stdout.contains("CPU Usage:"), "unexpected output: {}", stdout);
+    
+    // Without it the daemon refuses
+    let anonymous = dir.join("anonymous.yaml");
+    std::fs::write(&anonymous, "refresh_interval_ms: 50\n").unwrap();
+    let output = stats(&anonymous);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("401"));
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,96 @@
+//! Starts the hardware manager as a background daemon and controls it through its PID file
+
+#![cfg(unix)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+/// Kills the daemon recorded in the PID file when the test ends, pass or fail
+struct PidGuard(PathBuf);
+
+impl Drop for PidGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = std::fs::read_to_string(&self.0).ok().and_then(|pid| pid.trim().parse::<i32>().ok()) {
+            unsafe { libc::kill(pid, libc::SIGKILL) };
+        }
+    }
+}
+
+fn hm(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mcp-hm"))
+        .arg("--config").arg(dir.join("hm.yaml"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_daemon_lifecycle() {
+    let dir = std::env::temp_dir().join(format!("mcp-hm-daemon-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let pid_file = dir.join("mcp-hm.pid");
+    std::fs::write(dir.join("hm.yaml"), "refresh_interval_ms: 50\ndaemon_log_path: daemon.log\nfinal_report_path: report.json\n").unwrap();
+    let _guard = PidGuard(pid_file.clone());
+    
+    // Starting returns once the daemon holds its PID file
+    let started = hm(&dir, &["start"]);
+    assert!(started.status.success(), "start failed: {}", String::from_utf8_lossy(&started.stderr));
+    assert!(stdout(&started).contains("daemon started"), "unexpected output: {}", stdout(&started));
+    let pid: u32 = std::fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+    assert!(stdout(&started).contains(&format!("pid {}", pid)));
+    
+    let status = hm(&dir, &["status"]);
+    assert!(status.status.success());
+    assert!(stdout(&status).contains(&format!("running (pid {})", pid)));
+    
+    // A second daemon refuses to start
+    let again = hm(&dir, &["start"]);
+    assert!(!again.status.success());
+    assert!(String::from_utf8_lossy(&again.stderr).contains("already running"));
+    
+    // Stopping waits for a clean exit, which removes the PID file and writes the report
+    let stopped = hm(&dir, &["stop"]);
+    assert!(stopped.status.success(), "stop failed: {}", String::from_utf8_lossy(&stopped.stderr));
+    assert!(!pid_file.exists());
+    assert!(dir.join("report.json").exists());
+    assert_eq!(hm(&dir, &["status"]).status.code(), Some(3));
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_stale_pid_file() {
+    let dir = std::env::temp_dir().join(format!("mcp-hm-stale-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let pid_file = dir.join("mcp-hm.pid");
+    std::fs::write(dir.join("hm.yaml"), "refresh_interval_ms: 50\n").unwrap();
+    
+    // As left behind by a daemon that crashed
+    let mut crashed = Command::new("true").spawn().unwrap();
+    std::fs::write(&pid_file, format!("{}\n", crashed.id())).unwrap();
+    crashed.wait().unwrap();
+    
+    let status = hm(&dir, &["status"]);
+    assert_eq!(status.status.code(), Some(3));
+    assert!(stdout(&status).contains("not running"));
+    assert!(!pid_file.exists());
+    
+    // A daemon starts over a stale file too
+    std::fs::write(&pid_file, "999999999\n").unwrap();
+    let _guard = PidGuard(pid_file.clone());
+    assert!(hm(&dir, &["start"]).status.success());
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while std::fs::read_to_string(&pid_file).unwrap_or_default().trim() == "999999999" {
+        assert!(Instant::now() < deadline, "PID file wasn't replaced");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert!(hm(&dir, &["stop"]).status.success());
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,64 @@
+//! Starts the hardware manager daemon with the metrics endpoint on and scrapes it
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Kills the daemon when the test ends, pass or fail
+struct Daemon(Child);
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn scrape(addr: SocketAddr) -> Option<String> {
+    let mut stream = TcpStream::connect_timeout(&addr, Duration::from_millis(200)).ok()?;
+    stream.set_read_timeout(Some(Duration::from_secs(1))).ok()?;
+    write!(stream, "GET /metrics HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", addr).ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    Some(response)
+}
+
+#[test]
+fn test_metrics_endpoint() {
+    // Pick a free port for the endpoint
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    
+    let dir = std::env::temp_dir().join(format!("mcp-hm-metrics-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("hm.yaml");
+    std::fs::write(&config, format!("refresh_interval_ms: 50\nmetrics_listen: \"{}\"\n", addr)).unwrap();
+    
+    let _daemon = Daemon(Command::new(env!("CARGO_BIN_EXE_mcp-hm"))
+        .arg("--config").arg(&config)
+        .args(["start", "--foreground"])
+        .current_dir(&dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap());
+    
+    // Wait for the monitoring loop to publish a few samples
+    let wanted = ["mcp_hm_cpu_usage", "mcp_hm_memory_usage", "mcp_hm_cpu_limit 30", "mcp_hm_memory_limit 800", "mcp_hm_monitor_lag_ms"];
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut response = String::new();
+    while Instant::now() < deadline {
+        response = scrape(addr).unwrap_or_default();
+        if wanted.iter().all(|metric| response.contains(metric)) {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    
+    assert!(response.starts_with("HTTP/1.1 200"), "unexpected response: {}", response);
+    for metric in wanted {
+        assert!(response.contains(metric), "missing {} in: {}", metric, response);
+    }
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
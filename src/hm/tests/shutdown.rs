@@ -0,0 +1,47 @@
+//! Stops the hardware manager daemon with SIGTERM and checks it shuts down cleanly
+
+#![cfg(unix)]
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_sigterm_shutdown() {
+    let dir = std::env::temp_dir().join(format!("mcp-hm-shutdown-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("hm.yaml");
+    let report = dir.join("report.json");
+    std::fs::write(&config, format!("refresh_interval_ms: 50\nfinal_report_path: \"{}\"\n", report.display())).unwrap();
+    
+    let mut daemon = Command::new(env!("CARGO_BIN_EXE_mcp-hm"))
+        .arg("--config").arg(&config)
+        .args(["start", "--foreground"])
+        .current_dir(&dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    
+    // Let it take a few samples, then ask it to stop
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(unsafe { libc::kill(daemon.id() as libc::pid_t, libc::SIGTERM) }, 0);
+    
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let status = loop {
+        if let Some(status) = daemon.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() > deadline {
+            let _ = daemon.kill();
+            panic!("daemon didn't exit after SIGTERM");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    assert!(status.success(), "daemon exited with {}", status);
+    
+    // The final report was written on the way out
+    let report: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert!(report["limits"].is_object());
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,42 @@
+//! Compares cold plugin loads that compile the module against loads served by the
+//! on-disk module cache
+//!
+//! Run with `cargo bench -p mcp-kernel --bench module_cache`.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+use mcp_kernel::PluginManager;
+
+/// Loads per configuration, each by a fresh manager standing in for a process restart
+const ROUNDS: u32 = 20;
+
+const ECHO_PLUGIN: &str = include_str!("../tests/fixtures/echo_params.wat");
+
+fn cold_load(plugin_dir: &Path, cached: bool) -> Duration {
+    let plugin_id = "echo".to_string();
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..ROUNDS {
+        let mut manager = PluginManager::new(plugin_dir);
+        if cached {
+            manager = manager.with_module_cache(u64::MAX);
+        }
+        let started = Instant::now();
+        manager.load_plugin(&plugin_id).unwrap();
+        elapsed += started.elapsed();
+    }
+    elapsed / ROUNDS
+}
+
+fn main() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("echo.wasm"), ECHO_PLUGIN).unwrap();
+    
+    let compiled = cold_load(dir.path(), false);
+    
+    // Fill the cache before timing loads from it
+    PluginManager::new(dir.path()).with_module_cache(u64::MAX).load_plugin(&"echo".to_string()).unwrap();
+    let cached = cold_load(dir.path(), true);
+    
+    println!("{:<12} {:>8.2} ms per load", "compiled", compiled.as_secs_f64() * 1000.0);
+    println!("{:<12} {:>8.2} ms per load", "cached", cached.as_secs_f64() * 1000.0);
+}
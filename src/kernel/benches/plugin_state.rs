@@ -0,0 +1,80 @@
+//! Compares plugin executions that copy a large agent state into each call against
+//! executions sharing the agent's map, in time and bytes allocated per call
+//!
+//! Run with `cargo bench -p mcp-kernel --bench plugin_state`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use mcp_kernel::{CallContext, Plugin, PluginManager};
+
+/// Entries of 1KB each in the agent state, about 10MB in all
+const STATE_ENTRIES: usize = 10_000;
+
+const ROUNDS: u32 = 50;
+
+const ECHO_PLUGIN: &str = include_str!("../tests/fixtures/echo_params.wat");
+
+/// Counts the bytes allocated through the global allocator
+struct CountingAllocator;
+
+static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size.saturating_sub(layout.size()) as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn bench(label: &str, plugin: &Plugin, state: &Arc<HashMap<String, serde_json::Value>>, copy: bool) {
+    let params = serde_json::json!({"bench": true});
+    let agent_id = "agent_bench".to_string();
+    
+    let allocated = ALLOCATED.load(Ordering::Relaxed);
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        // Copying stands in for the store taking its own copy of the state
+        let state = if copy { Arc::new(HashMap::clone(state)) } else { state.clone() };
+        let context = CallContext::detached(Duration::from_secs(5));
+        plugin.execute("echo", &params, &agent_id, &state, &context).unwrap();
+    }
+    let elapsed = started.elapsed();
+    let allocated = ALLOCATED.load(Ordering::Relaxed) - allocated;
+    
+    println!(
+        "{:<8} {:>8.2} ms per call   {:>10.1} KB allocated per call",
+        label,
+        elapsed.as_secs_f64() * 1000.0 / ROUNDS as f64,
+        allocated as f64 / 1024.0 / ROUNDS as f64,
+    );
+}
+
+fn main() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("echo.wasm"), ECHO_PLUGIN).unwrap();
+    let manager = PluginManager::new(dir.path());
+    let plugin = manager.load_plugin(&"echo".to_string()).unwrap();
+    
+    let state: Arc<HashMap<String, serde_json::Value>> = Arc::new((0..STATE_ENTRIES)
+        .map(|i| (format!("key_{}", i), serde_json::json!("x".repeat(1024))))
+        .collect());
+    
+    bench("copied", &plugin, &state, true);
+    bench("shared", &plugin, &state, false);
+    assert_eq!(Arc::strong_count(&state), 1);
+}
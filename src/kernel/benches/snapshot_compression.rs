@@ -0,0 +1,66 @@
+//! Compares snapshot write/read time and on-disk size with and without compression
+//!
+//! Run with `cargo bench -p mcp-kernel --bench snapshot_compression`.
+
+use std::time::{Duration, Instant};
+use mcp_kernel::{Agent, AgentConfig, StorageBackend, StorageManager};
+
+/// Size of the state map written by each snapshot
+const STATE_BYTES: usize = 5 * 1024 * 1024;
+
+const ROUNDS: u32 = 5;
+
+/// An agent whose state is a log of roughly `STATE_BYTES` of structured entries
+fn large_agent() -> Agent {
+    let config = AgentConfig { name: "bench_agent".to_string(), ..Default::default() };
+    let mut agent = Agent::new("agent_bench".to_string(), config);
+    
+    let mut written = 0;
+    let mut index = 0;
+    while written < STATE_BYTES {
+        let entry = serde_json::json!({
+            "intent": "summarize",
+            "status": if index % 7 == 0 { "failed" } else { "ok" },
+            "latency_ms": (index * 37) % 1000,
+            "note": format!("request {} from client {}", index, index % 113),
+        });
+        written += entry.to_string().len();
+        agent.set_state(&format!("entry_{}", index), entry, usize::MAX).unwrap();
+        index += 1;
+    }
+    agent
+}
+
+fn bench(label: &str, storage: &StorageManager, agent: &Agent) {
+    let (mut write, mut read) = (Duration::ZERO, Duration::ZERO);
+    for _ in 0..ROUNDS {
+        let started = Instant::now();
+        storage.save_agent(agent.id(), agent).unwrap();
+        write += started.elapsed();
+        
+        let started = Instant::now();
+        storage.load_agent(agent.id()).unwrap();
+        read += started.elapsed();
+    }
+    
+    let size = storage.stored_size(agent.id()).unwrap();
+    println!(
+        "{:<12} write {:>8.1} ms   read {:>8.1} ms   size {:>9.1} KB",
+        label,
+        write.as_secs_f64() * 1000.0 / ROUNDS as f64,
+        read.as_secs_f64() * 1000.0 / ROUNDS as f64,
+        size as f64 / 1024.0,
+    );
+}
+
+fn main() {
+    let agent = large_agent();
+    let dir = tempfile::tempdir().unwrap();
+    
+    bench("plain", &StorageManager::new(dir.path().join("plain")).unwrap(), &agent);
+    for level in [1, 3, 9, 19] {
+        let storage = StorageManager::new(dir.path().join(format!("zstd_{}", level))).unwrap()
+            .with_compression(level);
+        bench(&format!("zstd -{}", level), &storage, &agent);
+    }
+}
@@ -0,0 +1,47 @@
+//! Compares the cost of recording a chatty event type with and without sampling
+//!
+//! Run with `cargo bench -p mcp-kernel --bench trace_sampling`.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use mcp_kernel::{PoseidonTracer, Tracer};
+
+/// Heartbeats recorded per round
+const EVENTS: usize = 100_000;
+
+const ROUNDS: u32 = 5;
+
+fn bench(label: &str, rate: f64) -> Duration {
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..ROUNDS {
+        let rates = HashMap::from([("agent.heartbeat".to_string(), rate)]);
+        let tracer = PoseidonTracer::new()
+            .with_buffer_limits(10_000, 0)
+            .with_sample_rates(rates);
+        let trace_id = tracer.begin_trace(&"agent_bench".to_string(), "heartbeat").unwrap();
+        
+        let started = Instant::now();
+        for beat in 0..EVENTS {
+            let data = serde_json::json!({"beat": beat, "load": (beat * 37) % 100});
+            tracer.record_trace_event(&trace_id, "agent.heartbeat", &data).unwrap();
+        }
+        elapsed += started.elapsed();
+        
+        tracer.end_trace(&trace_id, true, None).unwrap();
+    }
+    
+    let elapsed = elapsed / ROUNDS;
+    println!(
+        "{:<10} {:>8.1} ms   {:>6.2} us/event",
+        label,
+        elapsed.as_secs_f64() * 1000.0,
+        elapsed.as_secs_f64() * 1_000_000.0 / EVENTS as f64,
+    );
+    elapsed
+}
+
+fn main() {
+    let full = bench("rate 1.0", 1.0);
+    let sampled = bench("rate 0.01", 0.01);
+    println!("sampling at 1% takes {:.1}% of the unsampled time", sampled.as_secs_f64() * 100.0 / full.as_secs_f64());
+}
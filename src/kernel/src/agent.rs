@@ -5,11 +5,14 @@
 
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use anyhow::{Result, Context, anyhow};
 use blake3;
 use serde::{Serialize, Deserialize};
 
-use crate::plugin::{Plugin, PluginId};
+use crate::history::{ExecutionHistory, ExecutionRecord};
+use crate::plugin::{CallContext, Plugin, PluginId, DEFAULT_EXECUTION_TIMEOUT};
+use crate::schedule::{Schedule, ScheduleId};
 
 /// Agent ID type - based on Poseidon hash of pubkey + intent tree
 pub type AgentId = String;
@@ -23,8 +26,41 @@ pub enum AgentStatus {
     Recovered,
     /// Agent is paused
     Paused,
+    /// Agent was quarantined for repeated ethical violations and runs nothing until
+    /// an operator releases it
+    Quarantined,
     /// Agent is terminated
     Terminated,
+    /// Agent exists only in storage and has not been recovered (listing only)
+    Stored,
+}
+
+impl AgentStatus {
+    /// Check whether an agent in this status may execute intents
+    pub fn is_runnable(&self) -> bool {
+        matches!(self, AgentStatus::Active | AgentStatus::Recovered)
+    }
+    
+    /// Check whether a lifecycle transition to `next` is permitted
+    pub fn can_transition_to(&self, next: AgentStatus) -> bool {
+        match (self, next) {
+            // Running agents can be paused or terminated
+            (AgentStatus::Active | AgentStatus::Recovered, AgentStatus::Paused) => true,
+            (AgentStatus::Active | AgentStatus::Recovered, AgentStatus::Terminated) => true,
+            
+            // Paused agents can be resumed or terminated
+            (AgentStatus::Paused, AgentStatus::Active) => true,
+            (AgentStatus::Paused, AgentStatus::Terminated) => true,
+            
+            // Running agents are quarantined, then released or terminated
+            (AgentStatus::Active | AgentStatus::Recovered, AgentStatus::Quarantined) => true,
+            (AgentStatus::Quarantined, AgentStatus::Active) => true,
+            (AgentStatus::Quarantined, AgentStatus::Terminated) => true,
+            
+            // Terminated is final
+            _ => false,
+        }
+    }
 }
 
 /// Agent configuration
@@ -36,7 +72,7 @@ pub struct AgentConfig {
     /// Entry plugin for the agent
     pub entry: Option<String>,
     
-    /// Available intents
+    /// Available intents, exact or as glob patterns (`files.*`, `report:?`)
     pub intents: Vec<String>,
     
     /// Hardware constraints
@@ -46,6 +82,17 @@ pub struct AgentConfig {
     /// Additional metadata
     #[serde(default)]
     pub metadata: HashMap<String, serde_json::Value>,
+    
+    /// Execution timeout in milliseconds, overriding the kernel default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_timeout_ms: Option<u64>,
+}
+
+impl AgentConfig {
+    /// Get the entry of `intents` (exact or glob pattern) accepting an intent, if any
+    pub fn matching_intent(&self, intent: &str) -> Option<&str> {
+        crate::intent::match_intent(&self.intents, intent)
+    }
 }
 
 impl Default for AgentConfig {
@@ -56,7 +103,66 @@ impl Default for AgentConfig {
             intents: vec![],
             hm: HardwareConstraints::default(),
             metadata: HashMap::new(),
+            execution_timeout_ms: None,
+        }
+    }
+}
+
+/// Options for `MCPKernel::spawn_agent_with`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpawnOptions {
+    /// Replace an existing agent with the same ID instead of failing
+    #[serde(default)]
+    pub respawn: bool,
+    
+    /// Mixed into the agent ID so identical configs can be spawned more than once
+    #[serde(default)]
+    pub nonce: Option<String>,
+}
+
+/// Options for `MCPKernel::import_agent`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportOptions {
+    /// Plugins to attach in place of the exported ones, by exported plugin ID
+    #[serde(default)]
+    pub plugin_map: HashMap<PluginId, PluginId>,
+}
+
+/// Changes applied to a source agent's configuration when cloning it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentConfigOverrides {
+    /// Name of the clone
+    #[serde(default)]
+    pub name: Option<String>,
+    
+    /// Metadata entries added to, or replacing, the source's metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+    
+    /// Intents of the clone
+    #[serde(default)]
+    pub intents: Option<Vec<String>>,
+    
+    /// Execution timeout of the clone in milliseconds
+    #[serde(default)]
+    pub execution_timeout_ms: Option<u64>,
+}
+
+impl AgentConfigOverrides {
+    /// Build the clone's configuration from the source's
+    pub fn apply(&self, config: &AgentConfig) -> AgentConfig {
+        let mut config = config.clone();
+        if let Some(name) = &self.name {
+            config.name = name.clone();
+        }
+        config.metadata.extend(self.metadata.iter().map(|(key, value)| (key.clone(), value.clone())));
+        if let Some(intents) = &self.intents {
+            config.intents = intents.clone();
+        }
+        if self.execution_timeout_ms.is_some() {
+            config.execution_timeout_ms = self.execution_timeout_ms;
         }
+        config
     }
 }
 
@@ -68,6 +174,15 @@ pub struct HardwareConstraints {
     
     /// RAM usage cap in MB
     pub ram: Option<u32>,
+    
+    /// State size cap in bytes of serialized JSON, overriding the kernel default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_bytes: Option<usize>,
+    
+    /// Storage quota in bytes for snapshots, state and snapshot history, overriding the
+    /// kernel default (0 is unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_bytes: Option<u64>,
 }
 
 impl Default for HardwareConstraints {
@@ -75,10 +190,153 @@ impl Default for HardwareConstraints {
         Self {
             cpu: Some(10.0),  // Default 10% CPU limit
             ram: Some(100),   // Default 100MB RAM limit
+            state_bytes: None,
+            storage_bytes: None,
         }
     }
 }
 
+/// Row of `MCPKernel::list_agents`: (id, status, name, created_at, stored_size)
+pub type AgentListing = (AgentId, AgentStatus, String, i64, Option<u64>);
+
+/// Serializable summary of an agent for inspection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentInfo {
+    /// Agent ID
+    pub id: AgentId,
+    
+    /// Name of the agent
+    pub name: String,
+    
+    /// Current status
+    pub status: AgentStatus,
+    
+    /// Entry plugin for the agent
+    pub entry: Option<String>,
+    
+    /// Available intents
+    pub intents: Vec<String>,
+    
+    /// Attached plugin IDs
+    pub plugins: Vec<PluginId>,
+    
+    /// Additional metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+    
+    /// Creation timestamp
+    pub created_at: i64,
+    
+    /// Last updated timestamp
+    pub updated_at: i64,
+    
+    /// Size of the agent state in bytes of serialized JSON
+    #[serde(default)]
+    pub state_size: usize,
+    
+    /// Number of executions in the agent's history
+    #[serde(default)]
+    pub history_len: usize,
+}
+
+/// Criteria for `MCPKernel::find_agents`; unset criteria match every agent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentFilter {
+    /// Required status (`Stored` selects agents that exist only in storage)
+    #[serde(default)]
+    pub status: Option<AgentStatus>,
+    
+    /// Substring of the agent name
+    #[serde(default)]
+    pub name_contains: Option<String>,
+    
+    /// Intent the agent must accept, exactly or through one of its patterns
+    #[serde(default)]
+    pub intent: Option<String>,
+    
+    /// Plugin that must be attached
+    #[serde(default)]
+    pub plugin_id: Option<PluginId>,
+    
+    /// Metadata entries that must be present with equal values
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+    
+    /// Include agents that exist only in storage
+    #[serde(default)]
+    pub include_stored: bool,
+    
+    /// Number of matching agents to skip
+    #[serde(default)]
+    pub offset: usize,
+    
+    /// Maximum number of agents to return (unlimited when unset)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl AgentFilter {
+    /// Check whether an agent summary meets every criterion
+    pub fn matches(&self, info: &AgentInfo) -> bool {
+        self.status.is_none_or(|status| info.status == status)
+            && self.name_contains.as_ref().is_none_or(|name| info.name.contains(name.as_str()))
+            && self.intent.as_ref().is_none_or(|intent| crate::intent::match_intent(&info.intents, intent).is_some())
+            && self.plugin_id.as_ref().is_none_or(|plugin_id| info.plugins.contains(plugin_id))
+            && self.metadata.iter().all(|(key, value)| info.metadata.get(key) == Some(value))
+    }
+}
+
+/// Verdict of `MCPKernel::dry_run`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+pub enum DryRunVerdict {
+    /// The execution would run the entry plugin
+    Allowed,
+    /// The intent allow-list or ethical constraints refuse the execution
+    Denied {
+        code: String,
+        reason: String,
+    },
+    /// The execution is permitted but would fail before running, e.g. a missing plugin
+    WouldFail {
+        code: String,
+        reason: String,
+    },
+}
+
+/// Result of checking an execution without running it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DryRunResult {
+    /// Agent checked
+    pub agent_id: AgentId,
+    
+    /// Intent checked
+    pub intent: String,
+    
+    /// Whether the execution would run
+    #[serde(flatten)]
+    pub verdict: DryRunVerdict,
+    
+    /// Allow-list entry accepting the intent
+    pub matched_intent: Option<String>,
+    
+    /// Entry plugin that would run the intent
+    pub entry: Option<PluginId>,
+    
+    /// Hardware constraints the execution would run under
+    pub hm: HardwareConstraints,
+    
+    /// Execution timeout in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl DryRunResult {
+    /// Whether the execution would run
+    pub fn is_allowed(&self) -> bool {
+        self.verdict == DryRunVerdict::Allowed
+    }
+}
+
 /// Agent implementation
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Agent {
@@ -91,18 +349,34 @@ pub struct Agent {
     /// Current status
     status: AgentStatus,
     
-    /// Attached plugins
-    #[serde(skip)]
+    /// Attached plugins (persisted as IDs, restored as placeholders)
+    #[serde(with = "plugin_ids")]
     plugins: Arc<RwLock<HashMap<PluginId, Arc<Plugin>>>>,
     
-    /// Agent state storage for persistence
-    state: HashMap<String, serde_json::Value>,
+    /// Per-plugin configuration given at attach time
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    plugin_configs: HashMap<PluginId, serde_json::Value>,
+    
+    /// Agent state storage for persistence, shared with running plugins without copying
+    state: Arc<HashMap<String, serde_json::Value>>,
     
     /// Creation timestamp
     created_at: i64,
     
     /// Last updated timestamp
     updated_at: i64,
+    
+    /// Most recent executions
+    #[serde(default, skip_serializing_if = "ExecutionHistory::skip_serializing")]
+    history: ExecutionHistory,
+    
+    /// Recurring intent executions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    schedules: Vec<Schedule>,
+    
+    /// When recent executions were denied on ethical grounds, in milliseconds
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    violations: Vec<i64>,
 }
 
 impl Agent {
@@ -115,9 +389,13 @@ impl Agent {
             config,
             status: AgentStatus::Active,
             plugins: Arc::new(RwLock::new(HashMap::new())),
-            state: HashMap::new(),
+            plugin_configs: HashMap::new(),
+            state: Arc::default(),
             created_at: now,
             updated_at: now,
+            history: ExecutionHistory::default(),
+            schedules: Vec::new(),
+            violations: Vec::new(),
         }
     }
     
@@ -136,12 +414,70 @@ impl Agent {
         self.status
     }
     
+    /// Get creation timestamp
+    pub fn created_at(&self) -> i64 {
+        self.created_at
+    }
+    
+    /// Get last updated timestamp
+    pub fn updated_at(&self) -> i64 {
+        self.updated_at
+    }
+    
+    /// Get the IDs of attached plugins, sorted for stable output
+    pub fn plugin_ids(&self) -> Vec<PluginId> {
+        let mut ids: Vec<PluginId> = match self.plugins.read() {
+            Ok(plugins) => plugins.keys().cloned().collect(),
+            Err(_) => Vec::new(),
+        };
+        ids.sort();
+        ids
+    }
+    
+    /// Build a summary of the agent without copying its state
+    pub fn info(&self) -> AgentInfo {
+        AgentInfo {
+            id: self.id.clone(),
+            name: self.config.name.clone(),
+            status: self.status,
+            entry: self.config.entry.clone(),
+            intents: self.config.intents.clone(),
+            plugins: self.plugin_ids(),
+            metadata: self.config.metadata.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            state_size: self.state_size(),
+            history_len: self.history.len(),
+        }
+    }
+    
     /// Set agent status
     pub fn set_status(&mut self, status: AgentStatus) {
         self.status = status;
         self.updated_at = chrono::Utc::now().timestamp();
     }
     
+    /// Number of ethical violations counted toward quarantine
+    pub fn violations(&self) -> usize {
+        self.violations.len()
+    }
+    
+    /// Count an ethical violation at `now_ms`, forgetting those more than `window_ms`
+    /// earlier; returns the number within the window
+    pub(crate) fn record_violation(&mut self, now_ms: i64, window_ms: u64) -> usize {
+        let cutoff = now_ms.saturating_sub(window_ms as i64);
+        self.violations.retain(|&at| at > cutoff);
+        self.violations.push(now_ms);
+        self.updated_at = chrono::Utc::now().timestamp();
+        self.violations.len()
+    }
+    
+    /// Forget the agent's violations, as on release from quarantine
+    pub(crate) fn clear_violations(&mut self) {
+        self.violations.clear();
+        self.updated_at = chrono::Utc::now().timestamp();
+    }
+    
     /// Attach a plugin to the agent
     pub fn attach_plugin(&self, plugin_id: &PluginId) -> Result<()> {
         let mut plugins = self.plugins.write()
@@ -153,56 +489,371 @@ impl Agent {
         Ok(())
     }
     
-    /// Execute an intent
-    pub fn execute(&self, intent: &str) -> Result<serde_json::Value> {
-        // Check if the agent is active
-        match self.status {
-            AgentStatus::Active | AgentStatus::Recovered => {},
-            _ => return Err(anyhow!("Agent is not active")),
+    /// Attach an already loaded plugin to the agent
+    pub fn attach_loaded_plugin(&self, plugin: Arc<Plugin>) -> Result<()> {
+        let mut plugins = self.plugins.write()
+            .map_err(|_| anyhow!("Failed to acquire write lock on plugins"))?;
+        
+        plugins.insert(plugin.id().clone(), plugin);
+        
+        Ok(())
+    }
+    
+    /// Get the configuration given when a plugin was attached
+    pub fn plugin_config(&self, plugin_id: &PluginId) -> Option<&serde_json::Value> {
+        self.plugin_configs.get(plugin_id)
+    }
+    
+    /// Set the configuration passed to a plugin; null removes it
+    pub fn set_plugin_config(&mut self, plugin_id: &PluginId, config: serde_json::Value) {
+        if config.is_null() {
+            self.plugin_configs.remove(plugin_id);
+        } else {
+            self.plugin_configs.insert(plugin_id.clone(), config);
         }
+        self.updated_at = chrono::Utc::now().timestamp();
+    }
+    
+    /// Get the attached plugin IDs with their configs (null when none was given)
+    pub fn plugin_attachments(&self) -> Vec<(PluginId, serde_json::Value)> {
+        self.plugin_ids().into_iter()
+            .map(|plugin_id| {
+                let config = self.plugin_config(&plugin_id).cloned().unwrap_or(serde_json::Value::Null);
+                (plugin_id, config)
+            })
+            .collect()
+    }
+    
+    /// Get the attached plugins, placeholders included
+    pub(crate) fn attached_plugins(&self) -> Result<HashMap<PluginId, Arc<Plugin>>> {
+        self.plugins.read()
+            .map(|plugins| plugins.clone())
+            .map_err(|_| anyhow!("Failed to acquire read lock on plugins"))
+    }
+    
+    /// Get the IDs of attached plugins that are still placeholders
+    pub fn unloaded_plugin_ids(&self) -> Vec<PluginId> {
+        match self.plugins.read() {
+            Ok(plugins) => plugins.values()
+                .filter(|plugin| !plugin.is_loaded())
+                .map(|plugin| plugin.id().clone())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+    
+    /// Check whether a plugin is attached to the agent
+    pub fn has_plugin(&self, plugin_id: &PluginId) -> bool {
+        match self.plugins.read() {
+            Ok(plugins) => plugins.contains_key(plugin_id),
+            Err(_) => false,
+        }
+    }
+    
+    /// Detach a plugin from the agent
+    ///
+    /// Detaching the configured entry plugin is refused unless `clear_entry` is set,
+    /// in which case the entry is cleared as well.
+    pub fn detach_plugin(&mut self, plugin_id: &PluginId, clear_entry: bool) -> Result<()> {
+        let is_entry = self.config.entry.as_ref() == Some(plugin_id);
+        if is_entry && !clear_entry {
+            return Err(anyhow!("Plugin '{}' is the entry plugin for this agent", plugin_id));
+        }
+        
+        {
+            let mut plugins = self.plugins.write()
+                .map_err(|_| anyhow!("Failed to acquire write lock on plugins"))?;
+            
+            if plugins.remove(plugin_id).is_none() {
+                return Err(anyhow!("Plugin '{}' is not attached", plugin_id));
+            }
+        }
+        
+        if is_entry {
+            self.config.entry = None;
+        }
+        self.plugin_configs.remove(plugin_id);
+        self.updated_at = chrono::Utc::now().timestamp();
         
-        // Check if the intent is allowed
-        if !self.config.intents.contains(&intent.to_string()) {
-            return Err(anyhow!("Intent '{}' not allowed for this agent", intent));
+        Ok(())
+    }
+    
+    /// Release all plugin handles held by the agent
+    ///
+    /// The attachments are kept as placeholders, so the agent can still be inspected,
+    /// snapshotted or cloned with its plugins.
+    pub fn release_plugins(&self) -> Result<()> {
+        let mut plugins = self.plugins.write()
+            .map_err(|_| anyhow!("Failed to acquire write lock on plugins"))?;
+        
+        for (plugin_id, plugin) in plugins.iter_mut() {
+            *plugin = Arc::new(Plugin::placeholder(plugin_id));
         }
         
+        Ok(())
+    }
+    
+    /// Rename attached plugins, along with their configuration and the entry plugin
+    ///
+    /// Plugins not named in `map` keep their IDs. The attachments become placeholders.
+    pub(crate) fn remap_plugins(&mut self, map: &HashMap<PluginId, PluginId>) -> Result<()> {
+        if map.is_empty() {
+            return Ok(());
+        }
+        let rename = |plugin_id: &PluginId| map.get(plugin_id).unwrap_or(plugin_id).clone();
+        
+        let mut plugins = self.plugins.write()
+            .map_err(|_| anyhow!("Failed to acquire write lock on plugins"))?;
+        *plugins = plugins.keys()
+            .map(rename)
+            .map(|plugin_id| (plugin_id.clone(), Arc::new(Plugin::placeholder(&plugin_id))))
+            .collect();
+        drop(plugins);
+        
+        self.plugin_configs = self.plugin_configs.drain()
+            .map(|(plugin_id, config)| (rename(&plugin_id), config))
+            .collect();
+        self.config.entry = self.config.entry.as_ref().map(rename);
+        self.updated_at = chrono::Utc::now().timestamp();
+        
+        Ok(())
+    }
+    
+    /// Execute an intent
+    pub fn execute(&self, intent: &str) -> Result<serde_json::Value> {
+        self.execute_with_params(intent, &serde_json::Value::Null, &CallContext::detached(self.execution_timeout()))
+    }
+    
+    /// Get the execution timeout, falling back to the default when not configured
+    pub fn execution_timeout(&self) -> Duration {
+        self.config.execution_timeout_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_EXECUTION_TIMEOUT)
+    }
+    
+    /// Execute an intent with structured params within the given call context
+    pub fn execute_with_params(
+        &self,
+        intent: &str,
+        params: &serde_json::Value,
+        context: &CallContext,
+    ) -> Result<serde_json::Value> {
+        // Check if the agent is active and the intent is allowed
+        self.check_intent(intent)?;
+        
         // Get the entry plugin
-        let entry_plugin = match &self.config.entry {
-            Some(entry) => {
-                let plugins = self.plugins.read()
-                    .map_err(|_| anyhow!("Failed to acquire read lock on plugins"))?;
-                
-                plugins.get(entry).cloned()
-                    .ok_or_else(|| anyhow!("Entry plugin '{}' not attached", entry))
-            },
-            None => Err(anyhow!("No entry plugin defined for agent")),
-        }?;
+        let entry = self.entry_plugin_id()?;
+        let plugins = self.attached_plugins()?;
+        let entry_plugin = plugins.get(entry).cloned()
+            .ok_or_else(|| anyhow!("Entry plugin '{}' not attached", entry))?;
         
-        // Execute intent through the entry plugin
-        let result = entry_plugin.execute(intent, self.id(), &self.state)?;
+        // Execute intent through the entry plugin, with each plugin's configuration for this
+        // agent; its calls reach only the agent's other plugins
+        let context = context.clone()
+            .with_plugin_configs(self.plugin_configs.clone())
+            .with_attached_plugins(plugins);
+        let result = entry_plugin.execute(intent, params, self.id(), &self.state, &context)?;
         
         Ok(result)
     }
     
+    /// Check that the agent is active and allows an intent, returning the matching allow-list entry
+    pub fn check_intent(&self, intent: &str) -> Result<&str> {
+        if !self.status.is_runnable() {
+            return Err(anyhow!("Agent is not active"));
+        }
+        
+        self.config.matching_intent(intent)
+            .ok_or_else(|| anyhow!("Intent '{}' not allowed for this agent", intent))
+    }
+    
+    /// Get the ID of the entry plugin, checking that it is attached
+    pub fn entry_plugin_id(&self) -> Result<&PluginId> {
+        match &self.config.entry {
+            Some(entry) if self.has_plugin(entry) => Ok(entry),
+            Some(entry) => Err(anyhow!("Entry plugin '{}' not attached", entry)),
+            None => Err(anyhow!("No entry plugin defined for agent")),
+        }
+    }
+    
     /// Get agent state
     pub fn state(&self) -> &HashMap<String, serde_json::Value> {
         &self.state
     }
     
-    /// Set agent state value
-    pub fn set_state(&mut self, key: &str, value: serde_json::Value) {
-        self.state.insert(key.to_string(), value);
+    /// Get a shared handle to the agent state
+    pub fn shared_state(&self) -> &Arc<HashMap<String, serde_json::Value>> {
+        &self.state
+    }
+    
+    /// Get the size of the agent state in bytes of serialized JSON
+    pub fn state_size(&self) -> usize {
+        json_size(&*self.state)
+    }
+    
+    /// Set agent state value, failing if the serialized state would exceed `max_bytes`
+    ///
+    /// The map is copied only if a plugin still holds the previous version.
+    pub fn set_state(&mut self, key: &str, value: serde_json::Value, max_bytes: usize) -> Result<()> {
+        // Adjust the current size by the entry being replaced or added
+        let entry_size = |value: &serde_json::Value| json_size(key) + 1 + json_size(value);
+        let size = match self.state.get(key) {
+            Some(previous) => self.state_size() - entry_size(previous) + entry_size(&value),
+            None if self.state.is_empty() => self.state_size() + entry_size(&value),
+            None => self.state_size() + 1 + entry_size(&value),
+        };
+        if size > max_bytes {
+            anyhow::bail!("State of agent {} would be {} bytes (limit {})", self.id, size, max_bytes);
+        }
+        
+        Arc::make_mut(&mut self.state).insert(key.to_string(), value);
+        self.updated_at = chrono::Utc::now().timestamp();
+        Ok(())
+    }
+    
+    /// Replace the metadata, keeping the agent ID
+    pub(crate) fn set_metadata(&mut self, metadata: HashMap<String, serde_json::Value>) {
+        self.config.metadata = metadata;
+        self.updated_at = chrono::Utc::now().timestamp();
+    }
+    
+    /// Get up to `limit` of the most recent executions, newest first
+    pub fn execution_history(&self, limit: usize) -> Vec<ExecutionRecord> {
+        self.history.recent(limit)
+    }
+    
+    /// Add a finished execution to the history, keeping at most `capacity` records
+    pub(crate) fn record_execution(&self, record: ExecutionRecord, capacity: usize, persist: bool) {
+        self.history.record(record, capacity, persist);
+    }
+    
+    /// Get the agent's schedules
+    pub fn schedules(&self) -> &[Schedule] {
+        &self.schedules
+    }
+    
+    /// Add a schedule
+    pub(crate) fn add_schedule(&mut self, schedule: Schedule) {
+        self.schedules.push(schedule);
         self.updated_at = chrono::Utc::now().timestamp();
     }
+    
+    /// Remove a schedule, returning whether it existed
+    pub(crate) fn remove_schedule(&mut self, schedule_id: &ScheduleId) -> bool {
+        let before = self.schedules.len();
+        self.schedules.retain(|schedule| &schedule.id != schedule_id);
+        self.updated_at = chrono::Utc::now().timestamp();
+        self.schedules.len() < before
+    }
+    
+    /// Replace the whole state map, e.g. with a newer write-through copy from storage
+    pub(crate) fn restore_state(&mut self, state: HashMap<String, serde_json::Value>, updated_at: i64) {
+        self.state = Arc::new(state);
+        self.updated_at = self.updated_at.max(updated_at);
+    }
+}
+
+/// Serde helpers persisting attached plugins as a list of plugin IDs
+///
+/// Only the IDs are stored; on load each ID is restored as a placeholder plugin
+/// which the kernel resolves through the PluginManager before execution.
+mod plugin_ids {
+    use std::collections::HashMap;
+    use std::sync::{Arc, RwLock};
+    use serde::{Serialize, Deserialize, Serializer, Deserializer};
+    
+    use crate::plugin::{Plugin, PluginId};
+    
+    type PluginMap = Arc<RwLock<HashMap<PluginId, Arc<Plugin>>>>;
+    
+    pub fn serialize<S: Serializer>(plugins: &PluginMap, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut ids: Vec<PluginId> = plugins.read()
+            .map_err(|_| serde::ser::Error::custom("Failed to acquire read lock on plugins"))?
+            .keys()
+            .cloned()
+            .collect();
+        ids.sort();
+        ids.serialize(serializer)
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PluginMap, D::Error> {
+        let ids: Vec<PluginId> = Vec::deserialize(deserializer)?;
+        let plugins = ids.into_iter()
+            .map(|id| {
+                let placeholder = Arc::new(Plugin::placeholder(&id));
+                (id, placeholder)
+            })
+            .collect();
+        Ok(Arc::new(RwLock::new(plugins)))
+    }
+}
+
+/// Length of a value serialized as JSON, counted without building the string
+fn json_size<T: Serialize + ?Sized>(value: &T) -> usize {
+    struct Counter(usize);
+    
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+        
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    let mut counter = Counter(0);
+    // Writing to the counter can't fail, and JSON values and strings always serialize
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+/// Merge a JSON object into agent metadata, returning the sorted keys that changed
+///
+/// Entries in `patch` replace existing ones; a `null` value removes the key.
+pub(crate) fn merge_metadata(
+    metadata: &mut HashMap<String, serde_json::Value>,
+    patch: serde_json::Map<String, serde_json::Value>,
+) -> Vec<String> {
+    let mut changed = Vec::new();
+    for (key, value) in patch {
+        if metadata.get(&key) == Some(&value).filter(|value| !value.is_null()) {
+            continue;
+        }
+        if value.is_null() {
+            if metadata.remove(&key).is_none() {
+                continue;
+            }
+        } else {
+            metadata.insert(key.clone(), value);
+        }
+        changed.push(key);
+    }
+    changed.sort();
+    changed
 }
 
 /// Generate an agent ID from config
 pub fn generate_agent_id(config: &AgentConfig) -> AgentId {
+    generate_agent_id_with_nonce(config, None)
+}
+
+/// Generate an agent ID from config and an optional nonce (e.g. an owner's public key)
+///
+/// Different nonces give agents with identical configs distinct IDs.
+pub fn generate_agent_id_with_nonce(config: &AgentConfig, nonce: Option<&str>) -> AgentId {
     // Serialize the config to JSON for hashing
     let config_json = serde_json::to_string(config).unwrap_or_default();
     
     // Generate a deterministic hash using BLAKE3 (faster than SHA3 for this purpose)
-    let hash = blake3::hash(config_json.as_bytes());
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(config_json.as_bytes());
+    if let Some(nonce) = nonce {
+        hasher.update(b"\0");
+        hasher.update(nonce.as_bytes());
+    }
+    let hash = hasher.finalize();
     
     // Format as hex string for readability
     format!("agent_{}", hash.to_hex().chars().take(16).collect::<String>())
@@ -220,10 +871,118 @@ mod tests {
             intents: vec!["greet".to_string()],
             hm: HardwareConstraints::default(),
             metadata: HashMap::new(),
+            execution_timeout_ms: None,
         };
         
         let id = generate_agent_id(&config);
         assert!(id.starts_with("agent_"));
         assert_eq!(id.len(), 22); // "agent_" + 16 hex chars
     }
+    
+    #[test]
+    fn test_detach_plugin() {
+        let config = AgentConfig {
+            name: "detach_agent".to_string(),
+            entry: Some("entry_plugin".to_string()),
+            intents: vec!["greet".to_string()],
+            ..Default::default()
+        };
+        let mut agent = Agent::new(generate_agent_id(&config), config);
+        agent.attach_plugin(&"entry_plugin".to_string()).unwrap();
+        agent.attach_plugin(&"helper_plugin".to_string()).unwrap();
+        
+        // Attached plugin
+        agent.detach_plugin(&"helper_plugin".to_string(), false).unwrap();
+        assert!(!agent.has_plugin(&"helper_plugin".to_string()));
+        
+        // Non-attached plugin
+        assert!(agent.detach_plugin(&"helper_plugin".to_string(), false).is_err());
+        
+        // Entry plugin is refused unless the entry is cleared
+        assert!(agent.detach_plugin(&"entry_plugin".to_string(), false).is_err());
+        assert!(agent.has_plugin(&"entry_plugin".to_string()));
+        
+        agent.detach_plugin(&"entry_plugin".to_string(), true).unwrap();
+        assert!(!agent.has_plugin(&"entry_plugin".to_string()));
+        assert_eq!(agent.config().entry, None);
+    }
+    
+    #[test]
+    fn test_plugin_ids_roundtrip() {
+        let config = AgentConfig::default();
+        let agent = Agent::new(generate_agent_id(&config), config);
+        agent.attach_plugin(&"b_plugin".to_string()).unwrap();
+        agent.attach_plugin(&"a_plugin".to_string()).unwrap();
+        
+        let json = serde_json::to_value(&agent).unwrap();
+        assert_eq!(json["plugins"], serde_json::json!(["a_plugin", "b_plugin"]));
+        
+        let restored: Agent = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.plugin_ids(), vec!["a_plugin".to_string(), "b_plugin".to_string()]);
+        assert_eq!(restored.unloaded_plugin_ids().len(), 2);
+    }
+    
+    #[test]
+    fn test_agent_id_nonce() {
+        let config = AgentConfig::default();
+        let id = generate_agent_id(&config);
+        
+        assert_eq!(generate_agent_id_with_nonce(&config, None), id);
+        assert_eq!(generate_agent_id_with_nonce(&config, Some("key_a")), generate_agent_id_with_nonce(&config, Some("key_a")));
+        assert_ne!(generate_agent_id_with_nonce(&config, Some("key_a")), id);
+        assert_ne!(generate_agent_id_with_nonce(&config, Some("key_a")), generate_agent_id_with_nonce(&config, Some("key_b")));
+    }
+    
+    #[test]
+    fn test_state_size_limit() {
+        let config = AgentConfig::default();
+        let mut agent = Agent::new(generate_agent_id(&config), config);
+        assert_eq!(agent.state_size(), 2);
+        
+        // {"a":1} exactly hits the cap
+        agent.set_state("a", serde_json::json!(1), 7).unwrap();
+        assert_eq!(agent.state_size(), 7);
+        
+        // {"a":1,"b":2} would exceed it, leaving the state untouched
+        assert!(agent.set_state("b", serde_json::json!(2), 12).is_err());
+        assert_eq!(agent.state().len(), 1);
+        
+        // Replacing an entry only counts the difference
+        agent.set_state("a", serde_json::json!(10), 8).unwrap();
+        agent.set_state("b", serde_json::json!("é"), 17).unwrap();
+        assert_eq!(agent.state_size(), serde_json::to_vec(agent.state()).unwrap().len());
+        assert_eq!(agent.info().state_size, 17);
+    }
+    
+    #[test]
+    fn test_merge_metadata() {
+        let mut metadata: HashMap<String, serde_json::Value> = serde_json::from_value(
+            serde_json::json!({"owner": "ops", "tier": 1, "stale": true})
+        ).unwrap();
+        let patch = serde_json::json!({"tier": 2, "owner": "ops", "stale": null, "missing": null, "region": "eu"});
+        
+        let changed = merge_metadata(&mut metadata, patch.as_object().unwrap().clone());
+        assert_eq!(changed, vec!["region", "stale", "tier"]);
+        assert_eq!(serde_json::to_value(&metadata).unwrap(), serde_json::json!({"owner": "ops", "tier": 2, "region": "eu"}));
+    }
+    
+    #[test]
+    fn test_status_transitions() {
+        assert!(AgentStatus::Active.can_transition_to(AgentStatus::Paused));
+        assert!(AgentStatus::Recovered.can_transition_to(AgentStatus::Terminated));
+        assert!(AgentStatus::Paused.can_transition_to(AgentStatus::Active));
+        assert!(AgentStatus::Paused.can_transition_to(AgentStatus::Terminated));
+        assert!(AgentStatus::Recovered.can_transition_to(AgentStatus::Quarantined));
+        assert!(AgentStatus::Quarantined.can_transition_to(AgentStatus::Active));
+        assert!(AgentStatus::Quarantined.can_transition_to(AgentStatus::Terminated));
+        assert!(!AgentStatus::Quarantined.is_runnable());
+        
+        // Invalid transitions
+        assert!(!AgentStatus::Active.can_transition_to(AgentStatus::Active));
+        assert!(!AgentStatus::Paused.can_transition_to(AgentStatus::Paused));
+        assert!(!AgentStatus::Terminated.can_transition_to(AgentStatus::Active));
+        assert!(!AgentStatus::Terminated.can_transition_to(AgentStatus::Paused));
+        assert!(!AgentStatus::Paused.can_transition_to(AgentStatus::Quarantined));
+        assert!(!AgentStatus::Quarantined.can_transition_to(AgentStatus::Paused));
+    }
 }
@@ -0,0 +1,170 @@
+//! Signed trace anchors for MCP-ZERO kernel
+//!
+//! With a signing key configured, the tracer signs trace chain heads with ed25519 and
+//! records each signature in the trace as a `trace.anchor` entry. Publishing the latest
+//! anchor externally, such as to a transparency log, pins the trace up to that point:
+//! rewriting an anchored entry breaks the hash chain leading to a signed head, and the
+//! head can't be re-signed without the key.
+
+use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Serialize, Deserialize};
+
+use crate::agent::AgentId;
+use crate::encryption::{decode_hex, encode_hex, parse_key_hex, read_key_bytes};
+use crate::trace::{TraceEntry, TraceId};
+
+/// Event type of anchor entries
+pub const ANCHOR_EVENT: &str = "trace.anchor";
+
+/// An ed25519 key trace chain heads are signed with
+pub struct TraceSigningKey {
+    key: SigningKey,
+}
+
+impl TraceSigningKey {
+    /// Create a key from its 32-byte secret
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let secret: [u8; 32] = bytes.try_into()
+            .map_err(|_| anyhow!("Trace signing keys must be 32 bytes, got {}", bytes.len()))?;
+        Ok(Self { key: SigningKey::from_bytes(&secret) })
+    }
+    
+    /// Create a key from 64 hex digits
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        Self::from_bytes(&parse_key_hex(hex).context("Invalid trace signing key")?)
+    }
+    
+    /// Read a key file holding either 64 hex digits or 32 raw bytes
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_bytes(&read_key_bytes(path.as_ref(), "trace signing key")?)
+    }
+    
+    /// Hex-encoded public key anchors are verified with
+    pub fn public_key(&self) -> String {
+        encode_hex(self.key.verifying_key().as_bytes())
+    }
+    
+    /// Sign the head of a trace's chain
+    pub(crate) fn anchor(&self, agent_id: &AgentId, trace_id: &TraceId, sequence: u64, head_hash: &str, timestamp: i64) -> TraceAnchor {
+        let mut anchor = TraceAnchor {
+            agent_id: agent_id.clone(),
+            trace_id: trace_id.clone(),
+            sequence,
+            head_hash: head_hash.to_string(),
+            timestamp,
+            public_key: self.public_key(),
+            signature: String::new(),
+        };
+        anchor.signature = encode_hex(&self.key.sign(anchor.message().as_bytes()).to_bytes());
+        anchor
+    }
+}
+
+impl std::fmt::Debug for TraceSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TraceSigningKey").field("public_key", &self.public_key()).finish_non_exhaustive()
+    }
+}
+
+/// A signed trace chain head
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceAnchor {
+    pub agent_id: AgentId,
+    pub trace_id: TraceId,
+    
+    /// Position of the anchor among the trace's anchors, from 1
+    pub sequence: u64,
+    
+    /// Hash of the entry the anchor follows
+    pub head_hash: String,
+    
+    pub timestamp: i64,
+    
+    /// Hex-encoded ed25519 public key of the signer
+    pub public_key: String,
+    
+    /// Hex-encoded ed25519 signature
+    pub signature: String,
+}
+
+impl TraceAnchor {
+    /// The anchor recorded in a `trace.anchor` entry
+    pub fn from_entry(entry: &TraceEntry) -> Option<Self> {
+        if entry.event_type != ANCHOR_EVENT {
+            return None;
+        }
+        Some(Self {
+            agent_id: entry.agent_id.clone(),
+            trace_id: entry.id.clone(),
+            sequence: entry.data["sequence"].as_u64()?,
+            head_hash: entry.data["head_hash"].as_str()?.to_string(),
+            timestamp: entry.timestamp,
+            public_key: entry.data["public_key"].as_str()?.to_string(),
+            signature: entry.data["signature"].as_str()?.to_string(),
+        })
+    }
+    
+    /// Data of the anchor's entry; the entry itself carries the trace, agent and timestamp
+    pub(crate) fn entry_data(&self) -> serde_json::Value {
+        serde_json::json!({
+            "sequence": self.sequence,
+            "head_hash": self.head_hash,
+            "public_key": self.public_key,
+            "signature": self.signature
+        })
+    }
+    
+    /// Whether the signature is valid for the anchor's public key
+    ///
+    /// Check the public key against the one published for the kernel as well; anyone
+    /// can sign with a key of their own.
+    pub fn verify(&self) -> bool {
+        let verifying_key = decode_hex(&self.public_key).ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok());
+        let signature = decode_hex(&self.signature).ok()
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+            .map(|bytes| Signature::from_bytes(&bytes));
+        match (verifying_key, signature) {
+            (Some(key), Some(signature)) => key.verify_strict(self.message().as_bytes(), &signature).is_ok(),
+            _ => false,
+        }
+    }
+    
+    /// The signed message
+    fn message(&self) -> String {
+        format!("mcp-anchor:1:{}:{}:{}:{}:{}", self.agent_id, self.trace_id, self.sequence, self.head_hash, self.timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_anchor_signatures() {
+        let key = TraceSigningKey::from_hex(&"07".repeat(32)).unwrap();
+        let anchor = key.anchor(&"agent_1".to_string(), &"trace_1".to_string(), 1, "abc", 1_700_000_000);
+        assert!(anchor.verify());
+        assert_eq!(anchor.public_key, key.public_key());
+        
+        // Every signed field is covered
+        let mut moved = anchor.clone();
+        moved.head_hash = "abd".to_string();
+        assert!(!moved.verify());
+        let mut renumbered = anchor.clone();
+        renumbered.sequence = 2;
+        assert!(!renumbered.verify());
+        
+        // A signature only verifies under the signer's key
+        let other = TraceSigningKey::from_bytes(&[8; 32]).unwrap();
+        let mut resigned = anchor.clone();
+        resigned.public_key = other.public_key();
+        assert!(!resigned.verify());
+        
+        assert!(TraceSigningKey::from_bytes(&[7; 31]).is_err());
+        assert!(TraceSigningKey::from_hex("not hex").is_err());
+    }
+}
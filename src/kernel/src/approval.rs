@@ -0,0 +1,120 @@
+//! Operator approval of risky plugin attachments for MCP-ZERO kernel
+//!
+//! With plugin risk scoring on, attaching a plugin whose risk score falls between the
+//! approval and deny thresholds is held as a pending approval rather than refused.
+//! An operator grants it with `MCPKernel::approve_pending`, which completes the
+//! attachment. Pending approvals are kept in memory and don't survive a restart.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use serde::Serialize;
+
+use crate::agent::AgentId;
+use crate::error::KernelError;
+use crate::ethical::RiskAssessment;
+use crate::plugin::PluginId;
+
+/// Approval ID type
+pub type ApprovalId = String;
+
+/// Event type of the trace entries recording attachments held for approval
+pub const APPROVAL_REQUIRED_EVENT: &str = "ethics.approval_required";
+
+/// Event type of the trace entries recording granted approvals
+pub const APPROVAL_GRANTED_EVENT: &str = "ethics.approval_granted";
+
+/// Plugin attachment waiting for operator approval
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingApproval {
+    pub id: ApprovalId,
+    pub agent_id: AgentId,
+    pub plugin_id: PluginId,
+    
+    /// Risk score of the attachment
+    pub score: f64,
+    
+    /// Rules that held on the way to the score
+    pub factors: Vec<String>,
+    
+    /// Version of the ethics policy that scored the attachment
+    pub policy_version: String,
+    
+    pub requested_at: i64,
+    
+    /// Agent-specific plugin config to attach with, left out as it may hold secrets
+    #[serde(skip)]
+    pub(crate) config: serde_json::Value,
+}
+
+/// Pending approvals, oldest first
+#[derive(Debug)]
+pub(crate) struct Approvals {
+    pending: Mutex<Vec<PendingApproval>>,
+    next_id: AtomicU64,
+}
+
+impl Approvals {
+    pub(crate) fn new() -> Self {
+        Self { pending: Mutex::new(Vec::new()), next_id: AtomicU64::new(1) }
+    }
+    
+    /// Hold an attachment for approval
+    ///
+    /// Asking again for a plugin already waiting on the agent updates the existing
+    /// approval instead of adding another.
+    pub(crate) fn request(
+        &self,
+        agent_id: &AgentId,
+        plugin_id: &PluginId,
+        assessment: &RiskAssessment,
+        config: serde_json::Value,
+    ) -> PendingApproval {
+        let mut pending = self.lock_pending();
+        let existing = pending.iter().position(|approval| &approval.agent_id == agent_id && &approval.plugin_id == plugin_id);
+        let id = match existing {
+            Some(index) => pending.remove(index).id,
+            None => format!("approval_{}", self.next_id.fetch_add(1, Ordering::Relaxed)),
+        };
+        
+        let approval = PendingApproval {
+            id,
+            agent_id: agent_id.clone(),
+            plugin_id: plugin_id.clone(),
+            score: assessment.score,
+            factors: assessment.factors.clone(),
+            policy_version: assessment.verdict.policy_version.clone(),
+            requested_at: chrono::Utc::now().timestamp(),
+            config,
+        };
+        pending.push(approval.clone());
+        approval
+    }
+    
+    /// Remove an approval to act on it
+    pub(crate) fn take(&self, approval_id: &ApprovalId) -> Result<PendingApproval, KernelError> {
+        let mut pending = self.lock_pending();
+        let index = pending.iter().position(|approval| &approval.id == approval_id)
+            .ok_or_else(|| KernelError::ApprovalNotFound { approval_id: approval_id.clone() })?;
+        Ok(pending.remove(index))
+    }
+    
+    /// Put back an approval that couldn't be acted on
+    pub(crate) fn restore(&self, approval: PendingApproval) {
+        let mut pending = self.lock_pending();
+        let index = pending.partition_point(|other| other.requested_at <= approval.requested_at);
+        pending.insert(index, approval);
+    }
+    
+    pub(crate) fn list(&self) -> Vec<PendingApproval> {
+        self.lock_pending().clone()
+    }
+    
+    /// Drop an agent's approvals, once it's terminated
+    pub(crate) fn forget_agent(&self, agent_id: &AgentId) {
+        self.lock_pending().retain(|approval| &approval.agent_id != agent_id);
+    }
+    
+    fn lock_pending(&self) -> MutexGuard<'_, Vec<PendingApproval>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,187 @@
+//! Portable agent archives for MCP-ZERO
+//!
+//! `MCPKernel::export_agent` packs an agent into a single tar.gz so it can be moved
+//! between environments. The archive holds the agent snapshot, the capability files
+//! of its plugins, and `manifest.json`, which records the exporting kernel's version,
+//! the agent's plugins and schedules, and a BLAKE3 hash of every other file.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Serialize, Deserialize};
+
+use crate::agent::AgentId;
+use crate::plugin::PluginId;
+use crate::schedule::Schedule;
+use crate::storage;
+
+/// Archive layout version written by this kernel
+pub(crate) const ARCHIVE_FORMAT: u32 = 1;
+
+/// Name of the manifest inside an archive
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Name of the agent snapshot inside an archive
+pub(crate) const AGENT_FILE: &str = "agent.json";
+
+/// Name of a plugin's capabilities file inside an archive
+pub(crate) fn capabilities_file(plugin_id: &PluginId) -> String {
+    format!("plugins/{}.cap.yaml", plugin_id)
+}
+
+/// Description of an exported agent, stored as manifest.json
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// Archive layout version
+    pub format: u32,
+    
+    /// Version of the kernel that exported the agent
+    pub kernel_version: String,
+    
+    /// Exported agent
+    pub agent_id: AgentId,
+    
+    /// Export timestamp
+    pub exported_at: i64,
+    
+    /// Plugins attached to the agent
+    pub plugins: Vec<PluginId>,
+    
+    /// Recurring executions of the agent
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+    
+    /// BLAKE3 hash of every other file in the archive, by name
+    pub files: BTreeMap<String, String>,
+}
+
+/// Write an archive of `files`, recording their hashes in the manifest
+pub(crate) fn write(path: &Path, mut manifest: ArchiveManifest, files: Vec<(String, Vec<u8>)>) -> Result<ArchiveManifest> {
+    manifest.files = files.iter()
+        .map(|(name, data)| (name.clone(), blake3::hash(data).to_hex().to_string()))
+        .collect();
+    let manifest_data = serde_json::to_vec_pretty(&manifest)
+        .context("Failed to serialize archive manifest")?;
+    
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (name, data) in std::iter::once((MANIFEST_FILE.to_string(), manifest_data)).chain(files) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.exported_at.max(0) as u64);
+        builder.append_data(&mut header, &name, data.as_slice())
+            .with_context(|| format!("Failed to add {} to archive", name))?;
+    }
+    let archive = builder.into_inner()
+        .and_then(|encoder| encoder.finish())
+        .context("Failed to compress archive")?;
+    
+    storage::write_atomic(path, &archive)
+        .with_context(|| format!("Failed to write archive: {}", path.display()))?;
+    Ok(manifest)
+}
+
+/// Read an archive, checking its format and every file against the manifest
+pub(crate) fn read(path: &Path) -> Result<(ArchiveManifest, BTreeMap<String, Vec<u8>>)> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open archive: {}", path.display()))?;
+    
+    let mut files = BTreeMap::new();
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    for entry in archive.entries().context("Failed to read archive")? {
+        let mut entry = entry.context("Failed to read archive entry")?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)
+            .with_context(|| format!("Failed to read {} from archive", name))?;
+        files.insert(name, data);
+    }
+    
+    let manifest: ArchiveManifest = files.remove(MANIFEST_FILE)
+        .ok_or_else(|| anyhow!("Archive has no {}", MANIFEST_FILE))
+        .and_then(|data| serde_json::from_slice(&data).context("Archive manifest is not valid"))?;
+    if manifest.format != ARCHIVE_FORMAT {
+        return Err(anyhow!(
+            "Unsupported archive format {} (this kernel reads format {})",
+            manifest.format, ARCHIVE_FORMAT,
+        ));
+    }
+    
+    // Every file must be listed with a matching hash, and nothing else may be present
+    for (name, expected) in &manifest.files {
+        let data = files.get(name)
+            .ok_or_else(|| anyhow!("Archive is missing {}", name))?;
+        let actual = blake3::hash(data).to_hex();
+        if actual.as_str() != expected {
+            return Err(anyhow!("Archive file {} is corrupted: expected hash {}, found {}", name, expected, actual));
+        }
+    }
+    if let Some(name) = files.keys().find(|name| !manifest.files.contains_key(*name)) {
+        return Err(anyhow!("Archive file {} is not listed in the manifest", name));
+    }
+    
+    Ok((manifest, files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn manifest() -> ArchiveManifest {
+        ArchiveManifest {
+            format: ARCHIVE_FORMAT,
+            kernel_version: env!("CARGO_PKG_VERSION").to_string(),
+            agent_id: "agent_1".to_string(),
+            exported_at: 1_700_000_000,
+            plugins: vec!["echo".to_string()],
+            schedules: Vec::new(),
+            files: BTreeMap::new(),
+        }
+    }
+    
+    #[test]
+    fn test_archive_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent.tar.gz");
+        let files = vec![
+            (AGENT_FILE.to_string(), b"{\"id\":\"agent_1\"}".to_vec()),
+            (capabilities_file(&"echo".to_string()), b"network: false\n".to_vec()),
+        ];
+        let written = write(&path, manifest(), files.clone()).unwrap();
+        assert_eq!(written.files.len(), 2);
+        
+        let (read_manifest, read_files) = read(&path).unwrap();
+        assert_eq!(read_manifest, written);
+        assert_eq!(read_files.into_iter().collect::<Vec<_>>(), {
+            let mut files = files;
+            files.sort();
+            files
+        });
+    }
+    
+    #[test]
+    fn test_archive_rejects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent.tar.gz");
+        let mut tampered = write(&path, manifest(), vec![(AGENT_FILE.to_string(), b"{}".to_vec())]).unwrap();
+        
+        // A file whose hash no longer matches the manifest
+        tampered.files.insert(AGENT_FILE.to_string(), blake3::hash(b"[]").to_hex().to_string());
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (name, data) in [(MANIFEST_FILE, serde_json::to_vec(&tampered).unwrap()), (AGENT_FILE, b"{}".to_vec())] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            builder.append_data(&mut header, name, data.as_slice()).unwrap();
+        }
+        std::fs::write(&path, builder.into_inner().unwrap().finish().unwrap()).unwrap();
+        assert!(read(&path).unwrap_err().to_string().contains("is corrupted"));
+        
+        let future = ArchiveManifest { format: ARCHIVE_FORMAT + 1, ..manifest() };
+        write(&path, future, Vec::new()).unwrap();
+        assert!(read(&path).unwrap_err().to_string().contains("Unsupported archive format"));
+    }
+}
@@ -0,0 +1,243 @@
+//! Background snapshots for MCP-ZERO kernel
+//!
+//! A kernel-owned thread snapshots agents whose `updated_at` moved since their last
+//! snapshot, so a crash loses at most about one interval of changes. The writes of a
+//! round are spread evenly over the interval rather than issued back to back, and
+//! agents in the middle of an execution are left for the next round.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use dashmap::DashMap;
+use serde::{Serialize, Deserialize};
+
+use crate::agent::{Agent, AgentId};
+use crate::events::{EventBus, KernelEvent};
+use crate::shutdown::ExecutionGate;
+use crate::storage::StorageBackend;
+use crate::trace::Tracer;
+use crate::worker::{BackgroundWorker, StopSignal};
+
+/// Counters of background snapshots, reported in `KernelStats`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoSnapshotStats {
+    /// Snapshots written
+    pub taken: u64,
+    
+    /// Changed agents passed over because they were executing
+    pub skipped: u64,
+    
+    /// Snapshots that failed to write
+    pub failed: u64,
+}
+
+/// Kernel components the snapshot thread works with
+pub(crate) struct AutoSnapshotContext {
+    pub(crate) agents: Arc<DashMap<AgentId, Agent>>,
+    pub(crate) storage: Arc<dyn StorageBackend>,
+    pub(crate) tracer: Arc<dyn Tracer>,
+    pub(crate) events: Arc<EventBus>,
+    pub(crate) gate: Arc<ExecutionGate>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    /// When each agent's last snapshot was started, in Unix seconds
+    snapshotted_at: Mutex<HashMap<AgentId, i64>>,
+    
+    /// Held while any snapshot is written, as the storage backends don't expect two
+    /// writes of one agent at once; always taken after the agent store's locks
+    writing: Mutex<()>,
+    
+    taken: AtomicU64,
+    skipped: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl Shared {
+    fn lock_writing(&self) -> std::sync::MutexGuard<'_, ()> {
+        self.writing.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    fn lock_snapshotted_at(&self) -> std::sync::MutexGuard<'_, HashMap<AgentId, i64>> {
+        self.snapshotted_at.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Loaded agents updated since their last snapshot, ordered by ID
+    ///
+    /// `updated_at` has one-second resolution, so an agent updated in the second its
+    /// snapshot started counts as changed; that costs a spare snapshot, never a lost update.
+    fn changed_agents(&self, agents: &DashMap<AgentId, Agent>) -> Vec<AgentId> {
+        // Read the agents before taking the lock, which is also taken while agents are held
+        let updated: Vec<(AgentId, i64)> = agents.iter()
+            .map(|agent| (agent.key().clone(), agent.updated_at()))
+            .collect();
+        
+        let mut snapshotted_at = self.lock_snapshotted_at();
+        snapshotted_at.retain(|agent_id, _| updated.iter().any(|(id, _)| id == agent_id));
+        let mut changed: Vec<AgentId> = updated.into_iter()
+            .filter(|(agent_id, updated_at)| snapshotted_at.get(agent_id).is_none_or(|at| updated_at >= at))
+            .map(|(agent_id, _)| agent_id)
+            .collect();
+        changed.sort();
+        changed
+    }
+    
+    /// Snapshot one agent unless it is executing
+    fn snapshot(&self, context: &AutoSnapshotContext, agent_id: &AgentId) {
+        if context.gate.is_executing(agent_id) {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+            tracing::debug!("Skipping background snapshot of agent {}: it is executing", agent_id);
+            return;
+        }
+        
+        let started_at = chrono::Utc::now().timestamp();
+        let Some(agent) = context.agents.get(agent_id) else {
+            return;
+        };
+        let updated_at = agent.updated_at();
+        let result = {
+            let _writing = self.lock_writing();
+            context.storage.save_agent(agent_id, &agent)
+        };
+        drop(agent);
+        
+        match result {
+            Ok(()) => {
+                self.lock_snapshotted_at().insert(agent_id.clone(), started_at);
+                self.taken.fetch_add(1, Ordering::Relaxed);
+                
+                let timestamp = chrono::Utc::now().timestamp();
+                let event = serde_json::json!({"updated_at": updated_at, "timestamp": timestamp});
+                if let Err(e) = context.tracer.record_event(agent_id, "agent.auto_snapshot", &event) {
+                    tracing::warn!("Failed to trace background snapshot of agent {}: {}", agent_id, e);
+                }
+                context.events.publish(|| KernelEvent::SnapshotTaken { agent_id: agent_id.clone(), timestamp });
+            },
+            Err(e) => {
+                self.failed.fetch_add(1, Ordering::Relaxed);
+                context.events.alert_storage_error(agent_id, &e);
+                tracing::warn!("Background snapshot of agent {} failed: {:#}", agent_id, e);
+            },
+        }
+    }
+}
+
+/// Periodic snapshots of changed agents on a background thread
+#[derive(Debug, Default)]
+pub(crate) struct AutoSnapshotter {
+    shared: Arc<Shared>,
+    worker: BackgroundWorker,
+}
+
+impl AutoSnapshotter {
+    /// Start the snapshot thread, running a round every `interval`
+    pub(crate) fn start(&self, context: AutoSnapshotContext, interval: Duration) {
+        let shared = self.shared.clone();
+        self.worker.start("mcp-auto-snapshot", move |signal| run_rounds(signal, &shared, &context, interval));
+    }
+    
+    /// Hold off background snapshots while the kernel writes one itself
+    pub(crate) fn lock_writing(&self) -> std::sync::MutexGuard<'_, ()> {
+        self.shared.lock_writing()
+    }
+    
+    /// Record a snapshot of an agent's loaded state started at `at` (Unix seconds) elsewhere,
+    /// so unchanged agents aren't written again
+    pub(crate) fn mark_snapshotted(&self, agent_id: &AgentId, at: i64) {
+        self.shared.lock_snapshotted_at().insert(agent_id.clone(), at);
+    }
+    
+    /// Counters of the snapshots taken so far
+    pub(crate) fn stats(&self) -> AutoSnapshotStats {
+        AutoSnapshotStats {
+            taken: self.shared.taken.load(Ordering::Relaxed),
+            skipped: self.shared.skipped.load(Ordering::Relaxed),
+            failed: self.shared.failed.load(Ordering::Relaxed),
+        }
+    }
+    
+    /// Stop the thread and wait for a snapshot in progress to finish
+    pub(crate) fn stop(&self) {
+        self.worker.stop();
+    }
+}
+
+/// Snapshot changed agents, one round per interval, until stopped
+fn run_rounds(signal: &StopSignal, shared: &Shared, context: &AutoSnapshotContext, interval: Duration) {
+    // Leave the kernel to finish starting up before the first round
+    if signal.wait(interval) {
+        return;
+    }
+    loop {
+        let changed = shared.changed_agents(&context.agents);
+        if changed.is_empty() {
+            if signal.wait(interval) {
+                return;
+            }
+            continue;
+        }
+        
+        let spacing = interval / changed.len() as u32;
+        for agent_id in &changed {
+            shared.snapshot(context, agent_id);
+            if signal.wait(spacing) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentConfig;
+    use crate::storage::MemoryStorage;
+    use crate::trace::PoseidonTracer;
+    
+    fn agent(name: &str) -> Agent {
+        let config = AgentConfig { name: name.to_string(), ..Default::default() };
+        Agent::new(crate::agent::generate_agent_id(&config), config)
+    }
+    
+    #[test]
+    fn test_auto_snapshots_changed_agents() {
+        let agents = Arc::new(DashMap::new());
+        let storage = Arc::new(MemoryStorage::new());
+        let gate = Arc::new(ExecutionGate::default());
+        let (idle, busy) = (agent("idle"), agent("busy"));
+        let (idle_id, busy_id) = (idle.id().clone(), busy.id().clone());
+        agents.insert(idle_id.clone(), idle);
+        agents.insert(busy_id.clone(), busy);
+        
+        let permit = gate.enter(&busy_id).unwrap();
+        let snapshotter = AutoSnapshotter::default();
+        snapshotter.start(AutoSnapshotContext {
+            agents: agents.clone(),
+            storage: storage.clone(),
+            tracer: Arc::new(PoseidonTracer::new()),
+            events: Arc::new(EventBus::new(8)),
+            gate: gate.clone(),
+        }, Duration::from_millis(20));
+        
+        // The executing agent waits for a later round
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while snapshotter.stats().skipped < 2 && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(storage.has_agent(&idle_id));
+        assert!(!storage.has_agent(&busy_id));
+        
+        drop(permit);
+        while !storage.has_agent(&busy_id) && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        snapshotter.stop();
+        assert!(storage.has_agent(&busy_id));
+        
+        let stats = snapshotter.stats();
+        assert!(stats.taken >= 2);
+        assert_eq!(stats.failed, 0);
+    }
+}
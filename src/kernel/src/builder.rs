@@ -0,0 +1,376 @@
+//! Kernel construction for MCP-ZERO
+//!
+//! [`KernelBuilder`] assembles an [`MCPKernel`] from a configuration plus optional
+//! replacements for its components; anything not supplied is built from the config.
+
+use std::sync::Arc;
+use dashmap::DashMap;
+
+use crate::anchor::TraceSigningKey;
+use crate::autosnapshot::{AutoSnapshotContext, AutoSnapshotter};
+use crate::config::{KernelConfig, StorageBackendKind};
+use crate::encryption::StorageEncryption;
+use crate::error::KernelError;
+use crate::ethical::{Decision, DecisionHook, EthicalBinaryTree, ETHICS_DECISION_EVENT};
+use crate::events::EventBus;
+use crate::hasher::{self, Sha3Hasher};
+use crate::jobs::JobQueue;
+use crate::approval::Approvals;
+use crate::plugin::{PluginManager, PluginStatsReport};
+use crate::policy_watch::{PolicyReloadContext, PolicyWatcher};
+use crate::retention::{TraceMaintenance, TraceMaintenanceContext};
+use crate::schedule::Scheduler;
+#[cfg(feature = "sqlite")]
+use crate::sqlite::{SqliteStorage, SQLITE_FILE};
+use crate::shutdown::ExecutionGate;
+use crate::stats::KernelCounters;
+use crate::storage::{self, MemoryStorage, StorageBackend, StorageError, StorageManager, UnavailableStorage};
+use crate::trace::{PoseidonTracer, Tracer};
+use crate::{MCPKernel, KERNEL_TRACE_AGENT};
+
+/// Builder for [`MCPKernel`]
+pub struct KernelBuilder {
+    /// Kernel configuration
+    config: KernelConfig,
+    
+    /// Plugin manager, built from the config when unset
+    plugin_manager: Option<PluginManager>,
+    
+    /// Tracer, a PoseidonTracer when unset
+    tracer: Option<Arc<dyn Tracer>>,
+    
+    /// Ethical decision tree, the config's ethics policy or the default tree when unset
+    ethical_engine: Option<EthicalBinaryTree>,
+    
+    /// Storage backend, the config's storage directory when unset
+    storage: Option<Arc<dyn StorageBackend>>,
+    
+    /// Whether to install a global log subscriber
+    log_subscriber: bool,
+}
+
+impl KernelBuilder {
+    /// Create a builder with the default configuration
+    pub fn new() -> Self {
+        Self {
+            config: KernelConfig::default(),
+            plugin_manager: None,
+            tracer: None,
+            ethical_engine: None,
+            storage: None,
+            log_subscriber: false,
+        }
+    }
+    
+    /// Create a builder whose kernel keeps agent snapshots in memory
+    ///
+    /// The on-disk module cache is disabled too, so the kernel writes nothing
+    /// outside its plugin directory.
+    pub fn in_memory() -> Self {
+        let mut builder = Self::new().with_storage(Arc::new(MemoryStorage::new()));
+        builder.config.module_cache_max_mb = 0;
+        builder
+    }
+    
+    /// Use the given configuration
+    pub fn with_config(mut self, config: KernelConfig) -> Self {
+        self.config = config;
+        self
+    }
+    
+    /// Use the given plugin manager instead of one built from the config
+    pub fn with_plugin_manager(mut self, plugin_manager: PluginManager) -> Self {
+        self.plugin_manager = Some(plugin_manager);
+        self
+    }
+    
+    /// Use the given tracer instead of a PoseidonTracer
+    ///
+    /// The kernel records lifecycle events such as spawns and snapshots outside any
+    /// execution, so the tracer should accept events for agents with no current trace.
+    pub fn with_tracer(mut self, tracer: Arc<dyn Tracer>) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+    
+    /// Use the given ethical decision tree
+    ///
+    /// The tree keeps its own prohibited terms rather than taking the config's. Like a
+    /// policy file, it must pass [`EthicalBinaryTree::validate`] for the kernel to build.
+    pub fn with_ethical_engine(mut self, ethical_engine: EthicalBinaryTree) -> Self {
+        self.ethical_engine = Some(ethical_engine);
+        self
+    }
+    
+    /// Use the given storage backend instead of the config's storage directory
+    pub fn with_storage(mut self, storage: Arc<dyn StorageBackend>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+    
+    /// Install a global `tracing` fmt subscriber when the config enables tracing
+    ///
+    /// Off by default, since embedding applications usually install their own.
+    /// An already installed subscriber is left in place.
+    pub fn with_log_subscriber(mut self) -> Self {
+        self.log_subscriber = true;
+        self
+    }
+    
+    /// Build the kernel
+    ///
+    /// # Panics
+    ///
+    /// If the ethics policy can't be loaded or is malformed; [`KernelBuilder::try_build`]
+    /// returns that as an error instead.
+    pub fn build(self) -> MCPKernel {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+    
+    /// Build the kernel, failing if the ethics policy can't be loaded or is malformed
+    ///
+    /// Other components degrade rather than fail: storage that can't be opened is
+    /// reported by the operations that use it, for instance. An ethics policy can't be
+    /// left out without silently changing what agents may do.
+    pub fn try_build(self) -> Result<MCPKernel, KernelError> {
+        let config = self.config;
+        if config.plugin_risk_scoring && config.plugin_risk_approval_threshold > config.plugin_risk_deny_threshold {
+            return Err(KernelError::InvalidConfiguration(format!(
+                "plugin_risk_approval_threshold {} is above plugin_risk_deny_threshold {}",
+                config.plugin_risk_approval_threshold, config.plugin_risk_deny_threshold,
+            )));
+        }
+        
+        // Loaded first, so a bad policy leaves no storage lock or threads behind
+        let ethical_engine = match self.ethical_engine {
+            Some(ethical_engine) => ethical_engine,
+            None => match &config.ethics_policy_path {
+                Some(path) => EthicalBinaryTree::from_file(path)
+                    .map_err(|e| KernelError::InvalidConfiguration(format!("{:#}", e)))?,
+                None => EthicalBinaryTree::default(),
+            }
+            .with_prohibited_agent_names(config.prohibited_agent_names.clone())
+            .with_prohibited_intents(config.prohibited_intents.clone()),
+        };
+        let problems = ethical_engine.validate();
+        if !problems.is_empty() {
+            let problems: Vec<String> = problems.iter().map(ToString::to_string).collect();
+            return Err(KernelError::InvalidConfiguration(format!("Malformed ethical tree: {}", problems.join("; "))));
+        }
+        
+        if self.log_subscriber && config.enable_tracing {
+            if let Err(e) = tracing_subscriber::fmt().try_init() {
+                tracing::debug!("Keeping existing log subscriber: {}", e);
+            }
+        }
+        
+        let storage: Arc<dyn StorageBackend> = match self.storage {
+            Some(storage) => storage,
+            None => match StorageEncryption::from_config(&config) {
+                Err(e) => {
+                    // Never fall back to writing plaintext when encryption was asked for
+                    tracing::error!("Failed to load storage encryption keys: {:#}", e);
+                    Arc::new(UnavailableStorage::new(&e))
+                },
+                Ok(encryption) => match open_storage(&config, encryption) {
+                    Ok(storage) => storage,
+                    Err(e) if matches!(StorageError::find(&e), Some(StorageError::Locked { .. })) => {
+                        // Another kernel owns the directory; writing to it would clobber its snapshots
+                        tracing::error!("Failed to initialize storage: {:#}", e);
+                        Arc::new(UnavailableStorage::new(&e))
+                    },
+                    Err(e) => {
+                        // Keep the kernel usable; snapshots fail until the directory is fixed
+                        tracing::error!("Failed to initialize storage at {}: {:#}", config.storage_directory.display(), e);
+                        Arc::new(StorageManager::unchecked(&config.storage_directory))
+                    }
+                },
+            },
+        };
+        
+        let plugin_manager = self.plugin_manager.unwrap_or_else(|| {
+            // Set up plugin loading, caching compiled modules on disk unless disabled
+            let mut plugin_manager = PluginManager::with_max_resident(
+                config.plugin_directory.clone(),
+                config.max_resident_plugins,
+            ).with_hot_reload(config.plugin_hot_reload);
+            if config.module_cache_max_mb > 0 {
+                plugin_manager = plugin_manager.with_module_cache(config.module_cache_max_mb * 1024 * 1024);
+            }
+            plugin_manager
+        });
+        
+        let agent_store = Arc::new(DashMap::new());
+        
+        // Start write-through persistence if enabled
+        let state_flusher = if config.persist_state_on_write {
+            Some(storage::StateFlusher::start(
+                agent_store.clone(),
+                storage.clone(),
+                std::time::Duration::from_millis(config.state_flush_interval_ms),
+            ))
+        } else {
+            None
+        };
+        
+        // Lifecycle events outside executions go to each agent's general trace
+        let trace_engine = self.tracer.unwrap_or_else(|| {
+            let hasher = hasher::for_kind(config.trace_hasher).unwrap_or_else(|e| {
+                tracing::error!("{:#}; hashing traces with SHA3", e);
+                Arc::new(Sha3Hasher)
+            });
+            let tracer = PoseidonTracer::new()
+                .with_buffer_limits(config.trace_buffer_max_entries, config.trace_buffer_max_bytes)
+                .with_general_traces(true)
+                .with_hasher(hasher)
+                .with_subscriber_capacity(config.event_channel_capacity)
+                .with_sample_rates(config.trace_sample_rates.clone())
+                .with_max_trace_age(std::time::Duration::from_millis(config.trace_max_age_ms));
+            
+            // A missing or unreadable signing key leaves traces unanchored
+            let signing_key = config.trace_signing_key_file.as_ref()
+                .map(TraceSigningKey::from_file)
+                .transpose()
+                .unwrap_or_else(|e| {
+                    tracing::warn!("{:#}; trace anchoring is disabled", e);
+                    None
+                });
+            Arc::new(match signing_key {
+                Some(key) => tracer.with_signing_key(key),
+                None => tracer,
+            })
+        });
+        let ethical_engine = Arc::new(
+            ethical_engine.with_decision_hook(audit_hook(trace_engine.clone(), config.ethics_audit_denials_only)),
+        );
+        let execution_gate = Arc::new(ExecutionGate::default());
+        let events = Arc::new(EventBus::new(config.event_channel_capacity));
+        
+        // Start background snapshots if enabled
+        let auto_snapshots = AutoSnapshotter::default();
+        if config.auto_snapshot_interval_ms > 0 {
+            auto_snapshots.start(
+                AutoSnapshotContext {
+                    agents: agent_store.clone(),
+                    storage: storage.clone(),
+                    tracer: trace_engine.clone(),
+                    events: events.clone(),
+                    gate: execution_gate.clone(),
+                },
+                std::time::Duration::from_millis(config.auto_snapshot_interval_ms),
+            );
+        }
+        
+        // Start trace flushing and pruning if enabled
+        let trace_maintenance = TraceMaintenance::default();
+        if config.trace_maintenance_interval_ms > 0 {
+            trace_maintenance.start(
+                TraceMaintenanceContext {
+                    tracer: trace_engine.clone(),
+                    storage: storage.clone(),
+                    retention_days: config.trace_retention_days,
+                },
+                std::time::Duration::from_millis(config.trace_maintenance_interval_ms),
+            );
+        }
+        
+        // Watch the ethics policy file if enabled
+        let policy_watcher = PolicyWatcher::default();
+        if let Some(path) = config.ethics_policy_path.as_ref().filter(|_| config.ethics_policy_watch_interval_ms > 0) {
+            policy_watcher.start(
+                PolicyReloadContext {
+                    engine: ethical_engine.clone(),
+                    tracer: trace_engine.clone(),
+                    path: path.clone(),
+                },
+                std::time::Duration::from_millis(config.ethics_policy_watch_interval_ms),
+            );
+        }
+        
+        Ok(MCPKernel {
+            plugin_manager: Arc::new(plugin_manager),
+            trace_engine,
+            agent_store,
+            ethical_engine,
+            storage,
+            state_flusher,
+            plugin_stats: std::sync::Mutex::new(PluginStatsReport::default()),
+            execution_gate,
+            events,
+            scheduler: Scheduler::default(),
+            auto_snapshots,
+            trace_maintenance,
+            policy_watcher,
+            stats: KernelCounters::default(),
+            approvals: Approvals::new(),
+            job_queue: JobQueue::new(
+                config.async_worker_threads,
+                config.max_pending_jobs,
+                std::time::Duration::from_millis(config.job_result_ttl_ms),
+            ),
+            config,
+        })
+    }
+}
+
+/// Open the storage backend selected by the config
+///
+/// Snapshot history and compression are applied as configured, and snapshots are
+/// sealed with `encryption` when it is set.
+fn open_storage(config: &KernelConfig, encryption: Option<StorageEncryption>) -> anyhow::Result<Arc<dyn StorageBackend>> {
+    match config.storage_backend {
+        StorageBackendKind::Fs => {
+            let storage = if config.force_storage_unlock {
+                StorageManager::new_forced(&config.storage_directory)?
+            } else {
+                StorageManager::new(&config.storage_directory)?
+            };
+            let mut storage = storage
+                .with_snapshot_history(config.snapshot_history_limit)
+                .with_storage_quota(config.storage_quota_bytes);
+            if config.compress_snapshots {
+                storage = storage.with_compression(config.snapshot_compression_level);
+            }
+            if let Some(encryption) = encryption {
+                storage = storage.with_encryption(encryption);
+            }
+            Ok(Arc::new(storage))
+        },
+        #[cfg(feature = "sqlite")]
+        StorageBackendKind::Sqlite => {
+            let mut storage = SqliteStorage::open(config.storage_directory.join(SQLITE_FILE))?
+                .with_snapshot_history(config.snapshot_history_limit)
+                .with_storage_quota(config.storage_quota_bytes);
+            if config.compress_snapshots {
+                storage = storage.with_compression(config.snapshot_compression_level);
+            }
+            if let Some(encryption) = encryption {
+                storage = storage.with_encryption(encryption);
+            }
+            Ok(Arc::new(storage))
+        },
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackendKind::Sqlite => Err(anyhow::anyhow!("The sqlite storage backend requires the `sqlite` feature")),
+    }
+}
+
+/// Records the ethical engine's decisions as `ethics.decision` events in the general
+/// trace of the agent concerned, or the kernel's when the decision names no agent
+fn audit_hook(tracer: Arc<dyn Tracer>, denials_only: bool) -> DecisionHook {
+    Arc::new(move |decision| {
+        if denials_only && decision.decision == Decision::Allow {
+            return;
+        }
+        let agent_id = decision.agent_id.clone().unwrap_or_else(|| KERNEL_TRACE_AGENT.to_string());
+        let data = serde_json::to_value(decision).unwrap_or_default();
+        if let Err(e) = tracer.record_event(&agent_id, ETHICS_DECISION_EVENT, &data) {
+            tracing::warn!("Failed to audit ethical decision for agent {}: {}", agent_id, e);
+        }
+    })
+}
+
+impl Default for KernelBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,50 @@
+//! Snapshot compression for MCP-ZERO
+//!
+//! Storage backends can zstd-compress snapshot and state payloads before writing
+//! them, which shrinks agents with large state maps several times over. Compressed
+//! payloads are recognized on load by the zstd frame magic number, so a store keeps
+//! loading when compression is switched on or off.
+
+use anyhow::{Result, Context};
+
+/// Magic number opening every zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compress a payload at `level`, or leave it as is when compression is off
+pub(crate) fn compress(level: Option<i32>, data: Vec<u8>) -> Result<Vec<u8>> {
+    match level {
+        Some(level) => zstd::encode_all(data.as_slice(), level).context("Failed to compress payload"),
+        None => Ok(data),
+    }
+}
+
+/// Decompress a payload if it is compressed
+pub(crate) fn decompress(data: Vec<u8>) -> Result<Vec<u8>> {
+    if !is_compressed(&data) {
+        return Ok(data);
+    }
+    zstd::decode_all(data.as_slice()).context("Failed to decompress payload")
+}
+
+/// Whether a payload starts with a zstd frame
+pub(crate) fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(&ZSTD_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_compress_round_trip() {
+        let json = serde_json::json!({"state": {"log": "entry ".repeat(1000)}}).to_string().into_bytes();
+        let compressed = compress(Some(3), json.clone()).unwrap();
+        assert!(is_compressed(&compressed));
+        assert!(compressed.len() < json.len() / 10);
+        assert_eq!(decompress(compressed).unwrap(), json);
+        
+        // Plain payloads pass through either way
+        assert_eq!(compress(None, json.clone()).unwrap(), json);
+        assert_eq!(decompress(json.clone()).unwrap(), json);
+    }
+}
@@ -3,10 +3,13 @@
 //! Provides configuration management for the kernel, including loading from
 //! YAML files and environment variables.
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
 use anyhow::{Result, Context};
 
+use crate::ethical::ProhibitedTerms;
+
 /// Kernel configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KernelConfig {
@@ -16,7 +19,139 @@ pub struct KernelConfig {
     /// Storage directory path
     pub storage_directory: PathBuf,
     
-    /// Whether to enable tracing
+    /// How agents are stored in the storage directory
+    #[serde(default)]
+    pub storage_backend: StorageBackendKind,
+    
+    /// Open the storage directory even if another kernel process holds its lock; for
+    /// recovering from a hung kernel only, as both kernels will then write to it
+    #[serde(default)]
+    pub force_storage_unlock: bool,
+    
+    /// File holding the key snapshots are encrypted with at rest (64 hex digits or
+    /// 32 raw bytes); without one, `MCP_STORAGE_ENCRYPTION_KEY` is used if set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_encryption_key_file: Option<PathBuf>,
+    
+    /// File holding the ed25519 key trace chain heads are signed with (64 hex digits or
+    /// 32 raw bytes); anchoring is disabled without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_signing_key_file: Option<PathBuf>,
+    
+    /// YAML ethics policy replacing the built-in ethical decision tree; see
+    /// `EthicalBinaryTree::from_yaml` for the format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ethics_policy_path: Option<PathBuf>,
+    
+    /// Interval in milliseconds at which the ethics policy file is checked for changes
+    /// and reloaded (0 disables watching; `MCPKernel::reload_ethics_policy` still works)
+    #[serde(default)]
+    pub ethics_policy_watch_interval_ms: u64,
+    
+    /// Terms agent names must not contain, checked before the ethical decision tree
+    #[serde(default = "ProhibitedTerms::default_agent_names")]
+    pub prohibited_agent_names: ProhibitedTerms,
+    
+    /// Actions intents must not contain, checked before the ethical decision tree
+    #[serde(default = "ProhibitedTerms::default_intents")]
+    pub prohibited_intents: ProhibitedTerms,
+    
+    /// Audit only the ethical engine's denials rather than every decision, to keep
+    /// trace volume down
+    #[serde(default)]
+    pub ethics_audit_denials_only: bool,
+    
+    /// Ethical denials of an agent's executions within `quarantine_window_ms` that
+    /// quarantine it (0 disables quarantine); see `MCPKernel::release_quarantine`
+    #[serde(default = "default_quarantine_violation_threshold")]
+    pub quarantine_violation_threshold: usize,
+    
+    /// Window in milliseconds over which ethical denials count toward quarantine
+    #[serde(default = "default_quarantine_window_ms")]
+    pub quarantine_window_ms: u64,
+    
+    /// Weigh plugin attachments by the ethical tree's risk score against the thresholds
+    /// below, rather than taking its allow or deny decision
+    #[serde(default)]
+    pub plugin_risk_scoring: bool,
+    
+    /// Risk score from which a plugin attachment waits for operator approval, with
+    /// risk scoring on; see `MCPKernel::approve_pending`
+    #[serde(default = "default_plugin_risk_approval_threshold")]
+    pub plugin_risk_approval_threshold: f64,
+    
+    /// Risk score from which a plugin attachment is denied, with risk scoring on
+    #[serde(default = "default_plugin_risk_deny_threshold")]
+    pub plugin_risk_deny_threshold: f64,
+    
+    /// Files holding previous keys, used only to read snapshots written before a rotation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub storage_retired_key_files: Vec<PathBuf>,
+    
+    /// Whether unencrypted snapshots are still read while an encryption key is
+    /// configured, for stores written before encryption was enabled
+    #[serde(default)]
+    pub storage_allow_plaintext: bool,
+    
+    /// Whether snapshots and state are zstd-compressed when written; either kind loads
+    /// regardless of this setting
+    #[serde(default)]
+    pub compress_snapshots: bool,
+    
+    /// zstd level snapshots are compressed at (1 to 22; higher is smaller and slower)
+    #[serde(default = "default_snapshot_compression_level")]
+    pub snapshot_compression_level: i32,
+    
+    /// Number of past snapshots kept per agent for `MCPKernel::restore` (0 keeps none)
+    #[serde(default = "default_snapshot_history_limit")]
+    pub snapshot_history_limit: usize,
+    
+    /// Bytes each agent's snapshot, state and snapshot history may take up in storage,
+    /// unless the agent's hardware constraints set a quota (0 is unlimited)
+    #[serde(default = "default_storage_quota_bytes")]
+    pub storage_quota_bytes: u64,
+    
+    /// Interval of background snapshots of agents changed since their last snapshot,
+    /// in milliseconds (0 disables them)
+    #[serde(default = "default_auto_snapshot_interval_ms")]
+    pub auto_snapshot_interval_ms: u64,
+    
+    /// Most trace entries held in memory before the oldest are evicted (0 is unlimited)
+    #[serde(default = "default_trace_buffer_max_entries")]
+    pub trace_buffer_max_entries: usize,
+    
+    /// Most serialized bytes of trace entries held in memory (0 is unlimited)
+    #[serde(default = "default_trace_buffer_max_bytes")]
+    pub trace_buffer_max_bytes: usize,
+    
+    /// Days stored trace entries are kept before pruning; entries of active traces
+    /// are always kept (0 keeps them forever)
+    #[serde(default)]
+    pub trace_retention_days: u64,
+    
+    /// Interval of trace maintenance, which closes timed-out traces, flushes buffered
+    /// entries to storage and prunes expired ones, in milliseconds (0 disables it)
+    #[serde(default = "default_trace_maintenance_interval_ms")]
+    pub trace_maintenance_interval_ms: u64,
+    
+    /// Age in milliseconds at which a trace that was never ended is closed as failed,
+    /// unless begun with its own limit (0 never closes them)
+    #[serde(default = "default_trace_max_age_ms")]
+    pub trace_max_age_ms: u64,
+    
+    /// Hash function of new trace entries; stored entries keep the one they were
+    /// hashed with
+    #[serde(default)]
+    pub trace_hasher: TraceHasherKind,
+    
+    /// Fraction of events of each type recorded in traces, such as 0.01 for
+    /// `agent.heartbeat`; unlisted types are always recorded, as are trace begin and
+    /// end entries and failures
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub trace_sample_rates: HashMap<String, f64>,
+    
+    /// Whether to install a log subscriber when the kernel builder opts in
+    /// (see `KernelBuilder::with_log_subscriber`)
     #[serde(default = "default_enable_tracing")]
     pub enable_tracing: bool,
     
@@ -24,6 +159,10 @@ pub struct KernelConfig {
     #[serde(default)]
     pub enable_zk_proofs: bool,
     
+    /// Whether to emit per-plugin execution metrics and keep per-agent plugin stats
+    #[serde(default)]
+    pub enable_detailed_metrics: bool,
+    
     /// Maximum number of agents
     #[serde(default = "default_max_agents")]
     pub max_agents: usize,
@@ -32,11 +171,137 @@ pub struct KernelConfig {
     #[serde(default = "default_max_plugins_per_agent")]
     pub max_plugins_per_agent: usize,
     
+    /// Default execution timeout in milliseconds for agent intents
+    #[serde(default = "default_execution_timeout_ms")]
+    pub execution_timeout_ms: u64,
+    
+    /// Whether agent state changes are written through to storage
+    #[serde(default)]
+    pub persist_state_on_write: bool,
+    
+    /// Interval in milliseconds at which pending state writes are flushed
+    #[serde(default = "default_state_flush_interval_ms")]
+    pub state_flush_interval_ms: u64,
+    
+    /// Maximum nesting depth of plugin-to-plugin calls
+    #[serde(default = "default_max_plugin_call_depth")]
+    pub max_plugin_call_depth: u32,
+    
+    /// Whether plugins whose files changed on disk are recompiled on next use
+    #[serde(default)]
+    pub plugin_hot_reload: bool,
+    
+    /// Maximum size in MB of the on-disk compiled module cache (0 disables the cache)
+    #[serde(default = "default_module_cache_max_mb")]
+    pub module_cache_max_mb: u64,
+    
+    /// Maximum number of compiled plugin modules kept in memory (unlimited when unset)
+    #[serde(default)]
+    pub max_resident_plugins: Option<usize>,
+    
+    /// Maximum size in bytes of a plugin log message; longer messages are truncated
+    #[serde(default = "default_max_plugin_log_bytes")]
+    pub max_plugin_log_bytes: usize,
+    
+    /// Number of threads running intents submitted with `execute_async`
+    #[serde(default = "default_async_worker_threads")]
+    pub async_worker_threads: usize,
+    
+    /// Maximum number of async jobs waiting for a worker
+    #[serde(default = "default_max_pending_jobs")]
+    pub max_pending_jobs: usize,
+    
+    /// Maximum number of threads executing the requests of one `execute_batch` call
+    #[serde(default = "default_max_batch_parallelism")]
+    pub max_batch_parallelism: usize,
+    
+    /// How long finished async job results are kept, in milliseconds
+    #[serde(default = "default_job_result_ttl_ms")]
+    pub job_result_ttl_ms: u64,
+    
+    /// Number of recent executions kept per agent (0 disables the history)
+    #[serde(default = "default_execution_history_capacity")]
+    pub execution_history_capacity: usize,
+    
+    /// Write agents' execution history into their snapshots
+    #[serde(default)]
+    pub persist_execution_history: bool,
+    
+    /// Move agents that `recover_all` fails to recover into storage quarantine
+    #[serde(default)]
+    pub quarantine_failed_recovery: bool,
+    
+    /// Maximum size in bytes of an agent's state, serialized as JSON, unless the agent's
+    /// hardware constraints set one
+    #[serde(default = "default_max_state_bytes")]
+    pub max_state_bytes: usize,
+    
+    /// Maximum size in bytes of an agent's metadata, serialized as JSON, after `update_metadata`
+    #[serde(default = "default_max_metadata_bytes")]
+    pub max_metadata_bytes: usize,
+    
+    /// Record only the changed keys, not their values, in `agent.metadata_updated` traces
+    #[serde(default)]
+    pub redact_metadata_in_traces: bool,
+    
+    /// Number of events (or trace entries) buffered per `subscribe` (or
+    /// `subscribe_traces`) receiver before new ones are dropped
+    #[serde(default = "default_event_channel_capacity")]
+    pub event_channel_capacity: usize,
+    
     /// Hardware constraints
     #[serde(default)]
     pub hardware: HardwareConfig,
 }
 
+fn default_quarantine_violation_threshold() -> usize {
+    5
+}
+
+fn default_quarantine_window_ms() -> u64 {
+    60_000
+}
+
+fn default_plugin_risk_approval_threshold() -> f64 {
+    0.5
+}
+
+fn default_plugin_risk_deny_threshold() -> f64 {
+    1.0
+}
+
+fn default_snapshot_compression_level() -> i32 {
+    3 // zstd's default; most of the size reduction at a fraction of the cost
+}
+
+fn default_snapshot_history_limit() -> usize {
+    5
+}
+
+fn default_storage_quota_bytes() -> u64 {
+    256 * 1024 * 1024 // 256MB; room for a full history of states at max_state_bytes
+}
+
+fn default_auto_snapshot_interval_ms() -> u64 {
+    60_000 // 1 minute
+}
+
+fn default_trace_buffer_max_entries() -> usize {
+    100_000
+}
+
+fn default_trace_buffer_max_bytes() -> usize {
+    64 * 1024 * 1024 // 64MB
+}
+
+fn default_trace_max_age_ms() -> u64 {
+    60 * 60 * 1000 // 1 hour
+}
+
+fn default_trace_maintenance_interval_ms() -> u64 {
+    60_000 // 1 minute
+}
+
 fn default_enable_tracing() -> bool {
     true
 }
@@ -49,6 +314,80 @@ fn default_max_plugins_per_agent() -> usize {
     10
 }
 
+fn default_execution_timeout_ms() -> u64 {
+    30_000 // 30 seconds
+}
+
+fn default_state_flush_interval_ms() -> u64 {
+    1000 // 1 second
+}
+
+fn default_max_plugin_call_depth() -> u32 {
+    3
+}
+
+fn default_max_plugin_log_bytes() -> usize {
+    1024
+}
+
+fn default_module_cache_max_mb() -> u64 {
+    128 // 128MB of compiled modules
+}
+
+fn default_async_worker_threads() -> usize {
+    1 // Stay within the CPU budget on low-end hardware
+}
+
+fn default_max_pending_jobs() -> usize {
+    64
+}
+
+fn default_max_batch_parallelism() -> usize {
+    2
+}
+
+fn default_execution_history_capacity() -> usize {
+    50
+}
+
+fn default_max_state_bytes() -> usize {
+    16 * 1024 * 1024 // 16MB
+}
+
+fn default_max_metadata_bytes() -> usize {
+    16 * 1024
+}
+
+fn default_event_channel_capacity() -> usize {
+    256
+}
+
+fn default_job_result_ttl_ms() -> u64 {
+    300_000 // 5 minutes
+}
+
+/// Storage backend selected by `KernelConfig::storage_backend`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackendKind {
+    /// A directory per agent holding JSON files
+    #[default]
+    Fs,
+    /// A single SQLite database file (requires the `sqlite` feature)
+    Sqlite,
+}
+
+/// Trace hasher selected by `KernelConfig::trace_hasher`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceHasherKind {
+    /// SHA3-256
+    #[default]
+    Sha3,
+    /// Poseidon over the BN254 scalar field (requires the `poseidon` feature)
+    Poseidon,
+}
+
 /// Hardware configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HardwareConfig {
@@ -92,10 +431,58 @@ impl Default for KernelConfig {
         Self {
             plugin_directory: PathBuf::from("./plugins"),
             storage_directory: PathBuf::from("./storage"),
+            storage_backend: StorageBackendKind::default(),
+            force_storage_unlock: false,
+            storage_encryption_key_file: None,
+            trace_signing_key_file: None,
+            ethics_policy_path: None,
+            ethics_policy_watch_interval_ms: 0,
+            prohibited_agent_names: ProhibitedTerms::default_agent_names(),
+            prohibited_intents: ProhibitedTerms::default_intents(),
+            ethics_audit_denials_only: false,
+            quarantine_violation_threshold: default_quarantine_violation_threshold(),
+            quarantine_window_ms: default_quarantine_window_ms(),
+            plugin_risk_scoring: false,
+            plugin_risk_approval_threshold: default_plugin_risk_approval_threshold(),
+            plugin_risk_deny_threshold: default_plugin_risk_deny_threshold(),
+            storage_retired_key_files: Vec::new(),
+            storage_allow_plaintext: false,
+            compress_snapshots: false,
+            snapshot_compression_level: default_snapshot_compression_level(),
+            snapshot_history_limit: default_snapshot_history_limit(),
+            storage_quota_bytes: default_storage_quota_bytes(),
+            auto_snapshot_interval_ms: default_auto_snapshot_interval_ms(),
+            trace_buffer_max_entries: default_trace_buffer_max_entries(),
+            trace_buffer_max_bytes: default_trace_buffer_max_bytes(),
+            trace_retention_days: 0,
+            trace_maintenance_interval_ms: default_trace_maintenance_interval_ms(),
+            trace_max_age_ms: default_trace_max_age_ms(),
+            trace_hasher: TraceHasherKind::default(),
+            trace_sample_rates: HashMap::new(),
             enable_tracing: default_enable_tracing(),
             enable_zk_proofs: false,
+            enable_detailed_metrics: false,
             max_agents: default_max_agents(),
             max_plugins_per_agent: default_max_plugins_per_agent(),
+            execution_timeout_ms: default_execution_timeout_ms(),
+            persist_state_on_write: false,
+            state_flush_interval_ms: default_state_flush_interval_ms(),
+            max_plugin_call_depth: default_max_plugin_call_depth(),
+            max_resident_plugins: None,
+            plugin_hot_reload: false,
+            module_cache_max_mb: default_module_cache_max_mb(),
+            max_plugin_log_bytes: default_max_plugin_log_bytes(),
+            async_worker_threads: default_async_worker_threads(),
+            max_pending_jobs: default_max_pending_jobs(),
+            max_batch_parallelism: default_max_batch_parallelism(),
+            job_result_ttl_ms: default_job_result_ttl_ms(),
+            execution_history_capacity: default_execution_history_capacity(),
+            persist_execution_history: false,
+            quarantine_failed_recovery: false,
+            max_state_bytes: default_max_state_bytes(),
+            max_metadata_bytes: default_max_metadata_bytes(),
+            redact_metadata_in_traces: false,
+            event_channel_capacity: default_event_channel_capacity(),
             hardware: HardwareConfig::default(),
         }
     }
@@ -137,6 +524,160 @@ impl KernelConfig {
             config.storage_directory = PathBuf::from(storage_dir);
         }
         
+        if let Ok(var) = std::env::var("MCP_STORAGE_BACKEND") {
+            match var.to_lowercase().as_str() {
+                "fs" => config.storage_backend = StorageBackendKind::Fs,
+                "sqlite" => config.storage_backend = StorageBackendKind::Sqlite,
+                _ => tracing::warn!("Ignoring unknown MCP_STORAGE_BACKEND: {}", var),
+            }
+        }
+        
+        if let Ok(force) = std::env::var("MCP_FORCE_STORAGE_UNLOCK") {
+            config.force_storage_unlock = force.to_lowercase() == "true";
+        }
+        
+        if let Ok(key_file) = std::env::var("MCP_STORAGE_ENCRYPTION_KEY_FILE") {
+            config.storage_encryption_key_file = Some(PathBuf::from(key_file));
+        }
+        
+        if let Ok(allow) = std::env::var("MCP_STORAGE_ALLOW_PLAINTEXT") {
+            config.storage_allow_plaintext = allow.to_lowercase() == "true";
+        }
+        
+        if let Ok(key_file) = std::env::var("MCP_TRACE_SIGNING_KEY_FILE") {
+            config.trace_signing_key_file = Some(PathBuf::from(key_file));
+        }
+        
+        if let Ok(policy) = std::env::var("MCP_ETHICS_POLICY_PATH") {
+            config.ethics_policy_path = Some(PathBuf::from(policy));
+        }
+        
+        if let Ok(var) = std::env::var("MCP_ETHICS_POLICY_WATCH_INTERVAL_MS") {
+            if let Ok(interval) = var.parse() {
+                config.ethics_policy_watch_interval_ms = interval;
+            }
+        }
+        
+        // Comma-separated terms, replacing the list's terms but not how they match
+        let terms = |var: &str| var.split(',').map(str::trim).filter(|term| !term.is_empty()).map(str::to_string).collect();
+        if let Ok(var) = std::env::var("MCP_PROHIBITED_AGENT_NAMES") {
+            config.prohibited_agent_names.terms = terms(&var);
+        }
+        
+        if let Ok(var) = std::env::var("MCP_PROHIBITED_INTENTS") {
+            config.prohibited_intents.terms = terms(&var);
+        }
+        
+        if let Ok(denials_only) = std::env::var("MCP_ETHICS_AUDIT_DENIALS_ONLY") {
+            config.ethics_audit_denials_only = denials_only.to_lowercase() == "true";
+        }
+        
+        if let Ok(var) = std::env::var("MCP_QUARANTINE_VIOLATION_THRESHOLD") {
+            if let Ok(threshold) = var.parse() {
+                config.quarantine_violation_threshold = threshold;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_QUARANTINE_WINDOW_MS") {
+            if let Ok(window) = var.parse() {
+                config.quarantine_window_ms = window;
+            }
+        }
+        
+        if let Ok(scoring) = std::env::var("MCP_PLUGIN_RISK_SCORING") {
+            config.plugin_risk_scoring = scoring.to_lowercase() == "true";
+        }
+        
+        if let Ok(var) = std::env::var("MCP_PLUGIN_RISK_APPROVAL_THRESHOLD") {
+            if let Ok(threshold) = var.parse() {
+                config.plugin_risk_approval_threshold = threshold;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_PLUGIN_RISK_DENY_THRESHOLD") {
+            if let Ok(threshold) = var.parse() {
+                config.plugin_risk_deny_threshold = threshold;
+            }
+        }
+        
+        if let Ok(compress) = std::env::var("MCP_COMPRESS_SNAPSHOTS") {
+            config.compress_snapshots = compress.to_lowercase() == "true";
+        }
+        
+        if let Ok(var) = std::env::var("MCP_SNAPSHOT_COMPRESSION_LEVEL") {
+            if let Ok(level) = var.parse() {
+                config.snapshot_compression_level = level;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_SNAPSHOT_HISTORY_LIMIT") {
+            if let Ok(limit) = var.parse() {
+                config.snapshot_history_limit = limit;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_STORAGE_QUOTA_BYTES") {
+            if let Ok(quota) = var.parse() {
+                config.storage_quota_bytes = quota;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_AUTO_SNAPSHOT_INTERVAL_MS") {
+            if let Ok(interval) = var.parse() {
+                config.auto_snapshot_interval_ms = interval;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_TRACE_BUFFER_MAX_ENTRIES") {
+            if let Ok(max_entries) = var.parse() {
+                config.trace_buffer_max_entries = max_entries;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_TRACE_BUFFER_MAX_BYTES") {
+            if let Ok(max_bytes) = var.parse() {
+                config.trace_buffer_max_bytes = max_bytes;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_TRACE_RETENTION_DAYS") {
+            if let Ok(days) = var.parse() {
+                config.trace_retention_days = days;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_TRACE_MAINTENANCE_INTERVAL_MS") {
+            if let Ok(interval) = var.parse() {
+                config.trace_maintenance_interval_ms = interval;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_TRACE_MAX_AGE_MS") {
+            if let Ok(max_age) = var.parse() {
+                config.trace_max_age_ms = max_age;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_TRACE_HASHER") {
+            match var.to_lowercase().as_str() {
+                "sha3" => config.trace_hasher = TraceHasherKind::Sha3,
+                "poseidon" => config.trace_hasher = TraceHasherKind::Poseidon,
+                _ => tracing::warn!("Ignoring unknown MCP_TRACE_HASHER: {}", var),
+            }
+        }
+        
+        // Comma-separated `event_type=rate` pairs
+        if let Ok(var) = std::env::var("MCP_TRACE_SAMPLE_RATES") {
+            for rule in var.split(',').filter(|rule| !rule.trim().is_empty()) {
+                match rule.split_once('=').and_then(|(event_type, rate)| Some((event_type.trim(), rate.trim().parse().ok()?))) {
+                    Some((event_type, rate)) => {
+                        config.trace_sample_rates.insert(event_type.to_string(), rate);
+                    },
+                    None => tracing::warn!("Ignoring invalid MCP_TRACE_SAMPLE_RATES rule: {}", rule),
+                }
+            }
+        }
+        
         if let Ok(enable_tracing) = std::env::var("MCP_ENABLE_TRACING") {
             config.enable_tracing = enable_tracing.to_lowercase() == "true";
         }
@@ -145,6 +686,10 @@ impl KernelConfig {
             config.enable_zk_proofs = enable_zk.to_lowercase() == "true";
         }
         
+        if let Ok(detailed_metrics) = std::env::var("MCP_ENABLE_DETAILED_METRICS") {
+            config.enable_detailed_metrics = detailed_metrics.to_lowercase() == "true";
+        }
+        
         if let Ok(var) = std::env::var("MCP_MAX_AGENTS") {
             if let Ok(max_agents) = var.parse() {
                 config.max_agents = max_agents;
@@ -157,6 +702,110 @@ impl KernelConfig {
             }
         }
         
+        if let Ok(var) = std::env::var("MCP_EXECUTION_TIMEOUT_MS") {
+            if let Ok(timeout) = var.parse() {
+                config.execution_timeout_ms = timeout;
+            }
+        }
+        
+        if let Ok(persist) = std::env::var("MCP_PERSIST_STATE_ON_WRITE") {
+            config.persist_state_on_write = persist.to_lowercase() == "true";
+        }
+        
+        if let Ok(var) = std::env::var("MCP_STATE_FLUSH_INTERVAL_MS") {
+            if let Ok(interval) = var.parse() {
+                config.state_flush_interval_ms = interval;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_MAX_PLUGIN_CALL_DEPTH") {
+            if let Ok(depth) = var.parse() {
+                config.max_plugin_call_depth = depth;
+            }
+        }
+        
+        if let Ok(hot_reload) = std::env::var("MCP_PLUGIN_HOT_RELOAD") {
+            config.plugin_hot_reload = hot_reload.to_lowercase() == "true";
+        }
+        
+        if let Ok(var) = std::env::var("MCP_MODULE_CACHE_MAX_MB") {
+            if let Ok(max_mb) = var.parse() {
+                config.module_cache_max_mb = max_mb;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_MAX_RESIDENT_PLUGINS") {
+            if let Ok(max_resident) = var.parse() {
+                config.max_resident_plugins = Some(max_resident);
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_MAX_PLUGIN_LOG_BYTES") {
+            if let Ok(max_bytes) = var.parse() {
+                config.max_plugin_log_bytes = max_bytes;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_ASYNC_WORKER_THREADS") {
+            if let Ok(threads) = var.parse() {
+                config.async_worker_threads = threads;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_MAX_PENDING_JOBS") {
+            if let Ok(max_pending) = var.parse() {
+                config.max_pending_jobs = max_pending;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_MAX_BATCH_PARALLELISM") {
+            if let Ok(parallelism) = var.parse() {
+                config.max_batch_parallelism = parallelism;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_JOB_RESULT_TTL_MS") {
+            if let Ok(ttl) = var.parse() {
+                config.job_result_ttl_ms = ttl;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_EXECUTION_HISTORY_CAPACITY") {
+            if let Ok(capacity) = var.parse() {
+                config.execution_history_capacity = capacity;
+            }
+        }
+        
+        if let Ok(persist) = std::env::var("MCP_PERSIST_EXECUTION_HISTORY") {
+            config.persist_execution_history = persist.to_lowercase() == "true";
+        }
+        
+        if let Ok(quarantine) = std::env::var("MCP_QUARANTINE_FAILED_RECOVERY") {
+            config.quarantine_failed_recovery = quarantine.to_lowercase() == "true";
+        }
+        
+        if let Ok(var) = std::env::var("MCP_MAX_STATE_BYTES") {
+            if let Ok(max_bytes) = var.parse() {
+                config.max_state_bytes = max_bytes;
+            }
+        }
+        
+        if let Ok(var) = std::env::var("MCP_MAX_METADATA_BYTES") {
+            if let Ok(max_bytes) = var.parse() {
+                config.max_metadata_bytes = max_bytes;
+            }
+        }
+        
+        if let Ok(redact) = std::env::var("MCP_REDACT_METADATA_IN_TRACES") {
+            config.redact_metadata_in_traces = redact.to_lowercase() == "true";
+        }
+        
+        if let Ok(var) = std::env::var("MCP_EVENT_CHANNEL_CAPACITY") {
+            if let Ok(capacity) = var.parse() {
+                config.event_channel_capacity = capacity;
+            }
+        }
+        
         // Hardware constraints
         if let Ok(var) = std::env::var("MCP_MAX_CPU") {
             if let Ok(max_cpu) = var.parse() {
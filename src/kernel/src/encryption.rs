@@ -0,0 +1,301 @@
+//! At-rest encryption of agent snapshots for MCP-ZERO
+//!
+//! Storage backends seal each snapshot and state record with ChaCha20-Poly1305
+//! before writing it. A sealed payload is a single line of text,
+//! `mcp-sealed:1:chacha20poly1305:<key id>:<nonce>:<ciphertext>`, with the nonce and
+//! ciphertext hex-encoded. The header is authenticated along with the payload, and the
+//! key ID lets a kernel holding several keys pick the right one while keys are rotated.
+//! With a key configured, unsealed payloads are refused unless plaintext was allowed.
+
+use std::cell::Cell;
+use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+use crate::config::KernelConfig;
+
+/// Prefix identifying a sealed payload and its format version
+const SEALED_PREFIX: &str = "mcp-sealed:1:";
+
+/// Cipher named in the header of sealed payloads
+const CIPHER: &str = "chacha20poly1305";
+
+/// Environment variable holding a hex-encoded key, used when no key file is configured
+const KEY_ENV_VAR: &str = "MCP_STORAGE_ENCRYPTION_KEY";
+
+thread_local! {
+    /// Whether payloads opened on this thread may be unsealed despite a configured key
+    static PLAINTEXT_ALLOWED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A 256-bit key for encrypting stored snapshots
+#[derive(Clone)]
+pub struct StorageKey {
+    /// Short fingerprint naming the key in sealed payloads
+    id: String,
+    
+    cipher: ChaCha20Poly1305,
+}
+
+impl StorageKey {
+    /// Create a key from 32 raw bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != 32 {
+            return Err(anyhow!("Storage encryption keys must be 32 bytes, got {}", bytes.len()));
+        }
+        
+        let fingerprint = blake3::hash(bytes);
+        Ok(Self {
+            id: fingerprint.to_hex().chars().take(16).collect(),
+            cipher: ChaCha20Poly1305::new_from_slice(bytes).map_err(|_| anyhow!("Invalid storage encryption key"))?,
+        })
+    }
+    
+    /// Create a key from 64 hex digits
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        Self::from_bytes(&parse_key_hex(hex).context("Invalid storage encryption key")?)
+    }
+    
+    /// Read a key file holding either 64 hex digits or 32 raw bytes
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_bytes(&read_key_bytes(path.as_ref(), "storage encryption key")?)
+    }
+    
+    /// Fingerprint of the key, recorded in the header of payloads it seals
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl std::fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StorageKey").field("id", &self.id).finish_non_exhaustive()
+    }
+}
+
+/// Keys a storage backend seals and opens payloads with
+///
+/// New payloads are sealed with the current key; retired keys can still open payloads
+/// written before a rotation.
+#[derive(Debug, Clone)]
+pub struct StorageEncryption {
+    key: StorageKey,
+    retired: Vec<StorageKey>,
+    allow_plaintext: bool,
+}
+
+impl StorageEncryption {
+    /// Encrypt with `key`
+    pub fn new(key: StorageKey) -> Self {
+        Self { key, retired: Vec::new(), allow_plaintext: false }
+    }
+    
+    /// Also open payloads sealed with a previous key
+    pub fn with_retired_key(mut self, key: StorageKey) -> Self {
+        self.retired.push(key);
+        self
+    }
+    
+    /// Also accept payloads that were never sealed, written before encryption was enabled
+    pub fn with_plaintext_allowed(mut self, allowed: bool) -> Self {
+        self.allow_plaintext = allowed;
+        self
+    }
+    
+    /// Load the keys named by the config, or by `MCP_STORAGE_ENCRYPTION_KEY` when no key
+    /// file is configured; `None` when encryption is not configured
+    pub fn from_config(config: &KernelConfig) -> Result<Option<Self>> {
+        let key = match (&config.storage_encryption_key_file, std::env::var(KEY_ENV_VAR)) {
+            (Some(path), _) => StorageKey::from_file(path)?,
+            (None, Ok(hex)) => StorageKey::from_hex(&hex)
+                .with_context(|| format!("Invalid {}", KEY_ENV_VAR))?,
+            (None, Err(_)) => return Ok(None),
+        };
+        
+        let mut encryption = Self::new(key).with_plaintext_allowed(config.storage_allow_plaintext);
+        for path in &config.storage_retired_key_files {
+            encryption = encryption.with_retired_key(StorageKey::from_file(path)?);
+        }
+        Ok(Some(encryption))
+    }
+    
+    /// Encrypt a payload with the current key
+    pub(crate) fn seal(&self, plaintext: &[u8]) -> Result<String> {
+        let header = format!("{}{}:{}:", SEALED_PREFIX, CIPHER, self.key.id);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.key.cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad: header.as_bytes() })
+            .map_err(|_| anyhow!("Failed to encrypt payload"))?;
+        
+        Ok(format!("{}{}:{}", header, encode_hex(&nonce), encode_hex(&ciphertext)))
+    }
+    
+    /// Decrypt a sealed payload with whichever configured key sealed it
+    fn open_sealed(&self, sealed: &str) -> Result<Vec<u8>> {
+        let (cipher, key_id, nonce, ciphertext) = parse_sealed(sealed)?;
+        if cipher != CIPHER {
+            return Err(anyhow!("Unsupported storage cipher '{}'", cipher));
+        }
+        
+        let key = std::iter::once(&self.key).chain(&self.retired)
+            .find(|key| key.id == key_id)
+            .ok_or_else(|| anyhow!(
+                "Payload is encrypted with storage key {}, which is not configured (current key is {})",
+                key_id, self.key.id,
+            ))?;
+        
+        let header = format!("{}{}:{}:", SEALED_PREFIX, cipher, key_id);
+        let nonce = decode_hex(nonce)?;
+        if nonce.len() != 12 {
+            return Err(anyhow!("Sealed payload has a malformed nonce"));
+        }
+        key.cipher
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &decode_hex(ciphertext)?, aad: header.as_bytes() })
+            .map_err(|_| anyhow!("Failed to decrypt payload with storage key {}: it was altered or the key is wrong", key_id))
+    }
+}
+
+/// Seal a payload if encryption is configured
+pub(crate) fn seal(encryption: Option<&StorageEncryption>, plaintext: Vec<u8>) -> Result<Vec<u8>> {
+    match encryption {
+        Some(encryption) => encryption.seal(&plaintext).map(String::into_bytes),
+        None => Ok(plaintext),
+    }
+}
+
+/// Open a payload, sealed or plain
+///
+/// With encryption configured, plain payloads are refused unless plaintext is allowed
+/// by the keys or within `allowing_plaintext`, so a swapped-in unencrypted snapshot
+/// isn't loaded as if it were trusted.
+pub(crate) fn open(encryption: Option<&StorageEncryption>, data: Vec<u8>) -> Result<Vec<u8>> {
+    if !data.starts_with(SEALED_PREFIX.as_bytes()) {
+        return match encryption {
+            Some(encryption) if !encryption.allow_plaintext && !PLAINTEXT_ALLOWED.with(Cell::get) => Err(anyhow!(
+                "Payload is not encrypted, but a storage encryption key is configured \
+                 (set storage_allow_plaintext to read stores written before encryption was enabled)",
+            )),
+            _ => Ok(data),
+        };
+    }
+    
+    match encryption {
+        Some(encryption) => encryption.open_sealed(std::str::from_utf8(&data).context("Sealed payload is not UTF-8")?),
+        None => Err(anyhow!(
+            "Payload is encrypted, but no storage encryption key is configured \
+             (set storage_encryption_key_file or {})",
+            KEY_ENV_VAR,
+        )),
+    }
+}
+
+/// Run `f` with plain payloads accepted on this thread, as when re-encrypting them
+pub(crate) fn allowing_plaintext<T>(f: impl FnOnce() -> T) -> T {
+    let previous = PLAINTEXT_ALLOWED.with(|allowed| allowed.replace(true));
+    let result = f();
+    PLAINTEXT_ALLOWED.with(|allowed| allowed.set(previous));
+    result
+}
+
+/// Split a sealed payload into cipher, key ID, nonce and ciphertext
+fn parse_sealed(sealed: &str) -> Result<(&str, &str, &str, &str)> {
+    let fields: Vec<&str> = sealed.strip_prefix(SEALED_PREFIX)
+        .map(|rest| rest.trim_end().split(':').collect())
+        .unwrap_or_default();
+    match fields[..] {
+        [cipher, key_id, nonce, ciphertext] => Ok((cipher, key_id, nonce, ciphertext)),
+        _ => Err(anyhow!("Malformed sealed payload header")),
+    }
+}
+
+/// Decode a 256-bit key written as 64 hex digits
+pub(crate) fn parse_key_hex(hex: &str) -> Result<[u8; 32]> {
+    key_array(&decode_hex(hex.trim()).context("Key is not valid hex")?)
+}
+
+/// Read a 256-bit key from a file holding either 64 hex digits or 32 raw bytes;
+/// `what` names the key in errors
+pub(crate) fn read_key_bytes(path: &Path, what: &str) -> Result<[u8; 32]> {
+    let contents = std::fs::read(path)
+        .with_context(|| format!("Failed to read {}: {}", what, path.display()))?;
+    
+    match std::str::from_utf8(&contents) {
+        Ok(hex) if contents.len() != 32 => parse_key_hex(hex),
+        _ => key_array(&contents),
+    }
+    .with_context(|| format!("Invalid {} file: {}", what, path.display()))
+}
+
+fn key_array(bytes: &[u8]) -> Result<[u8; 32]> {
+    bytes.try_into().map_err(|_| anyhow!("Keys must be 32 bytes, got {}", bytes.len()))
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub(crate) fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(anyhow!("Invalid hex string"));
+    }
+    
+    (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| anyhow!("Invalid hex string")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_seal_and_open() {
+        let old = StorageKey::from_bytes(&[1; 32]).unwrap();
+        let new = StorageKey::from_hex(&"02".repeat(32)).unwrap();
+        let sealed = StorageEncryption::new(old.clone()).seal(b"{\"state\":{}}").unwrap();
+        assert!(sealed.starts_with(&format!("mcp-sealed:1:chacha20poly1305:{}:", old.id())));
+        
+        // Retired keys still open older payloads
+        let rotated = StorageEncryption::new(new.clone()).with_retired_key(old);
+        assert_eq!(open(Some(&rotated), sealed.clone().into_bytes()).unwrap(), b"{\"state\":{}}");
+        
+        // Plain payloads pass through only when allowed
+        assert!(open(Some(&rotated), b"{}".to_vec()).unwrap_err().to_string().contains("not encrypted"));
+        assert_eq!(allowing_plaintext(|| open(Some(&rotated), b"{}".to_vec())).unwrap(), b"{}");
+        assert!(open(Some(&rotated), b"{}".to_vec()).is_err());
+        let permissive = rotated.clone().with_plaintext_allowed(true);
+        assert_eq!(open(Some(&permissive), b"{}".to_vec()).unwrap(), b"{}");
+        assert_eq!(open(None, b"{}".to_vec()).unwrap(), b"{}");
+        
+        let error = open(Some(&StorageEncryption::new(new)), sealed.clone().into_bytes()).unwrap_err();
+        assert!(error.to_string().contains("not configured"));
+        assert!(open(None, sealed.clone().into_bytes()).unwrap_err().to_string().contains("no storage encryption key"));
+        
+        // Tampering with the header or ciphertext is detected
+        let tampered = sealed.replacen("chacha20poly1305:", "chacha20poly1305:0", 1);
+        assert!(open(Some(&rotated), tampered.into_bytes()).is_err());
+        let mut flipped = sealed.clone();
+        let last = if flipped.ends_with('0') { "1" } else { "0" };
+        flipped.replace_range(flipped.len() - 1.., last);
+        assert!(open(Some(&rotated), flipped.into_bytes()).is_err());
+    }
+    
+    #[test]
+    fn test_read_key_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (hex, raw, short) = (dir.path().join("hex.key"), dir.path().join("raw.key"), dir.path().join("short.key"));
+        std::fs::write(&hex, format!("{}\n", "ab".repeat(32))).unwrap();
+        std::fs::write(&raw, [0xab; 32]).unwrap();
+        std::fs::write(&short, "ab".repeat(31)).unwrap();
+        
+        // Either encoding gives the same key
+        assert_eq!(read_key_bytes(&hex, "test key").unwrap(), [0xab; 32]);
+        assert_eq!(read_key_bytes(&raw, "test key").unwrap(), [0xab; 32]);
+        
+        let error = format!("{:#}", read_key_bytes(&short, "test key").unwrap_err());
+        assert!(error.contains("Invalid test key file") && error.contains("got 31"), "{}", error);
+        let error = format!("{:#}", read_key_bytes(&dir.path().join("missing.key"), "test key").unwrap_err());
+        assert!(error.starts_with("Failed to read test key"), "{}", error);
+    }
+}
@@ -1,44 +1,200 @@
-use anyhow::Error;
+//! Error types for the MCP-ZERO kernel
+//!
+//! Every error has a stable string code (see [`KernelError::code`]) and serializes to
+//! `{"code", "message", "agent_id"?, "plugin_id"?, "path"?, "approval_id"?}` so RPC layers and the
+//! trace engine can record machine-readable failures.
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+use crate::agent::{AgentId, AgentStatus};
+use crate::approval::ApprovalId;
+use crate::ethical::{EthicalViolation, PathStep};
+use crate::plugin::PluginId;
+
+/// Error types for the MCP-ZERO kernel
+#[derive(Error, Debug, Clone)]
 pub enum KernelError {
-    #[error("Agent not found: {0}")]
-    AgentNotFound(String),
+    #[error("Agent not found: {agent_id}")]
+    AgentNotFound { agent_id: AgentId },
     
-    #[error("Plugin not found: {0}")]
-    PluginNotFound(String),
+    #[error("Agent already exists: {agent_id}")]
+    AgentAlreadyExists { agent_id: AgentId },
     
-    #[error("Plugin incompatible: {0}")]
-    PluginIncompatible(String),
+    #[error("Agent {agent_id} is not active (status: {status:?})")]
+    AgentNotActive { agent_id: AgentId, status: AgentStatus },
     
-    #[error("Execution failed: {0}")]
-    ExecutionFailed(String),
+    #[error("Invalid state transition for agent {agent_id}: {from:?} -> {to:?}")]
+    InvalidStateTransition { agent_id: AgentId, from: AgentStatus, to: AgentStatus },
+    
+    #[error("Plugin not found: {plugin_id}: {reason}")]
+    PluginNotFound { plugin_id: PluginId, reason: String },
+    
+    #[error("Plugin incompatible: {plugin_id}: {reason}")]
+    PluginIncompatible { plugin_id: PluginId, reason: String },
+    
+    #[error("Plugin {plugin_id} is the entry plugin of agent {agent_id}")]
+    EntryPluginInUse { agent_id: AgentId, plugin_id: PluginId },
+    
+    #[error("Agent {agent_id} needs plugins that are not available: {}", plugin_ids.join(", "))]
+    MissingPlugins { agent_id: AgentId, plugin_ids: Vec<PluginId> },
+    
+    #[error("Resource limit exceeded: {0}")]
+    ResourceLimitExceeded(String),
+    
+    #[error("Hardware constraints exceeded: {0}")]
+    HardwareConstraintsExceeded(String),
+    
+    #[error("Permission denied: {reason}")]
+    PermissionDenied { plugin_id: PluginId, reason: String },
     
-    #[error("Ethical constraint violated: {0}")]
-    EthicalConstraintViolated(String),
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
     
     #[error("Storage error: {0}")]
     StorageError(String),
     
+    #[error("Snapshot of agent {agent_id} is corrupted: expected checksum {expected}, found {actual}")]
+    SnapshotCorrupted { agent_id: AgentId, expected: String, actual: String },
+    
+    #[error("Agent {agent_id} is over its storage quota: the snapshot and state need {required} bytes of {quota}")]
+    StorageQuotaExceeded { agent_id: AgentId, required: u64, quota: u64 },
+    
+    #[error("Execution error: {0}")]
+    ExecutionError(String),
+    
+    #[error("Execution failed: {0}")]
+    ExecutionFailed(String),
+    
+    #[error("Execution timed out for agent {agent_id} after {elapsed_ms} ms")]
+    ExecutionTimeout { agent_id: AgentId, elapsed_ms: u64 },
+    
+    #[error("Plugin {plugin_id} exceeded its {resource} limit")]
+    PluginResourceExceeded { plugin_id: PluginId, resource: String },
+    
+    /// `path` holds the rules the ethical tree evaluated on the way to the denial
+    #[error("Ethical constraint violated: {reason}")]
+    EthicalConstraintViolated { reason: String, path: Vec<PathStep> },
+    
+    /// The attachment is held until an operator grants `approval_id`
+    #[error("Attaching plugin {plugin_id} to agent {agent_id} needs operator approval {approval_id} (risk score {score})")]
+    ApprovalRequired { approval_id: ApprovalId, agent_id: AgentId, plugin_id: PluginId, score: f64 },
+    
+    #[error("Approval not found: {approval_id}")]
+    ApprovalNotFound { approval_id: ApprovalId },
+    
     #[error("Trace error: {0}")]
     TraceError(String),
     
-    #[error("Hardware constraints exceeded: {0}")]
-    HardwareConstraintsExceeded(String),
+    #[error("Kernel is shutting down")]
+    ShuttingDown,
+    
+    #[error("Job not found: {job_id}")]
+    JobNotFound { job_id: String },
     
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
-// Implement From<anyhow::Error> for KernelError for easier error conversion
+impl KernelError {
+    /// Stable machine-readable code of the error
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::AgentNotFound { .. } => "agent_not_found",
+            Self::AgentAlreadyExists { .. } => "agent_already_exists",
+            Self::AgentNotActive { .. } => "agent_not_active",
+            Self::InvalidStateTransition { .. } => "invalid_state_transition",
+            Self::PluginNotFound { .. } => "plugin_not_found",
+            Self::PluginIncompatible { .. } => "plugin_incompatible",
+            Self::EntryPluginInUse { .. } => "entry_plugin_in_use",
+            Self::MissingPlugins { .. } => "missing_plugins",
+            Self::ResourceLimitExceeded(_) => "resource_limit_exceeded",
+            Self::HardwareConstraintsExceeded(_) => "hardware_constraints_exceeded",
+            Self::PermissionDenied { .. } => "permission_denied",
+            Self::InvalidConfiguration(_) => "invalid_configuration",
+            Self::StorageError(_) => "storage_error",
+            Self::SnapshotCorrupted { .. } => "snapshot_corrupted",
+            Self::StorageQuotaExceeded { .. } => "storage_quota_exceeded",
+            Self::ExecutionError(_) => "execution_error",
+            Self::ExecutionFailed(_) => "execution_failed",
+            Self::ExecutionTimeout { .. } => "execution_timeout",
+            Self::PluginResourceExceeded { .. } => "plugin_resource_exceeded",
+            Self::EthicalConstraintViolated { .. } => "ethical_constraint_violated",
+            Self::ApprovalRequired { .. } => "approval_required",
+            Self::ApprovalNotFound { .. } => "approval_not_found",
+            Self::TraceError(_) => "trace_error",
+            Self::ShuttingDown => "shutting_down",
+            Self::JobNotFound { .. } => "job_not_found",
+            Self::Internal(_) => "internal",
+        }
+    }
+    
+    /// Agent the error concerns, if any
+    pub fn agent_id(&self) -> Option<&AgentId> {
+        match self {
+            Self::AgentNotFound { agent_id }
+            | Self::AgentAlreadyExists { agent_id }
+            | Self::AgentNotActive { agent_id, .. }
+            | Self::InvalidStateTransition { agent_id, .. }
+            | Self::EntryPluginInUse { agent_id, .. }
+            | Self::MissingPlugins { agent_id, .. }
+            | Self::SnapshotCorrupted { agent_id, .. }
+            | Self::StorageQuotaExceeded { agent_id, .. }
+            | Self::ExecutionTimeout { agent_id, .. }
+            | Self::ApprovalRequired { agent_id, .. } => Some(agent_id),
+            _ => None,
+        }
+    }
+    
+    /// Plugin the error concerns, if any
+    pub fn plugin_id(&self) -> Option<&PluginId> {
+        match self {
+            Self::PluginNotFound { plugin_id, .. }
+            | Self::PluginIncompatible { plugin_id, .. }
+            | Self::EntryPluginInUse { plugin_id, .. }
+            | Self::PermissionDenied { plugin_id, .. }
+            | Self::PluginResourceExceeded { plugin_id, .. }
+            | Self::ApprovalRequired { plugin_id, .. } => Some(plugin_id),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for KernelError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("code", self.code())?;
+        map.serialize_entry("message", &self.to_string())?;
+        if let Some(agent_id) = self.agent_id() {
+            map.serialize_entry("agent_id", agent_id)?;
+        }
+        if let Some(plugin_id) = self.plugin_id() {
+            map.serialize_entry("plugin_id", plugin_id)?;
+        }
+        if let Self::EthicalConstraintViolated { path, .. } = self {
+            if !path.is_empty() {
+                map.serialize_entry("path", path)?;
+            }
+        }
+        if let Self::ApprovalRequired { approval_id, .. } | Self::ApprovalNotFound { approval_id } = self {
+            map.serialize_entry("approval_id", approval_id)?;
+        }
+        map.end()
+    }
+}
+
 impl From<anyhow::Error> for KernelError {
-    fn from(error: Error) -> Self {
+    fn from(error: anyhow::Error) -> Self {
         KernelError::Internal(error.to_string())
     }
 }
 
-// Additional From implementations for common error types
+impl From<EthicalViolation> for KernelError {
+    fn from(violation: EthicalViolation) -> Self {
+        KernelError::EthicalConstraintViolated { reason: violation.reason, path: violation.path }
+    }
+}
+
 impl From<std::io::Error> for KernelError {
     fn from(error: std::io::Error) -> Self {
         KernelError::StorageError(error.to_string())
@@ -50,3 +206,52 @@ impl From<serde_json::Error> for KernelError {
         KernelError::Internal(format!("JSON error: {}", error))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_error_serialization() {
+        let error = KernelError::EntryPluginInUse {
+            agent_id: "agent_1".to_string(),
+            plugin_id: "entry".to_string(),
+        };
+        assert_eq!(serde_json::to_value(&error).unwrap(), serde_json::json!({
+            "code": "entry_plugin_in_use",
+            "message": "Plugin entry is the entry plugin of agent agent_1",
+            "agent_id": "agent_1",
+            "plugin_id": "entry",
+        }));
+        
+        let error = KernelError::StorageError("disk full".to_string());
+        assert_eq!(serde_json::to_value(&error).unwrap(), serde_json::json!({
+            "code": "storage_error",
+            "message": "Storage error: disk full",
+        }));
+        
+        let error = KernelError::EthicalConstraintViolated {
+            reason: "Intent violates ethical constraints (path: root is_harmful: true)".to_string(),
+            path: vec![("root".to_string(), "is_harmful".to_string(), true)],
+        };
+        assert_eq!(serde_json::to_value(&error).unwrap(), serde_json::json!({
+            "code": "ethical_constraint_violated",
+            "message": "Ethical constraint violated: Intent violates ethical constraints (path: root is_harmful: true)",
+            "path": [["root", "is_harmful", true]],
+        }));
+        
+        let error = KernelError::ApprovalRequired {
+            approval_id: "approval_1".to_string(),
+            agent_id: "agent_1".to_string(),
+            plugin_id: "fetch".to_string(),
+            score: 0.7,
+        };
+        assert_eq!(serde_json::to_value(&error).unwrap(), serde_json::json!({
+            "code": "approval_required",
+            "message": "Attaching plugin fetch to agent agent_1 needs operator approval approval_1 (risk score 0.7)",
+            "agent_id": "agent_1",
+            "plugin_id": "fetch",
+            "approval_id": "approval_1",
+        }));
+    }
+}
@@ -3,26 +3,190 @@
 //! Implements a decision tree for ethical governance of agent actions,
 //! providing verifiable and traceable ethical decision-making.
 
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
-use anyhow::{Result, anyhow};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockWriteGuard};
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context, anyhow};
 use serde::{Serialize, Deserialize};
 
 use crate::agent::{AgentId, AgentConfig};
+use crate::intent::match_intent;
 use crate::plugin::Plugin;
 
 /// Node ID for the binary tree
-type NodeId = String;
+pub type NodeId = String;
+
+/// Event type of the trace entries auditing each decision
+pub const ETHICS_DECISION_EVENT: &str = "ethics.decision";
+
+/// Step of an evaluation: the node visited, its rule, and the rule's result
+pub type PathStep = (NodeId, String, bool);
+
+/// Rule of rate-limit nodes, true once an agent has used up its executions
+const RATE_LIMITED_RULE: &str = "rate_limited";
 
 /// Decision outcome
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Decision {
     /// Action is allowed
+    #[serde(alias = "allow")]
     Allow,
     /// Action is denied
+    #[serde(alias = "deny")]
     Deny,
 }
 
+impl Decision {
+    /// Risk weight of a leaf with this decision and no weight of its own
+    fn default_weight(self) -> f64 {
+        match self {
+            Decision::Allow => 0.0,
+            Decision::Deny => 1.0,
+        }
+    }
+}
+
+/// Outcome of an evaluation, with the path through the tree that led to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verdict {
+    pub decision: Decision,
+    
+    /// Rules evaluated on the way to the decision, from the root
+    pub path: Vec<PathStep>,
+    
+    /// Version of the policy that decided
+    pub policy_version: String,
+    
+    /// Leaf the evaluation ended at, unless a missing node or branch fell back to a
+    /// default decision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leaf: Option<NodeId>,
+}
+
+impl Verdict {
+    /// The path as `root is_harmful: true -> harmful has_consent: false`
+    pub fn describe_path(&self) -> String {
+        self.path.iter()
+            .map(|(node, rule, result)| format!("{} {}: {}", node, rule, result))
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+}
+
+/// Risk score of an evaluation, for weighing an action rather than taking the binary
+/// decision
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RiskAssessment {
+    /// Weight of the leaf reached: its `weight` in the policy, or 0 for an allow leaf
+    /// and 1 for a deny leaf without one
+    pub score: f64,
+    
+    /// Rules that held on the way to the leaf
+    pub factors: Vec<String>,
+    
+    pub verdict: Verdict,
+}
+
+/// A decision of the ethical engine, as passed to its decision hook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EthicsDecision {
+    /// Agent the decision concerns, when the evaluated data names one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<AgentId>,
+    
+    /// What was evaluated, such as `agent_spawn`
+    pub context: String,
+    
+    pub decision: Decision,
+    
+    /// Rules evaluated on the way to the decision; empty when a prohibited term
+    /// decided before the tree was consulted
+    pub path: Vec<PathStep>,
+    
+    /// Version of the policy in force
+    pub policy_version: String,
+    
+    /// BLAKE3 hash of the evaluated data's JSON, so the input can be matched later
+    /// without the audit log holding it
+    pub input_hash: String,
+    
+    pub timestamp: i64,
+}
+
+/// Called with every decision the ethical engine makes
+pub type DecisionHook = Arc<dyn Fn(&EthicsDecision) + Send + Sync>;
+
+/// Refusal of an operation by the ethical engine
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{reason}")]
+pub struct EthicalViolation {
+    pub reason: String,
+    
+    /// Path through the tree that led to the refusal; empty for checks made before
+    /// the tree is consulted, such as prohibited terms
+    pub path: Vec<PathStep>,
+}
+
+impl EthicalViolation {
+    fn new(reason: String) -> Self {
+        Self { reason, path: Vec::new() }
+    }
+    
+    /// Check a verdict, explaining a denial with its path
+    fn check(verdict: Verdict, reason: &str) -> Result<(), Self> {
+        match verdict.decision {
+            Decision::Allow => Ok(()),
+            Decision::Deny if verdict.path.is_empty() => Err(Self::new(reason.to_string())),
+            Decision::Deny => Err(Self {
+                reason: format!("{} (path: {})", reason, verdict.describe_path()),
+                path: verdict.path,
+            }),
+        }
+    }
+}
+
+/// Branch of a node a child hangs from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Branch {
+    /// Taken when the node's rule is false
+    Left,
+    /// Taken when the node's rule is true
+    Right,
+}
+
+impl Branch {
+    fn name(self) -> &'static str {
+        match self {
+            Branch::Left => "left",
+            Branch::Right => "right",
+        }
+    }
+}
+
+/// Structural problem of an ethical tree, see [`EthicalBinaryTree::validate`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, thiserror::Error)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum StructuralProblem {
+    /// The node leads back to itself, so an evaluation reaching it never ends
+    #[error("node {node} is in a cycle")]
+    Cycle { node: NodeId },
+    
+    /// The root doesn't lead to the node, so it's never evaluated
+    #[error("node {node} can't be reached from the root")]
+    Orphan { node: NodeId },
+    
+    /// The node names a child that doesn't exist
+    #[error("node {node} has a {} child that doesn't exist: {child}", branch.name())]
+    DanglingChild { node: NodeId, branch: Branch, child: NodeId },
+    
+    /// A node without a decision lacks a child, so evaluations taking that branch
+    /// fall back to a default decision
+    #[error("node {node} has neither a decision nor a {} child", branch.name())]
+    MissingChild { node: NodeId, branch: Branch },
+}
+
 /// Node in the ethical binary tree
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct EthicalNode {
@@ -43,15 +207,333 @@ struct EthicalNode {
     
     /// Terminal decision if this is a leaf node
     decision: Option<Decision>,
+    
+    /// Limit checked in place of the rule by rate-limit nodes
+    rate_limit: Option<RateLimit>,
+    
+    /// Risk weight of a leaf, see [`RiskAssessment`]
+    weight: Option<f64>,
 }
 
-/// Ethical Binary Tree implementation
-pub struct EthicalBinaryTree {
-    /// Nodes in the tree
-    nodes: Arc<RwLock<HashMap<NodeId, EthicalNode>>>,
+/// Parameters of a rate-limit node: each agent may run `limit` of the intents counted
+/// per `window_ms`, with spent executions refilled evenly over the window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// Executions allowed per window, and the most an agent can save up
+    pub limit: u32,
+    
+    /// Window length in milliseconds
+    pub window_ms: u64,
+    
+    /// Intents counted, exact or as glob patterns; every intent when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub intents: Vec<String>,
+}
+
+impl RateLimit {
+    /// Tokens refilled per millisecond
+    fn rate(&self) -> f64 {
+        self.limit as f64 / self.window_ms as f64
+    }
+}
+
+/// Rate-limit bucket key: the agent and the rate-limit node
+type BucketKey = (AgentId, NodeId);
+
+/// Token bucket of one agent at one rate-limit node
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    
+    /// When the tokens were last refilled
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Add the tokens refilled since the last update, up to the limit
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed_ms = now.saturating_duration_since(self.updated).as_secs_f64() * 1000.0;
+        self.tokens = (self.tokens + elapsed_ms * limit.rate()).min(limit.limit as f64);
+        self.updated = now;
+    }
+    
+    /// Time until a whole token is available
+    fn next_token(&self, limit: &RateLimit) -> Duration {
+        let missing = (1.0 - self.tokens).max(0.0);
+        Duration::from_millis((missing / limit.rate()).ceil() as u64)
+    }
+}
+
+/// How the terms of a [`ProhibitedTerms`] list match
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TermMatch {
+    /// Anywhere in the text, so `hack` matches `lifehack_coach`
+    #[default]
+    Substring,
+    /// Whole words only, delimited by anything but letters and digits
+    Word,
+}
+
+/// List of terms agent names or intents must not contain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProhibitedTerms {
+    pub terms: Vec<String>,
+    
+    #[serde(default)]
+    pub matching: TermMatch,
+    
+    #[serde(default)]
+    pub case_sensitive: bool,
+    
+    /// Exceptions: a term is ignored where it falls within one of these, so `lifehack`
+    /// lets `lifehack_coach` through without allowing `hack` itself
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+}
+
+impl ProhibitedTerms {
+    /// Case-insensitive substrings
+    pub fn new<S: Into<String>>(terms: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            terms: terms.into_iter().map(Into::into).collect(),
+            matching: TermMatch::Substring,
+            case_sensitive: false,
+            allow: Vec::new(),
+        }
+    }
+    
+    /// Terms agent names are checked against by default
+    pub fn default_agent_names() -> Self {
+        Self::new(["malware", "exploit", "hack", "attack", "virus"])
+    }
+    
+    /// Actions intents are checked against by default
+    pub fn default_intents() -> Self {
+        Self::new(["delete_all", "format", "wipe", "destroy"])
+    }
+    
+    /// First term the text contains outside the exceptions
+    pub fn find(&self, text: &str) -> Option<&str> {
+        let fold = |text: &str| if self.case_sensitive { text.to_string() } else { text.to_lowercase() };
+        let text = fold(text);
+        let occurrences = |term: &str| -> Vec<(usize, usize)> {
+            let term = fold(term);
+            if term.is_empty() {
+                return Vec::new();
+            }
+            text.match_indices(term.as_str()).map(|(start, found)| (start, start + found.len())).collect()
+        };
+        let allowed: Vec<(usize, usize)> = self.allow.iter().flat_map(|term| occurrences(term)).collect();
+        
+        self.terms.iter()
+            .find(|term| occurrences(term).into_iter().any(|(start, end)| {
+                let whole_word = text[..start].chars().next_back().is_none_or(|c| !c.is_alphanumeric())
+                    && text[end..].chars().next().is_none_or(|c| !c.is_alphanumeric());
+                (self.matching == TermMatch::Substring || whole_word)
+                    && !allowed.iter().any(|&(from, to)| from <= start && end <= to)
+            }))
+            .map(String::as_str)
+    }
+}
+
+/// Node as written in an ethics policy file; parents follow from the children named
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyNode {
+    id: NodeId,
+    
+    /// Decision rule, unused by leaves
+    #[serde(default)]
+    rule: String,
+    
+    left: Option<NodeId>,
+    
+    right: Option<NodeId>,
+    
+    decision: Option<Decision>,
+    
+    /// Makes the node a rate-limit node, see [`EthicalBinaryTree::from_yaml`]
+    rate_limit: Option<RateLimit>,
+    
+    /// Risk weight of a leaf
+    weight: Option<f64>,
+}
+
+/// Ethics policy file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EthicsPolicy {
+    nodes: Vec<PolicyNode>,
+}
+
+/// Serializable view of an ethical tree, in the shape of an ethics policy file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EthicalTreeView {
+    /// Nodes depth-first from the root, left branches first, then any the root
+    /// doesn't lead to by ID
+    pub nodes: Vec<EthicalNodeView>,
+}
+
+/// Node of an [`EthicalTreeView`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EthicalNodeView {
+    pub id: String,
+    
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub rule: String,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub left: Option<String>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub right: Option<String>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<Decision>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+}
+
+/// Nodes of a tree, replaced as a whole when rules change or the policy is reloaded
+#[derive(Debug, Clone)]
+struct Policy {
+    nodes: HashMap<NodeId, EthicalNode>,
     
     /// Root node ID
     root: NodeId,
+    
+    /// Hash of the tree, see [`EthicalBinaryTree::policy_version`]
+    version: String,
+}
+
+impl Policy {
+    fn new(nodes: HashMap<NodeId, EthicalNode>, root: NodeId) -> Self {
+        let mut policy = Self { nodes, root, version: String::new() };
+        policy.version = policy.hash();
+        policy
+    }
+    
+    /// First 16 hex digits of the BLAKE3 hash of the tree's view
+    fn hash(&self) -> String {
+        let view = serde_json::to_string(&self.view()).unwrap_or_default();
+        blake3::hash(view.as_bytes()).to_hex()[..16].to_string()
+    }
+    
+    /// Problems in how the nodes fit together, cycles first, then orphans, then
+    /// problems of single nodes by node ID
+    fn problems(&self) -> Vec<StructuralProblem> {
+        let mut ids: Vec<&NodeId> = self.nodes.keys().collect();
+        ids.sort();
+        let children = |id: &NodeId| -> Vec<&NodeId> {
+            self.nodes.get(id).into_iter()
+                .flat_map(|node| node.left.iter().chain(node.right.iter()))
+                .filter(|child| self.nodes.contains_key(*child))
+                .collect()
+        };
+        
+        // Depth-first from every node, finding edges back to a node still being explored
+        let mut problems = Vec::new();
+        let mut finished = HashSet::new();
+        let mut cyclic = HashSet::new();
+        for start in &ids {
+            if finished.contains(*start) {
+                continue;
+            }
+            let mut exploring = vec![(*start, children(start).into_iter())];
+            let mut on_path: HashSet<&NodeId> = HashSet::from([*start]);
+            while let Some((id, pending)) = exploring.last_mut() {
+                match pending.next() {
+                    Some(child) if on_path.contains(child) => {
+                        if !cyclic.contains(child) {
+                            problems.push(StructuralProblem::Cycle { node: child.clone() });
+                        }
+                        let entered = exploring.iter().position(|(id, _)| *id == child).unwrap_or(0);
+                        cyclic.extend(exploring[entered..].iter().map(|(id, _)| *id));
+                    },
+                    Some(child) if !finished.contains(child) => {
+                        on_path.insert(child);
+                        exploring.push((child, children(child).into_iter()));
+                    },
+                    Some(_) => {},
+                    None => {
+                        let id = *id;
+                        on_path.remove(id);
+                        finished.insert(id);
+                        exploring.pop();
+                    },
+                }
+            }
+        }
+        
+        let reached: HashSet<NodeId> = EthicalBinaryTree::subtree(&self.nodes, &self.root).into_iter().collect();
+        for id in &ids {
+            if !reached.contains(*id) && !cyclic.contains(*id) {
+                problems.push(StructuralProblem::Orphan { node: (*id).clone() });
+            }
+        }
+        
+        for id in &ids {
+            let node = &self.nodes[*id];
+            for branch in [Branch::Left, Branch::Right] {
+                match EthicalBinaryTree::child(node, branch) {
+                    Some(child) if !self.nodes.contains_key(child) => {
+                        problems.push(StructuralProblem::DanglingChild { node: node.id.clone(), branch, child: child.clone() });
+                    },
+                    None if node.decision.is_none() => {
+                        problems.push(StructuralProblem::MissingChild { node: node.id.clone(), branch });
+                    },
+                    _ => {},
+                }
+            }
+        }
+        problems
+    }
+    
+    fn view(&self) -> EthicalTreeView {
+        let mut ids = EthicalBinaryTree::subtree(&self.nodes, &self.root);
+        let reached: HashSet<NodeId> = ids.iter().cloned().collect();
+        let mut unreached: Vec<NodeId> = self.nodes.keys().filter(|id| !reached.contains(*id)).cloned().collect();
+        unreached.sort();
+        ids.extend(unreached);
+        
+        let nodes = ids.into_iter()
+            .filter_map(|id| self.nodes.get(&id))
+            .map(|node| EthicalNodeView {
+                id: node.id.clone(),
+                rule: node.rule.clone(),
+                left: node.left.clone(),
+                right: node.right.clone(),
+                decision: node.decision,
+                rate_limit: node.rate_limit.clone(),
+                weight: node.weight,
+            })
+            .collect();
+        EthicalTreeView { nodes }
+    }
+}
+
+/// Ethical Binary Tree implementation
+pub struct EthicalBinaryTree {
+    /// Policy in force; swapping it leaves evaluations under way with the old one
+    policy: RwLock<Arc<Policy>>,
+    
+    /// Terms agent names must not contain
+    prohibited_agent_names: ProhibitedTerms,
+    
+    /// Actions intents must not contain
+    prohibited_intents: ProhibitedTerms,
+    
+    /// Called with every decision, for auditing
+    decision_hook: Option<DecisionHook>,
+    
+    /// Token buckets of the rate-limit nodes, kept across policy reloads
+    rate_buckets: Mutex<HashMap<BucketKey, TokenBucket>>,
 }
 
 impl EthicalBinaryTree {
@@ -72,6 +554,8 @@ impl EthicalBinaryTree {
                 left: Some("allow".to_string()),  // not harmful -> allow
                 right: Some("harmful".to_string()), // harmful -> check more
                 decision: None,
+                rate_limit: None,
+                weight: None,
             },
         );
         
@@ -85,6 +569,8 @@ impl EthicalBinaryTree {
                 left: None,
                 right: None,
                 decision: Some(Decision::Allow),
+                rate_limit: None,
+                weight: None,
             },
         );
         
@@ -98,6 +584,8 @@ impl EthicalBinaryTree {
                 left: Some("deny".to_string()),   // no consent -> deny
                 right: Some("check_legal".to_string()), // has consent -> check legal
                 decision: None,
+                rate_limit: None,
+                weight: None,
             },
         );
         
@@ -111,6 +599,8 @@ impl EthicalBinaryTree {
                 left: None,
                 right: None,
                 decision: Some(Decision::Deny),
+                rate_limit: None,
+                weight: None,
             },
         );
         
@@ -124,6 +614,8 @@ impl EthicalBinaryTree {
                 left: Some("deny_illegal".to_string()),  // not legal -> deny
                 right: Some("allow_legal".to_string()),  // legal -> allow
                 decision: None,
+                rate_limit: None,
+                weight: None,
             },
         );
         
@@ -137,6 +629,8 @@ impl EthicalBinaryTree {
                 left: None,
                 right: None,
                 decision: Some(Decision::Deny),
+                rate_limit: None,
+                weight: None,
             },
         );
         
@@ -150,44 +644,290 @@ impl EthicalBinaryTree {
                 left: None,
                 right: None,
                 decision: Some(Decision::Allow),
+                rate_limit: None,
+                weight: None,
             },
         );
         
+        Self::with_policy(Policy::new(nodes, root_id))
+    }
+    
+    /// Replace the terms agent names must not contain
+    pub fn with_prohibited_agent_names(mut self, terms: ProhibitedTerms) -> Self {
+        self.prohibited_agent_names = terms;
+        self
+    }
+    
+    /// Replace the actions intents must not contain
+    pub fn with_prohibited_intents(mut self, terms: ProhibitedTerms) -> Self {
+        self.prohibited_intents = terms;
+        self
+    }
+    
+    /// Call `hook` with every decision made, including denials for prohibited terms
+    pub fn with_decision_hook(mut self, hook: DecisionHook) -> Self {
+        self.decision_hook = Some(hook);
+        self
+    }
+    
+    /// Replace the tree with the policy in a YAML ethics policy file, returning the new
+    /// version
+    ///
+    /// Evaluations under way finish against the old policy. A policy that can't be
+    /// read or is invalid leaves the old one in force.
+    pub fn reload<P: AsRef<Path>>(&self, path: P) -> Result<String> {
+        let policy = Self::from_file(path)?.policy();
+        let version = policy.version.clone();
+        *self.lock_policy() = policy;
+        Ok(version)
+    }
+    
+    /// Version of the policy in force: a hash of the tree, which changes with any rule
+    /// and is the same for identical trees however they were loaded
+    pub fn policy_version(&self) -> String {
+        self.policy().version.clone()
+    }
+    
+    fn policy(&self) -> Arc<Policy> {
+        self.policy.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    
+    fn lock_policy(&self) -> RwLockWriteGuard<'_, Arc<Policy>> {
+        self.policy.write().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Load a tree from a YAML ethics policy file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let yaml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read ethics policy: {}", path.display()))?;
+        Self::from_yaml(&yaml)
+            .with_context(|| format!("Invalid ethics policy: {}", path.display()))
+    }
+    
+    /// Load a tree from a YAML ethics policy
+    ///
+    /// The policy lists the tree's nodes. A branch names a `rule` and two children: the
+    /// `left` is taken when the rule is false, the `right` when it is true. A leaf
+    /// gives the `decision`, `allow` or `deny`:
+    ///
+    /// ```yaml
+    /// nodes:
+    ///   - id: root
+    ///     rule: is_harmful
+    ///     left: allow
+    ///     right: deny
+    ///   - id: allow
+    ///     decision: allow
+    ///   - id: deny
+    ///     decision: deny
+    /// ```
+    ///
+    /// A leaf may also give a risk `weight`, used when scoring rather than deciding
+    /// (see [`assess`](Self::assess)).
+    ///
+    /// A rate-limit node gives a `rate_limit` in place of the rule, limiting how many
+    /// of the `intents` listed (all when left out) each agent may execute per window.
+    /// Its rule, `rate_limited`, is true once the agent's executions are used up, so
+    /// its right child would usually deny:
+    ///
+    /// ```yaml
+    ///   - id: external_access
+    ///     rate_limit: {limit: 10, window_ms: 60000, intents: ["external.*"]}
+    ///     left: allow
+    ///     right: deny
+    /// ```
+    ///
+    /// The nodes must form one tree, with a single root, in which every path ends in a
+    /// decision; otherwise the error names the offending node.
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        let policy: EthicsPolicy = serde_yaml::from_str(yaml)
+            .context("Failed to parse ethics policy as YAML")?;
+        let (order, mut nodes) = Self::policy_nodes(policy)?;
+        
+        // Branches need both children, so every path goes on to a leaf
+        let mut links = Vec::new();
+        for id in &order {
+            let node = &nodes[id];
+            let children = [("left", &node.left), ("right", &node.right)];
+            if node.decision.is_some() {
+                if let Some((side, _)) = children.iter().find(|(_, child)| child.is_some()) {
+                    return Err(anyhow!("Ethics policy node {} has both a decision and a {} child", id, side));
+                }
+                continue;
+            }
+            for (side, child) in children {
+                let child = child.as_ref()
+                    .ok_or_else(|| anyhow!("Ethics policy node {} has neither a decision nor a {} child", id, side))?;
+                if !nodes.contains_key(child) {
+                    return Err(anyhow!("Ethics policy node {} has a {} child that doesn't exist: {}", id, side, child));
+                }
+                links.push((id.clone(), child.clone()));
+            }
+        }
+        for (parent, child) in links {
+            let node = nodes.get_mut(&child).expect("children were checked to exist");
+            if let Some(other) = &node.parent {
+                return Err(anyhow!("Ethics policy node {} has more than one parent: {} and {}", child, other, parent));
+            }
+            node.parent = Some(parent);
+        }
+        
+        let roots: Vec<&NodeId> = order.iter().filter(|id| nodes[*id].parent.is_none()).collect();
+        let root = match roots[..] {
+            [root] => root.clone(),
+            [] => return Err(anyhow!("Ethics policy has no root node: every node is another's child")),
+            _ => return Err(anyhow!(
+                "Ethics policy has more than one root node: {}",
+                roots.iter().map(|id| id.as_str()).collect::<Vec<_>>().join(", "),
+            )),
+        };
+        
+        // With one parent each, nodes the root doesn't lead to can only be in a cycle
+        let mut reached = HashSet::new();
+        let mut pending = vec![&root];
+        while let Some(id) = pending.pop() {
+            reached.insert(id);
+            let node = &nodes[id];
+            pending.extend(node.left.iter().chain(node.right.iter()));
+        }
+        if let Some(cycle) = order.iter().find(|id| !reached.contains(id)) {
+            return Err(anyhow!("Ethics policy node {} is in a cycle, out of reach of root {}", cycle, root));
+        }
+        
+        Ok(Self::with_policy(Policy::new(nodes, root)))
+    }
+    
+    /// Load a tree from JSON in the shape [`export_json`](Self::export_json) writes,
+    /// that of an ethics policy file
+    ///
+    /// Unlike [`from_yaml`](Self::from_yaml), only the nodes themselves are checked,
+    /// not how they fit together, so a malformed tree can be loaded for inspection.
+    /// The first node is taken as the root. Check the tree with
+    /// [`validate`](Self::validate) before use.
+    pub fn import_json(json: &str) -> Result<Self> {
+        let policy: EthicsPolicy = serde_json::from_str(json)
+            .context("Failed to parse ethics policy as JSON")?;
+        let (order, mut nodes) = Self::policy_nodes(policy)?;
+        
+        // A node named as a child more than once keeps its first parent
+        for id in &order {
+            let children: Vec<NodeId> = nodes[id].left.iter().chain(nodes[id].right.iter()).cloned().collect();
+            for child in children {
+                if let Some(node) = nodes.get_mut(&child) {
+                    node.parent.get_or_insert_with(|| id.clone());
+                }
+            }
+        }
+        
+        let root = order[0].clone();
+        Ok(Self::with_policy(Policy::new(nodes, root)))
+    }
+    
+    /// The tree as pretty-printed JSON, in the shape of an ethics policy file: nodes
+    /// depth-first from the root, then any the root doesn't lead to
+    pub fn export_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(&self.get_tree()).context("Failed to serialize ethical tree")
+    }
+    
+    /// Structural problems of the tree, which make evaluations fall back to default
+    /// decisions or never finish; empty for a well-formed tree
+    ///
+    /// Trees loaded with [`from_yaml`](Self::from_yaml) are always well-formed, but
+    /// [`add_rule`](Self::add_rule) leaves a new branch node without children until
+    /// they're added, and [`import_json`](Self::import_json) loads trees as they are.
+    pub fn validate(&self) -> Vec<StructuralProblem> {
+        self.policy().problems()
+    }
+    
+    /// Nodes of a policy file, checked one by one, along with their IDs in file order
+    fn policy_nodes(policy: EthicsPolicy) -> Result<(Vec<NodeId>, HashMap<NodeId, EthicalNode>)> {
+        if policy.nodes.is_empty() {
+            return Err(anyhow!("Ethics policy has no nodes"));
+        }
+        
+        let order: Vec<NodeId> = policy.nodes.iter().map(|node| node.id.clone()).collect();
+        let mut nodes = HashMap::new();
+        for node in policy.nodes {
+            let id = node.id.clone();
+            let mut rule = node.rule;
+            if let Some(limit) = &node.rate_limit {
+                if node.decision.is_some() || !rule.is_empty() {
+                    let other = if rule.is_empty() { "decision" } else { "rule" };
+                    return Err(anyhow!("Ethics policy node {} has both a rate_limit and a {}", id, other));
+                }
+                if limit.limit == 0 || limit.window_ms == 0 {
+                    return Err(anyhow!("Ethics policy node {} has a rate_limit with a zero limit or window", id));
+                }
+                rule = RATE_LIMITED_RULE.to_string();
+            }
+            if let Some(weight) = node.weight {
+                if node.decision.is_none() {
+                    return Err(anyhow!("Ethics policy node {} has a weight but no decision; only leaves are weighted", id));
+                }
+                if !(weight.is_finite() && weight >= 0.0) {
+                    return Err(anyhow!("Ethics policy node {} has a weight that isn't a non-negative number: {}", id, weight));
+                }
+            }
+            let duplicate = nodes.insert(id.clone(), EthicalNode {
+                id: node.id,
+                rule,
+                parent: None,
+                left: node.left,
+                right: node.right,
+                decision: node.decision,
+                rate_limit: node.rate_limit,
+                weight: node.weight,
+            });
+            if duplicate.is_some() {
+                return Err(anyhow!("Ethics policy node {} is defined more than once", id));
+            }
+        }
+        
+        Ok((order, nodes))
+    }
+    
+    fn with_policy(policy: Policy) -> Self {
         Self {
-            nodes: Arc::new(RwLock::new(nodes)),
-            root: root_id,
+            policy: RwLock::new(Arc::new(policy)),
+            prohibited_agent_names: ProhibitedTerms::default_agent_names(),
+            prohibited_intents: ProhibitedTerms::default_intents(),
+            decision_hook: None,
+            rate_buckets: Mutex::new(HashMap::new()),
         }
     }
     
     /// Validate agent spawn
-    pub fn validate_spawn(&self, config: &AgentConfig) -> Result<()> {
-        // Check if agent name contains prohibited terms
-        let prohibited_terms = ["malware", "exploit", "hack", "attack", "virus"];
-        let name_lower = config.name.to_lowercase();
+    pub fn validate_spawn(&self, agent_id: &AgentId, config: &AgentConfig) -> Result<(), EthicalViolation> {
+        let data = serde_json::json!({
+            "agent_id": agent_id,
+            "name": config.name,
+            "intents": config.intents,
+        });
         
-        for term in prohibited_terms {
-            if name_lower.contains(term) {
-                return Err(anyhow!("Agent name contains prohibited term: {}", term));
-            }
+        // Check if agent name contains prohibited terms
+        if let Some(term) = self.prohibited_agent_names.find(&config.name) {
+            self.audit("agent_spawn", &data, &self.denial());
+            return Err(EthicalViolation::new(format!(
+                "Agent name contains prohibited term {:?} from prohibited_agent_names", term,
+            )));
         }
         
         // Evaluate using the ethical tree
-        let eval_result = self.evaluate(
-            "agent_spawn",
-            &serde_json::json!({
-                "name": config.name,
-                "intents": config.intents,
-            }),
-        );
+        let verdict = self.evaluate("agent_spawn", &data);
         
-        match eval_result {
-            Decision::Allow => Ok(()),
-            Decision::Deny => Err(anyhow!("Ethical constraints prohibit this agent configuration")),
-        }
+        EthicalViolation::check(verdict, "Ethical constraints prohibit this agent configuration")
     }
     
-    /// Validate plugin
-    pub fn validate_plugin(&self, plugin: &Plugin) -> Result<()> {
+    /// Validate a plugin being attached to an agent
+    pub fn validate_plugin(&self, agent_id: &AgentId, plugin: &Plugin) -> Result<(), EthicalViolation> {
+        let verdict = self.assess_plugin(agent_id, plugin).verdict;
+        
+        EthicalViolation::check(verdict, "Plugin capabilities violate ethical constraints")
+    }
+    
+    /// Score the risk of a plugin being attached to an agent
+    pub fn assess_plugin(&self, agent_id: &AgentId, plugin: &Plugin) -> RiskAssessment {
         // Check capabilities
         let capabilities = plugin.capabilities();
         
@@ -201,99 +941,248 @@ impl EthicalBinaryTree {
         };
         
         // Evaluate using the ethical tree
-        let eval_result = self.evaluate(
+        self.assess(
             "plugin_validation",
             &serde_json::json!({
+                "agent_id": agent_id,
                 "id": plugin.id(),
                 "risk_level": risk_level,
                 "external_access": capabilities.external_access,
                 "plugin_call": capabilities.plugin_call,
             }),
-        );
-        
-        match eval_result {
-            Decision::Allow => Ok(()),
-            Decision::Deny => Err(anyhow!("Plugin capabilities violate ethical constraints")),
-        }
+        )
     }
     
     /// Validate execution
-    pub fn validate_execution(&self, agent_id: &AgentId, intent: &str) -> Result<()> {
-        // Check intent for prohibited actions
-        let prohibited_actions = ["delete_all", "format", "wipe", "destroy"];
-        let intent_lower = intent.to_lowercase();
+    ///
+    /// `pattern` is the agent's intent entry that accepted the intent (the intent
+    /// itself for exact matches), or None if the agent doesn't accept it. An allowed
+    /// execution counts toward the agent's rate limits.
+    pub fn validate_execution(
+        &self,
+        agent_id: &AgentId,
+        intent: &str,
+        pattern: Option<&str>,
+        params: &serde_json::Value,
+    ) -> Result<(), EthicalViolation> {
+        self.check_execution(agent_id, intent, pattern, params, true)
+    }
+    
+    /// Validate an execution as [`validate_execution`](Self::validate_execution) does,
+    /// without counting it toward rate limits
+    pub fn preview_execution(
+        &self,
+        agent_id: &AgentId,
+        intent: &str,
+        pattern: Option<&str>,
+        params: &serde_json::Value,
+    ) -> Result<(), EthicalViolation> {
+        self.check_execution(agent_id, intent, pattern, params, false)
+    }
+    
+    fn check_execution(
+        &self,
+        agent_id: &AgentId,
+        intent: &str,
+        pattern: Option<&str>,
+        params: &serde_json::Value,
+        consume: bool,
+    ) -> Result<(), EthicalViolation> {
+        let data = serde_json::json!({
+            "agent_id": agent_id,
+            "intent": intent,
+            "pattern": pattern,
+            "params": params,
+        });
         
-        for action in prohibited_actions {
-            if intent_lower.contains(action) {
-                return Err(anyhow!("Intent contains prohibited action: {}", action));
-            }
+        // Check intent for prohibited actions
+        if let Some(action) = self.prohibited_intents.find(intent) {
+            self.audit("execution_validation", &data, &self.denial());
+            return Err(EthicalViolation::new(format!(
+                "Intent contains prohibited action {:?} from prohibited_intents", action,
+            )));
         }
         
         // Evaluate using the ethical tree
-        let eval_result = self.evaluate(
-            "execution_validation",
-            &serde_json::json!({
-                "agent_id": agent_id,
-                "intent": intent,
-            }),
-        );
+        let verdict = self.decide(&self.policy(), "execution_validation", &data, consume);
         
-        match eval_result {
-            Decision::Allow => Ok(()),
-            Decision::Deny => Err(anyhow!("Intent violates ethical constraints")),
-        }
+        EthicalViolation::check(verdict, "Intent violates ethical constraints")
     }
     
     /// Validate agent recovery
-    pub fn validate_recovery(&self, _agent_id: &AgentId) -> Result<()> {
+    pub fn validate_recovery(&self, _agent_id: &AgentId) -> Result<(), EthicalViolation> {
         // Always allow recovery for now
         // In a real implementation, would check recovery policies
         Ok(())
     }
     
-    /// Evaluate a decision using the ethical tree
-    pub fn evaluate(&self, context: &str, data: &serde_json::Value) -> Decision {
-        let nodes = match self.nodes.read() {
-            Ok(guard) => guard,
-            Err(_) => return Decision::Deny, // Default to deny on error
+    /// Evaluate a decision using the ethical tree, keeping the path taken
+    ///
+    /// The decision hook, if any, is called with the verdict.
+    pub fn evaluate(&self, context: &str, data: &serde_json::Value) -> Verdict {
+        self.decide(&self.policy(), context, data, true)
+    }
+    
+    /// Evaluate a risk score using the ethical tree, for callers weighing the action
+    /// against thresholds of their own
+    ///
+    /// The decision hook, if any, is called with the verdict.
+    pub fn assess(&self, context: &str, data: &serde_json::Value) -> RiskAssessment {
+        let policy = self.policy();
+        let verdict = self.decide(&policy, context, data, true);
+        let leaf = verdict.leaf.as_ref().and_then(|id| policy.nodes.get(id));
+        RiskAssessment {
+            score: leaf.and_then(|node| node.weight).unwrap_or_else(|| verdict.decision.default_weight()),
+            factors: verdict.path.iter().filter(|(_, _, held)| *held).map(|(_, rule, _)| rule.clone()).collect(),
+            verdict,
+        }
+    }
+    
+    /// Evaluate and audit a decision
+    ///
+    /// Executions taken from rate-limit buckets on the way are given back unless the
+    /// decision is to allow and `consume` is set.
+    fn decide(&self, policy: &Policy, context: &str, data: &serde_json::Value, consume: bool) -> Verdict {
+        let mut taken = Vec::new();
+        let verdict = self.traverse(policy, context, data, &mut taken);
+        if !consume || verdict.decision == Decision::Deny {
+            self.give_back(policy, taken);
+        }
+        self.audit(context, data, &verdict);
+        verdict
+    }
+    
+    /// Denial made before consulting the tree
+    fn denial(&self) -> Verdict {
+        Verdict { decision: Decision::Deny, path: Vec::new(), policy_version: self.policy_version(), leaf: None }
+    }
+    
+    /// Pass a decision to the decision hook
+    fn audit(&self, context: &str, data: &serde_json::Value, verdict: &Verdict) {
+        let Some(hook) = &self.decision_hook else {
+            return;
+        };
+        hook(&EthicsDecision {
+            agent_id: data.get("agent_id").and_then(|id| id.as_str()).map(str::to_string),
+            context: context.to_string(),
+            decision: verdict.decision,
+            path: verdict.path.clone(),
+            policy_version: verdict.policy_version.clone(),
+            input_hash: blake3::hash(data.to_string().as_bytes()).to_hex().to_string(),
+            timestamp: chrono::Utc::now().timestamp(),
+        });
+    }
+    
+    /// Walk the tree from the root to a decision, noting the rate-limit buckets taken from
+    fn traverse(&self, policy: &Policy, context: &str, data: &serde_json::Value, taken: &mut Vec<BucketKey>) -> Verdict {
+        let mut path = Vec::new();
+        let verdict = |decision, path, leaf: Option<&NodeId>| Verdict {
+            decision,
+            path,
+            policy_version: policy.version.clone(),
+            leaf: leaf.cloned(),
         };
         
         // Start at root
-        let mut current_id = &self.root;
+        let mut current_id = &policy.root;
         
-        // Traverse tree
+        // Traverse tree; a path longer than the tree has nodes is going round a cycle
         loop {
+            if path.len() > policy.nodes.len() {
+                return verdict(Decision::Deny, path, None);
+            }
+            
             // Get current node
-            let node = match nodes.get(current_id) {
+            let node = match policy.nodes.get(current_id) {
                 Some(n) => n,
-                None => return Decision::Deny, // Default to deny on error
+                None => return verdict(Decision::Deny, path, None), // Default to deny on error
             };
             
             // Check if it's a leaf node with decision
             if let Some(decision) = node.decision {
-                return decision;
+                return verdict(decision, path, Some(&node.id));
             }
             
             // Evaluate rule
-            let rule_result = self.evaluate_rule(&node.rule, context, data);
+            let (rule_result, rule) = match &node.rate_limit {
+                Some(limit) => self.take_execution(node, limit, data, taken),
+                None => (self.evaluate_rule(&node.rule, context, data), node.rule.clone()),
+            };
+            path.push((node.id.clone(), rule, rule_result));
             
             // Choose branch
             current_id = if rule_result {
                 // True branch
                 match &node.right {
                     Some(id) => id,
-                    None => return Decision::Deny, // Default to deny on error
+                    None => return verdict(Decision::Deny, path, None), // Default to deny on error
                 }
             } else {
                 // False branch
                 match &node.left {
                     Some(id) => id,
-                    None => return Decision::Allow, // Default to allow on error
+                    None => return verdict(Decision::Allow, path, None), // Default to allow on error
                 }
             };
         }
     }
     
+    /// Take an execution from the agent's bucket at a rate-limit node, if the data is an
+    /// execution the node counts
+    ///
+    /// Returns whether the agent is over the limit, along with the rule as explained in
+    /// the path: with the executions left, or when the next one will be.
+    fn take_execution(&self, node: &EthicalNode, limit: &RateLimit, data: &serde_json::Value, taken: &mut Vec<BucketKey>) -> (bool, String) {
+        let agent_id = data.get("agent_id").and_then(|v| v.as_str());
+        let intent = data.get("intent").and_then(|v| v.as_str());
+        let (Some(agent_id), Some(intent)) = (agent_id, intent) else {
+            return (false, node.rule.clone());
+        };
+        if !limit.intents.is_empty() && match_intent(&limit.intents, intent).is_none() {
+            return (false, node.rule.clone());
+        }
+        
+        let now = Instant::now();
+        let key = (agent_id.to_string(), node.id.clone());
+        let mut buckets = self.lock_buckets();
+        let bucket = buckets.entry(key.clone())
+            .or_insert_with(|| TokenBucket { tokens: limit.limit as f64, updated: now });
+        bucket.refill(limit, now);
+        if bucket.tokens < 1.0 {
+            let next = bucket.next_token(limit).as_millis();
+            return (true, format!("{} (0 of {} left, next in {}ms)", node.rule, limit.limit, next));
+        }
+        
+        bucket.tokens -= 1.0;
+        taken.push(key);
+        (false, format!("{} ({} of {} left)", node.rule, bucket.tokens.floor(), limit.limit))
+    }
+    
+    /// Return executions taken from rate-limit buckets
+    fn give_back(&self, policy: &Policy, taken: Vec<BucketKey>) {
+        let mut buckets = self.lock_buckets();
+        for key in taken {
+            let limit = policy.nodes.get(&key.1).and_then(|node| node.rate_limit.as_ref());
+            if let (Some(bucket), Some(limit)) = (buckets.get_mut(&key), limit) {
+                bucket.tokens = (bucket.tokens + 1.0).min(limit.limit as f64);
+            }
+        }
+    }
+    
+    /// Drop an agent's rate-limit buckets, once it's terminated
+    pub fn forget_agent(&self, agent_id: &AgentId) {
+        self.lock_buckets().retain(|(agent, _), _| agent != agent_id);
+    }
+    
+    fn lock_buckets(&self) -> MutexGuard<'_, HashMap<BucketKey, TokenBucket>> {
+        self.rate_buckets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Evaluate a decision using the ethical tree
+    pub fn evaluate_decision(&self, context: &str, data: &serde_json::Value) -> Decision {
+        self.evaluate(context, data).decision
+    }
+    
     /// Evaluate a rule
     fn evaluate_rule(&self, rule: &str, context: &str, data: &serde_json::Value) -> bool {
         // Simple rule evaluation
@@ -352,14 +1241,43 @@ impl EthicalBinaryTree {
         }
     }
     
-    /// Add a custom ethical rule
-    pub fn add_rule(&self, parent_id: &NodeId, rule_id: &str, rule: &str, decision: Option<Decision>) -> Result<()> {
-        let mut nodes = self.nodes.write()
-            .map_err(|_| anyhow!("Failed to acquire write lock on nodes"))?;
+    /// Add a custom ethical rule on a branch of `parent_id`
+    ///
+    /// A branch that already has a child is only overwritten with `replace`, which
+    /// removes the child's whole subtree.
+    pub fn add_rule(
+        &self,
+        parent_id: &NodeId,
+        branch: Branch,
+        rule_id: &str,
+        rule: &str,
+        decision: Option<Decision>,
+        replace: bool,
+    ) -> Result<()> {
+        // Changes go to a copy, leaving evaluations under way with the current policy
+        let mut policy = self.lock_policy();
+        let mut next = Policy::clone(&policy);
+        let nodes = &mut next.nodes;
         
         // Check if parent exists
-        if !nodes.contains_key(parent_id) {
-            return Err(anyhow!("Parent node not found: {}", parent_id));
+        let parent = nodes.get(parent_id)
+            .ok_or_else(|| anyhow!("Parent node not found: {}", parent_id))?;
+        if parent.decision.is_some() {
+            return Err(anyhow!("Parent node {} is a leaf; its decision would shadow the rule", parent_id));
+        }
+        
+        let replaced = match Self::child(parent, branch) {
+            Some(child) if !replace => {
+                return Err(anyhow!("Node {} already has a {} child: {}", parent_id, branch.name(), child));
+            },
+            Some(child) => Self::subtree(nodes, child),
+            None => Vec::new(),
+        };
+        if nodes.contains_key(rule_id) && !replaced.iter().any(|id| id == rule_id) {
+            return Err(anyhow!("Node already exists: {}", rule_id));
+        }
+        for id in &replaced {
+            nodes.remove(id);
         }
         
         // Create new node
@@ -370,20 +1288,88 @@ impl EthicalBinaryTree {
             left: None,
             right: None,
             decision,
+            rate_limit: None,
+            weight: None,
         };
         
-        // Update parent node
-        let parent = nodes.get_mut(parent_id)
-            .ok_or_else(|| anyhow!("Parent node not found: {}", parent_id))?;
-        
         // Attach new node to parent
-        parent.left = Some(rule_id.to_string());
+        let parent = nodes.get_mut(parent_id).expect("parent was checked to exist");
+        *Self::child_mut(parent, branch) = Some(rule_id.to_string());
         
         // Add new node
         nodes.insert(rule_id.to_string(), node);
         
+        *policy = Arc::new(Policy::new(next.nodes, next.root));
+        Ok(())
+    }
+    
+    /// Remove a rule along with its subtree
+    ///
+    /// A node's missing branch falls back to a default decision, but a node left
+    /// with neither a decision nor any child has no decision path at all, so taking
+    /// its last child fails. The root can't be removed either.
+    pub fn remove_rule(&self, rule_id: &NodeId) -> Result<()> {
+        let mut policy = self.lock_policy();
+        let mut next = Policy::clone(&policy);
+        let nodes = &mut next.nodes;
+        
+        let node = nodes.get(rule_id)
+            .ok_or_else(|| anyhow!("Node not found: {}", rule_id))?;
+        let parent_id = node.parent.clone()
+            .ok_or_else(|| anyhow!("Can't remove the root node: {}", rule_id))?;
+        
+        let parent = nodes.get_mut(&parent_id)
+            .ok_or_else(|| anyhow!("Parent node not found: {}", parent_id))?;
+        let branch = if parent.left.as_ref() == Some(rule_id) { Branch::Left } else { Branch::Right };
+        let sibling = Self::child(parent, if branch == Branch::Left { Branch::Right } else { Branch::Left });
+        if parent.decision.is_none() && sibling.is_none() {
+            return Err(anyhow!("Removing node {} would leave node {} without a decision path", rule_id, parent_id));
+        }
+        *Self::child_mut(parent, branch) = None;
+        
+        for id in Self::subtree(nodes, rule_id) {
+            nodes.remove(&id);
+        }
+        
+        *policy = Arc::new(Policy::new(next.nodes, next.root));
         Ok(())
     }
+    
+    /// View of the tree for inspection
+    pub fn get_tree(&self) -> EthicalTreeView {
+        self.policy().view()
+    }
+    
+    fn child(node: &EthicalNode, branch: Branch) -> Option<&NodeId> {
+        match branch {
+            Branch::Left => node.left.as_ref(),
+            Branch::Right => node.right.as_ref(),
+        }
+    }
+    
+    fn child_mut(node: &mut EthicalNode, branch: Branch) -> &mut Option<NodeId> {
+        match branch {
+            Branch::Left => &mut node.left,
+            Branch::Right => &mut node.right,
+        }
+    }
+    
+    /// IDs of a node and its descendants, depth-first with left branches first
+    fn subtree(nodes: &HashMap<NodeId, EthicalNode>, id: &NodeId) -> Vec<NodeId> {
+        let mut ids = Vec::new();
+        let mut seen = HashSet::new();
+        let mut pending = vec![id.clone()];
+        while let Some(id) = pending.pop() {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            if let Some(node) = nodes.get(&id) {
+                pending.extend(node.right.iter().chain(node.left.iter()).cloned());
+            }
+            ids.push(id);
+        }
+        ids
+    }
 }
 
 impl Default for EthicalBinaryTree {
@@ -398,10 +1384,11 @@ mod tests {
     
     #[test]
     fn test_ethical_tree() {
+        let agent_id = "agent_1".to_string();
         let tree = EthicalBinaryTree::new();
         
         // Test allow decision
-        let allow_result = tree.evaluate(
+        let allow_result = tree.evaluate_decision(
             "test",
             &serde_json::json!({
                 "action": "benign"
@@ -416,9 +1403,10 @@ mod tests {
             intents: vec!["greet".to_string()],
             hm: Default::default(),
             metadata: Default::default(),
+            execution_timeout_ms: None,
         };
         
-        assert!(tree.validate_spawn(&config).is_ok());
+        assert!(tree.validate_spawn(&agent_id, &config).is_ok());
         
         // Test malicious agent rejection
         let malicious_config = AgentConfig {
@@ -427,8 +1415,333 @@ mod tests {
             intents: vec!["harm".to_string()],
             hm: Default::default(),
             metadata: Default::default(),
+            execution_timeout_ms: None,
         };
         
-        assert!(tree.validate_spawn(&malicious_config).is_err());
+        assert!(tree.validate_spawn(&agent_id, &malicious_config).is_err());
+    }
+    
+    #[test]
+    fn test_policy_yaml() {
+        // Deny anything harmful, whatever the consent
+        let tree = EthicalBinaryTree::from_yaml(
+            "nodes:
+              - id: root
+                rule: is_harmful
+                left: allow
+                right: deny
+              - {id: allow, decision: allow}
+              - {id: deny, decision: Deny}",
+        ).unwrap();
+        let intents = |intent: &str| serde_json::json!({"intents": [intent]});
+        assert_eq!(tree.evaluate_decision("agent_spawn", &intents("greet")), Decision::Allow);
+        assert_eq!(tree.evaluate_decision("agent_spawn", &intents("attack")), Decision::Deny);
+        assert_eq!(EthicalBinaryTree::new().evaluate_decision("agent_spawn", &intents("attack")), Decision::Allow);
+        
+        let leaves = "\n  - {id: allow, decision: allow}\n  - {id: deny, decision: deny}";
+        let invalid = [
+            ("nodes: []", "Ethics policy has no nodes"),
+            ("nodes:\n  - {id: root, rule: is_harmful, left: allow, right: denied}", "Ethics policy node root has a right child that doesn't exist: denied"),
+            ("nodes:\n  - {id: root, rule: is_harmful, left: allow}", "Ethics policy node root has neither a decision nor a right child"),
+            ("nodes:\n  - {id: root, left: allow, right: deny, decision: deny}", "Ethics policy node root has both a decision and a left child"),
+            ("nodes:\n  - {id: root, rule: is_harmful, left: allow, right: deny}\n  - {id: other, rule: is_legal, left: allow, right: deny}", "Ethics policy node allow has more than one parent: root and other"),
+            ("nodes:\n  - {id: root, rule: is_harmful, left: allow, right: deny}\n  - {id: other, decision: deny}", "Ethics policy has more than one root node: root, other"),
+            ("nodes:\n  - {id: root, rule: is_harmful, left: allow, right: deny}\n  - {id: a, rule: x, left: b, right: b2}\n  - {id: b, rule: x, left: a, right: b3}\n  - {id: b2, decision: deny}\n  - {id: b3, decision: deny}", "Ethics policy node a is in a cycle, out of reach of root root"),
+            ("nodes:\n  - {id: allow, decision: deny}", "Ethics policy node allow is defined more than once"),
+        ];
+        for (nodes, error) in invalid {
+            let policy = format!("{}{}", nodes, if nodes.contains("[]") { "" } else { leaves });
+            let result = EthicalBinaryTree::from_yaml(&policy);
+            assert_eq!(result.err().map(|e| e.to_string()).as_deref(), Some(error), "{}", policy);
+        }
+        assert!(EthicalBinaryTree::from_yaml("nodes:\n  - {id: root, decision: allow, priority: 2}").is_err());
+    }
+    
+    #[test]
+    fn test_prohibited_terms() {
+        let agent_id = "agent_1".to_string();
+        let names = ProhibitedTerms::default_agent_names();
+        assert_eq!(names.find("LifeHack_coach"), Some("hack"));
+        assert_eq!(names.find("greeter"), None);
+        
+        // Whole words only
+        let words = ProhibitedTerms { matching: TermMatch::Word, ..names.clone() };
+        assert_eq!(words.find("lifehack_coach"), None);
+        assert_eq!(words.find("growth-hack"), Some("hack"));
+        
+        // Exceptions only cover the term where it falls within them
+        let excepted = ProhibitedTerms { allow: vec!["lifehack".to_string()], ..names.clone() };
+        assert_eq!(excepted.find("lifehack_coach"), None);
+        assert_eq!(excepted.find("lifehack_hacker"), Some("hack"));
+        
+        let cased = ProhibitedTerms { case_sensitive: true, ..ProhibitedTerms::new(["Wipe"]) };
+        assert_eq!(cased.find("wipe_disk"), None);
+        assert_eq!(cased.find("Wipe_disk"), Some("Wipe"));
+        
+        // Denials name the term and the list
+        let tree = EthicalBinaryTree::new().with_prohibited_agent_names(words);
+        let config = |name: &str| AgentConfig { name: name.to_string(), ..Default::default() };
+        assert!(tree.validate_spawn(&agent_id, &config("lifehack_coach")).is_ok());
+        assert_eq!(
+            tree.validate_spawn(&agent_id, &config("virus scanner")).unwrap_err().to_string(),
+            "Agent name contains prohibited term \"virus\" from prohibited_agent_names"
+        );
+        assert_eq!(
+            tree.validate_execution(&agent_id, "wipe_cache", None, &serde_json::Value::Null).unwrap_err().to_string(),
+            "Intent contains prohibited action \"wipe\" from prohibited_intents"
+        );
+    }
+    
+    #[test]
+    fn test_decision_hook() {
+        let agent_id = "agent_1".to_string();
+        let decisions = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = decisions.clone();
+        let tree = EthicalBinaryTree::new()
+            .with_decision_hook(Arc::new(move |decision| recorded.lock().unwrap().push(decision.clone())));
+        
+        let config = |name: &str| AgentConfig { name: name.to_string(), ..Default::default() };
+        tree.validate_spawn(&agent_id, &config("greeter")).unwrap();
+        tree.validate_spawn(&agent_id, &config("greeter")).unwrap();
+        tree.validate_spawn(&agent_id, &config("malware")).unwrap_err();
+        tree.evaluate("test", &serde_json::json!({}));
+        
+        let decisions = decisions.lock().unwrap();
+        assert_eq!(decisions.len(), 4);
+        assert_eq!(decisions[0].agent_id.as_deref(), Some("agent_1"));
+        assert_eq!((decisions[0].context.as_str(), decisions[0].decision), ("agent_spawn", Decision::Allow));
+        assert_eq!(decisions[0].path.len(), 1);
+        
+        // The same input hashes the same; prohibited terms deny before the tree
+        assert_eq!(decisions[0].input_hash, decisions[1].input_hash);
+        assert_ne!(decisions[0].input_hash, decisions[2].input_hash);
+        assert_eq!((decisions[2].decision, decisions[2].path.len()), (Decision::Deny, 0));
+        assert_eq!(decisions[3].agent_id, None);
+    }
+    
+    #[test]
+    fn test_policy_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ethics.yaml");
+        let tree = EthicalBinaryTree::new();
+        let version = tree.policy_version();
+        assert_eq!(version.len(), 16);
+        assert_eq!(tree.evaluate("test", &serde_json::json!({})).policy_version, version);
+        
+        // The version follows the tree, not how it was written or loaded
+        let yaml = serde_yaml::to_string(&tree.get_tree()).unwrap();
+        std::fs::write(&path, format!("# tuned\n{}", yaml)).unwrap();
+        assert_eq!(tree.reload(&path).unwrap(), version);
+        tree.remove_rule(&"allow_legal".to_string()).unwrap();
+        assert_ne!(tree.policy_version(), version);
+        
+        // Evaluations hold on to the policy they started with
+        let started = tree.policy();
+        std::fs::write(&path, "nodes:\n  - {id: root, decision: deny}").unwrap();
+        let reloaded = tree.reload(&path).unwrap();
+        assert_eq!(tree.evaluate_decision("test", &serde_json::json!({})), Decision::Deny);
+        assert_eq!(tree.traverse(&started, "test", &serde_json::json!({}), &mut Vec::new()).decision, Decision::Allow);
+        
+        // A policy that fails to load leaves the old one in force
+        std::fs::write(&path, "nodes: [").unwrap();
+        assert!(tree.reload(&path).is_err());
+        assert!(tree.reload(dir.path().join("missing.yaml")).is_err());
+        assert_eq!(tree.policy_version(), reloaded);
+    }
+    
+    #[test]
+    fn test_rate_limits() {
+        let tree = EthicalBinaryTree::from_yaml(r#"
+nodes:
+  - id: external
+    rate_limit: {limit: 2, window_ms: 600, intents: ["external.*"]}
+    left: allow
+    right: deny
+  - id: allow
+    decision: allow
+  - id: deny
+    decision: deny
+"#).unwrap();
+        let (first, second) = ("agent_1".to_string(), "agent_2".to_string());
+        let execute = |agent_id: &AgentId, intent: &str| {
+            tree.validate_execution(agent_id, intent, Some(intent), &serde_json::Value::Null)
+        };
+        
+        // Previews and other intents don't count toward the limit
+        for _ in 0..3 {
+            assert!(tree.preview_execution(&first, "external.fetch", None, &serde_json::Value::Null).is_ok());
+            assert!(execute(&first, "local.read").is_ok());
+        }
+        assert!(execute(&first, "external.fetch").is_ok());
+        let verdict = tree.evaluate("execution_validation", &serde_json::json!({"agent_id": first, "intent": "external.post"}));
+        assert_eq!(verdict.path[0].1, "rate_limited (0 of 2 left)");
+        
+        // Past the limit, the denial says when the agent can try again
+        let violation = execute(&first, "external.fetch").unwrap_err();
+        assert!(violation.reason.contains("external rate_limited (0 of 2 left, next in"), "{}", violation.reason);
+        assert!(execute(&first, "external.fetch").is_err());
+        assert!(execute(&second, "external.fetch").is_ok());
+        
+        // The bucket refills over the window
+        std::thread::sleep(Duration::from_millis(600));
+        assert!(execute(&first, "external.fetch").is_ok());
+        assert!(execute(&first, "external.fetch").is_ok());
+        assert!(execute(&first, "external.fetch").is_err());
+        
+        // A terminated agent's buckets are dropped
+        tree.forget_agent(&first);
+        assert!(execute(&first, "external.fetch").is_ok());
+        assert_eq!(tree.lock_buckets().len(), 2);
+        tree.forget_agent(&second);
+        tree.forget_agent(&first);
+        assert!(tree.lock_buckets().is_empty());
+        
+        let with_rule = "nodes:\n  - {id: root, rule: is_harmful, rate_limit: {limit: 1, window_ms: 10}, left: a, right: b}";
+        let error = EthicalBinaryTree::from_yaml(with_rule).err().unwrap();
+        assert!(format!("{:#}", error).contains("node root has both a rate_limit and a rule"));
+        let zero = "nodes:\n  - {id: root, rate_limit: {limit: 0, window_ms: 10}, left: a, right: b}";
+        let error = EthicalBinaryTree::from_yaml(zero).err().unwrap();
+        assert!(format!("{:#}", error).contains("zero limit or window"));
+    }
+    
+    #[test]
+    fn test_risk_scores() {
+        let tree = EthicalBinaryTree::from_yaml(r#"
+nodes:
+  - {id: root, rule: has_consent, left: deny, right: legal}
+  - {id: deny, decision: deny}
+  - {id: legal, rule: is_legal, left: illegal, right: review}
+  - {id: illegal, decision: deny, weight: 2.5}
+  - {id: review, decision: allow, weight: 0.7}
+"#).unwrap();
+        let data = serde_json::json!({"agent_id": "agent_1", "risk_level": "high"});
+        let assessment = tree.assess("plugin_validation", &data);
+        assert_eq!(assessment.score, 0.7);
+        assert_eq!(assessment.factors, vec!["has_consent", "is_legal"]);
+        assert_eq!(assessment.verdict.decision, Decision::Allow);
+        assert_eq!(assessment.verdict.leaf.as_deref(), Some("review"));
+        assert_eq!(tree.get_tree().nodes.last().unwrap().weight, Some(0.7));
+        
+        // Leaves without a weight score by their decision
+        assert_eq!(EthicalBinaryTree::new().assess("plugin_validation", &data).score, 0.0);
+        let deny_all = EthicalBinaryTree::from_yaml("nodes:\n  - {id: root, decision: deny}").unwrap();
+        assert_eq!(deny_all.assess("plugin_validation", &data).score, 1.0);
+        
+        let branch = "nodes:\n  - {id: root, rule: is_legal, weight: 1, left: a, right: b}";
+        let error = EthicalBinaryTree::from_yaml(branch).err().unwrap();
+        assert!(format!("{:#}", error).contains("node root has a weight but no decision"));
+        let negative = "nodes:\n  - {id: root, decision: allow, weight: -1}";
+        let error = EthicalBinaryTree::from_yaml(negative).err().unwrap();
+        assert!(format!("{:#}", error).contains("isn't a non-negative number: -1"));
+    }
+    
+    #[test]
+    fn test_structural_validation() {
+        let tree = EthicalBinaryTree::new();
+        assert!(tree.validate().is_empty());
+        let imported = EthicalBinaryTree::import_json(&tree.export_json().unwrap()).unwrap();
+        assert_eq!(imported.get_tree(), tree.get_tree());
+        assert_eq!(imported.policy_version(), tree.policy_version());
+        
+        // A rule added without children is reported until they're added
+        tree.add_rule(&"check_legal".to_string(), Branch::Right, "review", "is_harmful", None, true).unwrap();
+        assert_eq!(tree.validate(), vec![
+            StructuralProblem::MissingChild { node: "review".to_string(), branch: Branch::Left },
+            StructuralProblem::MissingChild { node: "review".to_string(), branch: Branch::Right },
+        ]);
+        
+        let malformed = EthicalBinaryTree::import_json(r#"{"nodes": [
+            {"id": "root", "rule": "is_harmful", "left": "a", "right": "ghost"},
+            {"id": "a", "decision": "allow"},
+            {"id": "loop1", "rule": "is_legal", "left": "loop2", "right": "b"},
+            {"id": "loop2", "rule": "is_legal", "left": "loop1"},
+            {"id": "b", "decision": "deny"},
+            {"id": "stray", "decision": "deny"}
+        ]}"#).unwrap();
+        let problems = malformed.validate();
+        assert_eq!(problems, vec![
+            StructuralProblem::Cycle { node: "loop1".to_string() },
+            StructuralProblem::Orphan { node: "b".to_string() },
+            StructuralProblem::Orphan { node: "stray".to_string() },
+            StructuralProblem::MissingChild { node: "loop2".to_string(), branch: Branch::Right },
+            StructuralProblem::DanglingChild { node: "root".to_string(), branch: Branch::Right, child: "ghost".to_string() },
+        ]);
+        assert_eq!(problems[4].to_string(), "node root has a right child that doesn't exist: ghost");
+        assert_eq!(serde_json::to_value(&problems[3]).unwrap(), serde_json::json!({
+            "problem": "missing_child", "node": "loop2", "branch": "right",
+        }));
+        
+        // Exports keep the nodes the root doesn't lead to
+        let exported = malformed.export_json().unwrap();
+        assert_eq!(EthicalBinaryTree::import_json(&exported).unwrap().validate(), problems);
+        
+        // An evaluation caught in a cycle ends in a denial
+        let cycle = EthicalBinaryTree::import_json(r#"{"nodes": [
+            {"id": "root", "rule": "is_harmful", "left": "again", "right": "root"},
+            {"id": "again", "rule": "is_harmful", "left": "root", "right": "root"}
+        ]}"#).unwrap();
+        assert_eq!(cycle.validate(), vec![StructuralProblem::Cycle { node: "again".to_string() }]);
+        assert_eq!(cycle.evaluate_decision("test", &serde_json::json!({})), Decision::Deny);
+        
+        assert!(EthicalBinaryTree::import_json(r#"{"nodes": []}"#).is_err());
+        assert!(EthicalBinaryTree::import_json("nodes: []").is_err());
+    }
+    
+    #[test]
+    fn test_verdict_path() {
+        let agent_id = "agent_1".to_string();
+        let tree = EthicalBinaryTree::new();
+        let verdict = tree.evaluate("agent_spawn", &serde_json::json!({"intents": ["attack"]}));
+        assert_eq!(verdict.decision, Decision::Allow);
+        assert_eq!(verdict.describe_path(), "root is_harmful: true -> harmful has_consent: true -> check_legal is_legal: true");
+        
+        // Denials name the rules that led to them
+        tree.add_rule(&"root".to_string(), Branch::Right, "refuse", "deny", Some(Decision::Deny), true).unwrap();
+        let config = AgentConfig { name: "fighter".to_string(), intents: vec!["attack".to_string()], ..Default::default() };
+        let violation = tree.validate_spawn(&agent_id, &config).unwrap_err();
+        assert_eq!(violation.path, [("root".to_string(), "is_harmful".to_string(), true)]);
+        assert_eq!(violation.to_string(), "Ethical constraints prohibit this agent configuration (path: root is_harmful: true)");
+        
+        // Checks made before the tree have no path
+        let config = AgentConfig { name: "malware".to_string(), ..Default::default() };
+        assert!(tree.validate_spawn(&agent_id, &config).unwrap_err().path.is_empty());
+    }
+    
+    #[test]
+    fn test_add_and_remove_rules() {
+        let tree = EthicalBinaryTree::new();
+        let root = "root".to_string();
+        let intents = |intent: &str| serde_json::json!({"intents": [intent]});
+        
+        // Adding a rule used to overwrite the parent's left child, here the allow leaf
+        let error = tree.add_rule(&root, Branch::Left, "review", "is_legal", None, false).unwrap_err();
+        assert_eq!(error.to_string(), "Node root already has a left child: allow");
+        assert_eq!(tree.evaluate_decision("agent_spawn", &intents("greet")), Decision::Allow);
+        
+        // Rules attach to the true branch too, replacing the subtree there
+        assert_eq!(tree.evaluate_decision("agent_spawn", &intents("attack")), Decision::Allow);
+        tree.add_rule(&root, Branch::Right, "refuse", "deny", Some(Decision::Deny), true).unwrap();
+        assert_eq!(tree.evaluate_decision("agent_spawn", &intents("attack")), Decision::Deny);
+        let ids: Vec<_> = tree.get_tree().nodes.into_iter().map(|node| node.id).collect();
+        assert_eq!(ids, ["root", "allow", "refuse"]);
+        
+        assert_eq!(
+            tree.add_rule(&root, Branch::Right, "allow", "deny", Some(Decision::Deny), true).unwrap_err().to_string(),
+            "Node already exists: allow"
+        );
+        assert!(tree.add_rule(&"allow".to_string(), Branch::Left, "review", "is_legal", None, false).is_err());
+        
+        // A node may lose one branch, but not both
+        tree.remove_rule(&"refuse".to_string()).unwrap();
+        assert_eq!(tree.get_tree().nodes.len(), 2);
+        assert_eq!(
+            tree.remove_rule(&"allow".to_string()).unwrap_err().to_string(),
+            "Removing node allow would leave node root without a decision path"
+        );
+        assert!(tree.remove_rule(&root).is_err());
+        
+        // The view reads back as a policy
+        let yaml = serde_yaml::to_string(&EthicalBinaryTree::new().get_tree()).unwrap();
+        assert_eq!(EthicalBinaryTree::from_yaml(&yaml).unwrap().get_tree(), EthicalBinaryTree::new().get_tree());
     }
 }
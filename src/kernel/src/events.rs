@@ -0,0 +1,212 @@
+//! Kernel event notifications for MCP-ZERO
+//!
+//! External systems subscribe to a stream of [`KernelEvent`]s instead of polling.
+//! Publishing never blocks: an event is dropped for a subscriber whose channel is full.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Mutex;
+use serde::{Serialize, Deserialize};
+
+use crate::agent::AgentId;
+use crate::plugin::PluginId;
+use crate::storage::StorageError;
+use crate::trace::TraceId;
+
+/// Notable kernel activity, delivered to subscribers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KernelEvent {
+    /// An agent was spawned (or cloned)
+    AgentSpawned {
+        agent_id: AgentId,
+        timestamp: i64,
+    },
+    /// A plugin was attached to an agent
+    PluginAttached {
+        agent_id: AgentId,
+        plugin_id: PluginId,
+        timestamp: i64,
+    },
+    /// An execution passed validation and began its trace
+    ExecutionStarted {
+        agent_id: AgentId,
+        intent: String,
+        trace_id: TraceId,
+        timestamp: i64,
+    },
+    /// An execution succeeded
+    ExecutionCompleted {
+        agent_id: AgentId,
+        intent: String,
+        trace_id: TraceId,
+        timestamp: i64,
+    },
+    /// An execution failed; rejected executions have no trace
+    ExecutionFailed {
+        agent_id: AgentId,
+        intent: String,
+        trace_id: Option<TraceId>,
+        code: String,
+        error: String,
+        timestamp: i64,
+    },
+    /// An agent snapshot was written to storage
+    SnapshotTaken {
+        agent_id: AgentId,
+        timestamp: i64,
+    },
+    /// An agent was recovered from storage
+    AgentRecovered {
+        agent_id: AgentId,
+        timestamp: i64,
+    },
+    /// An agent was quarantined after `violations` ethical denials within the window
+    AgentQuarantined {
+        agent_id: AgentId,
+        violations: usize,
+        timestamp: i64,
+    },
+    /// An operator released an agent from quarantine
+    AgentReleased {
+        agent_id: AgentId,
+        timestamp: i64,
+    },
+    /// A snapshot was refused for exceeding the agent's storage quota
+    StorageQuotaExceeded {
+        agent_id: AgentId,
+        required_bytes: u64,
+        quota_bytes: u64,
+        timestamp: i64,
+    },
+}
+
+/// Fan-out of kernel events (or other records, such as trace entries) to bounded
+/// subscriber channels
+#[derive(Debug)]
+pub(crate) struct EventBus<E = KernelEvent> {
+    subscribers: Mutex<Vec<SyncSender<E>>>,
+    
+    /// Capacity of each subscriber's channel
+    capacity: usize,
+    
+    /// Events not delivered because a subscriber's channel was full
+    dropped: AtomicU64,
+}
+
+impl<E: Clone> EventBus<E> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            subscribers: Mutex::new(Vec::new()),
+            capacity,
+            dropped: AtomicU64::new(0),
+        }
+    }
+    
+    /// Add a subscriber receiving every event published from now on
+    pub(crate) fn subscribe(&self) -> Receiver<E> {
+        self.subscribe_with_backlog(Vec::new())
+    }
+    
+    /// Add a subscriber whose channel starts with `backlog`, ahead of every event
+    /// published from now on
+    ///
+    /// The channel has room for the whole backlog on top of its usual capacity.
+    pub(crate) fn subscribe_with_backlog(&self, backlog: Vec<E>) -> Receiver<E> {
+        let (sender, receiver) = mpsc::sync_channel(self.capacity + backlog.len());
+        for event in backlog {
+            // Can't fail: the channel has room and the receiver is still held
+            let _ = sender.try_send(event);
+        }
+        self.lock_subscribers().push(sender);
+        receiver
+    }
+    
+    /// Deliver an event to every subscriber without blocking
+    ///
+    /// The event is built only when someone is subscribed. Subscribers that hung up
+    /// are removed.
+    pub(crate) fn publish(&self, event: impl FnOnce() -> E) {
+        let mut subscribers = self.lock_subscribers();
+        if subscribers.is_empty() {
+            return;
+        }
+        
+        let event = event();
+        subscribers.retain(|subscriber| match subscriber.try_send(event.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                true
+            },
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+    
+    /// Number of events dropped for full subscriber channels
+    pub(crate) fn dropped_events(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+    
+    fn lock_subscribers(&self) -> std::sync::MutexGuard<'_, Vec<SyncSender<E>>> {
+        self.subscribers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl EventBus {
+    /// Publish `StorageQuotaExceeded` if a snapshot failed for exceeding the agent's quota
+    pub(crate) fn alert_storage_error(&self, agent_id: &AgentId, error: &anyhow::Error) {
+        if let Some(&StorageError::QuotaExceeded { required, quota }) = StorageError::find(error) {
+            tracing::warn!("Snapshot of agent {} refused: {:#}", agent_id, error);
+            self.publish(|| KernelEvent::StorageQuotaExceeded {
+                agent_id: agent_id.clone(),
+                required_bytes: required,
+                quota_bytes: quota,
+                timestamp: chrono::Utc::now().timestamp(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn spawned(agent_id: &str) -> KernelEvent {
+        KernelEvent::AgentSpawned { agent_id: agent_id.to_string(), timestamp: 0 }
+    }
+    
+    #[test]
+    fn test_event_bus_drops_when_full() {
+        let bus: EventBus = EventBus::new(1);
+        let slow = bus.subscribe();
+        let gone = bus.subscribe();
+        drop(gone);
+        
+        bus.publish(|| spawned("a"));
+        bus.publish(|| spawned("b"));
+        
+        // The hung-up subscriber was removed; the full one missed the second event
+        assert_eq!(bus.lock_subscribers().len(), 1);
+        assert_eq!(bus.dropped_events(), 1);
+        assert_eq!(slow.try_recv().unwrap(), spawned("a"));
+        assert!(slow.try_recv().is_err());
+        
+        assert_eq!(
+            serde_json::to_value(spawned("a")).unwrap(),
+            serde_json::json!({"type": "agent_spawned", "agent_id": "a", "timestamp": 0})
+        );
+    }
+    
+    #[test]
+    fn test_event_bus_backlog() {
+        let bus: EventBus = EventBus::new(1);
+        let late = bus.subscribe_with_backlog(vec![spawned("a"), spawned("b")]);
+        bus.publish(|| spawned("c"));
+        bus.publish(|| spawned("d"));
+        
+        // The backlog doesn't eat into the channel's capacity for live events
+        assert_eq!(late.try_iter().collect::<Vec<_>>(), vec![spawned("a"), spawned("b"), spawned("c")]);
+        assert_eq!(bus.dropped_events(), 1);
+    }
+}
@@ -0,0 +1,178 @@
+//! Trace export for MCP-ZERO kernel
+//!
+//! Auditors pull a trace, or a time range of traces, out of the kernel as a file.
+//! Entries are written one at a time as they are read, so an export never holds more
+//! than one entry's output in memory.
+
+use std::io::Write;
+use std::str::FromStr;
+use anyhow::{Result, Context, anyhow};
+use serde::{Serialize, Deserialize};
+
+use crate::trace::{TraceEntry, TraceFilter};
+
+/// File format of exported trace entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceExportFormat {
+    /// One JSON-serialized entry per line, as stored
+    #[default]
+    Jsonl,
+    /// A header row, then one row per entry with its data as a JSON string
+    Csv,
+}
+
+impl FromStr for TraceExportFormat {
+    type Err = anyhow::Error;
+    
+    fn from_str(format: &str) -> Result<Self> {
+        match format.to_lowercase().as_str() {
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            _ => Err(anyhow!("Unknown trace export format: {} (expected jsonl or csv)", format)),
+        }
+    }
+}
+
+/// Columns of a CSV export
+const CSV_HEADER: [&str; 7] = ["trace_id", "agent_id", "event_type", "timestamp", "hash", "prev_hash", "data"];
+
+/// Quote a CSV field if it holds a delimiter, quote or line break, doubling its quotes
+fn csv_field(field: &str) -> std::borrow::Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+/// Writes the entries a filter selects, in the order they are given
+pub(crate) struct TraceExporter<'a, W: Write> {
+    writer: W,
+    format: TraceExportFormat,
+    filter: &'a TraceFilter,
+    
+    /// Matching entries seen so far, including those skipped by the filter's offset
+    matched: usize,
+    
+    /// Entries written so far
+    written: usize,
+}
+
+impl<'a, W: Write> TraceExporter<'a, W> {
+    /// Start an export, writing the CSV header right away
+    pub(crate) fn new(mut writer: W, format: TraceExportFormat, filter: &'a TraceFilter) -> Result<Self> {
+        if format == TraceExportFormat::Csv {
+            writeln!(writer, "{}", CSV_HEADER.join(",")).context("Failed to write trace export")?;
+        }
+        Ok(Self { writer, format, filter, matched: 0, written: 0 })
+    }
+    
+    /// Write the entry if the filter selects it
+    pub(crate) fn write(&mut self, entry: &TraceEntry) -> Result<()> {
+        if self.is_complete() || !self.filter.matches(entry) {
+            return Ok(());
+        }
+        self.matched += 1;
+        if self.matched <= self.filter.offset {
+            return Ok(());
+        }
+        
+        match self.format {
+            TraceExportFormat::Jsonl => {
+                serde_json::to_writer(&mut self.writer, entry).context("Failed to serialize trace entry")?;
+                writeln!(self.writer)
+            },
+            TraceExportFormat::Csv => {
+                let timestamp = entry.timestamp.to_string();
+                let data = entry.data.to_string();
+                let row = [
+                    &entry.id,
+                    &entry.agent_id,
+                    &entry.event_type,
+                    &timestamp,
+                    &entry.hash,
+                    entry.prev_hash.as_deref().unwrap_or_default(),
+                    &data,
+                ];
+                let row: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+                writeln!(self.writer, "{}", row.join(","))
+            },
+        }.context("Failed to write trace export")?;
+        
+        self.written += 1;
+        Ok(())
+    }
+    
+    /// Whether the filter's limit has been reached, so no more entries will be written
+    pub(crate) fn is_complete(&self) -> bool {
+        self.filter.limit.is_some_and(|limit| self.written >= limit)
+    }
+    
+    /// Flush the writer, returning how many entries were written
+    pub(crate) fn finish(mut self) -> Result<usize> {
+        self.writer.flush().context("Failed to write trace export")?;
+        Ok(self.written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn entry(event_type: &str, data: serde_json::Value, timestamp: i64) -> TraceEntry {
+        TraceEntry {
+            id: "trace_1".to_string(),
+            agent_id: "agent_1".to_string(),
+            event_type: event_type.to_string(),
+            data,
+            timestamp,
+            prev_hash: None,
+            hash: "abc".to_string(),
+            hasher: crate::hasher::SHA3_HASHER.to_string(),
+        }
+    }
+    
+    fn export(entries: &[TraceEntry], format: TraceExportFormat, filter: &TraceFilter) -> String {
+        let mut output = Vec::new();
+        let mut exporter = TraceExporter::new(&mut output, format, filter).unwrap();
+        for entry in entries {
+            exporter.write(entry).unwrap();
+        }
+        exporter.finish().unwrap();
+        String::from_utf8(output).unwrap()
+    }
+    
+    #[test]
+    fn test_csv_escaping() {
+        let data = serde_json::json!({"message": "said \"hi\", then\nleft"});
+        let csv = export(&[entry("log", data, 7)], TraceExportFormat::Csv, &TraceFilter::default());
+        
+        // Quotes are doubled, and the field quoted so its comma and newline stay in it
+        assert_eq!(
+            csv,
+            "trace_id,agent_id,event_type,timestamp,hash,prev_hash,data\n\
+             trace_1,agent_1,log,7,abc,,\"{\"\"message\"\":\"\"said \\\"\"hi\\\"\", then\\nleft\"\"}\"\n",
+        );
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("line\r\nbreak"), "\"line\r\nbreak\"");
+    }
+    
+    #[test]
+    fn test_export_filter() {
+        let entries: Vec<TraceEntry> = (0..5)
+            .map(|step| entry("step", serde_json::json!({"step": step}), step))
+            .collect();
+        let filter = TraceFilter { since: Some(1), offset: 1, limit: Some(2), ..Default::default() };
+        
+        let jsonl = export(&entries, TraceExportFormat::Jsonl, &filter);
+        let steps: Vec<i64> = jsonl.lines()
+            .map(|line| serde_json::from_str::<TraceEntry>(line).unwrap().timestamp)
+            .collect();
+        assert_eq!(steps, [2, 3]);
+        
+        assert_eq!("CSV".parse::<TraceExportFormat>().unwrap(), TraceExportFormat::Csv);
+        assert!("xml".parse::<TraceExportFormat>().is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Trace entry hashing for MCP-ZERO kernel
+//!
+//! Trace hash chains are built with a [`TraceHasher`]. SHA3-256 is the default; a
+//! Poseidon hasher over the BN254 scalar field, whose hashes are cheap to check inside
+//! a ZK circuit, is available with the `poseidon` cargo feature. Every entry records
+//! the name of the hasher that produced it, so a store holding entries of several
+//! hashers still verifies.
+
+use std::sync::Arc;
+use anyhow::Result;
+use sha3::{Digest, Sha3_256};
+
+use crate::config::TraceHasherKind;
+
+/// Name recorded in entries hashed with [`Sha3Hasher`], and assumed for entries
+/// written before hashers were recorded
+pub const SHA3_HASHER: &str = "sha3-256";
+
+/// Name recorded in entries hashed with the Poseidon hasher
+pub const POSEIDON_HASHER: &str = "poseidon-bn254";
+
+/// Hash function for trace entries
+pub trait TraceHasher: Send + Sync {
+    /// Name recorded in the entries this hasher produces
+    fn name(&self) -> &'static str;
+    
+    /// Hash an entry's fields, chained onto the previous entry's hash; returns lowercase hex
+    fn hash(&self, data: &str, prev_hash: Option<&str>) -> String;
+}
+
+/// SHA3-256 over `prev_hash:data`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha3Hasher;
+
+impl TraceHasher for Sha3Hasher {
+    fn name(&self) -> &'static str {
+        SHA3_HASHER
+    }
+    
+    fn hash(&self, data: &str, prev_hash: Option<&str>) -> String {
+        let mut hasher = Sha3_256::new();
+        
+        // Include previous hash if available
+        if let Some(prev) = prev_hash {
+            hasher.update(prev.as_bytes());
+            hasher.update(b":");
+        }
+        
+        hasher.update(data.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[cfg(feature = "poseidon")]
+pub use poseidon::PoseidonHasher;
+
+#[cfg(feature = "poseidon")]
+mod poseidon {
+    use std::sync::Mutex;
+    use ark_bn254::Fr;
+    use light_poseidon::{Poseidon, PoseidonBytesHasher};
+    
+    use super::{TraceHasher, POSEIDON_HASHER};
+    
+    /// Bytes per field element absorbed; 31 bytes always fit below the BN254 modulus
+    const CHUNK_BYTES: usize = 31;
+    
+    /// Circom-compatible Poseidon over the BN254 scalar field
+    ///
+    /// The input, `prev_hash:data` as with SHA3, is split into 31-byte chunks that are
+    /// absorbed one at a time: the state starts as the input length and each chunk
+    /// replaces it with `poseidon(state, chunk)`. The hash is the final state as
+    /// 32 big-endian bytes.
+    pub struct PoseidonHasher {
+        poseidon: Mutex<Poseidon<Fr>>,
+    }
+    
+    impl PoseidonHasher {
+        pub fn new() -> Self {
+            Self {
+                poseidon: Mutex::new(Poseidon::<Fr>::new_circom(2).expect("circom parameters exist for two inputs")),
+            }
+        }
+    }
+    
+    impl Default for PoseidonHasher {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+    
+    impl TraceHasher for PoseidonHasher {
+        fn name(&self) -> &'static str {
+            POSEIDON_HASHER
+        }
+        
+        fn hash(&self, data: &str, prev_hash: Option<&str>) -> String {
+            let input = match prev_hash {
+                Some(prev) => format!("{}:{}", prev, data),
+                None => data.to_string(),
+            };
+            
+            let mut state = [0u8; 32];
+            state[24..].copy_from_slice(&(input.len() as u64).to_be_bytes());
+            let mut poseidon = self.poseidon.lock().unwrap_or_else(|e| e.into_inner());
+            for chunk in input.as_bytes().chunks(CHUNK_BYTES) {
+                let mut element = [0u8; 32];
+                element[32 - chunk.len()..].copy_from_slice(chunk);
+                state = poseidon.hash_bytes_be(&[state.as_slice(), element.as_slice()])
+                    .expect("absorbed values are below the field modulus");
+            }
+            state.iter().map(|byte| format!("{:02x}", byte)).collect()
+        }
+    }
+}
+
+static SHA3: Sha3Hasher = Sha3Hasher;
+
+/// The built-in hasher recording `name` in its entries, if this kernel has it
+pub(crate) fn named(name: &str) -> Option<&'static dyn TraceHasher> {
+    match name {
+        SHA3_HASHER => Some(&SHA3),
+        #[cfg(feature = "poseidon")]
+        POSEIDON_HASHER => {
+            static POSEIDON: std::sync::OnceLock<PoseidonHasher> = std::sync::OnceLock::new();
+            Some(POSEIDON.get_or_init(PoseidonHasher::new))
+        },
+        _ => None,
+    }
+}
+
+/// The hasher selected by `KernelConfig::trace_hasher`
+pub(crate) fn for_kind(kind: TraceHasherKind) -> Result<Arc<dyn TraceHasher>> {
+    match kind {
+        TraceHasherKind::Sha3 => Ok(Arc::new(Sha3Hasher)),
+        #[cfg(feature = "poseidon")]
+        TraceHasherKind::Poseidon => Ok(Arc::new(PoseidonHasher::new())),
+        #[cfg(not(feature = "poseidon"))]
+        TraceHasherKind::Poseidon => Err(anyhow::anyhow!("The poseidon trace hasher requires the `poseidon` feature")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "poseidon")]
+    use light_poseidon::{Poseidon, PoseidonBytesHasher};
+    
+    #[test]
+    fn test_sha3_vectors() {
+        // Computed independently with Python's hashlib.sha3_256
+        assert_eq!(
+            Sha3Hasher.hash("agent_1:greet:1700000000", None),
+            "5ad6fcbf6227f3d6667a8f39882b8373efd8794f2cf8aab8c8688022112a4312",
+        );
+        assert_eq!(
+            Sha3Hasher.hash("step:{\"n\":1}:1700000001", Some("0123")),
+            "dbb1e55bd93b1727cd0affbb94107a1c5ca6f933d13b6ca22c9e133af7797876",
+        );
+        assert_eq!(named(SHA3_HASHER).map(TraceHasher::name), Some(SHA3_HASHER));
+        assert!(named("md5").is_none());
+    }
+    
+    #[cfg(feature = "poseidon")]
+    #[test]
+    fn test_poseidon_vectors() {
+        // circomlibjs: poseidon([1, 2])
+        let (mut one, mut two) = ([0u8; 32], [0u8; 32]);
+        one[31] = 1;
+        two[31] = 2;
+        let hash = Poseidon::<ark_bn254::Fr>::new_circom(2).unwrap().hash_bytes_be(&[one.as_slice(), two.as_slice()]).unwrap();
+        assert_eq!(
+            hash.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+            "115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a",
+        );
+        
+        let hasher = PoseidonHasher::new();
+        let hash = hasher.hash("agent_1:greet:1700000000", None);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hasher.hash("agent_1:greet:1700000000", None), hash);
+        assert_ne!(hasher.hash("agent_1:greet:1700000000", Some(&hash)), hash);
+        assert_ne!(hash, Sha3Hasher.hash("agent_1:greet:1700000000", None));
+    }
+    
+    #[cfg(not(feature = "poseidon"))]
+    #[test]
+    fn test_poseidon_requires_feature() {
+        assert!(for_kind(TraceHasherKind::Poseidon).is_err());
+        assert!(named(POSEIDON_HASHER).is_none());
+    }
+}
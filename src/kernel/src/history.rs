@@ -0,0 +1,153 @@
+//! Per-agent execution history for MCP-ZERO
+//!
+//! Each agent keeps its most recent executions in a fixed-capacity ring buffer,
+//! filled when an execution's trace ends. The history is only written to snapshots
+//! when the kernel is configured to persist it.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::trace::TraceId;
+
+/// Maximum length in characters of a result summary
+const SUMMARY_MAX_CHARS: usize = 200;
+
+/// A finished execution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    /// Intent executed
+    pub intent: String,
+    
+    /// Trace of the execution
+    pub trace_id: TraceId,
+    
+    /// Start time in milliseconds since the Unix epoch
+    pub started_at_ms: i64,
+    
+    /// End time in milliseconds since the Unix epoch
+    pub ended_at_ms: i64,
+    
+    /// Whether the execution succeeded
+    pub success: bool,
+    
+    /// Result as JSON, truncated, for successful executions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    
+    /// Error message for failed executions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ExecutionRecord {
+    /// Summarize a result as JSON of at most `SUMMARY_MAX_CHARS` characters
+    pub(crate) fn summarize(value: &serde_json::Value) -> String {
+        let json = value.to_string();
+        match json.char_indices().nth(SUMMARY_MAX_CHARS) {
+            Some((end, _)) => format!("{}…", &json[..end]),
+            None => json,
+        }
+    }
+}
+
+/// Ring buffer of an agent's most recent executions
+///
+/// Shared through the agent store's read guards, so it locks internally.
+#[derive(Debug, Default)]
+pub(crate) struct ExecutionHistory {
+    /// Records, oldest first
+    records: Mutex<VecDeque<ExecutionRecord>>,
+    
+    /// Whether the records are written with the agent snapshot
+    persist: AtomicBool,
+}
+
+impl ExecutionHistory {
+    /// Add a record, evicting the oldest ones beyond `capacity`
+    pub(crate) fn record(&self, record: ExecutionRecord, capacity: usize, persist: bool) {
+        self.persist.store(persist, Ordering::Relaxed);
+        
+        let mut records = self.lock_records();
+        records.push_back(record);
+        while records.len() > capacity {
+            records.pop_front();
+        }
+    }
+    
+    /// Up to `limit` of the most recent records, newest first
+    pub(crate) fn recent(&self, limit: usize) -> Vec<ExecutionRecord> {
+        self.lock_records().iter().rev().take(limit).cloned().collect()
+    }
+    
+    /// Number of records held
+    pub(crate) fn len(&self) -> usize {
+        self.lock_records().len()
+    }
+    
+    /// Whether the history is left out of snapshots
+    pub(crate) fn skip_serializing(&self) -> bool {
+        !self.persist.load(Ordering::Relaxed) || self.len() == 0
+    }
+    
+    fn lock_records(&self) -> std::sync::MutexGuard<'_, VecDeque<ExecutionRecord>> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Serialize for ExecutionHistory {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.lock_records().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExecutionHistory {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let records = VecDeque::deserialize(deserializer)?;
+        Ok(Self {
+            records: Mutex::new(records),
+            persist: AtomicBool::new(true),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn record(intent: &str) -> ExecutionRecord {
+        ExecutionRecord {
+            intent: intent.to_string(),
+            trace_id: format!("trace_{}", intent),
+            started_at_ms: 0,
+            ended_at_ms: 1,
+            success: true,
+            summary: None,
+            error: None,
+        }
+    }
+    
+    #[test]
+    fn test_history_ring_buffer() {
+        let history = ExecutionHistory::default();
+        for intent in ["a", "b", "c"] {
+            history.record(record(intent), 2, false);
+        }
+        
+        // The oldest record was evicted; newest come first
+        let intents: Vec<String> = history.recent(10).into_iter().map(|record| record.intent).collect();
+        assert_eq!(intents, vec!["c", "b"]);
+        assert_eq!(history.recent(1)[0].intent, "c");
+        
+        // Only persisted histories are serialized
+        assert!(history.skip_serializing());
+        history.record(record("d"), 2, true);
+        assert!(!history.skip_serializing());
+        let restored: ExecutionHistory = serde_json::from_value(serde_json::to_value(&history).unwrap()).unwrap();
+        assert_eq!(restored.recent(10), history.recent(10));
+        
+        let summary = ExecutionRecord::summarize(&serde_json::json!("é".repeat(300)));
+        assert_eq!(summary.chars().count(), SUMMARY_MAX_CHARS + 1);
+    }
+}
@@ -0,0 +1,95 @@
+//! Intent matching for MCP-ZERO agents
+//!
+//! Agent configs list the intents they accept, either exactly (`files.read`) or as
+//! glob patterns declaring a namespace: `*` matches any run of characters, including
+//! none, and `?` matches exactly one character.
+
+/// Find the entry of `intents` accepting `intent`
+///
+/// An exact entry takes precedence; otherwise the first matching pattern is returned.
+pub fn match_intent<'a>(intents: &'a [String], intent: &str) -> Option<&'a str> {
+    intents.iter()
+        .find(|allowed| allowed.as_str() == intent)
+        .or_else(|| intents.iter().find(|allowed| glob_match(allowed, intent)))
+        .map(String::as_str)
+}
+
+/// Match a string against a glob pattern of `*` and `?` wildcards
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+    
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            },
+            // Mismatch: let the last `*` absorb one more character
+            _ => match backtrack {
+                Some((star, star_t)) => {
+                    backtrack = Some((star, star_t + 1));
+                    p = star + 1;
+                    t = star_t + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+    
+    // Trailing stars match the empty remainder
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_glob_match() {
+        let cases = [
+            ("files.*", "files.read", true),
+            ("files.*", "files.", true),
+            ("files.*", "files", false),
+            ("files.*", "reports.read", false),
+            ("report:?", "report:1", true),
+            ("report:?", "report:", false),
+            ("report:?", "report:12", false),
+            ("*", "", true),
+            ("", "", true),
+            ("", "a", false),
+            ("a*b*c", "abbbc", true),
+            ("a*b*c", "acb", false),
+            ("*.read", "files.archive.read", true),
+            ("**x", "abx", true),
+            ("?é*", "aé", true),
+            ("greet", "greet", true),
+            ("greet", "greeting", false),
+        ];
+        for (pattern, text, expected) in cases {
+            assert_eq!(glob_match(pattern, text), expected, "{:?} vs {:?}", pattern, text);
+        }
+    }
+    
+    #[test]
+    fn test_match_intent() {
+        let intents: Vec<String> = ["files.*", "files.delete", "report:?"].iter().map(|s| s.to_string()).collect();
+        
+        // Exact entries win over an earlier matching pattern
+        assert_eq!(match_intent(&intents, "files.delete"), Some("files.delete"));
+        assert_eq!(match_intent(&intents, "files.read"), Some("files.*"));
+        assert_eq!(match_intent(&intents, "report:7"), Some("report:?"));
+        assert_eq!(match_intent(&intents, "greet"), None);
+        
+        // An empty list denies everything
+        assert_eq!(match_intent(&[], "files.read"), None);
+    }
+}
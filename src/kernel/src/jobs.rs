@@ -0,0 +1,244 @@
+//! Asynchronous execution jobs for MCP-ZERO kernel
+//!
+//! Intents submitted with `MCPKernel::execute_async` are queued on a small, bounded
+//! worker pool. Callers poll the job's status; finished results are kept for a TTL.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::KernelError;
+
+/// Job ID type
+pub type JobId = String;
+
+/// Status of an asynchronous job
+#[derive(Debug, Clone, Serialize)]
+pub enum JobStatus {
+    /// Waiting for a worker
+    Pending,
+    /// Executing
+    Running,
+    /// Finished with a result
+    Completed(Value),
+    /// Finished with an error
+    Failed(KernelError),
+    /// Cancelled before it started
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Whether the job has finished (its status will no longer change)
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::Pending | Self::Running)
+    }
+}
+
+/// Work run by a job
+type Task = Box<dyn FnOnce() -> Result<Value, KernelError> + Send>;
+
+#[derive(Debug)]
+struct JobRecord {
+    status: JobStatus,
+    
+    /// When the job finished, for result expiry
+    finished_at: Option<Instant>,
+}
+
+/// Job statuses shared between the queue and its workers
+type JobTable = Arc<Mutex<HashMap<JobId, JobRecord>>>;
+
+/// Bounded queue of jobs run by a fixed number of worker threads
+pub(crate) struct JobQueue {
+    jobs: JobTable,
+    
+    /// Queue feeding the workers, created with them on first use
+    sender: OnceLock<SyncSender<(JobId, Task)>>,
+    
+    /// Number of worker threads
+    workers: usize,
+    
+    /// Maximum number of queued jobs
+    capacity: usize,
+    
+    /// How long finished jobs are kept
+    ttl: Duration,
+    
+    next_id: AtomicU64,
+}
+
+impl JobQueue {
+    pub(crate) fn new(workers: usize, capacity: usize, ttl: Duration) -> Self {
+        Self {
+            jobs: Arc::default(),
+            sender: OnceLock::new(),
+            workers: workers.max(1),
+            capacity,
+            ttl,
+            next_id: AtomicU64::new(1),
+        }
+    }
+    
+    /// Queue a task, failing if the queue is full
+    pub(crate) fn submit(&self, task: Task) -> Result<JobId, KernelError> {
+        self.expire();
+        
+        let job_id = format!("job_{}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.lock_jobs().insert(job_id.clone(), JobRecord { status: JobStatus::Pending, finished_at: None });
+        
+        let sender = self.sender.get_or_init(|| self.start_workers());
+        match sender.try_send((job_id.clone(), task)) {
+            Ok(()) => Ok(job_id),
+            Err(e) => {
+                self.lock_jobs().remove(&job_id);
+                Err(match e {
+                    TrySendError::Full(_) => KernelError::ResourceLimitExceeded(
+                        format!("Async job queue is full ({} pending jobs)", self.capacity)
+                    ),
+                    TrySendError::Disconnected(_) => KernelError::ShuttingDown,
+                })
+            }
+        }
+    }
+    
+    /// Get the status of a job
+    pub(crate) fn status(&self, job_id: &JobId) -> Result<JobStatus, KernelError> {
+        self.expire();
+        self.lock_jobs().get(job_id)
+            .map(|record| record.status.clone())
+            .ok_or_else(|| KernelError::JobNotFound { job_id: job_id.clone() })
+    }
+    
+    /// Cancel a job that hasn't started; returns false if it is running or finished
+    pub(crate) fn cancel(&self, job_id: &JobId) -> Result<bool, KernelError> {
+        let mut jobs = self.lock_jobs();
+        let record = jobs.get_mut(job_id)
+            .ok_or_else(|| KernelError::JobNotFound { job_id: job_id.clone() })?;
+        
+        if !matches!(record.status, JobStatus::Pending) {
+            return Ok(false);
+        }
+        finish(record, JobStatus::Cancelled);
+        Ok(true)
+    }
+    
+    /// Drop finished jobs older than the TTL
+    fn expire(&self) {
+        let ttl = self.ttl;
+        self.lock_jobs().retain(|_, record| {
+            record.finished_at.is_none_or(|finished_at| finished_at.elapsed() < ttl)
+        });
+    }
+    
+    fn lock_jobs(&self) -> std::sync::MutexGuard<'_, HashMap<JobId, JobRecord>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Spawn the worker threads; they exit once the queue is dropped and drained
+    fn start_workers(&self) -> SyncSender<(JobId, Task)> {
+        let (sender, receiver) = mpsc::sync_channel(self.capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+        
+        for index in 0..self.workers {
+            let jobs = self.jobs.clone();
+            let receiver = receiver.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("mcp-job-{}", index))
+                .spawn(move || run_worker(&jobs, &receiver));
+            if let Err(e) = spawned {
+                tracing::error!("Failed to start async job worker: {}", e);
+            }
+        }
+        
+        sender
+    }
+}
+
+/// Run queued jobs until the queue is closed
+fn run_worker(jobs: &JobTable, receiver: &Mutex<Receiver<(JobId, Task)>>) {
+    loop {
+        // Hold the receiver lock only while waiting, not while running the job
+        let next = receiver.lock().unwrap_or_else(|e| e.into_inner()).recv();
+        let (job_id, task) = match next {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        
+        {
+            let mut jobs = jobs.lock().unwrap_or_else(|e| e.into_inner());
+            match jobs.get_mut(&job_id) {
+                Some(record) if matches!(record.status, JobStatus::Pending) => record.status = JobStatus::Running,
+                // Cancelled (or expired) while queued
+                _ => continue,
+            }
+        }
+        
+        let status = match task() {
+            Ok(value) => JobStatus::Completed(value),
+            Err(e) => JobStatus::Failed(e),
+        };
+        
+        if let Some(record) = jobs.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&job_id) {
+            finish(record, status);
+        }
+    }
+}
+
+fn finish(record: &mut JobRecord, status: JobStatus) {
+    record.status = status;
+    record.finished_at = Some(Instant::now());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn wait_finished(queue: &JobQueue, job_id: &JobId) -> JobStatus {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let status = queue.status(job_id).unwrap();
+            if status.is_finished() || Instant::now() > deadline {
+                return status;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+    
+    #[test]
+    fn test_job_queue() {
+        let queue = JobQueue::new(1, 1, Duration::from_millis(50));
+        let (release, blocked) = mpsc::channel::<()>();
+        
+        // Occupy the only worker, then fill the one queue slot
+        let running = queue.submit(Box::new(move || {
+            blocked.recv().ok();
+            Ok(Value::from(1))
+        })).unwrap();
+        while !matches!(queue.status(&running).unwrap(), JobStatus::Running) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let queued = queue.submit(Box::new(|| Ok(Value::from(2)))).unwrap();
+        assert!(matches!(
+            queue.submit(Box::new(|| Ok(Value::Null))),
+            Err(KernelError::ResourceLimitExceeded(_))
+        ));
+        
+        // Only jobs that haven't started can be cancelled
+        assert!(!queue.cancel(&running).unwrap());
+        assert!(queue.cancel(&queued).unwrap());
+        release.send(()).unwrap();
+        assert!(matches!(wait_finished(&queue, &running), JobStatus::Completed(value) if value == 1));
+        assert!(matches!(queue.status(&queued).unwrap(), JobStatus::Cancelled));
+        
+        let failing = queue.submit(Box::new(|| Err(KernelError::Internal("boom".to_string())))).unwrap();
+        assert!(matches!(wait_finished(&queue, &failing), JobStatus::Failed(KernelError::Internal(_))));
+        
+        // Finished results expire after the TTL
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(matches!(queue.status(&running), Err(KernelError::JobNotFound { .. })));
+    }
+}
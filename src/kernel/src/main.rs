@@ -0,0 +1,94 @@
+//! MCP-ZERO Kernel command line
+//!
+//! Maintenance commands run against a kernel's storage directory.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use anyhow::{Result, Context};
+use clap::{Parser, Subcommand};
+
+use mcp_kernel::{KernelConfig, MCPKernel, TraceExportFormat, TraceFilter};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Path to configuration file
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Export stored trace entries as JSON lines or CSV
+    ExportTraces {
+        /// Only entries recorded for this agent
+        #[arg(long)]
+        agent: Option<String>,
+        
+        /// Only entries of this trace
+        #[arg(long)]
+        trace: Option<String>,
+        
+        /// Earliest timestamp, inclusive (Unix seconds or RFC 3339)
+        #[arg(long, value_parser = parse_timestamp)]
+        since: Option<i64>,
+        
+        /// Latest timestamp, inclusive (Unix seconds or RFC 3339)
+        #[arg(long, value_parser = parse_timestamp)]
+        until: Option<i64>,
+        
+        /// Output format: jsonl or csv
+        #[arg(long, default_value = "jsonl")]
+        format: TraceExportFormat,
+        
+        /// File to write, standard output when unset
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+fn main() -> Result<()> {
+    // Log to standard error, keeping standard output for exports
+    tracing_subscriber::fmt().with_writer(io::stderr).init();
+    
+    let cli = Cli::parse();
+    let config = match &cli.config {
+        Some(path) => KernelConfig::from_file(path)?,
+        None => KernelConfig::from_env(),
+    };
+    
+    match cli.command {
+        Commands::ExportTraces { agent, trace, since, until, format, output } => {
+            let filter = TraceFilter { agent_id: agent, trace_id: trace, since, until, ..Default::default() };
+            export_traces(config, &filter, format, output)
+        },
+    }
+}
+
+/// Export the stored traces `filter` selects
+fn export_traces(config: KernelConfig, filter: &TraceFilter, format: TraceExportFormat, output: Option<PathBuf>) -> Result<()> {
+    let kernel = MCPKernel::builder().with_config(config).try_build()?;
+    let writer: Box<dyn Write> = match &output {
+        Some(path) => Box::new(File::create(path)
+            .with_context(|| format!("Failed to create export file: {}", path.display()))?),
+        None => Box::new(io::stdout().lock()),
+    };
+    
+    let exported = kernel.export_traces(filter, format, BufWriter::new(writer))?;
+    tracing::info!("Exported {} trace entries", exported);
+    Ok(())
+}
+
+/// Parse Unix seconds or an RFC 3339 date-time
+fn parse_timestamp(value: &str) -> Result<i64> {
+    if let Ok(seconds) = value.parse() {
+        return Ok(seconds);
+    }
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|time| time.timestamp())
+        .with_context(|| format!("Invalid timestamp: {} (expected Unix seconds or RFC 3339)", value))
+}
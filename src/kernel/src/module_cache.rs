@@ -0,0 +1,162 @@
+//! Compiled module cache for MCP-ZERO kernel
+//!
+//! Stores serialized wasmtime modules on disk so that restarts can skip
+//! compiling plugins, which is slow on low-end hardware.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::{Result, Context};
+use wasmtime::{Engine, Module};
+
+/// Wasmtime version the cache entries are produced by; must match the wasmtime dependency
+///
+/// Artifacts from other versions are also rejected by `Module::deserialize`, so a stale
+/// key only costs a recompile.
+const WASMTIME_VERSION: &str = "10";
+
+/// File extension of cache entries
+const CACHE_EXTENSION: &str = "cwasm";
+
+/// On-disk cache of compiled modules keyed by module hash and wasmtime version
+#[derive(Debug)]
+pub(crate) struct ModuleCache {
+    /// Directory holding the cache entries
+    dir: PathBuf,
+    
+    /// Maximum total size of the cache entries in bytes
+    max_bytes: u64,
+}
+
+impl ModuleCache {
+    /// Create a cache in `dir` bounded to `max_bytes`
+    pub(crate) fn new<P: AsRef<Path>>(dir: P, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            max_bytes,
+        }
+    }
+    
+    /// Load the compiled module for the wasm bytes with the given hash, if cached
+    ///
+    /// Entries that fail to deserialize (e.g. written by another engine
+    /// configuration) are removed so they get rewritten.
+    pub(crate) fn load(&self, engine: &Engine, hash: &str) -> Option<Module> {
+        let path = self.entry_path(hash);
+        if !path.exists() {
+            return None;
+        }
+        
+        // SAFETY: entries are only ever written by `store` from `Module::serialize`,
+        // and the cache lives inside the trusted plugin directory.
+        match unsafe { Module::deserialize_file(engine, &path) } {
+            Ok(module) => {
+                // Refresh the entry's age for size-bounded eviction
+                if let Ok(file) = std::fs::File::options().write(true).open(&path) {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+                Some(module)
+            },
+            Err(e) => {
+                tracing::warn!("Discarding unusable compiled module {}: {}", path.display(), e);
+                let _ = std::fs::remove_file(&path);
+                None
+            }
+        }
+    }
+    
+    /// Store a compiled module under the hash of its wasm bytes, then enforce the size bound
+    pub(crate) fn store(&self, hash: &str, module: &Module) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create module cache directory: {}", self.dir.display()))?;
+        
+        let bytes = module.serialize()?;
+        
+        // Write atomically so a concurrent load never sees a partial entry
+        let path = self.entry_path(hash);
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, &bytes)
+            .with_context(|| format!("Failed to write compiled module: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write compiled module: {}", path.display()))?;
+        
+        self.evict()
+    }
+    
+    /// Remove the least recently used entries until the cache fits in max_bytes
+    fn evict(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(CACHE_EXTENSION) {
+                continue;
+            }
+            let meta = std::fs::metadata(&path)?;
+            entries.push((meta.modified().unwrap_or(SystemTime::UNIX_EPOCH), meta.len(), path));
+        }
+        
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            tracing::debug!("Evicting compiled module {}", path.display());
+            std::fs::remove_file(&path)?;
+            total -= len;
+        }
+        
+        Ok(())
+    }
+    
+    fn entry_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{}-wasmtime{}.{}", hash, WASMTIME_VERSION, CACHE_EXTENSION))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const ECHO_PLUGIN: &str = include_str!("../tests/fixtures/echo_params.wat");
+    const LOOP_PLUGIN: &str = include_str!("../tests/fixtures/infinite_loop.wat");
+    
+    fn entry_count(dir: &Path) -> usize {
+        std::fs::read_dir(dir).unwrap().count()
+    }
+    
+    #[test]
+    fn test_module_cache_roundtrip_and_invalidation() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::default();
+        let cache = ModuleCache::new(dir.path(), u64::MAX);
+        let module = Module::new(&engine, ECHO_PLUGIN).unwrap();
+        
+        assert!(cache.load(&engine, "echo").is_none());
+        cache.store("echo", &module).unwrap();
+        assert!(cache.load(&engine, "echo").is_some());
+        
+        // A corrupted entry is discarded instead of failing the load
+        let entry = cache.entry_path("echo");
+        std::fs::write(&entry, b"not a module").unwrap();
+        assert!(cache.load(&engine, "echo").is_none());
+        assert!(!entry.exists());
+    }
+    
+    #[test]
+    fn test_module_cache_size_bound() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = Engine::default();
+        let echo = Module::new(&engine, ECHO_PLUGIN).unwrap();
+        let spin = Module::new(&engine, LOOP_PLUGIN).unwrap();
+        
+        // Room for exactly one entry: storing a second evicts the older one
+        let max_bytes = echo.serialize().unwrap().len().max(spin.serialize().unwrap().len()) as u64;
+        let cache = ModuleCache::new(dir.path(), max_bytes);
+        cache.store("echo", &echo).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        cache.store("spin", &spin).unwrap();
+        
+        assert_eq!(entry_count(dir.path()), 1);
+        assert!(cache.load(&engine, "spin").is_some());
+    }
+}
@@ -4,17 +4,412 @@
 //! allowing for modular extension of the infrastructure.
 
 use std::collections::HashMap;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
 use anyhow::{Result, Context, anyhow};
 use serde::{Serialize, Deserialize};
-use wasmtime::{Engine, Module, Store, Linker, Caller, Func};
+use thiserror::Error;
+use wasmtime::{Config, Engine, FuncType, Instance, InstancePre, Module, ResourceLimiter, Store, Linker, Caller, Func, Trap, ValType};
 
 use crate::agent::AgentId;
+use crate::module_cache::ModuleCache;
+use crate::wasi::{self, CapturedOutput, WasiContext};
 
 /// Plugin ID type
 pub type PluginId = String;
 
+/// Interval at which the engine epoch advances; execution deadlines are measured in these ticks
+pub const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Execution timeout used when the caller does not provide one
+pub const DEFAULT_EXECUTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Fuel granted per call for each percent of `cpu_limit`; 100% or more disables metering
+pub const FUEL_PER_CPU_PERCENT: u64 = 10_000_000;
+
+/// Upper bound on the duration of a single `host.http_request`
+pub const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Upper bound on the size of a `host.http_request` response body; the plugin's memory_limit may lower it
+pub const MAX_HTTP_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// Maximum nesting of `host.call_plugin` calls used when the caller does not provide one
+pub const DEFAULT_MAX_CALL_DEPTH: u32 = 3;
+
+/// Errors raised by plugin execution that callers may want to handle specifically
+#[derive(Error, Debug)]
+pub enum PluginError {
+    #[error("Plugin {plugin_id} timed out after {elapsed_ms} ms")]
+    Timeout {
+        plugin_id: PluginId,
+        elapsed_ms: u64,
+    },
+    
+    #[error("Plugin {plugin_id} exceeded its {resource} limit")]
+    ResourceExceeded {
+        plugin_id: PluginId,
+        resource: &'static str,
+    },
+    
+    #[error("Plugin {plugin_id} lacks the {capability} capability")]
+    CapabilityDenied {
+        plugin_id: PluginId,
+        capability: &'static str,
+    },
+    
+    #[error("Plugin {plugin_id} exceeded the maximum plugin call depth of {max_depth}")]
+    CallDepthExceeded {
+        plugin_id: PluginId,
+        max_depth: u32,
+    },
+    
+    #[error("Plugin {plugin_id} is not allowed to reach {url}")]
+    HostNotAllowed {
+        plugin_id: PluginId,
+        url: String,
+    },
+}
+
+/// A nested plugin invocation made through `host.call_plugin`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginCall {
+    /// Plugin that made the call
+    pub caller: PluginId,
+    
+    /// Plugin that was called
+    pub target: PluginId,
+    
+    /// Nesting depth of the callee; calls from the entry plugin have depth 1
+    pub depth: u32,
+    
+    /// Whether the callee completed successfully
+    pub success: bool,
+    
+    /// Error message if the call failed
+    pub error: Option<String>,
+}
+
+/// An outbound request made through `host.http_request`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpRequestRecord {
+    /// Plugin that made the request
+    pub plugin_id: PluginId,
+    
+    /// HTTP method
+    pub method: String,
+    
+    /// Host (and port) the request was addressed to
+    pub host: String,
+    
+    /// Response status code, if a response was received
+    pub status: Option<u16>,
+    
+    /// Error message if the request was refused or failed
+    pub error: Option<String>,
+}
+
+/// Default maximum size in bytes of a plugin log message
+pub const DEFAULT_MAX_LOG_BYTES: usize = 1024;
+
+/// Number of most recent plugin log lines kept for the execution trace
+pub const MAX_TRACED_LOG_LINES: usize = 50;
+
+/// A message logged by a plugin through `host.log`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginLogLine {
+    /// Plugin that logged the message
+    pub plugin_id: PluginId,
+    
+    /// Log level name
+    pub level: String,
+    
+    /// Message, truncated to the kernel's limit
+    pub message: String,
+    
+    /// Whether the message was truncated
+    pub truncated: bool,
+}
+
+/// Duration and fuel of one plugin execution, collected when detailed metrics are enabled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginExecution {
+    /// Plugin that executed
+    pub plugin_id: PluginId,
+    
+    /// Wall-clock duration in milliseconds, including nested plugin calls
+    pub duration_ms: f64,
+    
+    /// Fuel consumed by the plugin's own code
+    pub fuel_consumed: u64,
+    
+    /// Whether the execution succeeded
+    pub success: bool,
+}
+
+/// A plugin `shutdown` that failed; the instance is discarded regardless
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginShutdownFailure {
+    /// Plugin whose shutdown failed
+    pub plugin_id: PluginId,
+    
+    /// Agent the instance belonged to
+    pub agent_id: AgentId,
+    
+    /// Error message
+    pub error: String,
+}
+
+/// Stdout/stderr captured from a WASI plugin execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginOutput {
+    /// Plugin that wrote the output
+    pub plugin_id: PluginId,
+    
+    /// Captured stdout
+    pub stdout: String,
+    
+    /// Captured stderr
+    pub stderr: String,
+    
+    /// Whether output beyond the capture limit was dropped
+    pub truncated: bool,
+}
+
+/// Context shared by a plugin execution and the plugins it calls
+#[derive(Debug, Clone)]
+pub struct CallContext {
+    /// Resolves call targets; dangling for detached executions
+    manager: Weak<PluginManager>,
+    
+    /// Nesting depth of the plugin executing in this context
+    depth: u32,
+    
+    /// Maximum nesting depth of plugin calls
+    max_depth: u32,
+    
+    /// Point in time after which the whole call chain is interrupted
+    deadline: Instant,
+    
+    /// Nested calls made so far, in completion order
+    calls: Arc<Mutex<Vec<PluginCall>>>,
+    
+    /// Outbound HTTP requests made so far
+    http_requests: Arc<Mutex<Vec<HttpRequestRecord>>>,
+    
+    /// Output captured from WASI plugins so far
+    outputs: Arc<Mutex<Vec<PluginOutput>>>,
+    
+    /// Most recent log lines of the plugins in this call chain
+    logs: Arc<Mutex<VecDeque<PluginLogLine>>>,
+    
+    /// Maximum size in bytes of a plugin log message
+    max_log_bytes: usize,
+    
+    /// Whether executions emit metrics and are recorded
+    detailed_metrics: bool,
+    
+    /// Executions recorded so far when detailed metrics are enabled
+    executions: Arc<Mutex<Vec<PluginExecution>>>,
+    
+    /// Agent-specific configuration of each plugin
+    plugin_configs: Arc<HashMap<PluginId, serde_json::Value>>,
+    
+    /// Plugins attached to the agent, the only ones its plugins may call
+    attached_plugins: Arc<HashMap<PluginId, Arc<Plugin>>>,
+}
+
+impl CallContext {
+    /// Create a context whose plugins may call other plugins loaded by `manager`
+    pub fn new(manager: &Arc<PluginManager>, max_depth: u32, timeout: Duration) -> Self {
+        Self {
+            manager: Arc::downgrade(manager),
+            depth: 0,
+            max_depth,
+            deadline: Instant::now() + timeout,
+            calls: Arc::new(Mutex::new(Vec::new())),
+            http_requests: Arc::new(Mutex::new(Vec::new())),
+            outputs: Arc::new(Mutex::new(Vec::new())),
+            logs: Arc::new(Mutex::new(VecDeque::new())),
+            max_log_bytes: DEFAULT_MAX_LOG_BYTES,
+            detailed_metrics: false,
+            executions: Arc::new(Mutex::new(Vec::new())),
+            plugin_configs: Arc::new(HashMap::new()),
+            attached_plugins: Arc::new(HashMap::new()),
+        }
+    }
+    
+    /// Create a context without a PluginManager; plugin calls fail in it
+    pub fn detached(timeout: Duration) -> Self {
+        Self {
+            manager: Weak::new(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_CALL_DEPTH,
+            deadline: Instant::now() + timeout,
+            calls: Arc::new(Mutex::new(Vec::new())),
+            http_requests: Arc::new(Mutex::new(Vec::new())),
+            outputs: Arc::new(Mutex::new(Vec::new())),
+            logs: Arc::new(Mutex::new(VecDeque::new())),
+            max_log_bytes: DEFAULT_MAX_LOG_BYTES,
+            detailed_metrics: false,
+            executions: Arc::new(Mutex::new(Vec::new())),
+            plugin_configs: Arc::new(HashMap::new()),
+            attached_plugins: Arc::new(HashMap::new()),
+        }
+    }
+    
+    /// Provide the agent-specific configuration plugins read through `host.get_config`
+    pub fn with_plugin_configs(mut self, configs: HashMap<PluginId, serde_json::Value>) -> Self {
+        self.plugin_configs = Arc::new(configs);
+        self
+    }
+    
+    /// Provide the plugins attached to the agent, which `host.call_plugin` may reach
+    pub fn with_attached_plugins(mut self, plugins: HashMap<PluginId, Arc<Plugin>>) -> Self {
+        self.attached_plugins = Arc::new(plugins);
+        self
+    }
+    
+    /// Truncate plugin log messages longer than `max_bytes`
+    pub fn with_max_log_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_log_bytes = max_bytes;
+        self
+    }
+    
+    /// Emit per-plugin metrics and record each execution's duration and fuel
+    pub fn with_detailed_metrics(mut self, enabled: bool) -> Self {
+        self.detailed_metrics = enabled;
+        self
+    }
+    
+    /// Configuration of a plugin, null if none was given
+    fn plugin_config(&self, plugin_id: &PluginId) -> serde_json::Value {
+        self.plugin_configs.get(plugin_id).cloned().unwrap_or_default()
+    }
+    
+    /// Time left before the call chain's deadline
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+    
+    /// Nested calls recorded so far
+    pub fn calls(&self) -> Vec<PluginCall> {
+        self.calls.lock().map(|calls| calls.clone()).unwrap_or_default()
+    }
+    
+    /// Outbound HTTP requests recorded so far
+    pub fn http_requests(&self) -> Vec<HttpRequestRecord> {
+        self.http_requests.lock().map(|requests| requests.clone()).unwrap_or_default()
+    }
+    
+    fn record_http_request(&self, record: HttpRequestRecord) {
+        if let Ok(mut requests) = self.http_requests.lock() {
+            requests.push(record);
+        }
+    }
+    
+    /// Output captured from WASI plugins in this call chain
+    pub fn outputs(&self) -> Vec<PluginOutput> {
+        self.outputs.lock().map(|outputs| outputs.clone()).unwrap_or_default()
+    }
+    
+    fn record_output(&self, output: PluginOutput) {
+        if let Ok(mut outputs) = self.outputs.lock() {
+            outputs.push(output);
+        }
+    }
+    
+    /// Executions in this call chain, nested calls first; empty unless detailed metrics are enabled
+    pub fn executions(&self) -> Vec<PluginExecution> {
+        self.executions.lock().map(|executions| executions.clone()).unwrap_or_default()
+    }
+    
+    fn record_execution(&self, execution: PluginExecution) {
+        if let Ok(mut executions) = self.executions.lock() {
+            executions.push(execution);
+        }
+    }
+    
+    /// Last MAX_TRACED_LOG_LINES messages logged by plugins in this call chain
+    pub fn logs(&self) -> Vec<PluginLogLine> {
+        self.logs.lock().map(|logs| logs.iter().cloned().collect()).unwrap_or_default()
+    }
+    
+    fn record_log(&self, line: PluginLogLine) {
+        if let Ok(mut logs) = self.logs.lock() {
+            if logs.len() == MAX_TRACED_LOG_LINES {
+                logs.pop_front();
+            }
+            logs.push_back(line);
+        }
+    }
+    
+    /// Invoke `target` on behalf of `caller`, recording the call
+    fn call(
+        &self,
+        caller: &PluginId,
+        target: &PluginId,
+        intent: &str,
+        payload: &serde_json::Value,
+        agent_id: &AgentId,
+        state: &Arc<HashMap<String, serde_json::Value>>,
+    ) -> Result<serde_json::Value> {
+        let result = self.dispatch(caller, target, intent, payload, agent_id, state);
+        
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push(PluginCall {
+                caller: caller.clone(),
+                target: target.clone(),
+                depth: self.depth + 1,
+                success: result.is_ok(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            });
+        }
+        
+        result
+    }
+    
+    fn dispatch(
+        &self,
+        caller: &PluginId,
+        target: &PluginId,
+        intent: &str,
+        payload: &serde_json::Value,
+        agent_id: &AgentId,
+        state: &Arc<HashMap<String, serde_json::Value>>,
+    ) -> Result<serde_json::Value> {
+        if self.depth >= self.max_depth {
+            return Err(PluginError::CallDepthExceeded {
+                plugin_id: caller.clone(),
+                max_depth: self.max_depth,
+            }.into());
+        }
+        
+        let manager = self.manager.upgrade()
+            .ok_or_else(|| anyhow!("Plugin calls are not available in this context"))?;
+        
+        // Only plugins attached to the agent were vetted and approved for it
+        let plugin = match self.attached_plugins.get(target) {
+            Some(plugin) if plugin.is_loaded() => plugin.clone(),
+            Some(_) => manager.load_plugin(target)?,
+            None => return Err(PluginError::CapabilityDenied {
+                plugin_id: caller.clone(),
+                capability: "plugin_call",
+            }.into()),
+        };
+        
+        let nested = Self {
+            depth: self.depth + 1,
+            ..self.clone()
+        };
+        plugin.execute(intent, payload, agent_id, state, &nested)
+    }
+}
+
 /// Plugin capability configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginCapabilities {
@@ -38,6 +433,26 @@ pub struct PluginCapabilities {
     #[serde(default = "default_memory_limit")]
     pub memory_limit: u32,
     
+    /// URL prefixes reachable through `host.http_request` when external_access is granted
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    
+    /// Plugin config keys whose values are redacted from traces
+    #[serde(default)]
+    pub sensitive_keys: Vec<String>,
+    
+    /// Whether the plugin gets WASI imports (clocks, random, captured stdio)
+    #[serde(default)]
+    pub wasi: bool,
+    
+    /// Host directories a WASI plugin may access, mounted at the same path
+    #[serde(default)]
+    pub preopened_dirs: Vec<String>,
+    
+    /// Most verbose level forwarded from `host.log` (error, warn, info, debug or trace)
+    #[serde(default = "default_log_level", with = "log_level_name")]
+    pub log_level: tracing::Level,
+    
     /// Additional capabilities
     #[serde(default)]
     pub additional: HashMap<String, serde_json::Value>,
@@ -51,6 +466,24 @@ fn default_memory_limit() -> u32 {
     50 // Default 50MB memory limit per plugin
 }
 
+fn default_log_level() -> tracing::Level {
+    tracing::Level::INFO
+}
+
+/// (De)serializes a log level by its name
+mod log_level_name {
+    use serde::{Deserialize, Deserializer, Serializer};
+    
+    pub fn serialize<S: Serializer>(level: &tracing::Level, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&level.as_str().to_lowercase())
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<tracing::Level, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(|_| serde::de::Error::custom(format!("unknown log level: {}", name)))
+    }
+}
+
 impl Default for PluginCapabilities {
     fn default() -> Self {
         Self {
@@ -59,11 +492,36 @@ impl Default for PluginCapabilities {
             external_access: false,
             cpu_limit: default_cpu_limit(),
             memory_limit: default_memory_limit(),
+            allowed_hosts: Vec::new(),
+            sensitive_keys: Vec::new(),
+            wasi: false,
+            preopened_dirs: Vec::new(),
+            log_level: default_log_level(),
             additional: HashMap::new(),
         }
     }
 }
 
+impl PluginCapabilities {
+    /// Copy of a plugin config with the values of sensitive keys replaced, at any depth
+    pub fn redact_config(&self, config: &serde_json::Value) -> serde_json::Value {
+        match config {
+            serde_json::Value::Object(map) => map.iter()
+                .map(|(key, value)| {
+                    let value = if self.sensitive_keys.contains(key) {
+                        serde_json::Value::String("[REDACTED]".to_string())
+                    } else {
+                        self.redact_config(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+            serde_json::Value::Array(items) => items.iter().map(|item| self.redact_config(item)).collect(),
+            other => other.clone(),
+        }
+    }
+}
+
 /// Plugin metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginMetadata {
@@ -122,6 +580,61 @@ impl AsRef<Module> for DebugModule {
     }
 }
 
+/// Calling convention of a plugin's `execute` export, detected from its signature
+///
+/// - `execute()`: the plugin reports its result by calling `host.set_result`.
+/// - `execute(params_ptr: i32, params_len: i32) -> (result_ptr: i32, result_len: i32)`:
+///   the host copies the JSON params into a buffer obtained from the plugin's exported
+///   `alloc(len: i32) -> i32` and reads the JSON result back from the returned range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExecuteAbi {
+    SetResult,
+    Direct,
+}
+
+impl ExecuteAbi {
+    /// Match an export signature against the supported conventions
+    fn detect(ty: &FuncType) -> Option<Self> {
+        let params: Vec<ValType> = ty.params().collect();
+        let results: Vec<ValType> = ty.results().collect();
+        match (params.as_slice(), results.as_slice()) {
+            ([], []) => Some(Self::SetResult),
+            ([ValType::I32, ValType::I32], [ValType::I32, ValType::I32]) => Some(Self::Direct),
+            _ => None,
+        }
+    }
+}
+
+/// Module linked against the host functions, ready for cheap instantiation
+struct LinkedModule(InstancePre<PluginState>);
+
+impl std::fmt::Debug for LinkedModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LinkedModule").finish()
+    }
+}
+
+/// A module instance with its store; lifecycle plugins keep one per agent between calls
+struct PluginInstance {
+    store: Store<PluginState>,
+    instance: Instance,
+    output: CapturedOutput,
+    
+    /// Fuel consumed by the store when its budget was last refilled
+    fuel_consumed: u64,
+    
+    /// Fuel consumed by the last `execute` call
+    last_call_fuel: u64,
+}
+
+impl std::fmt::Debug for PluginInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluginInstance")
+            .field("agent_id", &self.store.data().agent_id)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug)]
 pub struct Plugin {
     /// Unique plugin identifier
@@ -134,29 +647,48 @@ pub struct Plugin {
     metadata: PluginMetadata,
     
     /// WASM module
-    module: Option<DebugModule>,
+    module: RwLock<Option<DebugModule>>,
+    
+    /// Module with imports resolved against the shared linker
+    linked: RwLock<Option<LinkedModule>>,
+    
+    /// Whether the plugin is loaded; cleared when the PluginManager evicts it
+    loaded: AtomicBool,
     
-    /// Whether the plugin is loaded
-    loaded: bool,
+    /// Whether the module exports `init` or `shutdown`
+    lifecycle: bool,
+    
+    /// Initialized instances of a lifecycle plugin, one per agent
+    instances: Mutex<HashMap<AgentId, Arc<Mutex<PluginInstance>>>>,
 }
 
 impl Plugin {
     /// Create a new Plugin instance with full details
-    pub fn new(
+    ///
+    /// The module's imports are resolved against `linker` once here so each
+    /// execution only needs a fresh Store.
+    pub(crate) fn new(
         id: PluginId,
         capabilities: PluginCapabilities,
         metadata: PluginMetadata,
         module: Module,
-    ) -> Self {
+        linker: &Linker<PluginState>,
+    ) -> Result<Self> {
+        let instance_pre = linker.instantiate_pre(&module)
+            .with_context(|| format!("Failed to link plugin {}", id))?;
+        
+        let lifecycle = module.get_export("init").is_some() || module.get_export("shutdown").is_some();
         let debug_module = DebugModule::from(Arc::new(module));
-        let plugin = Plugin {
+        Ok(Plugin {
             id,
             capabilities,
             metadata,
-            module: Some(debug_module),
-            loaded: true,
-        };
-        plugin
+            module: RwLock::new(Some(debug_module)),
+            linked: RwLock::new(Some(LinkedModule(instance_pre))),
+            loaded: AtomicBool::new(true),
+            lifecycle,
+            instances: Mutex::new(HashMap::new()),
+        })
     }
     
     /// Create a placeholder Plugin instance (not fully loaded)
@@ -165,8 +697,11 @@ impl Plugin {
             id: id.clone(),
             capabilities: PluginCapabilities::default(),
             metadata: PluginMetadata::default(),
-            module: None,
-            loaded: false,
+            module: RwLock::new(None),
+            linked: RwLock::new(None),
+            loaded: AtomicBool::new(false),
+            lifecycle: false,
+            instances: Mutex::new(HashMap::new()),
         }
     }
     
@@ -185,76 +720,446 @@ impl Plugin {
         &self.metadata
     }
     
+    /// Get the compiled WASM module, if loaded
+    pub fn module(&self) -> Option<Module> {
+        self.module.read().ok()?.as_ref().map(|m| m.as_ref().clone())
+    }
+    
     /// Check if the plugin is loaded
     pub fn is_loaded(&self) -> bool {
-        self.loaded
+        self.loaded.load(Ordering::Acquire)
+    }
+    
+    /// Drop the compiled module so its memory can be reclaimed
+    ///
+    /// Holders of this plugin see it as unloaded and must reload it from the PluginManager.
+    pub(crate) fn unload(&self) {
+        for failure in self.shutdown_instances() {
+            tracing::warn!("Plugin {} failed to shut down for agent {}: {}", failure.plugin_id, failure.agent_id, failure.error);
+        }
+        self.loaded.store(false, Ordering::Release);
+        if let Ok(mut linked) = self.linked.write() {
+            linked.take();
+        }
+        if let Ok(mut module) = self.module.write() {
+            module.take();
+        }
     }
     
-    /// Execute the plugin with an intent
+    /// Execute the plugin with an intent and its structured parameters
+    ///
+    /// The plugin is interrupted once the context's deadline passes. Both `execute`
+    /// conventions described on [`ExecuteAbi`] are supported. Plugins exporting `init`
+    /// or `shutdown` keep one instance per agent across calls; other plugins are
+    /// instantiated afresh for every call.
     pub fn execute(
         &self,
         intent: &str,
+        params: &serde_json::Value,
         agent_id: &AgentId,
-        state: &HashMap<String, serde_json::Value>,
+        state: &Arc<HashMap<String, serde_json::Value>>,
+        context: &CallContext,
     ) -> Result<serde_json::Value> {
+        if !context.detailed_metrics {
+            return self.run_execute(intent, params, agent_id, state, context).0;
+        }
+        
+        let started = Instant::now();
+        let (result, fuel_consumed) = self.run_execute(intent, params, agent_id, state, context);
+        let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+        
+        metrics::histogram!("mcp.plugin.exec_duration_ms", duration_ms, "plugin" => self.id.clone());
+        metrics::histogram!("mcp.plugin.fuel_consumed", fuel_consumed as f64, "plugin" => self.id.clone());
+        if result.is_err() {
+            metrics::increment_counter!("mcp.plugin.exec_errors", "plugin" => self.id.clone());
+        }
+        context.record_execution(PluginExecution {
+            plugin_id: self.id.clone(),
+            duration_ms,
+            fuel_consumed,
+            success: result.is_ok(),
+        });
+        
+        result
+    }
+    
+    /// Execute on a fresh or the agent's instance, returning the fuel the call consumed
+    fn run_execute(
+        &self,
+        intent: &str,
+        params: &serde_json::Value,
+        agent_id: &AgentId,
+        state: &Arc<HashMap<String, serde_json::Value>>,
+        context: &CallContext,
+    ) -> (Result<serde_json::Value>, u64) {
+        if !self.lifecycle {
+            let mut instance = match self.instantiate(agent_id, context) {
+                Ok(instance) => instance,
+                Err(e) => return (Err(e), 0),
+            };
+            let result = self.execute_in(&mut instance, intent, params, state, context);
+            return (result, instance.last_call_fuel);
+        }
+        
+        let instance = match self.agent_instance(agent_id, state, context) {
+            Ok(instance) => instance,
+            Err(e) => return (Err(e), 0),
+        };
+        let mut guard = match instance.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => return (Err(anyhow!("Plugin {} is already executing for agent {}", self.id, agent_id)), 0),
+        };
+        let result = self.execute_in(&mut guard, intent, params, state, context);
+        let fuel_consumed = guard.last_call_fuel;
+        if result.is_err() {
+            // A failed call may leave the instance inconsistent; the next call initializes a new one
+            drop(guard);
+            if let Ok(mut instances) = self.instances.lock() {
+                instances.remove(agent_id);
+            }
+        }
+        (result, fuel_consumed)
+    }
+    
+    /// Whether the plugin exports `init` or `shutdown` and so keeps per-agent instances
+    pub fn has_lifecycle(&self) -> bool {
+        self.lifecycle
+    }
+    
+    /// Create the agent's instance and run `init` unless it already exists
+    ///
+    /// Does nothing for plugins without lifecycle exports.
+    pub fn init_instance(
+        &self,
+        agent_id: &AgentId,
+        state: &Arc<HashMap<String, serde_json::Value>>,
+        context: &CallContext,
+    ) -> Result<()> {
+        if self.lifecycle {
+            self.agent_instance(agent_id, state, context)?;
+        }
+        Ok(())
+    }
+    
+    /// Whether an initialized instance is held for the agent
+    pub fn has_instance(&self, agent_id: &AgentId) -> bool {
+        self.instances.lock().map(|instances| instances.contains_key(agent_id)).unwrap_or(false)
+    }
+    
+    /// Drop the agent's instance, running `shutdown` if the plugin exports it
+    ///
+    /// Waits for an in-flight call on the instance to finish. The instance is
+    /// discarded even when `shutdown` fails.
+    pub fn shutdown_instance(&self, agent_id: &AgentId) -> Result<()> {
+        let instance = match self.instances.lock().map_err(|_| anyhow!("Failed to acquire instances lock"))?.remove(agent_id) {
+            Some(instance) => instance,
+            None => return Ok(()),
+        };
+        let mut instance = instance.lock().map_err(|_| anyhow!("Failed to acquire instance lock"))?;
+        
+        // Shutdown runs outside any execution, with the config the instance last saw
+        let config = serde_json::from_slice(&instance.store.data().config).unwrap_or_default();
+        let context = CallContext::detached(DEFAULT_EXECUTION_TIMEOUT)
+            .with_plugin_configs(HashMap::from([(self.id.clone(), config)]));
+        self.call_lifecycle(&mut instance, "shutdown", &Arc::default(), &context)
+    }
+    
+    /// Shut down the instances of every agent, returning the failures
+    pub fn shutdown_instances(&self) -> Vec<PluginShutdownFailure> {
+        let agent_ids: Vec<AgentId> = match self.instances.lock() {
+            Ok(instances) => instances.keys().cloned().collect(),
+            Err(_) => return Vec::new(),
+        };
+        
+        agent_ids.into_iter()
+            .filter_map(|agent_id| {
+                let error = self.shutdown_instance(&agent_id).err()?;
+                Some(PluginShutdownFailure {
+                    plugin_id: self.id.clone(),
+                    agent_id,
+                    error: format!("{:#}", error),
+                })
+            })
+            .collect()
+    }
+    
+    /// The agent's instance, instantiated and initialized on first use
+    fn agent_instance(
+        &self,
+        agent_id: &AgentId,
+        state: &Arc<HashMap<String, serde_json::Value>>,
+        context: &CallContext,
+    ) -> Result<Arc<Mutex<PluginInstance>>> {
+        let existing = self.instances.lock()
+            .map_err(|_| anyhow!("Failed to acquire instances lock"))?
+            .get(agent_id)
+            .cloned();
+        if let Some(instance) = existing {
+            return Ok(instance);
+        }
+        
+        // The lock is not held during init, which may call other plugins
+        let mut instance = self.instantiate(agent_id, context)?;
+        self.call_lifecycle(&mut instance, "init", state, context)
+            .with_context(|| format!("Plugin {} failed to initialize for agent {}", self.id, agent_id))?;
+        
+        let mut instances = self.instances.lock().map_err(|_| anyhow!("Failed to acquire instances lock"))?;
+        Ok(instances.entry(agent_id.clone())
+            .or_insert_with(|| Arc::new(Mutex::new(instance)))
+            .clone())
+    }
+    
+    /// Instantiate the module in a new store on the shared engine
+    fn instantiate(&self, agent_id: &AgentId, context: &CallContext) -> Result<PluginInstance> {
         // If the plugin is not loaded, return an error
-        if !self.loaded {
+        if !self.is_loaded() {
             return Err(anyhow!("Plugin {} is not loaded", self.id));
         }
         
-        // Ensure we have a module
-        let debug_module = match &self.module {
-            Some(m) => m,
+        // Ensure we have a linked module; the clone keeps it alive if the plugin is evicted mid-call
+        let linked = match self.linked.read().map_err(|_| anyhow!("Failed to acquire read lock"))?.as_ref() {
+            Some(l) => l.0.clone(),
             None => return Err(anyhow!("Plugin {} has no module loaded", self.id)),
         };
-        // Access the underlying Module reference
-        let module_ref = debug_module.as_ref();
         
-        // Create a new store
-        let engine = Engine::default();
-        let mut store = Store::new(&engine, PluginState {
+        // WASI plugins get a context whose stdio is captured
+        let output = CapturedOutput::default();
+        let wasi = if self.capabilities.wasi {
+            Some(wasi::build_context(&self.capabilities, &output)?)
+        } else {
+            None
+        };
+        
+        // Per-call fields are filled in by begin_call
+        let mut store = Store::new(linked.module().engine(), PluginState {
             agent_id: agent_id.clone(),
-            intent: intent.to_string(),
-            state: state.clone(),
+            intent: String::new(),
+            params: Vec::new(),
+            config: Vec::new(),
+            state: Arc::default(),
             result: None,
+            limiter: PluginLimiter::new(&self.capabilities),
+            plugin_id: self.id.clone(),
+            plugin_call: self.capabilities.plugin_call,
+            context: context.clone(),
+            call_result: Vec::new(),
+            allowed_hosts: self.capabilities.allowed_hosts.clone(),
+            log_level: self.capabilities.log_level,
+            http_response: Vec::new(),
+            wasi,
         });
+        store.limiter(|state| &mut state.limiter);
+        store.add_fuel(self.fuel_budget())?;
         
-        // Create a linker with the appropriate host functions
-        let mut linker = Linker::new(&engine);
+        // Instantiate the pre-linked module; its start function runs under the deadline too
+        let instance = self.guarded_call(&mut store, &output, context, |store| linked.instantiate(store))?;
         
-        // Define host functions that the plugin can call
-        Self::define_host_functions(&mut linker)?;
+        Ok(PluginInstance { store, instance, output, fuel_consumed: 0, last_call_fuel: 0 })
+    }
+    
+    /// Prepare an instance's per-call data and fuel for the next call
+    fn begin_call(
+        &self,
+        instance: &mut PluginInstance,
+        intent: &str,
+        params: &serde_json::Value,
+        state: &Arc<HashMap<String, serde_json::Value>>,
+        context: &CallContext,
+    ) -> Result<()> {
+        let store = &mut instance.store;
+        let data = store.data_mut();
+        data.intent = intent.to_string();
+        data.params = serde_json::to_vec(params)?;
+        data.config = serde_json::to_vec(&context.plugin_config(&self.id))?;
+        data.state = state.clone();
+        data.result = None;
+        data.context = context.clone();
+        data.call_result.clear();
+        data.http_response.clear();
+        data.limiter.memory_exceeded = false;
         
-        // Instantiate the module
-        let instance = linker.instantiate(&mut store, module_ref)?;
+        // Refill what earlier calls on the instance used so every call gets the full budget
+        let consumed = store.fuel_consumed().unwrap_or_default();
+        if consumed > instance.fuel_consumed {
+            store.add_fuel(consumed - instance.fuel_consumed)?;
+            instance.fuel_consumed = consumed;
+        }
+        Ok(())
+    }
+    
+    /// Run `execute` on an instance
+    fn execute_in(
+        &self,
+        instance: &mut PluginInstance,
+        intent: &str,
+        params: &serde_json::Value,
+        state: &Arc<HashMap<String, serde_json::Value>>,
+        context: &CallContext,
+    ) -> Result<serde_json::Value> {
+        self.begin_call(instance, intent, params, state, context)?;
+        let PluginInstance { store, instance, output, last_call_fuel, .. } = instance;
         
-        // Get the execute function
-        let execute = instance.get_func(&mut store, "execute")
+        // Get the execute function and the calling convention it follows
+        let execute = instance.get_func(&mut *store, "execute")
             .ok_or_else(|| anyhow!("Plugin {} does not export 'execute' function", self.id))?;
+        let abi = ExecuteAbi::detect(&execute.ty(&*store))
+            .ok_or_else(|| anyhow!("Plugin {} exports 'execute' with an unsupported signature", self.id))?;
         
         // Execute the function
-        execute.call(&mut store, &[], &mut [])?;
+        let fuel_before = store.fuel_consumed().unwrap_or_default();
+        let returned = self.guarded_call(store, output, context, |store| match abi {
+            ExecuteAbi::SetResult => execute.call(store, &[], &mut []).map(|_| None),
+            ExecuteAbi::Direct => self.call_direct(instance, store, execute).map(Some),
+        });
+        *last_call_fuel = store.fuel_consumed().unwrap_or_default().saturating_sub(fuel_before);
+        let returned = returned?;
         
         // Get the result
-        let result = store.data().result.clone()
-            .unwrap_or_else(|| serde_json::json!({"status": "executed", "result": null}));
+        let result = match returned {
+            Some(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| anyhow!("Plugin {} returned invalid JSON: {}", self.id, e))?,
+            None => store.data().result.clone()
+                .unwrap_or_else(|| serde_json::json!({"status": "executed", "result": null})),
+        };
         
         Ok(result)
     }
     
-    /// Define host functions for the WASM module
-    fn define_host_functions(linker: &mut Linker<PluginState>) -> Result<()> {
-        // Function to set the execution result
-        linker.func_wrap("host", "set_result", |mut caller: Caller<'_, PluginState>, ptr: u32, len: u32| {
-            let memory = match caller.get_export("memory") {
-                Some(wasmtime::Extern::Memory(mem)) => mem,
-                _ => return Err(anyhow!("Failed to get memory export")),
-            };
-            
-            // Read the result data from the module's memory
-            let data = match memory.data(&caller).get(ptr as usize..(ptr + len) as usize) {
-                Some(data) => data,
-                None => return Err(anyhow!("Invalid memory range")),
+    /// Run the `init` or `shutdown` export, if present
+    fn call_lifecycle(
+        &self,
+        instance: &mut PluginInstance,
+        name: &str,
+        state: &Arc<HashMap<String, serde_json::Value>>,
+        context: &CallContext,
+    ) -> Result<()> {
+        self.begin_call(instance, name, &serde_json::Value::Null, state, context)?;
+        let PluginInstance { store, instance, output, .. } = instance;
+        let func = match instance.get_func(&mut *store, name) {
+            Some(func) => func.typed::<(), ()>(&*store)
+                .with_context(|| format!("Plugin {} exports '{}' with an unsupported signature", self.id, name))?,
+            None => return Ok(()),
+        };
+        
+        self.guarded_call(store, output, context, |store| func.call(store, ()))
+    }
+    
+    /// Call into the module under the context's deadline, mapping limit violations to PluginErrors
+    fn guarded_call<T>(
+        &self,
+        store: &mut Store<PluginState>,
+        output: &CapturedOutput,
+        context: &CallContext,
+        call: impl FnOnce(&mut Store<PluginState>) -> Result<T>,
+    ) -> Result<T> {
+        // Interrupt the module once the timeout has elapsed
+        let ticks = context.remaining().as_millis().div_ceil(EPOCH_TICK.as_millis()).max(1) as u64;
+        store.epoch_deadline_trap();
+        store.set_epoch_deadline(ticks);
+        
+        let started = Instant::now();
+        let outcome = call(store);
+        
+        // Drop the store's handle on the agent state so the agent's next write doesn't copy it
+        store.data_mut().state = Arc::default();
+        if self.capabilities.wasi {
+            self.record_output(context, output);
+        }
+        let value = match outcome {
+            Ok(value) => value,
+            Err(e) if e.downcast_ref::<Trap>() == Some(&Trap::Interrupt) => {
+                return Err(PluginError::Timeout {
+                    plugin_id: self.id.clone(),
+                    elapsed_ms: started.elapsed().as_millis() as u64,
+                }.into());
+            },
+            Err(e) => return Err(self.resource_error(store, e)),
+        };
+        
+        // A refused memory.grow is a violation even if the module carried on
+        if store.data().limiter.memory_exceeded {
+            return Err(self.resource_exceeded("memory"));
+        }
+        
+        Ok(value)
+    }
+    
+    /// Record captured stdio on the call context, also when the execution failed
+    fn record_output(&self, context: &CallContext, output: &CapturedOutput) {
+        let (stdout, stdout_truncated) = output.stdout.take();
+        let (stderr, stderr_truncated) = output.stderr.take();
+        if stdout.is_empty() && stderr.is_empty() {
+            return;
+        }
+        context.record_output(PluginOutput {
+            plugin_id: self.id.clone(),
+            stdout,
+            stderr,
+            truncated: stdout_truncated || stderr_truncated,
+        });
+    }
+    
+    /// Call an `execute(params_ptr, params_len) -> (result_ptr, result_len)` export
+    fn call_direct(&self, instance: &Instance, store: &mut Store<PluginState>, execute: Func) -> Result<Vec<u8>> {
+        let memory = instance.get_memory(&mut *store, "memory")
+            .ok_or_else(|| anyhow!("Plugin {} does not export 'memory'", self.id))?;
+        let alloc = instance.get_typed_func::<u32, u32>(&mut *store, "alloc")
+            .with_context(|| format!("Plugin {} returns its result directly but does not export 'alloc'", self.id))?;
+        
+        // Copy the params into a guest buffer
+        let params = store.data().params.clone();
+        let params_ptr = alloc.call(&mut *store, params.len() as u32)?;
+        memory.write(&mut *store, params_ptr as usize, &params)
+            .map_err(|_| anyhow!("Plugin {} allocated an invalid params buffer", self.id))?;
+        
+        let (result_ptr, result_len) = execute.typed::<(u32, u32), (u32, u32)>(&*store)?
+            .call(&mut *store, (params_ptr, params.len() as u32))?;
+        
+        // Read the JSON result out of guest memory
+        let result = guest_bytes(memory.data(&*store), result_ptr, result_len)?;
+        Ok(result.to_vec())
+    }
+    
+    /// Fuel available to a single call, derived from the plugin's `cpu_limit`
+    fn fuel_budget(&self) -> u64 {
+        let cpu_limit = self.capabilities.cpu_limit;
+        if cpu_limit >= 100.0 {
+            return u64::MAX;
+        }
+        (cpu_limit.max(0.0) as f64 * FUEL_PER_CPU_PERCENT as f64) as u64
+    }
+    
+    /// Maps a failed instantiation or call onto a resource violation when a limit caused it
+    fn resource_error(&self, store: &Store<PluginState>, error: anyhow::Error) -> anyhow::Error {
+        if store.data().limiter.memory_exceeded {
+            return self.resource_exceeded("memory");
+        }
+        if error.downcast_ref::<Trap>() == Some(&Trap::OutOfFuel) {
+            return self.resource_exceeded("cpu");
+        }
+        error
+    }
+    
+    fn resource_exceeded(&self, resource: &'static str) -> anyhow::Error {
+        PluginError::ResourceExceeded {
+            plugin_id: self.id.clone(),
+            resource,
+        }.into()
+    }
+    
+    /// Define host functions for the WASM module
+    pub(crate) fn define_host_functions(linker: &mut Linker<PluginState>) -> Result<()> {
+        // Function to set the execution result
+        linker.func_wrap("host", "set_result", |mut caller: Caller<'_, PluginState>, ptr: u32, len: u32| {
+            let memory = match caller.get_export("memory") {
+                Some(wasmtime::Extern::Memory(mem)) => mem,
+                _ => return Err(anyhow!("Failed to get memory export")),
+            };
+            
+            // Read the result data from the module's memory
+            let data = match memory.data(&caller).get(ptr as usize..(ptr + len) as usize) {
+                Some(data) => data,
+                None => return Err(anyhow!("Invalid memory range")),
             };
             
             // Convert to JSON
@@ -291,10 +1196,500 @@ impl Plugin {
             Ok(len as u32)
         })?;
         
+        // Function to get the length of the serialized params
+        linker.func_wrap("host", "get_params_len", |caller: Caller<'_, PluginState>| -> u32 {
+            caller.data().params.len() as u32
+        })?;
+        
+        // Function to get the intent params as JSON
+        linker.func_wrap("host", "get_params", |mut caller: Caller<'_, PluginState>, ptr: u32| -> Result<u32, anyhow::Error> {
+            let memory = match caller.get_export("memory") {
+                Some(wasmtime::Extern::Memory(mem)) => mem,
+                _ => return Err(anyhow!("Failed to get memory export")),
+            };
+            
+            // Copy the params out of the store data to avoid the borrow conflict
+            let params = caller.data().params.clone();
+            let len = params.len();
+            
+            // Write the params to the module's memory
+            let mem_slice = match memory.data_mut(&mut caller).get_mut(ptr as usize..ptr as usize + len) {
+                Some(slice) => slice,
+                None => return Err(anyhow!("Invalid memory range")),
+            };
+            mem_slice.copy_from_slice(&params);
+            
+            Ok(len as u32)
+        })?;
+        
+        // Function to log a message at a level (0 = error .. 4 = trace) into the kernel's tracing
+        linker.func_wrap("host", "log", |mut caller: Caller<'_, PluginState>, level: u32, ptr: u32, len: u32| -> Result<(), anyhow::Error> {
+            let level = match level {
+                0 => tracing::Level::ERROR,
+                1 => tracing::Level::WARN,
+                2 => tracing::Level::INFO,
+                3 => tracing::Level::DEBUG,
+                4 => tracing::Level::TRACE,
+                _ => return Err(anyhow!("Invalid log level: {}", level)),
+            };
+            
+            // Drop messages more verbose than the plugin's cap
+            if level > caller.data().log_level {
+                return Ok(());
+            }
+            
+            let memory = match caller.get_export("memory") {
+                Some(wasmtime::Extern::Memory(mem)) => mem,
+                _ => return Err(anyhow!("Failed to get memory export")),
+            };
+            let state = caller.data();
+            let bytes = guest_bytes(memory.data(&caller), ptr, len)?;
+            let (message, truncated) = truncate_message(bytes, state.context.max_log_bytes);
+            
+            let (plugin_id, agent_id) = (&state.plugin_id, &state.agent_id);
+            match level {
+                tracing::Level::ERROR => tracing::error!(plugin_id = %plugin_id, agent_id = %agent_id, "{}", message),
+                tracing::Level::WARN => tracing::warn!(plugin_id = %plugin_id, agent_id = %agent_id, "{}", message),
+                tracing::Level::INFO => tracing::info!(plugin_id = %plugin_id, agent_id = %agent_id, "{}", message),
+                tracing::Level::DEBUG => tracing::debug!(plugin_id = %plugin_id, agent_id = %agent_id, "{}", message),
+                _ => tracing::trace!(plugin_id = %plugin_id, agent_id = %agent_id, "{}", message),
+            }
+            
+            state.context.record_log(PluginLogLine {
+                plugin_id: plugin_id.clone(),
+                level: level.as_str().to_lowercase(),
+                message,
+                truncated,
+            });
+            
+            Ok(())
+        })?;
+        
+        // Function to get the length of the serialized plugin config
+        linker.func_wrap("host", "get_config_len", |caller: Caller<'_, PluginState>| -> u32 {
+            caller.data().config.len() as u32
+        })?;
+        
+        // Function to get the agent-specific plugin config as JSON
+        linker.func_wrap("host", "get_config", |mut caller: Caller<'_, PluginState>, ptr: u32| -> Result<u32, anyhow::Error> {
+            let memory = match caller.get_export("memory") {
+                Some(wasmtime::Extern::Memory(mem)) => mem,
+                _ => return Err(anyhow!("Failed to get memory export")),
+            };
+            
+            // Copy the config out of the store data to avoid the borrow conflict
+            let config = caller.data().config.clone();
+            let len = config.len();
+            
+            // Write the config to the module's memory
+            let mem_slice = match memory.data_mut(&mut caller).get_mut(ptr as usize..ptr as usize + len) {
+                Some(slice) => slice,
+                None => return Err(anyhow!("Invalid memory range")),
+            };
+            mem_slice.copy_from_slice(&config);
+            
+            Ok(len as u32)
+        })?;
+        
+        // Function to call another plugin with a JSON payload, returning the result length
+        linker.func_wrap("host", "call_plugin", |mut caller: Caller<'_, PluginState>, name_ptr: u32, name_len: u32, payload_ptr: u32, payload_len: u32| -> Result<u32, anyhow::Error> {
+            if !caller.data().plugin_call {
+                return Err(PluginError::CapabilityDenied {
+                    plugin_id: caller.data().plugin_id.clone(),
+                    capability: "plugin_call",
+                }.into());
+            }
+            
+            let memory = match caller.get_export("memory") {
+                Some(wasmtime::Extern::Memory(mem)) => mem,
+                _ => return Err(anyhow!("Failed to get memory export")),
+            };
+            
+            // Read the target name and payload from the module's memory
+            let data = memory.data(&caller);
+            let target = match data.get(name_ptr as usize..name_ptr as usize + name_len as usize) {
+                Some(name) => String::from_utf8(name.to_vec())
+                    .map_err(|e| anyhow!("Plugin name is not valid UTF-8: {}", e))?,
+                None => return Err(anyhow!("Invalid memory range")),
+            };
+            let payload: serde_json::Value = match data.get(payload_ptr as usize..payload_ptr as usize + payload_len as usize) {
+                Some(payload) => serde_json::from_slice(payload)
+                    .map_err(|e| anyhow!("Failed to parse payload as JSON: {}", e))?,
+                None => return Err(anyhow!("Invalid memory range")),
+            };
+            
+            // Run the target plugin for the same agent and intent
+            let state = caller.data();
+            let result = state.context.call(
+                &state.plugin_id,
+                &target,
+                &state.intent,
+                &payload,
+                &state.agent_id,
+                &state.state,
+            )?;
+            
+            // Keep the result for host.get_call_result
+            let result = serde_json::to_vec(&result)?;
+            let len = result.len();
+            caller.data_mut().call_result = result;
+            
+            Ok(len as u32)
+        })?;
+        
+        // Function to copy the result of the last plugin call into the module's memory
+        linker.func_wrap("host", "get_call_result", |mut caller: Caller<'_, PluginState>, ptr: u32| -> Result<u32, anyhow::Error> {
+            let memory = match caller.get_export("memory") {
+                Some(wasmtime::Extern::Memory(mem)) => mem,
+                _ => return Err(anyhow!("Failed to get memory export")),
+            };
+            
+            let result = std::mem::take(&mut caller.data_mut().call_result);
+            let len = result.len();
+            
+            let mem_slice = match memory.data_mut(&mut caller).get_mut(ptr as usize..ptr as usize + len) {
+                Some(slice) => slice,
+                None => return Err(anyhow!("Invalid memory range")),
+            };
+            mem_slice.copy_from_slice(&result);
+            
+            Ok(len as u32)
+        })?;
+        
         // Add more host functions as needed
         
         Ok(())
     }
+    
+    /// Define the network host functions, linked only for plugins with external_access
+    pub(crate) fn define_network_functions(linker: &mut Linker<PluginState>) -> Result<()> {
+        // Function to perform an HTTP request to an allowed URL, returning the response length
+        linker.func_wrap("host", "http_request", |mut caller: Caller<'_, PluginState>, method_ptr: u32, method_len: u32, url_ptr: u32, url_len: u32, body_ptr: u32, body_len: u32| -> Result<u32, anyhow::Error> {
+            let memory = match caller.get_export("memory") {
+                Some(wasmtime::Extern::Memory(mem)) => mem,
+                _ => return Err(anyhow!("Failed to get memory export")),
+            };
+            
+            // Read the request from the module's memory
+            let data = memory.data(&caller);
+            let method = String::from_utf8(guest_bytes(data, method_ptr, method_len)?.to_vec())
+                .map_err(|e| anyhow!("HTTP method is not valid UTF-8: {}", e))?;
+            let url = String::from_utf8(guest_bytes(data, url_ptr, url_len)?.to_vec())
+                .map_err(|e| anyhow!("URL is not valid UTF-8: {}", e))?;
+            let body = guest_bytes(data, body_ptr, body_len)?.to_vec();
+            
+            let state = caller.data();
+            let mut record = HttpRequestRecord {
+                plugin_id: state.plugin_id.clone(),
+                method: method.clone(),
+                host: url_host(&url).to_string(),
+                status: None,
+                error: None,
+            };
+            
+            // Refuse URLs outside the plugin's allowlist
+            if !is_url_allowed(&url, &state.allowed_hosts) {
+                let error = PluginError::HostNotAllowed {
+                    plugin_id: state.plugin_id.clone(),
+                    url,
+                };
+                record.error = Some(error.to_string());
+                state.context.record_http_request(record);
+                return Err(error.into());
+            }
+            
+            // Bound the request by the call chain's deadline and the plugin's memory budget
+            let timeout = HTTP_REQUEST_TIMEOUT.min(state.context.remaining());
+            let max_bytes = MAX_HTTP_RESPONSE_BYTES.min(state.limiter.memory_limit);
+            let outcome = send_http_request(&method, &url, &body, timeout, max_bytes);
+            match &outcome {
+                Ok((status, _)) => record.status = Some(*status),
+                Err(e) => record.error = Some(e.to_string()),
+            }
+            state.context.record_http_request(record);
+            let (status, body) = outcome?;
+            
+            // Keep the response for host.get_http_response
+            let response = serde_json::to_vec(&serde_json::json!({
+                "status": status,
+                "body": String::from_utf8_lossy(&body),
+            }))?;
+            let len = response.len();
+            caller.data_mut().http_response = response;
+            
+            Ok(len as u32)
+        })?;
+        
+        // Function to copy the last HTTP response into the module's memory
+        linker.func_wrap("host", "get_http_response", |mut caller: Caller<'_, PluginState>, ptr: u32| -> Result<u32, anyhow::Error> {
+            let memory = match caller.get_export("memory") {
+                Some(wasmtime::Extern::Memory(mem)) => mem,
+                _ => return Err(anyhow!("Failed to get memory export")),
+            };
+            
+            let response = std::mem::take(&mut caller.data_mut().http_response);
+            let len = response.len();
+            
+            let mem_slice = match memory.data_mut(&mut caller).get_mut(ptr as usize..ptr as usize + len) {
+                Some(slice) => slice,
+                None => return Err(anyhow!("Invalid memory range")),
+            };
+            mem_slice.copy_from_slice(&response);
+            
+            Ok(len as u32)
+        })?;
+        
+        Ok(())
+    }
+}
+
+/// Read a plugin's capabilities file, falling back to defaults when it does not exist
+fn read_capabilities(cap_path: &Path) -> Result<PluginCapabilities> {
+    if !cap_path.exists() {
+        return Ok(PluginCapabilities::default());
+    }
+    
+    let content = std::fs::read_to_string(cap_path)
+        .with_context(|| format!("Failed to read capabilities file: {}", cap_path.display()))?;
+    serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse capabilities file: {}", cap_path.display()))
+}
+
+/// Metadata for a plugin module, identified by the blake3 hash of its bytes
+fn plugin_metadata(plugin_id: &PluginId, wasm: &[u8]) -> PluginMetadata {
+    // Modules do not embed metadata yet, so only the name and hash are known
+    PluginMetadata {
+        name: plugin_id.clone(),
+        version: "1.0.0".to_string(),
+        author: "Unknown".to_string(),
+        description: "No description available".to_string(),
+        hash: Some(blake3::hash(wasm).to_hex().to_string()),
+        additional: HashMap::new(),
+    }
+}
+
+/// Decode a log message, truncated at a character boundary to at most `max_bytes`
+fn truncate_message(bytes: &[u8], max_bytes: usize) -> (String, bool) {
+    let mut message = String::from_utf8_lossy(bytes).into_owned();
+    if message.len() <= max_bytes {
+        return (message, false);
+    }
+    
+    let mut end = max_bytes;
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+    message.truncate(end);
+    (message, true)
+}
+
+/// Slice of guest memory, failing on out-of-bounds ranges
+fn guest_bytes(data: &[u8], ptr: u32, len: u32) -> Result<&[u8]> {
+    data.get(ptr as usize..ptr as usize + len as usize)
+        .ok_or_else(|| anyhow!("Invalid memory range"))
+}
+
+/// Whether `url` starts with one of the allowed prefixes at a path boundary
+fn is_url_allowed(url: &str, allowed_hosts: &[String]) -> bool {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return false;
+    }
+    
+    allowed_hosts.iter().any(|prefix| {
+        url.strip_prefix(prefix.as_str()).is_some_and(|rest| {
+            prefix.ends_with('/') || rest.is_empty() || rest.starts_with(['/', '?', '#'])
+        })
+    })
+}
+
+/// Authority part of a URL without credentials, e.g. `example.com:8080`
+fn url_host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    authority.rsplit('@').next().unwrap_or(authority)
+}
+
+/// Perform a blocking HTTP request, returning the status and at most `max_bytes` of body
+fn send_http_request(
+    method: &str,
+    url: &str,
+    body: &[u8],
+    timeout: Duration,
+    max_bytes: usize,
+) -> Result<(u16, Vec<u8>)> {
+    // Redirects are not followed so they cannot leave the allowlist
+    let agent = ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .redirects(0)
+        .build();
+    
+    let request = agent.request(method, url);
+    let response = if body.is_empty() {
+        request.call()
+    } else {
+        request.send_bytes(body)
+    };
+    let response = match response {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(e) => return Err(anyhow!("HTTP request to {} failed: {}", url_host(url), e)),
+    };
+    
+    let status = response.status();
+    let mut bytes = Vec::new();
+    response.into_reader()
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Failed to read HTTP response from {}", url_host(url)))?;
+    if bytes.len() > max_bytes {
+        return Err(anyhow!("HTTP response from {} exceeds {} bytes", url_host(url), max_bytes));
+    }
+    
+    Ok((status, bytes))
+}
+
+/// A plugin found in the plugin directory by [`PluginManager::discover`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredPlugin {
+    /// Plugin ID, taken from the module file name
+    pub id: PluginId,
+    
+    /// Plugin metadata
+    pub metadata: PluginMetadata,
+    
+    /// Declared capabilities, or None if the capabilities file is invalid
+    pub capabilities: Option<PluginCapabilities>,
+    
+    /// Why the plugin cannot be loaded, if its capabilities file is invalid
+    pub error: Option<String>,
+    
+    /// Whether the compiled module is currently resident
+    pub loaded: bool,
+}
+
+/// A plugin held in the PluginManager cache
+struct ResidentPlugin {
+    /// The loaded plugin
+    plugin: Arc<Plugin>,
+    
+    /// Value of the manager's use clock when the plugin was last requested
+    last_used: AtomicU64,
+    
+    /// Plugin files as they were when the module was (last attempted to be) compiled
+    stamp: Mutex<FileStamp>,
+}
+
+impl ResidentPlugin {
+    /// Whether the module or capabilities file changed since they were read
+    fn changed_on_disk(&self, plugin_dir: &Path) -> bool {
+        let current = FileStamp::read(plugin_dir, self.plugin.id());
+        self.stamp.lock().map(|stamp| *stamp != current).unwrap_or(true)
+    }
+}
+
+/// Modification time and size of a plugin's module and capabilities files
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    wasm: Option<(SystemTime, u64)>,
+    cap: Option<(SystemTime, u64)>,
+}
+
+impl FileStamp {
+    fn read(plugin_dir: &Path, plugin_id: &PluginId) -> Self {
+        let stamp = |path: PathBuf| {
+            let meta = std::fs::metadata(path).ok()?;
+            Some((meta.modified().ok()?, meta.len()))
+        };
+        
+        Self {
+            wasm: stamp(plugin_dir.join(format!("{}.wasm", plugin_id))),
+            cap: stamp(plugin_dir.join(format!("{}.cap.yaml", plugin_id))),
+        }
+    }
+}
+
+/// A hot reload of a plugin whose files changed on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginReload {
+    /// Reloaded plugin
+    pub plugin_id: PluginId,
+    
+    /// Hash of the module that was replaced
+    pub old_hash: Option<String>,
+    
+    /// Hash of the new module, or None if the reload failed
+    pub new_hash: Option<String>,
+    
+    /// Compile or load error that kept the old module in place
+    pub error: Option<String>,
+}
+
+/// Counters describing the PluginManager's module cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginCacheStats {
+    /// Lookups served from a resident module
+    pub hits: u64,
+    
+    /// Lookups that had to load and compile the module from disk
+    pub misses: u64,
+    
+    /// Modules dropped to stay within the resident limit
+    pub evictions: u64,
+    
+    /// Modules currently resident
+    pub resident: usize,
+}
+
+/// Execution totals of one plugin for one agent
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginStats {
+    /// Number of executions
+    pub executions: u64,
+    
+    /// Number of failed executions
+    pub errors: u64,
+    
+    /// Sum of execution durations in milliseconds
+    pub total_duration_ms: f64,
+    
+    /// Longest execution in milliseconds
+    pub max_duration_ms: f64,
+    
+    /// Sum of fuel consumed
+    pub fuel_consumed: u64,
+}
+
+impl PluginStats {
+    /// Add one execution to the totals
+    pub fn record(&mut self, execution: &PluginExecution) {
+        self.executions += 1;
+        if !execution.success {
+            self.errors += 1;
+        }
+        self.total_duration_ms += execution.duration_ms;
+        self.max_duration_ms = self.max_duration_ms.max(execution.duration_ms);
+        self.fuel_consumed += execution.fuel_consumed;
+    }
+}
+
+/// Per-agent plugin execution totals, keyed by agent ID and then plugin ID
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginStatsReport {
+    /// Totals of each plugin an agent executed, directly or through plugin calls
+    pub agents: BTreeMap<AgentId, BTreeMap<PluginId, PluginStats>>,
+}
+
+impl PluginStatsReport {
+    /// Add an agent's executions to the report
+    pub fn record(&mut self, agent_id: &AgentId, executions: &[PluginExecution]) {
+        if executions.is_empty() {
+            return;
+        }
+        let plugins = self.agents.entry(agent_id.clone()).or_default();
+        for execution in executions {
+            plugins.entry(execution.plugin_id.clone()).or_default().record(execution);
+        }
+    }
 }
 
 /// Plugin Manager for loading and managing plugins
@@ -303,35 +1698,206 @@ pub struct PluginManager {
     plugin_dir: PathBuf,
     
     /// Loaded plugins
-    plugins: Arc<RwLock<HashMap<PluginId, Arc<Plugin>>>>,
+    plugins: Arc<RwLock<HashMap<PluginId, ResidentPlugin>>>,
+    
+    /// Maximum number of resident compiled modules; least recently used ones are evicted
+    max_resident: Option<usize>,
+    
+    /// Monotonic counter ordering plugin uses for LRU eviction
+    clock: AtomicU64,
+    
+    /// Cache hit counter
+    hits: AtomicU64,
+    
+    /// Cache miss counter
+    misses: AtomicU64,
+    
+    /// Eviction counter
+    evictions: AtomicU64,
+    
+    /// Whether changed plugin files are recompiled on the next load
+    hot_reload: bool,
+    
+    /// On-disk cache of compiled modules
+    module_cache: Option<ModuleCache>,
+    
+    /// Reloads not yet collected with take_reloads
+    reloads: Mutex<Vec<PluginReload>>,
     
     /// WASM engine
     engine: Engine,
+    
+    /// Linker holding the host functions, shared by every plugin
+    linker: Linker<PluginState>,
+    
+    /// Linker that additionally resolves the network host functions
+    network_linker: Linker<PluginState>,
+    
+    /// Advances the engine epoch for execution timeouts
+    _ticker: EpochTicker,
 }
 
 impl PluginManager {
-    /// Create a new PluginManager
+    /// Create a new PluginManager that keeps every loaded plugin resident
     pub fn new<P: AsRef<Path>>(plugin_dir: P) -> Self {
+        Self::with_max_resident(plugin_dir, None)
+    }
+    
+    /// Create a new PluginManager holding at most `max_resident` compiled modules
+    ///
+    /// Evicted plugins are unloaded and transparently reloaded from disk on next use.
+    pub fn with_max_resident<P: AsRef<Path>>(plugin_dir: P, max_resident: Option<usize>) -> Self {
+        // Epoch interruption lets execution timeouts stop runaway modules,
+        // and fuel metering enforces each plugin's cpu_limit
+        let mut config = Config::new();
+        config.epoch_interruption(true);
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).unwrap_or_else(|e| {
+            tracing::error!("Failed to create WASM engine with epoch interruption: {}", e);
+            Engine::default()
+        });
+        
+        // Host functions are defined once and reused for every instantiation
+        let mut linker = Linker::new(&engine);
+        if let Err(e) = Plugin::define_host_functions(&mut linker) {
+            tracing::error!("Failed to define plugin host functions: {}", e);
+        }
+        
+        // Plugins without external_access cannot even import the network functions
+        let mut network_linker = linker.clone();
+        if let Err(e) = Plugin::define_network_functions(&mut network_linker) {
+            tracing::error!("Failed to define plugin network functions: {}", e);
+        }
+        
         Self {
             plugin_dir: plugin_dir.as_ref().to_path_buf(),
             plugins: Arc::new(RwLock::new(HashMap::new())),
-            engine: Engine::default(),
+            max_resident,
+            clock: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            hot_reload: false,
+            module_cache: None,
+            reloads: Mutex::new(Vec::new()),
+            _ticker: EpochTicker::start(engine.clone()),
+            linker,
+            network_linker,
+            engine,
         }
     }
     
+    /// Recompile plugins whose `.wasm` or `.cap.yaml` changed on disk when they are next loaded
+    ///
+    /// A failed reload keeps the previous module in use.
+    pub fn with_hot_reload(mut self, enabled: bool) -> Self {
+        self.hot_reload = enabled;
+        self
+    }
+    
+    /// Cache compiled modules under `<plugin_dir>/.cache`, bounded to `max_bytes` on disk
+    pub fn with_module_cache(mut self, max_bytes: u64) -> Self {
+        self.module_cache = Some(ModuleCache::new(self.plugin_dir.join(".cache"), max_bytes));
+        self
+    }
+    
     /// Load a plugin by ID
     pub fn load_plugin(&self, plugin_id: &PluginId) -> Result<Arc<Plugin>> {
-        // Check if plugin is already loaded
-        {
+        // Check if plugin is already loaded and, with hot reload, still current
+        let stale = {
             let plugins = self.plugins.read().map_err(|_| anyhow!("Failed to acquire read lock"))?;
-            if let Some(plugin) = plugins.get(plugin_id) {
-                return Ok(plugin.clone());
+            match plugins.get(plugin_id) {
+                Some(resident) if !(self.hot_reload && resident.changed_on_disk(&self.plugin_dir)) => {
+                    resident.last_used.store(self.tick(), Ordering::Relaxed);
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    metrics::increment_counter!("mcp.kernel.plugin_cache_hits");
+                    return Ok(resident.plugin.clone());
+                },
+                Some(resident) => Some(resident.plugin.clone()),
+                None => None,
+            }
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        metrics::increment_counter!("mcp.kernel.plugin_cache_misses");
+        
+        let stamp = FileStamp::read(&self.plugin_dir, plugin_id);
+        let plugin = match self.compile_plugin(plugin_id) {
+            Ok(plugin) => Arc::new(plugin),
+            Err(e) => {
+                let old = match stale {
+                    Some(old) => old,
+                    None => return Err(e),
+                };
+                
+                // Keep serving the old module, and don't retry until the files change again
+                tracing::error!("Failed to reload plugin {}: {:#}", plugin_id, e);
+                self.record_reload(PluginReload {
+                    plugin_id: plugin_id.clone(),
+                    old_hash: old.metadata().hash.clone(),
+                    new_hash: None,
+                    error: Some(format!("{:#}", e)),
+                });
+                let plugins = self.plugins.read().map_err(|_| anyhow!("Failed to acquire read lock"))?;
+                if let Some(resident) = plugins.get(plugin_id) {
+                    if let Ok(mut current) = resident.stamp.lock() {
+                        *current = stamp;
+                    }
+                }
+                return Ok(old);
+            }
+        };
+        
+        // Store plugin, unless another caller loaded (or reloaded) it in the meantime
+        let mut plugins = self.plugins.write().map_err(|_| anyhow!("Failed to acquire write lock"))?;
+        if let Some(resident) = plugins.get(plugin_id) {
+            if !stale.as_ref().is_some_and(|old| Arc::ptr_eq(old, &resident.plugin)) {
+                return Ok(resident.plugin.clone());
             }
         }
         
+        // Make room by evicting the least recently used modules
+        if let Some(max_resident) = self.max_resident {
+            while !plugins.contains_key(plugin_id) && !plugins.is_empty() && plugins.len() >= max_resident {
+                let lru = plugins.iter()
+                    .min_by_key(|(_, resident)| resident.last_used.load(Ordering::Relaxed))
+                    .map(|(id, _)| id.clone());
+                if let Some(evicted) = lru.and_then(|id| plugins.remove(&id)) {
+                    tracing::debug!("Evicting plugin {} from the module cache", evicted.plugin.id());
+                    evicted.plugin.unload();
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                    metrics::increment_counter!("mcp.kernel.plugin_cache_evictions");
+                }
+            }
+        }
+        
+        let replaced = plugins.insert(plugin_id.clone(), ResidentPlugin {
+            plugin: plugin.clone(),
+            last_used: AtomicU64::new(self.tick()),
+            stamp: Mutex::new(stamp),
+        });
+        metrics::gauge!("mcp.kernel.plugins_resident", plugins.len() as f64);
+        drop(plugins);
+        
+        // Holders of the old module see it unloaded and resolve the new one
+        if let Some(old) = replaced {
+            old.plugin.unload();
+            tracing::info!("Plugin reloaded: {}", plugin_id);
+            self.record_reload(PluginReload {
+                plugin_id: plugin_id.clone(),
+                old_hash: old.plugin.metadata().hash.clone(),
+                new_hash: plugin.metadata().hash.clone(),
+                error: None,
+            });
+        }
+        
+        Ok(plugin)
+    }
+    
+    /// Read, compile and link a plugin from the plugin directory
+    fn compile_plugin(&self, plugin_id: &PluginId) -> Result<Plugin> {
         // Construct plugin file path
         let plugin_path = self.plugin_dir.join(format!("{}.wasm", plugin_id));
-        let cap_path = self.plugin_dir.join(format!("{}.cap.yaml", plugin_id));
+        let cap_path = self.capabilities_path(plugin_id);
         
         // Verify the plugin exists
         if !plugin_path.exists() {
@@ -339,58 +1905,538 @@ impl PluginManager {
         }
         
         // Load capabilities
-        let capabilities = if cap_path.exists() {
-            match std::fs::read_to_string(&cap_path) {
-                Ok(content) => serde_yaml::from_str(&content)
-                    .with_context(|| format!("Failed to parse capabilities file: {}", cap_path.display()))?,
-                Err(e) => return Err(anyhow!("Failed to read capabilities file: {}", e)),
-            }
-        } else {
-            PluginCapabilities::default()
-        };
+        let capabilities = read_capabilities(&cap_path)?;
         
         // Load and compile the WASM module
-        let module = Module::from_file(&self.engine, &plugin_path)
+        let wasm = std::fs::read(&plugin_path)
+            .with_context(|| format!("Failed to read WASM module: {}", plugin_path.display()))?;
+        let metadata = plugin_metadata(plugin_id, &wasm);
+        let module = self.compile_module(&wasm, metadata.hash.as_deref().unwrap_or_default())
             .with_context(|| format!("Failed to load WASM module: {}", plugin_path.display()))?;
         
-        // Extract metadata from the module (placeholder for now)
-        let metadata = PluginMetadata {
-            name: plugin_id.clone(),
-            version: "1.0.0".to_string(),
-            author: "Unknown".to_string(),
-            description: "No description available".to_string(),
-            hash: None,
-            additional: HashMap::new(),
+        // Only plugins with external_access may link against the network functions
+        let linker = if capabilities.external_access {
+            &self.network_linker
+        } else {
+            &self.linker
         };
         
+        // WASI imports are only resolvable for plugins that declare them
+        if capabilities.wasi {
+            let mut linker = linker.clone();
+            wasi::add_to_linker(&mut linker)
+                .with_context(|| format!("Failed to link WASI for plugin {}", plugin_id))?;
+            return Plugin::new(plugin_id.clone(), capabilities, metadata, module, &linker);
+        }
+        
         // Create plugin instance
-        let plugin = Arc::new(Plugin::new(
+        Plugin::new(
             plugin_id.clone(),
             capabilities,
             metadata,
             module,
-        ));
+            linker,
+        )
+    }
+    
+    /// Compile a module, reusing a previously compiled copy from the module cache
+    fn compile_module(&self, wasm: &[u8], hash: &str) -> Result<Module> {
+        let cache = match &self.module_cache {
+            Some(cache) => cache,
+            None => return Module::new(&self.engine, wasm),
+        };
+        
+        if let Some(module) = cache.load(&self.engine, hash) {
+            return Ok(module);
+        }
         
-        // Store plugin
+        let module = Module::new(&self.engine, wasm)?;
+        if let Err(e) = cache.store(hash, &module) {
+            tracing::warn!("Failed to cache compiled module: {}", e);
+        }
+        Ok(module)
+    }
+    
+    /// Reloads (and failed reload attempts) since the last call
+    pub fn take_reloads(&self) -> Vec<PluginReload> {
+        self.reloads.lock().map(|mut reloads| std::mem::take(&mut *reloads)).unwrap_or_default()
+    }
+    
+    fn record_reload(&self, reload: PluginReload) {
+        if let Ok(mut reloads) = self.reloads.lock() {
+            reloads.push(reload);
+        }
+    }
+    
+    /// Unload a resident plugin, returning whether it was loaded
+    ///
+    /// Agents holding the plugin reload it from disk on their next execution.
+    pub fn unload_plugin(&self, plugin_id: &PluginId) -> Result<bool> {
         let mut plugins = self.plugins.write().map_err(|_| anyhow!("Failed to acquire write lock"))?;
-        plugins.insert(plugin_id.clone(), plugin.clone());
+        let removed = plugins.remove(plugin_id);
+        metrics::gauge!("mcp.kernel.plugins_resident", plugins.len() as f64);
         
-        Ok(plugin)
+        match removed {
+            Some(resident) => {
+                resident.plugin.unload();
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+    
+    /// Scan the plugin directory for installable plugins without compiling them
+    ///
+    /// A plugin whose capabilities file cannot be read or parsed is still listed,
+    /// with the error recorded instead of its capabilities.
+    pub fn discover(&self) -> Result<Vec<DiscoveredPlugin>> {
+        if !self.plugin_dir.exists() {
+            return Ok(Vec::new());
+        }
+        
+        let entries = std::fs::read_dir(&self.plugin_dir)
+            .with_context(|| format!("Failed to read plugin directory: {}", self.plugin_dir.display()))?;
+        let loaded = self.loaded_plugins();
+        
+        let mut discovered = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("wasm") {
+                continue;
+            }
+            let plugin_id: PluginId = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(stem) => stem.to_string(),
+                None => continue,
+            };
+            
+            let wasm = match std::fs::read(&path) {
+                Ok(wasm) => wasm,
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable plugin {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            
+            let cap_path = self.capabilities_path(&plugin_id);
+            let (capabilities, error) = match read_capabilities(&cap_path) {
+                Ok(capabilities) => (Some(capabilities), None),
+                Err(e) => (None, Some(format!("{:#}", e))),
+            };
+            
+            discovered.push(DiscoveredPlugin {
+                metadata: plugin_metadata(&plugin_id, &wasm),
+                loaded: loaded.contains(&plugin_id),
+                id: plugin_id,
+                capabilities,
+                error,
+            });
+        }
+        
+        discovered.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(discovered)
+    }
+    
+    /// Whether a plugin is resident or has a module in the plugin directory
+    ///
+    /// Neither loads nor compiles anything, unlike [`load_plugin`](Self::load_plugin).
+    pub fn is_available(&self, plugin_id: &PluginId) -> bool {
+        let resident = self.plugins.read()
+            .map(|plugins| plugins.contains_key(plugin_id))
+            .unwrap_or(false);
+        resident || self.plugin_dir.join(format!("{}.wasm", plugin_id)).is_file()
+    }
+    
+    /// Path of a plugin's capabilities file in the plugin directory
+    pub(crate) fn capabilities_path(&self, plugin_id: &PluginId) -> PathBuf {
+        self.plugin_dir.join(format!("{}.cap.yaml", plugin_id))
+    }
+    
+    /// Number of plugins whose compiled modules are currently resident
+    pub fn resident_count(&self) -> usize {
+        self.plugins.read().map(|plugins| plugins.len()).unwrap_or(0)
+    }
+    
+    /// IDs of the plugins whose compiled modules are currently resident
+    pub fn loaded_plugins(&self) -> Vec<PluginId> {
+        let mut ids: Vec<PluginId> = match self.plugins.read() {
+            Ok(plugins) => plugins.keys().cloned().collect(),
+            Err(_) => Vec::new(),
+        };
+        ids.sort();
+        ids
+    }
+    
+    /// Run `shutdown` for an agent's instance of a resident plugin
+    pub fn shutdown_instance(&self, agent_id: &AgentId, plugin_id: &PluginId) -> Result<()> {
+        let plugin = self.plugins.read()
+            .map_err(|_| anyhow!("Failed to acquire read lock"))?
+            .get(plugin_id)
+            .map(|resident| resident.plugin.clone());
+        match plugin {
+            Some(plugin) => plugin.shutdown_instance(agent_id),
+            None => Ok(()),
+        }
+    }
+    
+    /// Run `shutdown` for every instance held for an agent, returning the failures
+    pub fn shutdown_agent_instances(&self, agent_id: &AgentId) -> Vec<PluginShutdownFailure> {
+        self.resident_plugins().into_iter()
+            .filter_map(|plugin| {
+                let error = plugin.shutdown_instance(agent_id).err()?;
+                Some(PluginShutdownFailure {
+                    plugin_id: plugin.id().clone(),
+                    agent_id: agent_id.clone(),
+                    error: format!("{:#}", error),
+                })
+            })
+            .collect()
+    }
+    
+    /// Run `shutdown` for every instance of every resident plugin, returning the failures
+    pub fn shutdown_all_instances(&self) -> Vec<PluginShutdownFailure> {
+        self.resident_plugins().into_iter()
+            .flat_map(|plugin| plugin.shutdown_instances())
+            .collect()
+    }
+    
+    /// Resident plugins, collected so no lock is held while their code runs
+    fn resident_plugins(&self) -> Vec<Arc<Plugin>> {
+        match self.plugins.read() {
+            Ok(plugins) => plugins.values().map(|resident| resident.plugin.clone()).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+    
+    /// Snapshot of the module cache counters
+    pub fn cache_stats(&self) -> PluginCacheStats {
+        PluginCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            resident: self.plugins.read().map(|p| p.len()).unwrap_or(0),
+        }
+    }
+    
+    /// Advance the use clock
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// Background thread advancing an engine's epoch every EPOCH_TICK
+struct EpochTicker {
+    /// Signals the thread to stop
+    stop: Arc<AtomicBool>,
+    
+    /// Ticker thread handle
+    handle: Option<JoinHandle<()>>,
+}
+
+impl EpochTicker {
+    /// Start ticking the given engine
+    fn start(engine: Engine) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let handle = std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                std::thread::sleep(EPOCH_TICK);
+                engine.increment_epoch();
+            }
+        });
+        
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
     }
 }
 
 /// Plugin state for WASM execution
 #[derive(Debug)]
-struct PluginState {
+pub(crate) struct PluginState {
     /// Agent ID
     agent_id: AgentId,
     
     /// Intent being executed
     intent: String,
     
+    /// Intent params serialized as JSON
+    params: Vec<u8>,
+    
+    /// Agent-specific plugin config serialized as JSON
+    config: Vec<u8>,
+    
     /// Agent state
-    state: HashMap<String, serde_json::Value>,
+    state: Arc<HashMap<String, serde_json::Value>>,
     
     /// Execution result
     result: Option<serde_json::Value>,
+    
+    /// Enforces the plugin's memory_limit
+    limiter: PluginLimiter,
+    
+    /// ID of the executing plugin
+    plugin_id: PluginId,
+    
+    /// Whether the plugin may call other plugins
+    plugin_call: bool,
+    
+    /// Call chain this execution belongs to
+    context: CallContext,
+    
+    /// Result of the last host.call_plugin, until fetched
+    call_result: Vec<u8>,
+    
+    /// URL prefixes the plugin may request
+    allowed_hosts: Vec<String>,
+    
+    /// Most verbose level of messages forwarded from host.log
+    log_level: tracing::Level,
+    
+    /// Response of the last host.http_request, until fetched
+    http_response: Vec<u8>,
+    
+    /// WASI context, only for plugins with the wasi capability
+    #[cfg_attr(not(feature = "wasi"), allow(dead_code))]
+    pub(crate) wasi: Option<WasiContext>,
+}
+
+/// Caps a plugin's linear memory at its declared `memory_limit`
+#[derive(Debug)]
+struct PluginLimiter {
+    /// Maximum linear memory size in bytes
+    memory_limit: usize,
+    
+    /// Set once the module tried to grow past the limit
+    memory_exceeded: bool,
+}
+
+impl PluginLimiter {
+    fn new(capabilities: &PluginCapabilities) -> Self {
+        Self {
+            memory_limit: capabilities.memory_limit as usize * 1024 * 1024,
+            memory_exceeded: false,
+        }
+    }
+}
+
+impl ResourceLimiter for PluginLimiter {
+    fn memory_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> Result<bool> {
+        if desired > self.memory_limit {
+            self.memory_exceeded = true;
+            anyhow::bail!("memory growth to {} bytes exceeds limit of {} bytes", desired, self.memory_limit);
+        }
+        Ok(true)
+    }
+    
+    fn table_growing(&mut self, _current: u32, _desired: u32, _maximum: Option<u32>) -> Result<bool> {
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const ECHO_PLUGIN: &str = include_str!("../tests/fixtures/echo_params.wat");
+    const CALL_ECHO_PLUGIN: &str = include_str!("../tests/fixtures/call_echo.wat");
+    const ECHO_DIRECT_PLUGIN: &str = include_str!("../tests/fixtures/echo_direct.wat");
+    const WASI_HELLO_PLUGIN: &str = include_str!("../tests/fixtures/wasi_hello.wat");
+    const LOG_LEVELS_PLUGIN: &str = include_str!("../tests/fixtures/log_levels.wat");
+    
+    #[test]
+    fn test_execute_abi_conventions() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("echo.wasm"), ECHO_PLUGIN).unwrap();
+        std::fs::write(dir.path().join("echo_direct.wasm"), ECHO_DIRECT_PLUGIN).unwrap();
+        std::fs::write(dir.path().join("bad_signature.wasm"), "(module (func (export \"execute\") (param i32)))").unwrap();
+        let manager = PluginManager::new(dir.path());
+        let params = serde_json::json!({"message": "hello"});
+        let context = CallContext::detached(DEFAULT_EXECUTION_TIMEOUT);
+        
+        // Both conventions produce the same result
+        for id in ["echo", "echo_direct"] {
+            let plugin = manager.load_plugin(&id.to_string()).unwrap();
+            let result = plugin.execute("echo", &params, &"agent".to_string(), &Arc::default(), &context).unwrap();
+            assert_eq!(result, params, "{}", id);
+        }
+        
+        let plugin = manager.load_plugin(&"bad_signature".to_string()).unwrap();
+        let err = plugin.execute("echo", &params, &"agent".to_string(), &Arc::default(), &context).unwrap_err();
+        assert!(err.to_string().contains("unsupported signature"), "{}", err);
+    }
+    
+    #[test]
+    fn test_wasi_capability() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("hello.wasm"), WASI_HELLO_PLUGIN).unwrap();
+        std::fs::write(dir.path().join("undeclared.wasm"), WASI_HELLO_PLUGIN).unwrap();
+        std::fs::write(dir.path().join("hello.cap.yaml"), "wasi: true").unwrap();
+        let manager = PluginManager::new(dir.path());
+        
+        // Without the capability the WASI imports don't resolve
+        assert!(manager.load_plugin(&"undeclared".to_string()).is_err());
+        
+        #[cfg(feature = "wasi")]
+        {
+            let context = CallContext::detached(Duration::from_secs(5));
+            let plugin = manager.load_plugin(&"hello".to_string()).unwrap();
+            let result = plugin.execute("hello", &serde_json::Value::Null, &"agent".to_string(), &Arc::default(), &context).unwrap();
+            assert_eq!(result, serde_json::json!("ok"));
+            
+            let outputs = context.outputs();
+            assert_eq!(outputs.len(), 1);
+            assert_eq!(outputs[0].stdout, "hello\n");
+            assert!(!outputs[0].truncated);
+        }
+        
+        #[cfg(not(feature = "wasi"))]
+        {
+            let err = manager.load_plugin(&"hello".to_string()).unwrap_err();
+            assert!(format!("{:#}", err).contains("`wasi` feature"), "{:#}", err);
+        }
+    }
+    
+    #[test]
+    fn test_plugin_log() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("logger.wasm"), LOG_LEVELS_PLUGIN).unwrap();
+        let manager = PluginManager::new(dir.path());
+        let plugin = manager.load_plugin(&"logger".to_string()).unwrap();
+        
+        // The default info cap drops the debug message; the error message is cut inside the multibyte char
+        let context = CallContext::detached(Duration::from_secs(5)).with_max_log_bytes(9);
+        plugin.execute("run", &serde_json::Value::Null, &"agent".to_string(), &Arc::default(), &context).unwrap();
+        let logs: Vec<(String, String, bool)> = context.logs().into_iter()
+            .map(|line| (line.level, line.message, line.truncated))
+            .collect();
+        assert_eq!(logs, vec![
+            ("info".to_string(), "starting ".to_string(), true),
+            ("error".to_string(), "failed ".to_string(), true),
+        ]);
+        
+        let capabilities: PluginCapabilities = serde_yaml::from_str("log_level: error").unwrap();
+        assert_eq!(capabilities.log_level, tracing::Level::ERROR);
+        assert!(serde_yaml::from_str::<PluginCapabilities>("log_level: loud").is_err());
+    }
+    
+    #[test]
+    fn test_redact_config() {
+        let capabilities = PluginCapabilities {
+            sensitive_keys: vec!["api_key".to_string()],
+            ..Default::default()
+        };
+        let config = serde_json::json!({
+            "api_key": "secret",
+            "endpoints": [{"url": "https://example.com", "api_key": "nested"}],
+        });
+        assert_eq!(capabilities.redact_config(&config), serde_json::json!({
+            "api_key": "[REDACTED]",
+            "endpoints": [{"url": "https://example.com", "api_key": "[REDACTED]"}],
+        }));
+    }
+    
+    #[test]
+    fn test_call_plugin_is_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("echo.wasm"), ECHO_PLUGIN).unwrap();
+        std::fs::write(dir.path().join("caller.wasm"), CALL_ECHO_PLUGIN).unwrap();
+        std::fs::write(dir.path().join("caller.cap.yaml"), "plugin_call: true\n").unwrap();
+        let manager = Arc::new(PluginManager::new(dir.path()));
+        let caller = manager.load_plugin(&"caller".to_string()).unwrap();
+        let echo_id = "echo".to_string();
+        
+        let context = CallContext::new(&manager, DEFAULT_MAX_CALL_DEPTH, DEFAULT_EXECUTION_TIMEOUT)
+            .with_attached_plugins(HashMap::from([(echo_id.clone(), Arc::new(Plugin::placeholder(&echo_id)))]));
+        let result = caller.execute("call", &serde_json::Value::Null, &"agent".to_string(), &Arc::default(), &context).unwrap();
+        assert_eq!(result, serde_json::json!({"from": "caller"}));
+        
+        let calls = context.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!((calls[0].caller.as_str(), calls[0].target.as_str(), calls[0].depth), ("caller", "echo", 1));
+        assert!(calls[0].success);
+        
+        // Plugins in the directory but not attached to the agent cannot be called
+        let unattached = CallContext::new(&manager, DEFAULT_MAX_CALL_DEPTH, DEFAULT_EXECUTION_TIMEOUT);
+        let error = caller.execute("call", &serde_json::Value::Null, &"agent".to_string(), &Arc::default(), &unattached).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<PluginError>(),
+            Some(PluginError::CapabilityDenied { capability: "plugin_call", .. })
+        ), "{:#}", error);
+        
+        // Without a PluginManager the call fails
+        let detached = CallContext::detached(DEFAULT_EXECUTION_TIMEOUT);
+        assert!(caller.execute("call", &serde_json::Value::Null, &"agent".to_string(), &Arc::default(), &detached).is_err());
+        assert!(!detached.calls()[0].success);
+    }
+    
+    /// Compares per-call setup cost of a fresh engine and linker against the shared runtime.
+    ///
+    /// It builds stores from crate internals, so it runs as an ignored test rather than
+    /// from `benches/`. Run with
+    /// `cargo test -p mcp-kernel --release -- --ignored --nocapture bench_instantiation_overhead`.
+    #[test]
+    #[ignore]
+    fn bench_instantiation_overhead() {
+        const ITERATIONS: u32 = 200;
+        
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("echo.wasm"), ECHO_PLUGIN).unwrap();
+        let manager = PluginManager::new(dir.path());
+        let plugin = manager.load_plugin(&"echo".to_string()).unwrap();
+        let state: Arc<HashMap<String, serde_json::Value>> = Arc::default();
+        let params = serde_json::json!({"bench": true});
+        
+        // Before: every call built its own engine and linker (and had to recompile
+        // the module for that engine to instantiate it at all)
+        let started = Instant::now();
+        for _ in 0..ITERATIONS {
+            let engine = Engine::default();
+            let mut linker = Linker::new(&engine);
+            Plugin::define_host_functions(&mut linker).unwrap();
+            let module = Module::new(&engine, ECHO_PLUGIN).unwrap();
+            let mut store = Store::new(&engine, PluginState {
+                agent_id: "bench".to_string(),
+                intent: "echo".to_string(),
+                params: serde_json::to_vec(&params).unwrap(),
+                config: b"null".to_vec(),
+                state: state.clone(),
+                result: None,
+                limiter: PluginLimiter::new(&PluginCapabilities::default()),
+                plugin_id: "bench".to_string(),
+                plugin_call: false,
+                context: CallContext::detached(DEFAULT_EXECUTION_TIMEOUT),
+                call_result: Vec::new(),
+                allowed_hosts: Vec::new(),
+                log_level: tracing::Level::INFO,
+                http_response: Vec::new(),
+                wasi: None,
+            });
+            store.limiter(|state| &mut state.limiter);
+            let instance = linker.instantiate(&mut store, &module).unwrap();
+            let execute = instance.get_func(&mut store, "execute").unwrap();
+            execute.call(&mut store, &[], &mut []).unwrap();
+        }
+        let before = started.elapsed() / ITERATIONS;
+        
+        // After: one engine, host functions linked once, a fresh Store per call
+        let started = Instant::now();
+        for _ in 0..ITERATIONS {
+            let context = CallContext::detached(DEFAULT_EXECUTION_TIMEOUT);
+            plugin.execute("echo", &params, &"bench".to_string(), &state, &context).unwrap();
+        }
+        let after = started.elapsed() / ITERATIONS;
+        
+        println!("per-call overhead: fresh engine {:?}, shared runtime {:?}", before, after);
+        assert!(after < before);
+    }
 }
@@ -0,0 +1,100 @@
+//! Ethics policy reloading for MCP-ZERO kernel
+//!
+//! The kernel can replace its ethical decision tree with the contents of the configured
+//! policy file without restarting: on request, or from a kernel-owned thread that polls
+//! the file and reloads it when it changes. Evaluations under way finish against the
+//! old policy, and a policy that fails to load leaves the old one in force.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use anyhow::Result;
+
+use crate::ethical::EthicalBinaryTree;
+use crate::trace::Tracer;
+use crate::worker::BackgroundWorker;
+use crate::KERNEL_TRACE_AGENT;
+
+/// Event type of the trace entries recording successful reloads
+pub const POLICY_RELOADED_EVENT: &str = "ethics.policy_reloaded";
+
+/// Event type of the trace entries recording failed reloads
+pub const POLICY_RELOAD_FAILED_EVENT: &str = "ethics.policy_reload_failed";
+
+/// Kernel components a policy reload works with
+pub(crate) struct PolicyReloadContext {
+    pub(crate) engine: Arc<EthicalBinaryTree>,
+    pub(crate) tracer: Arc<dyn Tracer>,
+    pub(crate) path: PathBuf,
+}
+
+impl PolicyReloadContext {
+    /// Reload the policy file, returning the new version; the outcome is traced in the
+    /// kernel's general trace either way
+    pub(crate) fn reload(&self) -> Result<String> {
+        let previous = self.engine.policy_version();
+        let result = self.engine.reload(&self.path);
+        let (event_type, mut event) = match &result {
+            Ok(version) => {
+                tracing::info!("Reloaded ethics policy {}: version {} replaces {}", self.path.display(), version, previous);
+                (POLICY_RELOADED_EVENT, serde_json::json!({
+                    "path": self.path,
+                    "previous_version": previous,
+                    "version": version,
+                }))
+            },
+            Err(e) => {
+                tracing::error!("Keeping ethics policy version {}: {:#}", previous, e);
+                (POLICY_RELOAD_FAILED_EVENT, serde_json::json!({
+                    "path": self.path,
+                    "version": previous,
+                    "error": format!("{:#}", e),
+                }))
+            },
+        };
+        event["timestamp"] = serde_json::json!(chrono::Utc::now().timestamp());
+        if let Err(e) = self.tracer.record_event(&KERNEL_TRACE_AGENT.to_string(), event_type, &event) {
+            tracing::warn!("Failed to trace ethics policy reload: {}", e);
+        }
+        result
+    }
+    
+    /// Modification time and size of the policy file, if it can be read
+    fn stamp(&self) -> Option<(SystemTime, u64)> {
+        let metadata = std::fs::metadata(&self.path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+}
+
+/// Reloads the ethics policy from a background thread whenever the file changes
+#[derive(Debug, Default)]
+pub(crate) struct PolicyWatcher {
+    worker: BackgroundWorker,
+}
+
+impl PolicyWatcher {
+    /// Start the watching thread, checking the file every `interval`
+    ///
+    /// A change is seen as a new modification time or size. The file as it is when
+    /// the thread starts is taken to be the policy already loaded.
+    pub(crate) fn start(&self, context: PolicyReloadContext, interval: Duration) {
+        self.worker.start("mcp-policy-watch", move |signal| {
+            let mut seen = context.stamp();
+            while !signal.wait(interval) {
+                let stamp = context.stamp();
+                if stamp.is_none() || stamp == seen {
+                    continue;
+                }
+                seen = stamp;
+                
+                // Failures are logged and traced by the reload, leaving the old policy
+                let _ = context.reload();
+            }
+        });
+    }
+    
+    /// Stop the thread and wait for a reload in progress to finish
+    pub(crate) fn stop(&self) {
+        self.worker.stop();
+    }
+}
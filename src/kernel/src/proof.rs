@@ -0,0 +1,251 @@
+//! Merkle proofs of MCP-ZERO traces
+//!
+//! `PoseidonTracer::export_zk_proof` commits to a trace with a Merkle tree over its
+//! entry hashes. The exported proof carries the root and, for every entry, the
+//! sibling hashes leading from its leaf to the root, so [`verify_zk_proof`] can check
+//! a trace, or any of its entries, without the tracer that recorded it.
+//!
+//! Leaves and inner nodes are hashed with distinct prefixes so neither can pass for
+//! the other. A node without a sibling on its level is carried up unchanged rather
+//! than paired with itself, so no two entry lists share a root.
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sha3::{Digest, Sha3_256};
+
+use crate::agent::AgentId;
+use crate::trace::{self, TraceEntry, TraceId};
+
+/// Tree layout written in exported proofs
+const ALGORITHM: &str = "sha3-256-merkle-v1";
+
+/// Proof that a list of entries makes up a trace, as exported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TraceProof {
+    trace_id: TraceId,
+    agent_id: AgentId,
+    algorithm: String,
+    
+    /// Number of entries in the trace
+    entries: usize,
+    
+    /// Merkle root over the entry hashes
+    root_hash: String,
+    
+    /// Hash of the trace's last entry, the head of its hash chain
+    last_hash: String,
+    
+    /// Export timestamp
+    timestamp: i64,
+    
+    /// Path from each entry's leaf to the root, in entry order
+    inclusion_proofs: Vec<InclusionProof>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InclusionProof {
+    /// Position of the entry in the trace
+    index: usize,
+    
+    /// Hash of the entry the path starts from
+    entry_hash: String,
+    
+    /// Sibling hashes from the leaf's level up
+    path: Vec<ProofStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProofStep {
+    hash: String,
+    side: Side,
+}
+
+/// Which side of the running hash a sibling sits on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Side {
+    Left,
+    Right,
+}
+
+fn leaf_hash(entry_hash: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update(b"\x00");
+    hasher.update(entry_hash.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn node_hash(left: &str, right: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update(b"\x01");
+    hasher.update(left.as_bytes());
+    hasher.update(b":");
+    hasher.update(right.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Levels of the tree over `leaves`, leaves first and the root level last
+fn tree_levels(leaves: Vec<String>) -> Vec<Vec<String>> {
+    let mut levels = vec![leaves];
+    while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+        let parents = level.chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node_hash(left, right),
+                [carried] => carried.clone(),
+                _ => unreachable!("chunks of two"),
+            })
+            .collect();
+        levels.push(parents);
+    }
+    levels
+}
+
+/// Sibling hashes from leaf `index` to the root
+fn inclusion_path(levels: &[Vec<String>], mut index: usize) -> Vec<ProofStep> {
+    let mut path = Vec::new();
+    for level in &levels[..levels.len() - 1] {
+        let sibling = index ^ 1;
+        if let Some(hash) = level.get(sibling) {
+            let side = if sibling < index { Side::Left } else { Side::Right };
+            path.push(ProofStep { hash: hash.clone(), side });
+        }
+        index /= 2;
+    }
+    path
+}
+
+/// Fold a path onto an entry's leaf, giving the root it leads to
+fn root_from_path(entry_hash: &str, path: &[ProofStep]) -> String {
+    path.iter().fold(leaf_hash(entry_hash), |hash, step| match step.side {
+        Side::Left => node_hash(&step.hash, &hash),
+        Side::Right => node_hash(&hash, &step.hash),
+    })
+}
+
+/// Export a proof of a trace from its entries, in chain order
+pub(crate) fn export(trace_id: &TraceId, entries: &[&TraceEntry]) -> Option<Value> {
+    let first = entries.first()?;
+    let levels = tree_levels(entries.iter().map(|entry| leaf_hash(&entry.hash)).collect());
+    let proof = TraceProof {
+        trace_id: trace_id.clone(),
+        agent_id: first.agent_id.clone(),
+        algorithm: ALGORITHM.to_string(),
+        entries: entries.len(),
+        root_hash: levels.last()?.first()?.clone(),
+        last_hash: entries.last()?.hash.clone(),
+        timestamp: chrono::Utc::now().timestamp(),
+        inclusion_proofs: entries.iter().enumerate()
+            .map(|(index, entry)| InclusionProof {
+                index,
+                entry_hash: entry.hash.clone(),
+                path: inclusion_path(&levels, index),
+            })
+            .collect(),
+    };
+    serde_json::to_value(proof).ok()
+}
+
+/// Check trace entries against a proof exported by `PoseidonTracer::export_zk_proof`
+///
+/// Every entry must belong to the proven trace, hash to its recorded hash, and lead
+/// to the proof's root along its inclusion path. Given the whole trace, its hash
+/// chain must also verify and end at the proof's last hash; given some of its
+/// entries, each is checked on its own.
+pub fn verify_zk_proof(proof: &Value, entries: &[TraceEntry]) -> bool {
+    let Ok(proof) = TraceProof::deserialize(proof) else {
+        return false;
+    };
+    if proof.algorithm != ALGORITHM || entries.is_empty() || proof.inclusion_proofs.len() != proof.entries {
+        return false;
+    }
+    
+    let included = entries.iter().all(|entry| {
+        entry.id == proof.trace_id
+            && entry.agent_id == proof.agent_id
+            && trace::entry_hash(entry).as_ref() == Some(&entry.hash)
+            && proof.inclusion_proofs.iter()
+                .filter(|inclusion| inclusion.entry_hash == entry.hash)
+                .any(|inclusion| root_from_path(&entry.hash, &inclusion.path) == proof.root_hash)
+    });
+    if !included {
+        return false;
+    }
+    
+    if entries.len() == proof.entries {
+        let chain_valid = trace::verify_entries(&proof.trace_id, entries)
+            .is_some_and(|report| report.is_valid());
+        let leaves: Vec<String> = entries.iter().map(|entry| leaf_hash(&entry.hash)).collect();
+        let root = tree_levels(leaves).pop().and_then(|mut level| level.pop());
+        return chain_valid
+            && entries.last().is_some_and(|entry| entry.hash == proof.last_hash)
+            && root.as_ref() == Some(&proof.root_hash);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::{PoseidonTracer, Tracer};
+    
+    /// A proof of a trace with `steps` events between its begin and end, and its entries
+    fn proven_trace(steps: usize) -> (Value, Vec<TraceEntry>) {
+        let tracer = PoseidonTracer::new();
+        let agent_id = "agent_1".to_string();
+        let trace_id = tracer.begin_trace(&agent_id, "prove").unwrap();
+        for step in 0..steps {
+            tracer.record_trace_event(&trace_id, "step", &serde_json::json!({"step": step})).unwrap();
+        }
+        tracer.end_trace(&trace_id, true, None).unwrap();
+        
+        let filter = trace::TraceFilter { trace_id: Some(trace_id.clone()), ..Default::default() };
+        (tracer.export_zk_proof(&trace_id).unwrap(), tracer.query(&filter))
+    }
+    
+    #[test]
+    fn test_proof_round_trip() {
+        // Even and odd leaf counts, including a level with a carried node
+        for steps in [0, 1, 3, 4] {
+            let (proof, entries) = proven_trace(steps);
+            assert_eq!(proof["entries"], entries.len());
+            assert!(verify_zk_proof(&proof, &entries), "{} entries", entries.len());
+            
+            // Any single entry verifies against the root on its own
+            for entry in &entries {
+                assert!(verify_zk_proof(&proof, std::slice::from_ref(entry)));
+            }
+        }
+        
+        // A single-entry trace's root is its leaf
+        let entry = proven_trace(0).1.remove(0);
+        let proof = export(&entry.id, &[&entry]).unwrap();
+        assert_eq!(proof["root_hash"], leaf_hash(&entry.hash));
+        assert!(verify_zk_proof(&proof, &[entry]));
+    }
+    
+    #[test]
+    fn test_proof_rejects_forgery() {
+        let (proof, entries) = proven_trace(3);
+        
+        // Edited data no longer hashes to the entry's hash
+        let mut edited = entries.clone();
+        edited[2].data = serde_json::json!({"step": 7});
+        assert!(!verify_zk_proof(&proof, &edited));
+        assert!(!verify_zk_proof(&proof, &edited[2..3]));
+        
+        // A consistently rehashed forgery isn't under the root
+        let mut forged = entries[2].clone();
+        forged.data = serde_json::json!({"step": 7});
+        forged.hash = trace::entry_hash(&forged).unwrap();
+        assert!(!verify_zk_proof(&proof, &[forged]));
+        
+        // The whole trace must also be in chain order
+        let mut reordered = entries.clone();
+        reordered.swap(1, 2);
+        assert!(!verify_zk_proof(&proof, &reordered));
+        
+        // And entries only verify against their own trace's proof
+        let (other_proof, _) = proven_trace(3);
+        assert!(!verify_zk_proof(&other_proof, &entries));
+    }
+}
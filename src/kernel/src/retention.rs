@@ -0,0 +1,73 @@
+//! Trace maintenance for MCP-ZERO kernel
+//!
+//! A kernel-owned thread periodically closes traces left active past their maximum
+//! age, anchors the heads of active traces when a signing key is configured, flushes
+//! the tracer's in-memory buffer to storage, so bounded buffers don't evict entries
+//! that were never written, and prunes stored entries older than the configured
+//! retention. Entries of traces still being recorded are never pruned, so an active
+//! trace's hash chain stays verifiable.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+
+use crate::storage::StorageBackend;
+use crate::trace::Tracer;
+use crate::worker::BackgroundWorker;
+
+/// Kernel components the maintenance thread works with
+pub(crate) struct TraceMaintenanceContext {
+    pub(crate) tracer: Arc<dyn Tracer>,
+    pub(crate) storage: Arc<dyn StorageBackend>,
+    
+    /// Days stored entries are kept (0 keeps them forever)
+    pub(crate) retention_days: u64,
+}
+
+impl TraceMaintenanceContext {
+    /// Close stale traces, anchor active ones and flush buffered entries, then prune
+    /// expired ones; returns how many were pruned
+    pub(crate) fn run(&self) -> Result<usize> {
+        self.tracer.close_stale_traces()?;
+        self.tracer.anchor_traces()?;
+        self.tracer.flush(self.storage.as_ref())?;
+        if self.retention_days == 0 {
+            return Ok(0);
+        }
+        
+        let retention = i64::try_from(self.retention_days.saturating_mul(86_400)).unwrap_or(i64::MAX);
+        let before = chrono::Utc::now().timestamp().saturating_sub(retention);
+        let keep: HashSet<_> = self.tracer.active_trace_ids().into_iter().collect();
+        let pruned = self.storage.prune_traces(before, &keep)?;
+        if pruned > 0 {
+            tracing::info!("Pruned {} trace entries older than {} days", pruned, self.retention_days);
+        }
+        metrics::counter!("mcp.trace.pruned_entries", pruned as u64);
+        Ok(pruned)
+    }
+}
+
+/// Periodic trace flushing and pruning on a background thread
+#[derive(Debug, Default)]
+pub(crate) struct TraceMaintenance {
+    worker: BackgroundWorker,
+}
+
+impl TraceMaintenance {
+    /// Start the maintenance thread, running a round every `interval`
+    pub(crate) fn start(&self, context: TraceMaintenanceContext, interval: Duration) {
+        self.worker.start("mcp-trace-maintenance", move |signal| {
+            while !signal.wait(interval) {
+                if let Err(e) = context.run() {
+                    tracing::warn!("Trace maintenance failed: {:#}", e);
+                }
+            }
+        });
+    }
+    
+    /// Stop the thread and wait for a round in progress to finish
+    pub(crate) fn stop(&self) {
+        self.worker.stop();
+    }
+}
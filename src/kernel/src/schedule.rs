@@ -0,0 +1,212 @@
+//! Recurring intent execution for MCP-ZERO kernel
+//!
+//! Schedules are kept with their agent so they survive snapshot and recover. A single
+//! scheduler thread wakes when the next schedule is due and hands it to a dispatch
+//! callback; ticks missed while a dispatch was impossible are skipped, not queued.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+
+use crate::agent::AgentId;
+
+/// Schedule ID type
+pub type ScheduleId = String;
+
+/// An intent executed periodically on an agent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schedule {
+    /// Schedule ID
+    pub id: ScheduleId,
+    
+    /// Agent executing the intent
+    pub agent_id: AgentId,
+    
+    /// Intent to execute
+    pub intent: String,
+    
+    /// Interval between executions in milliseconds
+    pub every_ms: u64,
+    
+    /// Creation timestamp
+    pub created_at: i64,
+}
+
+impl Schedule {
+    /// Create a schedule with a fresh ID
+    pub(crate) fn new(agent_id: &AgentId, intent: &str, every: Duration) -> Self {
+        let now = chrono::Utc::now();
+        let seed = format!("{}:{}:{}", agent_id, intent, now.timestamp_nanos_opt().unwrap_or_default());
+        let hash = blake3::hash(seed.as_bytes());
+        
+        Self {
+            id: format!("schedule_{}", hash.to_hex().chars().take(16).collect::<String>()),
+            agent_id: agent_id.clone(),
+            intent: intent.to_string(),
+            every_ms: every.as_millis().max(1) as u64,
+            created_at: now.timestamp(),
+        }
+    }
+    
+    fn every(&self) -> Duration {
+        Duration::from_millis(self.every_ms)
+    }
+}
+
+#[derive(Debug, Default)]
+struct SchedulerState {
+    /// Active schedules with the time they are next due
+    schedules: HashMap<ScheduleId, (Schedule, Instant)>,
+    
+    /// Whether the thread has been started
+    started: bool,
+    
+    /// Whether the thread should exit
+    stopped: bool,
+}
+
+/// Table of active schedules driven by one background thread
+#[derive(Debug, Default)]
+pub(crate) struct Scheduler {
+    state: Arc<(Mutex<SchedulerState>, Condvar)>,
+}
+
+impl Scheduler {
+    /// Activate a schedule; its first run is one interval from now
+    pub(crate) fn add(&self, schedule: Schedule) {
+        let (lock, wake) = &*self.state;
+        let next_due = Instant::now() + schedule.every();
+        lock_state(lock).schedules.insert(schedule.id.clone(), (schedule, next_due));
+        wake.notify_one();
+    }
+    
+    /// Deactivate a schedule, returning it if it was active
+    pub(crate) fn remove(&self, schedule_id: &ScheduleId) -> Option<Schedule> {
+        let (lock, _) = &*self.state;
+        lock_state(lock).schedules.remove(schedule_id).map(|(schedule, _)| schedule)
+    }
+    
+    /// Deactivate every schedule of an agent
+    pub(crate) fn remove_agent(&self, agent_id: &AgentId) {
+        let (lock, _) = &*self.state;
+        lock_state(lock).schedules.retain(|_, (schedule, _)| &schedule.agent_id != agent_id);
+    }
+    
+    /// Active schedules, optionally of one agent, ordered by ID
+    pub(crate) fn list(&self, agent_id: Option<&AgentId>) -> Vec<Schedule> {
+        let (lock, _) = &*self.state;
+        let mut schedules: Vec<Schedule> = lock_state(lock).schedules.values()
+            .filter(|(schedule, _)| agent_id.is_none_or(|agent_id| &schedule.agent_id == agent_id))
+            .map(|(schedule, _)| schedule.clone())
+            .collect();
+        schedules.sort_by(|a, b| a.id.cmp(&b.id));
+        schedules
+    }
+    
+    /// Start the scheduler thread unless it is already running
+    ///
+    /// `dispatch` runs each due schedule and returns false once the schedules can no
+    /// longer run (e.g. the kernel is gone), which stops the thread.
+    pub(crate) fn start(&self, dispatch: impl Fn(&Schedule) -> bool + Send + 'static) {
+        let (lock, _) = &*self.state;
+        {
+            let mut state = lock_state(lock);
+            if state.started || state.stopped {
+                return;
+            }
+            state.started = true;
+        }
+        
+        let state = self.state.clone();
+        let spawned = std::thread::Builder::new()
+            .name("mcp-scheduler".to_string())
+            .spawn(move || run_scheduler(&state, dispatch));
+        if let Err(e) = spawned {
+            tracing::error!("Failed to start scheduler thread: {}", e);
+            lock_state(lock).started = false;
+        }
+    }
+    
+    /// Stop the scheduler thread; schedules stay listed but no longer run
+    pub(crate) fn stop(&self) {
+        let (lock, wake) = &*self.state;
+        lock_state(lock).stopped = true;
+        wake.notify_all();
+    }
+}
+
+/// Dispatch schedules as they fall due until stopped
+fn run_scheduler(state: &(Mutex<SchedulerState>, Condvar), dispatch: impl Fn(&Schedule) -> bool) {
+    let (lock, wake) = state;
+    loop {
+        let due: Vec<Schedule> = {
+            let mut state = lock_state(lock);
+            let now = loop {
+                if state.stopped {
+                    return;
+                }
+                
+                let now = Instant::now();
+                let next_due = state.schedules.values().map(|(_, next_due)| *next_due).min();
+                state = match next_due {
+                    Some(next_due) if next_due <= now => break now,
+                    Some(next_due) => wake.wait_timeout(state, next_due - now).unwrap_or_else(|e| e.into_inner()).0,
+                    None => wake.wait(state).unwrap_or_else(|e| e.into_inner()),
+                };
+            };
+            
+            // Advance past every missed tick rather than running them back to back
+            state.schedules.values_mut()
+                .filter(|(_, next_due)| *next_due <= now)
+                .map(|(schedule, next_due)| {
+                    while *next_due <= now {
+                        *next_due += schedule.every();
+                    }
+                    schedule.clone()
+                })
+                .collect()
+        };
+        
+        for schedule in &due {
+            if !dispatch(schedule) {
+                return;
+            }
+        }
+    }
+}
+
+fn lock_state(lock: &Mutex<SchedulerState>) -> std::sync::MutexGuard<'_, SchedulerState> {
+    lock.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    
+    #[test]
+    fn test_scheduler_dispatches_and_cancels() {
+        let scheduler = Scheduler::default();
+        let (sender, ticks) = mpsc::channel();
+        scheduler.start(move |schedule| sender.send(schedule.id.clone()).is_ok());
+        
+        let fast = Schedule::new(&"agent_1".to_string(), "sync", Duration::from_millis(10));
+        let slow = Schedule::new(&"agent_2".to_string(), "compact", Duration::from_secs(60));
+        scheduler.add(fast.clone());
+        scheduler.add(slow.clone());
+        assert_eq!(scheduler.list(Some(&"agent_2".to_string())), vec![slow.clone()]);
+        
+        for _ in 0..3 {
+            assert_eq!(ticks.recv_timeout(Duration::from_secs(5)).unwrap(), fast.id);
+        }
+        
+        // A removed schedule stops firing
+        assert_eq!(scheduler.remove(&fast.id), Some(fast));
+        while ticks.recv_timeout(Duration::from_millis(50)).is_ok() {}
+        assert!(ticks.recv_timeout(Duration::from_millis(50)).is_err());
+        
+        scheduler.stop();
+        assert_eq!(scheduler.list(None), vec![slow]);
+    }
+}
@@ -0,0 +1,146 @@
+//! Graceful shutdown for MCP-ZERO kernel
+//!
+//! Executions pass through an [`ExecutionGate`]; shutting down closes the gate so
+//! no new intents start, then waits for the running ones before agents are snapshotted.
+//! The gate also tells background snapshots which agents are mid-execution.
+
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+
+use crate::agent::AgentId;
+
+/// Outcome of `MCPKernel::shutdown`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShutdownReport {
+    /// Agents snapshotted successfully
+    pub snapshotted: Vec<AgentId>,
+    
+    /// Agents whose snapshot failed, with the error
+    pub failed: Vec<(AgentId, String)>,
+    
+    /// Executions still running when the timeout elapsed
+    pub abandoned_executions: usize,
+    
+    /// Number of trace entries written to storage
+    pub traces_flushed: usize,
+    
+    /// Error that stopped the trace flush, if any
+    pub trace_error: Option<String>,
+}
+
+impl ShutdownReport {
+    /// Whether every agent was snapshotted, all executions finished and traces were flushed
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty() && self.abandoned_executions == 0 && self.trace_error.is_none()
+    }
+}
+
+#[derive(Debug, Default)]
+struct GateState {
+    /// Whether new executions are refused
+    closed: bool,
+    
+    /// Executions currently running
+    active: usize,
+    
+    /// Executions currently running, by agent
+    agents: HashMap<AgentId, usize>,
+}
+
+/// Tracks running executions and refuses new ones once closed
+#[derive(Debug, Default)]
+pub(crate) struct ExecutionGate {
+    state: Mutex<GateState>,
+    idle: Condvar,
+}
+
+impl ExecutionGate {
+    /// Register a new execution of an agent, or None if the gate is closed
+    pub(crate) fn enter(&self, agent_id: &AgentId) -> Option<ExecutionPermit<'_>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.closed {
+            return None;
+        }
+        state.active += 1;
+        *state.agents.entry(agent_id.clone()).or_default() += 1;
+        Some(ExecutionPermit { gate: self, agent_id: agent_id.clone() })
+    }
+    
+    /// Whether an execution of the agent is running
+    pub(crate) fn is_executing(&self, agent_id: &AgentId) -> bool {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).agents.contains_key(agent_id)
+    }
+    
+    /// Refuse new executions; returns false if the gate was already closed
+    pub(crate) fn close(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        !std::mem::replace(&mut state.closed, true)
+    }
+    
+    /// Whether new executions are refused
+    pub(crate) fn is_closed(&self) -> bool {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).closed
+    }
+    
+    /// Wait up to `timeout` for running executions, returning how many are still running
+    pub(crate) fn wait_idle(&self, timeout: Duration) -> usize {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (state, _) = self.idle.wait_timeout_while(state, timeout, |state| state.active > 0)
+            .unwrap_or_else(|e| e.into_inner());
+        state.active
+    }
+}
+
+/// A running execution; dropping it marks the execution finished
+pub(crate) struct ExecutionPermit<'a> {
+    gate: &'a ExecutionGate,
+    agent_id: AgentId,
+}
+
+impl Drop for ExecutionPermit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.gate.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.active -= 1;
+        if let Some(count) = state.agents.get_mut(&self.agent_id) {
+            *count -= 1;
+            if *count == 0 {
+                state.agents.remove(&self.agent_id);
+            }
+        }
+        if state.active == 0 {
+            self.gate.idle.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_execution_gate() {
+        let gate = ExecutionGate::default();
+        let agent_id = "agent_1".to_string();
+        let permit = gate.enter(&agent_id).unwrap();
+        let second = gate.enter(&agent_id).unwrap();
+        drop(second);
+        assert!(gate.is_executing(&agent_id));
+        assert!(!gate.is_executing(&"agent_2".to_string()));
+        
+        assert!(gate.close());
+        assert!(!gate.close());
+        assert!(gate.enter(&agent_id).is_none());
+        assert_eq!(gate.wait_idle(Duration::from_millis(10)), 1);
+        
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                drop(permit);
+            });
+            assert_eq!(gate.wait_idle(Duration::from_secs(5)), 0);
+        });
+        assert!(!gate.is_executing(&agent_id));
+    }
+}
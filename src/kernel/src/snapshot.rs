@@ -0,0 +1,187 @@
+//! Versioned agent snapshot format for MCP-ZERO
+//!
+//! Snapshots are written as `{"version": N, "checksum": "...", "agent": {...}}`.
+//! Loading upgrades older snapshots one version at a time before deserializing, so
+//! changes to the agent's fields don't strand snapshots written by earlier kernels.
+//! Version 1 is the bare agent JSON written before the envelope existed.
+//!
+//! Since version 3 the envelope carries a BLAKE3 checksum of the agent JSON exactly as
+//! written. It is verified before the JSON is parsed, so bit-rot and truncated copies
+//! are reported as a [`StorageError::ChecksumMismatch`] rather than as parse errors or
+//! subtly wrong state.
+
+use anyhow::{Result, Context, anyhow};
+use serde_json::Value;
+
+use crate::agent::Agent;
+use crate::storage::StorageError;
+
+/// Snapshot version written by this kernel
+pub(crate) const SNAPSHOT_VERSION: u64 = 3;
+
+/// First version whose envelope carries a checksum
+const CHECKSUM_VERSION: u64 = 3;
+
+/// Upgrades from each version to the next, starting with version 1
+const MIGRATIONS: [fn(&mut Value) -> Result<()>; (SNAPSHOT_VERSION - 1) as usize] = [
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+];
+
+/// Serialize an agent in the current snapshot format
+///
+/// The envelope is laid out by hand so the checksummed agent JSON can be found again
+/// byte for byte when loading.
+pub(crate) fn encode(agent: &Agent) -> Result<String> {
+    let agent = serde_json::to_string(agent)
+        .context("Failed to serialize agent snapshot")?;
+    Ok(format!(
+        "{{\"version\":{},\"checksum\":\"{}\",\"agent\":{}}}",
+        SNAPSHOT_VERSION, blake3::hash(agent.as_bytes()).to_hex(), agent,
+    ))
+}
+
+/// Deserialize a snapshot of any supported version
+pub(crate) fn decode(data: &str) -> Result<Agent> {
+    let verified = verify_checksum(data)?;
+    let snapshot: Value = serde_json::from_str(data)
+        .context("Agent snapshot is not valid JSON")?;
+    
+    let (version, mut agent) = open_envelope(snapshot)?;
+    if version == 0 || version > SNAPSHOT_VERSION {
+        return Err(anyhow!(
+            "Unsupported snapshot version {} (this kernel reads versions 1 to {})",
+            version, SNAPSHOT_VERSION,
+        ));
+    }
+    if version >= CHECKSUM_VERSION && !verified {
+        return Err(anyhow!("Agent snapshot has no verifiable checksum; it was edited or reformatted"));
+    }
+    
+    for from in version..SNAPSHOT_VERSION {
+        MIGRATIONS[(from - 1) as usize](&mut agent)
+            .with_context(|| format!("Failed to migrate agent snapshot from version {}", from))?;
+    }
+    
+    serde_json::from_value(agent)
+        .with_context(|| format!("Agent snapshot does not match version {}", SNAPSHOT_VERSION))
+}
+
+/// Check the checksum of a snapshot written with one, before it is parsed
+///
+/// Returns whether a checksum was found; snapshots written before checksums existed
+/// have none.
+fn verify_checksum(data: &str) -> Result<bool> {
+    let Some((expected, agent)) = checksummed_parts(data) else {
+        return Ok(false);
+    };
+    
+    let actual = blake3::hash(agent.as_bytes()).to_hex().to_string();
+    if actual != expected {
+        return Err(StorageError::ChecksumMismatch { expected: expected.to_string(), actual }.into());
+    }
+    Ok(true)
+}
+
+/// Split a snapshot laid out by `encode` into its checksum and agent JSON
+///
+/// A truncated snapshot still splits, so the truncation fails the checksum.
+fn checksummed_parts(data: &str) -> Option<(&str, &str)> {
+    let rest = data.strip_prefix("{\"version\":")?
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .strip_prefix(",\"checksum\":\"")?;
+    let (checksum, rest) = rest.split_at_checked(64)?;
+    let agent = rest.strip_prefix("\",\"agent\":")?;
+    let agent = agent.trim_end();
+    Some((checksum, agent.strip_suffix('}').unwrap_or(agent)))
+}
+
+/// Split a snapshot into its version and agent
+fn open_envelope(snapshot: Value) -> Result<(u64, Value)> {
+    match snapshot {
+        Value::Object(mut envelope) if envelope.contains_key("version") => {
+            let version = envelope.get("version").and_then(Value::as_u64)
+                .ok_or_else(|| anyhow!("Unsupported snapshot version {}", envelope["version"]))?;
+            let agent = envelope.remove("agent")
+                .ok_or_else(|| anyhow!("Agent snapshot has no agent"))?;
+            Ok((version, agent))
+        },
+        // Version 1 snapshots are the bare agent
+        agent => Ok((1, agent)),
+    }
+}
+
+/// Version 2 always lists the attached plugin IDs; version 1 snapshots written before
+/// plugins were persisted have none
+fn migrate_v1_to_v2(agent: &mut Value) -> Result<()> {
+    let agent = agent.as_object_mut()
+        .ok_or_else(|| anyhow!("Agent snapshot is not an object"))?;
+    agent.entry("plugins").or_insert_with(|| Value::Array(Vec::new()));
+    Ok(())
+}
+
+/// Version 3 only adds the checksum to the envelope
+fn migrate_v2_to_v3(_agent: &mut Value) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentStatus;
+    
+    const SNAPSHOT_V1: &str = include_str!("../tests/fixtures/snapshot_v1.json");
+    const SNAPSHOT_V2: &str = include_str!("../tests/fixtures/snapshot_v2.json");
+    const SNAPSHOT_V3: &str = include_str!("../tests/fixtures/snapshot_v3.json");
+    
+    #[test]
+    fn test_decode_each_version() {
+        // Version 1 predates persisted plugins
+        let agent = decode(SNAPSHOT_V1).unwrap();
+        assert_eq!(agent.id(), "agent_5f1c0b9e2d7a4c31");
+        assert_eq!(agent.state()["visits"], 3);
+        assert!(agent.plugin_ids().is_empty());
+        
+        let agent = decode(SNAPSHOT_V2).unwrap();
+        assert_eq!(agent.status(), AgentStatus::Paused);
+        assert_eq!(agent.plugin_ids(), vec!["echo".to_string()]);
+        assert_eq!(agent.plugin_config(&"echo".to_string()), Some(&serde_json::json!({"greeting": "hi"})));
+        
+        // Version 3 adds only the checksum, which encoding writes
+        let checksummed = decode(SNAPSHOT_V3).unwrap();
+        assert_eq!(serde_json::to_value(&checksummed).unwrap(), serde_json::to_value(&agent).unwrap());
+        assert_eq!(encode(&agent).unwrap(), SNAPSHOT_V3.trim_end());
+    }
+    
+    #[test]
+    fn test_decode_checksum_mismatch() {
+        let expected = "f64734d65d230bcb3ebeac313423d73981800d71327b9c40535181b260f842fc";
+        let flipped = SNAPSHOT_V3.replacen("\"visits\":4", "\"visits\":5", 1);
+        let truncated = &SNAPSHOT_V3[..SNAPSHOT_V3.len() / 2];
+        for damaged in [flipped.as_str(), truncated] {
+            let error = decode(damaged).unwrap_err();
+            match StorageError::find(&error) {
+                Some(StorageError::ChecksumMismatch { expected: found, actual }) => {
+                    assert_eq!(found, expected);
+                    assert_ne!(actual, expected);
+                },
+                _ => panic!("expected a checksum mismatch, got {:#}", error),
+            }
+        }
+        
+        // A reformatted snapshot can't be verified
+        let pretty = serde_json::to_string_pretty(&serde_json::from_str::<Value>(SNAPSHOT_V3).unwrap()).unwrap();
+        assert!(decode(&pretty).unwrap_err().to_string().contains("no verifiable checksum"));
+    }
+    
+    #[test]
+    fn test_decode_unsupported_version() {
+        let future = SNAPSHOT_V2.replacen("\"version\":2", "\"version\":99", 1);
+        let error = decode(&future).unwrap_err();
+        assert!(error.to_string().contains("Unsupported snapshot version 99"));
+        
+        // A version 2 snapshot must list its plugins
+        let missing = serde_json::json!({"version": 2, "agent": serde_json::from_str::<Value>(SNAPSHOT_V1).unwrap()});
+        assert!(decode(&missing.to_string()).is_err());
+    }
+}
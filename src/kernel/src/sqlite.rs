@@ -0,0 +1,512 @@
+//! SQLite storage backend for MCP-ZERO kernel
+//!
+//! Keeps every agent in one database file instead of a directory per agent, so
+//! listing thousands of agents is a single query. Enabled with the `sqlite` feature
+//! and selected with `storage_backend: sqlite`.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use anyhow::{Result, Context, anyhow};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::agent::{Agent, AgentId};
+use crate::encryption::StorageEncryption;
+use crate::snapshot;
+use crate::storage::{self, PayloadCodec, SnapshotVersion, StateRecord, StorageBackend, StorageUsage};
+use crate::trace::{TraceEntry, TraceId};
+
+/// Database file created in the storage directory
+pub(crate) const SQLITE_FILE: &str = "agents.db";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS agents (
+        id TEXT PRIMARY KEY,
+        snapshot BLOB,
+        state BLOB
+    );
+    CREATE TABLE IF NOT EXISTS quarantine (
+        id TEXT PRIMARY KEY,
+        snapshot BLOB,
+        state BLOB
+    );
+    CREATE TABLE IF NOT EXISTS snapshots (
+        id TEXT NOT NULL,
+        taken_at INTEGER NOT NULL,
+        snapshot BLOB NOT NULL,
+        PRIMARY KEY (id, taken_at)
+    );
+    CREATE TABLE IF NOT EXISTS traces (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        entry TEXT NOT NULL
+    );
+";
+
+/// Storage backend keeping agents in a SQLite database
+///
+/// Snapshots use the same versioned format as the directory store; write-through
+/// state is kept as a blob alongside.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+    
+    /// Compression and encryption applied to stored payloads
+    codec: PayloadCodec,
+    
+    /// Historical snapshots kept per agent (0 keeps none)
+    history_limit: usize,
+    
+    /// Default storage quota per agent in bytes (0 is unlimited)
+    quota_bytes: u64,
+}
+
+impl SqliteStorage {
+    /// Open or create a database file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create storage directory: {}", dir.display()))?;
+        }
+        
+        let connection = Connection::open(path)
+            .with_context(|| format!("Failed to open storage database: {}", path.display()))?;
+        Self::with_connection(connection)
+    }
+    
+    /// Create a database held in memory
+    pub fn in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory().context("Failed to open in-memory database")?)
+    }
+    
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)
+            .context("Failed to create storage tables")?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            codec: PayloadCodec::default(),
+            history_limit: 0,
+            quota_bytes: 0,
+        })
+    }
+    
+    /// Keep the last `limit` snapshots of each agent for point-in-time restore
+    pub fn with_snapshot_history(mut self, limit: usize) -> Self {
+        self.history_limit = limit;
+        self
+    }
+    
+    /// Hold each agent's snapshot, state and history to `bytes`, unless its hardware
+    /// constraints set another quota; 0 is unlimited
+    pub fn with_storage_quota(mut self, bytes: u64) -> Self {
+        self.quota_bytes = bytes;
+        self
+    }
+    
+    /// Encrypt snapshots and state written from now on
+    pub fn with_encryption(mut self, encryption: StorageEncryption) -> Self {
+        self.codec.encryption = Some(encryption);
+        self
+    }
+    
+    /// Compress snapshots and state written from now on at a zstd `level`
+    pub fn with_compression(mut self, level: i32) -> Self {
+        self.codec.compression_level = Some(level);
+        self
+    }
+    
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+        self.connection.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on storage database"))
+    }
+}
+
+/// Historical snapshots of an agent, oldest first
+fn snapshot_versions(connection: &Connection, agent_id: &AgentId) -> Result<Vec<SnapshotVersion>> {
+    let mut statement = connection.prepare(
+        "SELECT taken_at, length(snapshot) FROM snapshots WHERE id = ?1 ORDER BY taken_at"
+    )?;
+    let versions = statement.query_map(params![agent_id], |row| Ok(SnapshotVersion {
+            timestamp_ms: row.get(0)?,
+            size: row.get(1)?,
+        }))?
+        .collect::<rusqlite::Result<Vec<SnapshotVersion>>>()
+        .with_context(|| format!("Failed to list snapshots: {}", agent_id))?;
+    Ok(versions)
+}
+
+impl StorageBackend for SqliteStorage {
+    fn save_agent(&self, agent_id: &AgentId, agent: &Agent) -> Result<()> {
+        let agent_data = snapshot::encode(agent)
+            .and_then(|data| self.codec.pack(data))
+            .with_context(|| format!("Failed to serialize agent: {}", agent_id))?;
+        
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction()?;
+        
+        // Check the quota before writing, keeping as much recent history as fits
+        let history = if self.history_limit > 0 { snapshot_versions(&transaction, agent_id)? } else { Vec::new() };
+        let history = &history[history.len().saturating_sub(self.history_limit.saturating_sub(1))..];
+        let state_size: u64 = transaction.query_row(
+            "SELECT coalesce(length(state), 0) FROM agents WHERE id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        ).optional().with_context(|| format!("Failed to read agent state: {}", agent_id))?.unwrap_or(0);
+        let quota = storage::agent_quota(agent, self.quota_bytes);
+        let kept = storage::history_within_quota(quota, agent_data.len() as u64 + state_size, history)
+            .with_context(|| format!("Agent {} is over its storage quota", agent_id))?;
+        
+        transaction.execute(
+            "INSERT INTO agents (id, snapshot) VALUES (?1, ?2)
+             ON CONFLICT(id) DO UPDATE SET snapshot = excluded.snapshot",
+            params![agent_id, agent_data],
+        ).with_context(|| format!("Failed to write agent: {}", agent_id))?;
+        
+        // Record the snapshot in the history and drop the oldest beyond the limit or quota
+        if self.history_limit > 0 {
+            transaction.execute(
+                "INSERT OR REPLACE INTO snapshots (id, taken_at, snapshot) VALUES (?1, ?2, ?3)",
+                params![agent_id, chrono::Utc::now().timestamp_millis(), agent_data],
+            ).with_context(|| format!("Failed to record snapshot history: {}", agent_id))?;
+            transaction.execute(
+                "DELETE FROM snapshots WHERE id = ?1 AND taken_at NOT IN
+                 (SELECT taken_at FROM snapshots WHERE id = ?1 ORDER BY taken_at DESC LIMIT ?2)",
+                params![agent_id, (kept + 1) as i64],
+            ).with_context(|| format!("Failed to prune snapshot history: {}", agent_id))?;
+        }
+        transaction.commit()
+            .with_context(|| format!("Failed to write agent: {}", agent_id))?;
+        Ok(())
+    }
+    
+    fn load_agent(&self, agent_id: &AgentId) -> Result<Agent> {
+        // Snapshots written before compression existed are stored as text
+        let row: Option<(Vec<u8>, Option<Vec<u8>>)> = self.lock()?.query_row(
+            "SELECT CAST(snapshot AS BLOB), state FROM agents WHERE id = ?1 AND snapshot IS NOT NULL",
+            params![agent_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional().with_context(|| format!("Failed to read agent: {}", agent_id))?;
+        let (agent_data, state_data) = row
+            .ok_or_else(|| anyhow!("Agent not found in storage: {}", agent_id))?;
+        
+        let mut agent = self.codec.unpack(agent_data)
+            .and_then(|data| snapshot::decode(&data))
+            .with_context(|| format!("Failed to deserialize agent: {}", agent_id))?;
+        if let Some(state_data) = state_data {
+            let record: StateRecord = self.codec.unpack(state_data)
+                .and_then(|data| Ok(serde_json::from_str(&data)?))
+                .with_context(|| format!("Failed to deserialize agent state: {}", agent_id))?;
+            record.apply(&mut agent);
+        }
+        
+        Ok(agent)
+    }
+    
+    fn save_state(&self, agent_id: &AgentId, agent: &Agent) -> Result<()> {
+        let state_data = serde_json::to_string(&StateRecord::of(agent))
+            .map_err(anyhow::Error::from)
+            .and_then(|data| self.codec.pack(data))
+            .with_context(|| format!("Failed to serialize agent state: {}", agent_id))?;
+        
+        self.lock()?.execute(
+            "INSERT INTO agents (id, state) VALUES (?1, ?2)
+             ON CONFLICT(id) DO UPDATE SET state = excluded.state",
+            params![agent_id, state_data],
+        ).with_context(|| format!("Failed to write agent state: {}", agent_id))?;
+        Ok(())
+    }
+    
+    fn has_agent(&self, agent_id: &AgentId) -> bool {
+        let Ok(connection) = self.lock() else {
+            return false;
+        };
+        connection.query_row(
+            "SELECT 1 FROM agents WHERE id = ?1 AND snapshot IS NOT NULL",
+            params![agent_id],
+            |_| Ok(()),
+        ).optional().ok().flatten().is_some()
+    }
+    
+    fn stored_size(&self, agent_id: &AgentId) -> Option<u64> {
+        let connection = self.lock().ok()?;
+        connection.query_row(
+            "SELECT length(CAST(snapshot AS BLOB)) + coalesce(length(state), 0) FROM agents
+             WHERE id = ?1 AND snapshot IS NOT NULL",
+            params![agent_id],
+            |row| row.get(0),
+        ).optional().ok().flatten()
+    }
+    
+    fn storage_usage(&self, agent_id: &AgentId) -> Result<StorageUsage> {
+        let connection = self.lock()?;
+        let (snapshot_bytes, state_bytes) = connection.query_row(
+            "SELECT length(CAST(snapshot AS BLOB)), coalesce(length(state), 0) FROM agents
+             WHERE id = ?1 AND snapshot IS NOT NULL",
+            params![agent_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional().with_context(|| format!("Failed to read agent: {}", agent_id))?
+            .ok_or_else(|| anyhow!("Agent not found in storage: {}", agent_id))?;
+        
+        // The newest history row is a copy of the current snapshot
+        let history = snapshot_versions(&connection, agent_id)?;
+        Ok(StorageUsage {
+            snapshot_bytes,
+            state_bytes,
+            history_bytes: history.iter().rev().skip(1).map(|version| version.size).sum(),
+            quota_bytes: None,
+        })
+    }
+    
+    fn list_agents(&self) -> Result<Vec<AgentId>> {
+        let connection = self.lock()?;
+        let mut statement = connection.prepare(
+            "SELECT id FROM agents WHERE snapshot IS NOT NULL ORDER BY id"
+        )?;
+        let agent_ids = statement.query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<AgentId>>>()
+            .context("Failed to list agents")?;
+        Ok(agent_ids)
+    }
+    
+    fn delete_agent(&self, agent_id: &AgentId) -> Result<()> {
+        let connection = self.lock()?;
+        let deleted = connection.execute(
+            "DELETE FROM agents WHERE id = ?1 AND snapshot IS NOT NULL",
+            params![agent_id],
+        ).with_context(|| format!("Failed to delete agent: {}", agent_id))?;
+        if deleted == 0 {
+            return Err(anyhow!("Agent not found in storage: {}", agent_id));
+        }
+        connection.execute("DELETE FROM snapshots WHERE id = ?1", params![agent_id])
+            .with_context(|| format!("Failed to delete snapshot history: {}", agent_id))?;
+        Ok(())
+    }
+    
+    fn quarantine_agent(&self, agent_id: &AgentId) -> Result<()> {
+        // Move the row, replacing an earlier quarantined copy of the same agent
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction()?;
+        let moved = transaction.execute(
+            "INSERT OR REPLACE INTO quarantine (id, snapshot, state)
+             SELECT id, snapshot, state FROM agents WHERE id = ?1 AND snapshot IS NOT NULL",
+            params![agent_id],
+        ).with_context(|| format!("Failed to quarantine agent: {}", agent_id))?;
+        if moved == 0 {
+            return Err(anyhow!("Agent not found in storage: {}", agent_id));
+        }
+        // Only the latest snapshot is quarantined; the history can't be restored from there
+        transaction.execute("DELETE FROM agents WHERE id = ?1", params![agent_id])
+            .with_context(|| format!("Failed to quarantine agent: {}", agent_id))?;
+        transaction.execute("DELETE FROM snapshots WHERE id = ?1", params![agent_id])
+            .with_context(|| format!("Failed to quarantine agent: {}", agent_id))?;
+        transaction.commit()
+            .with_context(|| format!("Failed to quarantine agent: {}", agent_id))?;
+        Ok(())
+    }
+    
+    fn append_traces(&self, entries: &[TraceEntry]) -> Result<()> {
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction()?;
+        {
+            let mut statement = transaction.prepare("INSERT INTO traces (entry) VALUES (?1)")?;
+            for entry in entries {
+                statement.execute(params![serde_json::to_string(entry)?])
+                    .context("Failed to append trace entry")?;
+            }
+        }
+        transaction.commit().context("Failed to append trace entries")?;
+        Ok(())
+    }
+    
+    fn load_traces(&self) -> Result<Vec<TraceEntry>> {
+        let connection = self.lock()?;
+        let mut statement = connection.prepare("SELECT entry FROM traces ORDER BY seq")?;
+        let entries = statement.query_map([], |row| row.get::<_, String>(0))?
+            .map(|entry| Ok(serde_json::from_str(&entry?)?))
+            .collect::<Result<Vec<TraceEntry>>>()
+            .context("Failed to load trace log")?;
+        Ok(entries)
+    }
+    
+    fn scan_traces(&self, visit: &mut dyn FnMut(TraceEntry) -> Result<()>) -> Result<()> {
+        let connection = self.lock()?;
+        let mut statement = connection.prepare("SELECT entry FROM traces ORDER BY seq")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let entry = serde_json::from_str(&row.get::<_, String>(0)?).context("Failed to deserialize trace entry")?;
+            visit(entry)?;
+        }
+        Ok(())
+    }
+    
+    fn prune_traces(&self, before: i64, keep: &HashSet<TraceId>) -> Result<usize> {
+        let mut connection = self.lock()?;
+        let transaction = connection.transaction()?;
+        let expired: Vec<i64> = {
+            let mut statement = transaction.prepare("SELECT seq, entry FROM traces")?;
+            let rows = statement.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+            let mut expired = Vec::new();
+            for row in rows {
+                let (seq, entry) = row?;
+                let entry: TraceEntry = serde_json::from_str(&entry).context("Failed to deserialize trace entry")?;
+                if entry.timestamp < before && !keep.contains(&entry.id) {
+                    expired.push(seq);
+                }
+            }
+            expired
+        };
+        {
+            let mut statement = transaction.prepare("DELETE FROM traces WHERE seq = ?1")?;
+            for seq in &expired {
+                statement.execute(params![seq])?;
+            }
+        }
+        transaction.commit().context("Failed to prune trace log")?;
+        Ok(expired.len())
+    }
+    
+    fn list_snapshots(&self, agent_id: &AgentId) -> Result<Vec<SnapshotVersion>> {
+        snapshot_versions(&*self.lock()?, agent_id)
+    }
+    
+    fn load_snapshot(&self, agent_id: &AgentId, timestamp_ms: i64) -> Result<Agent> {
+        let agent_data: Vec<u8> = self.lock()?.query_row(
+            "SELECT snapshot FROM snapshots WHERE id = ?1 AND taken_at = ?2",
+            params![agent_id, timestamp_ms],
+            |row| row.get(0),
+        ).optional().with_context(|| format!("Failed to read snapshot: {}", agent_id))?
+            .ok_or_else(|| anyhow!("No snapshot of agent {} taken at {}", agent_id, timestamp_ms))?;
+        
+        self.codec.unpack(agent_data)
+            .and_then(|data| snapshot::decode(&data))
+            .with_context(|| format!("Failed to deserialize snapshot: {}", agent_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentConfig;
+    use crate::config::{KernelConfig, StorageBackendKind};
+    use crate::storage::{copy_storage, StorageError, StorageManager};
+    use crate::MCPKernel;
+    
+    fn agent(name: &str) -> Agent {
+        let config = AgentConfig { name: name.to_string(), ..Default::default() };
+        Agent::new(crate::agent::generate_agent_id(&config), config)
+    }
+    
+    #[test]
+    fn test_sqlite_backend() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let mut first = agent("first");
+        let second = agent("second");
+        storage.save_agent(first.id(), &first).unwrap();
+        storage.save_agent(second.id(), &second).unwrap();
+        
+        // Write-through state newer than the snapshot is applied on load
+        first.set_state("visits", serde_json::json!(2), usize::MAX).unwrap();
+        storage.save_state(first.id(), &first).unwrap();
+        assert_eq!(storage.load_agent(first.id()).unwrap().state()["visits"], 2);
+        
+        let mut expected = vec![first.id().clone(), second.id().clone()];
+        expected.sort();
+        assert_eq!(storage.list_agents().unwrap(), expected);
+        
+        // Quarantined and deleted agents are no longer listed or loaded
+        storage.quarantine_agent(first.id()).unwrap();
+        storage.delete_agent(second.id()).unwrap();
+        assert!(storage.list_agents().unwrap().is_empty());
+        assert!(!storage.has_agent(first.id()));
+        assert!(storage.load_agent(second.id()).is_err());
+        assert!(storage.delete_agent(second.id()).is_err());
+    }
+    
+    #[test]
+    fn test_sqlite_snapshot_history() {
+        let storage = SqliteStorage::in_memory().unwrap().with_snapshot_history(2);
+        let mut agent = agent("historic");
+        for version in 1..=3 {
+            agent.set_state("version", serde_json::json!(version), usize::MAX).unwrap();
+            storage.save_agent(agent.id(), &agent).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        
+        let versions = storage.list_snapshots(agent.id()).unwrap();
+        assert_eq!(versions.len(), 2);
+        let oldest = storage.load_snapshot(agent.id(), versions[0].timestamp_ms).unwrap();
+        assert_eq!(oldest.state()["version"], 2);
+        
+        storage.delete_agent(agent.id()).unwrap();
+        assert!(storage.list_snapshots(agent.id()).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_sqlite_storage_quota() {
+        let mut agent = agent("bounded");
+        agent.set_state("blob", serde_json::json!("x".repeat(1000)), usize::MAX).unwrap();
+        let unbounded = SqliteStorage::in_memory().unwrap();
+        unbounded.save_agent(agent.id(), &agent).unwrap();
+        let size = unbounded.storage_usage(agent.id()).unwrap().total_bytes();
+        
+        // Room for the current snapshot and one older one
+        let storage = SqliteStorage::in_memory().unwrap().with_snapshot_history(5).with_storage_quota(size * 5 / 2);
+        for version in 1..=4 {
+            agent.set_state("version", serde_json::json!(version), usize::MAX).unwrap();
+            storage.save_agent(agent.id(), &agent).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        assert_eq!(storage.list_snapshots(agent.id()).unwrap().len(), 2);
+        let usage = storage.storage_usage(agent.id()).unwrap();
+        assert_eq!(usage.history_bytes, usage.snapshot_bytes);
+        
+        agent.set_state("blob", serde_json::json!("x".repeat(size as usize * 3)), usize::MAX).unwrap();
+        let error = storage.save_agent(agent.id(), &agent).unwrap_err();
+        assert!(matches!(StorageError::find(&error), Some(StorageError::QuotaExceeded { .. })));
+        assert_eq!(storage.list_snapshots(agent.id()).unwrap().len(), 2);
+        assert_eq!(storage.load_agent(agent.id()).unwrap().state()["version"], 4);
+    }
+    
+    #[test]
+    fn test_encrypted_sqlite_backend() {
+        let key = crate::encryption::StorageKey::from_bytes(&[7; 32]).unwrap();
+        let storage = SqliteStorage::in_memory().unwrap().with_encryption(StorageEncryption::new(key));
+        let mut agent = agent("sealed");
+        agent.set_state("api_token", serde_json::json!("hunter2"), usize::MAX).unwrap();
+        storage.save_agent(agent.id(), &agent).unwrap();
+        storage.save_state(agent.id(), &agent).unwrap();
+        
+        let (snapshot, state): (Vec<u8>, Vec<u8>) = storage.lock().unwrap().query_row(
+            "SELECT snapshot, state FROM agents WHERE id = ?1",
+            params![agent.id()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert!(snapshot.starts_with(b"mcp-sealed:1:") && !String::from_utf8(snapshot).unwrap().contains("hunter2"));
+        assert!(state.starts_with(b"mcp-sealed:1:"));
+        assert_eq!(storage.load_agent(agent.id()).unwrap().state()["api_token"], "hunter2");
+    }
+    
+    #[test]
+    fn test_copy_fs_store_into_sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        let fs_store = StorageManager::new(dir.path().join("storage")).unwrap();
+        let mut agent = agent("migrated");
+        agent.set_state("visits", serde_json::json!(3), usize::MAX).unwrap();
+        fs_store.save_agent(agent.id(), &agent).unwrap();
+        
+        let config = KernelConfig {
+            plugin_directory: dir.path().to_path_buf(),
+            storage_directory: dir.path().join("storage"),
+            storage_backend: StorageBackendKind::Sqlite,
+            ..Default::default()
+        };
+        let sqlite_store = SqliteStorage::open(config.storage_directory.join(SQLITE_FILE)).unwrap();
+        assert_eq!(copy_storage(&fs_store, &sqlite_store).unwrap(), vec![agent.id().clone()]);
+        drop(sqlite_store);
+        
+        // A kernel configured for sqlite recovers the copied agent
+        let kernel = MCPKernel::with_config(config);
+        kernel.recover(agent.id()).unwrap();
+        assert_eq!(kernel.get_state(agent.id(), "visits").unwrap(), Some(serde_json::json!(3)));
+    }
+}
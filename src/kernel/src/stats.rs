@@ -0,0 +1,186 @@
+//! Kernel-wide statistics for MCP-ZERO
+//!
+//! Counters are updated inline as agents are spawned, change status and run intents,
+//! so reading them never scans the agent store. Each update is also published as a
+//! `metrics` gauge for dashboards.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+
+use crate::agent::AgentStatus;
+use crate::autosnapshot::AutoSnapshotStats;
+
+/// Number of loaded agents in each status
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentStatusCounts {
+    pub active: usize,
+    pub recovered: usize,
+    pub paused: usize,
+    #[serde(default)]
+    pub quarantined: usize,
+    pub terminated: usize,
+}
+
+/// Snapshot of kernel activity, returned by `MCPKernel::stats`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KernelStats {
+    /// Loaded agents by status
+    pub agents: AgentStatusCounts,
+    
+    /// Plugins whose compiled modules are resident
+    pub loaded_plugins: usize,
+    
+    /// Executions requested, including rejected ones
+    pub executions: u64,
+    
+    /// Executions that failed or were rejected
+    pub failures: u64,
+    
+    /// Mean duration of executions that passed validation, in milliseconds
+    pub average_latency_ms: f64,
+    
+    /// Trace entries recorded by the tracer
+    pub trace_entries: usize,
+    
+    /// Background snapshots taken, skipped and failed
+    pub auto_snapshots: AutoSnapshotStats,
+    
+    /// Time since the kernel was built, in milliseconds
+    pub uptime_ms: u64,
+    
+    /// Version of the ethics policy in force
+    pub ethics_policy_version: String,
+}
+
+/// Atomic counters behind [`KernelStats`]
+#[derive(Debug)]
+pub(crate) struct KernelCounters {
+    started: Instant,
+    active: AtomicUsize,
+    recovered: AtomicUsize,
+    paused: AtomicUsize,
+    quarantined: AtomicUsize,
+    terminated: AtomicUsize,
+    executions: AtomicU64,
+    failures: AtomicU64,
+    
+    /// Executions that reached a trace, and their total duration in microseconds
+    timed_executions: AtomicU64,
+    latency_total_us: AtomicU64,
+}
+
+impl Default for KernelCounters {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            active: AtomicUsize::new(0),
+            recovered: AtomicUsize::new(0),
+            paused: AtomicUsize::new(0),
+            quarantined: AtomicUsize::new(0),
+            terminated: AtomicUsize::new(0),
+            executions: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            timed_executions: AtomicU64::new(0),
+            latency_total_us: AtomicU64::new(0),
+        }
+    }
+}
+
+impl KernelCounters {
+    /// Count an agent added to the agent store
+    pub(crate) fn agent_added(&self, status: AgentStatus) {
+        if let Some(counter) = self.status_counter(status) {
+            counter.fetch_add(1, Ordering::Relaxed);
+            self.publish_status(status, counter);
+        }
+    }
+    
+    /// Count an agent removed from the agent store
+    pub(crate) fn agent_removed(&self, status: AgentStatus) {
+        if let Some(counter) = self.status_counter(status) {
+            // Saturate so a missed add can't wrap the count around
+            let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| Some(n.saturating_sub(1)));
+            self.publish_status(status, counter);
+        }
+    }
+    
+    /// Count an agent moving between statuses
+    pub(crate) fn agent_transitioned(&self, from: AgentStatus, to: AgentStatus) {
+        self.agent_removed(from);
+        self.agent_added(to);
+    }
+    
+    /// Count a finished execution; `duration` is set for executions that passed validation
+    pub(crate) fn execution_finished(&self, duration: Option<Duration>, success: bool) {
+        let executions = self.executions.fetch_add(1, Ordering::Relaxed) + 1;
+        metrics::gauge!("mcp.kernel.executions", executions as f64);
+        
+        if !success {
+            let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+            metrics::gauge!("mcp.kernel.execution_failures", failures as f64);
+        }
+        
+        if let Some(duration) = duration {
+            self.timed_executions.fetch_add(1, Ordering::Relaxed);
+            self.latency_total_us.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+            metrics::gauge!("mcp.kernel.execution_latency_avg_ms", self.average_latency_ms());
+        }
+    }
+    
+    /// Current counts, with the values the kernel reads from its components
+    pub(crate) fn snapshot(
+        &self,
+        loaded_plugins: usize,
+        trace_entries: usize,
+        auto_snapshots: AutoSnapshotStats,
+        ethics_policy_version: String,
+    ) -> KernelStats {
+        let uptime_ms = self.started.elapsed().as_millis() as u64;
+        metrics::gauge!("mcp.kernel.trace_entries", trace_entries as f64);
+        metrics::gauge!("mcp.kernel.uptime_ms", uptime_ms as f64);
+        
+        KernelStats {
+            agents: AgentStatusCounts {
+                active: self.active.load(Ordering::Relaxed),
+                recovered: self.recovered.load(Ordering::Relaxed),
+                paused: self.paused.load(Ordering::Relaxed),
+                quarantined: self.quarantined.load(Ordering::Relaxed),
+                terminated: self.terminated.load(Ordering::Relaxed),
+            },
+            loaded_plugins,
+            executions: self.executions.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            average_latency_ms: self.average_latency_ms(),
+            trace_entries,
+            auto_snapshots,
+            uptime_ms,
+            ethics_policy_version,
+        }
+    }
+    
+    fn average_latency_ms(&self) -> f64 {
+        match self.timed_executions.load(Ordering::Relaxed) {
+            0 => 0.0,
+            timed => self.latency_total_us.load(Ordering::Relaxed) as f64 / timed as f64 / 1000.0,
+        }
+    }
+    
+    /// Counter for a status; stored agents are never loaded, so they have none
+    fn status_counter(&self, status: AgentStatus) -> Option<&AtomicUsize> {
+        match status {
+            AgentStatus::Active => Some(&self.active),
+            AgentStatus::Recovered => Some(&self.recovered),
+            AgentStatus::Paused => Some(&self.paused),
+            AgentStatus::Quarantined => Some(&self.quarantined),
+            AgentStatus::Terminated => Some(&self.terminated),
+            AgentStatus::Stored => None,
+        }
+    }
+    
+    fn publish_status(&self, status: AgentStatus, counter: &AtomicUsize) {
+        let status = format!("{:?}", status).to_lowercase();
+        metrics::gauge!("mcp.kernel.agents", counter.load(Ordering::Relaxed) as f64, "status" => status);
+    }
+}
+
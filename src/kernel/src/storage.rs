@@ -1,39 +1,646 @@
 //! Storage module for MCP-ZERO kernel
 //!
 //! Provides agent state persistence, optimized for minimal memory usage
-//! and disk footprint.
+//! and disk footprint. Kernels persist through a [`StorageBackend`]: the
+//! on-disk [`StorageManager`] by default, or [`MemoryStorage`] for tests.
 
 use std::path::{Path, PathBuf};
 use std::fs;
-use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use anyhow::{Result, Context, anyhow};
+use dashmap::DashMap;
+use fs2::FileExt;
 use serde::{Serialize, Deserialize};
+use thiserror::Error;
 
 use crate::agent::{Agent, AgentId};
+use crate::compression;
+use crate::encryption::{self, StorageEncryption};
+use crate::snapshot;
+use crate::trace::{TraceEntry, TraceId};
+use crate::worker::BackgroundWorker;
 
-/// Storage manager for agent persistence
+/// Write-through copy of an agent's state map, stored as state.json
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct StateRecord {
+    /// Agent update timestamp when the state was written
+    updated_at: i64,
+    
+    /// Agent state
+    state: HashMap<String, serde_json::Value>,
+}
+
+impl StateRecord {
+    pub(crate) fn of(agent: &Agent) -> Self {
+        Self {
+            updated_at: agent.updated_at(),
+            state: agent.state().clone(),
+        }
+    }
+    
+    /// Apply the record to an agent loaded from an older snapshot
+    pub(crate) fn apply(self, agent: &mut Agent) {
+        if self.updated_at >= agent.updated_at() {
+            agent.restore_state(self.state, self.updated_at);
+        }
+    }
+}
+
+/// Outcome of `MCPKernel::recover_all`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecoveryReport {
+    /// Agents recovered from storage
+    pub recovered: Vec<AgentId>,
+    
+    /// Stored agents that were already loaded
+    pub already_loaded: Vec<AgentId>,
+    
+    /// Agents that could not be recovered, with the reason
+    pub failed: Vec<(AgentId, String)>,
+    
+    /// Failed agents moved to quarantine
+    pub quarantined: Vec<AgentId>,
+}
+
+/// Storage failures callers handle specifically: damaged snapshots found when reading
+/// stored agents, snapshots refused by an agent's storage quota, and storage directories
+/// already in use by another kernel process
+///
+/// Raised inside the `anyhow` errors of storage backends; find it with
+/// [`StorageError::find`].
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum StorageError {
+    #[error("Snapshot checksum mismatch: expected {expected}, found {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    
+    #[error("Storage quota exceeded: the snapshot and state need {required} bytes of a {quota} byte quota")]
+    QuotaExceeded { required: u64, quota: u64 },
+    
+    #[error("Storage directory {directory} is locked by {}; is another kernel using it?", lock_holder(.pid))]
+    Locked { directory: String, pid: Option<u32> },
+}
+
+fn lock_holder(pid: &Option<u32>) -> String {
+    match pid {
+        Some(pid) => format!("process {}", pid),
+        None => "another process".to_string(),
+    }
+}
+
+impl StorageError {
+    /// The storage error anywhere in an error's chain of causes
+    pub fn find(error: &anyhow::Error) -> Option<&StorageError> {
+        error.chain().find_map(|cause| cause.downcast_ref())
+    }
+}
+
+/// Outcome of `MCPKernel::verify_storage`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerificationReport {
+    /// Agents whose snapshot and state read back intact
+    pub verified: Vec<AgentId>,
+    
+    /// Agents whose snapshot failed its checksum
+    pub corrupted: Vec<(AgentId, StorageError)>,
+    
+    /// Agents that could not be read for another reason
+    pub unreadable: Vec<(AgentId, String)>,
+}
+
+impl VerificationReport {
+    /// Whether every stored agent verified
+    pub fn is_clean(&self) -> bool {
+        self.corrupted.is_empty() && self.unreadable.is_empty()
+    }
+}
+
+/// A snapshot kept in an agent's snapshot history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotVersion {
+    /// When the snapshot was taken, in Unix milliseconds
+    pub timestamp_ms: i64,
+    
+    /// Bytes the snapshot takes up in storage
+    pub size: u64,
+}
+
+/// Bytes an agent takes up in storage
+///
+/// The current snapshot is counted once, even where the history also holds it. The
+/// backup of the previous snapshot is not counted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageUsage {
+    /// Current snapshot
+    pub snapshot_bytes: u64,
+    
+    /// Write-through state
+    pub state_bytes: u64,
+    
+    /// Older snapshots kept in the snapshot history
+    pub history_bytes: u64,
+    
+    /// Quota the agent is held to when snapshotted, if any; set by `MCPKernel::storage_usage`
+    pub quota_bytes: Option<u64>,
+}
+
+impl StorageUsage {
+    /// Bytes counted against the quota
+    pub fn total_bytes(&self) -> u64 {
+        self.snapshot_bytes + self.state_bytes + self.history_bytes
+    }
+}
+
+/// Storage quota of an agent: its own hardware constraint, else `default`; 0 is unlimited
+pub(crate) fn agent_quota(agent: &Agent, default: u64) -> Option<u64> {
+    Some(agent.config().hm.storage_bytes.unwrap_or(default)).filter(|&quota| quota > 0)
+}
+
+/// Number of historical snapshots, counting back from the newest, that fit in a quota
+/// beside `required` bytes of new snapshot and state
+///
+/// Fails when the snapshot and state don't fit even without any history, in which
+/// case nothing should be pruned.
+pub(crate) fn history_within_quota(quota: Option<u64>, required: u64, history: &[SnapshotVersion]) -> Result<usize, StorageError> {
+    let Some(quota) = quota else {
+        return Ok(history.len());
+    };
+    if required > quota {
+        return Err(StorageError::QuotaExceeded { required, quota });
+    }
+    
+    let mut used = required;
+    Ok(history.iter().rev()
+        .take_while(|version| {
+            used += version.size;
+            used <= quota
+        })
+        .count())
+}
+
+/// Persistence backend for agent snapshots and write-through state
+pub trait StorageBackend: Send + Sync {
+    /// Save a full agent snapshot
+    fn save_agent(&self, agent_id: &AgentId, agent: &Agent) -> Result<()>;
+    
+    /// Load an agent, applying a newer write-through state if one exists
+    fn load_agent(&self, agent_id: &AgentId) -> Result<Agent>;
+    
+    /// Save only the agent's state map
+    fn save_state(&self, agent_id: &AgentId, agent: &Agent) -> Result<()>;
+    
+    /// Check whether an agent snapshot exists
+    fn has_agent(&self, agent_id: &AgentId) -> bool;
+    
+    /// Read the agent's current snapshot and state back, failing on any damage
+    ///
+    /// Unlike `load_agent`, a damaged snapshot is reported even when a backup could
+    /// stand in for it.
+    fn verify_agent(&self, agent_id: &AgentId) -> Result<()> {
+        self.load_agent(agent_id).map(|_| ())
+    }
+    
+    /// Bytes the agent's snapshot and state take up in storage, if it is stored
+    fn stored_size(&self, _agent_id: &AgentId) -> Option<u64> {
+        None
+    }
+    
+    /// Break down the bytes a stored agent takes up
+    fn storage_usage(&self, agent_id: &AgentId) -> Result<StorageUsage> {
+        let snapshot_bytes = self.stored_size(agent_id)
+            .ok_or_else(|| anyhow!("Agent not found in storage: {}", agent_id))?;
+        Ok(StorageUsage { snapshot_bytes, ..Default::default() })
+    }
+    
+    /// List all stored agents
+    fn list_agents(&self) -> Result<Vec<AgentId>>;
+    
+    /// Delete an agent's snapshot and state
+    fn delete_agent(&self, agent_id: &AgentId) -> Result<()>;
+    
+    /// Move an agent's snapshot and state aside so it is no longer listed or loaded
+    fn quarantine_agent(&self, agent_id: &AgentId) -> Result<()>;
+    
+    /// Append trace entries to the stored trace log
+    fn append_traces(&self, entries: &[TraceEntry]) -> Result<()>;
+    
+    /// Load the stored trace log
+    fn load_traces(&self) -> Result<Vec<TraceEntry>>;
+    
+    /// Visit the stored trace log's entries in order, without loading them all at once
+    fn scan_traces(&self, visit: &mut dyn FnMut(TraceEntry) -> Result<()>) -> Result<()> {
+        self.load_traces()?.into_iter().try_for_each(visit)
+    }
+    
+    /// Delete trace entries timestamped before `before`, except those of the traces in
+    /// `keep`; returns how many were deleted
+    fn prune_traces(&self, before: i64, keep: &HashSet<TraceId>) -> Result<usize>;
+    
+    /// List the agent's historical snapshots, oldest first
+    fn list_snapshots(&self, _agent_id: &AgentId) -> Result<Vec<SnapshotVersion>> {
+        Ok(Vec::new())
+    }
+    
+    /// Load a historical snapshot as it was taken, without newer write-through state
+    fn load_snapshot(&self, agent_id: &AgentId, timestamp_ms: i64) -> Result<Agent> {
+        Err(anyhow!("No snapshot of agent {} taken at {}: this storage keeps no snapshot history", agent_id, timestamp_ms))
+    }
+    
+    /// Let other processes open the store; called on kernel shutdown, after which the
+    /// backend shouldn't be written
+    fn release_lock(&self) {}
+}
+
+/// Copy every agent and the trace log from one backend to another
+///
+/// Each agent is loaded with its latest write-through state and saved as a full
+/// snapshot, so e.g. a directory store can be moved into SQLite. Quarantined agents
+/// are not copied. Returns the IDs of the copied agents.
+pub fn copy_storage(from: &dyn StorageBackend, to: &dyn StorageBackend) -> Result<Vec<AgentId>> {
+    let mut agent_ids = from.list_agents()?;
+    agent_ids.sort();
+    
+    for agent_id in &agent_ids {
+        let agent = from.load_agent(agent_id)
+            .with_context(|| format!("Failed to load agent {} for copying", agent_id))?;
+        to.save_agent(agent_id, &agent)
+            .with_context(|| format!("Failed to copy agent {}", agent_id))?;
+    }
+    
+    to.append_traces(&from.load_traces()?)
+        .context("Failed to copy trace log")?;
+    Ok(agent_ids)
+}
+
+/// Directory of the trace log: a file per UTC day, one JSON entry per line
+const TRACE_LOG_DIR: &str = "traces";
+
+/// Trace log written before it was split by day, read before the daily files
+const LEGACY_TRACE_LOG_FILE: &str = "traces.jsonl";
+
+/// Trace log file for entries recorded on the UTC day of a Unix timestamp
+fn trace_log_file(timestamp: i64) -> String {
+    let day = chrono::DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+    format!("{}.jsonl", day.format("%Y-%m-%d"))
+}
+
+/// Start of the UTC day a trace log file covers, or `None` for any other file
+fn trace_log_day(file_name: &str) -> Option<i64> {
+    let day = chrono::NaiveDate::parse_from_str(file_name.strip_suffix(".jsonl")?, "%Y-%m-%d").ok()?;
+    Some(day.and_hms_opt(0, 0, 0)?.and_utc().timestamp())
+}
+
+/// Serialize trace entries as JSON lines
+fn trace_lines<'a>(entries: impl IntoIterator<Item = &'a TraceEntry>) -> Result<String> {
+    let mut lines = String::new();
+    for entry in entries {
+        lines.push_str(&serde_json::to_string(entry).context("Failed to serialize trace entry")?);
+        lines.push('\n');
+    }
+    Ok(lines)
+}
+
+fn read_trace_log(file: &Path) -> Result<Vec<TraceEntry>> {
+    let data = fs::read_to_string(file)
+        .with_context(|| format!("Failed to read trace log: {}", file.display()))?;
+    data.lines()
+        .map(|line| serde_json::from_str(line).context("Failed to deserialize trace entry"))
+        .collect()
+}
+
+/// Directory holding quarantined agent directories
+const QUARANTINE_DIR: &str = "quarantine";
+
+/// Previous agent snapshot, loaded when agent.json is unreadable
+const AGENT_BACKUP_FILE: &str = "agent.json.bak";
+
+/// Historical snapshot taken at a Unix millisecond timestamp
+fn history_file(timestamp_ms: i64) -> String {
+    format!("agent.{}.json", timestamp_ms)
+}
+
+/// Timestamp of a historical snapshot file, or `None` for any other file
+fn history_timestamp(file_name: &str) -> Option<i64> {
+    file_name.strip_prefix("agent.")?.strip_suffix(".json")?.parse().ok()
+}
+
+/// Replace a file so readers see either its old or its new contents, never a partial write
+///
+/// The data goes to `<file>.tmp` in the same directory, is synced to disk and then
+/// renamed over the file.
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+    
+    let mut file = fs::File::create(&tmp_path)
+        .with_context(|| format!("Failed to create temporary file: {}", tmp_path.display()))?;
+    file.write_all(data)
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("Failed to write temporary file: {}", tmp_path.display()))?;
+    drop(file);
+    
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to move {} into place", tmp_path.display()))?;
+    
+    // Persist the rename itself; not every platform can sync a directory
+    if let Some(dir) = path.parent() {
+        if let Err(e) = fs::File::open(dir).and_then(|dir| dir.sync_all()) {
+            tracing::debug!("Failed to sync directory {}: {}", dir.display(), e);
+        }
+    }
+    Ok(())
+}
+
+/// Point `backup` at the current contents of `file` before it is replaced
+fn keep_backup(file: &Path, backup: &Path) -> Result<()> {
+    match fs::remove_file(backup) {
+        Ok(()) => {},
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+        Err(e) => return Err(e.into()),
+    }
+    
+    // The file is replaced by rename, so a hard link keeps the old contents without copying
+    if fs::hard_link(file, backup).is_err() {
+        fs::copy(file, backup)?;
+    }
+    Ok(())
+}
+
+/// How snapshot and state payloads are transformed on their way to storage
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PayloadCodec {
+    /// zstd level payloads are compressed at, when enabled
+    pub(crate) compression_level: Option<i32>,
+    
+    /// Keys payloads are encrypted with, when enabled
+    pub(crate) encryption: Option<StorageEncryption>,
+}
+
+impl PayloadCodec {
+    /// Compress then encrypt a serialized payload
+    pub(crate) fn pack(&self, data: String) -> Result<Vec<u8>> {
+        let data = compression::compress(self.compression_level, data.into_bytes())?;
+        encryption::seal(self.encryption.as_ref(), data)
+    }
+    
+    /// Undo `pack`, accepting payloads written with any compression setting
+    pub(crate) fn unpack(&self, data: Vec<u8>) -> Result<String> {
+        let data = compression::decompress(encryption::open(self.encryption.as_ref(), data)?)?;
+        String::from_utf8(data).context("Payload is not UTF-8")
+    }
+}
+
+/// Name of the lock file in a storage directory
+const LOCK_FILE: &str = "kernel.lock";
+
+/// Advisory lock on a storage directory, held while a StorageManager has it open
+///
+/// The lock file records the holder's PID for the error other processes get. The OS
+/// drops the lock when the holder exits, so a crashed kernel never leaves it stale.
+#[derive(Debug)]
+struct StorageLock {
+    file: fs::File,
+}
+
+impl StorageLock {
+    /// Take the lock, or fail with [`StorageError::Locked`] naming the holder
+    fn acquire(dir: &Path) -> Result<Self> {
+        let path = dir.join(LOCK_FILE);
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open lock file: {}", path.display()))?;
+        
+        if let Err(e) = file.try_lock_exclusive() {
+            if e.kind() != fs2::lock_contended_error().kind() {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+            }
+            let pid = fs::read_to_string(&path).ok().and_then(|pid| pid.trim().parse().ok());
+            return Err(StorageError::Locked { directory: dir.display().to_string(), pid }.into());
+        }
+        
+        file.set_len(0)
+            .and_then(|()| file.write_all(std::process::id().to_string().as_bytes()))
+            .and_then(|()| file.sync_all())
+            .with_context(|| format!("Failed to write lock file: {}", path.display()))?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for StorageLock {
+    fn drop(&mut self) {
+        if let Err(e) = FileExt::unlock(&self.file) {
+            tracing::warn!("Failed to release storage lock: {}", e);
+        }
+    }
+}
+
+/// Storage manager for agent persistence in a directory
+///
+/// Opening a directory locks it, so a second kernel process pointed at the same
+/// directory fails instead of overwriting this one's snapshots.
 #[derive(Debug)]
 pub struct StorageManager {
     /// Storage directory
     storage_dir: PathBuf,
+    
+    /// Lock on the storage directory, until released on shutdown
+    lock: Mutex<Option<StorageLock>>,
+    
+    /// Held while the trace log is written, so appends and pruning don't interleave
+    trace_log: Mutex<()>,
+    
+    /// Compression and encryption applied to stored payloads
+    codec: PayloadCodec,
+    
+    /// Historical snapshots kept per agent (0 keeps none)
+    history_limit: usize,
+    
+    /// Default storage quota per agent in bytes (0 is unlimited)
+    quota_bytes: u64,
 }
 
 impl StorageManager {
-    /// Create a new StorageManager
+    /// Create a new StorageManager, locking its directory
+    ///
+    /// Fails with [`StorageError::Locked`] if another process has the directory open.
     pub fn new<P: AsRef<Path>>(storage_dir: P) -> Result<Self> {
-        let dir = storage_dir.as_ref().to_path_buf();
-        
+        Self::open(storage_dir.as_ref(), false)
+    }
+    
+    /// Create a StorageManager even if another process holds the directory's lock
+    ///
+    /// For recovery only, such as when the holder is hung and can't be stopped: the
+    /// lock is taken when free, but otherwise the directory is opened without it, and
+    /// two kernels writing to it will overwrite each other's snapshots.
+    pub fn new_forced<P: AsRef<Path>>(storage_dir: P) -> Result<Self> {
+        Self::open(storage_dir.as_ref(), true)
+    }
+    
+    fn open(dir: &Path, force: bool) -> Result<Self> {
         // Create directory if it doesn't exist
         if !dir.exists() {
-            fs::create_dir_all(&dir)
+            fs::create_dir_all(dir)
                 .with_context(|| format!("Failed to create storage directory: {}", dir.display()))?;
         }
         
-        Ok(Self { storage_dir: dir })
+        let lock = match StorageLock::acquire(dir) {
+            Ok(lock) => Some(lock),
+            Err(e) if force => {
+                tracing::warn!("Opening storage without its lock: {:#}", e);
+                None
+            },
+            Err(e) => return Err(e),
+        };
+        
+        let storage = Self::unchecked(dir);
+        *storage.lock.lock().unwrap_or_else(|e| e.into_inner()) = lock;
+        Ok(storage)
+    }
+    
+    /// Create a StorageManager without creating or locking its directory
+    pub(crate) fn unchecked<P: AsRef<Path>>(storage_dir: P) -> Self {
+        Self {
+            storage_dir: storage_dir.as_ref().to_path_buf(),
+            lock: Mutex::new(None),
+            trace_log: Mutex::new(()),
+            codec: PayloadCodec::default(),
+            history_limit: 0,
+            quota_bytes: 0,
+        }
+    }
+    
+    /// Keep the last `limit` snapshots of each agent as agent.<unix_ms>.json for
+    /// point-in-time restore
+    pub fn with_snapshot_history(mut self, limit: usize) -> Self {
+        self.history_limit = limit;
+        self
+    }
+    
+    /// Hold each agent's snapshot, state and history to `bytes`, unless its hardware
+    /// constraints set another quota; 0 is unlimited
+    ///
+    /// Snapshots that would exceed the quota prune the oldest history first and fail
+    /// with [`StorageError::QuotaExceeded`] if that isn't enough.
+    pub fn with_storage_quota(mut self, bytes: u64) -> Self {
+        self.quota_bytes = bytes;
+        self
+    }
+    
+    fn lock_trace_log(&self) -> std::sync::MutexGuard<'_, ()> {
+        self.trace_log.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Trace log files in the order they were written, each with the start of the day
+    /// it covers; the legacy log has none
+    fn trace_log_files(&self) -> Result<Vec<(Option<i64>, PathBuf)>> {
+        let mut files = Vec::new();
+        let legacy = self.storage_dir.join(LEGACY_TRACE_LOG_FILE);
+        if legacy.exists() {
+            files.push((None, legacy));
+        }
+        
+        let trace_dir = self.storage_dir.join(TRACE_LOG_DIR);
+        if trace_dir.exists() {
+            let mut days = Vec::new();
+            for entry in fs::read_dir(&trace_dir)
+                .with_context(|| format!("Failed to read trace log directory: {}", trace_dir.display()))?
+            {
+                let path = entry?.path();
+                if let Some(day) = path.file_name().and_then(|n| n.to_str()).and_then(trace_log_day) {
+                    days.push((Some(day), path));
+                }
+            }
+            days.sort();
+            files.extend(days);
+        }
+        Ok(files)
     }
     
-    /// Save agent to storage
-    pub fn save_agent(&self, agent_id: &AgentId, agent: &Agent) -> Result<()> {
+    /// Read and deserialize a snapshot file, migrating older snapshot versions
+    fn read_snapshot(&self, agent_id: &AgentId, file: &Path) -> Result<Agent> {
+        let agent_data = fs::read(file)
+            .with_context(|| format!("Failed to read agent data from file: {}", file.display()))?;
+        self.codec.unpack(agent_data)
+            .and_then(|data| snapshot::decode(&data))
+            .with_context(|| format!("Failed to deserialize agent: {}", agent_id))
+    }
+    
+    /// Apply a newer write-through state if one exists
+    fn apply_state(&self, agent_id: &AgentId, agent: &mut Agent) -> Result<()> {
+        let state_file = self.storage_dir.join(agent_id).join("state.json");
+        if state_file.exists() {
+            let state_data = fs::read(&state_file)
+                .with_context(|| format!("Failed to read agent state from file: {}", state_file.display()))?;
+            
+            let record: StateRecord = self.codec.unpack(state_data)
+                .and_then(|data| Ok(serde_json::from_str(&data)?))
+                .with_context(|| format!("Failed to deserialize agent state: {}", agent_id))?;
+            
+            record.apply(agent);
+        }
+        Ok(())
+    }
+    
+    /// Historical snapshot files of an agent with their timestamps, oldest first
+    fn history(&self, agent_id: &AgentId) -> Result<Vec<(i64, PathBuf)>> {
+        let agent_dir = self.storage_dir.join(agent_id);
+        if !agent_dir.exists() {
+            return Ok(Vec::new());
+        }
+        
+        let mut history = Vec::new();
+        for entry in fs::read_dir(&agent_dir)
+            .with_context(|| format!("Failed to read agent directory: {}", agent_dir.display()))?
+        {
+            let path = entry?.path();
+            if let Some(timestamp) = path.file_name().and_then(|n| n.to_str()).and_then(history_timestamp) {
+                history.push((timestamp, path));
+            }
+        }
+        history.sort();
+        Ok(history)
+    }
+    
+    /// Record the snapshot just written to agent.json in the history, then drop the
+    /// oldest entries beyond `limit`; the newest entry is always kept
+    fn record_history(&self, agent_id: &AgentId, agent_file: &Path, limit: usize) -> Result<()> {
+        let entry = agent_file.with_file_name(history_file(chrono::Utc::now().timestamp_millis()));
+        keep_backup(agent_file, &entry)
+            .with_context(|| format!("Failed to record snapshot history: {}", entry.display()))?;
+        
+        let history = self.history(agent_id)?;
+        let excess = history.len().saturating_sub(limit.max(1));
+        for (_, path) in &history[..excess] {
+            fs::remove_file(path)
+                .with_context(|| format!("Failed to prune snapshot history: {}", path.display()))?;
+        }
+        Ok(())
+    }
+    
+    /// Encrypt snapshots and state written from now on
+    pub fn with_encryption(mut self, encryption: StorageEncryption) -> Self {
+        self.codec.encryption = Some(encryption);
+        self
+    }
+    
+    /// Compress snapshots and state written from now on at a zstd `level`
+    pub fn with_compression(mut self, level: i32) -> Self {
+        self.codec.compression_level = Some(level);
+        self
+    }
+}
+
+impl StorageBackend for StorageManager {
+    fn save_agent(&self, agent_id: &AgentId, agent: &Agent) -> Result<()> {
         // Create agent directory
         let agent_dir = self.storage_dir.join(agent_id);
         if !agent_dir.exists() {
@@ -42,19 +649,35 @@ impl StorageManager {
         }
         
         // Serialize agent
-        let agent_data = serde_json::to_string(agent)
+        let agent_data = snapshot::encode(agent)
+            .and_then(|data| self.codec.pack(data))
             .with_context(|| format!("Failed to serialize agent: {}", agent_id))?;
         
-        // Write to file
+        // Check the quota before writing, keeping as much recent history as fits
+        let history = if self.history_limit > 0 { self.list_snapshots(agent_id)? } else { Vec::new() };
+        let history = &history[history.len().saturating_sub(self.history_limit.saturating_sub(1))..];
+        let state_size = fs::metadata(agent_dir.join("state.json")).map_or(0, |m| m.len());
+        let kept = history_within_quota(agent_quota(agent, self.quota_bytes), agent_data.len() as u64 + state_size, history)
+            .with_context(|| format!("Agent {} is over its storage quota", agent_id))?;
+        
+        // Write to file, keeping the previous snapshot as a backup
         let agent_file = agent_dir.join("agent.json");
-        fs::write(&agent_file, agent_data)
+        let backup = agent_dir.join(AGENT_BACKUP_FILE);
+        if agent_file.exists() {
+            keep_backup(&agent_file, &backup)
+                .with_context(|| format!("Failed to back up agent snapshot: {}", agent_file.display()))?;
+        }
+        write_atomic(&agent_file, &agent_data)
             .with_context(|| format!("Failed to write agent data to file: {}", agent_file.display()))?;
         
+        if self.history_limit > 0 {
+            self.record_history(agent_id, &agent_file, kept + 1)?;
+        }
+        
         Ok(())
     }
     
-    /// Load agent from storage
-    pub fn load_agent(&self, agent_id: &AgentId) -> Result<Agent> {
+    fn load_agent(&self, agent_id: &AgentId) -> Result<Agent> {
         // Get agent file
         let agent_file = self.storage_dir.join(agent_id).join("agent.json");
         
@@ -63,19 +686,82 @@ impl StorageManager {
             return Err(anyhow!("Agent not found in storage: {}", agent_id));
         }
         
-        // Read file
-        let agent_data = fs::read_to_string(&agent_file)
-            .with_context(|| format!("Failed to read agent data from file: {}", agent_file.display()))?;
-        
-        // Deserialize agent
-        let agent: Agent = serde_json::from_str(&agent_data)
-            .with_context(|| format!("Failed to deserialize agent: {}", agent_id))?;
+        // Fall back to the previous snapshot if the current one is unreadable
+        let backup = self.storage_dir.join(agent_id).join(AGENT_BACKUP_FILE);
+        let mut agent = match self.read_snapshot(agent_id, &agent_file) {
+            Ok(agent) => agent,
+            Err(e) if backup.exists() => {
+                tracing::warn!("Agent snapshot {} is unreadable, loading its backup: {:#}", agent_file.display(), e);
+                self.read_snapshot(agent_id, &backup)
+                    .with_context(|| format!("{:#}; its backup is unreadable too", e))?
+            },
+            Err(e) => return Err(e),
+        };
         
+        self.apply_state(agent_id, &mut agent)?;
         Ok(agent)
     }
     
-    /// List all agents in storage
-    pub fn list_agents(&self) -> Result<Vec<AgentId>> {
+    fn save_state(&self, agent_id: &AgentId, agent: &Agent) -> Result<()> {
+        // Create agent directory
+        let agent_dir = self.storage_dir.join(agent_id);
+        if !agent_dir.exists() {
+            fs::create_dir_all(&agent_dir)
+                .with_context(|| format!("Failed to create agent directory: {}", agent_dir.display()))?;
+        }
+        
+        // Serialize state
+        let state_data = serde_json::to_string(&StateRecord::of(agent))
+            .map_err(anyhow::Error::from)
+            .and_then(|data| self.codec.pack(data))
+            .with_context(|| format!("Failed to serialize agent state: {}", agent_id))?;
+        
+        // Write to file
+        let state_file = agent_dir.join("state.json");
+        write_atomic(&state_file, &state_data)
+            .with_context(|| format!("Failed to write agent state to file: {}", state_file.display()))?;
+        
+        Ok(())
+    }
+    
+    fn has_agent(&self, agent_id: &AgentId) -> bool {
+        self.storage_dir.join(agent_id).join("agent.json").exists()
+    }
+    
+    fn verify_agent(&self, agent_id: &AgentId) -> Result<()> {
+        let agent_file = self.storage_dir.join(agent_id).join("agent.json");
+        if !agent_file.exists() {
+            return Err(anyhow!("Agent not found in storage: {}", agent_id));
+        }
+        
+        let mut agent = self.read_snapshot(agent_id, &agent_file)?;
+        self.apply_state(agent_id, &mut agent)
+    }
+    
+    fn stored_size(&self, agent_id: &AgentId) -> Option<u64> {
+        let agent_dir = self.storage_dir.join(agent_id);
+        let snapshot = fs::metadata(agent_dir.join("agent.json")).ok()?.len();
+        let state = fs::metadata(agent_dir.join("state.json")).map(|m| m.len()).unwrap_or(0);
+        Some(snapshot + state)
+    }
+    
+    fn storage_usage(&self, agent_id: &AgentId) -> Result<StorageUsage> {
+        let agent_dir = self.storage_dir.join(agent_id);
+        let snapshot_bytes = fs::metadata(agent_dir.join("agent.json"))
+            .map_err(|_| anyhow!("Agent not found in storage: {}", agent_id))?
+            .len();
+        
+        // The newest history entry is the current snapshot
+        let history = self.list_snapshots(agent_id)?;
+        Ok(StorageUsage {
+            snapshot_bytes,
+            state_bytes: fs::metadata(agent_dir.join("state.json")).map_or(0, |m| m.len()),
+            history_bytes: history.iter().rev().skip(1).map(|version| version.size).sum(),
+            quota_bytes: None,
+        })
+    }
+    
+    fn list_agents(&self) -> Result<Vec<AgentId>> {
         let mut agents = Vec::new();
         
         // Check if storage directory exists
@@ -108,9 +794,8 @@ impl StorageManager {
         Ok(agents)
     }
     
-    /// Delete agent from storage
-    pub fn delete_agent(&self, agent_id: &AgentId) -> Result<()> {
-        // Get agent directory
+    fn delete_agent(&self, agent_id: &AgentId) -> Result<()> {
+        // Get agent directory, which also holds backups and leftover temporary files
         let agent_dir = self.storage_dir.join(agent_id);
         
         // Check if directory exists
@@ -124,73 +809,480 @@ impl StorageManager {
         
         Ok(())
     }
+    
+    fn quarantine_agent(&self, agent_id: &AgentId) -> Result<()> {
+        let agent_dir = self.storage_dir.join(agent_id);
+        if !agent_dir.exists() {
+            return Err(anyhow!("Agent not found in storage: {}", agent_id));
+        }
+        
+        // Replace an earlier quarantined copy of the same agent
+        let quarantine_dir = self.storage_dir.join(QUARANTINE_DIR);
+        let target = quarantine_dir.join(agent_id);
+        if target.exists() {
+            fs::remove_dir_all(&target)
+                .with_context(|| format!("Failed to remove quarantined agent directory: {}", target.display()))?;
+        }
+        fs::create_dir_all(&quarantine_dir)
+            .with_context(|| format!("Failed to create quarantine directory: {}", quarantine_dir.display()))?;
+        
+        fs::rename(&agent_dir, &target)
+            .with_context(|| format!("Failed to quarantine agent directory: {}", agent_dir.display()))?;
+        
+        Ok(())
+    }
+    
+    fn list_snapshots(&self, agent_id: &AgentId) -> Result<Vec<SnapshotVersion>> {
+        self.history(agent_id)?.into_iter()
+            .map(|(timestamp_ms, path)| Ok(SnapshotVersion {
+                timestamp_ms,
+                size: fs::metadata(&path)
+                    .with_context(|| format!("Failed to read snapshot: {}", path.display()))?
+                    .len(),
+            }))
+            .collect()
+    }
+    
+    fn load_snapshot(&self, agent_id: &AgentId, timestamp_ms: i64) -> Result<Agent> {
+        let file = self.storage_dir.join(agent_id).join(history_file(timestamp_ms));
+        if !file.exists() {
+            return Err(anyhow!("No snapshot of agent {} taken at {}", agent_id, timestamp_ms));
+        }
+        
+        self.read_snapshot(agent_id, &file)
+    }
+    
+    fn release_lock(&self) {
+        self.lock.lock().unwrap_or_else(|e| e.into_inner()).take();
+    }
+    
+    fn append_traces(&self, entries: &[TraceEntry]) -> Result<()> {
+        // Group the entries by day, keeping their order within each file
+        let mut days: Vec<(String, Vec<&TraceEntry>)> = Vec::new();
+        for entry in entries {
+            let file = trace_log_file(entry.timestamp);
+            match days.iter_mut().find(|(day, _)| *day == file) {
+                Some((_, day_entries)) => day_entries.push(entry),
+                None => days.push((file, vec![entry])),
+            }
+        }
+        
+        let _writing = self.lock_trace_log();
+        let trace_dir = self.storage_dir.join(TRACE_LOG_DIR);
+        if !days.is_empty() {
+            fs::create_dir_all(&trace_dir)
+                .with_context(|| format!("Failed to create trace log directory: {}", trace_dir.display()))?;
+        }
+        for (file, day_entries) in days {
+            let trace_file = trace_dir.join(file);
+            let mut file = fs::OpenOptions::new().create(true).append(true).open(&trace_file)
+                .with_context(|| format!("Failed to open trace log: {}", trace_file.display()))?;
+            file.write_all(trace_lines(day_entries)?.as_bytes())
+                .with_context(|| format!("Failed to write trace log: {}", trace_file.display()))?;
+        }
+        
+        Ok(())
+    }
+    
+    fn load_traces(&self) -> Result<Vec<TraceEntry>> {
+        let _reading = self.lock_trace_log();
+        let mut entries = Vec::new();
+        for (_, file) in self.trace_log_files()? {
+            entries.extend(read_trace_log(&file)?);
+        }
+        Ok(entries)
+    }
+    
+    fn scan_traces(&self, visit: &mut dyn FnMut(TraceEntry) -> Result<()>) -> Result<()> {
+        let _reading = self.lock_trace_log();
+        for (_, file) in self.trace_log_files()? {
+            let log = fs::File::open(&file)
+                .with_context(|| format!("Failed to read trace log: {}", file.display()))?;
+            for line in BufReader::new(log).lines() {
+                let line = line.with_context(|| format!("Failed to read trace log: {}", file.display()))?;
+                visit(serde_json::from_str(&line).context("Failed to deserialize trace entry")?)?;
+            }
+        }
+        Ok(())
+    }
+    
+    fn prune_traces(&self, before: i64, keep: &HashSet<TraceId>) -> Result<usize> {
+        let _writing = self.lock_trace_log();
+        let mut pruned = 0;
+        for (day, file) in self.trace_log_files()? {
+            // Only days starting before the cutoff can hold expired entries
+            if day.is_some_and(|start| start >= before) {
+                continue;
+            }
+            
+            let (kept, expired): (Vec<TraceEntry>, Vec<TraceEntry>) = read_trace_log(&file)?.into_iter()
+                .partition(|entry| entry.timestamp >= before || keep.contains(&entry.id));
+            if expired.is_empty() {
+                continue;
+            }
+            pruned += expired.len();
+            
+            if kept.is_empty() {
+                fs::remove_file(&file)
+                    .with_context(|| format!("Failed to delete trace log: {}", file.display()))?;
+            } else {
+                write_atomic(&file, trace_lines(&kept)?.as_bytes())
+                    .with_context(|| format!("Failed to rewrite trace log: {}", file.display()))?;
+            }
+        }
+        Ok(pruned)
+    }
 }
 
-// Global storage functions for easier access
+/// Storage backend keeping serialized agents in memory, for tests and ephemeral kernels
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    /// Serialized agent snapshots
+    agents: Mutex<HashMap<AgentId, String>>,
+    
+    /// Serialized write-through state records
+    states: Mutex<HashMap<AgentId, String>>,
+    
+    /// Quarantined agent snapshots
+    quarantined: Mutex<HashMap<AgentId, String>>,
+    
+    /// Trace log
+    traces: Mutex<Vec<TraceEntry>>,
+}
 
-/// Global storage instance
-static mut STORAGE: Option<StorageManager> = None;
+impl MemoryStorage {
+    /// Create an empty MemoryStorage
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
 
-/// Initialize global storage
-pub fn init_storage<P: AsRef<Path>>(storage_dir: P) -> Result<()> {
-    let storage = StorageManager::new(storage_dir)?;
+impl StorageBackend for MemoryStorage {
+    fn save_agent(&self, agent_id: &AgentId, agent: &Agent) -> Result<()> {
+        let agent_data = snapshot::encode(agent)
+            .with_context(|| format!("Failed to serialize agent: {}", agent_id))?;
+        
+        self.agents.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored agents"))?
+            .insert(agent_id.clone(), agent_data);
+        Ok(())
+    }
+    
+    fn load_agent(&self, agent_id: &AgentId) -> Result<Agent> {
+        let agent_data = self.agents.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored agents"))?
+            .get(agent_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Agent not found in storage: {}", agent_id))?;
+        
+        let mut agent = snapshot::decode(&agent_data)
+            .with_context(|| format!("Failed to deserialize agent: {}", agent_id))?;
+        
+        let state_data = self.states.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored states"))?
+            .get(agent_id)
+            .cloned();
+        if let Some(state_data) = state_data {
+            let record: StateRecord = serde_json::from_str(&state_data)
+                .with_context(|| format!("Failed to deserialize agent state: {}", agent_id))?;
+            record.apply(&mut agent);
+        }
+        
+        Ok(agent)
+    }
+    
+    fn save_state(&self, agent_id: &AgentId, agent: &Agent) -> Result<()> {
+        let state_data = serde_json::to_string(&StateRecord::of(agent))
+            .with_context(|| format!("Failed to serialize agent state: {}", agent_id))?;
+        
+        self.states.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored states"))?
+            .insert(agent_id.clone(), state_data);
+        Ok(())
+    }
     
-    // Set global storage
-    unsafe {
-        STORAGE = Some(storage);
+    fn has_agent(&self, agent_id: &AgentId) -> bool {
+        self.agents.lock()
+            .map(|agents| agents.contains_key(agent_id))
+            .unwrap_or(false)
     }
     
-    Ok(())
+    fn stored_size(&self, agent_id: &AgentId) -> Option<u64> {
+        let snapshot = self.agents.lock().ok()?.get(agent_id)?.len();
+        let state = self.states.lock().ok()?.get(agent_id).map_or(0, String::len);
+        Some((snapshot + state) as u64)
+    }
+    
+    fn storage_usage(&self, agent_id: &AgentId) -> Result<StorageUsage> {
+        let snapshot_bytes = self.agents.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored agents"))?
+            .get(agent_id)
+            .ok_or_else(|| anyhow!("Agent not found in storage: {}", agent_id))?
+            .len();
+        let state_bytes = self.states.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored states"))?
+            .get(agent_id)
+            .map_or(0, String::len);
+        Ok(StorageUsage { snapshot_bytes: snapshot_bytes as u64, state_bytes: state_bytes as u64, ..Default::default() })
+    }
+    
+    fn list_agents(&self) -> Result<Vec<AgentId>> {
+        let agents = self.agents.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored agents"))?;
+        Ok(agents.keys().cloned().collect())
+    }
+    
+    fn delete_agent(&self, agent_id: &AgentId) -> Result<()> {
+        let removed = self.agents.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored agents"))?
+            .remove(agent_id);
+        if removed.is_none() {
+            return Err(anyhow!("Agent not found in storage: {}", agent_id));
+        }
+        
+        if let Ok(mut states) = self.states.lock() {
+            states.remove(agent_id);
+        }
+        Ok(())
+    }
+    
+    fn quarantine_agent(&self, agent_id: &AgentId) -> Result<()> {
+        let agent_data = self.agents.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored agents"))?
+            .remove(agent_id)
+            .ok_or_else(|| anyhow!("Agent not found in storage: {}", agent_id))?;
+        
+        if let Ok(mut states) = self.states.lock() {
+            states.remove(agent_id);
+        }
+        self.quarantined.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on quarantined agents"))?
+            .insert(agent_id.clone(), agent_data);
+        Ok(())
+    }
+    
+    fn append_traces(&self, entries: &[TraceEntry]) -> Result<()> {
+        self.traces.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored traces"))?
+            .extend_from_slice(entries);
+        Ok(())
+    }
+    
+    fn load_traces(&self) -> Result<Vec<TraceEntry>> {
+        let traces = self.traces.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored traces"))?;
+        Ok(traces.clone())
+    }
+    
+    fn prune_traces(&self, before: i64, keep: &HashSet<TraceId>) -> Result<usize> {
+        let mut traces = self.traces.lock()
+            .map_err(|_| anyhow!("Failed to acquire lock on stored traces"))?;
+        let count = traces.len();
+        traces.retain(|entry| entry.timestamp >= before || keep.contains(&entry.id));
+        Ok(count - traces.len())
+    }
 }
 
-/// Save agent to storage
-pub fn save_agent(agent_id: &AgentId, agent: &Agent) -> Result<()> {
-    // Get global storage
-    let storage = unsafe {
-        match &STORAGE {
-            Some(s) => s,
-            None => return Err(anyhow!("Storage not initialized")),
-        }
-    };
+/// Storage backend that refuses every operation
+///
+/// Stands in when the configured store can't be opened safely, such as when its
+/// encryption key is unreadable, so agents are never written without the protection
+/// the config asks for.
+#[derive(Debug)]
+pub(crate) struct UnavailableStorage {
+    reason: String,
+}
+
+impl UnavailableStorage {
+    pub(crate) fn new(reason: &anyhow::Error) -> Self {
+        Self { reason: format!("{:#}", reason) }
+    }
     
-    storage.save_agent(agent_id, agent)
+    fn error(&self) -> anyhow::Error {
+        anyhow!("Storage is unavailable: {}", self.reason)
+    }
 }
 
-/// Load agent from storage
-pub fn load_agent(agent_id: &AgentId) -> Result<Agent> {
-    // Get global storage
-    let storage = unsafe {
-        match &STORAGE {
-            Some(s) => s,
-            None => return Err(anyhow!("Storage not initialized")),
-        }
-    };
+impl StorageBackend for UnavailableStorage {
+    fn save_agent(&self, _agent_id: &AgentId, _agent: &Agent) -> Result<()> {
+        Err(self.error())
+    }
+    
+    fn load_agent(&self, _agent_id: &AgentId) -> Result<Agent> {
+        Err(self.error())
+    }
+    
+    fn save_state(&self, _agent_id: &AgentId, _agent: &Agent) -> Result<()> {
+        Err(self.error())
+    }
+    
+    fn has_agent(&self, _agent_id: &AgentId) -> bool {
+        false
+    }
+    
+    fn list_agents(&self) -> Result<Vec<AgentId>> {
+        Err(self.error())
+    }
+    
+    fn delete_agent(&self, _agent_id: &AgentId) -> Result<()> {
+        Err(self.error())
+    }
+    
+    fn quarantine_agent(&self, _agent_id: &AgentId) -> Result<()> {
+        Err(self.error())
+    }
+    
+    fn append_traces(&self, _entries: &[TraceEntry]) -> Result<()> {
+        Err(self.error())
+    }
+    
+    fn load_traces(&self) -> Result<Vec<TraceEntry>> {
+        Err(self.error())
+    }
     
-    storage.load_agent(agent_id)
+    fn prune_traces(&self, _before: i64, _keep: &HashSet<TraceId>) -> Result<usize> {
+        Err(self.error())
+    }
 }
 
-/// List all agents in storage
-pub fn list_agents() -> Result<Vec<AgentId>> {
-    // Get global storage
-    let storage = unsafe {
-        match &STORAGE {
-            Some(s) => s,
-            None => return Err(anyhow!("Storage not initialized")),
-        }
-    };
+/// Debounced write-through of agent state
+///
+/// State changes mark an agent dirty; a background thread writes the state of
+/// every dirty agent once per flush interval so rapid updates are batched. Once
+/// stopped, pending state is written and later changes are no longer tracked.
+pub(crate) struct StateFlusher {
+    /// Agents with unflushed state changes
+    dirty: Arc<Mutex<HashSet<AgentId>>>,
+    
+    /// Agent store shared with the kernel
+    agents: Arc<DashMap<AgentId, Agent>>,
+    
+    /// Backend the state is written to
+    storage: Arc<dyn StorageBackend>,
+    
+    /// Whether changes are no longer tracked
+    stopped: AtomicBool,
     
-    storage.list_agents()
+    /// Flush thread
+    worker: BackgroundWorker,
 }
 
-/// Delete agent from storage
-pub fn delete_agent(agent_id: &AgentId) -> Result<()> {
-    // Get global storage
-    let storage = unsafe {
-        match &STORAGE {
-            Some(s) => s,
-            None => return Err(anyhow!("Storage not initialized")),
+impl StateFlusher {
+    /// Start the flush thread
+    pub fn start(agents: Arc<DashMap<AgentId, Agent>>, storage: Arc<dyn StorageBackend>, interval: Duration) -> Self {
+        let flusher = Self {
+            dirty: Arc::new(Mutex::new(HashSet::new())),
+            agents,
+            storage,
+            stopped: AtomicBool::new(false),
+            worker: BackgroundWorker::default(),
+        };
+        
+        let dirty = flusher.dirty.clone();
+        let agents = flusher.agents.clone();
+        let storage = flusher.storage.clone();
+        flusher.worker.start("mcp-state-flush", move |signal| {
+            while !signal.wait(interval) {
+                flush_dirty(&agents, storage.as_ref(), &dirty);
+            }
+        });
+        flusher
+    }
+    
+    /// Mark an agent's state as changed; ignored once stopped
+    pub fn mark_dirty(&self, agent_id: &AgentId) {
+        if self.stopped.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Ok(mut dirty) = self.dirty.lock() {
+            dirty.insert(agent_id.clone());
         }
+    }
+    
+    /// Write all pending state immediately
+    pub fn flush(&self) {
+        flush_dirty(&self.agents, self.storage.as_ref(), &self.dirty);
+    }
+    
+    /// Stop the flush thread, waiting for a flush in progress, then write anything
+    /// still pending; nothing is written after this returns
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.worker.stop();
+        self.flush();
+    }
+}
+
+impl Drop for StateFlusher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Write the state of every dirty agent
+fn flush_dirty(agents: &DashMap<AgentId, Agent>, storage: &dyn StorageBackend, dirty: &Mutex<HashSet<AgentId>>) {
+    let pending: Vec<AgentId> = match dirty.lock() {
+        Ok(mut dirty) => dirty.drain().collect(),
+        Err(_) => return,
     };
     
-    storage.delete_agent(agent_id)
+    for agent_id in pending {
+        let agent = match agents.get(&agent_id) {
+            Some(agent) => agent,
+            None => continue,
+        };
+        
+        // Agents without a snapshot need a full one so they can be recovered
+        let result = if storage.has_agent(&agent_id) {
+            storage.save_state(&agent_id, &agent)
+        } else {
+            storage.save_agent(&agent_id, &agent)
+        };
+        
+        if let Err(e) = result {
+            tracing::error!("Failed to persist state for agent {}: {}", agent_id, e);
+        }
+    }
+}
+
+// Global storage functions kept for callers written before kernels owned their storage
+
+/// Storage behind the deprecated free functions, independent of any kernel's
+static STORAGE: Mutex<Option<Arc<StorageManager>>> = Mutex::new(None);
+
+fn global_storage() -> Result<Arc<StorageManager>> {
+    STORAGE.lock().unwrap_or_else(|e| e.into_inner()).clone()
+        .ok_or_else(|| anyhow!("Storage not initialized"))
+}
+
+/// Initialize global storage
+#[deprecated(note = "kernels own their storage; open a `StorageManager` or use `KernelBuilder::with_storage`")]
+pub fn init_storage<P: AsRef<Path>>(storage_dir: P) -> Result<()> {
+    let storage = StorageManager::new(storage_dir)?;
+    *STORAGE.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(storage));
+    Ok(())
+}
+
+/// Save agent to global storage
+#[deprecated(note = "use `StorageBackend::save_agent` on the kernel's storage")]
+pub fn save_agent(agent_id: &AgentId, agent: &Agent) -> Result<()> {
+    global_storage()?.save_agent(agent_id, agent)
+}
+
+/// Load agent from global storage
+#[deprecated(note = "use `StorageBackend::load_agent` on the kernel's storage")]
+pub fn load_agent(agent_id: &AgentId) -> Result<Agent> {
+    global_storage()?.load_agent(agent_id)
+}
+
+/// List all agents in global storage
+#[deprecated(note = "use `StorageBackend::list_agents` on the kernel's storage")]
+pub fn list_agents() -> Result<Vec<AgentId>> {
+    global_storage()?.list_agents()
+}
+
+/// Delete agent from global storage
+#[deprecated(note = "use `StorageBackend::delete_agent` on the kernel's storage")]
+pub fn delete_agent(agent_id: &AgentId) -> Result<()> {
+    global_storage()?.delete_agent(agent_id)
 }
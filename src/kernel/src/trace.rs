@@ -2,15 +2,31 @@
 //!
 //! Implements Poseidon hash-based tracing for agent execution,
 //! providing cryptographic verification of execution paths.
+//!
+//! Each entry's hash covers its fields and the previous entry's hash, so a trace is
+//! a chain that [`verify_entries`] can recompute to find edited, removed or
+//! reordered entries.
 
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use anyhow::{Result, Context, anyhow};
-use sha3::{Digest, Sha3_256};
+use dashmap::DashMap;
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
 
 use crate::agent::AgentId;
+use crate::anchor::{TraceAnchor, TraceSigningKey, ANCHOR_EVENT};
+use crate::events::EventBus;
+use crate::export::{TraceExportFormat, TraceExporter};
+use crate::hasher::{self, Sha3Hasher, TraceHasher};
+use crate::proof;
+use crate::storage::StorageBackend;
+use crate::trace_buffer::TraceBuffer;
 
 /// Trace ID type
 pub type TraceId = String;
@@ -38,6 +54,120 @@ pub struct TraceEntry {
     
     /// Current entry hash
     pub hash: String,
+    
+    /// Name of the hasher that produced `hash`
+    #[serde(default = "default_hasher")]
+    pub hasher: String,
+}
+
+fn default_hasher() -> String {
+    hasher::SHA3_HASHER.to_string()
+}
+
+/// Criteria for `MCPKernel::get_traces`; unset criteria match every entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TraceFilter {
+    /// Agent the entries were recorded for
+    #[serde(default)]
+    pub agent_id: Option<AgentId>,
+    
+    /// Trace the entries belong to
+    #[serde(default)]
+    pub trace_id: Option<TraceId>,
+    
+    /// Exact event type
+    #[serde(default)]
+    pub event_type: Option<String>,
+    
+    /// Earliest timestamp, inclusive
+    #[serde(default)]
+    pub since: Option<i64>,
+    
+    /// Latest timestamp, inclusive
+    #[serde(default)]
+    pub until: Option<i64>,
+    
+    /// Number of matching entries to skip
+    #[serde(default)]
+    pub offset: usize,
+    
+    /// Maximum number of entries to return (unlimited when unset)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl TraceFilter {
+    /// Check whether an entry meets every criterion
+    pub fn matches(&self, entry: &TraceEntry) -> bool {
+        self.agent_id.as_ref().is_none_or(|agent_id| &entry.agent_id == agent_id)
+            && self.trace_id.as_ref().is_none_or(|trace_id| &entry.id == trace_id)
+            && self.event_type.as_ref().is_none_or(|event_type| &entry.event_type == event_type)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+    }
+    
+    /// The page of matching entries selected by `offset` and `limit`, in recorded order
+    pub(crate) fn select<'a>(&self, entries: impl IntoIterator<Item = &'a TraceEntry>) -> Vec<TraceEntry> {
+        entries.into_iter()
+            .filter(|entry| self.matches(entry))
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Why a trace entry failed verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "fault", rename_all = "snake_case")]
+pub enum TraceFault {
+    /// The entry's hash doesn't match its fields and previous hash
+    HashMismatch { expected: String, actual: String },
+    
+    /// The entry doesn't follow on from the previous entry's hash
+    BrokenChain { expected: Option<String>, actual: Option<String> },
+    
+    /// The entry is timestamped before the previous entry
+    OutOfOrder { previous: i64, timestamp: i64 },
+    
+    /// The entry was hashed with a hasher this kernel doesn't have
+    UnknownHasher { hasher: String },
+    
+    /// The entry is an anchor whose signature doesn't check out
+    InvalidAnchor { reason: String },
+}
+
+/// First entry at which a trace failed verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceDivergence {
+    /// Position of the entry in the trace
+    pub index: usize,
+    
+    /// Event type of the entry
+    pub event_type: String,
+    
+    /// What is wrong with the entry
+    pub fault: TraceFault,
+}
+
+/// Outcome of verifying a trace's hash chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceVerificationReport {
+    /// Verified trace
+    pub trace_id: TraceId,
+    
+    /// Number of entries in the trace
+    pub entries: usize,
+    
+    /// First entry that failed verification, if any
+    pub divergence: Option<TraceDivergence>,
+}
+
+impl TraceVerificationReport {
+    /// Whether every entry verified
+    pub fn is_valid(&self) -> bool {
+        self.divergence.is_none()
+    }
 }
 
 /// Trace status
@@ -60,183 +190,737 @@ struct TraceContext {
     /// Agent ID
     agent_id: AgentId,
     
-    /// Intent being traced
-    intent: String,
-    
     /// Last hash in the chain
     last_hash: String,
     
-    /// Start timestamp
-    start_time: i64,
+    /// Monotonic start, for the duration reported when the trace ends; the begin
+    /// entry keeps the wall-clock timestamp
+    started: Instant,
+    
+    /// Age at which the trace is closed as timed out if it hasn't ended (never when
+    /// unset)
+    max_age: Option<Duration>,
+    
+    /// Anchors recorded so far
+    anchors: u64,
+    
+    /// Head of the chain when it was last anchored
+    anchored_hash: Option<String>,
     
     /// Status
     status: TraceStatus,
 }
 
+impl TraceContext {
+    fn is_expired(&self) -> bool {
+        self.max_age.is_some_and(|max_age| self.started.elapsed() > max_age)
+    }
+}
+
+/// Execution tracer the kernel records agent activity with
+///
+/// [`PoseidonTracer`] is the default; tests can supply their own to observe events.
+pub trait Tracer: Send + Sync {
+    /// Begin a new trace for an agent execution
+    fn begin_trace(&self, agent_id: &AgentId, intent: &str) -> Result<TraceId> {
+        self.begin_trace_with_params(agent_id, intent, &Value::Null)
+    }
+    
+    /// Begin a new trace for an agent execution, recording the intent params
+    fn begin_trace_with_params(&self, agent_id: &AgentId, intent: &str, params: &Value) -> Result<TraceId>;
+    
+    /// Begin a trace that may stay active for up to `max_age`, overriding the tracer's
+    /// limit for a known long operation; tracers without a limit ignore it
+    fn begin_trace_with_max_age(&self, agent_id: &AgentId, intent: &str, params: &Value, _max_age: Duration) -> Result<TraceId> {
+        self.begin_trace_with_params(agent_id, intent, params)
+    }
+    
+    /// End a trace
+    fn end_trace(&self, trace_id: &TraceId, success: bool, result: Option<&Value>) -> Result<()>;
+    
+    /// Record an event in the agent's current trace: the one entered on this thread
+    /// with [`enter_trace`], or else whatever fallback the tracer offers
+    fn record_event(&self, agent_id: &AgentId, event_type: &str, data: &Value) -> Result<()>;
+    
+    /// Record an event in an active trace
+    fn record_trace_event(&self, trace_id: &TraceId, event_type: &str, data: &Value) -> Result<()>;
+    
+    /// Write entries not yet persisted to storage, returning how many were written
+    fn flush(&self, _storage: &dyn StorageBackend) -> Result<usize> {
+        Ok(0)
+    }
+    
+    /// Number of entries held in memory
+    fn entry_count(&self) -> usize {
+        0
+    }
+    
+    /// Traces still being recorded, whose stored entries must not be pruned
+    fn active_trace_ids(&self) -> Vec<TraceId> {
+        Vec::new()
+    }
+    
+    /// Close traces active for longer than their maximum age, returning how many
+    fn close_stale_traces(&self) -> Result<usize> {
+        Ok(0)
+    }
+    
+    /// Sign the heads of active traces that grew since they were last anchored,
+    /// returning how many were anchored
+    fn anchor_traces(&self) -> Result<usize> {
+        Ok(0)
+    }
+    
+    /// Hex-encoded public key of the anchor signing key, if anchoring is enabled
+    fn anchor_public_key(&self) -> Option<String> {
+        None
+    }
+    
+    /// Stream every entry recorded from now on, if the tracer supports it; with
+    /// `backfill`, the stream starts with the entries active traces already have
+    fn subscribe_entries(&self, _backfill: bool) -> Option<Receiver<TraceEntry>> {
+        None
+    }
+    
+    /// Entries not delivered because a subscriber's channel was full
+    fn undelivered_entries(&self) -> u64 {
+        0
+    }
+}
+
+/// Traces begun by this process, mixed into trace IDs
+static TRACE_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Intent of the per-agent traces events fall back to when no trace is current
+pub const GENERAL_INTENT: &str = "general";
+
+/// Terminal entry of a trace closed for outliving its maximum age
+pub const TIMEOUT_EVENT: &str = "trace.timeout";
+
+thread_local! {
+    /// Traces entered on this thread, innermost last
+    static CURRENT_TRACES: RefCell<Vec<(AgentId, TraceId)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Keeps a trace current for its agent on this thread until dropped
+#[must_use = "the trace stops being current when the scope is dropped"]
+pub struct TraceScope {
+    // Scopes are unwound on the thread that entered them
+    _thread: PhantomData<*const ()>,
+}
+
+impl Drop for TraceScope {
+    fn drop(&mut self) {
+        CURRENT_TRACES.with(|traces| traces.borrow_mut().pop());
+    }
+}
+
+/// Make `trace_id` the agent's current trace on this thread, so events recorded for
+/// the agent here are attributed to it
+pub fn enter_trace(agent_id: &AgentId, trace_id: &TraceId) -> TraceScope {
+    CURRENT_TRACES.with(|traces| traces.borrow_mut().push((agent_id.clone(), trace_id.clone())));
+    TraceScope { _thread: PhantomData }
+}
+
+/// The agent's innermost trace entered on this thread
+pub fn current_trace(agent_id: &AgentId) -> Option<TraceId> {
+    CURRENT_TRACES.with(|traces| {
+        traces.borrow().iter().rev()
+            .find(|(id, _)| id == agent_id)
+            .map(|(_, trace_id)| trace_id.clone())
+    })
+}
+
+/// Events of one sampled type seen so far
+#[derive(Debug, Default)]
+struct SampleCounts {
+    seen: u64,
+    sampled_out: u64,
+}
+
+/// Whether an event reports a failure, which sampling never skips
+fn is_failure(event_type: &str, data: &Value) -> bool {
+    event_type.contains("fail")
+        || event_type.contains("error")
+        || data.get("success") == Some(&Value::Bool(false))
+        || data.get("error").is_some()
+}
+
+/// Entries a subscriber's channel holds unless configured otherwise
+const DEFAULT_SUBSCRIBER_CAPACITY: usize = 256;
+
 /// Poseidon tracer implementation
+///
+/// Entries are buffered in memory until flushed to storage. The buffer can be bounded
+/// by entry count and size, evicting the oldest entries first; entries evicted before
+/// a flush are lost, so kernels flush periodically.
+///
+/// Each trace is recorded under locks of its own, in both the active traces and the
+/// buffer, so agents tracing at once only wait on each other while the buffer evicts
+/// or is flushed.
+///
+/// Events recorded for an agent with no current trace are refused unless general
+/// traces are enabled, in which case they go to the agent's long-lived
+/// [`GENERAL_INTENT`] trace.
+///
+/// Subscribers receive every entry as it is stored, each trace's in chain order.
+///
+/// A trace left active past its maximum age, say by a caller that never ended it, is
+/// closed as failed with a `trace.timeout` entry, by [`Tracer::close_stale_traces`] or
+/// when an event is next recorded in it. General traces never time out.
+///
+/// With a signing key, active traces are anchored by [`Tracer::anchor_traces`] and
+/// every trace once more as it closes, after its terminal entry.
+///
+/// Event types can be sampled to cut hashing costs for chatty agents. A skipped event
+/// never enters its trace's chain, so sampled traces still verify.
 pub struct PoseidonTracer {
     /// Active traces
-    active_traces: Arc<RwLock<HashMap<TraceId, TraceContext>>>,
+    active_traces: DashMap<TraceId, TraceContext>,
+    
+    /// Recent trace entries (in-memory cache, actual storage is done separately)
+    buffer: TraceBuffer,
     
-    /// All trace entries (in-memory cache, actual storage is done separately)
-    entries: Arc<RwLock<Vec<TraceEntry>>>,
+    /// Whether events without a current trace go to the agent's general trace
+    general_traces: bool,
+    
+    /// Each agent's general trace, once begun
+    general_trace_ids: DashMap<AgentId, TraceId>,
+    
+    /// Hash function of new entries
+    hasher: Arc<dyn TraceHasher>,
+    
+    /// Streams of stored entries
+    subscribers: EventBus<TraceEntry>,
+    
+    /// Fraction of events of each type recorded; unlisted types are always recorded
+    sample_rates: HashMap<String, f64>,
+    
+    /// Events of each sampled type seen and skipped
+    sampling: Mutex<HashMap<String, SampleCounts>>,
+    
+    /// Default age at which active traces time out (never when unset)
+    max_trace_age: Option<Duration>,
+    
+    /// Traces closed for timing out
+    timed_out: AtomicU64,
+    
+    /// Key trace chain heads are anchored with (no anchors when unset)
+    signer: Option<Arc<TraceSigningKey>>,
 }
 
 impl PoseidonTracer {
     /// Create a new PoseidonTracer instance
     pub fn new() -> Self {
         Self {
-            active_traces: Arc::new(RwLock::new(HashMap::new())),
-            entries: Arc::new(RwLock::new(Vec::new())),
+            active_traces: DashMap::new(),
+            buffer: TraceBuffer::default(),
+            general_traces: false,
+            general_trace_ids: DashMap::new(),
+            hasher: Arc::new(Sha3Hasher),
+            subscribers: EventBus::new(DEFAULT_SUBSCRIBER_CAPACITY),
+            sample_rates: HashMap::new(),
+            sampling: Mutex::new(HashMap::new()),
+            max_trace_age: None,
+            timed_out: AtomicU64::new(0),
+            signer: None,
         }
     }
     
-    /// Begin a new trace for an agent execution
-    pub fn begin_trace(&self, agent_id: &AgentId, intent: &str) -> Result<TraceId> {
+    /// Anchor trace chain heads, signed with `key`
+    pub fn with_signing_key(mut self, key: TraceSigningKey) -> Self {
+        self.signer = Some(Arc::new(key));
+        self
+    }
+    
+    /// Close traces still active after `max_age` as timed out; zero never does
+    pub fn with_max_trace_age(mut self, max_age: Duration) -> Self {
+        self.max_trace_age = Some(max_age).filter(|max_age| !max_age.is_zero());
+        self
+    }
+    
+    /// Record only a fraction of the events of each listed type, from 0.0 (none) to
+    /// 1.0 (all), evenly spaced; failures are always recorded
+    pub fn with_sample_rates(mut self, rates: HashMap<String, f64>) -> Self {
+        self.sample_rates = rates.into_iter()
+            .map(|(event_type, rate)| (event_type, rate.clamp(0.0, 1.0)))
+            .collect();
+        self
+    }
+    
+    /// Give each subscriber a channel of `capacity` entries
+    pub fn with_subscriber_capacity(mut self, capacity: usize) -> Self {
+        self.subscribers = EventBus::new(capacity);
+        self
+    }
+    
+    /// Hash new entries with `hasher` rather than SHA3
+    ///
+    /// Entries record the hasher's name, and are verified with the built-in hasher of
+    /// that name.
+    pub fn with_hasher(mut self, hasher: Arc<dyn TraceHasher>) -> Self {
+        self.hasher = hasher;
+        self
+    }
+    
+    /// Record events for agents with no current trace in a general trace per agent,
+    /// started on first use, rather than refusing them
+    pub fn with_general_traces(mut self, enabled: bool) -> Self {
+        self.general_traces = enabled;
+        self
+    }
+    
+    /// Bound the in-memory buffer to `max_entries` entries and `max_bytes` serialized
+    /// bytes, evicting the oldest entries first; 0 leaves a bound unlimited
+    pub fn with_buffer_limits(mut self, max_entries: usize, max_bytes: usize) -> Self {
+        self.buffer = TraceBuffer::new(max_entries, max_bytes);
+        self
+    }
+    
+    /// Begin a trace
+    ///
+    /// The begin entry's hash only covers the second a trace started in, so trace IDs
+    /// also hash the start in nanoseconds and a sequence number; traces of one intent
+    /// begun together still get IDs of their own.
+    fn start_trace(&self, agent_id: &AgentId, intent: &str, params: &Value, max_age: Option<Duration>) -> Result<TraceId> {
         let now = chrono::Utc::now().timestamp();
         
         // Create initial hash from agent_id + intent + timestamp
-        let initial_data = format!("{}:{}:{}", agent_id, intent, now);
-        let initial_hash = self.compute_hash(&initial_data, None);
+        let data = serde_json::json!({
+            "intent": intent,
+            "params": params,
+            "timestamp": now
+        });
+        let initial_hash = self.hasher.hash(&hash_input(agent_id, "trace.begin", &data, now), None);
         
         // Generate trace ID
-        let trace_id = format!("trace_{}", &initial_hash[..16]);
+        let nonce = format!(
+            "{}:{}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+            TRACE_SEQUENCE.fetch_add(1, Ordering::Relaxed),
+        );
+        let trace_id = format!("trace_{}", &Sha3Hasher.hash(&nonce, Some(&initial_hash))[..16]);
         
-        // Create trace context
-        let context = TraceContext {
-            id: trace_id.clone(),
-            agent_id: agent_id.clone(),
-            intent: intent.to_string(),
-            last_hash: initial_hash.clone(),
-            start_time: now,
-            status: TraceStatus::Active,
-        };
-        
-        // Store trace context
-        let mut active_traces = self.active_traces.write()
-            .map_err(|_| anyhow!("Failed to acquire write lock on active traces"))?;
-        active_traces.insert(trace_id.clone(), context);
-        
-        // Create and store initial trace entry
+        // Create and store initial trace entry, ahead of anything else in the trace
         let entry = TraceEntry {
             id: trace_id.clone(),
             agent_id: agent_id.clone(),
             event_type: "trace.begin".to_string(),
-            data: serde_json::json!({
-                "intent": intent,
-                "timestamp": now
-            }),
+            data,
             timestamp: now,
             prev_hash: None,
-            hash: initial_hash,
+            hash: initial_hash.clone(),
+            hasher: self.hasher.name().to_string(),
         };
         
         self.store_entry(entry)?;
         
+        // Store trace context
+        self.active_traces.insert(trace_id.clone(), TraceContext {
+            id: trace_id.clone(),
+            agent_id: agent_id.clone(),
+            last_hash: initial_hash,
+            started: Instant::now(),
+            max_age,
+            anchors: 0,
+            anchored_hash: None,
+            status: TraceStatus::Active,
+        });
+        
         tracing::debug!("Started trace {} for agent {}", trace_id, agent_id);
         Ok(trace_id)
     }
     
-    /// End a trace
-    pub fn end_trace(&self, trace_id: &TraceId, success: bool, result: Option<&Value>) -> Result<()> {
-        // Get trace context
-        let mut active_traces = self.active_traces.write()
-            .map_err(|_| anyhow!("Failed to acquire write lock on active traces"))?;
+    /// The agent's general trace, begun if it has none
+    fn general_trace(&self, agent_id: &AgentId) -> Result<TraceId> {
+        // The agent's entry stays locked while its trace begins, so it only gets one
+        let mut general = self.general_trace_ids.entry(agent_id.clone()).or_default();
+        if !self.active_traces.contains_key(general.value()) {
+            *general = self.start_trace(agent_id, GENERAL_INTENT, &Value::Null, None)?;
+        }
+        Ok(general.clone())
+    }
+    
+    /// Append the terminal entry of a trace taken out of the active traces
+    fn close_trace(&self, mut context: TraceContext, event_type: &str, data: Value, status: TraceStatus) -> Result<()> {
+        let trace_id = context.id.clone();
+        let agent_id = context.agent_id.clone();
+        let prev_hash = context.last_hash.clone();
+        let now = chrono::Utc::now().timestamp();
+        let hash = self.hasher.hash(&hash_input(&agent_id, event_type, &data, now), Some(&prev_hash));
+        let entry = TraceEntry {
+            id: trace_id.clone(),
+            agent_id,
+            event_type: event_type.to_string(),
+            data,
+            timestamp: now,
+            prev_hash: Some(prev_hash),
+            hash: hash.clone(),
+            hasher: self.hasher.name().to_string(),
+        };
         
-        let context = active_traces.get(trace_id)
-            .ok_or_else(|| anyhow!("Trace not found: {}", trace_id))?;
+        self.store_entry(entry)?;
+        context.last_hash = hash;
+        if self.signer.is_some() {
+            self.append_anchor(&mut context)?;
+        }
+        context.status = status;
+        self.buffer.close(&trace_id);
+        Ok(())
+    }
+    
+    /// Sign the head of a trace's chain, appending the anchor to it
+    fn append_anchor(&self, context: &mut TraceContext) -> Result<()> {
+        let Some(signer) = &self.signer else {
+            return Ok(());
+        };
         
+        let now = chrono::Utc::now().timestamp();
+        let anchor = signer.anchor(&context.agent_id, &context.id, context.anchors + 1, &context.last_hash, now);
+        let data = anchor.entry_data();
+        let hash = self.hasher.hash(&hash_input(&context.agent_id, ANCHOR_EVENT, &data, now), Some(&context.last_hash));
+        self.store_entry(TraceEntry {
+            id: context.id.clone(),
+            agent_id: context.agent_id.clone(),
+            event_type: ANCHOR_EVENT.to_string(),
+            data,
+            timestamp: now,
+            prev_hash: Some(anchor.head_hash),
+            hash: hash.clone(),
+            hasher: self.hasher.name().to_string(),
+        })?;
+        
+        context.anchors = anchor.sequence;
+        context.last_hash = hash.clone();
+        context.anchored_hash = Some(hash);
+        Ok(())
+    }
+    
+    /// The agent's latest buffered anchor, to publish externally
+    pub fn export_anchor(&self, agent_id: &AgentId) -> Option<TraceAnchor> {
+        let filter = TraceFilter {
+            agent_id: Some(agent_id.clone()),
+            event_type: Some(ANCHOR_EVENT.to_string()),
+            ..Default::default()
+        };
+        self.buffer.matching(&filter).iter().rev().find_map(TraceAnchor::from_entry)
+    }
+    
+    /// Close a trace that outlived its maximum age as failed
+    fn time_out(&self, context: TraceContext) -> Result<()> {
+        let trace_id = context.id.clone();
         let agent_id = context.agent_id.clone();
-        let prev_hash = context.last_hash.clone();
+        let duration_ms = context.started.elapsed().as_secs_f64() * 1000.0;
+        let data = serde_json::json!({
+            "success": false,
+            "duration_ms": duration_ms,
+            "max_age_ms": context.max_age.map(|max_age| max_age.as_millis() as u64)
+        });
+        self.close_trace(context, TIMEOUT_EVENT, data, TraceStatus::Failed)?;
+        
+        self.timed_out.fetch_add(1, Ordering::Relaxed);
+        metrics::increment_counter!("mcp.trace.timed_out");
+        tracing::warn!("Closed trace {} of agent {} after {:.0} ms without an end", trace_id, agent_id, duration_ms);
+        Ok(())
+    }
+    
+    /// Traces closed for outliving their maximum age
+    pub fn timed_out_traces(&self) -> u64 {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+    
+    /// Store a trace entry
+    fn store_entry(&self, entry: TraceEntry) -> Result<()> {
+        // Store in memory cache, streaming to subscribers as it goes in so a
+        // backfilling subscriber sees each entry exactly once
+        self.buffer.push(entry, |entry| self.subscribers.publish(|| entry.clone()));
+        Ok(())
+    }
+    
+    /// Whether to record an event, counting it as sampled out if not
+    fn sample(&self, event_type: &str, data: &Value) -> bool {
+        let Some(&rate) = self.sample_rates.get(event_type) else {
+            return true;
+        };
+        if is_failure(event_type, data) {
+            return true;
+        }
+        
+        let mut sampling = self.sampling.lock().unwrap_or_else(|e| e.into_inner());
+        let counts = sampling.entry(event_type.to_string()).or_default();
+        let seen = counts.seen as f64;
+        counts.seen += 1;
+        
+        // Keep an event each time the running total of kept events should go up
+        if ((seen + 1.0) * rate).floor() > (seen * rate).floor() {
+            return true;
+        }
+        counts.sampled_out += 1;
+        metrics::counter!("mcp.trace.sampled_out", 1, "event_type" => event_type.to_string());
+        false
+    }
+    
+    /// Events skipped by sampling so far, by event type
+    pub fn sampled_out(&self) -> HashMap<String, u64> {
+        self.sampling.lock().unwrap_or_else(|e| e.into_inner()).iter()
+            .map(|(event_type, counts)| (event_type.clone(), counts.sampled_out))
+            .collect()
+    }
+    
+    /// Serialized bytes held in the buffer
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffer.bytes()
+    }
+    
+    /// Entries evicted from the buffer before they were written to storage
+    pub fn dropped_entries(&self) -> u64 {
+        self.buffer.dropped()
+    }
+    
+    /// Stream every entry stored from now on
+    ///
+    /// Each subscriber gets a channel of the configured capacity. Entries are dropped
+    /// (and counted in `undelivered_entries`) rather than blocking tracing when a
+    /// subscriber falls behind; dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<TraceEntry> {
+        self.subscribers.subscribe()
+    }
+    
+    /// Stream every entry stored from now on, preceded by the buffered entries of the
+    /// traces still active, so a subscriber joining mid-trace sees it from the start
+    ///
+    /// Entries already evicted from the buffer can't be backfilled.
+    pub fn subscribe_with_backfill(&self) -> Result<Receiver<TraceEntry>> {
+        Ok(self.buffer.with_open_traces(|backlog| self.subscribers.subscribe_with_backlog(backlog)))
+    }
+    
+    /// Entries not delivered because a subscriber's channel was full
+    pub fn undelivered_entries(&self) -> u64 {
+        self.subscribers.dropped_events()
+    }
+    
+    /// Export a Merkle proof of a buffered trace, checkable with [`verify_zk_proof`]
+    ///
+    /// [`verify_zk_proof`]: crate::proof::verify_zk_proof
+    pub fn export_zk_proof(&self, trace_id: &TraceId) -> Result<Value> {
+        // Get all entries for the trace
+        let entries = self.buffer.trace(trace_id);
+        let trace_entries: Vec<&TraceEntry> = entries.iter().collect();
+        
+        if trace_entries.is_empty() {
+            return Err(anyhow!("No entries found for trace: {}", trace_id));
+        }
+        
+        proof::export(trace_id, &trace_entries)
+            .ok_or_else(|| anyhow!("Failed to export proof of trace: {}", trace_id))
+    }
+    
+    /// Buffered entries matching a filter, in the order they were recorded
+    ///
+    /// Entries of each trace are recorded in chain order, so interleaved traces each
+    /// come back as their own unbroken sequence.
+    pub fn query(&self, filter: &TraceFilter) -> Vec<TraceEntry> {
+        filter.select(&self.buffer.matching(filter))
+    }
+    
+    /// Write the buffered entries `filter` selects to `writer`, returning how many were
+    /// written
+    pub fn export<W: std::io::Write>(&self, filter: &TraceFilter, format: TraceExportFormat, writer: W) -> Result<usize> {
+        let mut exporter = TraceExporter::new(writer, format, filter)?;
+        for entry in &self.buffer.matching(filter) {
+            if exporter.is_complete() {
+                break;
+            }
+            exporter.write(entry)?;
+        }
+        exporter.finish()
+    }
+    
+    /// Recompute a buffered trace's hash chain, reporting the first entry that diverges
+    ///
+    /// A trace whose first entries were evicted from the buffer diverges at its first
+    /// buffered entry; `MCPKernel::verify_trace` checks the stored trace instead.
+    pub fn verify_trace(&self, trace_id: &TraceId) -> Result<TraceVerificationReport> {
+        let trusted_key = self.anchor_public_key();
+        verify_entries_with_key(trace_id, &self.buffer.trace(trace_id), trusted_key.as_deref())
+            .ok_or_else(|| anyhow!("No entries found for trace: {}", trace_id))
+    }
+    
+    /// Verify every buffered trace, in the order they were started
+    pub fn verify_all(&self) -> Result<Vec<TraceVerificationReport>> {
+        Ok(self.buffer.traces().iter()
+            .filter_map(|entries| verify_entries(&entries.first()?.id, entries))
+            .collect())
+    }
+}
+
+/// The fields an entry's hash covers, besides the previous hash
+///
+/// A trace's first entry covers the agent, intent and timestamp, its last the end
+/// data, and every other entry its event type, data and timestamp.
+fn hash_input(agent_id: &AgentId, event_type: &str, data: &Value, timestamp: i64) -> String {
+    let data_json = || serde_json::to_string(data).unwrap_or_default();
+    match event_type {
+        "trace.begin" => format!("{}:{}:{}", agent_id, data["intent"].as_str().unwrap_or_default(), timestamp),
+        "trace.end" => data_json(),
+        _ => format!("{}:{}:{}", event_type, data_json(), timestamp),
+    }
+}
+
+/// The hash an entry should have, given its fields and previous hash, or None if its
+/// hasher isn't built in
+pub(crate) fn entry_hash(entry: &TraceEntry) -> Option<String> {
+    let input = hash_input(&entry.agent_id, &entry.event_type, &entry.data, entry.timestamp);
+    hasher::named(&entry.hasher).map(|hasher| hasher.hash(&input, entry.prev_hash.as_deref()))
+}
+
+/// Verify the hash chain of one trace among `entries`, or None if it has no entries
+///
+/// Each entry's hash is recomputed from its fields and must match; it must follow on
+/// from the previous entry's hash, the first entry from none; and timestamps must
+/// not go backwards.
+pub(crate) fn verify_entries<'a>(
+    trace_id: &TraceId,
+    entries: impl IntoIterator<Item = &'a TraceEntry>,
+) -> Option<TraceVerificationReport> {
+    verify_entries_with_key(trace_id, entries, None)
+}
+
+/// Verify the hash chain of one trace, as [`verify_entries`], also requiring its
+/// anchors to be signed with `trusted_key` when one is given
+pub(crate) fn verify_entries_with_key<'a>(
+    trace_id: &TraceId,
+    entries: impl IntoIterator<Item = &'a TraceEntry>,
+    trusted_key: Option<&str>,
+) -> Option<TraceVerificationReport> {
+    let mut count = 0;
+    let mut divergence = None;
+    let mut previous: Option<&TraceEntry> = None;
+    
+    for entry in entries.into_iter().filter(|entry| &entry.id == trace_id) {
+        if divergence.is_none() {
+            divergence = check_entry(entry, previous, trusted_key).map(|fault| TraceDivergence {
+                index: count,
+                event_type: entry.event_type.clone(),
+                fault,
+            });
+        }
+        previous = Some(entry);
+        count += 1;
+    }
+    
+    (count > 0).then(|| TraceVerificationReport { trace_id: trace_id.clone(), entries: count, divergence })
+}
+
+fn check_entry(entry: &TraceEntry, previous: Option<&TraceEntry>, trusted_key: Option<&str>) -> Option<TraceFault> {
+    let expected_prev = previous.map(|previous| &previous.hash);
+    if entry.prev_hash.as_ref() != expected_prev {
+        return Some(TraceFault::BrokenChain { expected: expected_prev.cloned(), actual: entry.prev_hash.clone() });
+    }
+    if let Some(previous) = previous {
+        if entry.timestamp < previous.timestamp {
+            return Some(TraceFault::OutOfOrder { previous: previous.timestamp, timestamp: entry.timestamp });
+        }
+    }
+    
+    let Some(expected) = entry_hash(entry) else {
+        return Some(TraceFault::UnknownHasher { hasher: entry.hasher.clone() });
+    };
+    if expected != entry.hash {
+        return Some(TraceFault::HashMismatch { expected, actual: entry.hash.clone() });
+    }
+    if entry.event_type == ANCHOR_EVENT {
+        return check_anchor(entry, trusted_key).map(|reason| TraceFault::InvalidAnchor { reason: reason.to_string() });
+    }
+    None
+}
+
+/// Why an anchor entry is invalid, if it is
+fn check_anchor(entry: &TraceEntry, trusted_key: Option<&str>) -> Option<&'static str> {
+    let Some(anchor) = TraceAnchor::from_entry(entry) else {
+        return Some("malformed anchor");
+    };
+    if entry.prev_hash.as_ref() != Some(&anchor.head_hash) {
+        return Some("anchor doesn't sign the entry it follows");
+    }
+    if !anchor.verify() {
+        return Some("bad signature");
+    }
+    if trusted_key.is_some_and(|trusted_key| anchor.public_key != trusted_key) {
+        return Some("signed with an untrusted key");
+    }
+    None
+}
+
+impl Tracer for PoseidonTracer {
+    fn begin_trace_with_params(&self, agent_id: &AgentId, intent: &str, params: &Value) -> Result<TraceId> {
+        self.start_trace(agent_id, intent, params, self.max_trace_age)
+    }
+    
+    fn begin_trace_with_max_age(&self, agent_id: &AgentId, intent: &str, params: &Value, max_age: Duration) -> Result<TraceId> {
+        self.start_trace(agent_id, intent, params, Some(max_age).filter(|max_age| !max_age.is_zero()))
+    }
+    
+    fn end_trace(&self, trace_id: &TraceId, success: bool, result: Option<&Value>) -> Result<()> {
+        // Take the trace context, so nothing more can be recorded in the trace
+        let (_, context) = self.active_traces.remove(trace_id)
+            .ok_or_else(|| anyhow!("Trace not found: {}", trace_id))?;
         let status = if success { TraceStatus::Completed } else { TraceStatus::Failed };
         
         // Create end trace entry
-        let now = chrono::Utc::now().timestamp();
-        let duration = now - context.start_time;
+        let duration_ms = context.started.elapsed().as_secs_f64() * 1000.0;
+        metrics::histogram!("mcp.trace.duration_ms", duration_ms);
         
         let data = match result {
             Some(r) => serde_json::json!({
                 "success": success,
-                "duration_ms": duration * 1000,
+                "duration_ms": duration_ms,
                 "result": r
             }),
             None => serde_json::json!({
                 "success": success,
-                "duration_ms": duration * 1000
+                "duration_ms": duration_ms
             }),
         };
-        
-        // Compute hash
-        let hash = self.compute_hash(&serde_json::to_string(&data).unwrap_or_default(), Some(&prev_hash));
-        
-        // Create and store trace entry
-        let entry = TraceEntry {
-            id: trace_id.clone(),
-            agent_id,
-            event_type: "trace.end".to_string(),
-            data,
-            timestamp: now,
-            prev_hash: Some(prev_hash),
-            hash,
-        };
-        
-        self.store_entry(entry)?;
-        
-        // Update trace context
-        let context = active_traces.get_mut(trace_id)
-            .ok_or_else(|| anyhow!("Trace not found: {}", trace_id))?;
-        context.status = status;
-        
-        // If trace is completed, remove from active traces
-        if status != TraceStatus::Active {
-            active_traces.remove(trace_id);
-        }
+        self.close_trace(context, "trace.end", data, status)?;
         
         tracing::debug!("Ended trace {} with status {:?}", trace_id, status);
         Ok(())
     }
     
-    /// Record an event in a trace
-    pub fn record_event(&self, agent_id: &AgentId, event_type: &str, data: &Value) -> Result<()> {
-        // Default to a new trace if none is active
-        let trace_id = {
-            let active_traces = self.active_traces.read()
-                .map_err(|_| anyhow!("Failed to acquire read lock on active traces"))?;
-            
-            // Find an active trace for this agent
-            let mut trace_id = None;
-            for (id, context) in active_traces.iter() {
-                if &context.agent_id == agent_id && context.status == TraceStatus::Active {
-                    trace_id = Some(id.clone());
-                    break;
-                }
-            }
-            
-            // If no active trace, create a new one
-            match trace_id {
-                Some(id) => id,
-                None => {
-                    drop(active_traces); // Release read lock before calling begin_trace
-                    self.begin_trace(agent_id, "general")?
-                }
-            }
+    fn record_event(&self, agent_id: &AgentId, event_type: &str, data: &Value) -> Result<()> {
+        let trace_id = match current_trace(agent_id) {
+            Some(trace_id) => trace_id,
+            None if self.general_traces => self.general_trace(agent_id)?,
+            None => return Err(anyhow!("Agent {} has no current trace to record {} in", agent_id, event_type)),
         };
-        
-        // Get trace context
-        let mut active_traces = self.active_traces.write()
-            .map_err(|_| anyhow!("Failed to acquire write lock on active traces"))?;
-        
-        let context = active_traces.get_mut(&trace_id)
+        self.record_trace_event(&trace_id, event_type, data)
+    }
+    
+    fn record_trace_event(&self, trace_id: &TraceId, event_type: &str, data: &Value) -> Result<()> {
+        // Get trace context, holding it until the entry is stored so the trace's
+        // entries are stored in chain order
+        let mut context = self.active_traces.get_mut(trace_id)
             .ok_or_else(|| anyhow!("Trace not found: {}", trace_id))?;
+        if context.is_expired() {
+            drop(context);
+            if let Some((_, context)) = self.active_traces.remove_if(trace_id, |_, context| context.is_expired()) {
+                self.time_out(context)?;
+            }
+            return Err(anyhow!("Trace {} timed out before {} was recorded", trace_id, event_type));
+        }
+        if !self.sample(event_type, data) {
+            return Ok(());
+        }
         
+        let agent_id = context.agent_id.clone();
         let prev_hash = context.last_hash.clone();
         
         // Create event entry
         let now = chrono::Utc::now().timestamp();
         
         // Compute hash
-        let hash_input = format!("{}:{}:{}", event_type, serde_json::to_string(data).unwrap_or_default(), now);
-        let hash = self.compute_hash(&hash_input, Some(&prev_hash));
+        let hash = self.hasher.hash(&hash_input(&agent_id, event_type, data, now), Some(&prev_hash));
         
         // Create and store trace entry
         let entry = TraceEntry {
@@ -247,6 +931,7 @@ impl PoseidonTracer {
             timestamp: now,
             prev_hash: Some(prev_hash),
             hash: hash.clone(),
+            hasher: self.hasher.name().to_string(),
         };
         
         self.store_entry(entry)?;
@@ -258,61 +943,62 @@ impl PoseidonTracer {
         Ok(())
     }
     
-    /// Compute a Poseidon hash (simulated with SHA3 for now)
-    fn compute_hash(&self, data: &str, prev_hash: Option<&str>) -> String {
-        let mut hasher = Sha3_256::new();
-        
-        // Include previous hash if available
-        if let Some(prev) = prev_hash {
-            hasher.update(prev.as_bytes());
-            hasher.update(b":");
-        }
-        
-        // Add data
-        hasher.update(data.as_bytes());
-        
-        // Compute hash
-        let result = hasher.finalize();
-        format!("{:x}", result)
+    fn flush(&self, storage: &dyn StorageBackend) -> Result<usize> {
+        self.buffer.flush(storage)
     }
     
-    /// Store a trace entry
-    fn store_entry(&self, entry: TraceEntry) -> Result<()> {
-        // Store in memory cache
-        let mut entries = self.entries.write()
-            .map_err(|_| anyhow!("Failed to acquire write lock on entries"))?;
-        entries.push(entry.clone());
-        
-        // In production, would also persist to storage
-        // This is a simplified implementation
-        Ok(())
+    fn entry_count(&self) -> usize {
+        self.buffer.len()
     }
     
-    /// Export trace to a ZK-compatible format
-    pub fn export_zk_proof(&self, trace_id: &TraceId) -> Result<Value> {
-        // Get all entries for the trace
-        let entries = self.entries.read()
-            .map_err(|_| anyhow!("Failed to acquire read lock on entries"))?;
-        
-        let trace_entries: Vec<&TraceEntry> = entries.iter()
-            .filter(|e| &e.id == trace_id)
+    fn active_trace_ids(&self) -> Vec<TraceId> {
+        self.active_traces.iter().map(|context| context.key().clone()).collect()
+    }
+    
+    fn close_stale_traces(&self) -> Result<usize> {
+        let stale: Vec<TraceId> = self.active_traces.iter()
+            .filter(|context| context.is_expired())
+            .map(|context| context.key().clone())
             .collect();
         
-        if trace_entries.is_empty() {
-            return Err(anyhow!("No entries found for trace: {}", trace_id));
+        // A trace ended meanwhile is left alone
+        let mut closed = 0;
+        for trace_id in &stale {
+            if let Some((_, context)) = self.active_traces.remove_if(trace_id, |_, context| context.is_expired()) {
+                self.time_out(context)?;
+                closed += 1;
+            }
         }
-        
-        // Create proof structure
-        // This is a simplified implementation
-        let proof = serde_json::json!({
-            "trace_id": trace_id,
-            "agent_id": trace_entries[0].agent_id,
-            "entries": trace_entries.len(),
-            "root_hash": trace_entries.last().map(|e| &e.hash).unwrap_or(&String::new()),
-            "timestamp": chrono::Utc::now().timestamp(),
-        });
-        
-        Ok(proof)
+        Ok(closed)
+    }
+    
+    fn anchor_traces(&self) -> Result<usize> {
+        if self.signer.is_none() {
+            return Ok(0);
+        }
+        let mut anchored = 0;
+        for mut context in self.active_traces.iter_mut() {
+            if context.anchored_hash.as_ref() != Some(&context.last_hash) {
+                self.append_anchor(&mut context)?;
+                anchored += 1;
+            }
+        }
+        Ok(anchored)
+    }
+    
+    fn anchor_public_key(&self) -> Option<String> {
+        self.signer.as_ref().map(|signer| signer.public_key())
+    }
+    
+    fn subscribe_entries(&self, backfill: bool) -> Option<Receiver<TraceEntry>> {
+        match backfill {
+            true => self.subscribe_with_backfill().ok(),
+            false => Some(self.subscribe()),
+        }
+    }
+    
+    fn undelivered_entries(&self) -> u64 {
+        PoseidonTracer::undelivered_entries(self)
     }
 }
 
@@ -326,6 +1012,41 @@ impl Default for PoseidonTracer {
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_buffer_limits() {
+        let tracer = PoseidonTracer::new().with_buffer_limits(4, 0).with_general_traces(true);
+        let storage = crate::storage::MemoryStorage::new();
+        let agent_id = "test_agent".to_string();
+        for step in 0..3 {
+            tracer.record_event(&agent_id, "step", &serde_json::json!({"step": step})).unwrap();
+        }
+        
+        // Flushed entries are evicted first, without loss
+        assert_eq!(tracer.flush(&storage).unwrap(), 4);
+        for step in 3..6 {
+            tracer.record_event(&agent_id, "step", &serde_json::json!({"step": step})).unwrap();
+        }
+        assert_eq!(tracer.entry_count(), 4);
+        assert_eq!(tracer.dropped_entries(), 0);
+        
+        // Then unflushed entries are dropped, oldest first
+        for step in 6..8 {
+            tracer.record_event(&agent_id, "step", &serde_json::json!({"step": step})).unwrap();
+        }
+        assert_eq!(tracer.dropped_entries(), 1);
+        assert_eq!(tracer.flush(&storage).unwrap(), 4);
+        let stored: Vec<Value> = storage.load_traces().unwrap().iter().map(|entry| entry.data["step"].clone()).collect();
+        assert_eq!(stored[4..], [4, 5, 6, 7].map(|step| serde_json::json!(step)));
+        
+        // The size bound always keeps the newest entry
+        let tracer = PoseidonTracer::new().with_buffer_limits(0, 1).with_general_traces(true);
+        tracer.record_event(&agent_id, "step", &serde_json::json!({"step": 0})).unwrap();
+        tracer.record_event(&agent_id, "step", &serde_json::json!({"step": 1})).unwrap();
+        assert_eq!(tracer.entry_count(), 1);
+        assert!(tracer.buffered_bytes() > 1);
+        assert_eq!(tracer.query(&TraceFilter::default())[0].data["step"], 1);
+    }
+    
     #[test]
     fn test_trace_lifecycle() {
         let tracer = PoseidonTracer::new();
@@ -335,8 +1056,8 @@ mod tests {
         let trace_id = tracer.begin_trace(&agent_id, "test_intent").unwrap();
         
         // Record event
-        tracer.record_event(
-            &agent_id,
+        tracer.record_trace_event(
+            &trace_id,
             "test_event",
             &serde_json::json!({"data": "test"}),
         ).unwrap();
@@ -354,4 +1075,325 @@ mod tests {
         assert_eq!(proof["agent_id"], agent_id);
         assert_eq!(proof["entries"], 3); // begin, event, end
     }
+    
+    /// Hasher this kernel doesn't know by name
+    struct ReversedSha3;
+    
+    impl TraceHasher for ReversedSha3 {
+        fn name(&self) -> &'static str {
+            "reversed-sha3"
+        }
+        
+        fn hash(&self, data: &str, prev_hash: Option<&str>) -> String {
+            Sha3Hasher.hash(data, prev_hash).chars().rev().collect()
+        }
+    }
+    
+    /// Record a trace of two steps, returning its ID and entries
+    fn recorded_trace(tracer: &PoseidonTracer) -> (TraceId, Vec<TraceEntry>) {
+        let trace_id = tracer.begin_trace(&"agent_1".to_string(), "work").unwrap();
+        for step in 0..2 {
+            tracer.record_trace_event(&trace_id, "step", &serde_json::json!({"step": step})).unwrap();
+        }
+        tracer.end_trace(&trace_id, true, None).unwrap();
+        (trace_id.clone(), tracer.query(&TraceFilter { trace_id: Some(trace_id), ..Default::default() }))
+    }
+    
+    #[test]
+    fn test_verify_mixed_hashers() {
+        let (sha3_trace, sha3_entries) = recorded_trace(&PoseidonTracer::new());
+        let (custom_trace, custom_entries) = recorded_trace(&PoseidonTracer::new().with_hasher(Arc::new(ReversedSha3)));
+        assert!(custom_entries.iter().all(|entry| entry.hasher == "reversed-sha3"));
+        
+        // Entries written before hashers were recorded were hashed with SHA3
+        let mut stored: Vec<TraceEntry> = sha3_entries.iter()
+            .map(|entry| {
+                let mut entry = serde_json::to_value(entry).unwrap();
+                entry.as_object_mut().unwrap().remove("hasher");
+                serde_json::from_value(entry).unwrap()
+            })
+            .collect();
+        stored.extend(custom_entries);
+        assert!(verify_entries(&sha3_trace, &stored).unwrap().is_valid());
+        
+        // Only built-in hashers can be checked
+        let divergence = verify_entries(&custom_trace, &stored).unwrap().divergence.unwrap();
+        assert_eq!(divergence.index, 0);
+        assert_eq!(divergence.fault, TraceFault::UnknownHasher { hasher: "reversed-sha3".to_string() });
+        
+        #[cfg(feature = "poseidon")]
+        {
+            let tracer = PoseidonTracer::new().with_hasher(Arc::new(crate::hasher::PoseidonHasher::new()));
+            let (poseidon_trace, poseidon_entries) = recorded_trace(&tracer);
+            stored.extend(poseidon_entries);
+            assert!(verify_entries(&poseidon_trace, &stored).unwrap().is_valid());
+            assert!(verify_entries(&sha3_trace, &stored).unwrap().is_valid());
+        }
+    }
+    
+    #[test]
+    fn test_concurrent_traces_per_agent() {
+        let tracer = PoseidonTracer::new();
+        let agent_id = "agent_1".to_string();
+        assert!(tracer.record_event(&agent_id, "orphan", &serde_json::json!({})).is_err());
+        
+        // Two executions of one intent at once, each recording on its own thread
+        let trace_ids: Vec<TraceId> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..2).map(|_| scope.spawn(|| {
+                let trace_id = tracer.begin_trace(&agent_id, "work").unwrap();
+                let _scope = enter_trace(&agent_id, &trace_id);
+                for step in 0..50 {
+                    tracer.record_event(&agent_id, "step", &serde_json::json!({"step": step})).unwrap();
+                }
+                tracer.end_trace(&trace_id, true, None).unwrap();
+                trace_id
+            })).collect();
+            threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+        });
+        assert_ne!(trace_ids[0], trace_ids[1]);
+        assert_eq!(current_trace(&agent_id), None);
+        
+        for trace_id in &trace_ids {
+            let report = tracer.verify_trace(trace_id).unwrap();
+            assert!(report.is_valid(), "{:?}", report);
+            assert_eq!(report.entries, 52);
+        }
+    }
+    
+    #[test]
+    fn test_subscriber_ordering() {
+        let tracer = PoseidonTracer::new().with_subscriber_capacity(1024);
+        let agent_id = "agent_1".to_string();
+        let entries = tracer.subscribe();
+        let full = PoseidonTracer::new().with_subscriber_capacity(1);
+        let slow = full.subscribe();
+        
+        // Concurrent traces interleave on the stream, but each arrives in chain order
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let trace_id = tracer.begin_trace(&agent_id, "work").unwrap();
+                    for step in 0..25 {
+                        tracer.record_trace_event(&trace_id, "step", &serde_json::json!({"step": step})).unwrap();
+                    }
+                    tracer.end_trace(&trace_id, true, None).unwrap();
+                });
+            }
+        });
+        let mut by_trace: HashMap<TraceId, Vec<TraceEntry>> = HashMap::new();
+        for entry in entries.try_iter() {
+            by_trace.entry(entry.id.clone()).or_default().push(entry);
+        }
+        assert_eq!(by_trace.len(), 4);
+        for (trace_id, entries) in &by_trace {
+            assert_eq!(entries.len(), 27);
+            assert!(verify_entries(trace_id, entries).unwrap().is_valid());
+        }
+        assert_eq!(tracer.undelivered_entries(), 0);
+        
+        // A full channel drops entries rather than blocking the tracer
+        let trace_id = full.begin_trace(&agent_id, "work").unwrap();
+        full.end_trace(&trace_id, true, None).unwrap();
+        assert_eq!(slow.try_iter().count(), 1);
+        assert_eq!(full.undelivered_entries(), 1);
+    }
+    
+    #[test]
+    fn test_subscriber_backfill() {
+        let tracer = PoseidonTracer::new();
+        let agent_id = "agent_1".to_string();
+        let done = tracer.begin_trace(&agent_id, "done").unwrap();
+        tracer.end_trace(&done, true, None).unwrap();
+        let active = tracer.begin_trace(&agent_id, "work").unwrap();
+        tracer.record_trace_event(&active, "step", &serde_json::json!({"step": 0})).unwrap();
+        
+        // Joining mid-trace: the backfilled subscriber gets the active trace from its
+        // start, the plain one only what follows
+        let backfilled = tracer.subscribe_with_backfill().unwrap();
+        let live = tracer.subscribe_entries(false).unwrap();
+        tracer.record_trace_event(&active, "step", &serde_json::json!({"step": 1})).unwrap();
+        tracer.end_trace(&active, true, None).unwrap();
+        
+        let entries: Vec<TraceEntry> = backfilled.try_iter().collect();
+        assert_eq!(entries.len(), 4);
+        assert!(verify_entries(&active, &entries).unwrap().is_valid());
+        assert_eq!(live.try_iter().count(), 2);
+    }
+    
+    #[test]
+    fn test_sampling() {
+        let rates = HashMap::from([("agent.heartbeat".to_string(), 0.01), ("never".to_string(), 0.0)]);
+        let tracer = PoseidonTracer::new().with_sample_rates(rates);
+        let agent_id = "agent_1".to_string();
+        let trace_id = tracer.begin_trace(&agent_id, "chatty").unwrap();
+        for beat in 0..1000 {
+            tracer.record_trace_event(&trace_id, "agent.heartbeat", &serde_json::json!({"beat": beat})).unwrap();
+        }
+        tracer.record_trace_event(&trace_id, "agent.heartbeat", &serde_json::json!({"error": "missed"})).unwrap();
+        tracer.record_trace_event(&trace_id, "never", &serde_json::json!({})).unwrap();
+        tracer.record_trace_event(&trace_id, "step", &serde_json::json!({})).unwrap();
+        tracer.end_trace(&trace_id, true, None).unwrap();
+        
+        // One in a hundred heartbeats, the failure and the unsampled step were kept
+        let beats = TraceFilter { event_type: Some("agent.heartbeat".to_string()), ..Default::default() };
+        assert_eq!(tracer.query(&beats).len(), 11);
+        assert_eq!(tracer.sampled_out(), HashMap::from([("agent.heartbeat".to_string(), 990), ("never".to_string(), 1)]));
+        
+        // Skipped events never entered the chain
+        let report = tracer.verify_trace(&trace_id).unwrap();
+        assert!(report.is_valid(), "{:?}", report);
+        assert_eq!(report.entries, 14);
+    }
+    
+    #[test]
+    fn test_trace_timeout() {
+        let tracer = PoseidonTracer::new()
+            .with_general_traces(true)
+            .with_max_trace_age(Duration::from_millis(20));
+        let agent_id = "agent_1".to_string();
+        let stuck = tracer.begin_trace(&agent_id, "stuck").unwrap();
+        let long = tracer.begin_trace_with_max_age(&agent_id, "long", &Value::Null, Duration::from_secs(60)).unwrap();
+        tracer.record_event(&agent_id, "noise", &serde_json::json!({})).unwrap();
+        std::thread::sleep(Duration::from_millis(40));
+        
+        // Only the trace without its own limit is closed; general traces never are
+        assert_eq!(tracer.close_stale_traces().unwrap(), 1);
+        assert_eq!(tracer.timed_out_traces(), 1);
+        assert!(!tracer.active_trace_ids().contains(&stuck));
+        assert_eq!(tracer.active_trace_ids().len(), 2);
+        let entries = tracer.query(&TraceFilter { trace_id: Some(stuck.clone()), ..Default::default() });
+        assert_eq!(entries.last().unwrap().event_type, TIMEOUT_EVENT);
+        assert_eq!(entries.last().unwrap().data["max_age_ms"], 20);
+        assert!(tracer.verify_trace(&stuck).unwrap().is_valid());
+        assert!(tracer.end_trace(&stuck, true, None).is_err());
+        tracer.record_trace_event(&long, "step", &serde_json::json!({})).unwrap();
+        
+        // A stale trace found while recording is closed rather than extended
+        let forgotten = tracer.begin_trace(&agent_id, "forgotten").unwrap();
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(tracer.record_trace_event(&forgotten, "step", &serde_json::json!({})).is_err());
+        assert_eq!(tracer.timed_out_traces(), 2);
+        assert!(tracer.verify_trace(&forgotten).unwrap().is_valid());
+    }
+    
+    #[test]
+    fn test_trace_anchors() {
+        let tracer = PoseidonTracer::new().with_signing_key(TraceSigningKey::from_bytes(&[7; 32]).unwrap());
+        let agent_id = "agent_1".to_string();
+        let trace_id = tracer.begin_trace(&agent_id, "audited").unwrap();
+        tracer.record_trace_event(&trace_id, "step", &serde_json::json!({"n": 1})).unwrap();
+        
+        // Only heads that moved since the last round are anchored
+        assert_eq!(tracer.anchor_traces().unwrap(), 1);
+        assert_eq!(tracer.anchor_traces().unwrap(), 0);
+        tracer.end_trace(&trace_id, true, None).unwrap();
+        
+        let filter = TraceFilter { trace_id: Some(trace_id.clone()), ..Default::default() };
+        let entries = tracer.query(&filter);
+        let events: Vec<&str> = entries.iter().map(|entry| entry.event_type.as_str()).collect();
+        assert_eq!(events, ["trace.begin", "step", ANCHOR_EVENT, "trace.end", ANCHOR_EVENT]);
+        assert!(tracer.verify_trace(&trace_id).unwrap().is_valid());
+        
+        // The latest anchor signs the end of the trace
+        let anchor = tracer.export_anchor(&agent_id).unwrap();
+        assert_eq!((anchor.sequence, &anchor.head_hash), (2, &entries[3].hash));
+        assert_eq!(Some(anchor.public_key.clone()), tracer.anchor_public_key());
+        assert!(anchor.verify());
+        
+        // A consistently rehashed anchor with a forged signature is caught
+        let mut forged = entries.clone();
+        forged[4].data["signature"] = serde_json::json!("00".repeat(64));
+        forged[4].hash = entry_hash(&forged[4]).unwrap();
+        let fault = verify_entries(&trace_id, &forged).unwrap().divergence.unwrap().fault;
+        assert_eq!(fault, TraceFault::InvalidAnchor { reason: "bad signature".to_string() });
+        
+        // As is a trace re-signed with another key, once the kernel's key is trusted
+        let other = PoseidonTracer::new().with_signing_key(TraceSigningKey::from_bytes(&[8; 32]).unwrap());
+        let resigned = other.begin_trace(&agent_id, "audited").unwrap();
+        other.end_trace(&resigned, true, None).unwrap();
+        let entries = other.query(&TraceFilter { trace_id: Some(resigned.clone()), ..Default::default() });
+        assert!(verify_entries(&resigned, &entries).unwrap().is_valid());
+        let report = verify_entries_with_key(&resigned, &entries, tracer.anchor_public_key().as_deref()).unwrap();
+        assert!(matches!(report.divergence.unwrap().fault, TraceFault::InvalidAnchor { .. }));
+        
+        // Without a key nothing is anchored
+        let unsigned = PoseidonTracer::new();
+        let trace_id = unsigned.begin_trace(&agent_id, "plain").unwrap();
+        assert_eq!(unsigned.anchor_traces().unwrap(), 0);
+        unsigned.end_trace(&trace_id, true, None).unwrap();
+        assert!(unsigned.export_anchor(&agent_id).is_none());
+    }
+    
+    #[test]
+    fn test_query_interleaved_traces() {
+        let tracer = PoseidonTracer::new().with_general_traces(true);
+        let (agent_id, other) = ("agent_1".to_string(), "agent_2".to_string());
+        let first = tracer.begin_trace(&agent_id, "first").unwrap();
+        tracer.record_event(&other, "noise", &serde_json::json!({})).unwrap();
+        let second = tracer.begin_trace(&agent_id, "second").unwrap();
+        tracer.end_trace(&first, true, None).unwrap();
+        tracer.record_trace_event(&second, "step", &serde_json::json!({"n": 1})).unwrap();
+        tracer.record_trace_event(&second, "step", &serde_json::json!({"n": 2})).unwrap();
+        tracer.end_trace(&second, false, None).unwrap();
+        
+        // Each trace comes back as an unbroken chain
+        let by_trace = |trace_id: &TraceId| tracer.query(&TraceFilter { trace_id: Some(trace_id.clone()), ..Default::default() });
+        for (trace_id, len) in [(&first, 2), (&second, 4)] {
+            let entries = by_trace(trace_id);
+            assert_eq!(entries.len(), len);
+            assert!(entries.windows(2).all(|pair| pair[1].prev_hash.as_ref() == Some(&pair[0].hash)));
+        }
+        
+        let agent = TraceFilter { agent_id: Some(agent_id.clone()), ..Default::default() };
+        let events: Vec<String> = tracer.query(&agent).into_iter().map(|entry| entry.event_type).collect();
+        assert_eq!(events, ["trace.begin", "trace.begin", "trace.end", "step", "step", "trace.end"]);
+        
+        // Pagination applies after filtering
+        let steps = TraceFilter { event_type: Some("step".to_string()), offset: 1, limit: Some(5), ..agent.clone() };
+        let page = tracer.query(&steps);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].data["n"], 2);
+        
+        let now = chrono::Utc::now().timestamp();
+        let future = TraceFilter { since: Some(now + 60), ..Default::default() };
+        assert!(tracer.query(&future).is_empty());
+        let past = TraceFilter { until: Some(now + 60), ..Default::default() };
+        assert_eq!(tracer.query(&past).len(), 8);
+    }
+    
+    #[test]
+    fn test_verify_trace() {
+        let tracer = PoseidonTracer::new().with_general_traces(true);
+        let (first, second) = ("first_agent".to_string(), "second_agent".to_string());
+        let trace_id = tracer.begin_trace_with_params(&first, "greet", &serde_json::json!({"name": "ada"})).unwrap();
+        tracer.record_event(&second, "agent.spawn", &serde_json::json!({})).unwrap();
+        for step in 0..3 {
+            tracer.record_trace_event(&trace_id, "step", &serde_json::json!({"step": step})).unwrap();
+        }
+        tracer.end_trace(&trace_id, true, None).unwrap();
+        
+        let reports = tracer.verify_all().unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].trace_id, trace_id);
+        assert_eq!(reports[0].entries, 5);
+        assert!(reports.iter().all(TraceVerificationReport::is_valid));
+        
+        // An edited entry is pinpointed by its hash
+        tracer.buffer.tamper(&trace_id, |entries| entries[2].entry.data = serde_json::json!({"step": 9}));
+        let report = tracer.verify_trace(&trace_id).unwrap();
+        let divergence = report.divergence.unwrap();
+        assert_eq!((divergence.index, divergence.event_type.as_str()), (2, "step"));
+        assert!(matches!(divergence.fault, TraceFault::HashMismatch { .. }));
+        
+        // A removed entry breaks the chain at the entry after it
+        tracer.buffer.tamper(&trace_id, |entries| {
+            entries.remove(2);
+        });
+        let divergence = tracer.verify_trace(&trace_id).unwrap().divergence.unwrap();
+        assert_eq!(divergence.index, 2);
+        assert!(matches!(divergence.fault, TraceFault::BrokenChain { .. }));
+        
+        assert!(tracer.verify_all().unwrap()[1].is_valid());
+        assert!(tracer.verify_trace(&"trace_missing".to_string()).is_err());
+    }
 }
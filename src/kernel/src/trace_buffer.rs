@@ -0,0 +1,441 @@
+//! In-memory trace buffer for MCP-ZERO kernel
+//!
+//! Recorded entries are held in a segment per trace, in a sharded map, so agents
+//! recording unrelated traces never wait on each other. Each entry takes a sequence
+//! number as it is stored, and reads spanning traces merge segments back into
+//! recorded order. A small global index of each segment's oldest entry, and of the
+//! traces with entries in each minute, serves eviction and time-range queries; it is
+//! only locked when a trace starts a segment or a new minute, or entries are evicted.
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use anyhow::Result;
+use dashmap::DashMap;
+
+use crate::storage::StorageBackend;
+use crate::trace::{TraceEntry, TraceFilter, TraceId};
+
+/// The minute a timestamp falls in, the granularity of the time index
+fn minute(timestamp: i64) -> i64 {
+    timestamp.div_euclid(60)
+}
+
+/// A buffered entry
+#[derive(Debug)]
+pub(crate) struct BufferedEntry {
+    /// Position in the order entries were stored, across traces
+    sequence: u64,
+    
+    /// Serialized size
+    size: usize,
+    
+    pub(crate) entry: TraceEntry,
+}
+
+/// Buffered entries of one trace, oldest first
+#[derive(Debug, Default)]
+struct TraceSegment {
+    entries: VecDeque<BufferedEntry>,
+    
+    /// Number of leading entries already written to storage
+    flushed: usize,
+    
+    /// Whether the trace has ended
+    closed: bool,
+}
+
+impl TraceSegment {
+    fn has_minute(&self, minute_of: i64) -> bool {
+        self.entries.iter().any(|buffered| minute(buffered.entry.timestamp) == minute_of)
+    }
+}
+
+/// Where to find segments without visiting them all
+#[derive(Debug, Default)]
+struct BufferIndex {
+    /// Each segment's trace, by the sequence number of its oldest entry
+    oldest: BTreeMap<u64, TraceId>,
+    
+    /// Traces with entries in each minute
+    minutes: BTreeMap<i64, HashSet<TraceId>>,
+}
+
+/// Recently recorded entries, by trace
+///
+/// The buffer can be bounded by entry count and size, evicting the oldest entries
+/// first and always keeping the newest. Entries evicted before they were flushed are
+/// counted as dropped.
+#[derive(Debug, Default)]
+pub(crate) struct TraceBuffer {
+    segments: DashMap<TraceId, TraceSegment>,
+    
+    index: Mutex<BufferIndex>,
+    
+    /// Held shared while storing an entry, and exclusively to see or flush every
+    /// segment as of one instant
+    gate: RwLock<()>,
+    
+    /// Next sequence number
+    sequence: AtomicU64,
+    
+    /// Number of buffered entries
+    entries: AtomicUsize,
+    
+    /// Total serialized size of buffered entries
+    bytes: AtomicUsize,
+    
+    /// Entries evicted before they were written to storage
+    dropped: AtomicU64,
+    
+    /// Most entries to buffer (0 is unlimited)
+    max_entries: usize,
+    
+    /// Most serialized bytes to buffer (0 is unlimited)
+    max_bytes: usize,
+}
+
+impl TraceBuffer {
+    /// Create a buffer bounded to `max_entries` entries and `max_bytes` serialized
+    /// bytes; 0 leaves a bound unlimited
+    pub(crate) fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self { max_entries, max_bytes, ..Default::default() }
+    }
+    
+    /// Buffer an entry, evicting the oldest entries over the limits
+    ///
+    /// `stored` is called with the entry before any snapshot of the buffer can see it,
+    /// so what it is handed and what a snapshot holds never overlap. Entries of one
+    /// trace must be pushed one at a time.
+    pub(crate) fn push(&self, entry: TraceEntry, stored: impl FnOnce(&TraceEntry)) {
+        let size = serde_json::to_vec(&entry).map(|data| data.len()).unwrap_or(0);
+        let entry_minute = minute(entry.timestamp);
+        let _gate = self.gate.read().unwrap_or_else(|e| e.into_inner());
+        
+        // Most entries extend their segment within the minute of its last entry,
+        // leaving the index alone
+        let mut index = None;
+        let extended = self.segments.get_mut(&entry.id)
+            .filter(|segment| segment.entries.back().is_some_and(|last| minute(last.entry.timestamp) == entry_minute));
+        let mut segment = match extended {
+            Some(segment) => segment,
+            None => {
+                let index = index.insert(self.lock_index());
+                let segment = self.segments.entry(entry.id.clone()).or_default();
+                if !segment.has_minute(entry_minute) {
+                    index.minutes.entry(entry_minute).or_default().insert(entry.id.clone());
+                }
+                segment
+            },
+        };
+        
+        // Taken with the segment locked, so every segment stays in sequence order
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        if let Some(index) = index.as_mut().filter(|_| segment.entries.is_empty()) {
+            index.oldest.insert(sequence, entry.id.clone());
+        }
+        stored(&entry);
+        segment.entries.push_back(BufferedEntry { sequence, size, entry });
+        drop(segment);
+        self.entries.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(size, Ordering::Relaxed);
+        
+        if self.over_limits() {
+            let mut index = index.unwrap_or_else(|| self.lock_index());
+            self.evict(&mut index);
+        }
+        
+        metrics::gauge!("mcp.trace.buffer_entries", self.len() as f64);
+        metrics::gauge!("mcp.trace.buffer_bytes", self.bytes() as f64);
+        metrics::gauge!("mcp.trace.dropped_entries", self.dropped() as f64);
+    }
+    
+    fn over_limits(&self) -> bool {
+        (self.max_entries > 0 && self.len() > self.max_entries)
+            || (self.max_bytes > 0 && self.bytes() > self.max_bytes)
+    }
+    
+    /// Evict the oldest entries over the limits, always keeping the newest
+    fn evict(&self, index: &mut BufferIndex) {
+        let mut evicted_unflushed = 0;
+        while self.len() > 1 && self.over_limits() {
+            let Some((_, trace_id)) = index.oldest.pop_first() else {
+                break;
+            };
+            let Some(mut segment) = self.segments.get_mut(&trace_id) else {
+                continue;
+            };
+            let Some(oldest) = segment.entries.pop_front() else {
+                continue;
+            };
+            
+            self.entries.fetch_sub(1, Ordering::Relaxed);
+            self.bytes.fetch_sub(oldest.size, Ordering::Relaxed);
+            match segment.flushed {
+                0 => evicted_unflushed += 1,
+                _ => segment.flushed -= 1,
+            }
+            
+            // Entries are nearly always in time order, so the next usually settles it
+            let oldest_minute = minute(oldest.entry.timestamp);
+            let minute_left = segment.entries.front().is_some_and(|next| minute(next.entry.timestamp) == oldest_minute)
+                || segment.has_minute(oldest_minute);
+            if !minute_left {
+                if let Some(traces) = index.minutes.get_mut(&oldest_minute) {
+                    traces.remove(&trace_id);
+                    if traces.is_empty() {
+                        index.minutes.remove(&oldest_minute);
+                    }
+                }
+            }
+            match segment.entries.front() {
+                Some(next) => {
+                    index.oldest.insert(next.sequence, trace_id);
+                },
+                None => {
+                    drop(segment);
+                    self.segments.remove_if(&trace_id, |_, segment| segment.entries.is_empty());
+                },
+            }
+        }
+        
+        if evicted_unflushed > 0 {
+            self.dropped.fetch_add(evicted_unflushed, Ordering::Relaxed);
+            tracing::warn!("Trace buffer full: dropped {} entries not yet written to storage", evicted_unflushed);
+        }
+    }
+    
+    /// Mark a trace as ended
+    pub(crate) fn close(&self, trace_id: &TraceId) {
+        if let Some(mut segment) = self.segments.get_mut(trace_id) {
+            segment.closed = true;
+        }
+    }
+    
+    /// Write the entries not yet flushed to storage in recorded order, returning how
+    /// many were written
+    ///
+    /// Entries can't be stored while a flush is writing.
+    pub(crate) fn flush(&self, storage: &dyn StorageBackend) -> Result<usize> {
+        let _gate = self.gate.write().unwrap_or_else(|e| e.into_inner());
+        let mut pending = Vec::new();
+        for segment in self.segments.iter() {
+            pending.extend(segment.entries.iter().skip(segment.flushed).map(|buffered| (buffered.sequence, buffered.entry.clone())));
+        }
+        pending.sort_unstable_by_key(|(sequence, _)| *sequence);
+        let pending: Vec<TraceEntry> = pending.into_iter().map(|(_, entry)| entry).collect();
+        
+        storage.append_traces(&pending)?;
+        for mut segment in self.segments.iter_mut() {
+            segment.flushed = segment.entries.len();
+        }
+        Ok(pending.len())
+    }
+    
+    /// Call `snapshot` with the buffered entries of the traces still open, in recorded
+    /// order; no entry is stored until it returns
+    pub(crate) fn with_open_traces<T>(&self, snapshot: impl FnOnce(Vec<TraceEntry>) -> T) -> T {
+        let _gate = self.gate.write().unwrap_or_else(|e| e.into_inner());
+        let mut open = Vec::new();
+        for segment in self.segments.iter().filter(|segment| !segment.closed) {
+            open.extend(segment.entries.iter().map(|buffered| (buffered.sequence, buffered.entry.clone())));
+        }
+        open.sort_unstable_by_key(|(sequence, _)| *sequence);
+        snapshot(open.into_iter().map(|(_, entry)| entry).collect())
+    }
+    
+    /// Buffered entries matching a filter, ignoring its offset and limit, in recorded
+    /// order
+    ///
+    /// Only the filtered trace, or the traces with entries in the filtered time range,
+    /// are visited when the filter names them.
+    pub(crate) fn matching(&self, filter: &TraceFilter) -> Vec<TraceEntry> {
+        let mut matching = Vec::new();
+        let mut collect = |segment: &TraceSegment| matching.extend(
+            segment.entries.iter()
+                .filter(|buffered| filter.matches(&buffered.entry))
+                .map(|buffered| (buffered.sequence, buffered.entry.clone())),
+        );
+        
+        if let Some(trace_id) = &filter.trace_id {
+            if let Some(segment) = self.segments.get(trace_id) {
+                collect(&segment);
+            }
+        } else if filter.since.is_some() || filter.until.is_some() {
+            let first = minute(filter.since.unwrap_or(i64::MIN));
+            let last = minute(filter.until.unwrap_or(i64::MAX));
+            let trace_ids: HashSet<TraceId> = match first <= last {
+                true => self.lock_index().minutes.range(first..=last)
+                    .flat_map(|(_, trace_ids)| trace_ids.iter().cloned())
+                    .collect(),
+                false => HashSet::new(),
+            };
+            for trace_id in &trace_ids {
+                if let Some(segment) = self.segments.get(trace_id) {
+                    collect(&segment);
+                }
+            }
+        } else {
+            for segment in self.segments.iter() {
+                collect(&segment);
+            }
+        }
+        
+        matching.sort_unstable_by_key(|(sequence, _)| *sequence);
+        matching.into_iter().map(|(_, entry)| entry).collect()
+    }
+    
+    /// A trace's buffered entries, in chain order
+    pub(crate) fn trace(&self, trace_id: &TraceId) -> Vec<TraceEntry> {
+        self.segments.get(trace_id)
+            .map(|segment| segment.entries.iter().map(|buffered| buffered.entry.clone()).collect())
+            .unwrap_or_default()
+    }
+    
+    /// Every buffered trace's entries, the traces in the order they were started
+    pub(crate) fn traces(&self) -> Vec<Vec<TraceEntry>> {
+        let mut traces: Vec<(u64, Vec<TraceEntry>)> = self.segments.iter()
+            .filter_map(|segment| {
+                let first = segment.entries.front()?.sequence;
+                Some((first, segment.entries.iter().map(|buffered| buffered.entry.clone()).collect()))
+            })
+            .collect();
+        traces.sort_unstable_by_key(|(first, _)| *first);
+        traces.into_iter().map(|(_, entries)| entries).collect()
+    }
+    
+    /// Number of buffered entries
+    pub(crate) fn len(&self) -> usize {
+        self.entries.load(Ordering::Relaxed)
+    }
+    
+    /// Serialized bytes held in the buffer
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+    
+    /// Entries evicted before they were written to storage
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+    
+    /// Edit a trace's buffered entries directly, bypassing the index and counters
+    #[cfg(test)]
+    pub(crate) fn tamper(&self, trace_id: &TraceId, tamper: impl FnOnce(&mut VecDeque<BufferedEntry>)) {
+        if let Some(mut segment) = self.segments.get_mut(trace_id) {
+            tamper(&mut segment.entries);
+        }
+    }
+    
+    fn lock_index(&self) -> std::sync::MutexGuard<'_, BufferIndex> {
+        self.index.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    
+    fn entry(trace_id: &str, step: u64, timestamp: i64) -> TraceEntry {
+        TraceEntry {
+            id: trace_id.to_string(),
+            agent_id: "agent_1".to_string(),
+            event_type: "step".to_string(),
+            data: serde_json::json!({"step": step}),
+            timestamp,
+            prev_hash: None,
+            hash: format!("{}_{}", trace_id, step),
+            hasher: crate::hasher::SHA3_HASHER.to_string(),
+        }
+    }
+    
+    fn hashes(entries: &[TraceEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.hash.as_str()).collect()
+    }
+    
+    #[test]
+    fn test_eviction_across_traces() {
+        let buffer = TraceBuffer::new(3, 0);
+        let storage = MemoryStorage::new();
+        buffer.push(entry("a", 0, 100), |_| {});
+        buffer.push(entry("b", 0, 100), |_| {});
+        buffer.push(entry("a", 1, 100), |_| {});
+        assert_eq!(buffer.flush(&storage).unwrap(), 3);
+        
+        // The oldest entries go first, whichever trace they are in
+        buffer.push(entry("c", 0, 100), |_| {});
+        buffer.push(entry("b", 1, 100), |_| {});
+        assert_eq!(hashes(&buffer.matching(&TraceFilter::default())), ["a_1", "c_0", "b_1"]);
+        assert_eq!(buffer.dropped(), 0);
+        
+        // A trace whose entries were all evicted leaves no segment behind
+        buffer.push(entry("c", 1, 100), |_| {});
+        buffer.push(entry("c", 2, 100), |_| {});
+        assert_eq!(hashes(&buffer.matching(&TraceFilter::default())), ["b_1", "c_1", "c_2"]);
+        assert!(!buffer.segments.contains_key("a"));
+        assert_eq!(buffer.dropped(), 1);
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.lock_index().oldest.len(), 2);
+        
+        assert_eq!(buffer.flush(&storage).unwrap(), 3);
+        assert_eq!(hashes(&storage.load_traces().unwrap()), ["a_0", "b_0", "a_1", "b_1", "c_1", "c_2"]);
+    }
+    
+    #[test]
+    fn test_time_index() {
+        let buffer = TraceBuffer::new(4, 0);
+        buffer.push(entry("a", 0, 60), |_| {});
+        buffer.push(entry("b", 0, 130), |_| {});
+        buffer.push(entry("a", 1, 150), |_| {});
+        buffer.push(entry("c", 0, 200), |_| {});
+        
+        // Only traces with entries in the range's minutes are visited
+        let range = TraceFilter { since: Some(120), until: Some(179), ..Default::default() };
+        assert_eq!(hashes(&buffer.matching(&range)), ["b_0", "a_1"]);
+        let since = TraceFilter { since: Some(180), ..Default::default() };
+        assert_eq!(hashes(&buffer.matching(&since)), ["c_0"]);
+        let empty = TraceFilter { since: Some(200), until: Some(100), ..Default::default() };
+        assert!(buffer.matching(&empty).is_empty());
+        
+        // Minutes are dropped from the index once evicted
+        buffer.push(entry("c", 1, 200), |_| {});
+        assert_eq!(buffer.lock_index().minutes.keys().copied().collect::<Vec<_>>(), [2, 3]);
+        let until = TraceFilter { until: Some(119), ..Default::default() };
+        assert!(buffer.matching(&until).is_empty());
+    }
+    
+    #[test]
+    fn test_concurrent_pushes() {
+        let buffer = TraceBuffer::new(1000, 0);
+        let stored = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let (buffer, stored) = (&buffer, &stored);
+                scope.spawn(move || {
+                    for step in 0..500 {
+                        let trace_id = format!("trace_{}_{}", thread, step / 50);
+                        buffer.push(entry(&trace_id, step, 100 + step as i64 / 10), |_| {
+                            stored.fetch_add(1, Ordering::Relaxed);
+                        });
+                    }
+                });
+            }
+        });
+        
+        // Nothing was lost: every entry is either buffered or counted as dropped
+        assert_eq!(stored.load(Ordering::Relaxed), 4000);
+        assert_eq!(buffer.len(), 1000);
+        assert_eq!(buffer.dropped(), 3000);
+        assert_eq!(buffer.matching(&TraceFilter::default()).len(), 1000);
+        
+        // Each segment is in sequence order and indexed by its oldest entry
+        let index = buffer.lock_index();
+        assert_eq!(index.oldest.len(), buffer.segments.len());
+        for segment in buffer.segments.iter() {
+            let sequences: Vec<u64> = segment.entries.iter().map(|buffered| buffered.sequence).collect();
+            assert!(sequences.windows(2).all(|pair| pair[0] < pair[1]));
+            assert_eq!(index.oldest.get(&sequences[0]), Some(segment.key()));
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! WASI support for MCP-ZERO plugins
+//!
+//! Plugins declaring `wasi: true` in their capabilities get WASI preview1 imports
+//! (clocks, random, stdio). Their stdout/stderr are captured instead of reaching the
+//! host, and the filesystem is limited to the declared `preopened_dirs`. Support is
+//! compiled in with the `wasi` cargo feature; without it such plugins fail to load.
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use anyhow::Result;
+use wasmtime::Linker;
+
+use crate::plugin::{PluginCapabilities, PluginState};
+
+/// Maximum bytes of stdout (and of stderr) kept per execution
+pub const MAX_CAPTURED_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// Output stream of a plugin captured up to MAX_CAPTURED_OUTPUT_BYTES
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CapturedStream(Arc<Mutex<CapturedBuffer>>);
+
+#[derive(Debug, Default)]
+struct CapturedBuffer {
+    bytes: Vec<u8>,
+    truncated: bool,
+}
+
+impl CapturedStream {
+    /// Drain the captured text (lossily decoded) and whether output was dropped
+    pub(crate) fn take(&self) -> (String, bool) {
+        match self.0.lock() {
+            Ok(mut buffer) => {
+                let buffer = std::mem::take(&mut *buffer);
+                (String::from_utf8_lossy(&buffer.bytes).into_owned(), buffer.truncated)
+            },
+            Err(_) => (String::new(), false),
+        }
+    }
+}
+
+impl Write for CapturedStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut buffer = self.0.lock()
+            .map_err(|_| std::io::Error::other("captured output lock poisoned"))?;
+        let room = MAX_CAPTURED_OUTPUT_BYTES.saturating_sub(buffer.bytes.len());
+        if buf.len() > room {
+            buffer.truncated = true;
+        }
+        buffer.bytes.extend_from_slice(&buf[..buf.len().min(room)]);
+        
+        // Report everything as written so the plugin doesn't retry dropped output
+        Ok(buf.len())
+    }
+    
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Stdout and stderr of a WASI execution
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CapturedOutput {
+    pub(crate) stdout: CapturedStream,
+    pub(crate) stderr: CapturedStream,
+}
+
+#[cfg(feature = "wasi")]
+pub(crate) use enabled::{add_to_linker, build_context, WasiContext};
+
+#[cfg(not(feature = "wasi"))]
+pub(crate) use disabled::{add_to_linker, build_context, WasiContext};
+
+#[cfg(feature = "wasi")]
+mod enabled {
+    use super::*;
+    use anyhow::Context;
+    use wasi_common::pipe::WritePipe;
+    use wasmtime_wasi::sync::{ambient_authority, Dir, WasiCtxBuilder};
+    
+    /// WASI context of one execution
+    pub(crate) struct WasiContext(wasmtime_wasi::WasiCtx);
+    
+    impl std::fmt::Debug for WasiContext {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("WasiContext")
+        }
+    }
+    
+    /// Add the WASI preview1 imports, backed by the store's WASI context
+    pub(crate) fn add_to_linker(linker: &mut Linker<PluginState>) -> Result<()> {
+        wasmtime_wasi::add_to_linker(linker, |state: &mut PluginState| {
+            &mut state.wasi.as_mut().expect("WASI plugins always execute with a WASI context").0
+        })
+    }
+    
+    /// Build a WASI context with captured stdio and the plugin's preopened directories
+    pub(crate) fn build_context(capabilities: &PluginCapabilities, output: &CapturedOutput) -> Result<WasiContext> {
+        let mut builder = WasiCtxBuilder::new();
+        builder.stdout(Box::new(WritePipe::new(output.stdout.clone())));
+        builder.stderr(Box::new(WritePipe::new(output.stderr.clone())));
+        
+        for path in &capabilities.preopened_dirs {
+            let dir = Dir::open_ambient_dir(path, ambient_authority())
+                .with_context(|| format!("Failed to open preopened directory: {}", path))?;
+            builder.preopened_dir(dir, path)?;
+        }
+        
+        Ok(WasiContext(builder.build()))
+    }
+}
+
+#[cfg(not(feature = "wasi"))]
+mod disabled {
+    use super::*;
+    
+    /// Placeholder context; WASI plugins are rejected before they execute
+    #[derive(Debug)]
+    pub(crate) enum WasiContext {}
+    
+    pub(crate) fn add_to_linker(_linker: &mut Linker<PluginState>) -> Result<()> {
+        anyhow::bail!("Plugin requires WASI but the kernel was built without the `wasi` feature")
+    }
+    
+    pub(crate) fn build_context(_capabilities: &PluginCapabilities, _output: &CapturedOutput) -> Result<WasiContext> {
+        anyhow::bail!("Plugin requires WASI but the kernel was built without the `wasi` feature")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_captured_stream_is_bounded() {
+        let mut stream = CapturedStream::default();
+        stream.write_all(&vec![b'a'; MAX_CAPTURED_OUTPUT_BYTES - 1]).unwrap();
+        assert_eq!(stream.write(b"bcd").unwrap(), 3);
+        
+        let (text, truncated) = stream.take();
+        assert_eq!(text.len(), MAX_CAPTURED_OUTPUT_BYTES);
+        assert!(text.ends_with('b'));
+        assert!(truncated);
+        
+        // Taking the output resets the capture
+        assert_eq!(stream.take(), (String::new(), false));
+    }
+}
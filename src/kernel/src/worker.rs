@@ -0,0 +1,110 @@
+//! Background worker threads for MCP-ZERO kernel
+//!
+//! The kernel's periodic tasks each run on a thread of their own that sleeps between
+//! rounds. Stopping one wakes it from its sleep and waits for a round in progress to
+//! finish, so nothing is left half-written when the kernel shuts down.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Tells a background thread to exit, waking it if it is asleep
+#[derive(Debug, Default)]
+pub(crate) struct StopSignal {
+    /// Whether the thread should exit
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl StopSignal {
+    /// Sleep for `timeout` unless stopped first; returns whether the thread should exit
+    pub(crate) fn wait(&self, timeout: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap_or_else(|e| e.into_inner());
+        let (stopped, _) = self.wake.wait_timeout_while(stopped, timeout, |stopped| !*stopped)
+            .unwrap_or_else(|e| e.into_inner());
+        *stopped
+    }
+    
+    /// Ask the thread to exit
+    fn stop(&self) {
+        *self.stopped.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.wake.notify_all();
+    }
+}
+
+/// A named background thread, stopped when dropped
+#[derive(Debug, Default)]
+pub(crate) struct BackgroundWorker {
+    signal: Arc<StopSignal>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl BackgroundWorker {
+    /// Spawn a thread named `name` running `run`, which should return once the
+    /// signal it is given is stopped
+    pub(crate) fn start(&self, name: &str, run: impl FnOnce(&StopSignal) + Send + 'static) {
+        let signal = self.signal.clone();
+        let spawned = std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || run(&signal));
+        match spawned {
+            Ok(handle) => *self.handle.lock().unwrap_or_else(|e| e.into_inner()) = Some(handle),
+            Err(e) => tracing::error!("Failed to start background thread {}: {}", name, e),
+        }
+    }
+    
+    /// Stop the thread and wait for the round in progress to finish
+    pub(crate) fn stop(&self) {
+        self.signal.stop();
+        
+        let handle = self.handle.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(handle) = handle {
+            let name = handle.thread().name().unwrap_or("unnamed").to_string();
+            if handle.join().is_err() {
+                tracing::error!("Background thread {} panicked", name);
+            }
+        }
+    }
+}
+
+impl Drop for BackgroundWorker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Instant;
+    
+    #[test]
+    fn test_stop_wakes_worker() {
+        let rounds = Arc::new(AtomicU32::new(0));
+        let worker = BackgroundWorker::default();
+        let counted = rounds.clone();
+        worker.start("mcp-test-worker", move |signal| {
+            while !signal.wait(Duration::from_millis(10)) {
+                counted.fetch_add(1, Ordering::Relaxed);
+                if counted.load(Ordering::Relaxed) == 2 {
+                    // A long sleep is cut short by stop
+                    let _ = signal.wait(Duration::from_secs(60));
+                }
+            }
+        });
+        
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while rounds.load(Ordering::Relaxed) < 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let started = Instant::now();
+        worker.stop();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(rounds.load(Ordering::Relaxed), 2);
+        
+        // Stopping twice, or a worker never started, is harmless
+        worker.stop();
+        BackgroundWorker::default().stop();
+    }
+}
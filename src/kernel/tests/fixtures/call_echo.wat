@@ -0,0 +1,14 @@
+;; Calls the "echo" plugin with a fixed payload and returns its result.
+(module
+  (import "host" "call_plugin" (func $call_plugin (param i32 i32 i32 i32) (result i32)))
+  (import "host" "get_call_result" (func $get_call_result (param i32) (result i32)))
+  (import "host" "set_result" (func $set_result (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "echo")
+  (data (i32.const 16) "{\"from\":\"caller\"}")
+  (func (export "execute")
+    (local $len i32)
+    (local.set $len
+      (call $call_plugin (i32.const 0) (i32.const 4) (i32.const 16) (i32.const 17)))
+    (drop (call $get_call_result (i32.const 1024)))
+    (call $set_result (i32.const 1024) (local.get $len))))
@@ -0,0 +1,8 @@
+;; Calls itself (as plugin "recurse") without a base case.
+(module
+  (import "host" "call_plugin" (func $call_plugin (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "recurse")
+  (data (i32.const 16) "null")
+  (func (export "execute")
+    (drop (call $call_plugin (i32.const 0) (i32.const 7) (i32.const 16) (i32.const 4)))))
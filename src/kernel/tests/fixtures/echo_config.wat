@@ -0,0 +1,9 @@
+;; Config plugin: returns its agent-specific config as the execution result.
+(module
+  (import "host" "get_config" (func $get_config (param i32) (result i32)))
+  (import "host" "set_result" (func $set_result (param i32 i32)))
+  (memory (export "memory") 1)
+  (func (export "execute")
+    (local $len i32)
+    (local.set $len (call $get_config (i32.const 1024)))
+    (call $set_result (i32.const 1024) (local.get $len))))
@@ -0,0 +1,12 @@
+;; Echoes its params using the direct convention: execute(ptr, len) -> (ptr, len).
+(module
+  (memory (export "memory") 1)
+  (global $next (mut i32) (i32.const 1024))
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (local.get $ptr) (local.get $len)))
+    (local.get $ptr))
+  (func (export "execute") (param $ptr i32) (param $len i32) (result i32 i32)
+    (local.get $ptr)
+    (local.get $len)))
@@ -0,0 +1,9 @@
+;; Echo plugin: returns the intent params unchanged as the execution result.
+(module
+  (import "host" "get_params" (func $get_params (param i32) (result i32)))
+  (import "host" "set_result" (func $set_result (param i32 i32)))
+  (memory (export "memory") 1)
+  (func (export "execute")
+    (local $len i32)
+    (local.set $len (call $get_params (i32.const 1024)))
+    (call $set_result (i32.const 1024) (local.get $len))))
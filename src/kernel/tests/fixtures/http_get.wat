@@ -0,0 +1,20 @@
+;; GETs the URL passed as a JSON string param and returns the response.
+(module
+  (import "host" "get_params" (func $get_params (param i32) (result i32)))
+  (import "host" "http_request" (func $http_request (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "host" "get_http_response" (func $get_http_response (param i32) (result i32)))
+  (import "host" "set_result" (func $set_result (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "GET")
+  (func (export "execute")
+    (local $params_len i32)
+    (local $len i32)
+    (local.set $params_len (call $get_params (i32.const 1024)))
+    ;; Skip the quotes around the URL
+    (local.set $len
+      (call $http_request
+        (i32.const 0) (i32.const 3)
+        (i32.const 1025) (i32.sub (local.get $params_len) (i32.const 2))
+        (i32.const 0) (i32.const 0)))
+    (drop (call $get_http_response (i32.const 4096)))
+    (call $set_result (i32.const 4096) (local.get $len))))
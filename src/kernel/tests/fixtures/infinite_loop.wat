@@ -0,0 +1,6 @@
+;; Misbehaving plugin: spins forever without returning.
+(module
+  (memory (export "memory") 1)
+  (func (export "execute")
+    (loop $spin
+      (br $spin))))
@@ -0,0 +1,17 @@
+;; Lifecycle plugin: init requires a config and resets a counter that each
+;; execute increments and returns; shutdown always traps.
+(module
+  (import "host" "get_config_len" (func $get_config_len (result i32)))
+  (import "host" "set_result" (func $set_result (param i32 i32)))
+  (memory (export "memory") 1)
+  (func (export "init")
+    ;; A null config ("null", 4 bytes) is rejected
+    (if (i32.eq (call $get_config_len) (i32.const 4))
+      (then unreachable))
+    (i32.store8 (i32.const 0) (i32.const 48)))
+  (func (export "execute")
+    ;; Uninitialized memory would make the result invalid JSON
+    (i32.store8 (i32.const 0) (i32.add (i32.load8_u (i32.const 0)) (i32.const 1)))
+    (call $set_result (i32.const 0) (i32.const 1)))
+  (func (export "shutdown")
+    unreachable))
@@ -0,0 +1,11 @@
+;; Logging plugin: logs at debug, info and error, then returns null.
+(module
+  (import "host" "log" (func $log (param i32 i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "debug details")
+  (data (i32.const 16) "starting up")
+  (data (i32.const 32) "failed \e2\9c\97 badly")
+  (func (export "execute")
+    (call $log (i32.const 3) (i32.const 0) (i32.const 13))
+    (call $log (i32.const 2) (i32.const 16) (i32.const 11))
+    (call $log (i32.const 0) (i32.const 32) (i32.const 16))))
@@ -0,0 +1,5 @@
+;; Misbehaving plugin: grows its linear memory by 64 MB and keeps going if refused.
+(module
+  (memory (export "memory") 1)
+  (func (export "execute")
+    (drop (memory.grow (i32.const 1024)))))
@@ -0,0 +1,8 @@
+;; Misbehaving plugin: its start function spins forever, so instantiation never finishes.
+(module
+  (memory (export "memory") 1)
+  (func $start
+    (loop $spin
+      (br $spin)))
+  (start $start)
+  (func (export "execute")))
@@ -0,0 +1,12 @@
+;; WASI plugin: prints "hello" to stdout and returns "ok".
+(module
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "host" "set_result" (func $set_result (param i32 i32)))
+  (memory (export "memory") 1)
+  ;; iovec { buf = 16, buf_len = 6 }
+  (data (i32.const 0) "\10\00\00\00\06\00\00\00")
+  (data (i32.const 16) "hello\n")
+  (data (i32.const 32) "\"ok\"")
+  (func (export "execute")
+    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+    (call $set_result (i32.const 32) (i32.const 4))))